@@ -463,6 +463,7 @@ pub(crate) async fn expand_schema(
         Format::Parquet(_) => Ok(schema),
         Format::RawString(_) => Ok(schema),
         Format::RawBytes(_) => Ok(schema),
+        Format::Csv(_) => Ok(schema),
         Format::Protobuf(_) => {
             expand_proto_schema(
                 connector,
@@ -490,7 +491,7 @@ async fn expand_avro_schema(
     {
         let schema_response = get_schema(connector, table_config, profile_config).await?;
         match connection_type {
-            ConnectionType::Source => {
+            ConnectionType::Source | ConnectionType::Lookup => {
                 let (schema_response, _) = schema_response.ok_or_else(|| bad_request(
                         "No schema was found; ensure that the topic exists and has a value schema configured in the schema registry".to_string()))?;
 
@@ -511,7 +512,7 @@ async fn expand_avro_schema(
 
     let Some(SchemaDefinition::AvroSchema(definition)) = schema.definition.as_ref() else {
         return match connection_type {
-            ConnectionType::Source => Err(bad_request(
+            ConnectionType::Source | ConnectionType::Lookup => Err(bad_request(
                 "avro format requires an avro schema be set for sources",
             )),
             ConnectionType::Sink => {
@@ -560,7 +561,7 @@ async fn expand_proto_schema(
     if *confluent_schema_registry {
         let schema_response = get_schema(connector, table_config, profile_config).await?;
         match connection_type {
-            ConnectionType::Source => {
+            ConnectionType::Source | ConnectionType::Lookup => {
                 let (schema_response, dependencies) = schema_response.ok_or_else(|| bad_request(
                     "No schema was found; ensure that the topic exists and has a value schema configured in the schema registry".to_string()))?;
 
@@ -676,7 +677,7 @@ async fn expand_json_schema(
         let schema_response = get_schema(connector, table_config, profile_config).await?;
 
         match connection_type {
-            ConnectionType::Source => {
+            ConnectionType::Source | ConnectionType::Lookup => {
                 let schema_response = schema_response.ok_or_else(|| bad_request(
                     "No schema was found; ensure that the topic exists and has a value schema configured in the schema registry".to_string()))?;
 
@@ -295,10 +295,15 @@ impl IntoResponse for HttpError {
         ParquetFormat,
         RawStringFormat,
         RawBytesFormat,
+        CsvFormat,
         TimestampFormat,
         Framing,
         FramingMethod,
         NewlineDelimitedFraming,
+        LengthPrefixedFraming,
+        LengthPrefixWidth,
+        Endianness,
+        DelimitedFraming,
         PaginationQueryParams,
         CheckpointEventSpan,
         CheckpointSpanType,
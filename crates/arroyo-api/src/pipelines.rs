@@ -1,4 +1,4 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use arrow_schema::SchemaRef;
 use arroyo_connectors::connector_for_type;
 use axum::extract::{Path, Query, State};
@@ -241,6 +241,31 @@ async fn try_register_confluent_schema(
                 config.format = Some(Format::Json(json))
             }
         }
+        Some(Format::Protobuf(mut proto)) => {
+            if proto.confluent_schema_registry && proto.schema_id.is_none() {
+                // protobuf schemas can't be generated from the compiled descriptor, so we
+                // write against the latest version registered for the subject
+                let schema = schema_registry
+                    .get_schema_for_version(None)
+                    .await?
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "no protobuf schema is registered for subject '{}'",
+                            table.subject()
+                        )
+                    })?;
+
+                if schema.schema_type != ConfluentSchemaType::Protobuf {
+                    bail!(
+                        "Format configured is protobuf, but confluent schema registry returned a {:?} schema",
+                        schema.schema_type
+                    );
+                }
+
+                proto.schema_id = Some(schema.id);
+                config.format = Some(Format::Protobuf(proto))
+            }
+        }
         _ => {
             // unsupported for schema registry
         }
@@ -7,10 +7,12 @@ use std::collections::HashMap;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
 };
+use arroyo_rpc::formats::Format;
 use arroyo_rpc::OperatorConfig;
 
 use crate::filesystem::{
-    file_system_sink_from_options, CommitStyle, FileSystemTable, FormatSettings, TableType,
+    file_system_sink_from_options, file_system_source_from_options, CommitStyle, FileSystemTable,
+    FormatSettings, TableFormat, TableType,
 };
 use crate::EmptyConfig;
 
@@ -18,6 +20,7 @@ use arroyo_operator::connector::Connector;
 use arroyo_operator::operator::OperatorNode;
 
 use super::sink::{LocalParquetFileSystemSink, ParquetFileSystemSink};
+use super::source::FileSystemSourceFunc;
 
 const TABLE_SCHEMA: &str = include_str!("./table.json");
 
@@ -37,9 +40,9 @@ impl Connector for DeltaLakeConnector {
             id: "delta".to_string(),
             name: "Delta Lake".to_string(),
             icon: "".to_string(),
-            description: "Write to a Delta Lake table".to_string(),
+            description: "Read from or write to a Delta Lake table".to_string(),
             enabled: true,
-            source: false,
+            source: true,
             sink: true,
             testing: false,
             hidden: true,
@@ -67,8 +70,11 @@ impl Connector for DeltaLakeConnector {
         });
     }
 
-    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
-        ConnectionType::Source
+    fn table_type(&self, _: Self::ProfileT, table: Self::TableT) -> ConnectionType {
+        match table.table_type {
+            TableType::Source { .. } => ConnectionType::Source,
+            TableType::Sink { .. } => ConnectionType::Sink,
+        }
     }
 
     fn from_config(
@@ -80,37 +86,54 @@ impl Connector for DeltaLakeConnector {
         schema: Option<&ConnectionSchema>,
         _metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<arroyo_operator::connector::Connection> {
-        let TableType::Sink {
-            write_path,
-            file_settings,
-            format_settings,
-            ..
-        } = &table.table_type
-        else {
-            bail!("Delta Lake connector only supports sink tables");
-        };
-        // confirm commit style is DeltaLake
-        if let Some(CommitStyle::DeltaLake) = file_settings
-            .as_ref()
-            .ok_or_else(|| anyhow!("no file_settings"))?
-            .commit_style
-        {
-            // ok
-        } else {
-            bail!("commit_style must be DeltaLake");
-        }
-
-        let backend_config = BackendConfig::parse_url(write_path, true)?;
-        let is_local = backend_config.is_local();
-        let description = match (&format_settings, is_local) {
-            (Some(FormatSettings::Parquet { .. }), true) => "LocalDeltaLake<Parquet>".to_string(),
-            (Some(FormatSettings::Parquet { .. }), false) => "DeltaLake<Parquet>".to_string(),
-            _ => bail!("Delta Lake sink only supports Parquet format"),
+        let (description, connection_type) = match &table.table_type {
+            TableType::Source { table_format, .. } => {
+                if *table_format != Some(TableFormat::DeltaLake) {
+                    bail!("table_format must be delta_lake for Delta Lake sources");
+                }
+                ("DeltaLake<Parquet>".to_string(), ConnectionType::Source)
+            }
+            TableType::Sink {
+                write_path,
+                file_settings,
+                format_settings,
+                ..
+            } => {
+                // confirm commit style is DeltaLake
+                if let Some(CommitStyle::DeltaLake) = file_settings
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("no file_settings"))?
+                    .commit_style
+                {
+                    // ok
+                } else {
+                    bail!("commit_style must be DeltaLake");
+                }
+
+                let backend_config = BackendConfig::parse_url(write_path, true)?;
+                let is_local = backend_config.is_local();
+                let description = match (&format_settings, is_local) {
+                    (Some(FormatSettings::Parquet { .. }), true) => {
+                        "LocalDeltaLake<Parquet>".to_string()
+                    }
+                    (Some(FormatSettings::Parquet { .. }), false) => {
+                        "DeltaLake<Parquet>".to_string()
+                    }
+                    _ => bail!("Delta Lake sink only supports Parquet format"),
+                };
+                (description, ConnectionType::Sink)
+            }
         };
 
         let schema = schema
             .map(|s| s.to_owned())
-            .ok_or_else(|| anyhow!("no schema defined for Delta Lake sink"))?;
+            .ok_or_else(|| anyhow!("no schema defined for Delta Lake connection"))?;
+
+        if connection_type == ConnectionType::Source
+            && !matches!(schema.format, Some(Format::Parquet(_)))
+        {
+            bail!("Delta Lake sources must use 'parquet' format");
+        }
 
         let format = schema
             .format
@@ -132,7 +155,7 @@ impl Connector for DeltaLakeConnector {
             id,
             connector: self.name(),
             name: name.to_string(),
-            connection_type: ConnectionType::Sink,
+            connection_type,
             schema,
             config: serde_json::to_string(&config).unwrap(),
             description,
@@ -147,7 +170,17 @@ impl Connector for DeltaLakeConnector {
         _profile: Option<&ConnectionProfile>,
         _metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
-        let table = file_system_sink_from_options(options, schema, CommitStyle::DeltaLake)?;
+        // tables without a type are sinks, as that was all the connector used to support
+        let table = match options.remove("type").as_deref() {
+            Some("source") => file_system_source_from_options(options, TableFormat::DeltaLake)?,
+            Some("sink") | None => {
+                file_system_sink_from_options(options, schema, CommitStyle::DeltaLake)?
+            }
+            Some(t) => bail!(
+                "unknown type '{}' for Delta Lake table; expected 'source' or 'sink'",
+                t
+            ),
+        };
 
         self.from_config(None, name, EmptyConfig {}, table, schema, None)
     }
@@ -165,7 +198,9 @@ impl Connector for DeltaLakeConnector {
             ..
         } = &table.table_type
         else {
-            bail!("Delta Lake connector only supports sink tables");
+            return Ok(OperatorNode::from_source(Box::new(
+                FileSystemSourceFunc::new(table.table_type.clone(), config)?,
+            )));
         };
         // confirm commit style is DeltaLake
         if let Some(CommitStyle::DeltaLake) = file_settings
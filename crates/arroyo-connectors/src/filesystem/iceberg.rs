@@ -0,0 +1,190 @@
+use anyhow::{anyhow, bail};
+use arrow::datatypes::DataType;
+use arroyo_operator::connector::Connection;
+use arroyo_storage::BackendConfig;
+use std::collections::HashMap;
+
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::OperatorConfig;
+
+use crate::filesystem::{
+    file_system_sink_from_options, CommitStyle, FileSettings, FileSystemTable, FormatSettings,
+    TableType,
+};
+use crate::EmptyConfig;
+
+use arroyo_operator::connector::Connector;
+use arroyo_operator::operator::OperatorNode;
+
+use super::sink::{LocalParquetFileSystemSink, ParquetFileSystemSink};
+
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+
+pub struct IcebergConnector {}
+
+/// Checks that the sink is configured to commit to an Iceberg table, and returns the path of the
+/// table and whether it's on the local filesystem
+fn validate_table(table: &FileSystemTable) -> anyhow::Result<(&String, bool)> {
+    let TableType::Sink {
+        write_path,
+        file_settings,
+        format_settings,
+        ..
+    } = &table.table_type
+    else {
+        bail!("Iceberg connector only supports sink tables");
+    };
+
+    let Some(FileSettings {
+        commit_style: Some(CommitStyle::Iceberg),
+        partitioning,
+        ..
+    }) = file_settings
+    else {
+        bail!("commit_style must be Iceberg");
+    };
+
+    if partitioning
+        .as_ref()
+        .is_some_and(|p| p.time_partition_pattern.is_some())
+    {
+        bail!("Iceberg tables can only be partitioned by fields, not by time_partition_pattern");
+    }
+
+    let Some(FormatSettings::Parquet { .. }) = format_settings else {
+        bail!("Iceberg sink only supports Parquet format");
+    };
+
+    let backend_config = BackendConfig::parse_url(write_path, true)?;
+    Ok((write_path, backend_config.is_local()))
+}
+
+impl Connector for IcebergConnector {
+    type ProfileT = EmptyConfig;
+
+    type TableT = FileSystemTable;
+
+    fn name(&self) -> &'static str {
+        "iceberg"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "iceberg".to_string(),
+            name: "Apache Iceberg".to_string(),
+            icon: "".to_string(),
+            description: "Write to an Apache Iceberg table".to_string(),
+            enabled: true,
+            source: false,
+            sink: true,
+            testing: false,
+            hidden: true,
+            custom_schemas: true,
+            connection_config: None,
+            table_config: TABLE_SCHEMA.to_owned(),
+        }
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        _: Self::ProfileT,
+        _: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: tokio::sync::mpsc::Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            let message = TestSourceMessage {
+                error: false,
+                done: true,
+                message: "Successfully validated connection".to_string(),
+            };
+            tx.send(message).await.unwrap();
+        });
+    }
+
+    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
+        ConnectionType::Sink
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+    ) -> anyhow::Result<Connection> {
+        let (_, is_local) = validate_table(&table)?;
+        let description = if is_local {
+            "LocalIceberg<Parquet>".to_string()
+        } else {
+            "Iceberg<Parquet>".to_string()
+        };
+
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("no schema defined for Iceberg sink"))?;
+
+        let format = schema
+            .format
+            .as_ref()
+            .map(|t| t.to_owned())
+            .ok_or_else(|| anyhow!("'format' must be set for Iceberg connection"))?;
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            format: Some(format),
+            bad_data: schema.bad_data.clone(),
+            framing: schema.framing.clone(),
+            additional_fields: None,
+        };
+
+        Ok(Connection {
+            id,
+            connector: self.name(),
+            name: name.to_string(),
+            connection_type: ConnectionType::Sink,
+            schema,
+            config: serde_json::to_string(&config).unwrap(),
+            description,
+        })
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut HashMap<String, String>,
+        schema: Option<&ConnectionSchema>,
+        _profile: Option<&ConnectionProfile>,
+        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+    ) -> anyhow::Result<Connection> {
+        let table = file_system_sink_from_options(options, schema, CommitStyle::Iceberg)?;
+
+        self.from_config(None, name, EmptyConfig {}, table, schema, None)
+    }
+
+    fn make_operator(
+        &self,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+    ) -> anyhow::Result<OperatorNode> {
+        let (write_path, is_local) = validate_table(&table)?;
+
+        if is_local {
+            Ok(OperatorNode::from_operator(Box::new(
+                LocalParquetFileSystemSink::new(write_path.to_string(), table, config),
+            )))
+        } else {
+            Ok(OperatorNode::from_operator(Box::new(
+                ParquetFileSystemSink::new(table, config),
+            )))
+        }
+    }
+}
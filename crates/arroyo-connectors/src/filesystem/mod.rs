@@ -1,4 +1,5 @@
 pub mod delta;
+pub mod iceberg;
 mod sink;
 mod source;
 
@@ -14,18 +15,19 @@ use arroyo_operator::connector::Connection;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
 };
-use arroyo_rpc::formats::Format;
+use arroyo_rpc::formats::{Format, FramingMethod};
 use arroyo_rpc::OperatorConfig;
 use serde::{Deserialize, Serialize};
 
-use crate::{pull_opt, pull_option_to_i64, EmptyConfig};
+use crate::{pull_opt, pull_option_to_i64, pull_option_to_u64, EmptyConfig};
 
 use crate::filesystem::source::FileSystemSourceFunc;
 use arroyo_operator::connector::Connector;
 use arroyo_operator::operator::OperatorNode;
 
 use self::sink::{
-    JsonFileSystemSink, LocalJsonFileSystemSink, LocalParquetFileSystemSink, ParquetFileSystemSink,
+    CsvFileSystemSink, JsonFileSystemSink, LocalCsvFileSystemSink, LocalJsonFileSystemSink,
+    LocalParquetFileSystemSink, ParquetFileSystemSink,
 };
 
 const TABLE_SCHEMA: &str = include_str!("./table.json");
@@ -147,6 +149,8 @@ impl Connector for FileSystemConnector {
                         "LocalFileSystem<JSON>".to_string()
                     }
                     (Some(FormatSettings::Json { .. }), false) => "FileSystem<JSON>".to_string(),
+                    (Some(FormatSettings::Csv { .. }), true) => "LocalFileSystem<CSV>".to_string(),
+                    (Some(FormatSettings::Csv { .. }), false) => "FileSystem<CSV>".to_string(),
                     (None, _) => bail!("have to have some format settings"),
                 };
                 (description, ConnectionType::Sink)
@@ -163,6 +167,29 @@ impl Connector for FileSystemConnector {
             .map(|t| t.to_owned())
             .ok_or_else(|| anyhow!("'format' must be set for FileSystem connection"))?;
 
+        // sinks write files of newline-delimited records
+        if connection_type == ConnectionType::Sink
+            && schema
+                .framing
+                .as_ref()
+                .is_some_and(|framing| !matches!(framing.method, FramingMethod::Newline(_)))
+        {
+            bail!("FileSystem sinks only support newline framing");
+        }
+
+        if matches!(
+            table.table_type,
+            TableType::Source {
+                append_only: Some(true),
+                ..
+            }
+        ) && matches!(format, Format::Parquet(_))
+        {
+            bail!(
+                "Parquet files can't be appended to, so 'source.append_only' can't be set for them"
+            );
+        }
+
         let config = OperatorConfig {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
@@ -194,28 +221,9 @@ impl Connector for FileSystemConnector {
     ) -> anyhow::Result<Connection> {
         match options.remove("type") {
             Some(t) if t == "source" => {
-                let (storage_url, storage_options) = get_storage_url_and_options(options)?;
-                let compression_format = options
-                    .remove("compression_format")
-                    .map(|format| format.as_str().try_into().map_err(|err: &str| anyhow!(err)))
-                    .transpose()?
-                    .unwrap_or(CompressionFormat::None);
-                let matching_pattern = options.remove("source.regex-pattern");
-                self.from_config(
-                    None,
-                    name,
-                    EmptyConfig {},
-                    FileSystemTable {
-                        table_type: TableType::Source {
-                            path: storage_url,
-                            storage_options,
-                            compression_format: Some(compression_format),
-                            regex_pattern: matching_pattern,
-                        },
-                    },
-                    schema,
-                    None,
-                )
+                let table = file_system_source_from_options(options, TableFormat::Files)?;
+
+                self.from_config(None, name, EmptyConfig {}, table, schema, None)
             }
             Some(t) if t == "sink" => {
                 let table = file_system_sink_from_options(options, schema, CommitStyle::Direct)?;
@@ -234,17 +242,9 @@ impl Connector for FileSystemConnector {
         config: OperatorConfig,
     ) -> Result<OperatorNode> {
         match &table.table_type {
-            TableType::Source { .. } => {
-                Ok(OperatorNode::from_source(Box::new(FileSystemSourceFunc {
-                    table: table.table_type.clone(),
-                    format: config
-                        .format
-                        .ok_or_else(|| anyhow!("format required for FileSystem source"))?,
-                    framing: config.framing.clone(),
-                    bad_data: config.bad_data.clone(),
-                    file_states: HashMap::new(),
-                })))
-            }
+            TableType::Source { .. } => Ok(OperatorNode::from_source(Box::new(
+                FileSystemSourceFunc::new(table.table_type.clone(), config)?,
+            ))),
             TableType::Sink {
                 file_settings: _,
                 format_settings,
@@ -271,6 +271,14 @@ impl Connector for FileSystemConnector {
                     (Some(FormatSettings::Json { .. }), false) => Ok(OperatorNode::from_operator(
                         Box::new(JsonFileSystemSink::new(table, config)),
                     )),
+                    (Some(FormatSettings::Csv { .. }), true) => {
+                        Ok(OperatorNode::from_operator(Box::new(
+                            LocalCsvFileSystemSink::new(write_path.to_string(), table, config),
+                        )))
+                    }
+                    (Some(FormatSettings::Csv { .. }), false) => Ok(OperatorNode::from_operator(
+                        Box::new(CsvFileSystemSink::new(table, config)),
+                    )),
                     (None, _) => bail!("have to have some format settings"),
                 }
             }
@@ -292,6 +300,44 @@ fn get_storage_url_and_options(
     Ok((storage_url, storage_options))
 }
 
+pub fn file_system_source_from_options(
+    options: &mut HashMap<String, String>,
+    table_format: TableFormat,
+) -> Result<FileSystemTable> {
+    let (storage_url, storage_options) = get_storage_url_and_options(options)?;
+    let compression_format = options
+        .remove("compression_format")
+        .map(|format| format.as_str().try_into().map_err(|err: &str| anyhow!(err)))
+        .transpose()?
+        .unwrap_or(CompressionFormat::None);
+    let matching_pattern = options.remove("source.regex-pattern");
+    let monitor_interval_ms = pull_option_to_u64("source.monitor_interval_ms", options)?;
+    let append_only = options
+        .remove("source.append_only")
+        .map(|s| {
+            s.parse::<bool>()
+                .map_err(|_| anyhow!("'source.append_only' must be either 'true' or 'false'"))
+        })
+        .transpose()?;
+    let file_order = options
+        .remove("source.file_order")
+        .map(|order| order.as_str().try_into().map_err(|err: &str| anyhow!(err)))
+        .transpose()?;
+
+    Ok(FileSystemTable {
+        table_type: TableType::Source {
+            path: storage_url,
+            storage_options,
+            compression_format: Some(compression_format),
+            regex_pattern: matching_pattern,
+            monitor_interval_ms: monitor_interval_ms.map(|i| i as i64),
+            append_only,
+            file_order,
+            table_format: Some(table_format),
+        },
+    })
+}
+
 pub fn file_system_sink_from_options(
     opts: &mut std::collections::HashMap<String, String>,
     schema: Option<&ConnectionSchema>,
@@ -374,6 +420,9 @@ pub fn file_system_sink_from_options(
         Format::Json(..) => Some(FormatSettings::Json {
             json_format: JsonFormat::Json,
         }),
+        Format::Csv(..) => Some(FormatSettings::Csv {
+            csv_format: CsvFormat::Csv,
+        }),
         other => bail!("Unsupported format: {:?}", other),
     };
     Ok(FileSystemTable {
@@ -0,0 +1,87 @@
+use arrow::record_batch::RecordBatch;
+use arroyo_rpc::{df::ArroyoSchemaRef, formats::Format};
+
+use super::{
+    json::{JsonLocalWriter, JsonWriter},
+    local::{CurrentFileRecovery, FilePreCommit, LocalWriter},
+    BatchBufferingWriter, FileSystemTable, MultiPartWriterStats,
+};
+
+// CSV files are written record-per-line, just like JSON files; the encoding (including the
+// header row) is handled by the ArrowSerializer, so these writers only differ in their suffix
+
+pub struct CsvWriter(JsonWriter);
+
+impl BatchBufferingWriter for CsvWriter {
+    fn new(config: &FileSystemTable, format: Option<Format>, schema: ArroyoSchemaRef) -> Self {
+        Self(JsonWriter::new(config, format, schema))
+    }
+
+    fn suffix() -> String {
+        "csv".to_string()
+    }
+
+    fn add_batch_data(&mut self, batch: RecordBatch) -> Option<Vec<u8>> {
+        self.0.add_batch_data(batch)
+    }
+
+    fn buffer_length(&self) -> usize {
+        self.0.buffer_length()
+    }
+
+    fn evict_current_buffer(&mut self) -> Vec<u8> {
+        self.0.evict_current_buffer()
+    }
+
+    fn get_trailing_bytes_for_checkpoint(&mut self) -> Option<Vec<u8>> {
+        self.0.get_trailing_bytes_for_checkpoint()
+    }
+
+    fn close(&mut self, final_batch: Option<RecordBatch>) -> Option<Vec<u8>> {
+        self.0.close(final_batch)
+    }
+}
+
+pub struct CsvLocalWriter(JsonLocalWriter);
+
+impl LocalWriter for CsvLocalWriter {
+    fn new(
+        tmp_path: String,
+        final_path: String,
+        table_properties: &FileSystemTable,
+        format: Option<Format>,
+        schema: ArroyoSchemaRef,
+    ) -> Self {
+        Self(JsonLocalWriter::new(
+            tmp_path,
+            final_path,
+            table_properties,
+            format,
+            schema,
+        ))
+    }
+
+    fn file_suffix() -> &'static str {
+        "csv"
+    }
+
+    fn write_batch(&mut self, batch: RecordBatch) -> anyhow::Result<()> {
+        self.0.write_batch(batch)
+    }
+
+    fn sync(&mut self) -> anyhow::Result<usize> {
+        LocalWriter::sync(&mut self.0)
+    }
+
+    fn close(&mut self) -> anyhow::Result<FilePreCommit> {
+        LocalWriter::close(&mut self.0)
+    }
+
+    fn checkpoint(&mut self) -> anyhow::Result<Option<CurrentFileRecovery>> {
+        self.0.checkpoint()
+    }
+
+    fn stats(&self) -> MultiPartWriterStats {
+        self.0.stats()
+    }
+}
@@ -0,0 +1,1175 @@
+use super::FinishedFile;
+use crate::filesystem::FileSettings;
+use anyhow::{anyhow, bail, Context, Result};
+use apache_avro::types::Value as AvroValue;
+use apache_avro::{Reader, Schema as AvroSchema, Writer};
+use arrow::array::RecordBatch;
+use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use arroyo_storage::StorageProvider;
+use arroyo_types::to_millis;
+use chrono::NaiveDate;
+use futures::TryStreamExt;
+use object_store::{path::Path, ObjectStore, PutMode, PutPayload};
+use parquet::arrow::async_reader::ParquetObjectReader;
+use parquet::arrow::ParquetRecordBatchStreamBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Snapshot summary property holding the first (by path) data file added by a commit, which lets
+/// us tell whether a commit that was interrupted by a failure made it into the table
+const COMMIT_MARKER_PROPERTY: &str = "arroyo.first-data-file";
+const NAME_MAPPING_PROPERTY: &str = "schema.name-mapping.default";
+const VERSION_HINT_FILE: &str = "version-hint.text";
+const FIRST_PARTITION_FIELD_ID: i64 = 1000;
+
+const MANIFEST_LIST_SCHEMA: &str = r#"{
+  "type": "record",
+  "name": "manifest_file",
+  "fields": [
+    {"name": "manifest_path", "type": "string", "field-id": 500},
+    {"name": "manifest_length", "type": "long", "field-id": 501},
+    {"name": "partition_spec_id", "type": "int", "field-id": 502},
+    {"name": "content", "type": "int", "default": 0, "field-id": 517},
+    {"name": "sequence_number", "type": "long", "default": 0, "field-id": 515},
+    {"name": "min_sequence_number", "type": "long", "default": 0, "field-id": 516},
+    {"name": "added_snapshot_id", "type": "long", "field-id": 503},
+    {"name": "added_files_count", "type": "int", "field-id": 504},
+    {"name": "existing_files_count", "type": "int", "field-id": 505},
+    {"name": "deleted_files_count", "type": "int", "field-id": 506},
+    {"name": "added_rows_count", "type": "long", "field-id": 512},
+    {"name": "existing_rows_count", "type": "long", "field-id": 513},
+    {"name": "deleted_rows_count", "type": "long", "field-id": 514},
+    {"name": "partitions", "type": ["null", {"type": "array", "items": {
+      "type": "record",
+      "name": "r508",
+      "fields": [
+        {"name": "contains_null", "type": "boolean", "field-id": 509},
+        {"name": "contains_nan", "type": ["null", "boolean"], "default": null, "field-id": 518},
+        {"name": "lower_bound", "type": ["null", "bytes"], "default": null, "field-id": 510},
+        {"name": "upper_bound", "type": ["null", "bytes"], "default": null, "field-id": 511}
+      ]
+    }, "element-id": 508}], "default": null, "field-id": 507}
+  ]
+}"#;
+
+/// The parts of the Iceberg table metadata that we read or update on commit; everything else
+/// is carried through unchanged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TableMetadata {
+    format_version: i32,
+    table_uuid: String,
+    location: String,
+    #[serde(default)]
+    last_sequence_number: i64,
+    last_updated_ms: i64,
+    last_column_id: i64,
+    schemas: Vec<Value>,
+    current_schema_id: i64,
+    partition_specs: Vec<PartitionSpec>,
+    default_spec_id: i64,
+    last_partition_id: i64,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    current_snapshot_id: Option<i64>,
+    #[serde(default)]
+    snapshots: Vec<Snapshot>,
+    #[serde(default)]
+    snapshot_log: Vec<Value>,
+    #[serde(default)]
+    metadata_log: Vec<Value>,
+    #[serde(default)]
+    refs: Map<String, Value>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+impl TableMetadata {
+    fn current_schema(&self) -> Result<&Value> {
+        self.schemas
+            .iter()
+            .find(|s| s["schema-id"].as_i64() == Some(self.current_schema_id))
+            .ok_or_else(|| anyhow!("current schema {} not found", self.current_schema_id))
+    }
+
+    fn default_spec(&self) -> Result<&PartitionSpec> {
+        self.partition_specs
+            .iter()
+            .find(|s| s.spec_id == self.default_spec_id)
+            .ok_or_else(|| anyhow!("default partition spec {} not found", self.default_spec_id))
+    }
+
+    fn current_snapshot(&self) -> Option<&Snapshot> {
+        let id = self.current_snapshot_id.filter(|id| *id != -1)?;
+        self.snapshots.iter().find(|s| s.snapshot_id == id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PartitionSpec {
+    spec_id: i64,
+    fields: Vec<PartitionField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PartitionField {
+    source_id: i64,
+    field_id: i64,
+    name: String,
+    transform: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Snapshot {
+    snapshot_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent_snapshot_id: Option<i64>,
+    #[serde(default)]
+    sequence_number: i64,
+    timestamp_ms: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    manifest_list: Option<String>,
+    #[serde(default)]
+    summary: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema_id: Option<i64>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+/// A data file to be added to the table
+struct DataFile {
+    uri: String,
+    size: i64,
+    record_count: i64,
+    partition: Vec<(String, AvroValue)>,
+}
+
+/// The partition columns configured for the sink, which become identity partitions of the table
+pub(crate) fn partition_fields(file_settings: &FileSettings) -> &[String] {
+    file_settings
+        .partitioning
+        .as_ref()
+        .map(|p| p.partition_fields.as_slice())
+        .unwrap_or_default()
+}
+
+/// Iceberg (before format version 3) only supports microsecond timestamps, so nanosecond
+/// timestamps are truncated when they're written to data files
+pub(crate) fn data_file_schema(schema: &Schema) -> Schema {
+    Schema::new(
+        schema
+            .fields()
+            .iter()
+            .map(|f| match f.data_type() {
+                DataType::Timestamp(TimeUnit::Nanosecond, tz) => f
+                    .as_ref()
+                    .clone()
+                    .with_data_type(DataType::Timestamp(TimeUnit::Microsecond, tz.clone())),
+                _ => f.as_ref().clone(),
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+pub(crate) fn cast_to_data_file_schema(
+    batch: &RecordBatch,
+    schema: &SchemaRef,
+) -> Result<RecordBatch> {
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(c, f)| arrow::compute::cast(c, f.data_type()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// Commits the finished files to the Iceberg table at `relative_table_path`, using the layout of
+/// Iceberg's Hadoop (file-based) catalog: each commit writes a new `metadata/v<N>.metadata.json`,
+/// which must not already exist, and then updates `metadata/version-hint.text`. Returns the new
+/// version of the table metadata, or the version that already contains these files.
+pub(crate) async fn commit_files_to_iceberg(
+    finished_files: &[FinishedFile],
+    relative_table_path: &Path,
+    storage_provider: &StorageProvider,
+    last_version: i64,
+    schema: SchemaRef,
+    partition_fields: &[String],
+) -> Result<Option<i64>> {
+    if finished_files.is_empty() {
+        return Ok(None);
+    }
+
+    let table = IcebergTable {
+        store: storage_provider.get_backing_store(),
+        base_url: storage_provider
+            .object_store_base_url()
+            .trim_end_matches('/')
+            .to_string(),
+        path: relative_table_path.clone(),
+    };
+
+    let marker = table.uri(
+        finished_files
+            .iter()
+            .map(|f| f.filename.as_str())
+            .min()
+            .unwrap(),
+    );
+
+    let (version, metadata) = match table.load().await? {
+        Some((version, metadata)) => {
+            if version > last_version
+                && metadata
+                    .snapshots
+                    .iter()
+                    .any(|s| s.summary.get(COMMIT_MARKER_PROPERTY) == Some(&marker))
+            {
+                debug!("files starting with {} have already been committed", marker);
+                return Ok(Some(version));
+            }
+            (version, metadata)
+        }
+        None => {
+            info!(
+                "creating Iceberg table at {}",
+                table.uri(table.path.as_ref())
+            );
+            (0, table.new_metadata(&schema, partition_fields)?)
+        }
+    };
+
+    if metadata.format_version != 2 {
+        bail!(
+            "only Iceberg format version 2 is supported, but table has version {}",
+            metadata.format_version
+        );
+    }
+
+    let mut data_files = vec![];
+    for file in finished_files {
+        data_files.push(table.data_file(file, &metadata).await?);
+    }
+
+    let new_metadata = table.append(metadata, version, data_files, marker).await?;
+    table.write_metadata(version + 1, &new_metadata).await?;
+
+    Ok(Some(version + 1))
+}
+
+struct IcebergTable {
+    store: Arc<dyn ObjectStore>,
+    base_url: String,
+    path: Path,
+}
+
+impl IcebergTable {
+    fn uri(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, key)
+    }
+
+    fn metadata_path(&self, file: &str) -> Path {
+        self.path.child("metadata").child(file)
+    }
+
+    fn metadata_file(version: i64) -> String {
+        format!("v{}.metadata.json", version)
+    }
+
+    /// Finds the current version of the table metadata. The version hint is written after the
+    /// metadata file, so it may be behind if a writer failed between the two.
+    async fn current_version(&self) -> Result<Option<i64>> {
+        let hint = match self.store.get(&self.metadata_path(VERSION_HINT_FILE)).await {
+            Ok(result) => Some(
+                String::from_utf8(result.bytes().await?.to_vec())?
+                    .trim()
+                    .parse::<i64>()
+                    .context("invalid Iceberg version hint")?,
+            ),
+            Err(object_store::Error::NotFound { .. }) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut version = match hint {
+            Some(version) => version,
+            None => {
+                let files: Vec<_> = self
+                    .store
+                    .list(Some(&self.path.child("metadata")))
+                    .try_collect()
+                    .await?;
+                let Some(version) = files
+                    .iter()
+                    .filter_map(|f| {
+                        f.location
+                            .filename()?
+                            .strip_prefix('v')?
+                            .strip_suffix(".metadata.json")?
+                            .parse::<i64>()
+                            .ok()
+                    })
+                    .max()
+                else {
+                    return Ok(None);
+                };
+                version
+            }
+        };
+
+        while self
+            .exists(&self.metadata_path(&Self::metadata_file(version + 1)))
+            .await?
+        {
+            version += 1;
+        }
+
+        Ok(Some(version))
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        match self.store.head(path).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn load(&self) -> Result<Option<(i64, TableMetadata)>> {
+        let Some(version) = self.current_version().await? else {
+            return Ok(None);
+        };
+
+        let bytes = self
+            .store
+            .get(&self.metadata_path(&Self::metadata_file(version)))
+            .await?
+            .bytes()
+            .await?;
+        let metadata = serde_json::from_slice(&bytes).with_context(|| {
+            format!("invalid metadata for version {} of Iceberg table", version)
+        })?;
+        Ok(Some((version, metadata)))
+    }
+
+    fn new_metadata(&self, schema: &Schema, partition_fields: &[String]) -> Result<TableMetadata> {
+        let mut converter = SchemaConverter { last_id: 0 };
+        let (fields, name_mapping) = converter.convert_fields(schema.fields())?;
+
+        let partition_fields = partition_fields
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let field = fields
+                    .iter()
+                    .find(|f| f["name"].as_str() == Some(name))
+                    .ok_or_else(|| anyhow!("partition field '{}' is not in the schema", name))?;
+                let source_type = field["type"].as_str().unwrap_or("nested");
+                if partition_value_type(source_type).is_none() {
+                    bail!(
+                        "cannot partition Iceberg table by '{}' of type {}",
+                        name,
+                        source_type
+                    );
+                }
+
+                Ok(PartitionField {
+                    source_id: field["id"].as_i64().unwrap(),
+                    field_id: FIRST_PARTITION_FIELD_ID + i as i64,
+                    name: name.clone(),
+                    transform: "identity".to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(TableMetadata {
+            format_version: 2,
+            table_uuid: Uuid::new_v4().to_string(),
+            location: self.uri(self.path.as_ref()),
+            last_sequence_number: 0,
+            last_updated_ms: to_millis(SystemTime::now()) as i64,
+            last_column_id: converter.last_id,
+            schemas: vec![json!({
+                "type": "struct",
+                "schema-id": 0,
+                "fields": fields,
+            })],
+            current_schema_id: 0,
+            last_partition_id: FIRST_PARTITION_FIELD_ID + partition_fields.len() as i64 - 1,
+            partition_specs: vec![PartitionSpec {
+                spec_id: 0,
+                fields: partition_fields,
+            }],
+            default_spec_id: 0,
+            // our parquet files don't have Iceberg field ids, so readers need to map by name
+            properties: HashMap::from([(
+                NAME_MAPPING_PROPERTY.to_string(),
+                serde_json::to_string(&name_mapping)?,
+            )]),
+            current_snapshot_id: None,
+            snapshots: vec![],
+            snapshot_log: vec![],
+            metadata_log: vec![],
+            refs: Map::new(),
+            other: Map::from_iter([
+                (
+                    "sort-orders".to_string(),
+                    json!([{"order-id": 0, "fields": []}]),
+                ),
+                ("default-sort-order-id".to_string(), json!(0)),
+            ]),
+        })
+    }
+
+    async fn data_file(&self, file: &FinishedFile, metadata: &TableMetadata) -> Result<DataFile> {
+        let location = Path::parse(&file.filename)?;
+        let object_meta = self.store.head(&location).await?;
+        let record_count = ParquetRecordBatchStreamBuilder::new(ParquetObjectReader::new(
+            self.store.clone(),
+            object_meta.clone(),
+        ))
+        .await?
+        .metadata()
+        .file_metadata()
+        .num_rows();
+
+        // partitioned files are written under `<table>/<field>=<value>/.../<file>`
+        let subpath = file
+            .filename
+            .strip_prefix(self.path.as_ref())
+            .ok_or_else(|| anyhow!("file {} is not in table {}", file.filename, self.path))?
+            .trim_start_matches('/');
+        let partition_values: HashMap<&str, &str> = subpath
+            .split('/')
+            .rev()
+            .skip(1)
+            .filter_map(|part| part.split_once('='))
+            .collect();
+
+        let schema_fields = metadata.current_schema()?["fields"]
+            .as_array()
+            .ok_or_else(|| anyhow!("invalid Iceberg schema"))?;
+        let partition = metadata
+            .default_spec()?
+            .fields
+            .iter()
+            .map(|field| {
+                let source_type = schema_fields
+                    .iter()
+                    .find(|f| f["id"].as_i64() == Some(field.source_id))
+                    .and_then(|f| f["type"].as_str())
+                    .ok_or_else(|| anyhow!("no source column for partition {}", field.name))?;
+                let value = partition_value(
+                    source_type,
+                    partition_values.get(field.name.as_str()).copied(),
+                )
+                .with_context(|| format!("invalid value for partition {}", field.name))?;
+                Ok((field.name.clone(), value))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(DataFile {
+            uri: self.uri(&file.filename),
+            size: object_meta.size as i64,
+            record_count,
+            partition,
+        })
+    }
+
+    /// Writes a manifest for the data files and a manifest list containing it along with the
+    /// manifests of the current snapshot, and returns the table metadata with the new snapshot
+    async fn append(
+        &self,
+        mut metadata: TableMetadata,
+        version: i64,
+        data_files: Vec<DataFile>,
+        marker: String,
+    ) -> Result<TableMetadata> {
+        let now = to_millis(SystemTime::now()) as i64;
+        let snapshot_id = (Uuid::new_v4().as_u64_pair().0 & i64::MAX as u64) as i64;
+        let sequence_number = metadata.last_sequence_number + 1;
+        let spec = metadata.default_spec()?.clone();
+        let table_schema = metadata.current_schema()?;
+
+        let mut partition_schema = vec![];
+        for field in &spec.fields {
+            let source_type = table_schema["fields"]
+                .as_array()
+                .and_then(|fields| {
+                    fields
+                        .iter()
+                        .find(|f| f["id"].as_i64() == Some(field.source_id))
+                })
+                .and_then(|f| f["type"].as_str())
+                .ok_or_else(|| anyhow!("no source column for partition {}", field.name))?;
+            partition_schema.push(json!({
+                "name": field.name,
+                "type": ["null", partition_value_type(source_type)
+                    .ok_or_else(|| anyhow!("unsupported partition type {}", source_type))?],
+                "default": null,
+                "field-id": field.field_id,
+            }));
+        }
+
+        let manifest_schema = AvroSchema::parse(&json!({
+            "type": "record",
+            "name": "manifest_entry",
+            "fields": [
+                {"name": "status", "type": "int", "field-id": 0},
+                {"name": "snapshot_id", "type": ["null", "long"], "default": null, "field-id": 1},
+                {"name": "sequence_number", "type": ["null", "long"], "default": null, "field-id": 3},
+                {"name": "file_sequence_number", "type": ["null", "long"], "default": null, "field-id": 4},
+                {"name": "data_file", "type": {
+                    "type": "record",
+                    "name": "r2",
+                    "fields": [
+                        {"name": "content", "type": "int", "field-id": 134},
+                        {"name": "file_path", "type": "string", "field-id": 100},
+                        {"name": "file_format", "type": "string", "field-id": 101},
+                        {"name": "partition", "type": {
+                            "type": "record",
+                            "name": "r102",
+                            "fields": partition_schema,
+                        }, "field-id": 102},
+                        {"name": "record_count", "type": "long", "field-id": 103},
+                        {"name": "file_size_in_bytes", "type": "long", "field-id": 104},
+                    ]
+                }, "field-id": 2},
+            ]
+        }))?;
+
+        let mut writer = Writer::new(&manifest_schema, Vec::new());
+        writer.add_user_metadata("schema".to_string(), serde_json::to_string(table_schema)?)?;
+        writer.add_user_metadata(
+            "schema-id".to_string(),
+            metadata.current_schema_id.to_string(),
+        )?;
+        writer.add_user_metadata(
+            "partition-spec".to_string(),
+            serde_json::to_string(&spec.fields)?,
+        )?;
+        writer.add_user_metadata("partition-spec-id".to_string(), spec.spec_id.to_string())?;
+        writer.add_user_metadata("format-version".to_string(), "2")?;
+        writer.add_user_metadata("content".to_string(), "data")?;
+
+        let added_files = data_files.len();
+        let added_rows: i64 = data_files.iter().map(|f| f.record_count).sum();
+        let added_size: i64 = data_files.iter().map(|f| f.size).sum();
+        for file in data_files {
+            writer.append(AvroValue::Record(vec![
+                ("status".to_string(), AvroValue::Int(1)),
+                (
+                    "snapshot_id".to_string(),
+                    AvroValue::Union(1, Box::new(AvroValue::Long(snapshot_id))),
+                ),
+                (
+                    "sequence_number".to_string(),
+                    AvroValue::Union(1, Box::new(AvroValue::Long(sequence_number))),
+                ),
+                (
+                    "file_sequence_number".to_string(),
+                    AvroValue::Union(1, Box::new(AvroValue::Long(sequence_number))),
+                ),
+                (
+                    "data_file".to_string(),
+                    AvroValue::Record(vec![
+                        ("content".to_string(), AvroValue::Int(0)),
+                        ("file_path".to_string(), AvroValue::String(file.uri)),
+                        (
+                            "file_format".to_string(),
+                            AvroValue::String("PARQUET".to_string()),
+                        ),
+                        ("partition".to_string(), AvroValue::Record(file.partition)),
+                        (
+                            "record_count".to_string(),
+                            AvroValue::Long(file.record_count),
+                        ),
+                        ("file_size_in_bytes".to_string(), AvroValue::Long(file.size)),
+                    ]),
+                ),
+            ]))?;
+        }
+
+        let manifest = writer.into_inner()?;
+        let manifest_path = self.metadata_path(&format!("{}-m0.avro", Uuid::new_v4()));
+        let manifest_length = manifest.len() as i64;
+        self.store
+            .put(&manifest_path, PutPayload::from(manifest))
+            .await?;
+
+        let manifest_list_schema = AvroSchema::parse_str(MANIFEST_LIST_SCHEMA)?;
+        let mut writer = Writer::new(&manifest_list_schema, Vec::new());
+        writer.add_user_metadata("snapshot-id".to_string(), snapshot_id.to_string())?;
+        writer.add_user_metadata("sequence-number".to_string(), sequence_number.to_string())?;
+        writer.add_user_metadata("format-version".to_string(), "2")?;
+
+        let parent = metadata.current_snapshot().cloned();
+        if let Some(parent) = &parent {
+            writer.add_user_metadata(
+                "parent-snapshot-id".to_string(),
+                parent.snapshot_id.to_string(),
+            )?;
+
+            let manifest_list = parent.manifest_list.as_ref().ok_or_else(|| {
+                anyhow!(
+                    "snapshot {} of the Iceberg table has no manifest list",
+                    parent.snapshot_id
+                )
+            })?;
+            let key = manifest_list
+                .strip_prefix(&self.base_url)
+                .ok_or_else(|| anyhow!("manifest list {} is not in this store", manifest_list))?
+                .trim_start_matches('/');
+            let bytes = self.store.get(&Path::parse(key)?).await?.bytes().await?;
+            for manifest in Reader::with_schema(&manifest_list_schema, &bytes[..])? {
+                writer.append(manifest?)?;
+            }
+        }
+
+        writer.append(AvroValue::Record(vec![
+            (
+                "manifest_path".to_string(),
+                AvroValue::String(self.uri(manifest_path.as_ref())),
+            ),
+            (
+                "manifest_length".to_string(),
+                AvroValue::Long(manifest_length),
+            ),
+            (
+                "partition_spec_id".to_string(),
+                AvroValue::Int(spec.spec_id as i32),
+            ),
+            ("content".to_string(), AvroValue::Int(0)),
+            (
+                "sequence_number".to_string(),
+                AvroValue::Long(sequence_number),
+            ),
+            (
+                "min_sequence_number".to_string(),
+                AvroValue::Long(sequence_number),
+            ),
+            (
+                "added_snapshot_id".to_string(),
+                AvroValue::Long(snapshot_id),
+            ),
+            (
+                "added_files_count".to_string(),
+                AvroValue::Int(added_files as i32),
+            ),
+            ("existing_files_count".to_string(), AvroValue::Int(0)),
+            ("deleted_files_count".to_string(), AvroValue::Int(0)),
+            ("added_rows_count".to_string(), AvroValue::Long(added_rows)),
+            ("existing_rows_count".to_string(), AvroValue::Long(0)),
+            ("deleted_rows_count".to_string(), AvroValue::Long(0)),
+            (
+                "partitions".to_string(),
+                AvroValue::Union(0, Box::new(AvroValue::Null)),
+            ),
+        ]))?;
+
+        let manifest_list_path =
+            self.metadata_path(&format!("snap-{}-1-{}.avro", snapshot_id, Uuid::new_v4()));
+        self.store
+            .put(&manifest_list_path, PutPayload::from(writer.into_inner()?))
+            .await?;
+
+        let mut summary = HashMap::from([
+            ("operation".to_string(), "append".to_string()),
+            ("added-data-files".to_string(), added_files.to_string()),
+            ("added-records".to_string(), added_rows.to_string()),
+            ("added-files-size".to_string(), added_size.to_string()),
+            (COMMIT_MARKER_PROPERTY.to_string(), marker),
+        ]);
+        if let Some(parent) = &parent {
+            for (total, added) in [
+                ("total-data-files", added_files as i64),
+                ("total-records", added_rows),
+                ("total-files-size", added_size),
+            ] {
+                if let Some(previous) = parent
+                    .summary
+                    .get(total)
+                    .and_then(|v| v.parse::<i64>().ok())
+                {
+                    summary.insert(total.to_string(), (previous + added).to_string());
+                }
+            }
+        } else {
+            summary.insert("total-data-files".to_string(), added_files.to_string());
+            summary.insert("total-records".to_string(), added_rows.to_string());
+            summary.insert("total-files-size".to_string(), added_size.to_string());
+        }
+
+        if version > 0 {
+            metadata.metadata_log.push(json!({
+                "metadata-file": self.uri(self.metadata_path(&Self::metadata_file(version)).as_ref()),
+                "timestamp-ms": metadata.last_updated_ms,
+            }));
+        }
+
+        metadata.snapshots.push(Snapshot {
+            snapshot_id,
+            parent_snapshot_id: parent.map(|p| p.snapshot_id),
+            sequence_number,
+            timestamp_ms: now,
+            manifest_list: Some(self.uri(manifest_list_path.as_ref())),
+            summary,
+            schema_id: Some(metadata.current_schema_id),
+            other: Map::new(),
+        });
+        metadata.snapshot_log.push(json!({
+            "snapshot-id": snapshot_id,
+            "timestamp-ms": now,
+        }));
+        metadata.refs.insert(
+            "main".to_string(),
+            json!({"snapshot-id": snapshot_id, "type": "branch"}),
+        );
+        metadata.current_snapshot_id = Some(snapshot_id);
+        metadata.last_sequence_number = sequence_number;
+        metadata.last_updated_ms = now;
+
+        Ok(metadata)
+    }
+
+    async fn write_metadata(&self, version: i64, metadata: &TableMetadata) -> Result<()> {
+        let path = self.metadata_path(&Self::metadata_file(version));
+        let payload = PutPayload::from(serde_json::to_vec_pretty(metadata)?);
+
+        match self
+            .store
+            .put_opts(&path, payload.clone(), PutMode::Create.into())
+            .await
+        {
+            Ok(_) => {}
+            Err(object_store::Error::AlreadyExists { .. }) => {
+                bail!(
+                    "version {} of the Iceberg table at {} was committed by another writer",
+                    version,
+                    self.path
+                );
+            }
+            Err(object_store::Error::NotImplemented) => {
+                // stores without conditional puts can't guarantee that the commit is atomic, so
+                // this relies on the sink being the only writer to the table
+                warn!("object store does not support conditional puts; Iceberg commits are not atomic");
+                if self.exists(&path).await? {
+                    bail!(
+                        "version {} of the Iceberg table at {} was committed by another writer",
+                        version,
+                        self.path
+                    );
+                }
+                self.store.put(&path, payload).await?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        self.store
+            .put(
+                &self.metadata_path(VERSION_HINT_FILE),
+                PutPayload::from(version.to_string()),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// The Avro type of an identity partition on a column of the given Iceberg type, for the types
+/// we can recover from the partition directory names
+fn partition_value_type(source_type: &str) -> Option<Value> {
+    Some(match source_type {
+        "string" | "int" | "long" | "boolean" => json!(source_type),
+        "date" => json!({"type": "int", "logicalType": "date"}),
+        _ => return None,
+    })
+}
+
+fn partition_value(source_type: &str, value: Option<&str>) -> Result<AvroValue> {
+    let value = match (source_type, value) {
+        (_, None) => return Ok(AvroValue::Union(0, Box::new(AvroValue::Null))),
+        ("string", Some(v)) => AvroValue::String(v.to_string()),
+        // nulls in non-string columns are written as empty partition values
+        (_, Some("")) => return Ok(AvroValue::Union(0, Box::new(AvroValue::Null))),
+        ("int", Some(v)) => AvroValue::Int(v.parse()?),
+        ("long", Some(v)) => AvroValue::Long(v.parse()?),
+        ("boolean", Some(v)) => AvroValue::Boolean(v.parse()?),
+        ("date", Some(v)) => AvroValue::Date(
+            (NaiveDate::parse_from_str(v, "%Y-%m-%d")? - NaiveDate::default()).num_days() as i32,
+        ),
+        (t, _) => bail!("unsupported partition type {}", t),
+    };
+    Ok(AvroValue::Union(1, Box::new(value)))
+}
+
+/// Converts arrow schemas to Iceberg schemas, assigning field ids, along with the name mapping
+/// that lets readers match up the columns in our data files with the Iceberg fields
+struct SchemaConverter {
+    last_id: i64,
+}
+
+impl SchemaConverter {
+    fn next_id(&mut self) -> i64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn convert_fields(&mut self, fields: &Fields) -> Result<(Vec<Value>, Vec<Value>)> {
+        // as in Iceberg, the fields of a struct get their ids before any of their children
+        let ids: Vec<_> = fields.iter().map(|_| self.next_id()).collect();
+
+        let mut iceberg_fields = vec![];
+        let mut mappings = vec![];
+        for (field, id) in fields.iter().zip(ids) {
+            let (field_type, nested) = self.convert_type(field)?;
+            iceberg_fields.push(json!({
+                "id": id,
+                "name": field.name(),
+                "required": !field.is_nullable(),
+                "type": field_type,
+            }));
+            mappings.push(name_mapping(id, &[field.name()], nested));
+        }
+
+        Ok((iceberg_fields, mappings))
+    }
+
+    fn convert_type(&mut self, field: &Field) -> Result<(Value, Vec<Value>)> {
+        let primitive = match field.data_type() {
+            DataType::Boolean => "boolean".to_string(),
+            DataType::Int8 | DataType::Int16 | DataType::Int32 => "int".to_string(),
+            DataType::UInt8 | DataType::UInt16 => "int".to_string(),
+            DataType::Int64 | DataType::UInt32 => "long".to_string(),
+            DataType::Float32 => "float".to_string(),
+            DataType::Float64 => "double".to_string(),
+            DataType::Utf8 | DataType::LargeUtf8 => "string".to_string(),
+            DataType::Binary | DataType::LargeBinary => "binary".to_string(),
+            DataType::Date32 => "date".to_string(),
+            DataType::Time64(TimeUnit::Microsecond) => "time".to_string(),
+            DataType::Timestamp(_, None) => "timestamp".to_string(),
+            DataType::Timestamp(_, Some(_)) => "timestamptz".to_string(),
+            DataType::Decimal128(precision, scale) => format!("decimal({}, {})", precision, scale),
+            DataType::Struct(fields) => {
+                let (fields, mappings) = self.convert_fields(fields)?;
+                return Ok((json!({"type": "struct", "fields": fields}), mappings));
+            }
+            DataType::List(element) | DataType::LargeList(element) => {
+                let id = self.next_id();
+                let (element_type, nested) = self.convert_type(element)?;
+                return Ok((
+                    json!({
+                        "type": "list",
+                        "element-id": id,
+                        "element": element_type,
+                        "element-required": !element.is_nullable(),
+                    }),
+                    vec![name_mapping(id, &["element", element.name()], nested)],
+                ));
+            }
+            DataType::Map(entries, _) => {
+                let DataType::Struct(entry_fields) = entries.data_type() else {
+                    bail!("invalid map type for field '{}'", field.name());
+                };
+                let (Some(key), Some(value)) = (entry_fields.first(), entry_fields.get(1)) else {
+                    bail!("invalid map type for field '{}'", field.name());
+                };
+                let (key_id, value_id) = (self.next_id(), self.next_id());
+                let (key_type, key_nested) = self.convert_type(key)?;
+                let (value_type, value_nested) = self.convert_type(value)?;
+                return Ok((
+                    json!({
+                        "type": "map",
+                        "key-id": key_id,
+                        "key": key_type,
+                        "value-id": value_id,
+                        "value": value_type,
+                        "value-required": !value.is_nullable(),
+                    }),
+                    vec![
+                        name_mapping(key_id, &["key", key.name()], key_nested),
+                        name_mapping(value_id, &["value", value.name()], value_nested),
+                    ],
+                ));
+            }
+            t => bail!(
+                "field '{}' has type {}, which is not supported in Iceberg tables",
+                field.name(),
+                t
+            ),
+        };
+
+        Ok((json!(primitive), vec![]))
+    }
+}
+
+fn name_mapping(id: i64, names: &[&str], fields: Vec<Value>) -> Value {
+    let mut names: Vec<_> = names.to_vec();
+    names.dedup();
+    let mut mapping = json!({"field-id": id, "names": names});
+    if !fields.is_empty() {
+        mapping["fields"] = json!(fields);
+    }
+    mapping
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        commit_files_to_iceberg, partition_value, FinishedFile, IcebergTable, SchemaConverter,
+        TableMetadata, COMMIT_MARKER_PROPERTY, MANIFEST_LIST_SCHEMA,
+    };
+    use apache_avro::types::Value as AvroValue;
+    use apache_avro::{Reader, Schema as AvroSchema};
+    use arrow::array::{Int64Array, RecordBatch};
+    use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+    use arroyo_storage::StorageProvider;
+    use object_store::path::Path;
+    use parquet::arrow::ArrowWriter;
+    use rand::random;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_schema_conversion() {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new(
+                "tags",
+                DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+                true,
+            ),
+            Field::new(
+                "location",
+                DataType::Struct(Fields::from(vec![
+                    Field::new("lat", DataType::Float64, false),
+                    Field::new("lon", DataType::Float64, false),
+                ])),
+                true,
+            ),
+            Field::new(
+                "created",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]);
+
+        let mut converter = SchemaConverter { last_id: 0 };
+        let (fields, mapping) = converter.convert_fields(schema.fields()).unwrap();
+
+        assert_eq!(converter.last_id, 7);
+        assert_eq!(
+            json!(fields),
+            json!([
+                {"id": 1, "name": "id", "required": true, "type": "long"},
+                {"id": 2, "name": "tags", "required": false, "type": {
+                    "type": "list", "element-id": 5, "element": "string", "element-required": false
+                }},
+                {"id": 3, "name": "location", "required": false, "type": {
+                    "type": "struct",
+                    "fields": [
+                        {"id": 6, "name": "lat", "required": true, "type": "double"},
+                        {"id": 7, "name": "lon", "required": true, "type": "double"},
+                    ]
+                }},
+                {"id": 4, "name": "created", "required": true, "type": "timestamp"},
+            ])
+        );
+
+        assert_eq!(
+            json!(mapping),
+            json!([
+                {"field-id": 1, "names": ["id"]},
+                {"field-id": 2, "names": ["tags"], "fields": [
+                    {"field-id": 5, "names": ["element", "item"]}
+                ]},
+                {"field-id": 3, "names": ["location"], "fields": [
+                    {"field-id": 6, "names": ["lat"]},
+                    {"field-id": 7, "names": ["lon"]},
+                ]},
+                {"field-id": 4, "names": ["created"]},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        let schema = Schema::new(vec![Field::new("n", DataType::UInt64, false)]);
+        assert!(SchemaConverter { last_id: 0 }
+            .convert_fields(schema.fields())
+            .is_err());
+    }
+
+    #[test]
+    fn test_partition_values() {
+        assert_eq!(
+            partition_value("long", Some("42")).unwrap(),
+            AvroValue::Union(1, Box::new(AvroValue::Long(42)))
+        );
+        assert_eq!(
+            partition_value("string", Some("")).unwrap(),
+            AvroValue::Union(1, Box::new(AvroValue::String("".to_string())))
+        );
+        assert_eq!(
+            partition_value("int", Some("")).unwrap(),
+            AvroValue::Union(0, Box::new(AvroValue::Null))
+        );
+        assert_eq!(
+            partition_value("date", Some("1970-01-03")).unwrap(),
+            AvroValue::Union(1, Box::new(AvroValue::Date(2)))
+        );
+        assert!(partition_value("int", Some("abc")).is_err());
+    }
+
+    /// Writes a parquet file with the given ids under `dir` and returns it as a finished file
+    fn write_data_file(
+        dir: &std::path::Path,
+        name: &str,
+        schema: &SchemaRef,
+        ids: Vec<i64>,
+    ) -> FinishedFile {
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(ids))]).unwrap();
+        let mut buf = vec![];
+        let mut writer = ArrowWriter::try_new(&mut buf, schema.clone(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        std::fs::create_dir_all(dir.join("table/data")).unwrap();
+        std::fs::write(dir.join("table/data").join(name), &buf).unwrap();
+
+        FinishedFile {
+            filename: format!("table/data/{}", name),
+            partition: None,
+            size: buf.len(),
+        }
+    }
+
+    /// Reads the `added_rows_count` of each manifest in a manifest list
+    fn manifest_row_counts(manifest_list: &str) -> Vec<i64> {
+        let path = manifest_list.strip_prefix("file://").unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        let schema = AvroSchema::parse_str(MANIFEST_LIST_SCHEMA).unwrap();
+        Reader::with_schema(&schema, &bytes[..])
+            .unwrap()
+            .map(|record| match record.unwrap() {
+                AvroValue::Record(fields) => fields
+                    .into_iter()
+                    .find_map(|(name, value)| match (name.as_str(), value) {
+                        ("added_rows_count", AvroValue::Long(count)) => Some(count),
+                        _ => None,
+                    })
+                    .unwrap(),
+                v => panic!("unexpected manifest list entry {:?}", v),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_commit_two_epochs() {
+        let dir = std::env::temp_dir().join(format!("arroyo-iceberg-{}", random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage_provider =
+            StorageProvider::for_url(&format!("file://{}", dir.to_str().unwrap()))
+                .await
+                .unwrap();
+        let table_path = Path::parse("table").unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+
+        let first = write_data_file(&dir, "part-1.parquet", &schema, vec![1, 2, 3]);
+        let version = commit_files_to_iceberg(
+            &[first],
+            &table_path,
+            &storage_provider,
+            0,
+            schema.clone(),
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(version, Some(1));
+
+        let second = write_data_file(&dir, "part-2.parquet", &schema, vec![4, 5]);
+        let version = commit_files_to_iceberg(
+            &[second.clone()],
+            &table_path,
+            &storage_provider,
+            1,
+            schema.clone(),
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(version, Some(2));
+
+        // committing the same files again after a restore finds the existing commit
+        let version =
+            commit_files_to_iceberg(&[second], &table_path, &storage_provider, 1, schema, &[])
+                .await
+                .unwrap();
+        assert_eq!(version, Some(2));
+
+        let metadata_dir = dir.join("table/metadata");
+        assert_eq!(
+            std::fs::read_to_string(metadata_dir.join("version-hint.text")).unwrap(),
+            "2"
+        );
+        assert!(metadata_dir.join("v1.metadata.json").exists());
+        assert!(!metadata_dir.join("v3.metadata.json").exists());
+
+        let metadata: TableMetadata =
+            serde_json::from_slice(&std::fs::read(metadata_dir.join("v2.metadata.json")).unwrap())
+                .unwrap();
+
+        assert_eq!(metadata.last_sequence_number, 2);
+        assert_eq!(metadata.snapshots.len(), 2);
+        let (first, second) = (&metadata.snapshots[0], &metadata.snapshots[1]);
+        assert_eq!(first.parent_snapshot_id, None);
+        assert_eq!(second.parent_snapshot_id, Some(first.snapshot_id));
+        assert_eq!(metadata.current_snapshot_id, Some(second.snapshot_id));
+        assert_eq!((first.sequence_number, second.sequence_number), (1, 2));
+        assert_eq!(second.summary["total-records"], "5");
+        assert_eq!(second.summary["added-records"], "2");
+        assert_eq!(
+            second.summary[COMMIT_MARKER_PROPERTY],
+            format!("file://{}/table/data/part-2.parquet", dir.to_str().unwrap())
+        );
+        assert_eq!(metadata.snapshot_log.len(), 2);
+        assert_eq!(metadata.metadata_log.len(), 1);
+        assert!(metadata.metadata_log[0]["metadata-file"]
+            .as_str()
+            .unwrap()
+            .ends_with("table/metadata/v1.metadata.json"));
+
+        // the second snapshot's manifest list carries the first snapshot's manifest forward
+        assert_eq!(
+            manifest_row_counts(first.manifest_list.as_ref().unwrap()),
+            vec![3]
+        );
+        let mut counts = manifest_row_counts(second.manifest_list.as_ref().unwrap());
+        counts.sort();
+        assert_eq!(counts, vec![2, 3]);
+
+        // metadata versions are created exclusively, so a concurrent writer can't overwrite one
+        let table = IcebergTable {
+            store: storage_provider.get_backing_store(),
+            base_url: storage_provider.object_store_base_url().to_string(),
+            path: table_path,
+        };
+        let err = table.write_metadata(2, &metadata).await.unwrap_err();
+        assert!(
+            err.to_string().contains("was committed by another writer"),
+            "{}",
+            err
+        );
+    }
+}
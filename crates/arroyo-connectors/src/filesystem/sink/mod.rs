@@ -44,13 +44,16 @@ use uuid::Uuid;
 
 use arroyo_types::*;
 pub mod arrow;
-mod delta;
+pub mod csv;
+pub(crate) mod delta;
+mod iceberg;
 pub mod json;
 pub mod local;
 pub mod parquet;
 mod two_phase_committer;
 
 use self::{
+    csv::{CsvLocalWriter, CsvWriter},
     json::{JsonLocalWriter, JsonWriter},
     local::LocalFileSystemWriter,
     parquet::{
@@ -82,6 +85,10 @@ pub type LocalParquetFileSystemSink = LocalFileSystemWriter<ParquetLocalWriter>;
 
 pub type LocalJsonFileSystemSink = LocalFileSystemWriter<JsonLocalWriter>;
 
+pub type CsvFileSystemSink = FileSystemSink<BatchMultipartWriter<CsvWriter>>;
+
+pub type LocalCsvFileSystemSink = LocalFileSystemWriter<CsvLocalWriter>;
+
 impl<R: MultiPartWriter + Send + 'static> FileSystemSink<R> {
     pub fn create_and_start(
         table: FileSystemTable,
@@ -92,7 +99,7 @@ impl<R: MultiPartWriter + Send + 'static> FileSystemSink<R> {
         };
         let commit_strategy = match file_settings.as_ref().unwrap().commit_style.unwrap() {
             CommitStyle::Direct => CommitStrategy::PerSubtask,
-            CommitStyle::DeltaLake => CommitStrategy::PerOperator,
+            CommitStyle::DeltaLake | CommitStyle::Iceberg => CommitStrategy::PerOperator,
         };
 
         TwoPhaseCommitterOperator::new(Self {
@@ -443,6 +450,7 @@ struct AsyncMultipartFileSystemWriter<R: MultiPartWriter> {
 #[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
 pub enum CommitState {
     DeltaLake { last_version: i64 },
+    Iceberg { last_version: i64 },
     VanillaParquet,
 }
 
@@ -724,6 +732,7 @@ where
 
         let commit_state = match file_settings.commit_style.unwrap() {
             CommitStyle::DeltaLake => CommitState::DeltaLake { last_version: -1 },
+            CommitStyle::Iceberg => CommitState::Iceberg { last_version: -1 },
             CommitStyle::Direct => CommitState::VanillaParquet,
         };
         let mut file_naming = file_settings.file_naming.clone().unwrap_or(FileNaming {
@@ -944,6 +953,29 @@ where
                 };
             }
         }
+        if let CommitState::Iceberg { last_version } = self.commit_state {
+            let TableType::Sink {
+                file_settings: Some(file_settings),
+                ..
+            } = &self.properties.table_type
+            else {
+                unreachable!("AsyncMultipartFileSystemWriter can only be used as a sink");
+            };
+            if let Some(new_version) = iceberg::commit_files_to_iceberg(
+                &finished_files,
+                &self.path,
+                &self.object_store,
+                last_version,
+                Arc::new(self.schema.schema_without_timestamp()),
+                iceberg::partition_fields(file_settings),
+            )
+            .await?
+            {
+                self.commit_state = CommitState::Iceberg {
+                    last_version: new_version,
+                };
+            }
+        }
         let finished_message = CheckpointData::Finished {
             max_file_index: self.max_file_index,
             delta_version: self.delta_version(),
@@ -954,7 +986,9 @@ where
 
     fn delta_version(&mut self) -> i64 {
         match self.commit_state {
-            CommitState::DeltaLake { last_version } => last_version,
+            CommitState::DeltaLake { last_version } | CommitState::Iceberg { last_version } => {
+                last_version
+            }
             CommitState::VanillaParquet => 0,
         }
     }
@@ -5,11 +5,12 @@ use std::{
     time::{Instant, SystemTime},
 };
 
-use crate::filesystem::{Compression, FormatSettings};
+use crate::filesystem::{CommitStyle, Compression, FormatSettings};
 use anyhow::Result;
 use arrow::{
     array::{Array, RecordBatch, StringArray, TimestampNanosecondArray},
     compute::{sort_to_indices, take},
+    datatypes::SchemaRef,
 };
 use arroyo_rpc::{df::ArroyoSchemaRef, formats::Format};
 use arroyo_types::from_nanos;
@@ -21,6 +22,7 @@ use parquet::{
 };
 
 use super::{
+    iceberg,
     local::{CurrentFileRecovery, FilePreCommit, LocalWriter},
     BatchBufferingWriter, FileSettings, FileSystemTable, MultiPartWriterStats, TableType,
 };
@@ -55,6 +57,22 @@ fn writer_properties_from_table(table: &FileSystemTable) -> WriterProperties {
     parquet_writer_options.build()
 }
 
+/// The schema of the data files written for the table, and whether batches need to be cast to it
+fn file_schema(table: &FileSystemTable, schema: &ArroyoSchemaRef) -> (SchemaRef, bool) {
+    let schema = schema.schema_without_timestamp();
+    match &table.table_type {
+        TableType::Sink {
+            file_settings:
+                Some(FileSettings {
+                    commit_style: Some(CommitStyle::Iceberg),
+                    ..
+                }),
+            ..
+        } => (Arc::new(iceberg::data_file_schema(&schema)), true),
+        _ => (Arc::new(schema), false),
+    }
+}
+
 /// A buffer with interior mutability shared by the [`ArrowWriter`] and
 /// [`AsyncArrowWriter`]. From Arrow. This lets us write data from the buffer to S3.
 #[derive(Clone)]
@@ -91,6 +109,7 @@ pub struct RecordBatchBufferingWriter {
     shared_buffer: SharedBuffer,
     target_part_size: usize,
     schema: ArroyoSchemaRef,
+    cast_to: Option<SchemaRef>,
 }
 
 impl BatchBufferingWriter for RecordBatchBufferingWriter {
@@ -110,9 +129,10 @@ impl BatchBufferingWriter for RecordBatchBufferingWriter {
         };
         let shared_buffer = SharedBuffer::new(target_part_size);
         let writer_properties = writer_properties_from_table(config);
+        let (file_schema, needs_cast) = file_schema(config, &schema);
         let writer = ArrowWriter::try_new(
             shared_buffer.clone(),
-            Arc::new(schema.schema_without_timestamp()),
+            file_schema.clone(),
             Some(writer_properties),
         )
         .unwrap();
@@ -122,6 +142,7 @@ impl BatchBufferingWriter for RecordBatchBufferingWriter {
             shared_buffer,
             target_part_size,
             schema,
+            cast_to: needs_cast.then_some(file_schema),
         }
     }
 
@@ -133,6 +154,9 @@ impl BatchBufferingWriter for RecordBatchBufferingWriter {
         let writer = self.writer.as_mut().unwrap();
         // remove timestamp column
         self.schema.remove_timestamp_column(&mut data);
+        if let Some(schema) = &self.cast_to {
+            data = iceberg::cast_to_data_file_schema(&data, schema).unwrap();
+        }
         writer.write(&data).unwrap();
         if self.buffer_length() > self.target_part_size {
             Some(self.evict_current_buffer())
@@ -187,6 +211,7 @@ pub struct ParquetLocalWriter {
     shared_buffer: SharedBuffer,
     stats: Option<MultiPartWriterStats>,
     schema: ArroyoSchemaRef,
+    cast_to: Option<SchemaRef>,
 }
 
 impl LocalWriter for ParquetLocalWriter {
@@ -199,9 +224,10 @@ impl LocalWriter for ParquetLocalWriter {
     ) -> Self {
         let shared_buffer = SharedBuffer::new(0);
         let writer_properties = writer_properties_from_table(table_properties);
+        let (file_schema, needs_cast) = file_schema(table_properties, &schema);
         let writer = ArrowWriter::try_new(
             shared_buffer.clone(),
-            Arc::new(schema.schema_without_timestamp()),
+            file_schema.clone(),
             Some(writer_properties),
         )
         .unwrap();
@@ -214,6 +240,7 @@ impl LocalWriter for ParquetLocalWriter {
             shared_buffer,
             stats: None,
             schema,
+            cast_to: needs_cast.then_some(file_schema),
         }
     }
 
@@ -236,6 +263,9 @@ impl LocalWriter for ParquetLocalWriter {
             self.stats.as_mut().unwrap().last_write_at = Instant::now();
         }
         self.schema.remove_timestamp_column(&mut batch);
+        if let Some(schema) = &self.cast_to {
+            batch = iceberg::cast_to_data_file_schema(&batch, schema)?;
+        }
         self.writer.as_mut().unwrap().write(&batch)?;
         Ok(())
     }
@@ -0,0 +1,115 @@
+use arroyo_storage::StorageProvider;
+use arroyo_types::UserError;
+use bincode::{Decode, Encode};
+use deltalake::kernel::{Action, Add};
+use deltalake::table::PeekCommit;
+use deltalake::{DeltaTable, DeltaTableBuilder};
+use object_store::path::Path;
+
+use crate::filesystem::sink::delta::configure_storage_options;
+
+/// How far through a Delta Lake table a subtask has read
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq)]
+pub struct DeltaReadState {
+    /// The last version of the table that has been read
+    pub version: i64,
+    /// Whether all of the files in the snapshot of the table at `version` have been read; until
+    /// then, `version` is the version of the snapshot being read
+    pub snapshot_complete: bool,
+}
+
+fn delta_error(err: impl ToString) -> UserError {
+    UserError::new("failed to read Delta Lake table", err.to_string())
+}
+
+/// Loads the Delta Lake table at `path`, at the given version or otherwise the latest one
+pub(crate) async fn load_table(
+    path: &str,
+    storage_provider: &StorageProvider,
+    version: Option<i64>,
+) -> Result<DeltaTable, UserError> {
+    deltalake::aws::register_handlers(None);
+    let storage_options = configure_storage_options(path, storage_provider)
+        .await
+        .map_err(delta_error)?;
+
+    let mut builder = DeltaTableBuilder::from_uri(path).with_storage_options(storage_options);
+    if let Some(version) = version {
+        builder = builder.with_version(version);
+    }
+
+    let table = builder.load().await.map_err(delta_error)?;
+    check_partition_columns(&table.metadata().map_err(delta_error)?.partition_columns)?;
+    Ok(table)
+}
+
+/// Partition values aren't stored in the data files, so reading a partitioned table would drop
+/// its partition columns
+fn check_partition_columns(partition_columns: &[String]) -> Result<(), UserError> {
+    if partition_columns.is_empty() {
+        Ok(())
+    } else {
+        Err(delta_error(format!(
+            "partitioned tables are not supported by the Delta Lake source, but the table is \
+            partitioned by {}",
+            partition_columns.join(", ")
+        )))
+    }
+}
+
+/// Rows deleted through a deletion vector are still present in the data file, so files with them
+/// can't be read as-is
+fn data_file(add: Add) -> Result<Path, UserError> {
+    if add.deletion_vector.is_some() {
+        return Err(delta_error(format!(
+            "the data file {} has a deletion vector, which is not supported by the Delta Lake \
+            source",
+            add.path
+        )));
+    }
+    Path::from_url_path(&add.path).map_err(delta_error)
+}
+
+/// The data files that make up the loaded snapshot of the table, relative to the table root
+pub(crate) fn snapshot_files(table: &DeltaTable) -> Result<Vec<Path>, UserError> {
+    table
+        .snapshot()
+        .and_then(|snapshot| snapshot.file_actions())
+        .map_err(delta_error)?
+        .into_iter()
+        .map(data_file)
+        .collect()
+}
+
+/// Returns the version of the commit that follows `version`, along with the data files it added,
+/// or None if there are no new commits. As the source only reads appends, files that were
+/// rewritten without changing the data (as in a compaction) are skipped, while commits that
+/// remove data, or that partition the table, fail the source.
+pub(crate) async fn next_commit(
+    table: &DeltaTable,
+    version: i64,
+) -> Result<Option<(i64, Vec<Path>)>, UserError> {
+    match table.peek_next_commit(version).await.map_err(delta_error)? {
+        PeekCommit::New(version, actions) => {
+            let mut files = vec![];
+            for action in actions {
+                match action {
+                    Action::Add(add) if add.data_change => files.push(data_file(add)?),
+                    Action::Remove(remove) if remove.data_change => {
+                        return Err(delta_error(format!(
+                            "commit {} removes data from the table (in {}), but the Delta Lake \
+                            source only supports tables that are appended to",
+                            version, remove.path
+                        )));
+                    }
+                    Action::Metadata(metadata) => {
+                        check_partition_columns(&metadata.partition_columns)?
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Some((version, files)))
+        }
+        PeekCommit::UpToDate => Ok(None),
+    }
+}
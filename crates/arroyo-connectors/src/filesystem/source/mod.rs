@@ -1,8 +1,12 @@
+mod delta;
+#[cfg(test)]
+mod test;
+
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::future::ready;
 use std::hash::{Hash, Hasher};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use anyhow::Result;
 use arrow::array::RecordBatch;
@@ -15,6 +19,8 @@ use async_trait::async_trait;
 use bincode::{Decode, Encode};
 use datafusion::common::ScalarValue;
 use futures::StreamExt;
+use object_store::path::Path;
+use object_store::ObjectMeta;
 use parquet::arrow::async_reader::ParquetObjectReader;
 use parquet::arrow::ParquetRecordBatchStreamBuilder;
 
@@ -26,15 +32,18 @@ use tokio_stream::wrappers::LinesStream;
 use tokio_stream::Stream;
 use tracing::info;
 
-use crate::filesystem::{CompressionFormat, TableType};
+use crate::filesystem::{CompressionFormat, FileOrder, TableFormat, TableType};
+use crate::without_csv_header;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
-use arroyo_rpc::formats::{BadData, Format, Framing};
+use arroyo_rpc::formats::{BadData, CsvFormat, Format, Framing};
 use arroyo_rpc::grpc::rpc::TableConfig;
-use arroyo_rpc::{grpc::rpc::StopMode, ControlMessage};
+use arroyo_rpc::{grpc::rpc::StopMode, ControlMessage, OperatorConfig};
 use arroyo_storage::StorageProvider;
 use arroyo_types::{to_nanos, UserError};
 
+use self::delta::DeltaReadState;
+
 #[allow(unused)]
 pub struct FileSystemSourceFunc {
     pub table: TableType,
@@ -42,18 +51,44 @@ pub struct FileSystemSourceFunc {
     pub framing: Option<Framing>,
     pub bad_data: Option<BadData>,
     pub file_states: HashMap<String, FileReadState>,
+    /// For Delta Lake tables, how far through the table this subtask has read
+    pub delta_state: Option<DeltaReadState>,
 }
 
 #[derive(Encode, Decode, Debug, Clone, PartialEq, PartialOrd)]
 pub enum FileReadState {
     Finished,
     RecordsRead(usize),
+    /// The file has been fully read as of the given last modified time (in milliseconds since the
+    /// epoch); when monitoring, if its last modified time changes it's read again, either from
+    /// the start or, for append-only sources, after the records that were already read
+    FinishedModified {
+        last_modified: i64,
+        records_read: usize,
+    },
+}
+
+impl FileReadState {
+    /// Whether a file with this state and the given last modified time needs to be read
+    fn needs_read(state: Option<&FileReadState>, last_modified: i64) -> bool {
+        match state {
+            None | Some(FileReadState::RecordsRead(_)) => true,
+            Some(FileReadState::Finished) => false,
+            Some(FileReadState::FinishedModified {
+                last_modified: t, ..
+            }) => *t != last_modified,
+        }
+    }
 }
 
 #[async_trait]
 impl SourceOperator for FileSystemSourceFunc {
     fn tables(&self) -> HashMap<String, TableConfig> {
-        global_table_config("a", "fs")
+        let mut tables = global_table_config("a", "fs");
+        if self.is_delta() {
+            tables.extend(global_table_config("v", "delta versions"));
+        }
+        tables
     }
 
     fn name(&self) -> String {
@@ -73,6 +108,29 @@ impl SourceOperator for FileSystemSourceFunc {
 }
 
 impl FileSystemSourceFunc {
+    pub fn new(table: TableType, config: OperatorConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            table,
+            format: config
+                .format
+                .ok_or_else(|| anyhow::anyhow!("format required for FileSystem source"))?,
+            framing: config.framing,
+            bad_data: config.bad_data,
+            file_states: HashMap::new(),
+            delta_state: None,
+        })
+    }
+
+    fn is_delta(&self) -> bool {
+        matches!(
+            self.table,
+            TableType::Source {
+                table_format: Some(TableFormat::DeltaLake),
+                ..
+            }
+        )
+    }
+
     #[allow(unused)]
     fn get_compression_format(&self) -> CompressionFormat {
         match &self.table {
@@ -84,68 +142,60 @@ impl FileSystemSourceFunc {
     }
 
     async fn run_int(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
-        let (storage_provider, regex_pattern) = match &self.table {
-            TableType::Source {
-                path,
-                storage_options,
-                compression_format: _,
-                regex_pattern,
-            } => {
-                let storage_provider =
-                    StorageProvider::for_url_with_options(path, storage_options.clone())
-                        .await
+        let (path, storage_provider, regex_pattern, monitor_interval, append_only, file_order) =
+            match &self.table {
+                TableType::Source {
+                    path,
+                    storage_options,
+                    compression_format: _,
+                    regex_pattern,
+                    monitor_interval_ms,
+                    append_only,
+                    file_order,
+                    table_format: _,
+                } => {
+                    let storage_provider =
+                        StorageProvider::for_url_with_options(path, storage_options.clone())
+                            .await
+                            .map_err(|err| {
+                                UserError::new("failed to create storage provider", err.to_string())
+                            })?;
+                    let matcher = regex_pattern
+                        .as_ref()
+                        .map(|pattern| Regex::new(pattern))
+                        .transpose()
                         .map_err(|err| {
-                            UserError::new("failed to create storage provider", err.to_string())
+                            UserError::new(
+                                format!(
+                                    "invalid regex pattern {}",
+                                    regex_pattern.as_ref().unwrap()
+                                ),
+                                err.to_string(),
+                            )
                         })?;
-                let matcher = regex_pattern
-                    .as_ref()
-                    .map(|pattern| Regex::new(pattern))
-                    .transpose()
-                    .map_err(|err| {
-                        UserError::new(
-                            format!("invalid regex pattern {}", regex_pattern.as_ref().unwrap()),
-                            err.to_string(),
-                        )
-                    })?;
-                (storage_provider, matcher)
-            }
-            TableType::Sink { .. } => {
-                return Err(UserError::new(
-                    "invalid table config",
-                    "filesystem source cannot be used as a sink".to_string(),
-                ))
-            }
-        };
+                    let monitor_interval =
+                        monitor_interval_ms.map(|ms| Duration::from_millis(ms.max(0) as u64));
+                    (
+                        path.clone(),
+                        storage_provider,
+                        matcher,
+                        monitor_interval,
+                        append_only.unwrap_or(false),
+                        (*file_order).unwrap_or(FileOrder::Path),
+                    )
+                }
+                TableType::Sink { .. } => {
+                    return Err(UserError::new(
+                        "invalid table config",
+                        "filesystem source cannot be used as a sink".to_string(),
+                    ))
+                }
+            };
         ctx.initialize_deserializer(
-            self.format.clone(),
+            without_csv_header(&self.format),
             self.framing.clone(),
             self.bad_data.clone(),
         );
-        let parallelism = ctx.task_info.parallelism;
-        let task_index = ctx.task_info.task_index;
-
-        // TODO: sort by creation time
-        let mut file_paths = storage_provider
-            .list(regex_pattern.is_some())
-            .await
-            .map_err(|err| UserError::new("could not list files", err.to_string()))?
-            .filter(|path| {
-                let Ok(path) = path else {
-                    return ready(true);
-                };
-                // hash the path and modulo by the number of tasks
-                let mut hasher = DefaultHasher::new();
-                path.hash(&mut hasher);
-                if (hasher.finish() as usize) % parallelism != task_index {
-                    return ready(false);
-                }
-
-                if let Some(matcher) = &regex_pattern {
-                    ready(matcher.is_match(path.as_ref()))
-                } else {
-                    ready(true)
-                }
-            });
 
         let state: &mut GlobalKeyedView<String, (String, FileReadState)> = ctx
             .table_manager
@@ -154,31 +204,257 @@ impl FileSystemSourceFunc {
             .expect("should have table");
         self.file_states = state.get_all().clone().into_values().collect();
 
-        while let Some(path) = file_paths.next().await {
-            let obj_key = path
-                .map_err(|err| UserError::new("could not get next path", err.to_string()))?
-                .to_string();
+        if self.is_delta() {
+            return self
+                .run_delta(ctx, &path, &storage_provider, monitor_interval)
+                .await;
+        }
 
-            if let Some(FileReadState::Finished) = self.file_states.get(&obj_key) {
-                // already finished
-                continue;
+        loop {
+            let files = self
+                .list_unread_files(ctx, &storage_provider, regex_pattern.as_ref(), file_order)
+                .await?;
+
+            for (obj_key, last_modified) in files {
+                if let Some(FileReadState::FinishedModified { records_read, .. }) =
+                    self.file_states.get(&obj_key)
+                {
+                    let records_read = if append_only { *records_read } else { 0 };
+                    info!(
+                        "{} has been modified; reading it from record {}",
+                        obj_key, records_read
+                    );
+                    self.file_states
+                        .insert(obj_key.clone(), FileReadState::RecordsRead(records_read));
+                }
+
+                if let Some(finish_type) = self.read_file(ctx, &storage_provider, &obj_key).await? {
+                    return Ok(finish_type);
+                }
+
+                let Some(FileReadState::RecordsRead(records_read)) = self.file_states.get(&obj_key)
+                else {
+                    unreachable!("{} should have been read", obj_key);
+                };
+                self.file_states.insert(
+                    obj_key.clone(),
+                    FileReadState::FinishedModified {
+                        last_modified,
+                        records_read: *records_read,
+                    },
+                );
             }
 
-            if let Some(finish_type) = self.read_file(ctx, &storage_provider, &obj_key).await? {
+            let Some(monitor_interval) = monitor_interval else {
+                break;
+            };
+
+            if let Some(finish_type) = self.wait_for_next_poll(ctx, monitor_interval).await {
                 return Ok(finish_type);
             }
         }
+
         info!("FileSystem source finished");
         Ok(SourceFinishType::Final)
     }
 
+    /// Reads the snapshot of the Delta Lake table at `path`, then, if monitoring, each new
+    /// commit to the table as it's made
+    async fn run_delta(
+        &mut self,
+        ctx: &mut ArrowContext,
+        path: &str,
+        storage_provider: &StorageProvider,
+        monitor_interval: Option<Duration>,
+    ) -> Result<SourceFinishType, UserError> {
+        let versions: &mut GlobalKeyedView<usize, DeltaReadState> = ctx
+            .table_manager
+            .get_global_keyed_state("v")
+            .await
+            .expect("should have table");
+
+        // resume from the subtask that's furthest behind; any files that it would read again
+        // have already been marked as finished
+        let restored = versions
+            .get_all()
+            .values()
+            .min_by_key(|s| (s.snapshot_complete, s.version))
+            .copied();
+
+        let mut state = match restored {
+            Some(state) => state,
+            None => {
+                let table = delta::load_table(path, storage_provider, None).await?;
+                DeltaReadState {
+                    version: table.version(),
+                    snapshot_complete: false,
+                }
+            }
+        };
+        self.delta_state = Some(state);
+
+        if !state.snapshot_complete {
+            info!(
+                "reading snapshot of Delta Lake table {} at version {}",
+                path, state.version
+            );
+            let table = delta::load_table(path, storage_provider, Some(state.version)).await?;
+            let files = delta::snapshot_files(&table)?;
+            if let Some(finish_type) = self.read_delta_files(ctx, storage_provider, files).await? {
+                return Ok(finish_type);
+            }
+
+            state.snapshot_complete = true;
+            self.delta_state = Some(state);
+        }
+
+        let Some(monitor_interval) = monitor_interval else {
+            info!("FileSystem source finished");
+            return Ok(SourceFinishType::Final);
+        };
+
+        let table = delta::load_table(path, storage_provider, Some(state.version)).await?;
+        loop {
+            while let Some((version, files)) = delta::next_commit(&table, state.version).await? {
+                info!("reading commit {} of Delta Lake table {}", version, path);
+                if let Some(finish_type) =
+                    self.read_delta_files(ctx, storage_provider, files).await?
+                {
+                    return Ok(finish_type);
+                }
+
+                state.version = version;
+                self.delta_state = Some(state);
+            }
+
+            if let Some(finish_type) = self.wait_for_next_poll(ctx, monitor_interval).await {
+                return Ok(finish_type);
+            }
+        }
+    }
+
+    /// Reads the given data files (relative to the table root) of a Delta Lake table that are
+    /// assigned to this subtask and haven't yet been read
+    async fn read_delta_files(
+        &mut self,
+        ctx: &mut ArrowContext,
+        storage_provider: &StorageProvider,
+        files: Vec<Path>,
+    ) -> Result<Option<SourceFinishType>, UserError> {
+        for file in files {
+            let location = storage_provider.qualify_path(&file).into_owned();
+            if !Self::assigned_to_task(ctx, &location) {
+                continue;
+            }
+
+            let obj_key = location.to_string();
+            if matches!(
+                self.file_states.get(&obj_key),
+                Some(FileReadState::Finished | FileReadState::FinishedModified { .. })
+            ) {
+                continue;
+            }
+
+            if let Some(finish_type) = self.read_file(ctx, storage_provider, &obj_key).await? {
+                return Ok(Some(finish_type));
+            }
+            self.file_states.insert(obj_key, FileReadState::Finished);
+        }
+
+        Ok(None)
+    }
+
+    /// Waits for the given interval before the source is polled again, while continuing to handle
+    /// checkpoints and stops
+    async fn wait_for_next_poll(
+        &mut self,
+        ctx: &mut ArrowContext,
+        interval: Duration,
+    ) -> Option<SourceFinishType> {
+        let next_poll = tokio::time::sleep(interval);
+        tokio::pin!(next_poll);
+        loop {
+            select! {
+                _ = &mut next_poll => return None,
+                msg_res = ctx.control_rx.recv() => {
+                    if let Some(control_message) = msg_res {
+                        if let Some(finish_type) = self.process_control_message(ctx, control_message).await {
+                            return Some(finish_type);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Files are distributed among the subtasks by the hash of their path
+    fn assigned_to_task(ctx: &ArrowContext, location: &Path) -> bool {
+        let mut hasher = DefaultHasher::new();
+        location.hash(&mut hasher);
+        (hasher.finish() as usize) % ctx.task_info.parallelism == ctx.task_info.task_index
+    }
+
+    /// Lists the files under the source path that are assigned to this subtask and still need to
+    /// be read, along with their last modified times, in the configured order
+    async fn list_unread_files(
+        &self,
+        ctx: &ArrowContext,
+        storage_provider: &StorageProvider,
+        regex_pattern: Option<&Regex>,
+        file_order: FileOrder,
+    ) -> Result<Vec<(String, i64)>, UserError> {
+        let mut files: Vec<ObjectMeta> = storage_provider
+            .list_with_metadata(regex_pattern.is_some())
+            .await
+            .map_err(|err| UserError::new("could not list files", err.to_string()))?
+            .filter(|meta| {
+                let Ok(meta) = meta else {
+                    return ready(true);
+                };
+                if !Self::assigned_to_task(ctx, &meta.location) {
+                    return ready(false);
+                }
+
+                if let Some(matcher) = regex_pattern {
+                    ready(matcher.is_match(meta.location.as_ref()))
+                } else {
+                    ready(true)
+                }
+            })
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .map_err(|err| UserError::new("could not get next path", err.to_string()))?;
+
+        match file_order {
+            FileOrder::Path => files.sort_by(|a, b| a.location.cmp(&b.location)),
+            FileOrder::ModifiedTime => files.sort_by(|a, b| {
+                (a.last_modified, &a.location).cmp(&(b.last_modified, &b.location))
+            }),
+        }
+
+        Ok(files
+            .into_iter()
+            .map(|meta| {
+                (
+                    meta.location.to_string(),
+                    meta.last_modified.timestamp_millis(),
+                )
+            })
+            .filter(|(obj_key, last_modified)| {
+                FileReadState::needs_read(self.file_states.get(obj_key), *last_modified)
+            })
+            .collect())
+    }
+
     async fn get_newline_separated_stream(
         &mut self,
         storage_provider: &StorageProvider,
         path: String,
     ) -> Result<Box<dyn Stream<Item = Result<String, UserError>> + Unpin + Send>, UserError> {
         match &self.format {
-            Format::Json(_) => {
+            Format::Json(_) | Format::Csv(_) => {
                 let stream_reader = storage_provider.get_as_stream(path).await.unwrap();
 
                 let compression_reader: Box<dyn AsyncRead + Unpin + Send> =
@@ -272,6 +548,9 @@ impl FileSystemSourceFunc {
         }
     }
 
+    /// Reads the file, skipping the records that have already been read. Once it's been read to
+    /// the end its state is left with the number of records read, for the caller to mark it as
+    /// finished.
     async fn read_file(
         &mut self,
         ctx: &mut ArrowContext,
@@ -284,7 +563,7 @@ impl FileSystemSourceFunc {
             .or_insert(FileReadState::RecordsRead(0));
         let records_read = match read_state {
             FileReadState::RecordsRead(records_read) => *records_read,
-            FileReadState::Finished => {
+            FileReadState::Finished | FileReadState::FinishedModified { .. } => {
                 return Err(UserError::new(
                     "reading finished file",
                     format!("{} has already been read", obj_key),
@@ -293,7 +572,7 @@ impl FileSystemSourceFunc {
         };
 
         match self.format {
-            Format::Json(_) => {
+            Format::Json(_) | Format::Csv(_) => {
                 let line_reader = self
                     .get_newline_separated_stream(storage_provider, obj_key.to_string())
                     .await?
@@ -338,7 +617,7 @@ impl FileSystemSourceFunc {
                         }
                         None => {
                             info!("finished reading file {}", obj_key);
-                            self.file_states.insert(obj_key.to_string(), FileReadState::Finished);
+                            self.file_states.insert(obj_key.to_string(), FileReadState::RecordsRead(records_read));
                             return Ok(None);
                         }
                     }
@@ -362,12 +641,16 @@ impl FileSystemSourceFunc {
         obj_key: &String,
         mut records_read: usize,
     ) -> Result<Option<SourceFinishType>, UserError> {
+        let has_header = matches!(self.format, Format::Csv(CsvFormat { header: true, .. }));
+
         loop {
             select! {
                 line = line_reader.next() => {
                     match line.transpose()? {
                         Some(line) => {
-                            ctx.deserialize_slice(line.as_bytes(), SystemTime::now(), None).await?;
+                            if !(has_header && records_read == 0) {
+                                ctx.deserialize_slice(line.as_bytes(), SystemTime::now(), None).await?;
+                            }
                             records_read += 1;
                             if ctx.should_flush() {
                                 ctx.flush_buffer().await?;
@@ -376,7 +659,7 @@ impl FileSystemSourceFunc {
                         None => {
                             info!("finished reading file {}", obj_key);
                             ctx.flush_buffer().await?;
-                            self.file_states.insert(obj_key.to_string(), FileReadState::Finished);
+                            self.file_states.insert(obj_key.to_string(), FileReadState::RecordsRead(records_read));
                             return Ok(None);
                         }
                     }
@@ -408,6 +691,14 @@ impl FileSystemSourceFunc {
                         .insert(file.clone(), (file.clone(), read_state.clone()))
                         .await;
                 }
+                if let Some(delta_state) = self.delta_state {
+                    ctx.table_manager
+                        .get_global_keyed_state("v")
+                        .await
+                        .unwrap()
+                        .insert(ctx.task_info.task_index, delta_state)
+                        .await;
+                }
                 // checkpoint our state
                 if self.start_checkpoint(c, ctx).await {
                     Some(SourceFinishType::Immediate)
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arrow::array::{Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use arroyo_operator::context::{batch_bounded, ArrowContext, BatchReceiver};
+use arroyo_operator::operator::SourceOperator;
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::formats::{CsvFormat, Format, JsonFormat, ParquetFormat};
+use arroyo_rpc::grpc::rpc::StopMode;
+use arroyo_rpc::ControlMessage;
+use arroyo_storage::StorageProvider;
+use arroyo_types::ArrowMessage;
+use parquet::arrow::ArrowWriter;
+use rand::random;
+use tokio::sync::mpsc::{channel, Sender};
+
+use crate::filesystem::{FileOrder, TableFormat, TableType};
+
+use super::{delta, FileSystemSourceFunc};
+
+async fn next_values(data_recv: &mut BatchReceiver, count: usize) -> Vec<String> {
+    let mut values = vec![];
+    while values.len() < count {
+        let message = tokio::time::timeout(Duration::from_secs(10), data_recv.recv())
+            .await
+            .expect("timed out waiting for records")
+            .expect("source should still be running");
+
+        if let ArrowMessage::Data(batch) = message {
+            values.extend(string_values(&batch));
+        }
+    }
+    values
+}
+
+fn string_values(batch: &RecordBatch) -> Vec<String> {
+    batch
+        .column_by_name("value")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap()
+        .iter()
+        .map(|v| v.unwrap().to_string())
+        .collect()
+}
+
+async fn test_context(
+    source: &FileSystemSourceFunc,
+    fields: Vec<Field>,
+    timestamp_index: usize,
+) -> (ArrowContext, Sender<ControlMessage>, BatchReceiver) {
+    let (control_tx, control_rx) = channel(128);
+    let (command_tx, _command_rx) = channel(128);
+    let (data_tx, data_recv) = batch_bounded(128);
+
+    let mut task_info = arroyo_types::get_test_task_info();
+    task_info.job_id = format!("fs-job-{}", random::<u64>());
+
+    let ctx = ArrowContext::new(
+        task_info,
+        None,
+        control_rx,
+        command_tx,
+        1,
+        vec![],
+        Some(ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(fields)),
+            timestamp_index,
+        )),
+        None,
+        vec![vec![data_tx]],
+        vec![],
+        source.tables(),
+    )
+    .await;
+
+    (ctx, control_tx, data_recv)
+}
+
+/// Runs a monitoring source over a JSON file containing `a` and `b`, then applies `modify` to the
+/// file and returns the values that are read after it's modified
+async fn values_after_modification(
+    append_only: Option<bool>,
+    modify: impl FnOnce(&Path),
+) -> Vec<String> {
+    let dir = std::env::temp_dir().join(format!("arroyo-fs-source-{}", random::<u64>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("events.json");
+    std::fs::write(&file, "{\"value\": \"a\"}\n{\"value\": \"b\"}\n").unwrap();
+
+    let mut source = Box::new(FileSystemSourceFunc {
+        table: TableType::Source {
+            path: format!("file://{}", dir.to_str().unwrap()),
+            storage_options: HashMap::new(),
+            compression_format: None,
+            regex_pattern: None,
+            monitor_interval_ms: Some(50),
+            append_only,
+            file_order: Some(FileOrder::Path),
+            table_format: Some(TableFormat::Files),
+        },
+        format: Format::Json(JsonFormat::default()),
+        framing: None,
+        bad_data: None,
+        file_states: HashMap::new(),
+        delta_state: None,
+    });
+
+    let (mut ctx, control_tx, mut data_recv) = test_context(
+        &source,
+        vec![
+            Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+            Field::new("value", DataType::Utf8, false),
+        ],
+        0,
+    )
+    .await;
+
+    tokio::spawn(async move {
+        source.run(&mut ctx).await;
+    });
+
+    assert_eq!(next_values(&mut data_recv, 2).await, vec!["a", "b"]);
+
+    // make sure the change is seen as a modification
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    modify(&file);
+
+    let mut values = next_values(&mut data_recv, 1).await;
+    // collect anything else the source reads from the file
+    while let Ok(Some(message)) =
+        tokio::time::timeout(Duration::from_millis(300), data_recv.recv()).await
+    {
+        if let ArrowMessage::Data(batch) = message {
+            values.extend(string_values(&batch));
+        }
+    }
+
+    control_tx
+        .send(ControlMessage::Stop {
+            mode: StopMode::Immediate,
+        })
+        .await
+        .unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    values
+}
+
+#[tokio::test]
+async fn test_appended_file_is_read_from_where_it_left_off() {
+    let values = values_after_modification(Some(true), |file| {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(file)
+            .unwrap()
+            .write_all(b"{\"value\": \"c\"}\n")
+            .unwrap();
+    })
+    .await;
+
+    assert_eq!(values, vec!["c"]);
+}
+
+#[tokio::test]
+async fn test_modified_file_is_read_again() {
+    let values = values_after_modification(None, |file| {
+        std::fs::write(file, "{\"value\": \"x\"}\n{\"value\": \"b\"}\n").unwrap();
+    })
+    .await;
+
+    assert_eq!(values, vec!["x", "b"]);
+}
+
+#[tokio::test]
+async fn test_csv_header_is_only_skipped_at_start_of_file() {
+    let dir = std::env::temp_dir().join(format!("arroyo-fs-source-{}", random::<u64>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("events.csv"), "value\nvalue\na\n").unwrap();
+
+    let mut source = Box::new(FileSystemSourceFunc {
+        table: TableType::Source {
+            path: format!("file://{}", dir.to_str().unwrap()),
+            storage_options: HashMap::new(),
+            compression_format: None,
+            regex_pattern: None,
+            monitor_interval_ms: None,
+            append_only: None,
+            file_order: Some(FileOrder::Path),
+            table_format: Some(TableFormat::Files),
+        },
+        format: Format::Csv(CsvFormat {
+            header: true,
+            ..Default::default()
+        }),
+        framing: None,
+        bad_data: None,
+        file_states: HashMap::new(),
+        delta_state: None,
+    });
+
+    let (mut ctx, _control_tx, mut data_recv) = test_context(
+        &source,
+        vec![
+            Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+            Field::new("value", DataType::Utf8, false),
+        ],
+        0,
+    )
+    .await;
+
+    tokio::spawn(async move {
+        source.run(&mut ctx).await;
+    });
+
+    assert_eq!(next_values(&mut data_recv, 2).await, vec!["value", "a"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn delta_test_table(name: &str) -> String {
+    format!(
+        "{}/src/filesystem/source/test_tables/delta/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    )
+}
+
+/// Copies the given commits from the log of the checked-in `appends` table into the table at `dir`
+fn copy_delta_commits(dir: &std::path::Path, versions: RangeInclusive<i64>) {
+    let log = dir.join("_delta_log");
+    std::fs::create_dir_all(&log).unwrap();
+    for version in versions {
+        let file = format!("{:020}.json", version);
+        std::fs::copy(
+            std::path::Path::new(&delta_test_table("appends"))
+                .join("_delta_log")
+                .join(&file),
+            log.join(&file),
+        )
+        .unwrap();
+    }
+}
+
+fn write_delta_data_file(dir: &std::path::Path, file: &str, values: Vec<&str>) {
+    let schema = Arc::new(Schema::new(vec![Field::new("value", DataType::Utf8, true)]));
+    let batch =
+        RecordBatch::try_new(schema.clone(), vec![Arc::new(StringArray::from(values))]).unwrap();
+    let mut writer =
+        ArrowWriter::try_new(std::fs::File::create(dir.join(file)).unwrap(), schema, None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+}
+
+#[tokio::test]
+async fn test_delta_snapshot_and_appends_are_read_without_compactions() {
+    let dir = std::env::temp_dir().join(format!("arroyo-delta-source-{}", random::<u64>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    write_delta_data_file(&dir, "part-00000.parquet", vec!["a", "b"]);
+    write_delta_data_file(&dir, "part-00001.parquet", vec!["c"]);
+    // the compaction of the first two files
+    write_delta_data_file(&dir, "part-00002.parquet", vec!["a", "b", "c"]);
+    write_delta_data_file(&dir, "part-00003.parquet", vec!["d"]);
+    copy_delta_commits(&dir, 0..=0);
+
+    let mut source = Box::new(FileSystemSourceFunc {
+        table: TableType::Source {
+            path: format!("file://{}", dir.to_str().unwrap()),
+            storage_options: HashMap::new(),
+            compression_format: None,
+            regex_pattern: None,
+            monitor_interval_ms: Some(50),
+            append_only: None,
+            file_order: None,
+            table_format: Some(TableFormat::DeltaLake),
+        },
+        format: Format::Parquet(ParquetFormat {}),
+        framing: None,
+        bad_data: None,
+        file_states: HashMap::new(),
+        delta_state: None,
+    });
+
+    let (mut ctx, control_tx, mut data_recv) = test_context(
+        &source,
+        vec![
+            Field::new("value", DataType::Utf8, false),
+            Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ],
+        1,
+    )
+    .await;
+
+    tokio::spawn(async move {
+        source.run(&mut ctx).await;
+    });
+
+    assert_eq!(next_values(&mut data_recv, 2).await, vec!["a", "b"]);
+
+    copy_delta_commits(&dir, 1..=3);
+
+    assert_eq!(next_values(&mut data_recv, 2).await, vec!["c", "d"]);
+
+    control_tx
+        .send(ControlMessage::Stop {
+            mode: StopMode::Immediate,
+        })
+        .await
+        .unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_delta_removals_are_rejected() {
+    let path = delta_test_table("removes");
+    let storage_provider = StorageProvider::for_url(&path).await.unwrap();
+    let table = delta::load_table(&path, &storage_provider, Some(0))
+        .await
+        .unwrap();
+
+    let err = delta::next_commit(&table, 0).await.unwrap_err();
+    assert!(
+        err.details.contains("commit 1 removes data"),
+        "{}",
+        err.details
+    );
+}
+
+#[tokio::test]
+async fn test_delta_deletion_vectors_are_rejected() {
+    let path = delta_test_table("deletion_vectors");
+    let storage_provider = StorageProvider::for_url(&path).await.unwrap();
+    let table = delta::load_table(&path, &storage_provider, None)
+        .await
+        .unwrap();
+
+    let err = delta::snapshot_files(&table).unwrap_err();
+    assert!(
+        err.details.contains("has a deletion vector"),
+        "{}",
+        err.details
+    );
+}
+
+#[tokio::test]
+async fn test_delta_partitioned_tables_are_rejected() {
+    let path = delta_test_table("partitioned");
+    let storage_provider = StorageProvider::for_url(&path).await.unwrap();
+
+    let err = delta::load_table(&path, &storage_provider, None)
+        .await
+        .unwrap_err();
+    assert!(
+        err.details.contains("partitioned by region"),
+        "{}",
+        err.details
+    );
+}
@@ -1,7 +1,7 @@
 use anyhow::{anyhow, bail};
 use arrow::datatypes::DataType;
 use arroyo_formats::ser::ArrowSerializer;
-use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::connector::{Connection, Connector, MetadataDef};
 use arroyo_operator::operator::OperatorNode;
 use arroyo_rpc::api_types::connections::{ConnectionProfile, ConnectionSchema, TestSourceMessage};
 use arroyo_rpc::OperatorConfig;
@@ -49,6 +49,19 @@ impl Connector for FluvioConnector {
         }
     }
 
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        &[
+            MetadataDef {
+                name: "offset_id",
+                data_type: DataType::Int64,
+            },
+            MetadataDef {
+                name: "partition",
+                data_type: DataType::Int32,
+            },
+        ]
+    }
+
     fn table_type(&self, _: Self::ProfileT, t: Self::TableT) -> ConnectionType {
         match t.type_ {
             TableType::Source { .. } => ConnectionType::Source,
@@ -89,7 +102,7 @@ impl Connector for FluvioConnector {
         options: &mut HashMap<String, String>,
         schema: Option<&ConnectionSchema>,
         _profile: Option<&ConnectionProfile>,
-        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+        metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
         let endpoint = options.remove("endpoint");
         let topic = pull_opt("topic", options)?;
@@ -118,7 +131,15 @@ impl Connector for FluvioConnector {
             type_: table_type,
         };
 
-        Self::from_config(self, None, name, EmptyConfig {}, table, schema, None)
+        Self::from_config(
+            self,
+            None,
+            name,
+            EmptyConfig {},
+            table,
+            schema,
+            metadata_fields,
+        )
     }
 
     fn from_config(
@@ -128,7 +149,7 @@ impl Connector for FluvioConnector {
         config: EmptyConfig,
         table: FluvioTable,
         schema: Option<&ConnectionSchema>,
-        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+        metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
         let (typ, desc) = match table.type_ {
             TableType::Source { .. } => (
@@ -150,6 +171,13 @@ impl Connector for FluvioConnector {
             .map(|t| t.to_owned())
             .ok_or_else(|| anyhow!("'format' must be set for Fluvio connection"))?;
 
+        let metadata_fields = metadata_fields.map(|fields| {
+            fields
+                .into_iter()
+                .map(|(k, (v, _))| (k, v))
+                .collect::<HashMap<String, String>>()
+        });
+
         let config = OperatorConfig {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
@@ -157,7 +185,7 @@ impl Connector for FluvioConnector {
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
-            additional_fields: None,
+            additional_fields: metadata_fields,
         };
 
         Ok(Connection {
@@ -188,16 +216,18 @@ impl Connector for FluvioConnector {
                         .ok_or_else(|| anyhow!("format required for fluvio source"))?,
                     framing: config.framing,
                     bad_data: config.bad_data,
+                    metadata_fields: config.additional_fields,
                 })))
             }
             TableType::Sink { .. } => Ok(OperatorNode::from_operator(Box::new(FluvioSinkFunc {
                 topic: table.topic,
                 endpoint: table.endpoint,
                 producer: None,
-                serializer: ArrowSerializer::new(
+                serializer: ArrowSerializer::with_framing(
                     config
                         .format
                         .ok_or_else(|| anyhow!("format required for fluvio sink"))?,
+                    config.framing,
                 ),
             }))),
         }
@@ -1,4 +1,5 @@
 use anyhow::anyhow;
+use arroyo_formats::de::FieldValueType;
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
@@ -30,6 +31,7 @@ pub struct FluvioSourceFunc {
     pub format: Format,
     pub framing: Option<Framing>,
     pub bad_data: Option<BadData>,
+    pub metadata_fields: Option<HashMap<String, String>>,
 }
 
 #[derive(Copy, Clone, Debug, Encode, Decode, PartialEq, PartialOrd)]
@@ -166,7 +168,24 @@ impl FluvioSourceFunc {
                     match message {
                         Some((_, Ok(msg))) => {
                             let timestamp = from_millis(msg.timestamp().max(0) as u64);
-                            ctx.deserialize_slice(msg.value(), timestamp, None).await?;
+                            let connector_metadata = self.metadata_fields.as_ref().map(|fields| {
+                                fields.iter()
+                                    .filter_map(|(k, v)| {
+                                        let value = match v.as_str() {
+                                            "offset_id" => FieldValueType::Int64(msg.offset()),
+                                            "partition" => FieldValueType::Int32(msg.partition() as i32),
+                                            _ => return None,
+                                        };
+                                        Some((k, value))
+                                    })
+                                    .collect()
+                            });
+                            let position = [
+                                ("topic", FieldValueType::String(&self.topic)),
+                                ("partition", FieldValueType::Int32(msg.partition() as i32)),
+                                ("offset", FieldValueType::Int64(msg.offset())),
+                            ];
+                            ctx.deserialize_slice_at(msg.value(), timestamp, connector_metadata, &position).await?;
 
                             if ctx.should_flush() {
                                 ctx.flush_buffer().await?;
@@ -0,0 +1,334 @@
+mod operator;
+#[cfg(test)]
+mod test;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use arrow::compute::can_cast_types;
+use arrow::datatypes::{DataType, Field};
+use arroyo_formats::ser::ArrowSerializer;
+use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::operator::OperatorNode;
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::formats::{Format, FramingMethod};
+use arroyo_rpc::var_str::VarStr;
+use arroyo_rpc::OperatorConfig;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use typify::import_types;
+
+use crate::http::operator::{HttpSinkFunc, UrlTemplate};
+use crate::{construct_http_client, header_map, pull_opt, pull_option_to_u64, EmptyConfig};
+
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+const ICON: &str = include_str!("./http.svg");
+
+import_types!(
+    schema = "src/http/table.json",
+    convert = { {type = "string", format = "var-str"} = VarStr }
+);
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_BATCH_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_IN_FLIGHT: u32 = 4;
+
+pub struct HttpSinkConnector {}
+
+impl HttpSinkConnector {
+    /// Checks that the endpoint is a valid URL once its templated fields are filled in, that
+    /// those fields are part of the table and can be converted to strings, and that the headers
+    /// are valid
+    fn validate_endpoint(
+        table: &HttpSinkTable,
+        schema: Option<&ConnectionSchema>,
+    ) -> anyhow::Result<UrlTemplate> {
+        let template = UrlTemplate::parse(&table.endpoint.sub_env_vars()?)?;
+
+        for field in template.fields() {
+            let Some(source_field) =
+                schema.and_then(|s| s.fields.iter().find(|f| f.field_name == field))
+            else {
+                bail!(
+                    "endpoint refers to field '{}', which is not a field in the table",
+                    field
+                );
+            };
+
+            let data_type = Field::from(source_field.clone()).data_type().clone();
+            if !can_cast_types(&data_type, &DataType::Utf8) {
+                bail!(
+                    "endpoint refers to field '{}', whose type {} can't be used in a URL",
+                    field,
+                    data_type
+                );
+            }
+        }
+
+        construct_http_client(
+            &template.example(),
+            table
+                .headers
+                .as_ref()
+                .map(|s| s.sub_env_vars())
+                .transpose()?,
+        )?;
+
+        Ok(template)
+    }
+
+    async fn test_int(config: &HttpSinkTable, tx: Sender<TestSourceMessage>) -> anyhow::Result<()> {
+        let template = UrlTemplate::parse(&config.endpoint.sub_env_vars()?)?;
+        if !template.fields().is_empty() {
+            // without a row, we don't know which URL to send a test request to
+            tx.send(TestSourceMessage::done(
+                "Endpoint is templated from row fields; skipping test request",
+            ))
+            .await
+            .unwrap();
+            return Ok(());
+        }
+
+        let headers = config
+            .headers
+            .as_ref()
+            .map(|s| s.sub_env_vars())
+            .transpose()?;
+        let client = construct_http_client(&template.example(), headers)?;
+
+        tx.send(TestSourceMessage::info("Sending HTTP request"))
+            .await
+            .unwrap();
+
+        let response = client
+            .request(method(config), template.example())
+            .header(reqwest::header::CONTENT_TYPE, content_type(config))
+            .body(operator::encode_body(
+                config.body_format.unwrap_or(BodyFormat::JsonArray),
+                vec![],
+            ))
+            .send()
+            .await
+            .map_err(|e| anyhow!("HTTP request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            bail!("server responded with error code: {}", response.status());
+        }
+
+        Ok(())
+    }
+}
+
+fn method(table: &HttpSinkTable) -> reqwest::Method {
+    match table.method {
+        None | Some(Method::Post) => reqwest::Method::POST,
+        Some(Method::Put) => reqwest::Method::PUT,
+        Some(Method::Patch) => reqwest::Method::PATCH,
+    }
+}
+
+fn content_type(table: &HttpSinkTable) -> &'static str {
+    match table.body_format {
+        None | Some(BodyFormat::JsonArray) => "application/json",
+        Some(BodyFormat::Ndjson) => "application/x-ndjson",
+    }
+}
+
+impl Connector for HttpSinkConnector {
+    type ProfileT = EmptyConfig;
+    type TableT = HttpSinkTable;
+
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "http".to_string(),
+            name: "HTTP".to_string(),
+            icon: ICON.to_string(),
+            description: "Send batches of results to an HTTP endpoint".to_string(),
+            enabled: true,
+            source: false,
+            sink: true,
+            testing: true,
+            hidden: false,
+            custom_schemas: true,
+            connection_config: None,
+            table_config: TABLE_SCHEMA.to_owned(),
+        }
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            let message = match Self::test_int(&table, tx.clone()).await {
+                Ok(_) => TestSourceMessage::done("Successfully validated HTTP endpoint"),
+                Err(err) => TestSourceMessage::fail(format!("{:?}", err)),
+            };
+
+            tx.send(message).await.unwrap();
+        });
+    }
+
+    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
+        ConnectionType::Sink
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+    ) -> anyhow::Result<Connection> {
+        Self::validate_endpoint(&table, schema)?;
+
+        let description = format!("HttpSink<{}>", table.endpoint.sub_env_vars()?);
+
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("no schema defined for HTTP sink"))?;
+
+        let format = schema
+            .format
+            .as_ref()
+            .map(|t| t.to_owned())
+            .ok_or_else(|| anyhow!("'format' must be set for HTTP sink"))?;
+
+        if !matches!(format, Format::Json(_)) {
+            bail!("HTTP sinks only support the json format, as rows are batched into JSON bodies");
+        }
+
+        if schema
+            .framing
+            .as_ref()
+            .is_some_and(|framing| !matches!(framing.method, FramingMethod::Newline(_)))
+        {
+            bail!("HTTP sinks don't support framing, as rows are batched into bodies according to 'body_format'");
+        }
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            format: Some(format),
+            bad_data: schema.bad_data.clone(),
+            framing: schema.framing.clone(),
+            additional_fields: None,
+        };
+
+        Ok(Connection {
+            id,
+            connector: self.name(),
+            name: name.to_string(),
+            connection_type: ConnectionType::Sink,
+            schema,
+            config: serde_json::to_string(&config).unwrap(),
+            description,
+        })
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut HashMap<String, String>,
+        schema: Option<&ConnectionSchema>,
+        _profile: Option<&ConnectionProfile>,
+        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+    ) -> anyhow::Result<Connection> {
+        let endpoint = pull_opt("endpoint", options)?;
+
+        let method = match options.remove("method").as_deref() {
+            Some("POST") | Some("post") | None => None,
+            Some("PUT") | Some("put") => Some(Method::Put),
+            Some("PATCH") | Some("patch") => Some(Method::Patch),
+            Some(m) => bail!("invalid method '{}'; must be one of POST, PUT or PATCH", m),
+        };
+
+        let body_format = match options.remove("body_format").as_deref() {
+            Some("json_array") | None => None,
+            Some("ndjson") => Some(BodyFormat::Ndjson),
+            Some(f) => bail!(
+                "invalid body_format '{}'; must be one of 'json_array' or 'ndjson'",
+                f
+            ),
+        };
+
+        let table = HttpSinkTable {
+            endpoint: VarStr::new(endpoint),
+            method,
+            headers: options.remove("headers").map(VarStr::new),
+            body_format,
+            batch_size: pull_option_to_u64("batch_size", options)?
+                .map(|t| t.try_into())
+                .transpose()
+                .map_err(|_| anyhow!("batch_size must be greater than 0"))?,
+            batch_timeout_ms: pull_option_to_u64("batch_timeout_ms", options)?
+                .map(|t| t.try_into())
+                .transpose()
+                .map_err(|_| anyhow!("batch_timeout_ms must be greater than 0"))?,
+            max_in_flight: pull_option_to_u64("max_in_flight", options)?
+                .map(|t| t.try_into())
+                .transpose()
+                .map_err(|_| anyhow!("max_in_flight must be greater than 0"))?,
+            max_retries: pull_option_to_u64("max_retries", options)?,
+        };
+
+        self.from_config(None, name, EmptyConfig {}, table, schema, None)
+    }
+
+    fn make_operator(
+        &self,
+        _: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+    ) -> anyhow::Result<OperatorNode> {
+        let url = UrlTemplate::parse(&table.endpoint.sub_env_vars()?)?;
+
+        // a content-type set in the headers takes precedence over the one for the body format
+        let has_content_type = header_map(table.headers.clone())
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("content-type"));
+
+        Ok(OperatorNode::from_operator(Box::new(HttpSinkFunc::new(
+            construct_http_client(
+                &url.example(),
+                table
+                    .headers
+                    .as_ref()
+                    .map(|s| s.sub_env_vars())
+                    .transpose()?,
+            )?,
+            method(&table),
+            url,
+            (!has_content_type).then(|| content_type(&table)),
+            table.body_format.unwrap_or(BodyFormat::JsonArray),
+            table
+                .batch_size
+                .map(|n| n.get() as usize)
+                .unwrap_or(DEFAULT_BATCH_SIZE),
+            table
+                .batch_timeout_ms
+                .map(|t| Duration::from_millis(t.get()))
+                .unwrap_or(DEFAULT_BATCH_TIMEOUT),
+            table
+                .max_in_flight
+                .map(|n| n.get().try_into().unwrap_or(u32::MAX))
+                .unwrap_or(DEFAULT_MAX_IN_FLIGHT),
+            table.max_retries,
+            ArrowSerializer::new(config.format.expect("No format configured for HTTP sink")),
+        ))))
+    }
+}
@@ -0,0 +1,580 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::bail;
+use arrow::array::{Array, AsArray, RecordBatch, StringArray};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+use arroyo_formats::ser::ArrowSerializer;
+use arroyo_operator::context::{ArrowContext, ErrorReporter};
+use arroyo_operator::operator::ArrowOperator;
+use arroyo_rpc::grpc::rpc::TableConfig;
+use arroyo_state::global_table_config;
+use arroyo_state::tables::global_keyed_map::GlobalKeyedView;
+use arroyo_types::{CheckpointBarrier, SignalMessage};
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::header::{CONTENT_TYPE, RETRY_AFTER};
+use reqwest::StatusCode;
+use tokio::sync::{Mutex, Semaphore};
+use tracing::warn;
+
+use crate::http::BodyFormat;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+enum UrlPart {
+    Literal(String),
+    Field(String),
+}
+
+/// An endpoint URL whose path may refer to fields of the row, as `{field}`
+#[derive(Debug, Clone)]
+pub struct UrlTemplate {
+    parts: Vec<UrlPart>,
+}
+
+impl UrlTemplate {
+    pub fn parse(template: &str) -> anyhow::Result<Self> {
+        let mut parts = vec![];
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(UrlPart::Literal(rest[..start].to_string()));
+            }
+
+            let Some(end) = rest[start..].find('}') else {
+                bail!("unclosed '{{' in endpoint '{}'", template);
+            };
+
+            let field = rest[start + 1..start + end].trim();
+            if field.is_empty() {
+                bail!("empty field reference '{{}}' in endpoint '{}'", template);
+            }
+
+            parts.push(UrlPart::Field(field.to_string()));
+            rest = &rest[start + end + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(UrlPart::Literal(rest.to_string()));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// The fields referred to by the template, in the order they appear
+    pub fn fields(&self) -> Vec<&str> {
+        self.parts
+            .iter()
+            .filter_map(|p| match p {
+                UrlPart::Field(f) => Some(f.as_str()),
+                UrlPart::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    /// Fills in the template with the given values for its fields (in the order returned by
+    /// `fields`), percent-encoding them; null values are rendered as empty strings
+    pub fn render<'a>(&self, mut values: impl Iterator<Item = Option<&'a str>>) -> String {
+        let mut url = String::new();
+        for part in &self.parts {
+            match part {
+                UrlPart::Literal(s) => url.push_str(s),
+                UrlPart::Field(_) => {
+                    if let Some(value) = values.next().flatten() {
+                        percent_encode(value, &mut url);
+                    }
+                }
+            }
+        }
+        url
+    }
+
+    /// A URL with placeholder values for the fields, used to validate the template
+    pub fn example(&self) -> String {
+        self.render(self.fields().into_iter().map(|_| Some("value")))
+    }
+}
+
+fn percent_encode(value: &str, out: &mut String) {
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+}
+
+/// Parses a Retry-After header, which may be either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+fn backoff(retries: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(1 << retries.min(16))
+        .min(MAX_BACKOFF)
+}
+
+pub(crate) fn encode_body(format: BodyFormat, rows: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut body = vec![];
+    match format {
+        BodyFormat::JsonArray => {
+            body.push(b'[');
+            for (i, row) in rows.into_iter().enumerate() {
+                if i > 0 {
+                    body.push(b',');
+                }
+                body.extend(row);
+            }
+            body.push(b']');
+        }
+        BodyFormat::Ndjson => {
+            for row in rows {
+                body.extend(row);
+                body.push(b'\n');
+            }
+        }
+    }
+    body
+}
+
+struct PendingBatch {
+    rows: Vec<Vec<u8>>,
+    started: Instant,
+}
+
+/// A batch of rows to be sent to a single URL, along with what's needed to retry it
+struct Request {
+    client: reqwest::Client,
+    method: reqwest::Method,
+    url: String,
+    content_type: Option<&'static str>,
+    body: Bytes,
+    max_retries: Option<u64>,
+}
+
+impl Request {
+    /// Sends the request, retrying connection errors, 429s and 5xxs with exponential backoff
+    /// (or after the server's Retry-After) until it succeeds or runs out of retries
+    async fn execute(
+        &self,
+        reporter: &mut ErrorReporter,
+        last_reported_error_at: &Mutex<SystemTime>,
+    ) -> Result<(), String> {
+        let mut retries = 0;
+        loop {
+            let mut request = self
+                .client
+                .request(self.method.clone(), &self.url)
+                .body(self.body.clone());
+            if let Some(content_type) = self.content_type {
+                request = request.header(CONTENT_TYPE, content_type);
+            }
+
+            let (error, retry_after) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response)
+                    if response.status() == StatusCode::TOO_MANY_REQUESTS
+                        || response.status().is_server_error() =>
+                {
+                    let retry_after = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(parse_retry_after);
+                    (
+                        format!("server responded with error code: {}", response.status()),
+                        retry_after,
+                    )
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(format!(
+                        "server responded with error code: {}: {}",
+                        status, body
+                    ));
+                }
+                Err(e) => (e.to_string(), None),
+            };
+
+            if self.max_retries.is_some_and(|max| retries as u64 >= max) {
+                return Err(format!("{} (gave up after {} retries)", error, retries));
+            }
+
+            warn!("HTTP sink request to {} failed: {}", self.url, error);
+            if let Ok(mut last_reported) = last_reported_error_at.try_lock() {
+                if last_reported.elapsed().unwrap_or_default() > Duration::from_secs(1) {
+                    reporter
+                        .report_error(
+                            format!("HTTP sink request failed (retry {})", retries),
+                            error,
+                        )
+                        .await;
+                    *last_reported = SystemTime::now();
+                }
+            }
+
+            tokio::time::sleep(retry_after.unwrap_or_else(|| backoff(retries))).await;
+            retries += 1;
+        }
+    }
+}
+
+pub struct HttpSinkFunc {
+    client: reqwest::Client,
+    method: reqwest::Method,
+    url: UrlTemplate,
+    content_type: Option<&'static str>,
+    body_format: BodyFormat,
+    batch_size: usize,
+    batch_timeout: Duration,
+    max_in_flight: u32,
+    max_retries: Option<u64>,
+    serializer: ArrowSerializer,
+    semaphore: Arc<Semaphore>,
+    last_reported_error_at: Arc<Mutex<SystemTime>>,
+    /// the columns of the fields in the URL template, in template order
+    url_columns: Vec<usize>,
+    /// rows that have not been sent yet, batched by the URL they're sent to
+    pending: HashMap<String, PendingBatch>,
+    /// the URLs and bodies of the requests that are being sent (or retried), by id
+    in_flight: Arc<std::sync::Mutex<HashMap<u64, (String, Bytes)>>>,
+    next_request_id: u64,
+}
+
+impl HttpSinkFunc {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        client: reqwest::Client,
+        method: reqwest::Method,
+        url: UrlTemplate,
+        content_type: Option<&'static str>,
+        body_format: BodyFormat,
+        batch_size: usize,
+        batch_timeout: Duration,
+        max_in_flight: u32,
+        max_retries: Option<u64>,
+        serializer: ArrowSerializer,
+    ) -> Self {
+        Self {
+            client,
+            method,
+            url,
+            content_type,
+            body_format,
+            batch_size,
+            batch_timeout,
+            max_in_flight,
+            max_retries,
+            serializer,
+            semaphore: Arc::new(Semaphore::new(max_in_flight as usize)),
+            last_reported_error_at: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
+            url_columns: vec![],
+            pending: HashMap::new(),
+            in_flight: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            next_request_id: 0,
+        }
+    }
+
+    /// Sends a batch in the background, once there are fewer than `max_in_flight` requests
+    /// in progress
+    async fn send(&mut self, url: String, rows: Vec<Vec<u8>>, ctx: &mut ArrowContext) {
+        let body = encode_body(self.body_format, rows).into();
+        self.send_body(url, body, ctx).await;
+    }
+
+    async fn send_body(&mut self, url: String, body: Bytes, ctx: &mut ArrowContext) {
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("HTTP sink semaphore closed");
+
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        self.in_flight
+            .lock()
+            .unwrap()
+            .insert(id, (url.clone(), body.clone()));
+
+        let request = Request {
+            client: self.client.clone(),
+            method: self.method.clone(),
+            url,
+            content_type: self.content_type,
+            body,
+            max_retries: self.max_retries,
+        };
+
+        let mut reporter = ctx.error_reporter.clone();
+        let last_reported_error_at = self.last_reported_error_at.clone();
+        let in_flight = self.in_flight.clone();
+
+        tokio::task::spawn(async move {
+            // move the permit into the task
+            let _permit = permit;
+            if let Err(e) = request
+                .execute(&mut reporter, &last_reported_error_at)
+                .await
+            {
+                warn!("HTTP sink dropped batch for {}: {}", request.url, e);
+                reporter
+                    .report_error("HTTP sink failed to send batch", e)
+                    .await;
+            }
+            in_flight.lock().unwrap().remove(&id);
+        });
+    }
+
+    /// Sends all pending batches, or only those that have been buffered for longer than the
+    /// batch timeout
+    async fn flush(&mut self, expired_only: bool, ctx: &mut ArrowContext) {
+        let urls: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, b)| !expired_only || b.started.elapsed() >= self.batch_timeout)
+            .map(|(url, _)| url.clone())
+            .collect();
+
+        for url in urls {
+            let batch = self.pending.remove(&url).unwrap();
+            self.send(url, batch.rows, ctx).await;
+        }
+    }
+
+    /// Flushes everything and waits for all in-flight requests to complete
+    async fn drain(&mut self, ctx: &mut ArrowContext) {
+        self.flush(false, ctx).await;
+        let _permits = self
+            .semaphore
+            .acquire_many(self.max_in_flight)
+            .await
+            .expect("HTTP sink semaphore closed");
+    }
+}
+
+#[async_trait]
+impl ArrowOperator for HttpSinkFunc {
+    fn name(&self) -> String {
+        "HttpSink".to_string()
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        global_table_config("r", "unsent requests")
+    }
+
+    fn tick_interval(&self) -> Option<Duration> {
+        Some(self.batch_timeout.min(MAX_TICK_INTERVAL))
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let schema = &ctx.in_schemas[0].schema;
+        self.url_columns = self
+            .url
+            .fields()
+            .into_iter()
+            .map(|f| {
+                schema
+                    .index_of(f)
+                    .expect("endpoint fields are validated when the table is created")
+            })
+            .collect();
+
+        // requests that hadn't been sent as of the checkpoint are sent again; they're divided
+        // among the subtasks by the subtask that stored them
+        let parallelism = ctx.task_info.parallelism;
+        let task_index = ctx.task_info.task_index;
+        let state: &mut GlobalKeyedView<usize, Vec<(String, Vec<u8>)>> = ctx
+            .table_manager
+            .get_global_keyed_state("r")
+            .await
+            .expect("should have table");
+        let unsent: Vec<_> = state
+            .get_all()
+            .iter()
+            .filter(|(task, _)| **task % parallelism == task_index)
+            .flat_map(|(_, requests)| requests.clone())
+            .collect();
+
+        for (url, body) in unsent {
+            self.send_body(url, body.into(), ctx).await;
+        }
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        let url_values = match self
+            .url_columns
+            .iter()
+            .map(|i| cast(batch.column(*i), &DataType::Utf8))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(values) => values,
+            Err(e) => {
+                ctx.report_error(
+                    "HTTP sink could not convert endpoint fields to strings; skipping rows",
+                    e.to_string(),
+                )
+                .await;
+                return;
+            }
+        };
+        let url_values: Vec<&StringArray> = url_values.iter().map(|v| v.as_string()).collect();
+
+        let mut skipped = 0;
+        let rows: Vec<_> = self.serializer.serialize(&batch).collect();
+        for (i, row) in rows.into_iter().enumerate() {
+            // values that can't be converted to strings are converted to nulls
+            if self
+                .url_columns
+                .iter()
+                .zip(&url_values)
+                .any(|(c, v)| v.is_null(i) && batch.column(*c).is_valid(i))
+            {
+                skipped += 1;
+                continue;
+            }
+
+            let url = self
+                .url
+                .render(url_values.iter().map(|v| v.is_valid(i).then(|| v.value(i))));
+
+            let pending = self
+                .pending
+                .entry(url.clone())
+                .or_insert_with(|| PendingBatch {
+                    rows: vec![],
+                    started: Instant::now(),
+                });
+            pending.rows.push(row);
+
+            if pending.rows.len() >= self.batch_size {
+                let pending = self.pending.remove(&url).unwrap();
+                self.send(url, pending.rows, ctx).await;
+            }
+        }
+
+        if skipped > 0 {
+            ctx.report_error(
+                "HTTP sink could not convert endpoint fields to strings; skipping rows",
+                format!("skipped {} rows", skipped),
+            )
+            .await;
+        }
+    }
+
+    async fn handle_tick(&mut self, _: u64, ctx: &mut ArrowContext) {
+        self.flush(true, ctx).await;
+    }
+
+    async fn handle_checkpoint(&mut self, _: CheckpointBarrier, ctx: &mut ArrowContext) {
+        // rather than waiting for requests that may be retrying indefinitely, the requests that
+        // are in flight and the pending batches are stored, and sent again on restore
+        let mut unsent: Vec<(String, Vec<u8>)> = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(url, body)| (url.clone(), body.to_vec()))
+            .collect();
+        unsent.extend(
+            self.pending
+                .iter()
+                .map(|(url, b)| (url.clone(), encode_body(self.body_format, b.rows.clone()))),
+        );
+
+        ctx.table_manager
+            .get_global_keyed_state("r")
+            .await
+            .expect("should have table")
+            .insert(ctx.task_info.task_index, unsent)
+            .await;
+    }
+
+    async fn on_close(&mut self, _: &Option<SignalMessage>, ctx: &mut ArrowContext) {
+        self.drain(ctx).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_template() {
+        let template =
+            UrlTemplate::parse("https://example.com/users/{user_id}/events/{ kind }").unwrap();
+        assert_eq!(template.fields(), vec!["user_id", "kind"]);
+        assert_eq!(
+            template.render([Some("a b/c"), Some("click")].into_iter()),
+            "https://example.com/users/a%20b%2Fc/events/click"
+        );
+        assert_eq!(
+            template.render([None, Some("click")].into_iter()),
+            "https://example.com/users//events/click"
+        );
+
+        let plain = UrlTemplate::parse("http://localhost:8080/ingest").unwrap();
+        assert!(plain.fields().is_empty());
+        assert_eq!(plain.example(), "http://localhost:8080/ingest");
+
+        assert!(UrlTemplate::parse("http://localhost/{user_id").is_err());
+        assert!(UrlTemplate::parse("http://localhost/{}").is_err());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("0"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        let parsed = parse_retry_after(&later).unwrap();
+        assert!(parsed > Duration::from_secs(55) && parsed <= Duration::from_secs(60));
+
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_encode_body() {
+        let rows = vec![b"{\"a\":1}".to_vec(), b"{\"a\":2}".to_vec()];
+        assert_eq!(
+            encode_body(BodyFormat::JsonArray, rows.clone()),
+            b"[{\"a\":1},{\"a\":2}]"
+        );
+        assert_eq!(
+            encode_body(BodyFormat::Ndjson, rows),
+            b"{\"a\":1}\n{\"a\":2}\n"
+        );
+        assert_eq!(encode_body(BodyFormat::JsonArray, vec![]), b"[]");
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), Duration::from_millis(100));
+        assert_eq!(backoff(3), Duration::from_millis(800));
+        assert_eq!(backoff(30), MAX_BACKOFF);
+    }
+}
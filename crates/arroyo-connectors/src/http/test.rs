@@ -0,0 +1,288 @@
+use std::collections::VecDeque;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use arrow::array::{Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arroyo_formats::ser::ArrowSerializer;
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::ArrowOperator;
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::formats::{Format, JsonFormat};
+use arroyo_rpc::ControlResp;
+use arroyo_types::{get_test_task_info, CheckpointBarrier};
+use axum::extract::State;
+use axum::http::{StatusCode, Uri};
+use axum::routing::post;
+use axum::Router;
+use bytes::Bytes;
+use tokio::sync::mpsc::{channel, Receiver};
+use tokio::sync::Mutex;
+
+use super::operator::{HttpSinkFunc, UrlTemplate};
+use super::BodyFormat;
+
+/// A local HTTP server that responds with the queued statuses in order (and 200 once they run
+/// out), recording the path and body of each successful request
+#[derive(Default)]
+struct Stub {
+    responses: Mutex<VecDeque<StatusCode>>,
+    attempts: Mutex<usize>,
+    received: Mutex<Vec<(String, String)>>,
+}
+
+async fn handle(
+    State(stub): State<Arc<Stub>>,
+    uri: Uri,
+    body: Bytes,
+) -> (StatusCode, [(&'static str, &'static str); 1]) {
+    *stub.attempts.lock().await += 1;
+    let status = stub
+        .responses
+        .lock()
+        .await
+        .pop_front()
+        .unwrap_or(StatusCode::OK);
+
+    if status.is_success() {
+        stub.received.lock().await.push((
+            uri.path().to_string(),
+            String::from_utf8(body.to_vec()).unwrap(),
+        ));
+    }
+
+    (status, [("retry-after", "0")])
+}
+
+fn start_stub(responses: Vec<StatusCode>) -> (Arc<Stub>, u16) {
+    let stub = Arc::new(Stub {
+        responses: Mutex::new(responses.into()),
+        ..Default::default()
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let app = Router::new()
+        .route("/*path", post(handle))
+        .with_state(stub.clone());
+
+    tokio::spawn(async move {
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service())
+            .await
+            .unwrap();
+    });
+
+    (stub, port)
+}
+
+fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("user_id", DataType::Utf8, false),
+        Field::new("value", DataType::Int64, false),
+    ]))
+}
+
+fn batch(rows: &[(&str, i64)]) -> RecordBatch {
+    RecordBatch::try_new(
+        schema(),
+        vec![
+            Arc::new(StringArray::from_iter_values(rows.iter().map(|(u, _)| *u))),
+            Arc::new(Int64Array::from_iter_values(rows.iter().map(|(_, v)| *v))),
+        ],
+    )
+    .unwrap()
+}
+
+async fn sink(
+    endpoint: &str,
+    body_format: BodyFormat,
+    batch_size: usize,
+) -> (HttpSinkFunc, ArrowContext, Receiver<ControlResp>) {
+    let url = UrlTemplate::parse(endpoint).unwrap();
+    let mut sink = HttpSinkFunc::new(
+        crate::construct_http_client(&url.example(), None).unwrap(),
+        reqwest::Method::POST,
+        url,
+        Some("application/json"),
+        body_format,
+        batch_size,
+        Duration::from_millis(50),
+        2,
+        None,
+        ArrowSerializer::new(Format::Json(JsonFormat::default())),
+    );
+
+    let (_, control_rx) = channel(128);
+    // keep the receiver for control responses, so that we can check for reported errors
+    let (command_tx, command_rx) = channel(128);
+
+    let mut ctx = ArrowContext::new(
+        get_test_task_info(),
+        None,
+        control_rx,
+        command_tx,
+        1,
+        vec![ArroyoSchema::new_unkeyed(schema(), 0)],
+        None,
+        None,
+        vec![vec![]],
+        vec![],
+        sink.tables(),
+    )
+    .await;
+
+    sink.on_start(&mut ctx).await;
+
+    (sink, ctx, command_rx)
+}
+
+#[tokio::test]
+async fn test_batches_by_templated_url_and_retries() {
+    let (stub, port) = start_stub(vec![StatusCode::SERVICE_UNAVAILABLE]);
+    let (mut sink, mut ctx, _control_rx) = sink(
+        &format!("http://127.0.0.1:{}/users/{{user_id}}", port),
+        BodyFormat::JsonArray,
+        2,
+    )
+    .await;
+
+    sink.process_batch(batch(&[("a", 1), ("b", 2), ("a", 3)]), &mut ctx)
+        .await;
+
+    // the batch for user a is full and is sent immediately, while b's is sent once it times out
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    sink.handle_tick(0, &mut ctx).await;
+    sink.on_close(&None, &mut ctx).await;
+
+    assert_eq!(*stub.attempts.lock().await, 3);
+
+    let mut received = stub.received.lock().await.clone();
+    received.sort();
+    assert_eq!(
+        received,
+        vec![
+            (
+                "/users/a".to_string(),
+                r#"[{"user_id":"a","value":1},{"user_id":"a","value":3}]"#.to_string()
+            ),
+            (
+                "/users/b".to_string(),
+                r#"[{"user_id":"b","value":2}]"#.to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_ndjson_body() {
+    let (stub, port) = start_stub(vec![]);
+    let (mut sink, mut ctx, _control_rx) = sink(
+        &format!("http://127.0.0.1:{}/ingest", port),
+        BodyFormat::Ndjson,
+        100,
+    )
+    .await;
+
+    sink.process_batch(batch(&[("a", 1), ("b", 2)]), &mut ctx)
+        .await;
+    sink.on_close(&None, &mut ctx).await;
+
+    assert_eq!(
+        *stub.received.lock().await,
+        vec![(
+            "/ingest".to_string(),
+            "{\"user_id\":\"a\",\"value\":1}\n{\"user_id\":\"b\",\"value\":2}\n".to_string()
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_client_errors_are_reported_and_not_retried() {
+    let (stub, port) = start_stub(vec![StatusCode::BAD_REQUEST]);
+    let (mut sink, mut ctx, mut control_rx) = sink(
+        &format!("http://127.0.0.1:{}/ingest", port),
+        BodyFormat::JsonArray,
+        1,
+    )
+    .await;
+
+    sink.process_batch(batch(&[("a", 1)]), &mut ctx).await;
+    sink.on_close(&None, &mut ctx).await;
+
+    assert_eq!(*stub.attempts.lock().await, 1);
+    assert!(stub.received.lock().await.is_empty());
+
+    let Some(ControlResp::Error {
+        message, details, ..
+    }) = control_rx.recv().await
+    else {
+        panic!("expected an error to be reported");
+    };
+    assert_eq!(message, "HTTP sink failed to send batch");
+    assert!(details.contains("400"), "{}", details);
+}
+
+#[tokio::test]
+async fn test_checkpoint_stores_unsent_requests() {
+    // nothing is listening on the port, so requests are retried until the sink is restored
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let (mut sink, mut ctx, _control_rx) = sink(
+        &format!("http://127.0.0.1:{}/users/{{user_id}}", port),
+        BodyFormat::JsonArray,
+        2,
+    )
+    .await;
+
+    sink.process_batch(batch(&[("a", 1), ("a", 2), ("b", 3)]), &mut ctx)
+        .await;
+
+    tokio::time::timeout(
+        Duration::from_secs(1),
+        sink.handle_checkpoint(
+            CheckpointBarrier {
+                epoch: 1,
+                min_epoch: 1,
+                timestamp: SystemTime::now(),
+                then_stop: false,
+            },
+            &mut ctx,
+        ),
+    )
+    .await
+    .expect("checkpoint shouldn't wait for requests to complete");
+
+    let mut unsent: Vec<(String, String)> = ctx
+        .table_manager
+        .get_global_keyed_state::<usize, Vec<(String, Vec<u8>)>>("r")
+        .await
+        .unwrap()
+        .get(&0)
+        .unwrap()
+        .iter()
+        .map(|(url, body)| (url.clone(), String::from_utf8(body.clone()).unwrap()))
+        .collect();
+    unsent.sort();
+
+    assert_eq!(
+        unsent,
+        vec![
+            (
+                format!("http://127.0.0.1:{}/users/a", port),
+                r#"[{"user_id":"a","value":1},{"user_id":"a","value":2}]"#.to_string()
+            ),
+            (
+                format!("http://127.0.0.1:{}/users/b", port),
+                r#"[{"user_id":"b","value":3}]"#.to_string()
+            ),
+        ]
+    );
+}
@@ -0,0 +1,364 @@
+mod sink;
+
+use anyhow::{anyhow, bail};
+use arrow::datatypes::DataType;
+use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::operator::OperatorNode;
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::formats::{Format, JsonFormat};
+use arroyo_rpc::var_str::VarStr;
+use arroyo_rpc::OperatorConfig;
+use mysql_async::prelude::Queryable;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot::Receiver;
+use tracing::error;
+use typify::import_types;
+
+use crate::jdbc::sink::JdbcSinkFunc;
+use crate::{pull_opt, pull_option_to_i64};
+
+const CONFIG_SCHEMA: &str = include_str!("./profile.json");
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+const ICON: &str = include_str!("./jdbc.svg");
+
+const DEFAULT_BATCH_SIZE: usize = 1000;
+
+import_types!(
+    schema = "src/jdbc/profile.json",
+    convert = {
+        {type = "string", format = "var-str"} = VarStr
+    }
+);
+
+import_types!(schema = "src/jdbc/table.json");
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Postgres,
+    MySql,
+}
+
+impl Dialect {
+    fn from_url(url: &str) -> anyhow::Result<Self> {
+        let scheme = url
+            .split_once("://")
+            .map(|(scheme, _)| scheme)
+            .ok_or_else(|| {
+                anyhow!("invalid database URL; expected a URL like 'postgres://host:port/database'")
+            })?;
+
+        match scheme {
+            "postgres" | "postgresql" => Ok(Dialect::Postgres),
+            "mysql" => Ok(Dialect::MySql),
+            other => bail!(
+                "unsupported database '{}'; the URL scheme must be one of 'postgres' or 'mysql'",
+                other
+            ),
+        }
+    }
+}
+
+/// A connection to one of the supported databases. Statements are sent as complete SQL text
+/// (rather than as prepared statements with parameters), which lets the database coerce each
+/// literal to the type of its column.
+pub enum DatabaseClient {
+    Postgres(tokio_postgres::Client),
+    MySql(mysql_async::Conn),
+}
+
+impl DatabaseClient {
+    pub async fn connect(config: &JdbcConfig) -> anyhow::Result<Self> {
+        let url = config.url.sub_env_vars()?;
+        let username = config
+            .username
+            .as_ref()
+            .map(|u| u.sub_env_vars())
+            .transpose()?;
+        let password = config
+            .password
+            .as_ref()
+            .map(|p| p.sub_env_vars())
+            .transpose()?;
+
+        match Dialect::from_url(&url)? {
+            Dialect::Postgres => {
+                let mut pg_config: tokio_postgres::Config = url
+                    .parse()
+                    .map_err(|e| anyhow!("invalid Postgres URL: {}", e))?;
+                if let Some(username) = username {
+                    pg_config.user(username);
+                }
+                if let Some(password) = password {
+                    pg_config.password(password);
+                }
+
+                let (client, connection) = pg_config.connect(tokio_postgres::NoTls).await?;
+                tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        error!("Postgres connection error: {}", e);
+                    }
+                });
+
+                Ok(DatabaseClient::Postgres(client))
+            }
+            Dialect::MySql => {
+                let opts = mysql_async::OptsBuilder::from_opts(
+                    mysql_async::Opts::from_url(&url)
+                        .map_err(|e| anyhow!("invalid MySQL URL: {}", e))?,
+                );
+                let opts = match username {
+                    Some(username) => opts.user(Some(username)),
+                    None => opts,
+                };
+                let opts = match password {
+                    Some(password) => opts.pass(Some(password)),
+                    None => opts,
+                };
+
+                Ok(DatabaseClient::MySql(mysql_async::Conn::new(opts).await?))
+            }
+        }
+    }
+
+    pub async fn execute(&mut self, sql: &str) -> anyhow::Result<()> {
+        match self {
+            DatabaseClient::Postgres(client) => client.batch_execute(sql).await?,
+            DatabaseClient::MySql(conn) => conn.query_drop(sql).await?,
+        }
+        Ok(())
+    }
+
+    /// Returns the ids of the prepared (two-phase) transactions whose id starts with `prefix`
+    pub async fn prepared_transactions(&mut self, prefix: &str) -> anyhow::Result<Vec<String>> {
+        match self {
+            DatabaseClient::Postgres(client) => Ok(client
+                .query(
+                    "SELECT gid FROM pg_prepared_xacts \
+                    WHERE database = current_database() AND starts_with(gid, $1)",
+                    &[&prefix],
+                )
+                .await?
+                .iter()
+                .map(|row| row.get(0))
+                .collect()),
+            DatabaseClient::MySql(conn) => {
+                // rows are (formatID, gtrid_length, bqual_length, data), where data is the
+                // global transaction id followed by the branch qualifier
+                let rows: Vec<(i64, i64, i64, Vec<u8>)> = conn.query("XA RECOVER").await?;
+                Ok(rows
+                    .into_iter()
+                    .filter_map(|(_, gtrid_length, _, data)| {
+                        let gtrid = data.get(..gtrid_length as usize)?;
+                        String::from_utf8(gtrid.to_vec()).ok()
+                    })
+                    .filter(|gtrid| gtrid.starts_with(prefix))
+                    .collect())
+            }
+        }
+    }
+}
+
+pub struct JdbcConnector {}
+
+async fn test_inner(config: JdbcConfig, tx: Sender<TestSourceMessage>) -> anyhow::Result<String> {
+    let mut client = DatabaseClient::connect(&config).await?;
+
+    tx.send(TestSourceMessage::info("Connected to database"))
+        .await
+        .unwrap();
+
+    client
+        .execute("SELECT 1")
+        .await
+        .map_err(|e| anyhow!("failed to run test query: {}", e))?;
+
+    Ok("Successfully validated database connection".to_string())
+}
+
+impl Connector for JdbcConnector {
+    type ProfileT = JdbcConfig;
+    type TableT = JdbcTable;
+
+    fn name(&self) -> &'static str {
+        "jdbc"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "jdbc".to_string(),
+            name: "JDBC".to_string(),
+            icon: ICON.to_string(),
+            description: "Write results to a relational database like Postgres or MySQL"
+                .to_string(),
+            enabled: true,
+            source: false,
+            sink: true,
+            testing: true,
+            hidden: false,
+            custom_schemas: true,
+            connection_config: Some(CONFIG_SCHEMA.to_string()),
+            table_config: TABLE_SCHEMA.to_string(),
+        }
+    }
+
+    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
+        ConnectionType::Sink
+    }
+
+    fn test_profile(&self, profile: Self::ProfileT) -> Option<Receiver<TestSourceMessage>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        tokio::spawn(async move {
+            let (itx, _rx) = tokio::sync::mpsc::channel(8);
+            let message = match test_inner(profile, itx).await {
+                Ok(_) => TestSourceMessage::done("Successfully connected to database"),
+                Err(e) => {
+                    TestSourceMessage::fail(format!("Failed to connect to database: {:?}", e))
+                }
+            };
+
+            tx.send(message).unwrap();
+        });
+
+        Some(rx)
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        config: Self::ProfileT,
+        _: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            let resp = match test_inner(config, tx.clone()).await {
+                Ok(c) => TestSourceMessage::done(c),
+                Err(e) => TestSourceMessage::fail(e.to_string()),
+            };
+
+            tx.send(resp).await.unwrap();
+        });
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut HashMap<String, String>,
+        schema: Option<&ConnectionSchema>,
+        profile: Option<&ConnectionProfile>,
+        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+    ) -> anyhow::Result<Connection> {
+        let connection = match profile {
+            Some(connection_profile) => {
+                serde_json::from_value(connection_profile.config.clone())
+                    .map_err(|e| anyhow!("Failed to parse connection config: {:?}", e))?
+            }
+            None => JdbcConfig {
+                url: VarStr::new(pull_opt("url", options)?),
+                username: options.remove("username").map(VarStr::new),
+                password: options.remove("password").map(VarStr::new),
+            },
+        };
+
+        if let Some(typ) = options.remove("type") {
+            if typ != "sink" {
+                bail!("'{}' is not a valid type; must be `sink`", typ);
+            }
+        }
+
+        let table = JdbcTable {
+            table: pull_opt("table", options)?,
+            primary_keys: schema
+                .map(|s| {
+                    let mut keys: Vec<_> = s.primary_keys.iter().cloned().collect();
+                    keys.sort();
+                    keys
+                })
+                .unwrap_or_default(),
+            commit_mode: match options.remove("commit_mode").as_deref() {
+                Some("at_least_once") | None => JdbcTableCommitMode::AtLeastOnce,
+                Some("exactly_once") => JdbcTableCommitMode::ExactlyOnce,
+                Some(other) => bail!("invalid value for commit_mode '{}'", other),
+            },
+            batch_size: pull_option_to_i64("batch_size", options)?,
+        };
+
+        self.from_config(None, name, connection, table, schema, None)
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+    ) -> anyhow::Result<Connection> {
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("No schema defined for JDBC connection"))?;
+
+        match &schema.format {
+            None | Some(Format::Json(JsonFormat { .. })) => {}
+            Some(_) => bail!("JDBC sinks only support the 'json' and 'debezium_json' formats"),
+        }
+
+        let is_updating = schema.format.as_ref().is_some_and(|f| f.is_updating());
+        if is_updating && table.primary_keys.is_empty() {
+            bail!("JDBC sinks for updating tables must have at least one PRIMARY KEY field");
+        }
+
+        for key in &table.primary_keys {
+            if !schema.fields.iter().any(|f| &f.field_name == key) {
+                bail!("primary key '{}' is not a field in the table", key);
+            }
+        }
+
+        if table.batch_size.is_some_and(|b| b <= 0) {
+            bail!("batch_size must be greater than 0");
+        }
+
+        // the URL may reference environment variables that are only set on the workers, so the
+        // dialect is determined when the operator is constructed
+        let description = format!("JdbcSink<{}>", table.table);
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            format: schema.format.clone(),
+            bad_data: schema.bad_data.clone(),
+            framing: schema.framing.clone(),
+            additional_fields: None,
+        };
+
+        Ok(Connection {
+            id,
+            connector: self.name(),
+            name: name.to_string(),
+            connection_type: ConnectionType::Sink,
+            schema,
+            config: serde_json::to_string(&config).unwrap(),
+            description,
+        })
+    }
+
+    fn make_operator(
+        &self,
+        profile: Self::ProfileT,
+        table: Self::TableT,
+        _: OperatorConfig,
+    ) -> anyhow::Result<OperatorNode> {
+        let dialect = Dialect::from_url(&profile.url.sub_env_vars()?)?;
+
+        Ok(OperatorNode::from_operator(Box::new(JdbcSinkFunc::new(
+            profile, table, dialect,
+        ))))
+    }
+}
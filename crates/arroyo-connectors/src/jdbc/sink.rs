@@ -0,0 +1,1011 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
+use arrow::datatypes::{DataType, Schema};
+use arrow::util::display::array_value_to_string;
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{ArrowOperator, AsDisplayable, DisplayableOperator};
+use arroyo_rpc::grpc::rpc::{GlobalKeyedTableConfig, TableConfig, TableEnum};
+use arroyo_rpc::{CheckpointEvent, ControlMessage, ControlResp};
+use arroyo_types::*;
+use async_trait::async_trait;
+use itertools::Itertools;
+use prost::Message;
+use tracing::{error, warn};
+
+use super::{
+    DatabaseClient, Dialect, JdbcConfig, JdbcTable, JdbcTableCommitMode, DEFAULT_BATCH_SIZE,
+};
+
+pub struct JdbcSinkFunc {
+    config: JdbcConfig,
+    table: JdbcTable,
+    dialect: Dialect,
+    batch_size: usize,
+    consistency_mode: ConsistencyMode,
+    layout: Option<RowLayout>,
+    statements: Option<StatementBuilder>,
+    client: Option<DatabaseClient>,
+    /// a separate connection for finishing prepared transactions, as the write connection may
+    /// have the next epoch's transaction open
+    commit_client: Option<DatabaseClient>,
+    pending: PendingWrites,
+}
+
+/// In exactly-once mode, each epoch's writes are made in a two-phase transaction which is
+/// prepared at the checkpoint and committed once the checkpoint has completed. Prepared
+/// transactions survive the loss of the worker, so those left behind by a failure are committed
+/// or rolled back on restore (see [`JdbcSinkFunc::recover_transactions`]).
+enum ConsistencyMode {
+    AtLeastOnce,
+    ExactlyOnce {
+        /// the prefix shared by the ids of this operator's transactions
+        prefix: String,
+        /// the epoch of the latest checkpoint, which names the transaction for the writes after it
+        last_epoch: u32,
+        /// the id of the transaction open on the write connection
+        open_transaction: Option<String>,
+        /// transactions prepared at a checkpoint, with its epoch, that have yet to be committed
+        prepared: Vec<(u32, String)>,
+    },
+}
+
+impl Display for ConsistencyMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsistencyMode::AtLeastOnce => write!(f, "AtLeastOnce"),
+            ConsistencyMode::ExactlyOnce { .. } => write!(f, "ExactlyOnce"),
+        }
+    }
+}
+
+impl From<JdbcTableCommitMode> for ConsistencyMode {
+    fn from(commit_mode: JdbcTableCommitMode) -> Self {
+        match commit_mode {
+            JdbcTableCommitMode::AtLeastOnce => ConsistencyMode::AtLeastOnce,
+            JdbcTableCommitMode::ExactlyOnce => ConsistencyMode::ExactlyOnce {
+                prefix: String::new(),
+                last_epoch: 0,
+                open_transaction: None,
+                prepared: vec![],
+            },
+        }
+    }
+}
+
+/// How rows are laid out in the input batches
+#[derive(Clone)]
+enum RowLayout {
+    /// an append-only stream, where every row is an insert
+    Append { columns: Vec<usize> },
+    /// the output of an updating query, as debezium-style before/after/op columns
+    Debezium {
+        before: usize,
+        after: usize,
+        op: usize,
+    },
+}
+
+impl RowLayout {
+    /// Determines the layout of the input, returning it along with the names of the value columns
+    fn for_schema(schema: &Schema) -> anyhow::Result<(Self, Vec<String>)> {
+        if let (Ok(before), Ok(after), Ok(op)) = (
+            schema.index_of("before"),
+            schema.index_of("after"),
+            schema.index_of("op"),
+        ) {
+            let DataType::Struct(fields) = schema.field(after).data_type() else {
+                anyhow::bail!("expected 'after' column to be a struct");
+            };
+
+            return Ok((
+                RowLayout::Debezium { before, after, op },
+                fields.iter().map(|f| f.name().clone()).collect(),
+            ));
+        }
+
+        let (columns, names) = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.name() != TIMESTAMP_FIELD)
+            .map(|(i, f)| (i, f.name().clone()))
+            .unzip();
+
+        Ok((RowLayout::Append { columns }, names))
+    }
+}
+
+/// Writes that have been received since the last flush. For tables with a primary key, only the
+/// latest write for each key needs to be applied, which also avoids touching the same row twice
+/// in a single statement.
+#[derive(Default)]
+struct PendingWrites {
+    /// the latest row for each key, rendered as SQL literals; `None` represents a delete
+    keyed: HashMap<Vec<String>, Option<Vec<String>>>,
+    /// rows for tables without a primary key, which can only be inserted
+    inserts: Vec<Vec<String>>,
+}
+
+impl PendingWrites {
+    fn len(&self) -> usize {
+        self.keyed.len() + self.inserts.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Renders the statements that apply writes to the table
+struct StatementBuilder {
+    dialect: Dialect,
+    table: String,
+    columns: Vec<String>,
+    key_indices: Vec<usize>,
+}
+
+impl StatementBuilder {
+    fn new(
+        dialect: Dialect,
+        table: &str,
+        columns: Vec<String>,
+        primary_keys: &[String],
+    ) -> anyhow::Result<Self> {
+        let key_indices = primary_keys
+            .iter()
+            .map(|k| {
+                columns.iter().position(|c| c == k).ok_or_else(|| {
+                    anyhow::anyhow!("primary key '{}' is not a column in the input", k)
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            dialect,
+            table: table
+                .split('.')
+                .map(|part| quote_identifier(dialect, part))
+                .join("."),
+            columns,
+            key_indices,
+        })
+    }
+
+    fn has_keys(&self) -> bool {
+        !self.key_indices.is_empty()
+    }
+
+    fn key(&self, row: &[String]) -> Vec<String> {
+        self.key_indices.iter().map(|i| row[*i].clone()).collect()
+    }
+
+    fn column_list(&self) -> String {
+        self.columns
+            .iter()
+            .map(|c| quote_identifier(self.dialect, c))
+            .join(", ")
+    }
+
+    fn values(rows: &[&Vec<String>]) -> String {
+        rows.iter()
+            .map(|row| format!("({})", row.join(", ")))
+            .join(", ")
+    }
+
+    fn insert(&self, rows: &[&Vec<String>]) -> String {
+        format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            self.table,
+            self.column_list(),
+            Self::values(rows)
+        )
+    }
+
+    fn upsert(&self, rows: &[&Vec<String>]) -> String {
+        let insert = self.insert(rows);
+
+        let value_columns: Vec<_> = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.key_indices.contains(i))
+            .map(|(_, c)| quote_identifier(self.dialect, c))
+            .collect();
+
+        match self.dialect {
+            Dialect::Postgres => {
+                let keys = self
+                    .key_indices
+                    .iter()
+                    .map(|i| quote_identifier(self.dialect, &self.columns[*i]))
+                    .join(", ");
+
+                if value_columns.is_empty() {
+                    format!("{} ON CONFLICT ({}) DO NOTHING", insert, keys)
+                } else {
+                    format!(
+                        "{} ON CONFLICT ({}) DO UPDATE SET {}",
+                        insert,
+                        keys,
+                        value_columns
+                            .iter()
+                            .map(|c| format!("{} = EXCLUDED.{}", c, c))
+                            .join(", ")
+                    )
+                }
+            }
+            Dialect::MySql => {
+                let updates = if value_columns.is_empty() {
+                    // a no-op update, so that rows that already exist are left alone
+                    let key = quote_identifier(self.dialect, &self.columns[self.key_indices[0]]);
+                    format!("{} = {}", key, key)
+                } else {
+                    value_columns
+                        .iter()
+                        .map(|c| format!("{} = VALUES({})", c, c))
+                        .join(", ")
+                };
+
+                format!("{} ON DUPLICATE KEY UPDATE {}", insert, updates)
+            }
+        }
+    }
+
+    fn delete(&self, keys: &[&Vec<String>]) -> String {
+        let conditions = keys
+            .iter()
+            .map(|key| {
+                let condition = self
+                    .key_indices
+                    .iter()
+                    .zip(key.iter())
+                    .map(|(i, value)| {
+                        format!(
+                            "{} = {}",
+                            quote_identifier(self.dialect, &self.columns[*i]),
+                            value
+                        )
+                    })
+                    .join(" AND ");
+                format!("({})", condition)
+            })
+            .join(" OR ");
+
+        format!("DELETE FROM {} WHERE {}", self.table, conditions)
+    }
+
+    /// Renders the statements for a set of pending writes, with at most `batch_size` rows in each
+    fn statements(&self, pending: &PendingWrites, batch_size: usize) -> Vec<String> {
+        let mut statements = vec![];
+
+        let mut deletes = vec![];
+        let mut upserts = vec![];
+        for (key, row) in &pending.keyed {
+            match row {
+                Some(row) => upserts.push(row),
+                None => deletes.push(key),
+            }
+        }
+
+        for chunk in deletes.chunks(batch_size) {
+            statements.push(self.delete(chunk));
+        }
+
+        for chunk in upserts.chunks(batch_size) {
+            statements.push(self.upsert(chunk));
+        }
+
+        let inserts: Vec<_> = pending.inserts.iter().collect();
+        for chunk in inserts.chunks(batch_size) {
+            statements.push(self.insert(chunk));
+        }
+
+        statements
+    }
+}
+
+fn quote_identifier(dialect: Dialect, s: &str) -> String {
+    match dialect {
+        Dialect::Postgres => format!("\"{}\"", s.replace('"', "\"\"")),
+        Dialect::MySql => format!("`{}`", s.replace('`', "``")),
+    }
+}
+
+fn string_literal(dialect: Dialect, s: &str) -> String {
+    match dialect {
+        Dialect::Postgres => format!("'{}'", s.replace('\'', "''")),
+        // MySQL treats backslashes in strings as escapes unless NO_BACKSLASH_ESCAPES is set
+        Dialect::MySql => format!("'{}'", s.replace('\\', "\\\\").replace('\'', "''")),
+    }
+}
+
+fn bytes_literal(dialect: Dialect, b: &[u8]) -> String {
+    let hex: String = b.iter().map(|b| format!("{:02x}", b)).collect();
+    match dialect {
+        Dialect::Postgres => format!("'\\x{}'", hex),
+        Dialect::MySql => format!("X'{}'", hex),
+    }
+}
+
+/// Returns the prefix for the ids of an operator's transactions. Ids have the form
+/// `<prefix><task index>-<epoch>`, where the epoch is that of the checkpoint before the
+/// transaction's writes. The job and operator ids are hashed (with FNV-1a, which unlike the std
+/// hasher is stable across releases) to keep ids within MySQL's 64 byte limit.
+fn transaction_prefix(task_info: &TaskInfo) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in task_info
+        .job_id
+        .bytes()
+        .chain([b'/'])
+        .chain(task_info.operator_id.bytes())
+    {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("arroyo-{:016x}-", hash)
+}
+
+fn transaction_id(prefix: &str, task_index: usize, epoch: u32) -> String {
+    format!("{}{}-{}", prefix, task_index, epoch)
+}
+
+/// Parses a transaction id into its task index and epoch
+fn parse_transaction_id(prefix: &str, id: &str) -> Option<(usize, u32)> {
+    let (task_index, epoch) = id.strip_prefix(prefix)?.split_once('-')?;
+    Some((task_index.parse().ok()?, epoch.parse().ok()?))
+}
+
+fn begin_statement(dialect: Dialect, id: &str) -> String {
+    match dialect {
+        Dialect::Postgres => "BEGIN".to_string(),
+        Dialect::MySql => format!("XA START {}", string_literal(dialect, id)),
+    }
+}
+
+fn prepare_statements(dialect: Dialect, id: &str) -> Vec<String> {
+    let id = string_literal(dialect, id);
+    match dialect {
+        Dialect::Postgres => vec![format!("PREPARE TRANSACTION {}", id)],
+        Dialect::MySql => vec![format!("XA END {}", id), format!("XA PREPARE {}", id)],
+    }
+}
+
+fn commit_prepared_statement(dialect: Dialect, id: &str) -> String {
+    let id = string_literal(dialect, id);
+    match dialect {
+        Dialect::Postgres => format!("COMMIT PREPARED {}", id),
+        Dialect::MySql => format!("XA COMMIT {}", id),
+    }
+}
+
+fn rollback_prepared_statement(dialect: Dialect, id: &str) -> String {
+    let id = string_literal(dialect, id);
+    match dialect {
+        Dialect::Postgres => format!("ROLLBACK PREPARED {}", id),
+        Dialect::MySql => format!("XA ROLLBACK {}", id),
+    }
+}
+
+/// Renders a single value as a SQL literal
+fn literal(dialect: Dialect, array: &dyn Array, i: usize) -> String {
+    if array.is_null(i) {
+        return "NULL".to_string();
+    }
+
+    match array.data_type() {
+        DataType::Boolean => {
+            if array.as_boolean().value(i) {
+                "TRUE".to_string()
+            } else {
+                "FALSE".to_string()
+            }
+        }
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64
+        | DataType::Decimal128(_, _)
+        | DataType::Decimal256(_, _) => array_value_to_string(array, i).unwrap(),
+        DataType::Float32 | DataType::Float64 => {
+            let value = array_value_to_string(array, i).unwrap();
+            if value.parse::<f64>().is_ok_and(|f| f.is_finite()) {
+                value
+            } else {
+                // NaN and infinities must be quoted
+                string_literal(dialect, &value)
+            }
+        }
+        DataType::Utf8 => string_literal(dialect, array.as_string::<i32>().value(i)),
+        DataType::LargeUtf8 => string_literal(dialect, array.as_string::<i64>().value(i)),
+        DataType::Binary => bytes_literal(dialect, array.as_binary::<i32>().value(i)),
+        DataType::LargeBinary => bytes_literal(dialect, array.as_binary::<i64>().value(i)),
+        _ => string_literal(dialect, &array_value_to_string(array, i).unwrap()),
+    }
+}
+
+fn render_row(dialect: Dialect, columns: &[ArrayRef], i: usize) -> Vec<String> {
+    columns
+        .iter()
+        .map(|c| literal(dialect, c.as_ref(), i))
+        .collect()
+}
+
+impl JdbcSinkFunc {
+    pub fn new(config: JdbcConfig, table: JdbcTable, dialect: Dialect) -> Self {
+        Self {
+            batch_size: table
+                .batch_size
+                .map(|b| b as usize)
+                .unwrap_or(DEFAULT_BATCH_SIZE),
+            consistency_mode: table.commit_mode.into(),
+            config,
+            table,
+            dialect,
+            layout: None,
+            statements: None,
+            client: None,
+            commit_client: None,
+            pending: PendingWrites::default(),
+        }
+    }
+
+    fn is_committing(&self) -> bool {
+        matches!(self.consistency_mode, ConsistencyMode::ExactlyOnce { .. })
+    }
+
+    async fn connect(&self, ctx: &mut ArrowContext) -> DatabaseClient {
+        match DatabaseClient::connect(&self.config).await {
+            Ok(client) => client,
+            Err(e) => {
+                ctx.report_error("Failed to connect to database", format!("{:?}", e))
+                    .await;
+                panic!("Failed to connect to database: {:?}", e);
+            }
+        }
+    }
+
+    fn add_row(&mut self, row: Vec<String>) {
+        let statements = self.statements.as_ref().unwrap();
+        if statements.has_keys() {
+            self.pending.keyed.insert(statements.key(&row), Some(row));
+        } else {
+            self.pending.inserts.push(row);
+        }
+    }
+
+    fn delete_row(&mut self, row: Vec<String>) {
+        let statements = self.statements.as_ref().unwrap();
+        if statements.has_keys() {
+            self.pending.keyed.insert(statements.key(&row), None);
+        } else {
+            warn!(
+                "JDBC sink for {} received a delete, but has no primary key; ignoring",
+                self.table.table
+            );
+        }
+    }
+
+    async fn execute(&mut self, sql: &str, ctx: &mut ArrowContext) {
+        if let Err(e) = self.client.as_mut().unwrap().execute(sql).await {
+            ctx.report_error("Failed to write to database", format!("{:?}", e))
+                .await;
+            panic!("Failed to write to database: {:?}", e);
+        }
+    }
+
+    /// Commits or rolls back a prepared transaction, retrying on failure
+    async fn finish_prepared(&mut self, sql: &str, ctx: &mut ArrowContext) {
+        let mut attempts = 0;
+        loop {
+            if self.commit_client.is_none() {
+                self.commit_client = Some(self.connect(ctx).await);
+            }
+
+            match self.commit_client.as_mut().unwrap().execute(sql).await {
+                Ok(_) => return,
+                Err(e) if attempts == 5 => {
+                    ctx.report_error("Failed to commit to database", format!("{:?}", e))
+                        .await;
+                    panic!("failed to run '{}' 5 times, giving up: {:?}", sql, e);
+                }
+                Err(e) => {
+                    error!(
+                        "failed to run '{}' {} times, retrying: {:?}",
+                        sql, attempts, e
+                    );
+                    attempts += 1;
+                    // reconnect, in case the connection was lost
+                    self.commit_client = None;
+                }
+            }
+        }
+    }
+
+    /// Finishes the transactions left prepared by a previous run of this operator. A
+    /// transaction is named by the checkpoint before its writes and prepared at the next one,
+    /// so those named by an epoch before the restored checkpoint belong to it and are
+    /// committed, while the rest are rolled back. Each subtask handles the transactions of the
+    /// previous subtasks that map onto it, so none are left behind if the parallelism changed.
+    async fn recover_transactions(
+        &mut self,
+        prefix: &str,
+        restored_epoch: u32,
+        ctx: &mut ArrowContext,
+    ) {
+        if self.commit_client.is_none() {
+            self.commit_client = Some(self.connect(ctx).await);
+        }
+
+        let ids = match self
+            .commit_client
+            .as_mut()
+            .unwrap()
+            .prepared_transactions(prefix)
+            .await
+        {
+            Ok(ids) => ids,
+            Err(e) => {
+                ctx.report_error("Failed to list prepared transactions", format!("{:?}", e))
+                    .await;
+                panic!("Failed to list prepared transactions: {:?}", e);
+            }
+        };
+
+        for id in ids {
+            let Some((task_index, epoch)) = parse_transaction_id(prefix, &id) else {
+                continue;
+            };
+
+            if task_index % ctx.task_info.parallelism != ctx.task_info.task_index {
+                continue;
+            }
+
+            let sql = if epoch < restored_epoch {
+                commit_prepared_statement(self.dialect, &id)
+            } else {
+                rollback_prepared_statement(self.dialect, &id)
+            };
+            self.finish_prepared(&sql, ctx).await;
+        }
+    }
+
+    /// Writes all pending rows to the database. In at-least-once mode these are committed
+    /// immediately, while in exactly-once mode they are added to the epoch's open transaction.
+    async fn flush(&mut self, ctx: &mut ArrowContext) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let statements = self
+            .statements
+            .as_ref()
+            .unwrap()
+            .statements(&self.pending, self.batch_size);
+        self.pending = PendingWrites::default();
+
+        let (begin, commit) = match &mut self.consistency_mode {
+            ConsistencyMode::AtLeastOnce => (Some("BEGIN".to_string()), true),
+            ConsistencyMode::ExactlyOnce {
+                prefix,
+                last_epoch,
+                open_transaction,
+                ..
+            } => match open_transaction {
+                Some(_) => (None, false),
+                None => {
+                    let id = transaction_id(prefix, ctx.task_info.task_index, *last_epoch);
+                    let begin = begin_statement(self.dialect, &id);
+                    *open_transaction = Some(id);
+                    (Some(begin), false)
+                }
+            },
+        };
+
+        if let Some(begin) = begin {
+            self.execute(&begin, ctx).await;
+        }
+
+        for statement in statements {
+            self.execute(&statement, ctx).await;
+        }
+
+        if commit {
+            self.execute("COMMIT", ctx).await;
+        }
+    }
+}
+
+#[async_trait]
+impl ArrowOperator for JdbcSinkFunc {
+    fn name(&self) -> String {
+        format!("jdbc-sink-{}", self.table.table)
+    }
+
+    fn display(&self) -> DisplayableOperator {
+        DisplayableOperator {
+            name: Cow::Borrowed("JdbcSinkFunc"),
+            fields: vec![
+                ("table", self.table.table.as_str().into()),
+                ("dialect", AsDisplayable::Debug(&self.dialect)),
+                (
+                    "primary_keys",
+                    AsDisplayable::Debug(&self.table.primary_keys),
+                ),
+                (
+                    "consistency_mode",
+                    AsDisplayable::Display(&self.consistency_mode),
+                ),
+            ],
+        }
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        if self.is_committing() {
+            single_item_hash_map(
+                "e".to_string(),
+                TableConfig {
+                    table_type: TableEnum::GlobalKeyValue.into(),
+                    config: GlobalKeyedTableConfig {
+                        table_name: "e".to_string(),
+                        description: "epochs for jdbc transactions".to_string(),
+                        uses_two_phase_commit: true,
+                    }
+                    .encode_to_vec(),
+                },
+            )
+        } else {
+            HashMap::new()
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let (layout, columns) = match RowLayout::for_schema(&ctx.in_schemas[0].schema) {
+            Ok(r) => r,
+            Err(e) => {
+                ctx.report_error("Invalid input for JDBC sink", e.to_string())
+                    .await;
+                panic!("Invalid input for JDBC sink: {}", e);
+            }
+        };
+
+        let statements = match StatementBuilder::new(
+            self.dialect,
+            &self.table.table,
+            columns,
+            &self.table.primary_keys,
+        ) {
+            Ok(s) => s,
+            Err(e) => {
+                ctx.report_error("Invalid primary key for JDBC sink", e.to_string())
+                    .await;
+                panic!("Invalid primary key for JDBC sink: {}", e);
+            }
+        };
+
+        self.layout = Some(layout);
+        self.statements = Some(statements);
+        self.client = Some(self.connect(ctx).await);
+
+        if self.is_committing() {
+            let prefix = transaction_prefix(&ctx.task_info);
+            let restored_epoch = ctx
+                .table_manager
+                .get_global_keyed_state::<usize, u32>("e")
+                .await
+                .expect("should be able to get jdbc epoch state")
+                .get_all()
+                .values()
+                .copied()
+                .max()
+                .unwrap_or(0);
+
+            self.recover_transactions(&prefix, restored_epoch, ctx)
+                .await;
+
+            if let ConsistencyMode::ExactlyOnce {
+                prefix: p,
+                last_epoch,
+                ..
+            } = &mut self.consistency_mode
+            {
+                *p = prefix;
+                *last_epoch = restored_epoch;
+            }
+        }
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        match self.layout.clone().unwrap() {
+            RowLayout::Append { columns } => {
+                let columns: Vec<_> = columns.iter().map(|i| batch.column(*i).clone()).collect();
+                for i in 0..batch.num_rows() {
+                    let row = render_row(self.dialect, &columns, i);
+                    self.add_row(row);
+                }
+            }
+            RowLayout::Debezium { before, after, op } => {
+                let before = batch.column(before).as_struct().clone();
+                let after = batch.column(after).as_struct().clone();
+                let ops = batch.column(op).as_string::<i32>().clone();
+
+                for i in 0..batch.num_rows() {
+                    match ops.value(i) {
+                        "c" | "r" => {
+                            let row = render_row(self.dialect, after.columns(), i);
+                            self.add_row(row);
+                        }
+                        "u" => {
+                            let old = render_row(self.dialect, before.columns(), i);
+                            let new = render_row(self.dialect, after.columns(), i);
+                            // if the key has changed, the old row needs to be removed
+                            let statements = self.statements.as_ref().unwrap();
+                            if statements.key(&old) != statements.key(&new) {
+                                self.delete_row(old);
+                            }
+                            self.add_row(new);
+                        }
+                        "d" => {
+                            let row = render_row(self.dialect, before.columns(), i);
+                            self.delete_row(row);
+                        }
+                        op => {
+                            warn!("unknown debezium op '{}' in JDBC sink; ignoring", op);
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.pending.len() >= self.batch_size {
+            self.flush(ctx).await;
+        }
+    }
+
+    async fn handle_checkpoint(&mut self, b: CheckpointBarrier, ctx: &mut ArrowContext) {
+        self.flush(ctx).await;
+
+        let ConsistencyMode::ExactlyOnce {
+            last_epoch,
+            open_transaction,
+            ..
+        } = &mut self.consistency_mode
+        else {
+            return;
+        };
+
+        *last_epoch = b.epoch;
+        let to_prepare = open_transaction.take();
+
+        // prepare this epoch's transaction, to be committed once the checkpoint completes
+        if let Some(id) = to_prepare {
+            for statement in prepare_statements(self.dialect, &id) {
+                self.execute(&statement, ctx).await;
+            }
+
+            if self.dialect == Dialect::MySql {
+                // before MySQL 8.0.29 a session can't start a new transaction while the one it
+                // prepared is outstanding, so continue on a new connection
+                self.client = Some(self.connect(ctx).await);
+            }
+
+            if let ConsistencyMode::ExactlyOnce { prepared, .. } = &mut self.consistency_mode {
+                prepared.push((b.epoch, id));
+            }
+        }
+
+        ctx.table_manager
+            .get_global_keyed_state("e")
+            .await
+            .as_mut()
+            .unwrap()
+            .insert(ctx.task_info.task_index, b.epoch)
+            .await;
+    }
+
+    async fn handle_commit(
+        &mut self,
+        epoch: u32,
+        _commit_data: &HashMap<String, HashMap<u32, Vec<u8>>>,
+        ctx: &mut ArrowContext,
+    ) {
+        let ConsistencyMode::ExactlyOnce { prepared, .. } = &mut self.consistency_mode else {
+            warn!("received commit but consistency mode is not exactly once");
+            return;
+        };
+
+        let (to_commit, remaining): (Vec<_>, Vec<_>) = std::mem::take(prepared)
+            .into_iter()
+            .partition(|(e, _)| *e <= epoch);
+        *prepared = remaining;
+
+        for (_, id) in to_commit {
+            let sql = commit_prepared_statement(self.dialect, &id);
+            self.finish_prepared(&sql, ctx).await;
+        }
+
+        let checkpoint_event = ControlResp::CheckpointEvent(CheckpointEvent {
+            checkpoint_epoch: epoch,
+            operator_id: ctx.task_info.operator_id.clone(),
+            subtask_index: ctx.task_info.task_index as u32,
+            time: SystemTime::now(),
+            event_type: arroyo_rpc::grpc::rpc::TaskCheckpointEventType::FinishedCommit,
+        });
+        ctx.control_tx
+            .send(checkpoint_event)
+            .await
+            .expect("sent commit event");
+    }
+
+    async fn on_close(&mut self, _: &Option<SignalMessage>, ctx: &mut ArrowContext) {
+        self.flush(ctx).await;
+        if !self.is_committing() {
+            return;
+        }
+        if let Some(ControlMessage::Commit { epoch, commit_data }) = ctx.control_rx.recv().await {
+            self.handle_commit(epoch, &commit_data, ctx).await;
+        } else {
+            warn!("no commit message received, not committing")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{BinaryArray, Float64Array, StringArray};
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|r| r.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_postgres_statements() {
+        let builder = StatementBuilder::new(
+            Dialect::Postgres,
+            "public.orders",
+            vec!["id".to_string(), "price".to_string()],
+            &["id".to_string()],
+        )
+        .unwrap();
+
+        let r = rows(&[&["1", "10.5"], &["2", "NULL"]]);
+        assert_eq!(
+            builder.upsert(&r.iter().collect::<Vec<_>>()),
+            "INSERT INTO \"public\".\"orders\" (\"id\", \"price\") VALUES (1, 10.5), (2, NULL) \
+            ON CONFLICT (\"id\") DO UPDATE SET \"price\" = EXCLUDED.\"price\""
+        );
+
+        let keys = rows(&[&["1"], &["3"]]);
+        assert_eq!(
+            builder.delete(&keys.iter().collect::<Vec<_>>()),
+            "DELETE FROM \"public\".\"orders\" WHERE (\"id\" = 1) OR (\"id\" = 3)"
+        );
+    }
+
+    #[test]
+    fn test_mysql_statements() {
+        let builder = StatementBuilder::new(
+            Dialect::MySql,
+            "orders",
+            vec!["id".to_string(), "region".to_string()],
+            &["id".to_string(), "region".to_string()],
+        )
+        .unwrap();
+
+        let r = rows(&[&["1", "'us'"]]);
+        assert_eq!(
+            builder.upsert(&r.iter().collect::<Vec<_>>()),
+            "INSERT INTO `orders` (`id`, `region`) VALUES (1, 'us') \
+            ON DUPLICATE KEY UPDATE `id` = `id`"
+        );
+
+        assert_eq!(
+            builder.delete(&r.iter().collect::<Vec<_>>()),
+            "DELETE FROM `orders` WHERE (`id` = 1 AND `region` = 'us')"
+        );
+    }
+
+    #[test]
+    fn test_pending_writes() {
+        let builder = StatementBuilder::new(
+            Dialect::Postgres,
+            "t",
+            vec!["id".to_string(), "v".to_string()],
+            &["id".to_string()],
+        )
+        .unwrap();
+
+        let mut pending = PendingWrites::default();
+        for row in rows(&[&["1", "'a'"], &["2", "'b'"], &["1", "'c'"]]) {
+            pending.keyed.insert(builder.key(&row), Some(row));
+        }
+        pending.keyed.insert(vec!["2".to_string()], None);
+
+        assert_eq!(pending.len(), 2);
+        assert_eq!(
+            builder.statements(&pending, 10),
+            vec![
+                "DELETE FROM \"t\" WHERE (\"id\" = 2)".to_string(),
+                "INSERT INTO \"t\" (\"id\", \"v\") VALUES (1, 'c') \
+                ON CONFLICT (\"id\") DO UPDATE SET \"v\" = EXCLUDED.\"v\""
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transaction_ids() {
+        let prefix = transaction_prefix(&TaskInfo::for_test("job_1", "sink_orders_3"));
+        assert!(prefix.starts_with("arroyo-"));
+        assert_ne!(
+            prefix,
+            transaction_prefix(&TaskInfo::for_test("job_2", "sink_orders_3"))
+        );
+
+        let id = transaction_id(&prefix, 2, 17);
+        assert!(id.len() <= 64);
+        assert_eq!(parse_transaction_id(&prefix, &id), Some((2, 17)));
+        assert_eq!(parse_transaction_id("arroyo-other-", &id), None);
+        assert_eq!(
+            parse_transaction_id(&prefix, &format!("{}x-1", prefix)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_transaction_statements() {
+        assert_eq!(begin_statement(Dialect::Postgres, "t-1"), "BEGIN");
+        assert_eq!(
+            prepare_statements(Dialect::Postgres, "t-1"),
+            vec!["PREPARE TRANSACTION 't-1'"]
+        );
+        assert_eq!(
+            commit_prepared_statement(Dialect::Postgres, "t-1"),
+            "COMMIT PREPARED 't-1'"
+        );
+        assert_eq!(
+            rollback_prepared_statement(Dialect::Postgres, "t-1"),
+            "ROLLBACK PREPARED 't-1'"
+        );
+
+        assert_eq!(begin_statement(Dialect::MySql, "t-1"), "XA START 't-1'");
+        assert_eq!(
+            prepare_statements(Dialect::MySql, "t-1"),
+            vec!["XA END 't-1'", "XA PREPARE 't-1'"]
+        );
+        assert_eq!(
+            commit_prepared_statement(Dialect::MySql, "t-1"),
+            "XA COMMIT 't-1'"
+        );
+        assert_eq!(
+            rollback_prepared_statement(Dialect::MySql, "t-1"),
+            "XA ROLLBACK 't-1'"
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        let strings = StringArray::from(vec![Some("it's a \\ test"), None]);
+        assert_eq!(literal(Dialect::Postgres, &strings, 0), "'it''s a \\ test'");
+        assert_eq!(literal(Dialect::MySql, &strings, 0), "'it''s a \\\\ test'");
+        assert_eq!(literal(Dialect::Postgres, &strings, 1), "NULL");
+
+        let floats = Float64Array::from(vec![1.5, f64::NAN]);
+        assert_eq!(literal(Dialect::Postgres, &floats, 0), "1.5");
+        assert_eq!(literal(Dialect::Postgres, &floats, 1), "'NaN'");
+
+        let bytes = BinaryArray::from(vec![&[0xde, 0xad][..]]);
+        assert_eq!(literal(Dialect::Postgres, &bytes, 0), "'\\xdead'");
+        assert_eq!(literal(Dialect::MySql, &bytes, 0), "X'dead'");
+    }
+}
@@ -1,11 +1,11 @@
 use anyhow::{anyhow, bail};
-use arrow::datatypes::DataType;
+use arrow::datatypes::{DataType, TimeUnit};
 use arroyo_formats::de::ArrowDeserializer;
 use arroyo_formats::ser::ArrowSerializer;
-use arroyo_operator::connector::Connection;
+use arroyo_operator::connector::{Connection, MetadataDef};
 use arroyo_rpc::api_types::connections::{ConnectionProfile, ConnectionSchema, TestSourceMessage};
 use arroyo_rpc::df::ArroyoSchema;
-use arroyo_rpc::formats::{BadData, Format, JsonFormat};
+use arroyo_rpc::formats::{BadData, CsvFormat, Format, JsonFormat};
 use arroyo_rpc::schema_resolver::{
     ConfluentSchemaRegistry, ConfluentSchemaRegistryClient, SchemaResolver,
 };
@@ -102,14 +102,49 @@ impl KafkaConnector {
         let typ = pull_opt("type", options)?;
         let table_type = match typ.as_str() {
             "source" => {
-                let offset = options.remove("source.offset");
+                let offset = match options.remove("source.offset").as_deref() {
+                    Some("earliest") => SourceOffset::Earliest,
+                    Some("group") => SourceOffset::Group,
+                    Some("timestamp") => SourceOffset::Timestamp,
+                    Some("explicit") => SourceOffset::Explicit,
+                    None | Some("latest") => SourceOffset::Latest,
+                    Some(other) => bail!("invalid value for source.offset '{}'", other),
+                };
+
+                let start_timestamp = options
+                    .remove("source.start_timestamp")
+                    .map(|t| {
+                        t.parse::<i64>().map_err(|_| {
+                            anyhow!(
+                                "invalid value for source.start_timestamp '{}'; expected \
+                                milliseconds since the Unix epoch",
+                                t
+                            )
+                        })
+                    })
+                    .transpose()?;
+                let start_offsets = options
+                    .remove("source.start_offsets")
+                    .map(|o| parse_start_offsets(&o))
+                    .transpose()?
+                    .unwrap_or_default();
+
+                match offset {
+                    SourceOffset::Timestamp if start_timestamp.is_none() => {
+                        bail!(
+                            "source.start_timestamp must be set when source.offset is 'timestamp'"
+                        )
+                    }
+                    SourceOffset::Explicit if start_offsets.is_empty() => {
+                        bail!("source.start_offsets must be set when source.offset is 'explicit'")
+                    }
+                    _ => {}
+                }
+
                 TableType::Source {
-                    offset: match offset.as_deref() {
-                        Some("earliest") => SourceOffset::Earliest,
-                        Some("group") => SourceOffset::Group,
-                        None | Some("latest") => SourceOffset::Latest,
-                        Some(other) => bail!("invalid value for source.offset '{}'", other),
-                    },
+                    offset,
+                    start_timestamp,
+                    start_offsets,
                     read_mode: match options.remove("source.read_mode").as_deref() {
                         Some("read_committed") => Some(ReadMode::ReadCommitted),
                         Some("read_uncommitted") | None => Some(ReadMode::ReadUncommitted),
@@ -129,6 +164,8 @@ impl KafkaConnector {
                     },
                     timestamp_field: options.remove("sink.timestamp_field"),
                     key_field: options.remove("sink.key_field"),
+                    topic_field: options.remove("sink.topic_field"),
+                    headers_field: options.remove("sink.headers_field"),
                 }
             }
             _ => {
@@ -178,6 +215,44 @@ impl Connector for KafkaConnector {
         }
     }
 
+    /// Tables can't declare map columns, so `headers` is provided as a JSON object of header
+    /// names to values (with values that aren't UTF-8 replaced lossily), which can be declared as
+    /// `JSON` and read with the JSON functions. Likewise the key is only provided raw (`key`) or
+    /// as UTF-8 text (`key_text`, which can be declared as `JSON` for JSON keys); keys in other
+    /// formats have to be decoded from `key` in the query.
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        &[
+            MetadataDef {
+                name: "offset_id",
+                data_type: DataType::Int64,
+            },
+            MetadataDef {
+                name: "partition",
+                data_type: DataType::Int32,
+            },
+            MetadataDef {
+                name: "topic",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "key",
+                data_type: DataType::Binary,
+            },
+            MetadataDef {
+                name: "key_text",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "headers",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "timestamp",
+                data_type: DataType::Timestamp(TimeUnit::Nanosecond, None),
+            },
+        ]
+    }
+
     fn config_description(&self, config: Self::ProfileT) -> String {
         (*config.bootstrap_servers).clone()
     }
@@ -209,6 +284,12 @@ impl Connector for KafkaConnector {
             .map(|t| t.to_owned())
             .ok_or_else(|| anyhow!("'format' must be set for Kafka connection"))?;
 
+        if typ == ConnectionType::Sink {
+            if let Format::Csv(CsvFormat { header: true, .. }) = format {
+                bail!("csv.header is not supported for Kafka sinks, as each message is a single record");
+            }
+        }
+
         let metadata_fields = metadata_fields.map(|fields| {
             fields
                 .into_iter()
@@ -334,36 +415,6 @@ impl Connector for KafkaConnector {
 
         let table = Self::table_from_options(options)?;
 
-        let allowed_metadata_udf_args: HashMap<&str, DataType> = [
-            ("offset_id", DataType::Int64),
-            ("partition", DataType::Int32),
-            ("topic", DataType::Utf8),
-        ]
-        .iter()
-        .cloned()
-        .collect();
-
-        if let Some(fields) = &metadata_fields {
-            for (field_name, data_type) in fields.values() {
-                match allowed_metadata_udf_args.get(field_name.as_str()) {
-                    Some(expected_type) => {
-                        if expected_type != data_type {
-                            return Err(anyhow!(
-                                "Invalid datatype for metadata field '{}': expected '{:?}', found '{:?}'",
-                                field_name, expected_type, data_type
-                            ));
-                        }
-                    }
-                    None => {
-                        return Err(anyhow!(
-                            "Invalid metadata field name for Kafka connector: '{}'",
-                            field_name
-                        ));
-                    }
-                }
-            }
-        }
-
         Self::from_config(self, None, name, connection, table, schema, metadata_fields)
     }
 
@@ -379,6 +430,8 @@ impl Connector for KafkaConnector {
                 offset,
                 read_mode,
                 group_id_prefix,
+                start_timestamp,
+                start_offsets,
             } => {
                 let mut client_configs = client_configs(&profile, &table);
                 if let Some(ReadMode::ReadCommitted) = read_mode {
@@ -412,6 +465,11 @@ impl Connector for KafkaConnector {
                     group_id: group_id.clone(),
                     group_id_prefix: group_id_prefix.clone(),
                     offset_mode: *offset,
+                    start_timestamp: *start_timestamp,
+                    start_offsets: start_offsets
+                        .iter()
+                        .map(|o| (o.partition, o.offset))
+                        .collect(),
                     format: config.format.expect("Format must be set for Kafka source"),
                     framing: config.framing,
                     schema_resolver,
@@ -431,6 +489,8 @@ impl Connector for KafkaConnector {
                 commit_mode,
                 key_field,
                 timestamp_field,
+                topic_field,
+                headers_field,
             } => Ok(OperatorNode::from_operator(Box::new(KafkaSinkFunc {
                 bootstrap_servers: profile.bootstrap_servers.to_string(),
                 producer: None,
@@ -439,11 +499,16 @@ impl Connector for KafkaConnector {
                 timestamp_col: None,
                 key_field: key_field.clone(),
                 key_col: None,
+                topic_field: topic_field.clone(),
+                topic_col: None,
+                headers_field: headers_field.clone(),
+                headers_col: None,
                 write_futures: vec![],
                 client_config: client_configs(&profile, &table),
                 topic: table.topic,
-                serializer: ArrowSerializer::new(
+                serializer: ArrowSerializer::with_framing(
                     config.format.expect("Format must be defined for KafkaSink"),
+                    config.framing,
                 ),
             }))),
         }
@@ -713,6 +778,28 @@ impl KafkaTester {
             Format::RawBytes(_) => {
                 // all bytes are valid
             }
+            Format::Csv(_) => {
+                let aschema: ArroyoSchema = schema.clone().into();
+                let mut deserializer =
+                    ArrowDeserializer::new(format.clone(), aschema.clone(), None, BadData::Fail {});
+                let mut builders = aschema.builders();
+
+                let mut error = deserializer
+                    .deserialize_slice(&mut builders, &msg, SystemTime::now(), None)
+                    .await
+                    .into_iter()
+                    .next();
+                if let Some(Err(e)) = deserializer.flush_buffer() {
+                    error.replace(e);
+                }
+
+                if let Some(error) = error {
+                    bail!(
+                        "Failed to parse message as CSV: {}. Ensure that the format and schema type are correct.",
+                        error.details()
+                    );
+                }
+            }
             Format::Protobuf(_) => {
                 let aschema: ArroyoSchema = schema.clone().into();
                 let mut deserializer =
@@ -917,10 +1004,43 @@ impl SourceOffset {
             SourceOffset::Earliest => Offset::Beginning,
             SourceOffset::Latest => Offset::End,
             SourceOffset::Group => Offset::Stored,
+            SourceOffset::Timestamp | SourceOffset::Explicit => {
+                unreachable!("{:?} offsets are resolved per partition", self)
+            }
         }
     }
 }
 
+/// Parses start offsets of the form `partition:offset,partition:offset`
+fn parse_start_offsets(s: &str) -> anyhow::Result<Vec<PartitionOffset>> {
+    let mut offsets: Vec<PartitionOffset> = vec![];
+    for entry in s.split(',') {
+        let parsed = entry.split_once(':').and_then(|(partition, offset)| {
+            Some(PartitionOffset {
+                partition: partition.trim().parse::<i32>().ok()?,
+                offset: offset.trim().parse::<i64>().ok()?,
+            })
+        });
+
+        let Some(parsed) = parsed.filter(|o| o.partition >= 0 && o.offset >= 0) else {
+            bail!(
+                "invalid entry '{}' in source.start_offsets; expected `partition:offset`",
+                entry
+            );
+        };
+
+        if offsets.iter().any(|o| o.partition == parsed.partition) {
+            bail!(
+                "partition {} appears more than once in source.start_offsets",
+                parsed.partition
+            );
+        }
+        offsets.push(parsed);
+    }
+
+    Ok(offsets)
+}
+
 pub fn client_configs(connection: &KafkaConfig, table: &KafkaTable) -> HashMap<String, String> {
     let mut client_configs: HashMap<String, String> = HashMap::new();
 
@@ -958,3 +1078,76 @@ pub fn client_configs(connection: &KafkaConfig, table: &KafkaTable) -> HashMap<S
 
     client_configs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_offset_options() {
+        let mut options = HashMap::from([
+            ("type".to_string(), "source".to_string()),
+            ("topic".to_string(), "events".to_string()),
+            ("source.offset".to_string(), "explicit".to_string()),
+            ("source.start_offsets".to_string(), "0:100, 2:7".to_string()),
+        ]);
+
+        let table = KafkaConnector::table_from_options(&mut options).unwrap();
+        let TableType::Source {
+            offset,
+            start_offsets,
+            ..
+        } = table.type_
+        else {
+            panic!("expected a source table");
+        };
+        assert!(matches!(offset, SourceOffset::Explicit));
+        assert_eq!(
+            start_offsets
+                .iter()
+                .map(|o| (o.partition, o.offset))
+                .collect::<Vec<_>>(),
+            vec![(0, 100), (2, 7)]
+        );
+
+        assert!(parse_start_offsets("0=100").is_err());
+        assert!(parse_start_offsets("-1:100").is_err());
+        assert!(parse_start_offsets("0:100,0:200").is_err());
+
+        let mut options = HashMap::from([
+            ("type".to_string(), "source".to_string()),
+            ("topic".to_string(), "events".to_string()),
+            ("source.offset".to_string(), "timestamp".to_string()),
+            (
+                "source.start_timestamp".to_string(),
+                "1709251200000".to_string(),
+            ),
+        ]);
+        let table = KafkaConnector::table_from_options(&mut options).unwrap();
+        let TableType::Source {
+            start_timestamp, ..
+        } = table.type_
+        else {
+            panic!("expected a source table");
+        };
+        assert_eq!(start_timestamp, Some(1709251200000));
+
+        let mut options = HashMap::from([
+            ("type".to_string(), "source".to_string()),
+            ("topic".to_string(), "events".to_string()),
+            ("source.offset".to_string(), "timestamp".to_string()),
+            (
+                "source.start_timestamp".to_string(),
+                "2024-03-01T00:00:00Z".to_string(),
+            ),
+        ]);
+        assert!(KafkaConnector::table_from_options(&mut options).is_err());
+
+        let mut options = HashMap::from([
+            ("type".to_string(), "source".to_string()),
+            ("topic".to_string(), "events".to_string()),
+            ("source.offset".to_string(), "timestamp".to_string()),
+        ]);
+        assert!(KafkaConnector::table_from_options(&mut options).is_err());
+    }
+}
@@ -8,13 +8,16 @@ use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use tracing::{error, warn};
 
+use rdkafka::message::{Header, OwnedHeaders};
 use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord, Producer};
 use rdkafka::util::Timeout;
 
 use rdkafka::ClientConfig;
 
 use super::SinkCommitMode;
-use arrow::array::{Array, AsArray, RecordBatch};
+use arrow::array::{Array, ArrayRef, AsArray, BinaryArray, MapArray, RecordBatch};
+use arrow::buffer::NullBuffer;
+use arrow::compute::{can_cast_types, cast};
 use arrow::datatypes::{DataType, TimeUnit};
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::ArrowContext;
@@ -37,6 +40,10 @@ pub struct KafkaSinkFunc {
     pub timestamp_col: Option<usize>,
     pub key_field: Option<String>,
     pub key_col: Option<usize>,
+    pub topic_field: Option<String>,
+    pub topic_col: Option<usize>,
+    pub headers_field: Option<String>,
+    pub headers_col: Option<usize>,
     pub producer: Option<FutureProducer>,
     pub write_futures: Vec<DeliveryFuture>,
     pub client_config: HashMap<String, String>,
@@ -125,6 +132,48 @@ impl KafkaSinkFunc {
         }
     }
 
+    fn set_topic_col(&mut self, schema: &ArroyoSchema) {
+        if let Some(f) = &self.topic_field {
+            if let Ok(f) = schema.schema.field_with_name(f) {
+                if matches!(f.data_type(), DataType::Utf8) {
+                    self.topic_col = Some(schema.schema.index_of(f.name()).unwrap());
+                } else {
+                    warn!(
+                        "Kafka sink configured with topic_field '{f}', but it has type \
+                {}, not TEXT... ignoring",
+                        f.data_type()
+                    );
+                }
+            } else {
+                warn!(
+                    "Kafka sink configured with topic_field '{f}', but that \
+                does not appear in the schema... ignoring"
+                );
+            }
+        }
+    }
+
+    fn set_headers_col(&mut self, schema: &ArroyoSchema) {
+        if let Some(f) = &self.headers_field {
+            if let Ok(f) = schema.schema.field_with_name(f) {
+                if is_headers_type(f.data_type()) {
+                    self.headers_col = Some(schema.schema.index_of(f.name()).unwrap());
+                } else {
+                    warn!(
+                        "Kafka sink configured with headers_field '{f}', but it has type \
+                {}, not a struct or a map with TEXT keys... ignoring",
+                        f.data_type()
+                    );
+                }
+            } else {
+                warn!(
+                    "Kafka sink configured with headers_field '{f}', but that \
+                does not appear in the schema... ignoring"
+                );
+            }
+        }
+    }
+
     fn init_producer(&mut self, task_info: &TaskInfo) -> Result<()> {
         let mut client_config = ClientConfig::new();
         client_config.set("bootstrap.servers", &self.bootstrap_servers);
@@ -180,21 +229,28 @@ impl KafkaSinkFunc {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn publish(
         &mut self,
         ts: Option<i64>,
         k: Option<Vec<u8>>,
+        topic: Option<&str>,
+        headers: Option<OwnedHeaders>,
         v: Vec<u8>,
         ctx: &mut ArrowContext,
     ) {
         let mut rec = {
-            let mut rec = FutureRecord::<Vec<u8>, Vec<u8>>::to(&self.topic);
+            let mut rec =
+                FutureRecord::<Vec<u8>, Vec<u8>>::to(topic.unwrap_or(self.topic.as_str()));
             if let Some(ts) = ts {
                 rec = rec.timestamp(ts);
             }
             if let Some(k) = k.as_ref() {
                 rec = rec.key(k);
             }
+            if let Some(headers) = headers {
+                rec = rec.headers(headers);
+            }
 
             rec.payload(&v)
         };
@@ -244,6 +300,8 @@ impl ArrowOperator for KafkaSinkFunc {
                     AsDisplayable::Debug(&self.timestamp_field),
                 ),
                 ("key_field", AsDisplayable::Debug(&self.key_field)),
+                ("topic_field", AsDisplayable::Debug(&self.topic_field)),
+                ("headers_field", AsDisplayable::Debug(&self.headers_field)),
                 ("client_config", AsDisplayable::Debug(&self.client_config)),
             ],
         }
@@ -271,6 +329,8 @@ impl ArrowOperator for KafkaSinkFunc {
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
         self.set_timestamp_col(&ctx.in_schemas[0]);
         self.set_key_col(&ctx.in_schemas[0]);
+        self.set_topic_col(&ctx.in_schemas[0]);
+        self.set_headers_col(&ctx.in_schemas[0]);
 
         self.init_producer(&ctx.task_info)
             .expect("Producer creation failed");
@@ -287,6 +347,10 @@ impl ArrowOperator for KafkaSinkFunc {
             .downcast_ref::<arrow::array::TimestampNanosecondArray>();
 
         let keys = self.key_col.map(|i| batch.column(i).as_string::<i32>());
+        let topics = self.topic_col.map(|i| batch.column(i).as_string::<i32>());
+        let headers = self
+            .headers_col
+            .map(|i| HeadersColumn::new(batch.column(i)));
 
         for (i, v) in values.enumerate() {
             // kafka timestamp as unix millis
@@ -299,7 +363,9 @@ impl ArrowOperator for KafkaSinkFunc {
             });
             // TODO: this copy should be unnecessary but likely needs a custom trait impl
             let key = keys.map(|k| k.value(i).as_bytes().to_vec());
-            self.publish(timestamp, key, v, ctx).await;
+            let topic = topics.and_then(|t| t.is_valid(i).then(|| t.value(i)));
+            let headers = headers.as_ref().and_then(|h| h.headers(i));
+            self.publish(timestamp, key, topic, headers, v, ctx).await;
         }
     }
 
@@ -380,3 +446,112 @@ impl ArrowOperator for KafkaSinkFunc {
         }
     }
 }
+
+/// Whether a column of this type can be written as record headers: either a struct, whose
+/// field names become the header keys, or a map with TEXT keys
+fn is_headers_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Struct(fields) => fields.iter().all(|f| is_header_value_type(f.data_type())),
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(kv) if kv.len() == 2 => {
+                kv[0].data_type() == &DataType::Utf8 && is_header_value_type(kv[1].data_type())
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn is_header_value_type(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Binary | DataType::LargeBinary)
+        || can_cast_types(data_type, &DataType::Utf8)
+}
+
+/// Header values are written as their raw bytes if they're binary, or otherwise as their
+/// string representation
+fn header_values(array: &ArrayRef) -> BinaryArray {
+    let array = match array.data_type() {
+        DataType::Binary => array.clone(),
+        DataType::LargeBinary => cast(array, &DataType::Binary).unwrap(),
+        _ => cast(&cast(array, &DataType::Utf8).unwrap(), &DataType::Binary).unwrap(),
+    };
+
+    array.as_binary::<i32>().clone()
+}
+
+/// The headers column of a batch, with the header values converted to binary
+enum HeadersColumn {
+    Struct {
+        keys: Vec<String>,
+        values: Vec<BinaryArray>,
+        nulls: Option<NullBuffer>,
+    },
+    Map {
+        map: MapArray,
+        values: BinaryArray,
+    },
+}
+
+impl HeadersColumn {
+    fn new(array: &ArrayRef) -> Self {
+        match array.data_type() {
+            DataType::Struct(fields) => {
+                let array = array.as_struct();
+                Self::Struct {
+                    keys: fields.iter().map(|f| f.name().clone()).collect(),
+                    values: array.columns().iter().map(header_values).collect(),
+                    nulls: array.nulls().cloned(),
+                }
+            }
+            DataType::Map(..) => {
+                let map = array.as_map();
+                Self::Map {
+                    values: header_values(map.values()),
+                    map: map.clone(),
+                }
+            }
+            t => unreachable!("invalid type for headers column: {}", t),
+        }
+    }
+
+    fn headers(&self, row: usize) -> Option<OwnedHeaders> {
+        match self {
+            Self::Struct {
+                keys,
+                values,
+                nulls,
+            } => {
+                if nulls.as_ref().is_some_and(|n| n.is_null(row)) {
+                    return None;
+                }
+
+                let mut headers = OwnedHeaders::new_with_capacity(keys.len());
+                for (key, values) in keys.iter().zip(values) {
+                    headers = headers.insert(Header {
+                        key,
+                        value: values.is_valid(row).then(|| values.value(row)),
+                    });
+                }
+                Some(headers)
+            }
+            Self::Map { map, values } => {
+                if map.is_null(row) {
+                    return None;
+                }
+
+                let keys = map.keys().as_string::<i32>();
+                let offsets = map.value_offsets();
+                let (start, end) = (offsets[row] as usize, offsets[row + 1] as usize);
+
+                let mut headers = OwnedHeaders::new_with_capacity(end - start);
+                for i in start..end {
+                    headers = headers.insert(Header {
+                        key: keys.value(i),
+                        value: values.is_valid(i).then(|| values.value(i)),
+                    });
+                }
+                Some(headers)
+            }
+        }
+    }
+}
@@ -4,7 +4,11 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
-use arrow::array::{RecordBatch, UInt32Array};
+use arrow::array::{
+    Array, ArrayRef, Int32Array, MapBuilder, RecordBatch, StringArray, StringBuilder, StructArray,
+    UInt32Array,
+};
+use arrow::buffer::NullBuffer;
 use arrow::datatypes::Field;
 use arrow::datatypes::{DataType, Schema, SchemaRef};
 use arroyo_formats::ser::ArrowSerializer;
@@ -17,12 +21,13 @@ use arroyo_types::*;
 use itertools::Itertools;
 use rdkafka::admin::{AdminClient, AdminOptions, NewTopic};
 use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::message::{Headers, OwnedHeaders};
 use rdkafka::producer::Producer;
 use rdkafka::{ClientConfig, Message};
 use serde::Deserialize;
 use tokio::sync::mpsc::channel;
 
-use super::{ConsistencyMode, KafkaSinkFunc};
+use super::{is_headers_type, ConsistencyMode, HeadersColumn, KafkaSinkFunc};
 
 pub struct KafkaTopicTester {
     topic: String,
@@ -82,6 +87,10 @@ impl KafkaTopicTester {
             client_config: HashMap::new(),
             serializer: ArrowSerializer::new(Format::Json(JsonFormat::default())),
             key_col: None,
+            topic_field: None,
+            topic_col: None,
+            headers_field: None,
+            headers_col: None,
         };
 
         let (_, control_rx) = channel(128);
@@ -99,6 +108,7 @@ impl KafkaTopicTester {
             None,
             None,
             vec![vec![]],
+            vec![],
             HashMap::new(),
         )
         .await;
@@ -208,3 +218,67 @@ async fn test_kafka() {
         assert_eq!(message, result.value);
     }
 }
+
+fn header_values(headers: Option<OwnedHeaders>) -> Option<Vec<(String, Option<String>)>> {
+    headers.map(|headers| {
+        headers
+            .iter()
+            .map(|h| {
+                (
+                    h.key.to_string(),
+                    h.value.map(|v| String::from_utf8(v.to_vec()).unwrap()),
+                )
+            })
+            .collect()
+    })
+}
+
+#[test]
+fn test_headers_column() {
+    let tenants: ArrayRef = Arc::new(StringArray::from(vec![Some("a"), None, Some("c")]));
+    let attempts: ArrayRef = Arc::new(Int32Array::from(vec![1, 2, 3]));
+    let structs: ArrayRef = Arc::new(StructArray::new(
+        vec![
+            Field::new("tenant", DataType::Utf8, true),
+            Field::new("attempt", DataType::Int32, false),
+        ]
+        .into(),
+        vec![tenants, attempts],
+        Some(NullBuffer::from(vec![true, true, false])),
+    ));
+
+    assert!(is_headers_type(structs.data_type()));
+    let column = HeadersColumn::new(&structs);
+    assert_eq!(
+        header_values(column.headers(0)),
+        Some(vec![
+            ("tenant".to_string(), Some("a".to_string())),
+            ("attempt".to_string(), Some("1".to_string())),
+        ])
+    );
+    assert_eq!(
+        header_values(column.headers(1)),
+        Some(vec![
+            ("tenant".to_string(), None),
+            ("attempt".to_string(), Some("2".to_string())),
+        ])
+    );
+    assert_eq!(header_values(column.headers(2)), None);
+
+    let mut builder = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+    builder.keys().append_value("region");
+    builder.values().append_value("eu");
+    builder.append(true).unwrap();
+    builder.append(true).unwrap();
+    let map: ArrayRef = Arc::new(builder.finish());
+
+    assert!(is_headers_type(map.data_type()));
+    let column = HeadersColumn::new(&map);
+    assert_eq!(
+        header_values(column.headers(0)),
+        Some(vec![("region".to_string(), Some("eu".to_string()))])
+    );
+    assert_eq!(header_values(column.headers(1)), Some(vec![]));
+
+    assert!(!is_headers_type(&DataType::Utf8));
+}
@@ -12,6 +12,7 @@ use async_trait::async_trait;
 use bincode::{Decode, Encode};
 use governor::{Quota, RateLimiter as GovernorRateLimiter};
 use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::{BorrowedHeaders, Headers};
 use rdkafka::{ClientConfig, Message as KMessage, Offset, TopicPartitionList};
 use std::collections::HashMap;
 use std::num::NonZeroU32;
@@ -21,6 +22,8 @@ use tokio::select;
 use tokio::time::MissedTickBehavior;
 use tracing::{debug, error, info, warn};
 
+use super::SourceOffset;
+
 #[cfg(test)]
 mod test;
 
@@ -29,7 +32,9 @@ pub struct KafkaSourceFunc {
     pub bootstrap_servers: String,
     pub group_id: Option<String>,
     pub group_id_prefix: Option<String>,
-    pub offset_mode: super::SourceOffset,
+    pub offset_mode: SourceOffset,
+    pub start_timestamp: Option<i64>,
+    pub start_offsets: HashMap<i32, i64>,
     pub format: Format,
     pub framing: Option<Framing>,
     pub bad_data: Option<BadData>,
@@ -113,7 +118,7 @@ impl KafkaSourceFunc {
                                 // new, and we want to start from the beginning so we don't drop data
                                 Offset::Beginning
                             } else {
-                                self.offset_mode.get_offset()
+                                self.initial_offset(p.id())
                             }
                         });
 
@@ -127,13 +132,43 @@ impl KafkaSourceFunc {
             self.topic, ctx.task_info.task_index, our_partitions
         );
 
-        let topic_partitions = TopicPartitionList::from_topic_map(&our_partitions)?;
+        let mut topic_partitions = TopicPartitionList::from_topic_map(&our_partitions)?;
+
+        if !has_state
+            && matches!(self.offset_mode, SourceOffset::Timestamp)
+            && topic_partitions.count() > 0
+        {
+            topic_partitions =
+                consumer.offsets_for_times(topic_partitions, Duration::from_secs(30))?;
+            info!(
+                "resolved start timestamp for {}-{} to offsets {:?}",
+                self.topic, ctx.task_info.task_index, topic_partitions
+            );
+        }
 
         consumer.assign(&topic_partitions)?;
 
         Ok(consumer)
     }
 
+    /// The offset to start reading a partition from when there is no checkpointed state for it
+    fn initial_offset(&self, partition: i32) -> Offset {
+        match self.offset_mode {
+            SourceOffset::Explicit => self
+                .start_offsets
+                .get(&partition)
+                .map(|offset| Offset::Offset(*offset))
+                .unwrap_or(Offset::Beginning),
+            // this is a timestamp rather than an offset; it's resolved to the offset of the first
+            // message at or after it by offsets_for_times once the partitions are known
+            SourceOffset::Timestamp => Offset::Offset(
+                self.start_timestamp
+                    .expect("start_timestamp must be set for timestamp offsets"),
+            ),
+            mode => mode.get_offset(),
+        }
+    }
+
     async fn run_int(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
         let consumer = self
             .get_consumer(ctx)
@@ -180,21 +215,25 @@ impl KafkaSourceFunc {
                                     .ok_or_else(|| UserError::new("Failed to read timestamp from Kafka record",
                                         "The message read from Kafka did not contain a message timestamp"))?;
 
+                                let headers = self.metadata_fields.as_ref()
+                                    .filter(|fields| fields.values().any(|v| v == "headers"))
+                                    .map(|_| headers_to_json(msg.headers()));
+
                                 let connector_metadata = if let Some(metadata_fields) = &self.metadata_fields {
                                     let mut connector_metadata = HashMap::new();
                                     for (key, value) in metadata_fields {
-                                        match value.as_str() {
-                                            "offset_id" => {
-                                                connector_metadata.insert(key, FieldValueType::Int64(msg.offset()));
-                                            }
-                                            "partition" => {
-                                                connector_metadata.insert(key, FieldValueType::Int32(msg.partition()));
-                                            }
-                                            "topic" => {
-                                                connector_metadata.insert(key, FieldValueType::String(&self.topic));
-                                            }
-                                            _ => {}
-                                        }
+                                        let value = match value.as_str() {
+                                            "offset_id" => FieldValueType::Int64(msg.offset()),
+                                            "partition" => FieldValueType::Int32(msg.partition()),
+                                            "topic" => FieldValueType::String(&self.topic),
+                                            "key" => FieldValueType::Bytes(msg.key()),
+                                            "key_text" => FieldValueType::OptionalString(
+                                                msg.key().and_then(|k| std::str::from_utf8(k).ok())),
+                                            "headers" => FieldValueType::OptionalString(headers.as_deref()),
+                                            "timestamp" => FieldValueType::Timestamp(Some(from_millis(timestamp as u64))),
+                                            _ => continue,
+                                        };
+                                        connector_metadata.insert(key, value);
                                     }
                                     Some(connector_metadata)
                                 } else {
@@ -202,7 +241,12 @@ impl KafkaSourceFunc {
                                 };
 
 
-                                ctx.deserialize_slice(v, from_millis(timestamp as u64), connector_metadata).await?;
+                                let position = [
+                                    ("topic", FieldValueType::String(&self.topic)),
+                                    ("partition", FieldValueType::Int32(msg.partition())),
+                                    ("offset", FieldValueType::Int64(msg.offset())),
+                                ];
+                                ctx.deserialize_slice_at(v, from_millis(timestamp as u64), connector_metadata, &position).await?;
 
 
                                 if ctx.should_flush() {
@@ -306,3 +350,24 @@ impl SourceOperator for KafkaSourceFunc {
         arroyo_state::global_table_config("k", "kafka offsets")
     }
 }
+
+/// Encodes the headers of a message as a JSON object of header names to values, for the
+/// `headers` metadata column (see `KafkaConnector::metadata_defs`); values that are not valid
+/// UTF-8 are replaced lossily, and headers without values are mapped to null
+fn headers_to_json(headers: Option<&BorrowedHeaders>) -> String {
+    let headers: serde_json::Map<String, serde_json::Value> = headers
+        .into_iter()
+        .flat_map(|headers| headers.iter())
+        .map(|header| {
+            (
+                header.key.to_string(),
+                header
+                    .value
+                    .map(|v| String::from_utf8_lossy(v).into())
+                    .unwrap_or(serde_json::Value::Null),
+            )
+        })
+        .collect();
+
+    serde_json::Value::Object(headers).to_string()
+}
@@ -23,12 +23,13 @@ use arroyo_types::{
     single_item_hash_map, to_micros, ArrowMessage, CheckpointBarrier, SignalMessage, TaskInfo,
 };
 use rdkafka::admin::{AdminClient, AdminOptions, NewTopic};
+use rdkafka::message::{Header, OwnedHeaders};
 use rdkafka::producer::{BaseProducer, BaseRecord};
 use rdkafka::ClientConfig;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
-use super::KafkaSourceFunc;
+use super::{headers_to_json, KafkaSourceFunc};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct TestData {
@@ -81,6 +82,8 @@ impl KafkaTopicTester {
             group_id: self.group_id.clone(),
             group_id_prefix: None,
             offset_mode: SourceOffset::Earliest,
+            start_timestamp: None,
+            start_offsets: HashMap::new(),
             format: Format::RawString(RawStringFormat {}),
             framing: None,
             bad_data: None,
@@ -123,6 +126,7 @@ impl KafkaTopicTester {
             )),
             None,
             vec![vec![data_tx]],
+            vec![],
             kafka.tables(),
         )
         .await;
@@ -370,6 +374,8 @@ async fn test_kafka_with_metadata_fields() {
         group_id: kafka_topic_tester.group_id.clone(),
         group_id_prefix: None,
         offset_mode: SourceOffset::Earliest,
+        start_timestamp: None,
+        start_offsets: HashMap::new(),
         format: Format::RawString(RawStringFormat {}),
         framing: None,
         bad_data: None,
@@ -406,6 +412,7 @@ async fn test_kafka_with_metadata_fields() {
         )),
         None,
         vec![vec![data_tx]],
+        vec![],
         kafka.tables(),
     )
     .await;
@@ -441,3 +448,22 @@ async fn test_kafka_with_metadata_fields() {
         .await
         .unwrap();
 }
+
+#[test]
+fn test_headers_to_json() {
+    let headers = OwnedHeaders::new()
+        .insert(Header {
+            key: "region",
+            value: Some("eu"),
+        })
+        .insert(Header {
+            key: "trace",
+            value: None::<&[u8]>,
+        });
+
+    let json: serde_json::Value =
+        serde_json::from_str(&headers_to_json(Some(headers.as_borrowed()))).unwrap();
+    assert_eq!(json, serde_json::json!({"region": "eu", "trace": null}));
+
+    assert_eq!(headers_to_json(None), "{}");
+}
@@ -1,10 +1,10 @@
 use anyhow::{anyhow, bail, Result};
 use arrow::datatypes::DataType;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use typify::import_types;
 
 use arroyo_formats::ser::ArrowSerializer;
-use arroyo_operator::connector::Connection;
+use arroyo_operator::connector::{Connection, MetadataDef};
 use arroyo_rpc::api_types::connections::{ConnectionProfile, TestSourceMessage};
 use arroyo_rpc::{api_types, OperatorConfig};
 use serde::{Deserialize, Serialize};
@@ -52,6 +52,19 @@ impl Connector for KinesisConnector {
         }
     }
 
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        &[
+            MetadataDef {
+                name: "sequence_number",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "partition_key",
+                data_type: DataType::Utf8,
+            },
+        ]
+    }
+
     fn test(
         &self,
         _: &str,
@@ -84,7 +97,7 @@ impl Connector for KinesisConnector {
         config: Self::ProfileT,
         table: Self::TableT,
         schema: Option<&ConnectionSchema>,
-        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+        metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<arroyo_operator::connector::Connection> {
         let (connection_type, description) = match table.type_ {
             TableType::Source { .. } => (
@@ -106,6 +119,13 @@ impl Connector for KinesisConnector {
             .map(|format| format.to_owned())
             .ok_or_else(|| anyhow!("'format' must be set for kinesis connections"))?;
 
+        let metadata_fields = metadata_fields.map(|fields| {
+            fields
+                .into_iter()
+                .map(|(k, (v, _))| (k, v))
+                .collect::<HashMap<String, String>>()
+        });
+
         let config = OperatorConfig {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
@@ -113,7 +133,7 @@ impl Connector for KinesisConnector {
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
-            additional_fields: None,
+            additional_fields: metadata_fields,
         };
 
         Ok(Connection {
@@ -133,7 +153,7 @@ impl Connector for KinesisConnector {
         options: &mut HashMap<String, String>,
         schema: Option<&ConnectionSchema>,
         _profile: Option<&ConnectionProfile>,
-        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+        metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
         let typ = pull_opt("type", options)?;
         let table_type = match typ.as_str() {
@@ -145,6 +165,7 @@ impl Connector for KinesisConnector {
                         None | Some("latest") => SourceOffset::Latest,
                         Some(other) => bail!("invalid value for source.offset '{}'", other),
                     },
+                    consumer_name: options.remove("source.consumer_name"),
                 }
             }
             "sink" => {
@@ -170,7 +191,15 @@ impl Connector for KinesisConnector {
             aws_region: options.remove("aws_region").map(|s| s.to_string()),
         };
 
-        Self::from_config(self, None, name, EmptyConfig {}, table, schema, None)
+        Self::from_config(
+            self,
+            None,
+            name,
+            EmptyConfig {},
+            table,
+            schema,
+            metadata_fields,
+        )
     }
 
     fn make_operator(
@@ -180,20 +209,27 @@ impl Connector for KinesisConnector {
         config: OperatorConfig,
     ) -> Result<OperatorNode> {
         match table.type_ {
-            TableType::Source { offset } => {
-                Ok(OperatorNode::from_source(Box::new(KinesisSourceFunc {
-                    stream_name: table.stream_name,
-                    kinesis_client: None,
-                    aws_region: table.aws_region,
-                    offset,
-                    shards: HashMap::new(),
-                    format: config
-                        .format
-                        .ok_or_else(|| anyhow!("format required for kinesis source"))?,
-                    framing: config.framing,
-                    bad_data: config.bad_data,
-                })))
-            }
+            TableType::Source {
+                offset,
+                consumer_name,
+            } => Ok(OperatorNode::from_source(Box::new(KinesisSourceFunc {
+                stream_name: table.stream_name,
+                kinesis_client: None,
+                aws_region: table.aws_region,
+                offset,
+                shards: HashMap::new(),
+                consumer_name,
+                consumer_arn: None,
+                parents: HashMap::new(),
+                groups: HashMap::new(),
+                waiting_shards: HashSet::new(),
+                format: config
+                    .format
+                    .ok_or_else(|| anyhow!("format required for kinesis source"))?,
+                framing: config.framing,
+                bad_data: config.bad_data,
+                metadata_fields: config.additional_fields,
+            }))),
             TableType::Sink {
                 batch_flush_interval_millis,
                 batch_max_buffer_size,
@@ -209,10 +245,11 @@ impl Connector for KinesisConnector {
                     in_progress_batch: None,
                     aws_region: table.aws_region,
                     name: table.stream_name,
-                    serializer: ArrowSerializer::new(
+                    serializer: ArrowSerializer::with_framing(
                         config
                             .format
                             .ok_or_else(|| anyhow!("Format must be defined for KinesisSink"))?,
+                        config.framing,
                     ),
                     flush_config,
                 })))
@@ -1,5 +1,6 @@
 use std::{
-    collections::{hash_map::DefaultHasher, HashMap},
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
     fmt::Debug,
     hash::{Hash, Hasher},
     pin::Pin,
@@ -7,6 +8,7 @@ use std::{
 };
 
 use anyhow::{anyhow, bail, Context as AnyhowContext, Result};
+use arroyo_formats::de::FieldValueType;
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
@@ -21,7 +23,11 @@ use aws_config::{from_env, Region};
 use aws_sdk_kinesis::error::SdkError;
 use aws_sdk_kinesis::operation::get_records::GetRecordsOutput;
 use aws_sdk_kinesis::operation::get_shard_iterator::builders::GetShardIteratorFluentBuilder;
-use aws_sdk_kinesis::types::{Shard, ShardIteratorType};
+use aws_sdk_kinesis::primitives::event_stream::EventReceiver;
+use aws_sdk_kinesis::types::error::SubscribeToShardEventStreamError;
+use aws_sdk_kinesis::types::{
+    ConsumerStatus, Record, Shard, ShardIteratorType, StartingPosition, SubscribeToShardEventStream,
+};
 use aws_sdk_kinesis::Client as KinesisClient;
 use bincode::{Decode, Encode};
 use futures::{stream::FuturesUnordered, Future, StreamExt};
@@ -39,6 +45,16 @@ pub enum KinesisOffset {
     Latest,
     SequenceNumber(String),
     Timestamp(SystemTime),
+    AfterSequenceNumber(String),
+}
+
+impl From<SourceOffset> for KinesisOffset {
+    fn from(offset: SourceOffset) -> Self {
+        match offset {
+            SourceOffset::Earliest => KinesisOffset::Earliest,
+            SourceOffset::Latest => KinesisOffset::Latest,
+        }
+    }
 }
 
 pub struct KinesisSourceFunc {
@@ -50,46 +66,108 @@ pub struct KinesisSourceFunc {
     pub aws_region: Option<String>,
     pub shards: HashMap<String, ShardState>,
     pub offset: SourceOffset,
+    pub metadata_fields: Option<HashMap<String, String>>,
+    /// If set, shards are read through this enhanced fan-out consumer rather than by polling
+    pub consumer_name: Option<String>,
+    pub consumer_arn: Option<String>,
+    /// The parents of every shard we've seen, whether or not it's read by this subtask
+    pub parents: HashMap<String, Vec<String>>,
+    /// The root of every shard's group (see [`group_roots`]), which determines its subtask
+    pub groups: HashMap<String, String>,
+    /// Shards assigned to this subtask that are waiting for their parents to be fully read
+    pub waiting_shards: HashSet<String>,
 }
 
+/// The table holding the state of each shard
+const SHARDS_TABLE: &str = "s";
+/// The table shard state was kept in before shards tracked their parents, which is still read
+/// so that checkpoints taken by earlier versions can be restored
+const LEGACY_SHARDS_TABLE: &str = "k";
+
 #[derive(Clone, Debug, Encode, Decode, PartialEq, PartialOrd)]
 pub struct ShardState {
     stream_name: String,
     shard_id: String,
     offset: KinesisOffset,
     closed: bool,
+    /// The shards this one was split or merged from, which are read to the end before it
+    parent_shard_ids: Vec<String>,
 }
 
-impl TryFrom<(String, Shard, SourceOffset)> for ShardState {
-    type Error = anyhow::Error;
-    fn try_from(
-        (stream_name, shard, source_offset): (String, Shard, SourceOffset),
-    ) -> Result<Self> {
-        let shard_id = shard.shard_id().to_string();
-        let offset = match source_offset {
-            SourceOffset::Earliest => KinesisOffset::Earliest,
-            SourceOffset::Latest => KinesisOffset::Latest,
-        };
-        Ok(Self {
-            stream_name,
-            shard_id,
-            offset,
-            closed: false,
-        })
+/// Shard state as stored in the legacy table
+#[derive(Clone, Debug, Encode, Decode, PartialEq, PartialOrd)]
+pub struct LegacyShardState {
+    stream_name: String,
+    shard_id: String,
+    offset: KinesisOffset,
+    closed: bool,
+}
+
+impl From<LegacyShardState> for ShardState {
+    fn from(state: LegacyShardState) -> Self {
+        Self {
+            stream_name: state.stream_name,
+            shard_id: state.shard_id,
+            offset: state.offset,
+            closed: state.closed,
+            parent_shard_ids: vec![],
+        }
     }
 }
+
 type BoxedFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
 
+fn parent_shard_ids(shard: &Shard) -> Vec<String> {
+    shard
+        .parent_shard_id()
+        .into_iter()
+        .chain(shard.adjacent_parent_shard_id())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Groups shards that are connected through splits and merges, returning the root of each
+/// shard's group, which is the smallest shard id in it. All the shards in a group are read by the
+/// same subtask, so that the parents of a shard (including both parents of a merge) are read to
+/// the end before it. Parents that are no longer listed still connect their children.
+fn group_roots(parents: &HashMap<String, Vec<String>>) -> HashMap<String, String> {
+    // maps each shard that isn't the root of its group to a smaller shard in the same group
+    let mut smaller: HashMap<&str, &str> = HashMap::new();
+
+    fn find<'a>(smaller: &HashMap<&'a str, &'a str>, shard_id: &'a str) -> &'a str {
+        let mut current = shard_id;
+        while let Some(next) = smaller.get(current) {
+            current = next;
+        }
+        current
+    }
+
+    for (shard_id, shard_parents) in parents {
+        for parent in shard_parents {
+            let a = find(&smaller, shard_id);
+            let b = find(&smaller, parent);
+            match a.cmp(b) {
+                Ordering::Less => smaller.insert(b, a),
+                Ordering::Greater => smaller.insert(a, b),
+                Ordering::Equal => None,
+            };
+        }
+    }
+
+    parents
+        .keys()
+        .map(|shard_id| (shard_id.clone(), find(&smaller, shard_id).to_string()))
+        .collect()
+}
+
 impl ShardState {
-    fn new(stream_name: String, shard: Shard, source_offset: SourceOffset) -> Self {
+    fn new(stream_name: String, shard: &Shard, offset: KinesisOffset) -> Self {
         Self {
             stream_name,
             shard_id: shard.shard_id().to_string(),
-            offset: match source_offset {
-                SourceOffset::Earliest => KinesisOffset::Earliest,
-                SourceOffset::Latest => KinesisOffset::Latest,
-            },
+            offset,
             closed: false,
+            parent_shard_ids: parent_shard_ids(shard),
         }
     }
     fn get_update_shard_iterator_future(
@@ -111,6 +189,9 @@ impl ShardState {
             KinesisOffset::SequenceNumber(sequence_number) => shard_iterator_call
                 .shard_iterator_type(ShardIteratorType::AtSequenceNumber)
                 .starting_sequence_number(sequence_number.clone()),
+            KinesisOffset::AfterSequenceNumber(sequence_number) => shard_iterator_call
+                .shard_iterator_type(ShardIteratorType::AfterSequenceNumber)
+                .starting_sequence_number(sequence_number.clone()),
             KinesisOffset::Timestamp(timestamp) => shard_iterator_call
                 .shard_iterator_type(ShardIteratorType::AtTimestamp)
                 .timestamp((*timestamp).into()),
@@ -127,8 +208,77 @@ impl ShardState {
             ))
         }))
     }
+
+    /// Subscribes to the shard through an enhanced fan-out consumer, starting from the shard's
+    /// current offset
+    fn get_subscribe_future(
+        &self,
+        kinesis_client: &KinesisClient,
+        consumer_arn: &str,
+    ) -> BoxedFuture<AsyncNamedResult<AsyncResult>> {
+        let starting_position = match &self.offset {
+            KinesisOffset::Earliest => {
+                StartingPosition::builder().r#type(ShardIteratorType::TrimHorizon)
+            }
+            KinesisOffset::Latest => StartingPosition::builder().r#type(ShardIteratorType::Latest),
+            KinesisOffset::SequenceNumber(sequence_number) => StartingPosition::builder()
+                .r#type(ShardIteratorType::AtSequenceNumber)
+                .sequence_number(sequence_number.clone()),
+            KinesisOffset::AfterSequenceNumber(sequence_number) => StartingPosition::builder()
+                .r#type(ShardIteratorType::AfterSequenceNumber)
+                .sequence_number(sequence_number.clone()),
+            KinesisOffset::Timestamp(timestamp) => StartingPosition::builder()
+                .r#type(ShardIteratorType::AtTimestamp)
+                .timestamp((*timestamp).into()),
+        };
+
+        let subscribe_call = kinesis_client
+            .subscribe_to_shard()
+            .consumer_arn(consumer_arn)
+            .shard_id(&self.shard_id);
+        let shard_id = self.shard_id.clone();
+        Box::pin(AsyncNamedResult::wrap_future(shard_id, async move {
+            let subscribe_call = subscribe_call.starting_position(starting_position.build()?);
+            let mut retries = 0;
+            loop {
+                match subscribe_call.clone().send().await {
+                    Ok(output) => return Ok(AsyncResult::Subscribed(output.event_stream)),
+                    // the previous subscription to the shard may not have been closed yet, or we
+                    // may have hit the limit on subscribe calls
+                    Err(SdkError::ServiceError(e))
+                        if (e.err().is_resource_in_use_exception()
+                            || e.err().is_limit_exceeded_exception())
+                            && retries < 10 =>
+                    {
+                        retries += 1;
+                        tokio::time::sleep(Duration::from_millis(200 * (1 << retries.min(5))))
+                            .await;
+                    }
+                    Err(error) => {
+                        return Err(anyhow!(error).context("failed to subscribe to shard"));
+                    }
+                }
+            }
+        }))
+    }
+}
+
+fn next_event_future(
+    shard_id: String,
+    mut events: ShardEventReceiver,
+) -> BoxedFuture<AsyncNamedResult<AsyncResult>> {
+    Box::pin(AsyncNamedResult::wrap_future(shard_id, async move {
+        let event = events
+            .recv()
+            .await
+            .context("failed to read from shard subscription")?;
+        Ok(AsyncResult::SubscriptionEvent(events, event))
+    }))
 }
 
+type ShardEventReceiver =
+    EventReceiver<SubscribeToShardEventStream, SubscribeToShardEventStreamError>;
+
 struct AsyncNamedResult<T: Debug> {
     name: String,
     result: Result<T>,
@@ -152,6 +302,9 @@ enum AsyncResult {
     ShardIteratorIdUpdate(Option<String>),
     GetRecords(GetRecordsOutput),
     NeedNewIterator,
+    Subscribed(ShardEventReceiver),
+    // the next event from a subscription, or None if the subscription has expired
+    SubscriptionEvent(ShardEventReceiver, Option<SubscribeToShardEventStream>),
 }
 
 #[async_trait]
@@ -161,7 +314,12 @@ impl SourceOperator for KinesisSourceFunc {
     }
 
     fn tables(&self) -> HashMap<String, TableConfig> {
-        global_table_config("k", "kinesis source state")
+        let mut tables = global_table_config(SHARDS_TABLE, "kinesis source state");
+        tables.extend(global_table_config(
+            LEGACY_SHARDS_TABLE,
+            "legacy kinesis source state",
+        ));
+        tables
     }
 
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
@@ -184,39 +342,145 @@ impl SourceOperator for KinesisSourceFunc {
 }
 
 impl KinesisSourceFunc {
-    /// Initializes the shards for the operator. First shards are read out of state,
-    /// then `sync_shards()` is called to find any new shards.
-    /// It returns a future for each shard to fetch the next shard iterator id.
+    /// Initializes the shards for the operator. The state of every shard is read out of the
+    /// checkpoint and combined with the currently listed shards to group them, and the shards in
+    /// the groups assigned to this subtask are read. It returns a future for each shard that is
+    /// ready to be read.
     async fn init_shards(
         &mut self,
         ctx: &mut ArrowContext,
     ) -> anyhow::Result<Vec<BoxedFuture<AsyncNamedResult<AsyncResult>>>> {
-        let mut futures = Vec::new();
+        let legacy: &mut GlobalKeyedView<String, LegacyShardState> = ctx
+            .table_manager
+            .get_global_keyed_state(LEGACY_SHARDS_TABLE)
+            .await
+            .expect("failed to get legacy state for kinesis source");
+        let mut restored: Vec<ShardState> =
+            legacy.get_all().values().cloned().map(Into::into).collect();
+
         let s: &mut GlobalKeyedView<String, ShardState> = ctx
             .table_manager
-            .get_global_keyed_state("k")
+            .get_global_keyed_state(SHARDS_TABLE)
             .await
             .expect("failed to get state for kinesis source");
-        for (shard_id, shard_state) in s
-            .get_all()
-            .values()
-            .map(|shard_state| (shard_state.shard_id.clone(), shard_state.clone()))
-            .filter(|(shard_id, _shard_state)| {
-                let mut hasher = DefaultHasher::new();
-                shard_id.hash(&mut hasher);
-                let shard_hash = hasher.finish() as usize;
-                shard_hash % ctx.task_info.parallelism == ctx.task_info.task_index
-            })
-        {
-            futures.push(
-                shard_state.get_update_shard_iterator_future(self.kinesis_client.as_ref().unwrap()),
+        restored.extend(s.get_all().values().cloned());
+
+        for shard_state in &restored {
+            self.parents.insert(
+                shard_state.shard_id.clone(),
+                shard_state.parent_shard_ids.clone(),
             );
+        }
+
+        // shards that exist when we first start are read from the configured offset; any that
+        // appear after a checkpoint are children of a reshard and must be read from the beginning
+        let offset = if restored.is_empty() {
+            self.offset.into()
+        } else {
+            KinesisOffset::Earliest
+        };
+
+        let listed = self.list_shards().await?;
+        let new_shards = self.regroup(&listed);
+
+        for shard_state in restored {
+            if !self.owns_shard(&shard_state.shard_id, ctx) {
+                continue;
+            }
+
+            if !shard_state.closed {
+                self.waiting_shards.insert(shard_state.shard_id.clone());
+            }
+            self.shards
+                .insert(shard_state.shard_id.clone(), shard_state);
+        }
+
+        self.add_shards(&listed, new_shards, offset, ctx);
+        Ok(self.start_ready_shards())
+    }
+
+    /// Shards are assigned to subtasks by the root of their group, so that they're read by the
+    /// same subtask as their parents
+    fn owns_group(root: &str, ctx: &ArrowContext) -> bool {
+        let mut hasher = DefaultHasher::new();
+        root.hash(&mut hasher);
+        let shard_hash = hasher.finish() as usize;
+        shard_hash % ctx.task_info.parallelism == ctx.task_info.task_index
+    }
+
+    fn owns_shard(&self, shard_id: &str, ctx: &ArrowContext) -> bool {
+        Self::owns_group(&self.groups[shard_id], ctx)
+    }
+
+    /// Records the parents of any shards we haven't seen before and regroups all shards,
+    /// returning the ids of the new shards
+    fn regroup(&mut self, listed: &HashMap<String, Shard>) -> Vec<String> {
+        let new_shards: Vec<String> = listed
+            .values()
+            .filter(|shard| !self.parents.contains_key(shard.shard_id()))
+            .map(|shard| shard.shard_id().to_string())
+            .collect();
+
+        for shard_id in &new_shards {
+            self.parents
+                .insert(shard_id.clone(), parent_shard_ids(&listed[shard_id]));
+        }
+
+        self.groups = group_roots(&self.parents);
+        new_shards
+    }
+
+    /// Adds the new shards assigned to this subtask, which wait to be read until their parents
+    /// have been
+    fn add_shards(
+        &mut self,
+        listed: &HashMap<String, Shard>,
+        new_shards: Vec<String>,
+        offset: KinesisOffset,
+        ctx: &ArrowContext,
+    ) {
+        for shard_id in new_shards {
+            if !self.owns_shard(&shard_id, ctx) {
+                continue;
+            }
+
+            let shard_state =
+                ShardState::new(self.stream_name.clone(), &listed[&shard_id], offset.clone());
+            self.waiting_shards.insert(shard_id.clone());
             self.shards.insert(shard_id, shard_state);
         }
-        let new_futures = self.sync_shards(ctx).await?;
-        futures.extend(new_futures.into_iter());
+    }
+
+    /// Starts reading any waiting shards whose parents have been fully read. As parents are read
+    /// by the same subtask as their children, parents that aren't in our state have expired.
+    fn start_ready_shards(&mut self) -> Vec<BoxedFuture<AsyncNamedResult<AsyncResult>>> {
+        let ready: Vec<String> = self
+            .waiting_shards
+            .iter()
+            .filter(|shard_id| {
+                self.shards[*shard_id]
+                    .parent_shard_ids
+                    .iter()
+                    .all(|parent| self.shards.get(parent).map(|p| p.closed).unwrap_or(true))
+            })
+            .cloned()
+            .collect();
 
-        Ok(futures)
+        ready
+            .into_iter()
+            .map(|shard_id| {
+                self.waiting_shards.remove(&shard_id);
+                debug!("starting to read shard {}", shard_id);
+                let shard_state = &self.shards[&shard_id];
+                let kinesis_client = self.kinesis_client.as_ref().unwrap();
+                match &self.consumer_arn {
+                    Some(consumer_arn) => {
+                        shard_state.get_subscribe_future(kinesis_client, consumer_arn)
+                    }
+                    None => shard_state.get_update_shard_iterator_future(kinesis_client),
+                }
+            })
+            .collect()
     }
 
     async fn handle_async_result_split(
@@ -234,6 +498,11 @@ impl KinesisSourceFunc {
                 self.handle_get_records(shard_id, get_records, ctx).await
             }
             AsyncResult::NeedNewIterator => self.handle_need_new_iterator(shard_id).await,
+            AsyncResult::Subscribed(events) => Ok(Some(next_event_future(shard_id, events))),
+            AsyncResult::SubscriptionEvent(events, event) => {
+                self.handle_subscription_event(shard_id, events, event, ctx)
+                    .await
+            }
         }
     }
 
@@ -311,14 +580,15 @@ impl KinesisSourceFunc {
             .last()
             .map(|record| record.sequence_number().to_owned());
 
-        let next_shard_iterator = self.process_records(get_records, ctx).await?;
+        self.process_records(&shard_id, get_records.records, ctx)
+            .await?;
         let shard_state = self.shards.get_mut(&shard_id).unwrap();
 
         if let Some(last_sequence_number) = last_sequence_number {
-            shard_state.offset = KinesisOffset::SequenceNumber(last_sequence_number.to_string());
+            shard_state.offset = KinesisOffset::AfterSequenceNumber(last_sequence_number);
         }
 
-        match next_shard_iterator {
+        match get_records.next_shard_iterator {
             Some(shard_iterator_id) => Ok(Some(self.next_read_future(shard_id, shard_iterator_id))),
             None => {
                 shard_state.closed = true;
@@ -336,6 +606,46 @@ impl KinesisSourceFunc {
         )))
     }
 
+    async fn handle_subscription_event(
+        &mut self,
+        shard_id: String,
+        events: ShardEventReceiver,
+        event: Option<SubscribeToShardEventStream>,
+        ctx: &mut ArrowContext,
+    ) -> Result<Option<BoxedFuture<AsyncNamedResult<AsyncResult>>>, UserError> {
+        let event = match event {
+            Some(SubscribeToShardEventStream::SubscribeToShardEvent(event)) => event,
+            Some(other) => {
+                debug!("ignoring unknown event on shard {}: {:?}", shard_id, other);
+                return Ok(Some(next_event_future(shard_id, events)));
+            }
+            None => {
+                // subscriptions expire after five minutes, so we resubscribe from where we left off
+                let shard_state = &self.shards[&shard_id];
+                return Ok(Some(shard_state.get_subscribe_future(
+                    self.kinesis_client.as_ref().unwrap(),
+                    self.consumer_arn.as_ref().unwrap(),
+                )));
+            }
+        };
+
+        let continuation = event.continuation_sequence_number().to_string();
+        let shard_closed = continuation.is_empty() || !event.child_shards().is_empty();
+        self.process_records(&shard_id, event.records, ctx).await?;
+
+        let shard_state = self.shards.get_mut(&shard_id).unwrap();
+        if !continuation.is_empty() {
+            shard_state.offset = KinesisOffset::AfterSequenceNumber(continuation);
+        }
+
+        if shard_closed {
+            shard_state.closed = true;
+            Ok(None)
+        } else {
+            Ok(Some(next_event_future(shard_id, events)))
+        }
+    }
+
     async fn init_client(&mut self) {
         let mut loader = from_env();
         if let Some(region) = &self.aws_region {
@@ -344,6 +654,74 @@ impl KinesisSourceFunc {
         self.kinesis_client = Some(KinesisClient::new(&loader.load().await));
     }
 
+    /// Registers the enhanced fan-out consumer for the stream, if it doesn't already exist, and
+    /// waits for it to become active
+    async fn init_consumer(&mut self, consumer_name: &str) -> Result<String> {
+        let kinesis_client = self.kinesis_client.as_ref().unwrap();
+        let stream_arn = kinesis_client
+            .describe_stream_summary()
+            .stream_name(&self.stream_name)
+            .send()
+            .await
+            .context("failed to describe stream")?
+            .stream_description_summary()
+            .ok_or_else(|| anyhow!("no description returned for stream {}", self.stream_name))?
+            .stream_arn()
+            .to_string();
+
+        let mut retries = 0;
+        loop {
+            match kinesis_client
+                .register_stream_consumer()
+                .stream_arn(&stream_arn)
+                .consumer_name(consumer_name)
+                .send()
+                .await
+            {
+                Ok(_) => {
+                    info!("registered kinesis consumer {}", consumer_name);
+                    break;
+                }
+                // the consumer has already been registered, by another subtask or a previous run
+                Err(SdkError::ServiceError(e)) if e.err().is_resource_in_use_exception() => {
+                    break;
+                }
+                Err(SdkError::ServiceError(e))
+                    if e.err().is_limit_exceeded_exception() && retries < 10 =>
+                {
+                    retries += 1;
+                    tokio::time::sleep(Duration::from_millis(200 * (1 << retries.min(5)))).await;
+                }
+                Err(error) => {
+                    return Err(anyhow!(error).context("failed to register stream consumer"));
+                }
+            }
+        }
+
+        for _ in 0..60 {
+            let description = kinesis_client
+                .describe_stream_consumer()
+                .stream_arn(&stream_arn)
+                .consumer_name(consumer_name)
+                .send()
+                .await
+                .context("failed to describe stream consumer")?;
+
+            if let Some(consumer) = description.consumer_description() {
+                if *consumer.consumer_status() == ConsumerStatus::Active {
+                    return Ok(consumer.consumer_arn().to_string());
+                }
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        bail!(
+            "kinesis consumer {} did not become active in time",
+            consumer_name
+        );
+    }
+
     /// Runs the Kinesis source, handling incoming records and control messages.
     ///
     /// This method initializes the Kinesis client, initializes the shards, and enters a loop to handle incoming
@@ -353,6 +731,12 @@ impl KinesisSourceFunc {
     /// * Polling off of the control queue, to perform checkpointing and stop the operator.
     async fn run_int(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
         self.init_client().await;
+        if let Some(consumer_name) = self.consumer_name.clone() {
+            let consumer_arn = self.init_consumer(&consumer_name).await.map_err(|e| {
+                UserError::new("failed to initialize kinesis consumer", format!("{:#}", e))
+            })?;
+            self.consumer_arn = Some(consumer_arn);
+        }
         let starting_futures = self
             .init_shards(ctx)
             .await
@@ -371,18 +755,21 @@ impl KinesisSourceFunc {
                         result.result.map_err(|e| UserError::new("Fatal Kinesis error", e.to_string()))?, ctx).await? {
                             futures.push(future);
                         }
+                    if !self.waiting_shards.is_empty() {
+                        futures.extend(self.start_ready_shards());
+                    }
                 },
                 _ = shard_poll_interval.tick() => {
                     if ctx.should_flush() {
                         ctx.flush_buffer().await?;
                     }
-                    match self.sync_shards(ctx).await {
+                    match self.list_shards().await {
                         Err(err) => {
                             warn!("failed to sync shards: {}", err);
                             ctx.report_error("failed to sync shards".to_string(), err.to_string()).await;
                         },
-                        Ok(new_futures) => {
-                            futures.extend(new_futures.into_iter());
+                        Ok(listed) => {
+                            futures.extend(self.sync_shards(listed, ctx)?.into_iter());
                         }
                      }
                 }
@@ -390,7 +777,7 @@ impl KinesisSourceFunc {
                     match control_message {
                         Some(ControlMessage::Checkpoint(c)) => {
                             debug!("starting checkpointing {}", ctx.task_info.task_index);
-                            let s = ctx.table_manager.get_global_keyed_state("k").await.unwrap();
+                            let s = ctx.table_manager.get_global_keyed_state(SHARDS_TABLE).await.unwrap();
                             for (shard_id, shard_state) in &self.shards {
                                 s.insert(shard_id.clone(), shard_state.clone()).await;
                             }
@@ -427,48 +814,98 @@ impl KinesisSourceFunc {
 
     async fn process_records(
         &mut self,
-        get_records_output: GetRecordsOutput,
+        shard_id: &str,
+        records: Vec<Record>,
         ctx: &mut ArrowContext,
-    ) -> Result<Option<String>, UserError> {
-        let records = get_records_output.records;
+    ) -> Result<(), UserError> {
         for record in records {
-            let data = record.data.into_inner();
             let timestamp = record.approximate_arrival_timestamp.unwrap();
-            ctx.deserialize_slice(&data, from_nanos(timestamp.as_nanos() as u128), None)
-                .await?;
+            let connector_metadata = self.metadata_fields.as_ref().map(|fields| {
+                fields
+                    .iter()
+                    .filter_map(|(k, v)| {
+                        let value = match v.as_str() {
+                            "sequence_number" => {
+                                FieldValueType::OptionalString(Some(record.sequence_number()))
+                            }
+                            "partition_key" => {
+                                FieldValueType::OptionalString(Some(record.partition_key()))
+                            }
+                            _ => return None,
+                        };
+                        Some((k, value))
+                    })
+                    .collect()
+            });
+            let position = [
+                ("stream", FieldValueType::String(&self.stream_name)),
+                ("shard_id", FieldValueType::OptionalString(Some(shard_id))),
+                (
+                    "sequence_number",
+                    FieldValueType::OptionalString(Some(record.sequence_number())),
+                ),
+            ];
+            ctx.deserialize_slice_at(
+                record.data().as_ref(),
+                from_nanos(timestamp.as_nanos() as u128),
+                connector_metadata,
+                &position,
+            )
+            .await?;
 
             if ctx.should_flush() {
                 ctx.flush_buffer().await?
             }
         }
-        Ok(get_records_output.next_shard_iterator)
+        Ok(())
     }
 
-    async fn sync_shards(
+    /// Adds any new shards assigned to this subtask, and returns futures for those that are ready
+    /// to be read. Shards created by a split or merge wait until their parents have been read.
+    ///
+    /// A merge of shards from groups read by different subtasks joins those groups, which moves
+    /// some shards to another subtask. As subtasks can't hand shards to each other while running,
+    /// this fails the operator so that the job is restored from its last checkpoint, at which
+    /// point the shards are read by their new subtask from their checkpointed offsets.
+    fn sync_shards(
         &mut self,
-        ctx: &mut ArrowContext,
-    ) -> Result<Vec<BoxedFuture<AsyncNamedResult<AsyncResult>>>> {
-        let mut futures = Vec::new();
-        for shard in self.get_splits().await? {
-            // check hash
-            let shard_id = shard.shard_id().to_string();
-            let mut hasher = DefaultHasher::new();
-            shard_id.hash(&mut hasher);
-            let shard_hash = hasher.finish() as usize;
-
-            if self.shards.contains_key(&shard_id)
-                || shard_hash % ctx.task_info.parallelism != ctx.task_info.task_index
-            {
-                continue;
-            }
-            let shard_state = ShardState::new(self.stream_name.clone(), shard, self.offset);
+        listed: HashMap<String, Shard>,
+        ctx: &ArrowContext,
+    ) -> Result<Vec<BoxedFuture<AsyncNamedResult<AsyncResult>>>, UserError> {
+        let previous_groups = std::mem::take(&mut self.groups);
+        let new_shards = self.regroup(&listed);
 
-            futures.push(
-                shard_state.get_update_shard_iterator_future(self.kinesis_client.as_ref().unwrap()),
-            );
-            self.shards.insert(shard_id, shard_state);
+        let mut moved: Vec<&String> = previous_groups
+            .iter()
+            .filter(|(shard_id, root)| {
+                Self::owns_group(root, ctx) != self.owns_shard(shard_id, ctx)
+            })
+            .map(|(shard_id, _)| shard_id)
+            .collect();
+
+        if !moved.is_empty() {
+            moved.sort();
+            return Err(UserError::new(
+                "kinesis shards were reassigned",
+                format!(
+                    "shards {:?} were merged with shards read by another subtask; restarting so \
+                    that merged shards are read by the same subtask",
+                    moved
+                ),
+            ));
         }
-        Ok(futures)
+
+        self.add_shards(&listed, new_shards, KinesisOffset::Earliest, ctx);
+        Ok(self.start_ready_shards())
+    }
+
+    async fn list_shards(&mut self) -> Result<HashMap<String, Shard>> {
+        Ok(self
+            .get_splits()
+            .await?
+            .into_iter()
+            .map(|shard| (shard.shard_id().to_string(), shard))
+            .collect())
     }
 
     async fn get_splits(&mut self) -> Result<Vec<Shard>> {
@@ -498,3 +935,103 @@ impl KinesisSourceFunc {
         Ok(shard_collect)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{group_roots, KinesisOffset, LegacyShardState, ShardState};
+    use std::collections::HashMap;
+
+    fn parents(shards: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        shards
+            .iter()
+            .map(|(id, parents)| {
+                (
+                    id.to_string(),
+                    parents.iter().map(|p| p.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_group_roots_for_splits() {
+        let roots = group_roots(&parents(&[
+            ("shard-1", &[]),
+            ("shard-2", &["shard-1"]),
+            ("shard-3", &["shard-1"]),
+            ("shard-4", &["shard-3"]),
+            ("shard-5", &[]),
+        ]));
+
+        assert_eq!(roots["shard-1"], "shard-1");
+        assert_eq!(roots["shard-2"], "shard-1");
+        assert_eq!(roots["shard-4"], "shard-1");
+        assert_eq!(roots["shard-5"], "shard-5");
+    }
+
+    #[test]
+    fn test_group_roots_for_merges() {
+        // shard-4 is a merge of shard-2 (split from shard-1) and shard-3, which joins the groups
+        // so that both parents are read by the same subtask as the child
+        let roots = group_roots(&parents(&[
+            ("shard-1", &[]),
+            ("shard-2", &["shard-1"]),
+            ("shard-3", &[]),
+            ("shard-4", &["shard-2", "shard-3"]),
+            ("shard-5", &[]),
+        ]));
+
+        for shard in ["shard-1", "shard-2", "shard-3", "shard-4"] {
+            assert_eq!(roots[shard], "shard-1", "{}", shard);
+        }
+        assert_eq!(roots["shard-5"], "shard-5");
+
+        // the order in which merges are found doesn't change the groups
+        let roots = group_roots(&parents(&[
+            ("shard-6", &["shard-8", "shard-7"]),
+            ("shard-7", &[]),
+            ("shard-8", &["shard-9"]),
+            ("shard-9", &[]),
+        ]));
+        for shard in ["shard-6", "shard-7", "shard-8", "shard-9"] {
+            assert_eq!(roots[shard], "shard-6", "{}", shard);
+        }
+    }
+
+    #[test]
+    fn test_group_roots_with_expired_parents() {
+        // children of a parent that is no longer listed are still in the same group
+        let roots = group_roots(&parents(&[
+            ("shard-2", &["shard-1"]),
+            ("shard-3", &["shard-1"]),
+        ]));
+
+        assert_eq!(roots["shard-2"], "shard-1");
+        assert_eq!(roots["shard-3"], "shard-1");
+    }
+
+    #[test]
+    fn test_legacy_shard_state() {
+        let legacy = LegacyShardState {
+            stream_name: "stream".to_string(),
+            shard_id: "shard-1".to_string(),
+            offset: KinesisOffset::SequenceNumber("42".to_string()),
+            closed: false,
+        };
+
+        let encoded = bincode::encode_to_vec(&legacy, bincode::config::standard()).unwrap();
+        let (decoded, _): (LegacyShardState, _) =
+            bincode::decode_from_slice(&encoded, bincode::config::standard()).unwrap();
+
+        assert_eq!(
+            ShardState::from(decoded),
+            ShardState {
+                stream_name: "stream".to_string(),
+                shard_id: "shard-1".to_string(),
+                offset: KinesisOffset::SequenceNumber("42".to_string()),
+                closed: false,
+                parent_shard_ids: vec![],
+            }
+        );
+    }
+}
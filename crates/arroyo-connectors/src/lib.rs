@@ -1,19 +1,26 @@
 use crate::confluent::ConfluentConnector;
 use crate::filesystem::delta::DeltaLakeConnector;
+use crate::filesystem::iceberg::IcebergConnector;
 use crate::filesystem::FileSystemConnector;
+use crate::http::HttpSinkConnector;
+use crate::jdbc::JdbcConnector;
 use crate::kinesis::KinesisConnector;
 use crate::mqtt::MqttConnector;
 use crate::polling_http::PollingHTTPConnector;
+use crate::postgres_cdc::PostgresCdcConnector;
 use crate::preview::PreviewConnector;
 use crate::redis::RedisConnector;
 use crate::single_file::SingleFileConnector;
 use crate::stdout::StdoutConnector;
 use crate::webhook::WebhookConnector;
 use anyhow::{anyhow, bail, Context};
+use arrow::array::{RecordBatch, StructArray};
+use arrow::datatypes::Schema;
 use arroyo_operator::connector::ErasedConnector;
 use arroyo_rpc::api_types::connections::{
     ConnectionSchema, ConnectionType, FieldType, SourceField, SourceFieldType, TestSourceMessage,
 };
+use arroyo_rpc::formats::{CsvFormat, Format};
 use arroyo_rpc::primitive_to_sql;
 use arroyo_rpc::var_str::VarStr;
 use arroyo_types::string_to_map;
@@ -27,6 +34,7 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sse::SSEConnector;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 use tracing::warn;
@@ -38,13 +46,16 @@ pub mod blackhole;
 pub mod confluent;
 pub mod filesystem;
 pub mod fluvio;
+pub mod http;
 pub mod impulse;
+pub mod jdbc;
 pub mod kafka;
 pub mod kinesis;
 pub mod mqtt;
 pub mod nats;
 pub mod nexmark;
 pub mod polling_http;
+pub mod postgres_cdc;
 pub mod preview;
 pub mod redis;
 pub mod single_file;
@@ -60,13 +71,17 @@ pub fn connectors() -> HashMap<&'static str, Box<dyn ErasedConnector>> {
         Box::new(DeltaLakeConnector {}),
         Box::new(FileSystemConnector {}),
         Box::new(FluvioConnector {}),
+        Box::new(HttpSinkConnector {}),
+        Box::new(IcebergConnector {}),
         Box::new(ImpulseConnector {}),
+        Box::new(JdbcConnector {}),
         Box::new(KafkaConnector {}),
         Box::new(KinesisConnector {}),
         Box::new(MqttConnector {}),
         Box::new(NatsConnector {}),
         Box::new(NexmarkConnector {}),
         Box::new(PollingHTTPConnector {}),
+        Box::new(PostgresCdcConnector {}),
         Box::new(PreviewConnector {}),
         Box::new(RedisConnector {}),
         Box::new(SingleFileConnector {}),
@@ -122,6 +137,33 @@ pub(crate) fn pull_option_to_u64(
         .transpose()
 }
 
+/// Converts the rows of a struct column, like the `before` and `after` columns of an updating
+/// sink's input, into a batch. Unlike `RecordBatch::from`, this allows null rows (for example the
+/// `before` of an insert), which callers are expected to skip.
+pub(crate) fn struct_rows(array: &StructArray) -> RecordBatch {
+    let fields: Vec<_> = array
+        .fields()
+        .iter()
+        .map(|f| f.as_ref().clone().with_nullable(true))
+        .collect();
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), array.columns().to_vec())
+        .expect("struct columns should form a valid batch")
+}
+
+/// The format to deserialize a file with when it's read a line at a time. The deserializer skips
+/// the CSV header at the start of every buffer it's given, so line-based sources skip the first
+/// line of the file themselves.
+pub(crate) fn without_csv_header(format: &Format) -> Format {
+    match format {
+        Format::Csv(csv) => Format::Csv(CsvFormat {
+            header: false,
+            ..csv.clone()
+        }),
+        format => format.clone(),
+    }
+}
+
 pub fn connector_for_type(t: &str) -> Option<Box<dyn ErasedConnector>> {
     connectors().remove(t)
 }
@@ -0,0 +1,198 @@
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+
+use bytes::Bytes;
+use rumqttc::v5::mqttbytes::v5::PublishProperties;
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::Outgoing;
+
+/// rumqttc implements MQTT 3.1.1 and MQTT 5 as separate clients; this wraps whichever one the
+/// connection is configured to use, so that the source and sink can work with either
+#[derive(Clone)]
+pub enum MqttClient {
+    V3(rumqttc::AsyncClient),
+    V5(rumqttc::v5::AsyncClient),
+}
+
+pub enum MqttEventLoop {
+    V3(rumqttc::EventLoop),
+    V5(rumqttc::v5::EventLoop),
+}
+
+/// The events from the event loop that the connector cares about
+#[derive(Debug)]
+pub enum MqttEvent {
+    Message(MqttMessage),
+    Subscribed,
+    Published,
+    Disconnected,
+    Other,
+}
+
+#[derive(Debug)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: Bytes,
+    /// Always empty for MQTT 3.1.1, which doesn't support properties
+    pub user_properties: Vec<(String, String)>,
+}
+
+/// MQTT 5 properties to set on a published message
+#[derive(Debug, Clone, Default)]
+pub struct MessageProperties {
+    pub user_properties: Vec<(String, String)>,
+    pub message_expiry_interval: Option<u32>,
+}
+
+#[derive(Debug)]
+pub enum MqttError {
+    V3(rumqttc::ConnectionError),
+    V5(rumqttc::v5::ConnectionError),
+}
+
+impl MqttError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            MqttError::V3(
+                rumqttc::ConnectionError::NetworkTimeout | rumqttc::ConnectionError::FlushTimeout
+            ) | MqttError::V5(rumqttc::v5::ConnectionError::Timeout(_))
+        )
+    }
+
+    /// Whether the connection was closed by the broker, which the event loop recovers from by
+    /// reconnecting on the next poll
+    pub fn is_connection_reset(&self) -> bool {
+        let err = match self {
+            MqttError::V3(
+                rumqttc::ConnectionError::MqttState(rumqttc::StateError::Io(err))
+                | rumqttc::ConnectionError::Io(err),
+            ) => err,
+            MqttError::V5(
+                rumqttc::v5::ConnectionError::MqttState(rumqttc::v5::StateError::Io(err))
+                | rumqttc::v5::ConnectionError::Io(err),
+            ) => err,
+            _ => return false,
+        };
+
+        matches!(
+            err.kind(),
+            ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset
+        )
+    }
+}
+
+impl Display for MqttError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MqttError::V3(e) => write!(f, "{}", e),
+            MqttError::V5(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for MqttError {}
+
+fn v3_qos(qos: QoS) -> rumqttc::QoS {
+    match qos {
+        QoS::AtMostOnce => rumqttc::QoS::AtMostOnce,
+        QoS::AtLeastOnce => rumqttc::QoS::AtLeastOnce,
+        QoS::ExactlyOnce => rumqttc::QoS::ExactlyOnce,
+    }
+}
+
+impl MqttClient {
+    pub async fn subscribe(&self, topic: impl Into<String>, qos: QoS) -> anyhow::Result<()> {
+        match self {
+            MqttClient::V3(client) => client.subscribe(topic, v3_qos(qos)).await?,
+            MqttClient::V5(client) => client.subscribe(topic, qos).await?,
+        }
+        Ok(())
+    }
+
+    pub async fn publish(
+        &self,
+        topic: impl Into<String>,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Vec<u8>>,
+    ) -> anyhow::Result<()> {
+        self.publish_with_properties(topic, qos, retain, payload, None)
+            .await
+    }
+
+    /// Publishes a message with the given properties; these are only supported by MQTT 5, and
+    /// are ignored for MQTT 3.1.1 connections
+    pub async fn publish_with_properties(
+        &self,
+        topic: impl Into<String>,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Vec<u8>>,
+        properties: Option<MessageProperties>,
+    ) -> anyhow::Result<()> {
+        match self {
+            MqttClient::V3(client) => client.publish(topic, v3_qos(qos), retain, payload).await?,
+            MqttClient::V5(client) => {
+                let payload = Bytes::from(payload.into());
+                match properties {
+                    Some(properties) => {
+                        client
+                            .publish_with_properties(
+                                topic,
+                                qos,
+                                retain,
+                                payload,
+                                PublishProperties {
+                                    user_properties: properties.user_properties,
+                                    message_expiry_interval: properties.message_expiry_interval,
+                                    ..Default::default()
+                                },
+                            )
+                            .await?
+                    }
+                    None => client.publish(topic, qos, retain, payload).await?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MqttEventLoop {
+    pub async fn poll(&mut self) -> Result<MqttEvent, MqttError> {
+        Ok(match self {
+            MqttEventLoop::V3(eventloop) => match eventloop.poll().await.map_err(MqttError::V3)? {
+                rumqttc::Event::Incoming(rumqttc::Packet::Publish(p)) => {
+                    MqttEvent::Message(MqttMessage {
+                        topic: p.topic,
+                        payload: p.payload,
+                        user_properties: vec![],
+                    })
+                }
+                rumqttc::Event::Incoming(rumqttc::Packet::Disconnect)
+                | rumqttc::Event::Outgoing(Outgoing::Disconnect) => MqttEvent::Disconnected,
+                rumqttc::Event::Outgoing(Outgoing::Subscribe(_)) => MqttEvent::Subscribed,
+                rumqttc::Event::Outgoing(Outgoing::Publish(_)) => MqttEvent::Published,
+                _ => MqttEvent::Other,
+            },
+            MqttEventLoop::V5(eventloop) => match eventloop.poll().await.map_err(MqttError::V5)? {
+                rumqttc::v5::Event::Incoming(rumqttc::v5::Incoming::Publish(p)) => {
+                    MqttEvent::Message(MqttMessage {
+                        topic: String::from_utf8_lossy(&p.topic).to_string(),
+                        payload: p.payload,
+                        user_properties: p
+                            .properties
+                            .map(|p| p.user_properties)
+                            .unwrap_or_default(),
+                    })
+                }
+                rumqttc::v5::Event::Incoming(rumqttc::v5::Incoming::Disconnect { .. })
+                | rumqttc::v5::Event::Outgoing(Outgoing::Disconnect) => MqttEvent::Disconnected,
+                rumqttc::v5::Event::Outgoing(Outgoing::Subscribe(_)) => MqttEvent::Subscribed,
+                rumqttc::v5::Event::Outgoing(Outgoing::Publish(_)) => MqttEvent::Published,
+                _ => MqttEvent::Other,
+            },
+        })
+    }
+}
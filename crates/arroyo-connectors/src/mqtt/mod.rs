@@ -4,21 +4,20 @@ use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::mqtt::client::{MqttClient, MqttEvent, MqttEventLoop};
 use crate::mqtt::sink::MqttSinkFunc;
 use crate::mqtt::source::MqttSourceFunc;
-use crate::pull_opt;
+use crate::{pull_opt, pull_option_to_i64};
 use anyhow::{anyhow, bail};
 use arrow::datatypes::DataType;
 use arroyo_formats::ser::ArrowSerializer;
-use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::connector::{Connection, Connector, MetadataDef};
 use arroyo_operator::operator::OperatorNode;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
 };
 use arroyo_rpc::{var_str::VarStr, OperatorConfig};
 use rumqttc::v5::mqttbytes::QoS;
-use rumqttc::v5::{AsyncClient, Event as MqttEvent, EventLoop, Incoming, MqttOptions};
-use rumqttc::Outgoing;
 use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer};
 use rustls_native_certs::load_native_certs;
 use serde::{Deserialize, Serialize};
@@ -31,6 +30,7 @@ const CONFIG_SCHEMA: &str = include_str!("./profile.json");
 const TABLE_SCHEMA: &str = include_str!("./table.json");
 const ICON: &str = include_str!("./mqtt.svg");
 
+pub mod client;
 pub mod sink;
 pub mod source;
 
@@ -54,6 +54,56 @@ impl MqttTable {
             })
             .unwrap_or(QoS::AtMostOnce)
     }
+
+    /// The topic filter to subscribe to, which for shared subscriptions includes the group
+    pub fn subscription_topic(&self) -> String {
+        match &self.type_ {
+            TableType::Source {
+                shared_group: Some(group),
+            } => format!("$share/{}/{}", group, self.topic),
+            _ => self.topic.clone(),
+        }
+    }
+}
+
+impl MqttConfig {
+    pub fn is_v5(&self) -> bool {
+        !matches!(self.protocol_version, Some(ProtocolVersion::V3))
+    }
+}
+
+/// Checks that options that rely on MQTT 5 features are only used with v5 connections
+fn validate_table(config: &MqttConfig, table: &MqttTable) -> anyhow::Result<()> {
+    let v5_option = match &table.type_ {
+        TableType::Source { shared_group } => shared_group.as_ref().map(|_| "shared_group"),
+        TableType::Sink {
+            user_properties_field,
+            message_expiry_secs,
+            ..
+        } => {
+            if let Some(secs) = message_expiry_secs {
+                if u32::try_from(*secs).is_err() {
+                    bail!("message_expiry_secs must be between 0 and {}", u32::MAX);
+                }
+            }
+
+            user_properties_field
+                .as_ref()
+                .map(|_| "user_properties_field")
+                .or_else(|| message_expiry_secs.map(|_| "message_expiry_secs"))
+        }
+    };
+
+    if let Some(option) = v5_option {
+        if !config.is_v5() {
+            bail!(
+                "'{}' requires MQTT v5, but the connection is configured to use v3",
+                option
+            );
+        }
+    }
+
+    Ok(())
 }
 
 impl MqttConnector {
@@ -71,6 +121,14 @@ impl MqttConnector {
         let cert = options.remove("tls.cert").map(VarStr::new);
         let key = options.remove("tls.key").map(VarStr::new);
 
+        let protocol_version = options
+            .remove("protocol_version")
+            .map(|s| {
+                ProtocolVersion::try_from(s)
+                    .map_err(|s| anyhow!("invalid value for 'protocol_version': {s}"))
+            })
+            .transpose()?;
+
         let parsed_url = url::Url::parse(&url)?;
 
         let tls = if matches!(parsed_url.scheme(), "mqtts" | "ssl") {
@@ -85,6 +143,7 @@ impl MqttConnector {
             password,
             tls,
             client_prefix: options.remove("client_prefix"),
+            protocol_version,
         })
     }
 
@@ -98,7 +157,9 @@ impl MqttConnector {
             .transpose()?;
 
         let table_type = match typ.as_str() {
-            "source" => TableType::Source {},
+            "source" => TableType::Source {
+                shared_group: options.remove("source.shared_group"),
+            },
             "sink" => TableType::Sink {
                 retain: options
                     .remove("sink.retain")
@@ -108,6 +169,8 @@ impl MqttConnector {
                     })
                     .transpose()?
                     .unwrap_or(false),
+                user_properties_field: options.remove("sink.user_properties_field"),
+                message_expiry_secs: pull_option_to_i64("sink.message_expiry_secs", options)?,
             },
             _ => {
                 bail!("type must be one of 'source' or 'sink")
@@ -147,6 +210,22 @@ impl Connector for MqttConnector {
         }
     }
 
+    /// As with the Kafka source's headers, tables can't declare map columns, so `user_properties`
+    /// is provided as a JSON object of property names to values, which can be declared as `JSON`
+    /// and read with the JSON functions
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        &[
+            MetadataDef {
+                name: "topic",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "user_properties",
+                data_type: DataType::Utf8,
+            },
+        ]
+    }
+
     fn config_description(&self, config: Self::ProfileT) -> String {
         config.url.clone()
     }
@@ -160,6 +239,8 @@ impl Connector for MqttConnector {
         schema: Option<&ConnectionSchema>,
         metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
+        validate_table(&config, &table)?;
+
         let (typ, desc) = match table.type_ {
             TableType::Source { .. } => (
                 ConnectionType::Source,
@@ -265,24 +346,6 @@ impl Connector for MqttConnector {
 
         let table = Self::table_from_options(options)?;
 
-        if let Some(fields) = &metadata_fields {
-            for (k, (v, t)) in fields {
-                if v != "topic" {
-                    return Err(anyhow!(
-                        "Invalid metadata field name for mqtt connector: {}",
-                        k
-                    ));
-                }
-                if *t != DataType::Utf8 {
-                    return Err(anyhow!(
-                        "Invalid datatype: {} for metadata field: {} for mqtt connector",
-                        k,
-                        v
-                    ));
-                }
-            }
-        }
-
         Self::from_config(self, None, name, connection, table, schema, metadata_fields)
     }
 
@@ -293,35 +356,47 @@ impl Connector for MqttConnector {
         config: OperatorConfig,
     ) -> anyhow::Result<OperatorNode> {
         let qos = table.qos();
+        let subscription_topic = table.subscription_topic();
         Ok(match table.type_ {
-            TableType::Source {} => OperatorNode::from_source(Box::new(MqttSourceFunc {
-                config: profile,
-                topic: table.topic,
-                qos,
-                format: config
-                    .format
-                    .ok_or_else(|| anyhow!("format is required for mqtt source"))?,
-                framing: config.framing,
-                bad_data: config.bad_data,
-                messages_per_second: NonZeroU32::new(
-                    config
-                        .rate_limit
-                        .map(|l| l.messages_per_second)
-                        .unwrap_or(u32::MAX),
-                )
-                .unwrap(),
-                subscribed: Arc::new(AtomicBool::new(false)),
-                metadata_fields: config.additional_fields,
-            })),
-            TableType::Sink { retain } => OperatorNode::from_operator(Box::new(MqttSinkFunc {
+            TableType::Source { shared_group } => {
+                OperatorNode::from_source(Box::new(MqttSourceFunc {
+                    config: profile,
+                    topic: subscription_topic,
+                    shared: shared_group.is_some(),
+                    qos,
+                    format: config
+                        .format
+                        .ok_or_else(|| anyhow!("format is required for mqtt source"))?,
+                    framing: config.framing,
+                    bad_data: config.bad_data,
+                    messages_per_second: NonZeroU32::new(
+                        config
+                            .rate_limit
+                            .map(|l| l.messages_per_second)
+                            .unwrap_or(u32::MAX),
+                    )
+                    .unwrap(),
+                    subscribed: Arc::new(AtomicBool::new(false)),
+                    metadata_fields: config.additional_fields,
+                }))
+            }
+            TableType::Sink {
+                retain,
+                user_properties_field,
+                message_expiry_secs,
+            } => OperatorNode::from_operator(Box::new(MqttSinkFunc {
                 config: profile,
                 qos,
                 topic: table.topic,
                 retain,
-                serializer: ArrowSerializer::new(
+                user_properties_field,
+                user_properties_col: None,
+                message_expiry_interval: message_expiry_secs.map(u32::try_from).transpose()?,
+                serializer: ArrowSerializer::with_framing(
                     config
                         .format
                         .ok_or_else(|| anyhow!("format is required for mqtt sink"))?,
+                    config.framing,
                 ),
                 stopped: Arc::new(AtomicBool::new(false)),
                 client: None,
@@ -343,29 +418,21 @@ async fn test_inner(
 
     let wait_for_incomming = match t {
         Some(t) => {
+            let qos = t.qos();
+            let subscription_topic = t.subscription_topic();
             let topic = t.topic;
-            let qos = t
-                .qos
-                .map(|qos| match qos {
-                    QualityOfService::AtMostOnce => QoS::AtMostOnce,
-                    QualityOfService::AtLeastOnce => QoS::AtLeastOnce,
-                    QualityOfService::ExactlyOnce => QoS::ExactlyOnce,
-                })
-                .unwrap_or(QoS::AtMostOnce);
             if let TableType::Sink { retain, .. } = t.type_ {
-                client
-                    .publish(topic, qos, retain, "test".as_bytes())
-                    .await?;
+                client.publish(topic, qos, retain, "test").await?;
                 false
             } else {
-                client.subscribe(&topic, qos).await?;
-                client.publish(topic, qos, false, "test".as_bytes()).await?;
+                client.subscribe(subscription_topic, qos).await?;
+                client.publish(topic, qos, false, "test").await?;
                 true
             }
         }
         None => {
             client
-                .publish("test-arroyo", QoS::AtMostOnce, false, "test".as_bytes())
+                .publish("test-arroyo", QoS::AtMostOnce, false, "test")
                 .await?;
             false
         }
@@ -374,17 +441,16 @@ async fn test_inner(
     loop {
         match eventloop.poll().await {
             Ok(notification) => match notification {
-                MqttEvent::Incoming(Incoming::Publish(p)) => {
-                    let _payload = String::from_utf8(p.payload.to_vec())?;
+                MqttEvent::Message(m) => {
+                    let _payload = String::from_utf8(m.payload.to_vec())?;
                     return Ok("Successfully subscribed".to_string());
                 }
-                MqttEvent::Outgoing(Outgoing::Publish(_p)) => {
+                MqttEvent::Published => {
                     if !wait_for_incomming {
                         return Ok("Successfully published".to_string());
                     }
                 }
-                MqttEvent::Incoming(Incoming::Disconnect { .. })
-                | MqttEvent::Outgoing(Outgoing::Disconnect) => {
+                MqttEvent::Disconnected => {
                     bail!("Disconnected from Mqtt");
                 }
                 _ => (),
@@ -420,7 +486,7 @@ fn load_private_key<'a>(certificate: &str) -> anyhow::Result<PrivatePkcs8KeyDer<
 pub(crate) fn create_connection(
     c: &MqttConfig,
     task_id: usize,
-) -> anyhow::Result<(AsyncClient, EventLoop)> {
+) -> anyhow::Result<(MqttClient, MqttEventLoop)> {
     // It creates a client id with the format: <client_prefix>_<task_id><current_time_in_millis>
     // because the client id must be unique for each connection. Otherwise, the broker will only keep one active connection
     // per client id
@@ -439,9 +505,7 @@ pub(crate) fn create_connection(
     let ssl = matches!(url.scheme(), "mqtts" | "ssl");
     url.query_pairs_mut().append_pair("client_id", &client_id);
 
-    let mut options = MqttOptions::try_from(url)?;
-
-    options.set_keep_alive(Duration::from_secs(10));
+    let mut transport = None;
     if ssl {
         let mut root_cert_store = RootCertStore::empty();
 
@@ -474,7 +538,7 @@ pub(crate) fn create_connection(
             builder.with_no_client_auth()
         };
 
-        options.set_transport(rumqttc::Transport::tls_with_config(
+        transport = Some(rumqttc::Transport::tls_with_config(
             rumqttc::TlsConfiguration::Rustls(Arc::new(tls_config)),
         ));
     }
@@ -485,12 +549,36 @@ pub(crate) fn create_connection(
         "".to_string()
     };
 
-    if let Some(username) = &c.username {
-        options.set_credentials(
+    let credentials = match &c.username {
+        Some(username) => Some((
             username.sub_env_vars().map_err(|e| anyhow!("{}", e))?,
             password,
-        );
+        )),
+        None => None,
+    };
+
+    // the v3 and v5 clients have separate (but equivalent) options types
+    macro_rules! configure_options {
+        ($options:expr) => {{
+            let mut options = $options;
+            options.set_keep_alive(Duration::from_secs(10));
+            if let Some(transport) = transport {
+                options.set_transport(transport);
+            }
+            if let Some((username, password)) = credentials {
+                options.set_credentials(username, password);
+            }
+            options
+        }};
     }
 
-    Ok(AsyncClient::new(options, 100))
+    if c.is_v5() {
+        let options = configure_options!(rumqttc::v5::MqttOptions::try_from(url)?);
+        let (client, eventloop) = rumqttc::v5::AsyncClient::new(options, 100);
+        Ok((MqttClient::V5(client), MqttEventLoop::V5(eventloop)))
+    } else {
+        let options = configure_options!(rumqttc::MqttOptions::try_from(url)?);
+        let (client, eventloop) = rumqttc::AsyncClient::new(options, 100);
+        Ok((MqttClient::V3(client), MqttEventLoop::V3(eventloop)))
+    }
 }
@@ -1,18 +1,23 @@
+use arrow::array::{Array, ArrayRef, AsArray, MapArray, StringArray};
+use arrow::buffer::NullBuffer;
+use arrow::compute::{can_cast_types, cast};
+use arrow::datatypes::DataType;
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Duration;
+use tracing::warn;
 
+use crate::mqtt::client::{MessageProperties, MqttClient};
 use crate::mqtt::MqttConfig;
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::ArrowOperator;
+use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::formats::Format;
 use arroyo_rpc::ControlResp;
 use rumqttc::v5::mqttbytes::QoS;
-use rumqttc::v5::AsyncClient;
-use rumqttc::v5::ConnectionError;
 
 #[cfg(test)]
 mod test;
@@ -22,8 +27,12 @@ pub struct MqttSinkFunc {
     pub qos: QoS,
     pub topic: String,
     pub retain: bool,
+    pub user_properties_field: Option<String>,
+    pub user_properties_col: Option<usize>,
+    /// The MQTT 5 message expiry interval, in seconds
+    pub message_expiry_interval: Option<u32>,
     pub serializer: ArrowSerializer,
-    pub client: Option<AsyncClient>,
+    pub client: Option<MqttClient>,
     pub stopped: Arc<AtomicBool>,
 }
 
@@ -34,11 +43,35 @@ impl MqttSinkFunc {
             qos,
             topic,
             retain,
+            user_properties_field: None,
+            user_properties_col: None,
+            message_expiry_interval: None,
             serializer: ArrowSerializer::new(format),
             client: None,
             stopped: Arc::new(AtomicBool::new(false)),
         }
     }
+
+    fn set_user_properties_col(&mut self, schema: &ArroyoSchema) {
+        if let Some(f) = &self.user_properties_field {
+            if let Ok(f) = schema.schema.field_with_name(f) {
+                if is_user_properties_type(f.data_type()) {
+                    self.user_properties_col = Some(schema.schema.index_of(f.name()).unwrap());
+                } else {
+                    warn!(
+                        "MQTT sink configured with user_properties_field '{f}', but it has type \
+                {}, not a struct or a map with TEXT keys... ignoring",
+                        f.data_type()
+                    );
+                }
+            } else {
+                warn!(
+                    "MQTT sink configured with user_properties_field '{f}', but that \
+                does not appear in the schema... ignoring"
+                );
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -47,6 +80,8 @@ impl ArrowOperator for MqttSinkFunc {
         format!("mqtt-producer-{}", self.topic)
     }
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        self.set_user_properties_col(&ctx.in_schemas[0]);
+
         let mut attempts = 0;
         while attempts < 20 {
             match super::create_connection(&self.config, ctx.task_info.task_index) {
@@ -57,23 +92,11 @@ impl ArrowOperator for MqttSinkFunc {
                         while !stopped.load(std::sync::atomic::Ordering::Relaxed) {
                             match eventloop.poll().await {
                                 Ok(_) => (),
-                                Err(err) => match err {
-                                    ConnectionError::Timeout(_) => (),
-                                    ConnectionError::MqttState(rumqttc::v5::StateError::Io(
-                                        err,
-                                    ))
-                                    | ConnectionError::Io(err)
-                                        if err.kind() == std::io::ErrorKind::ConnectionAborted
-                                            || err.kind()
-                                                == std::io::ErrorKind::ConnectionReset =>
-                                    {
-                                        continue;
-                                    }
-                                    err => {
-                                        tracing::error!("Failed to poll mqtt eventloop: {:?}", err);
-                                        tokio::time::sleep(Duration::from_secs(1)).await;
-                                    }
-                                },
+                                Err(err) if err.is_timeout() || err.is_connection_reset() => (),
+                                Err(err) => {
+                                    tracing::error!("Failed to poll mqtt eventloop: {:?}", err);
+                                    tokio::time::sleep(Duration::from_secs(1)).await;
+                                }
                             }
                         }
                     });
@@ -92,12 +115,28 @@ impl ArrowOperator for MqttSinkFunc {
     }
 
     async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
-        for v in self.serializer.serialize(&batch) {
+        let user_properties = self
+            .user_properties_col
+            .map(|i| UserPropertiesColumn::new(batch.column(i)));
+
+        for (i, v) in self.serializer.serialize(&batch).enumerate() {
+            let user_properties = user_properties
+                .as_ref()
+                .map(|p| p.user_properties(i))
+                .unwrap_or_default();
+
+            let properties = (!user_properties.is_empty()
+                || self.message_expiry_interval.is_some())
+            .then(|| MessageProperties {
+                user_properties,
+                message_expiry_interval: self.message_expiry_interval,
+            });
+
             match self
                 .client
                 .as_mut()
                 .unwrap()
-                .publish(&self.topic, self.qos, self.retain, v)
+                .publish_with_properties(&self.topic, self.qos, self.retain, v, properties)
                 .await
             {
                 Ok(_) => (),
@@ -125,3 +164,99 @@ impl Drop for MqttSinkFunc {
             .store(true, std::sync::atomic::Ordering::Relaxed);
     }
 }
+
+/// Whether a column of this type can be written as user properties: either a struct, whose
+/// field names become the property names, or a map with TEXT keys
+fn is_user_properties_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Struct(fields) => fields
+            .iter()
+            .all(|f| can_cast_types(f.data_type(), &DataType::Utf8)),
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(kv) if kv.len() == 2 => {
+                kv[0].data_type() == &DataType::Utf8
+                    && can_cast_types(kv[1].data_type(), &DataType::Utf8)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn property_values(array: &ArrayRef) -> StringArray {
+    cast(array, &DataType::Utf8)
+        .unwrap()
+        .as_string::<i32>()
+        .clone()
+}
+
+/// The user properties column of a batch, with the values converted to strings
+enum UserPropertiesColumn {
+    Struct {
+        keys: Vec<String>,
+        values: Vec<StringArray>,
+        nulls: Option<NullBuffer>,
+    },
+    Map {
+        map: MapArray,
+        values: StringArray,
+    },
+}
+
+impl UserPropertiesColumn {
+    fn new(array: &ArrayRef) -> Self {
+        match array.data_type() {
+            DataType::Struct(fields) => {
+                let array = array.as_struct();
+                Self::Struct {
+                    keys: fields.iter().map(|f| f.name().clone()).collect(),
+                    values: array.columns().iter().map(property_values).collect(),
+                    nulls: array.nulls().cloned(),
+                }
+            }
+            DataType::Map(..) => {
+                let map = array.as_map();
+                Self::Map {
+                    values: property_values(map.values()),
+                    map: map.clone(),
+                }
+            }
+            t => unreachable!("invalid type for user properties column: {}", t),
+        }
+    }
+
+    /// The user properties for a row; as properties can't be null, null values are skipped
+    fn user_properties(&self, row: usize) -> Vec<(String, String)> {
+        match self {
+            Self::Struct {
+                keys,
+                values,
+                nulls,
+            } => {
+                if nulls.as_ref().is_some_and(|n| n.is_null(row)) {
+                    return vec![];
+                }
+
+                keys.iter()
+                    .zip(values)
+                    .filter(|(_, values)| values.is_valid(row))
+                    .map(|(key, values)| (key.clone(), values.value(row).to_string()))
+                    .collect()
+            }
+            Self::Map { map, values } => {
+                if map.is_null(row) {
+                    return vec![];
+                }
+
+                let keys = map.keys().as_string::<i32>();
+                let offsets = map.value_offsets();
+                let (start, end) = (offsets[row] as usize, offsets[row + 1] as usize);
+
+                (start..end)
+                    .filter(|i| values.is_valid(*i))
+                    .map(|i| (keys.value(i).to_string(), values.value(i).to_string()))
+                    .collect()
+            }
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use arrow::array::{RecordBatch, StringArray};
+use arrow::array::{RecordBatch, StringArray, StructArray};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::mqtt::client::{MqttClient, MqttEvent, MqttEventLoop};
 use crate::mqtt::{create_connection, MqttConfig, Tls};
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use arroyo_operator::context::ArrowContext;
@@ -13,10 +14,7 @@ use arroyo_rpc::{
 };
 use arroyo_types::get_test_task_info;
 use parquet::data_type::AsBytes;
-use rumqttc::{
-    v5::{mqttbytes::QoS, Event, Incoming},
-    Outgoing,
-};
+use rumqttc::v5::mqttbytes::QoS;
 use serde::Deserialize;
 use tokio::sync::mpsc::channel;
 
@@ -57,15 +55,20 @@ impl MqttTopicTester {
                 cert: self.cert.as_ref().map(|ca| VarStr::new(ca.clone())),
                 key: self.key.as_ref().map(|ca| VarStr::new(ca.clone())),
             }),
+            protocol_version: None,
         }
     }
 
-    async fn get_client(&self) -> (rumqttc::v5::AsyncClient, rumqttc::v5::EventLoop) {
+    async fn get_client(&self) -> (MqttClient, MqttEventLoop) {
         let config = self.get_config();
         create_connection(&config, 0).expect("Failed to create connection")
     }
 
-    async fn get_sink_with_writes(&self) -> MqttSinkWithWrites {
+    async fn get_sink_with_writes(
+        &self,
+        schema: SchemaRef,
+        user_properties_field: Option<String>,
+    ) -> MqttSinkWithWrites {
         let config = self.get_config();
         let mut mqtt = MqttSinkFunc::new(
             config,
@@ -74,6 +77,7 @@ impl MqttTopicTester {
             false,
             Format::Json(JsonFormat::default()),
         );
+        mqtt.user_properties_field = user_properties_field;
 
         let (_, control_rx) = channel(128);
         let (command_tx, _) = channel(128);
@@ -86,10 +90,11 @@ impl MqttTopicTester {
             control_rx,
             command_tx,
             1,
-            vec![ArroyoSchema::new_unkeyed(schema(), 0)],
+            vec![ArroyoSchema::new_unkeyed(schema, 0)],
             None,
             None,
             vec![vec![]],
+            vec![],
             HashMap::new(),
         )
         .await;
@@ -117,7 +122,7 @@ async fn test_mqtt() {
         password: None,
     };
 
-    let mut sink_with_writes = mqtt_tester.get_sink_with_writes().await;
+    let mut sink_with_writes = mqtt_tester.get_sink_with_writes(schema(), None).await;
     let (client, mut eventloop) = mqtt_tester.get_client().await;
 
     client
@@ -128,7 +133,7 @@ async fn test_mqtt() {
 
     loop {
         match eventloop.poll().await {
-            Ok(Event::Outgoing(Outgoing::Subscribe(_))) => {
+            Ok(MqttEvent::Subscribed) => {
                 break;
             }
             _ => {
@@ -153,7 +158,7 @@ async fn test_mqtt() {
 
     loop {
         match eventloop.poll().await {
-            Ok(Event::Incoming(Incoming::Publish(p))) => {
+            Ok(MqttEvent::Message(p)) => {
                 let result: TestData = serde_json::from_slice(p.payload.as_bytes()).unwrap();
                 assert_eq!(
                     message.to_string(),
@@ -174,3 +179,81 @@ async fn test_mqtt() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_mqtt_user_properties() {
+    let mqtt_tester = MqttTopicTester {
+        topic: "mqtt-arroyo-test-sink-properties".to_string(),
+        port: 1883,
+        ca: None,
+        cert: None,
+        key: None,
+        username: None,
+        password: None,
+    };
+
+    let properties_type = DataType::Struct(vec![Field::new("source", DataType::Utf8, true)].into());
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("value", DataType::Utf8, false),
+        Field::new("properties", properties_type, true),
+    ]));
+
+    let mut sink_with_writes = mqtt_tester
+        .get_sink_with_writes(schema.clone(), Some("properties".to_string()))
+        .await;
+    let (client, mut eventloop) = mqtt_tester.get_client().await;
+
+    client
+        .subscribe(&mqtt_tester.topic, QoS::AtLeastOnce)
+        .await
+        .unwrap();
+    let start = std::time::Instant::now();
+
+    loop {
+        match eventloop.poll().await {
+            Ok(MqttEvent::Subscribed) => {
+                break;
+            }
+            _ => {
+                if start.elapsed().as_secs() > 5 {
+                    panic!("Failed to subscribe to topic");
+                }
+            }
+        }
+    }
+
+    let properties = StructArray::new(
+        vec![Field::new("source", DataType::Utf8, true)].into(),
+        vec![Arc::new(StringArray::from(vec![Some("arroyo"), None]))],
+        None,
+    );
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(StringArray::from(vec!["1", "2"])),
+            Arc::new(properties),
+        ],
+    )
+    .unwrap();
+
+    sink_with_writes
+        .sink
+        .process_batch(batch, &mut sink_with_writes.ctx)
+        .await;
+
+    let mut messages = vec![];
+    while messages.len() < 2 {
+        match eventloop.poll().await {
+            Ok(MqttEvent::Message(p)) => messages.push(p.user_properties),
+            Ok(_) => (),
+            Err(err) => {
+                panic!("Error in mqtt event loop: {:?}", err);
+            }
+        }
+    }
+
+    assert_eq!(
+        messages,
+        vec![vec![("source".to_string(), "arroyo".to_string())], vec![]]
+    );
+}
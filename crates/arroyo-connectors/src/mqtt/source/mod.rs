@@ -11,9 +11,8 @@ use arroyo_rpc::{grpc::rpc::StopMode, ControlMessage, ControlResp};
 use arroyo_types::{ArrowMessage, SignalMessage, UserError, Watermark};
 use governor::{Quota, RateLimiter as GovernorRateLimiter};
 use rumqttc::v5::mqttbytes::QoS;
-use rumqttc::v5::{ConnectionError, Event as MqttEvent, Incoming};
-use rumqttc::Outgoing;
 
+use crate::mqtt::client::MqttEvent;
 use crate::mqtt::{create_connection, MqttConfig};
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::SourceOperator;
@@ -27,7 +26,11 @@ mod test;
 
 pub struct MqttSourceFunc {
     pub config: MqttConfig,
+    /// The topic filter to subscribe to
     pub topic: String,
+    /// Whether `topic` is a shared subscription, in which case every subtask subscribes and the
+    /// broker divides messages between them
+    pub shared: bool,
     pub qos: QoS,
     pub format: Format,
     pub framing: Option<Framing>,
@@ -82,6 +85,7 @@ impl MqttSourceFunc {
         Self {
             config,
             topic,
+            shared: false,
             qos,
             format,
             framing,
@@ -103,7 +107,7 @@ impl MqttSourceFunc {
             self.bad_data.clone(),
         );
 
-        if ctx.task_info.task_index > 0 {
+        if ctx.task_info.task_index > 0 && !self.shared {
             tracing::warn!(
                 "Mqtt Consumer {}-{} can only be executed on a single worker... setting idle",
                 ctx.task_info.operator_id,
@@ -147,29 +151,34 @@ impl MqttSourceFunc {
             select! {
                 event = eventloop.poll() => {
                     match event {
-                        Ok(MqttEvent::Incoming(Incoming::Publish(p))) => {
-                            let topic = String::from_utf8_lossy(&p.topic).to_string();
+                        Ok(MqttEvent::Message(m)) => {
+                            let user_properties = self.metadata_fields.as_ref()
+                                .filter(|fields| fields.values().any(|v| v == "user_properties"))
+                                .map(|_| user_properties_to_json(&m.user_properties));
+
                             let connector_metadata: Option<HashMap<&String, FieldValueType<'_>>> =
                             self.metadata_fields.as_ref().map(|fields| {
                                 fields.iter()
                                     .filter_map(|(k, v)| {
-                                        if v == "topic" {
-                                            Some((k, FieldValueType::String(&topic)))
-                                        } else {
-                                            None
-                                        }
+                                        let value = match v.as_str() {
+                                            "topic" => FieldValueType::String(&m.topic),
+                                            "user_properties" => FieldValueType::OptionalString(user_properties.as_deref()),
+                                            _ => return None,
+                                        };
+                                        Some((k, value))
                                     })
                                     .collect()
                             });
-                            ctx.deserialize_slice(&p.payload, SystemTime::now(), connector_metadata).await?;
+                            let position = [("topic", FieldValueType::String(&m.topic))];
+                            ctx.deserialize_slice_at(&m.payload, SystemTime::now(), connector_metadata, &position).await?;
                             rate_limiter.until_ready().await;
                         }
-                        Ok(MqttEvent::Outgoing(Outgoing::Subscribe(_))) => {
+                        Ok(MqttEvent::Subscribed) => {
                             self.subscribed.store(true, Ordering::Relaxed);
                         }
                         Ok(_) => (),
                         Err(err) => {
-                            if let ConnectionError::Timeout(_) = err {
+                            if err.is_timeout() {
                                 continue;
                             }
                             tracing::error!("Failed to poll mqtt eventloop: {}", err);
@@ -228,3 +237,15 @@ impl MqttSourceFunc {
         }
     }
 }
+
+/// Encodes the user properties of a message as a JSON object, for the `user_properties` metadata
+/// column; MQTT allows a property name to appear more than once, in which case the last value is
+/// used
+fn user_properties_to_json(user_properties: &[(String, String)]) -> String {
+    let properties: serde_json::Map<String, serde_json::Value> = user_properties
+        .iter()
+        .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+        .collect();
+
+    serde_json::Value::Object(properties).to_string()
+}
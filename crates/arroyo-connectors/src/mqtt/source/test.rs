@@ -3,6 +3,7 @@ use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+use crate::mqtt::client::MqttClient;
 use crate::mqtt::{create_connection, MqttConfig, Tls};
 use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
 use arroyo_operator::context::{batch_bounded, ArrowContext, BatchReceiver};
@@ -17,7 +18,7 @@ use rumqttc::v5::mqttbytes::QoS;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
-use super::MqttSourceFunc;
+use super::{user_properties_to_json, MqttSourceFunc};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct TestData {
@@ -93,10 +94,11 @@ impl MqttTopicTester {
                 cert: self.cert.as_ref().map(|ca| VarStr::new(ca.clone())),
                 key: self.key.as_ref().map(|ca| VarStr::new(ca.clone())),
             }),
+            protocol_version: None,
         }
     }
 
-    async fn get_client(&self) -> rumqttc::v5::AsyncClient {
+    async fn get_client(&self) -> MqttClient {
         let config = self.get_config();
         let (client, mut eventloop) =
             create_connection(&config, 0).expect("Failed to create connection");
@@ -152,6 +154,7 @@ impl MqttTopicTester {
             )),
             None,
             vec![vec![data_tx]],
+            vec![],
             mqtt.tables(),
         )
         .await;
@@ -221,3 +224,17 @@ async fn test_mqtt() {
         .await
         .unwrap();
 }
+
+#[test]
+fn test_user_properties_to_json() {
+    let properties = vec![
+        ("region".to_string(), "eu".to_string()),
+        ("trace".to_string(), "a".to_string()),
+        ("trace".to_string(), "b".to_string()),
+    ];
+
+    let json: serde_json::Value =
+        serde_json::from_str(&user_properties_to_json(&properties)).unwrap();
+    assert_eq!(json, serde_json::json!({"region": "eu", "trace": "b"}));
+    assert_eq!(user_properties_to_json(&[]), "{}");
+}
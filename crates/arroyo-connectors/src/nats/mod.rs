@@ -5,11 +5,12 @@ use anyhow::anyhow;
 use anyhow::bail;
 use arrow::datatypes::DataType;
 use arroyo_formats::ser::ArrowSerializer;
-use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::connector::{Connection, Connector, MetadataDef};
 use arroyo_operator::operator::OperatorNode;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
 };
+use arroyo_rpc::formats::{Format, JsonFormat};
 use arroyo_rpc::var_str::VarStr;
 use arroyo_rpc::OperatorConfig;
 use async_nats::ServerAddr;
@@ -153,11 +154,30 @@ impl NatsConnector {
                 ConnectorType::Source { source_type }
             }
             "sink" => {
-                let sink_type = match pull_opt("subject", options).ok() {
-                    Some(subject) => Some(SinkType::Subject(subject)),
-                    None => bail!("`subject` must be set for sink"),
+                let sink_type = match (
+                    options.remove("subject"),
+                    options.remove("kv.bucket"),
+                    options.remove("object_store.bucket"),
+                ) {
+                    (Some(subject), None, None) => SinkType::Subject(subject),
+                    (None, Some(bucket), None) => SinkType::KeyValue {
+                        bucket,
+                        key_field: pull_opt("kv.key_field", options)?,
+                    },
+                    (None, None, Some(bucket)) => SinkType::ObjectStore {
+                        bucket,
+                        name_field: pull_opt("object_store.name_field", options)?,
+                    },
+                    (None, None, None) => bail!(
+                        "One of `subject`, `kv.bucket` or `object_store.bucket` must be set for sink"
+                    ),
+                    _ => bail!(
+                        "Only one of `subject`, `kv.bucket` or `object_store.bucket` may be set for sink"
+                    ),
                 };
-                ConnectorType::Sink { sink_type }
+                ConnectorType::Sink {
+                    sink_type: Some(sink_type),
+                }
             }
             _ => bail!("Type must be one of 'source' or 'sink'"),
         };
@@ -203,6 +223,19 @@ impl Connector for NatsConnector {
         .to_string()
     }
 
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        &[
+            MetadataDef {
+                name: "subject",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "headers",
+                data_type: DataType::Utf8,
+            },
+        ]
+    }
+
     fn table_type(&self, _: Self::ProfileT, table: Self::TableT) -> ConnectionType {
         match &table.connector_type {
             ConnectorType::Source { .. } => ConnectionType::Source,
@@ -247,8 +280,12 @@ impl Connector for NatsConnector {
         config: NatsConfig,
         table: NatsTable,
         schema: Option<&ConnectionSchema>,
-        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+        metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
+        let is_updating = schema
+            .and_then(|s| s.format.as_ref())
+            .is_some_and(|f| f.is_updating());
+
         let stream_or_subject = match &table.connector_type {
             ConnectorType::Source { source_type, .. } => {
                 match source_type
@@ -265,6 +302,17 @@ impl Connector for NatsConnector {
                     .ok_or_else(|| anyhow!("sinkType is required"))?
                 {
                     SinkType::Subject(s) => s,
+                    SinkType::KeyValue { bucket, key_field } => {
+                        validate_sink_field(schema, key_field, is_updating)?;
+                        bucket
+                    }
+                    SinkType::ObjectStore { bucket, name_field } => {
+                        if is_updating {
+                            bail!("NATS object store sinks do not support updating tables");
+                        }
+                        validate_sink_field(schema, name_field, is_updating)?;
+                        bucket
+                    }
                 }
             }
         };
@@ -290,6 +338,13 @@ impl Connector for NatsConnector {
             .map(|t| t.to_owned())
             .ok_or_else(|| anyhow!("'format' must be set for NATS connection"))?;
 
+        let metadata_fields = metadata_fields.map(|fields| {
+            fields
+                .into_iter()
+                .map(|(k, (v, _))| (k, v))
+                .collect::<HashMap<String, String>>()
+        });
+
         let config = OperatorConfig {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
@@ -297,7 +352,7 @@ impl Connector for NatsConnector {
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
-            additional_fields: None,
+            additional_fields: metadata_fields,
         };
 
         Ok(Connection {
@@ -317,7 +372,7 @@ impl Connector for NatsConnector {
         options: &mut HashMap<String, String>,
         schema: Option<&ConnectionSchema>,
         profile: Option<&ConnectionProfile>,
-        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+        metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
         let connection = profile
             .map(|p| {
@@ -329,7 +384,7 @@ impl Connector for NatsConnector {
 
         let table = Self::table_from_options(options)?;
 
-        Self::from_config(self, None, name, connection, table, schema, None)
+        Self::from_config(self, None, name, connection, table, schema, metadata_fields)
     }
 
     fn make_operator(
@@ -361,6 +416,7 @@ impl Connector for NatsConnector {
                             .unwrap_or(u32::MAX),
                     )
                     .unwrap(),
+                    metadata_fields: config.additional_fields,
                 }))
             }
             ConnectorType::Sink { ref sink_type } => {
@@ -376,15 +432,51 @@ impl Connector for NatsConnector {
                     connection: profile.clone(),
                     table: table.clone(),
                     publisher: None,
-                    serializer: ArrowSerializer::new(
-                        config.format.expect("Format must be set for NATS source"),
-                    ),
+                    bucket: None,
+                    layout: None,
+                    serializer: match sink_type {
+                        // the key-value sink writes the rows themselves, rather than changelog
+                        // messages, so updating tables are serialized as plain JSON
+                        Some(SinkType::KeyValue { .. }) => ArrowSerializer::new(
+                            match config.format.expect("Format must be set for NATS sink") {
+                                Format::Json(json) => Format::Json(JsonFormat {
+                                    debezium: false,
+                                    ..json
+                                }),
+                                format => format,
+                            },
+                        ),
+                        Some(SinkType::ObjectStore { .. }) => ArrowSerializer::new(
+                            config.format.expect("Format must be set for NATS sink"),
+                        ),
+                        _ => ArrowSerializer::with_framing(
+                            config.format.expect("Format must be set for NATS source"),
+                            config.framing,
+                        ),
+                    },
                 }))
             }
         })
     }
 }
 
+/// Checks that a field used to name the written keys or objects is part of the schema; for
+/// updating tables, this is checked when the sink starts
+fn validate_sink_field(
+    schema: Option<&ConnectionSchema>,
+    field: &str,
+    is_updating: bool,
+) -> anyhow::Result<()> {
+    if is_updating {
+        return Ok(());
+    }
+
+    if !schema.is_some_and(|s| s.fields.iter().any(|f| f.field_name == field)) {
+        bail!("field '{}' is not a field in the table", field);
+    }
+    Ok(())
+}
+
 async fn get_nats_client(connection: &NatsConfig) -> anyhow::Result<async_nats::Client> {
     let mut opts = async_nats::ConnectOptions::new();
 
@@ -1,7 +1,10 @@
 use super::NatsConfig;
 use super::NatsTable;
 use super::{get_nats_client, SinkType};
-use arrow::array::RecordBatch;
+use crate::struct_rows;
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch, StringArray, StructArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Schema};
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::ArrowOperator;
@@ -9,8 +12,10 @@ use arroyo_rpc::grpc::rpc::TableConfig;
 use arroyo_rpc::ControlMessage;
 use arroyo_rpc::ControlResp;
 use arroyo_types::*;
+use async_nats::jetstream::{kv, object_store};
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::fmt::Display;
 use tracing::warn;
 
 pub struct NatsSinkFunc {
@@ -19,35 +24,138 @@ pub struct NatsSinkFunc {
     pub connection: NatsConfig,
     pub table: NatsTable,
     pub publisher: Option<async_nats::Client>,
+    pub bucket: Option<Bucket>,
+    pub layout: Option<RowLayout>,
     pub serializer: ArrowSerializer,
 }
 
+/// The JetStream bucket written to by the key-value and object store sinks
+pub enum Bucket {
+    KeyValue(kv::Store),
+    ObjectStore(object_store::ObjectStore),
+}
+
+/// How rows are laid out in the input batches
+#[derive(Clone)]
+pub enum RowLayout {
+    /// an append-only stream, where every row is an insert
+    Append { field: usize },
+    /// the output of an updating query, as debezium-style before/after/op columns
+    Debezium {
+        before: usize,
+        after: usize,
+        op: usize,
+    },
+}
+
+impl RowLayout {
+    /// Determines the layout of the input, which must contain `field` (the key or object name)
+    fn for_schema(schema: &Schema, field: &str) -> anyhow::Result<Self> {
+        if let (Ok(before), Ok(after), Ok(op)) = (
+            schema.index_of("before"),
+            schema.index_of("after"),
+            schema.index_of("op"),
+        ) {
+            let DataType::Struct(fields) = schema.field(after).data_type() else {
+                anyhow::bail!("expected 'after' column to be a struct");
+            };
+
+            if fields.find(field).is_none() {
+                anyhow::bail!("field '{}' is not a field in the table", field);
+            }
+
+            return Ok(RowLayout::Debezium { before, after, op });
+        }
+
+        Ok(RowLayout::Append {
+            field: schema
+                .index_of(field)
+                .map_err(|_| anyhow::anyhow!("field '{}' is not a field in the table", field))?,
+        })
+    }
+}
+
+/// Keys and object names are written as the string representation of their column
+fn key_strings(array: &ArrayRef) -> StringArray {
+    cast(array, &DataType::Utf8)
+        .expect("key field cannot be converted to a string")
+        .as_string::<i32>()
+        .clone()
+}
+
+fn struct_keys(array: &StructArray, field: &str) -> StringArray {
+    key_strings(array.column_by_name(field).unwrap())
+}
+
+/// Reports the error to the controller and panics
+async fn fail(ctx: &mut ArrowContext, message: &str, e: impl Display) {
+    ctx.control_tx
+        .send(ControlResp::Error {
+            operator_id: ctx.task_info.operator_id.clone(),
+            task_index: ctx.task_info.task_index,
+            message: message.to_string(),
+            details: e.to_string(),
+        })
+        .await
+        .expect("Something went wrong, data will never be received.");
+    panic!("{}: {}", message, e);
+}
+
 #[async_trait]
 impl ArrowOperator for NatsSinkFunc {
     fn name(&self) -> String {
-        format!(
-            "nats-publisher-{}",
-            match &self.sink_type {
-                SinkType::Subject(s) => {
-                    s
-                }
-            }
-        )
+        match &self.sink_type {
+            SinkType::Subject(s) => format!("nats-publisher-{}", s),
+            SinkType::KeyValue { bucket, .. } => format!("nats-kv-{}", bucket),
+            SinkType::ObjectStore { bucket, .. } => format!("nats-object-store-{}", bucket),
+        }
     }
 
     fn tables(&self) -> HashMap<String, TableConfig> {
         HashMap::new()
     }
 
-    async fn on_start(&mut self, _ctx: &mut ArrowContext) {
-        match get_nats_client(&self.connection).await {
-            Ok(client) => {
-                self.publisher = Some(client);
-            }
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let client = match get_nats_client(&self.connection).await {
+            Ok(client) => client,
             Err(e) => {
                 panic!("Failed to construct NATS publisher: {:?}", e);
             }
+        };
+
+        let jetstream = async_nats::jetstream::new(client.clone());
+        let (bucket, field) = match &self.sink_type {
+            SinkType::Subject(_) => (None, None),
+            SinkType::KeyValue { bucket, key_field } => (
+                Some(Bucket::KeyValue(
+                    jetstream.get_key_value(bucket).await.unwrap_or_else(|e| {
+                        panic!("Failed to open NATS key-value bucket '{}': {}", bucket, e)
+                    }),
+                )),
+                Some(key_field),
+            ),
+            SinkType::ObjectStore { bucket, name_field } => (
+                Some(Bucket::ObjectStore(
+                    jetstream
+                        .get_object_store(bucket)
+                        .await
+                        .unwrap_or_else(|e| {
+                            panic!("Failed to open NATS object store '{}': {}", bucket, e)
+                        }),
+                )),
+                Some(name_field),
+            ),
+        };
+
+        if let Some(field) = field {
+            match RowLayout::for_schema(&ctx.in_schemas[0].schema, field) {
+                Ok(layout) => self.layout = Some(layout),
+                Err(e) => fail(ctx, "Invalid schema for NATS sink", e).await,
+            }
         }
+
+        self.publisher = Some(client);
+        self.bucket = bucket;
     }
 
     async fn on_close(&mut self, _: &Option<SignalMessage>, ctx: &mut ArrowContext) {
@@ -75,8 +183,17 @@ impl ArrowOperator for NatsSinkFunc {
     }
 
     async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
-        let SinkType::Subject(s) = &self.sink_type;
-        let nats_subject = async_nats::Subject::from(s.clone());
+        let nats_subject = match &self.sink_type {
+            SinkType::Subject(s) => async_nats::Subject::from(s.clone()),
+            SinkType::KeyValue { key_field, .. } => {
+                let key_field = key_field.clone();
+                return self.write_key_value(&batch, &key_field, ctx).await;
+            }
+            SinkType::ObjectStore { .. } => {
+                return self.write_objects(&batch, ctx).await;
+            }
+        };
+
         for msg in self.serializer.serialize(&batch) {
             let publisher = self
                 .publisher
@@ -101,3 +218,279 @@ impl ArrowOperator for NatsSinkFunc {
         }
     }
 }
+
+/// A write to a key-value bucket
+#[derive(Debug, PartialEq)]
+enum KeyValueWrite {
+    Put(String, Vec<u8>),
+    Delete(String),
+}
+
+/// The writes that apply a batch to a key-value bucket: each row is put under its key, and for
+/// updating tables, retracted rows have their keys deleted, as do the old keys of updates that
+/// change the key
+fn key_value_writes(
+    layout: &RowLayout,
+    batch: &RecordBatch,
+    key_field: &str,
+    serializer: &mut ArrowSerializer,
+) -> Vec<KeyValueWrite> {
+    let mut writes = vec![];
+    match layout {
+        RowLayout::Append { field } => {
+            let keys = key_strings(batch.column(*field));
+            for (i, value) in serializer.serialize(batch).enumerate() {
+                if keys.is_null(i) {
+                    warn!("NATS key-value sink received a row with a null key; skipping");
+                    continue;
+                }
+                writes.push(KeyValueWrite::Put(keys.value(i).to_string(), value));
+            }
+        }
+        RowLayout::Debezium { before, after, op } => {
+            let before = batch.column(*before).as_struct();
+            let after = batch.column(*after).as_struct();
+            let ops = batch.column(*op).as_string::<i32>();
+
+            let old_keys = struct_keys(before, key_field);
+            let new_keys = struct_keys(after, key_field);
+            let values: Vec<_> = serializer.serialize(&struct_rows(after)).collect();
+
+            for (i, value) in values.into_iter().enumerate() {
+                let old_key = old_keys.is_valid(i).then(|| old_keys.value(i));
+                let new_key = new_keys.is_valid(i).then(|| new_keys.value(i));
+
+                let (delete, put) = match ops.value(i) {
+                    "c" | "r" => (None, new_key),
+                    "u" => (old_key.filter(|k| Some(*k) != new_key), new_key),
+                    "d" => (old_key, None),
+                    op => {
+                        warn!(
+                            "unknown debezium op '{}' in NATS key-value sink; ignoring",
+                            op
+                        );
+                        continue;
+                    }
+                };
+
+                if let Some(key) = delete {
+                    writes.push(KeyValueWrite::Delete(key.to_string()));
+                }
+                if let Some(key) = put {
+                    writes.push(KeyValueWrite::Put(key.to_string(), value));
+                }
+            }
+        }
+    }
+    writes
+}
+
+/// The objects to write for a batch, each named by the value of the row's name field
+fn objects(
+    field: usize,
+    batch: &RecordBatch,
+    serializer: &mut ArrowSerializer,
+) -> Vec<(String, Vec<u8>)> {
+    let names = key_strings(batch.column(field));
+    serializer
+        .serialize(batch)
+        .enumerate()
+        .filter_map(|(i, value)| {
+            if names.is_null(i) {
+                warn!("NATS object store sink received a row with a null name; skipping");
+                return None;
+            }
+            Some((names.value(i).to_string(), value))
+        })
+        .collect()
+}
+
+impl NatsSinkFunc {
+    async fn write_key_value(
+        &mut self,
+        batch: &RecordBatch,
+        key_field: &str,
+        ctx: &mut ArrowContext,
+    ) {
+        let Some(Bucket::KeyValue(store)) = &self.bucket else {
+            unreachable!("key-value sink has no key-value bucket");
+        };
+
+        let layout = self.layout.as_ref().unwrap();
+        for write in key_value_writes(layout, batch, key_field, &mut self.serializer) {
+            match write {
+                KeyValueWrite::Put(key, value) => {
+                    if let Err(e) = store.put(key, value.into()).await {
+                        fail(ctx, "Failed to write to NATS key-value bucket", e).await;
+                    }
+                }
+                KeyValueWrite::Delete(key) => {
+                    if let Err(e) = store.delete(key).await {
+                        fail(ctx, "Failed to delete from NATS key-value bucket", e).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn write_objects(&mut self, batch: &RecordBatch, ctx: &mut ArrowContext) {
+        let Some(Bucket::ObjectStore(store)) = &self.bucket else {
+            unreachable!("object store sink has no object store bucket");
+        };
+
+        let Some(RowLayout::Append { field }) = self.layout else {
+            unreachable!("object store sinks only support append-only tables");
+        };
+
+        for (name, value) in objects(field, batch, &mut self.serializer) {
+            if let Err(e) = store.put(name.as_str(), &mut value.as_slice()).await {
+                fail(ctx, "Failed to write to NATS object store", e).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, TimestampNanosecondArray};
+    use arrow::datatypes::{Field, Fields, TimeUnit};
+    use arroyo_rpc::formats::{Format, JsonFormat};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn fields() -> Fields {
+        Fields::from(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("value", DataType::Utf8, true),
+        ])
+    }
+
+    fn rows(ids: Vec<Option<i64>>, values: Vec<Option<&str>>) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(Int64Array::from(ids)),
+            Arc::new(StringArray::from(values)),
+        ]
+    }
+
+    fn json_serializer() -> ArrowSerializer {
+        ArrowSerializer::new(Format::Json(JsonFormat::default()))
+    }
+
+    fn parse(value: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(value).unwrap()
+    }
+
+    fn append_batch(ids: Vec<Option<i64>>, values: Vec<Option<&str>>) -> RecordBatch {
+        let mut schema_fields: Vec<_> = fields().iter().cloned().collect();
+        schema_fields.push(Arc::new(Field::new(
+            "_timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        )));
+        let len = ids.len();
+        let mut columns = rows(ids, values);
+        columns.push(Arc::new(TimestampNanosecondArray::from(vec![0; len])));
+        RecordBatch::try_new(Arc::new(Schema::new(schema_fields)), columns).unwrap()
+    }
+
+    #[test]
+    fn test_key_value_append() {
+        let batch = append_batch(
+            vec![Some(1), None, Some(2)],
+            vec![Some("a"), Some("b"), Some("c")],
+        );
+        let layout = RowLayout::for_schema(&batch.schema(), "id").unwrap();
+
+        let writes = key_value_writes(&layout, &batch, "id", &mut json_serializer());
+        let [KeyValueWrite::Put(k1, v1), KeyValueWrite::Put(k2, v2)] = writes.as_slice() else {
+            panic!("expected two puts, got {:?}", writes);
+        };
+
+        assert_eq!(k1, "1");
+        assert_eq!(parse(v1), json!({"id": 1, "value": "a"}));
+        assert_eq!(k2, "2");
+        assert_eq!(parse(v2), json!({"id": 2, "value": "c"}));
+    }
+
+    #[test]
+    fn test_key_value_updates_and_deletes() {
+        let struct_type = DataType::Struct(fields());
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("before", struct_type.clone(), true),
+            Field::new("after", struct_type, true),
+            Field::new("op", DataType::Utf8, false),
+        ]));
+
+        let before = StructArray::new(
+            fields(),
+            rows(
+                vec![None, Some(1), Some(2), Some(3)],
+                vec![None, Some("a"), Some("b"), Some("c")],
+            ),
+            Some(vec![false, true, true, true].into()),
+        );
+        let after = StructArray::new(
+            fields(),
+            rows(
+                vec![Some(1), Some(1), Some(4), None],
+                vec![Some("a"), Some("a2"), Some("b2"), None],
+            ),
+            Some(vec![true, true, true, false].into()),
+        );
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(before),
+                Arc::new(after),
+                Arc::new(StringArray::from(vec!["c", "u", "u", "d"])),
+            ],
+        )
+        .unwrap();
+
+        let layout = RowLayout::for_schema(&schema, "id").unwrap();
+        assert!(matches!(layout, RowLayout::Debezium { .. }));
+
+        let writes: Vec<_> = key_value_writes(&layout, &batch, "id", &mut json_serializer())
+            .into_iter()
+            .map(|write| match write {
+                KeyValueWrite::Put(key, value) => (key, Some(parse(&value))),
+                KeyValueWrite::Delete(key) => (key, None),
+            })
+            .collect();
+
+        assert_eq!(
+            writes,
+            vec![
+                // an insert is a put
+                ("1".to_string(), Some(json!({"id": 1, "value": "a"}))),
+                // an update that keeps its key is a put
+                ("1".to_string(), Some(json!({"id": 1, "value": "a2"}))),
+                // an update that changes its key deletes the old key
+                ("2".to_string(), None),
+                ("4".to_string(), Some(json!({"id": 4, "value": "b2"}))),
+                // a delete only deletes
+                ("3".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_objects() {
+        let batch = append_batch(
+            vec![Some(1), Some(2), Some(3)],
+            vec![Some("a"), None, Some("c")],
+        );
+        let Ok(RowLayout::Append { field }) = RowLayout::for_schema(&batch.schema(), "value")
+        else {
+            panic!("expected an append layout");
+        };
+
+        let objects = objects(field, &batch, &mut json_serializer());
+        let names: Vec<_> = objects.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["a", "c"]);
+        assert_eq!(parse(&objects[1].1), json!({"id": 3, "value": "c"}));
+
+        assert!(RowLayout::for_schema(&batch.schema(), "missing").is_err());
+    }
+}
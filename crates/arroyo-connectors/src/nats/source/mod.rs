@@ -5,6 +5,7 @@ use super::NatsState;
 use super::NatsTable;
 use super::ReplayPolicy;
 use super::{get_nats_client, SourceType};
+use arroyo_formats::de::FieldValueType;
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
@@ -36,6 +37,7 @@ pub struct NatsSourceFunc {
     pub framing: Option<Framing>,
     pub bad_data: Option<BadData>,
     pub messages_per_second: NonZeroU32,
+    pub metadata_fields: Option<HashMap<String, String>>,
 }
 
 #[async_trait]
@@ -117,9 +119,39 @@ impl NatsSourceFunc {
                     .unwrap_or(u32::MAX),
             )
             .unwrap(),
+            metadata_fields: config.additional_fields,
         }
     }
 
+    /// Builds the additional fields for a message from the metadata fields requested by the
+    /// table; `headers` is only computed if one of those fields needs it
+    fn connector_metadata<'a>(
+        &'a self,
+        message: &'a async_nats::Message,
+        headers: &'a mut Option<String>,
+    ) -> Option<HashMap<&'a String, FieldValueType<'a>>> {
+        let fields = self.metadata_fields.as_ref()?;
+
+        if fields.values().any(|v| v == "headers") {
+            *headers = Some(headers_to_json(message.headers.as_ref()));
+        }
+        let headers: &'a Option<String> = headers;
+
+        Some(
+            fields
+                .iter()
+                .filter_map(move |(k, v)| {
+                    let value = match v.as_str() {
+                        "subject" => FieldValueType::OptionalString(Some(message.subject.as_str())),
+                        "headers" => FieldValueType::OptionalString(headers.as_deref()),
+                        _ => return None,
+                    };
+                    Some((k, value))
+                })
+                .collect(),
+        )
+    }
+
     async fn get_nats_stream(
         &mut self,
         client: async_nats::Client,
@@ -366,7 +398,9 @@ impl NatsSourceFunc {
                                     let payload = msg.payload.as_ref();
                                     let message_info = msg.info().expect("Couldn't get message information");
                                     let timestamp = message_info.published.into() ;
-                                    ctx.deserialize_slice(payload, timestamp, None).await?;
+                                    let mut headers = None;
+                                    let connector_metadata = self.connector_metadata(&msg, &mut headers);
+                                    ctx.deserialize_slice(payload, timestamp, connector_metadata).await?;
 
                                     debug!("---------------------------------------------->");
                                     debug!(
@@ -492,7 +526,9 @@ impl NatsSourceFunc {
                                 Some(msg) => {
                                     let payload = msg.payload.as_ref();
                                     let timestamp = SystemTime::now();
-                                    ctx.deserialize_slice(payload, timestamp, None).await?;
+                                    let mut headers = None;
+                                    let connector_metadata = self.connector_metadata(&msg, &mut headers);
+                                    ctx.deserialize_slice(payload, timestamp, connector_metadata).await?;
                                     if ctx.should_flush() {
                                         ctx.flush_buffer().await?;
                                     }
@@ -540,3 +576,19 @@ impl NatsSourceFunc {
         }
     }
 }
+
+/// Encodes the headers of a message as a JSON object of header names to their list of values
+fn headers_to_json(headers: Option<&async_nats::HeaderMap>) -> String {
+    let headers: serde_json::Map<String, serde_json::Value> = headers
+        .into_iter()
+        .flat_map(|headers| headers.iter())
+        .map(|(name, values)| {
+            (
+                name.to_string(),
+                values.iter().map(|v| v.to_string()).collect(),
+            )
+        })
+        .collect();
+
+    serde_json::Value::Object(headers).to_string()
+}
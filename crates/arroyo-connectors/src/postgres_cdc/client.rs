@@ -0,0 +1,442 @@
+use anyhow::{anyhow, bail};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use postgres_protocol::authentication::md5_hash;
+use postgres_protocol::authentication::sasl::{ChannelBinding, ScramSha256, SCRAM_SHA_256};
+use postgres_protocol::message::frontend;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, warn};
+
+use super::PostgresCdcConfig;
+
+/// Seconds between the unix epoch and the Postgres epoch (2000-01-01)
+const POSTGRES_EPOCH_OFFSET_SECS: u64 = 946_684_800;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A minimal client for the Postgres streaming replication protocol. tokio-postgres does not
+/// support replication connections, so this speaks just enough of the frontend/backend protocol
+/// to authenticate, run simple queries and consume a logical replication stream.
+pub struct ReplicationClient {
+    stream: TcpStream,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+}
+
+/// A message received from the server while in the COPY BOTH replication sub-protocol
+#[derive(Debug)]
+pub enum ReplicationMessage {
+    XLogData {
+        wal_start: u64,
+        wal_end: u64,
+        data: Bytes,
+    },
+    Keepalive {
+        wal_end: u64,
+        reply_requested: bool,
+    },
+}
+
+impl ReplicationClient {
+    pub async fn connect(config: &PostgresCdcConfig) -> anyhow::Result<Self> {
+        let port = config.port.unwrap_or(5432);
+        let address = format!("{}:{}", config.host, port);
+        let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&address))
+            .await
+            .map_err(|_| anyhow!("timed out connecting to Postgres at {}", address))?
+            .map_err(|e| anyhow!("failed to connect to Postgres at {}: {}", address, e))?;
+
+        stream.set_nodelay(true)?;
+
+        let mut client = Self {
+            stream,
+            read_buf: BytesMut::with_capacity(8 * 1024),
+            write_buf: BytesMut::new(),
+        };
+
+        let username = config.username.sub_env_vars()?;
+        let password = config
+            .password
+            .as_ref()
+            .map(|p| p.sub_env_vars())
+            .transpose()?;
+
+        frontend::startup_message(
+            [
+                ("user", username.as_str()),
+                ("database", config.database.as_str()),
+                ("replication", "database"),
+                ("application_name", "arroyo"),
+            ],
+            &mut client.write_buf,
+        )?;
+        client.flush().await?;
+
+        client.authenticate(&username, password.as_deref()).await?;
+
+        // wait for the server to finish sending parameters
+        loop {
+            let (tag, body) = client.read_message().await?;
+            match tag {
+                b'Z' => break,
+                b'E' => bail!("{}", error_message(&body)),
+                _ => {}
+            }
+        }
+
+        Ok(client)
+    }
+
+    async fn authenticate(&mut self, username: &str, password: Option<&str>) -> anyhow::Result<()> {
+        let require_password =
+            || password.ok_or_else(|| anyhow!("server requested a password, but none was set"));
+
+        loop {
+            let (tag, mut body) = self.read_message().await?;
+            match tag {
+                b'R' => {}
+                b'E' => bail!("authentication failed: {}", error_message(&body)),
+                t => bail!("unexpected message '{}' during authentication", t as char),
+            }
+
+            match body.get_i32() {
+                // AuthenticationOk
+                0 => return Ok(()),
+                // AuthenticationCleartextPassword
+                3 => {
+                    frontend::password_message(
+                        require_password()?.as_bytes(),
+                        &mut self.write_buf,
+                    )?;
+                    self.flush().await?;
+                }
+                // AuthenticationMD5Password
+                5 => {
+                    let mut salt = [0; 4];
+                    body.copy_to_slice(&mut salt);
+                    let hash = md5_hash(username.as_bytes(), require_password()?.as_bytes(), salt);
+                    frontend::password_message(hash.as_bytes(), &mut self.write_buf)?;
+                    self.flush().await?;
+                }
+                // AuthenticationSASL
+                10 => {
+                    let mechanisms: Vec<_> = body
+                        .split(|b| *b == 0)
+                        .filter(|m| !m.is_empty())
+                        .map(|m| String::from_utf8_lossy(m).to_string())
+                        .collect();
+
+                    if !mechanisms.iter().any(|m| m == SCRAM_SHA_256) {
+                        bail!(
+                            "server requested unsupported SASL mechanisms: {}",
+                            mechanisms.join(", ")
+                        );
+                    }
+
+                    self.authenticate_scram(require_password()?).await?;
+                }
+                other => bail!(
+                    "unsupported authentication method requested by server ({})",
+                    other
+                ),
+            }
+        }
+    }
+
+    async fn authenticate_scram(&mut self, password: &str) -> anyhow::Result<()> {
+        let mut scram = ScramSha256::new(password.as_bytes(), ChannelBinding::unsupported());
+        frontend::sasl_initial_response(SCRAM_SHA_256, scram.message(), &mut self.write_buf)?;
+        self.flush().await?;
+
+        let mut body = self.expect_auth().await?;
+        if body.get_i32() != 11 {
+            bail!("expected SASL continue message from server");
+        }
+        scram.update(&body)?;
+        frontend::sasl_response(scram.message(), &mut self.write_buf)?;
+        self.flush().await?;
+
+        let mut body = self.expect_auth().await?;
+        if body.get_i32() != 12 {
+            bail!("expected SASL final message from server");
+        }
+        scram.finish(&body)?;
+
+        Ok(())
+    }
+
+    async fn expect_auth(&mut self) -> anyhow::Result<Bytes> {
+        match self.read_message().await? {
+            (b'R', body) => Ok(body),
+            (b'E', body) => bail!("authentication failed: {}", error_message(&body)),
+            (t, _) => bail!("unexpected message '{}' during authentication", t as char),
+        }
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        self.stream.write_all(&self.write_buf).await?;
+        self.write_buf.clear();
+        Ok(())
+    }
+
+    /// Reads a single message from the server, returning its tag and body. This is cancel-safe,
+    /// as partially-read messages are retained in the read buffer.
+    async fn read_message(&mut self) -> anyhow::Result<(u8, Bytes)> {
+        loop {
+            if self.read_buf.len() >= 5 {
+                let len = (&self.read_buf[1..5]).get_i32() as usize;
+                if len < 4 {
+                    bail!("invalid message length from server");
+                }
+
+                if self.read_buf.len() > len {
+                    let mut message = self.read_buf.split_to(len + 1).freeze();
+                    let tag = message.get_u8();
+                    message.advance(4);
+                    return Ok((tag, message));
+                }
+            }
+
+            if self.stream.read_buf(&mut self.read_buf).await? == 0 {
+                bail!("connection closed by server");
+            }
+        }
+    }
+
+    /// Runs a query using the simple query protocol, returning all rows in text format
+    pub async fn simple_query(&mut self, query: &str) -> anyhow::Result<Vec<Vec<Option<String>>>> {
+        debug!("running query: {}", query);
+        frontend::query(query, &mut self.write_buf)?;
+        self.flush().await?;
+
+        let mut rows = vec![];
+        let mut error = None;
+        loop {
+            let (tag, mut body) = self.read_message().await?;
+            match tag {
+                b'D' => {
+                    let columns = body.get_i16();
+                    let mut row = Vec::with_capacity(columns as usize);
+                    for _ in 0..columns {
+                        let len = body.get_i32();
+                        if len < 0 {
+                            row.push(None);
+                        } else {
+                            let value = body.split_to(len as usize);
+                            row.push(Some(String::from_utf8_lossy(&value).to_string()));
+                        }
+                    }
+                    rows.push(row);
+                }
+                b'E' => {
+                    error = Some(error_message(&body));
+                }
+                b'Z' => break,
+                _ => {}
+            }
+        }
+
+        match error {
+            Some(e) => bail!("{}", e),
+            None => Ok(rows),
+        }
+    }
+
+    /// Starts streaming changes from the slot using the `pgoutput` plugin. After this returns
+    /// successfully, the connection is in COPY BOTH mode and `next_message` should be used to
+    /// read changes.
+    pub async fn start_replication(
+        &mut self,
+        slot: &str,
+        publication: &str,
+        start_lsn: u64,
+    ) -> anyhow::Result<()> {
+        // the replication grammar doesn't accept E'' strings, which `quote_literal` produces for
+        // values containing backslashes
+        if publication.contains('\\') {
+            bail!(
+                "publication name '{}' can't be used for replication as it contains a backslash",
+                publication
+            );
+        }
+
+        let query = format!(
+            "START_REPLICATION SLOT {} LOGICAL {} (\"proto_version\" '1', \"publication_names\" {})",
+            quote_identifier(slot),
+            format_lsn(start_lsn),
+            quote_literal(publication)
+        );
+
+        debug!("running query: {}", query);
+        frontend::query(&query, &mut self.write_buf)?;
+        self.flush().await?;
+
+        loop {
+            let (tag, body) = self.read_message().await?;
+            match tag {
+                // CopyBothResponse
+                b'W' => return Ok(()),
+                b'E' => bail!("failed to start replication: {}", error_message(&body)),
+                b'N' => {
+                    warn!("notice from Postgres: {}", error_message(&body));
+                }
+                t => bail!(
+                    "unexpected message '{}' while starting replication",
+                    t as char
+                ),
+            }
+        }
+    }
+
+    /// Returns the next replication message, or None if the server has ended the stream
+    pub async fn next_message(&mut self) -> anyhow::Result<Option<ReplicationMessage>> {
+        loop {
+            let (tag, mut body) = self.read_message().await?;
+            match tag {
+                b'd' => match body.get_u8() {
+                    b'w' => {
+                        let wal_start = body.get_u64();
+                        let wal_end = body.get_u64();
+                        // server clock
+                        body.advance(8);
+                        return Ok(Some(ReplicationMessage::XLogData {
+                            wal_start,
+                            wal_end,
+                            data: body,
+                        }));
+                    }
+                    b'k' => {
+                        let wal_end = body.get_u64();
+                        body.advance(8);
+                        let reply_requested = body.get_u8() == 1;
+                        return Ok(Some(ReplicationMessage::Keepalive {
+                            wal_end,
+                            reply_requested,
+                        }));
+                    }
+                    t => {
+                        warn!("ignoring unknown replication message type '{}'", t as char);
+                    }
+                },
+                b'c' => return Ok(None),
+                b'E' => bail!("replication error: {}", error_message(&body)),
+                b'N' => {
+                    warn!("notice from Postgres: {}", error_message(&body));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Sends a standby status update, which tells the server that all changes up to `flushed_lsn`
+    /// have been durably processed and can be removed from the slot
+    pub async fn send_status_update(
+        &mut self,
+        received_lsn: u64,
+        flushed_lsn: u64,
+    ) -> anyhow::Result<()> {
+        let mut buf = BytesMut::with_capacity(34);
+        buf.put_u8(b'r');
+        buf.put_u64(received_lsn);
+        buf.put_u64(flushed_lsn);
+        buf.put_u64(flushed_lsn);
+        buf.put_i64(postgres_now());
+        buf.put_u8(0);
+
+        frontend::CopyData::new(buf.freeze())?.write(&mut self.write_buf);
+        self.flush().await
+    }
+}
+
+fn error_message(body: &[u8]) -> String {
+    let mut severity = None;
+    let mut message = None;
+    let mut detail = None;
+
+    for field in body.split(|b| *b == 0) {
+        let Some((t, value)) = field.split_first() else {
+            continue;
+        };
+        let value = String::from_utf8_lossy(value).to_string();
+        match *t {
+            b'S' => severity = Some(value),
+            b'M' => message = Some(value),
+            b'D' => detail = Some(value),
+            _ => {}
+        }
+    }
+
+    let mut s = format!(
+        "{}: {}",
+        severity.as_deref().unwrap_or("ERROR"),
+        message.as_deref().unwrap_or("unknown error")
+    );
+    if let Some(detail) = detail {
+        s.push_str(&format!(" ({})", detail));
+    }
+    s
+}
+
+fn postgres_now() -> i64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    since_epoch.as_micros() as i64 - (POSTGRES_EPOCH_OFFSET_SECS * 1_000_000) as i64
+}
+
+/// Converts a timestamp in microseconds since the Postgres epoch to a SystemTime
+pub fn from_postgres_micros(micros: i64) -> SystemTime {
+    let unix_micros = micros + (POSTGRES_EPOCH_OFFSET_SECS * 1_000_000) as i64;
+    if unix_micros >= 0 {
+        UNIX_EPOCH + Duration::from_micros(unix_micros as u64)
+    } else {
+        UNIX_EPOCH
+    }
+}
+
+pub fn format_lsn(lsn: u64) -> String {
+    format!("{:X}/{:X}", lsn >> 32, lsn & 0xFFFF_FFFF)
+}
+
+pub fn parse_lsn(s: &str) -> anyhow::Result<u64> {
+    let (high, low) = s
+        .split_once('/')
+        .ok_or_else(|| anyhow!("invalid LSN '{}'", s))?;
+    let high = u64::from_str_radix(high, 16).map_err(|_| anyhow!("invalid LSN '{}'", s))?;
+    let low = u64::from_str_radix(low, 16).map_err(|_| anyhow!("invalid LSN '{}'", s))?;
+    Ok((high << 32) | low)
+}
+
+pub fn quote_identifier(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+/// Quotes a string literal for use in SQL, as Postgres's `quote_literal` does
+pub fn quote_literal(s: &str) -> String {
+    if s.contains('\\') {
+        format!("E'{}'", s.replace('\\', "\\\\").replace('\'', "''"))
+    } else {
+        format!("'{}'", s.replace('\'', "''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_lsn, parse_lsn, quote_literal};
+
+    #[test]
+    fn test_lsn_round_trip() {
+        assert_eq!(parse_lsn("0/0").unwrap(), 0);
+        assert_eq!(parse_lsn("16/B374D848").unwrap(), 0x16_B374_D848);
+        assert_eq!(format_lsn(0x16_B374_D848), "16/B374D848");
+        assert!(parse_lsn("16B374D848").is_err());
+    }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("arroyo_pub"), "'arroyo_pub'");
+        assert_eq!(quote_literal("x' OR '1'='1"), "'x'' OR ''1''=''1'");
+        assert_eq!(quote_literal("a\\'b"), "E'a\\\\''b'");
+    }
+}
@@ -0,0 +1,247 @@
+mod client;
+mod pgoutput;
+mod source;
+#[cfg(test)]
+mod test;
+
+use anyhow::{anyhow, bail};
+use arrow::datatypes::DataType;
+use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::operator::OperatorNode;
+use arroyo_rpc::api_types::connections::{
+    ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
+};
+use arroyo_rpc::formats::{Format, JsonFormat};
+use arroyo_rpc::var_str::VarStr;
+use arroyo_rpc::OperatorConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot::Receiver;
+use typify::import_types;
+
+use crate::postgres_cdc::client::ReplicationClient;
+use crate::postgres_cdc::source::PostgresCdcSourceFunc;
+use crate::{pull_opt, pull_option_to_i64};
+
+const CONFIG_SCHEMA: &str = include_str!("./profile.json");
+const TABLE_SCHEMA: &str = include_str!("./table.json");
+const ICON: &str = include_str!("./postgres.svg");
+
+import_types!(
+    schema = "src/postgres_cdc/profile.json",
+    convert = {
+        {type = "string", format = "var-str"} = VarStr
+    }
+);
+
+import_types!(schema = "src/postgres_cdc/table.json");
+
+pub struct PostgresCdcConnector {}
+
+async fn test_inner(
+    config: PostgresCdcConfig,
+    tx: Sender<TestSourceMessage>,
+) -> anyhow::Result<String> {
+    let mut client = ReplicationClient::connect(&config).await?;
+
+    tx.send(TestSourceMessage::info("Connected to Postgres"))
+        .await
+        .unwrap();
+
+    client
+        .simple_query("IDENTIFY_SYSTEM")
+        .await
+        .map_err(|e| anyhow!("failed to open replication connection: {}", e))?;
+
+    let wal_level = client
+        .simple_query("SHOW wal_level")
+        .await?
+        .into_iter()
+        .next()
+        .and_then(|row| row.into_iter().next().flatten())
+        .ok_or_else(|| anyhow!("could not determine wal_level"))?;
+
+    if wal_level != "logical" {
+        bail!(
+            "wal_level must be set to 'logical' for change data capture, but is '{}'",
+            wal_level
+        );
+    }
+
+    Ok("Successfully validated replication connection".to_string())
+}
+
+impl Connector for PostgresCdcConnector {
+    type ProfileT = PostgresCdcConfig;
+    type TableT = PostgresCdcTable;
+
+    fn name(&self) -> &'static str {
+        "postgres_cdc"
+    }
+
+    fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector {
+        arroyo_rpc::api_types::connections::Connector {
+            id: "postgres_cdc".to_string(),
+            name: "Postgres CDC".to_string(),
+            icon: ICON.to_string(),
+            description: "Read changes from Postgres via logical replication".to_string(),
+            enabled: true,
+            source: true,
+            sink: false,
+            testing: true,
+            hidden: false,
+            custom_schemas: true,
+            connection_config: Some(CONFIG_SCHEMA.to_string()),
+            table_config: TABLE_SCHEMA.to_string(),
+        }
+    }
+
+    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
+        ConnectionType::Source
+    }
+
+    fn test_profile(&self, profile: Self::ProfileT) -> Option<Receiver<TestSourceMessage>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        tokio::spawn(async move {
+            let (itx, _rx) = tokio::sync::mpsc::channel(8);
+            let message = match test_inner(profile, itx).await {
+                Ok(_) => TestSourceMessage::done("Successfully connected to Postgres"),
+                Err(e) => {
+                    TestSourceMessage::fail(format!("Failed to connect to Postgres: {:?}", e))
+                }
+            };
+
+            tx.send(message).unwrap();
+        });
+
+        Some(rx)
+    }
+
+    fn test(
+        &self,
+        _: &str,
+        config: Self::ProfileT,
+        _: Self::TableT,
+        _: Option<&ConnectionSchema>,
+        tx: Sender<TestSourceMessage>,
+    ) {
+        tokio::task::spawn(async move {
+            let resp = match test_inner(config, tx.clone()).await {
+                Ok(c) => TestSourceMessage::done(c),
+                Err(e) => TestSourceMessage::fail(e.to_string()),
+            };
+
+            tx.send(resp).await.unwrap();
+        });
+    }
+
+    fn from_options(
+        &self,
+        name: &str,
+        options: &mut HashMap<String, String>,
+        schema: Option<&ConnectionSchema>,
+        profile: Option<&ConnectionProfile>,
+        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+    ) -> anyhow::Result<Connection> {
+        let connection = match profile {
+            Some(connection_profile) => {
+                serde_json::from_value(connection_profile.config.clone())
+                    .map_err(|e| anyhow!("Failed to parse connection config: {:?}", e))?
+            }
+            None => PostgresCdcConfig {
+                host: pull_opt("host", options)?,
+                port: pull_option_to_i64("port", options)?,
+                database: pull_opt("database", options)?,
+                username: VarStr::new(pull_opt("username", options)?),
+                password: options.remove("password").map(VarStr::new),
+            },
+        };
+
+        let table = PostgresCdcTable {
+            table: pull_opt("table", options)?,
+            slot: pull_opt("slot", options)?,
+            publication: pull_opt("publication", options)?,
+        };
+
+        self.from_config(None, name, connection, table, schema, None)
+    }
+
+    fn from_config(
+        &self,
+        id: Option<i64>,
+        name: &str,
+        config: Self::ProfileT,
+        table: Self::TableT,
+        schema: Option<&ConnectionSchema>,
+        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+    ) -> anyhow::Result<Connection> {
+        let schema = schema
+            .map(|s| s.to_owned())
+            .ok_or_else(|| anyhow!("No schema defined for Postgres CDC connection"))?;
+
+        let format = schema
+            .format
+            .as_ref()
+            .map(|t| t.to_owned())
+            .ok_or_else(|| anyhow!("'format' must be set for Postgres CDC connection"))?;
+
+        if !matches!(format, Format::Json(JsonFormat { debezium: true, .. })) {
+            bail!("Postgres CDC sources must use the 'debezium_json' format");
+        }
+
+        for (field, value) in [("slot", &table.slot), ("publication", &table.publication)] {
+            if value.is_empty()
+                || !value
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            {
+                bail!(
+                    "invalid {} name '{}'; may only contain lowercase letters, numbers and underscores",
+                    field,
+                    value
+                );
+            }
+        }
+
+        let description = format!("PostgresCdcSource<{}>", table.table);
+
+        let config = OperatorConfig {
+            connection: serde_json::to_value(config).unwrap(),
+            table: serde_json::to_value(table).unwrap(),
+            rate_limit: None,
+            format: Some(format),
+            bad_data: schema.bad_data.clone(),
+            framing: schema.framing.clone(),
+            additional_fields: None,
+        };
+
+        Ok(Connection {
+            id,
+            connector: self.name(),
+            name: name.to_string(),
+            connection_type: ConnectionType::Source,
+            schema,
+            config: serde_json::to_string(&config).unwrap(),
+            description,
+        })
+    }
+
+    fn make_operator(
+        &self,
+        profile: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+    ) -> anyhow::Result<OperatorNode> {
+        Ok(OperatorNode::from_source(Box::new(PostgresCdcSourceFunc {
+            config: profile,
+            table,
+            format: config
+                .format
+                .expect("Format must be set for Postgres CDC source"),
+            framing: config.framing,
+            bad_data: config.bad_data,
+        })))
+    }
+}
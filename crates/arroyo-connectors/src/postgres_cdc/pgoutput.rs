@@ -0,0 +1,485 @@
+use anyhow::{anyhow, bail};
+use bytes::{Buf, Bytes};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::{json, Map, Value};
+
+// type oids from pg_type.dat
+const BOOL_OID: u32 = 16;
+const INT8_OID: u32 = 20;
+const INT2_OID: u32 = 21;
+const INT4_OID: u32 = 23;
+const OID_OID: u32 = 26;
+const FLOAT4_OID: u32 = 700;
+const FLOAT8_OID: u32 = 701;
+const NUMERIC_OID: u32 = 1700;
+const TIMESTAMP_OID: u32 = 1114;
+const TIMESTAMPTZ_OID: u32 = 1184;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelationColumn {
+    pub name: String,
+    pub type_oid: u32,
+    pub is_key: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relation {
+    pub oid: u32,
+    pub namespace: String,
+    pub name: String,
+    pub columns: Vec<RelationColumn>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TupleValue {
+    Null,
+    /// An unchanged TOASTed value; the actual value is not sent by the server
+    Unchanged,
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgOutputMessage {
+    Begin {
+        final_lsn: u64,
+        commit_timestamp: i64,
+    },
+    Commit {
+        commit_lsn: u64,
+        end_lsn: u64,
+        commit_timestamp: i64,
+    },
+    Relation(Relation),
+    Insert {
+        relation: u32,
+        new: Vec<TupleValue>,
+    },
+    Update {
+        relation: u32,
+        old: Option<Vec<TupleValue>>,
+        new: Vec<TupleValue>,
+    },
+    Delete {
+        relation: u32,
+        old: Vec<TupleValue>,
+    },
+    Truncate {
+        relations: Vec<u32>,
+    },
+    /// Origin, Type and Message records, which we don't need to act on
+    Other(u8),
+}
+
+fn get_cstr(buf: &mut Bytes) -> anyhow::Result<String> {
+    let end = memchr(buf)?;
+    let s = String::from_utf8_lossy(&buf[..end]).to_string();
+    buf.advance(end + 1);
+    Ok(s)
+}
+
+fn memchr(buf: &[u8]) -> anyhow::Result<usize> {
+    buf.iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| anyhow!("unterminated string in pgoutput message"))
+}
+
+fn check_remaining(buf: &Bytes, n: usize) -> anyhow::Result<()> {
+    if buf.remaining() < n {
+        bail!("truncated pgoutput message");
+    }
+    Ok(())
+}
+
+fn parse_tuple(buf: &mut Bytes) -> anyhow::Result<Vec<TupleValue>> {
+    check_remaining(buf, 2)?;
+    let columns = buf.get_i16();
+    let mut values = Vec::with_capacity(columns.max(0) as usize);
+
+    for _ in 0..columns {
+        check_remaining(buf, 1)?;
+        values.push(match buf.get_u8() {
+            b'n' => TupleValue::Null,
+            b'u' => TupleValue::Unchanged,
+            b't' => {
+                check_remaining(buf, 4)?;
+                let len = buf.get_i32() as usize;
+                check_remaining(buf, len)?;
+                let value = buf.split_to(len);
+                TupleValue::Text(String::from_utf8_lossy(&value).to_string())
+            }
+            t => bail!("unsupported tuple data type '{}'", t as char),
+        });
+    }
+
+    Ok(values)
+}
+
+impl PgOutputMessage {
+    pub fn parse(mut buf: Bytes) -> anyhow::Result<Self> {
+        check_remaining(&buf, 1)?;
+        let tag = buf.get_u8();
+
+        Ok(match tag {
+            b'B' => {
+                check_remaining(&buf, 20)?;
+                let final_lsn = buf.get_u64();
+                let commit_timestamp = buf.get_i64();
+                PgOutputMessage::Begin {
+                    final_lsn,
+                    commit_timestamp,
+                }
+            }
+            b'C' => {
+                check_remaining(&buf, 25)?;
+                // flags, currently unused
+                buf.advance(1);
+                let commit_lsn = buf.get_u64();
+                let end_lsn = buf.get_u64();
+                let commit_timestamp = buf.get_i64();
+                PgOutputMessage::Commit {
+                    commit_lsn,
+                    end_lsn,
+                    commit_timestamp,
+                }
+            }
+            b'R' => {
+                check_remaining(&buf, 4)?;
+                let oid = buf.get_u32();
+                let namespace = get_cstr(&mut buf)?;
+                let name = get_cstr(&mut buf)?;
+                check_remaining(&buf, 3)?;
+                // replica identity setting
+                buf.advance(1);
+                let n = buf.get_i16();
+                let mut columns = Vec::with_capacity(n.max(0) as usize);
+                for _ in 0..n {
+                    check_remaining(&buf, 1)?;
+                    let flags = buf.get_u8();
+                    let name = get_cstr(&mut buf)?;
+                    check_remaining(&buf, 8)?;
+                    let type_oid = buf.get_u32();
+                    // type modifier
+                    buf.advance(4);
+                    columns.push(RelationColumn {
+                        name,
+                        type_oid,
+                        is_key: flags & 1 == 1,
+                    });
+                }
+
+                PgOutputMessage::Relation(Relation {
+                    oid,
+                    namespace: if namespace.is_empty() {
+                        "pg_catalog".to_string()
+                    } else {
+                        namespace
+                    },
+                    name,
+                    columns,
+                })
+            }
+            b'I' => {
+                check_remaining(&buf, 5)?;
+                let relation = buf.get_u32();
+                if buf.get_u8() != b'N' {
+                    bail!("expected new tuple in insert message");
+                }
+                PgOutputMessage::Insert {
+                    relation,
+                    new: parse_tuple(&mut buf)?,
+                }
+            }
+            b'U' => {
+                check_remaining(&buf, 5)?;
+                let relation = buf.get_u32();
+                let mut old = None;
+                let mut t = buf.get_u8();
+                if t == b'K' || t == b'O' {
+                    old = Some(parse_tuple(&mut buf)?);
+                    check_remaining(&buf, 1)?;
+                    t = buf.get_u8();
+                }
+                if t != b'N' {
+                    bail!("expected new tuple in update message");
+                }
+                PgOutputMessage::Update {
+                    relation,
+                    old,
+                    new: parse_tuple(&mut buf)?,
+                }
+            }
+            b'D' => {
+                check_remaining(&buf, 5)?;
+                let relation = buf.get_u32();
+                let t = buf.get_u8();
+                if t != b'K' && t != b'O' {
+                    bail!("expected old tuple in delete message");
+                }
+                PgOutputMessage::Delete {
+                    relation,
+                    old: parse_tuple(&mut buf)?,
+                }
+            }
+            b'T' => {
+                check_remaining(&buf, 5)?;
+                let n = buf.get_i32();
+                // options
+                buf.advance(1);
+                check_remaining(&buf, 4 * n.max(0) as usize)?;
+                PgOutputMessage::Truncate {
+                    relations: (0..n).map(|_| buf.get_u32()).collect(),
+                }
+            }
+            t => PgOutputMessage::Other(t),
+        })
+    }
+}
+
+/// Converts a value in Postgres text format to JSON in a form that the Arrow JSON decoder can
+/// read into the corresponding SQL type
+fn value_to_json(type_oid: u32, value: &str) -> Value {
+    match type_oid {
+        BOOL_OID => Value::Bool(value == "t"),
+        INT2_OID | INT4_OID | INT8_OID | OID_OID => value
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(value.to_string())),
+        FLOAT4_OID | FLOAT8_OID | NUMERIC_OID => value
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(value.to_string())),
+        TIMESTAMP_OID => NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+            .map(|t| Value::String(t.and_utc().to_rfc3339()))
+            .unwrap_or_else(|_| Value::String(value.to_string())),
+        TIMESTAMPTZ_OID => DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z")
+            .map(|t| Value::String(t.with_timezone(&Utc).to_rfc3339()))
+            .unwrap_or_else(|_| Value::String(value.to_string())),
+        _ => Value::String(value.to_string()),
+    }
+}
+
+impl Relation {
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}", self.namespace, self.name)
+    }
+
+    /// Converts a tuple for this relation into a JSON object. Unchanged TOAST values are not sent
+    /// by Postgres, so they are omitted (and will be read as nulls).
+    pub fn tuple_to_json(&self, tuple: &[TupleValue]) -> Value {
+        let mut map = Map::new();
+        for (column, value) in self.columns.iter().zip(tuple) {
+            match value {
+                TupleValue::Null => {
+                    map.insert(column.name.clone(), Value::Null);
+                }
+                TupleValue::Unchanged => {}
+                TupleValue::Text(s) => {
+                    map.insert(column.name.clone(), value_to_json(column.type_oid, s));
+                }
+            }
+        }
+        Value::Object(map)
+    }
+
+    fn key_changed(&self, old: &[TupleValue], new: &[TupleValue]) -> bool {
+        self.columns
+            .iter()
+            .zip(old.iter().zip(new))
+            .any(|(c, (o, n))| c.is_key && *o != TupleValue::Null && o != n)
+    }
+
+    /// Converts a row-level change into one or more Debezium-style change records
+    pub fn to_debezium(&self, message: &PgOutputMessage) -> Vec<Value> {
+        match message {
+            PgOutputMessage::Insert { new, .. } => {
+                vec![json!({"before": null, "after": self.tuple_to_json(new), "op": "c"})]
+            }
+            PgOutputMessage::Update { old, new, .. } => match old {
+                // if the replica identity changed, this is a delete of the old key and an
+                // insert of the new one
+                Some(old) if self.key_changed(old, new) => vec![
+                    json!({"before": self.tuple_to_json(old), "after": null, "op": "d"}),
+                    json!({"before": null, "after": self.tuple_to_json(new), "op": "c"}),
+                ],
+                old => vec![json!({
+                    "before": old.as_ref().map(|o| self.tuple_to_json(o)),
+                    "after": self.tuple_to_json(new),
+                    "op": "u"
+                })],
+            },
+            PgOutputMessage::Delete { old, .. } => {
+                vec![json!({"before": self.tuple_to_json(old), "after": null, "op": "d"})]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::{BufMut, BytesMut};
+
+    fn relation() -> Relation {
+        Relation {
+            oid: 16384,
+            namespace: "public".to_string(),
+            name: "orders".to_string(),
+            columns: vec![
+                RelationColumn {
+                    name: "id".to_string(),
+                    type_oid: INT4_OID,
+                    is_key: true,
+                },
+                RelationColumn {
+                    name: "price".to_string(),
+                    type_oid: FLOAT8_OID,
+                    is_key: false,
+                },
+                RelationColumn {
+                    name: "created".to_string(),
+                    type_oid: TIMESTAMP_OID,
+                    is_key: false,
+                },
+                RelationColumn {
+                    name: "note".to_string(),
+                    type_oid: 25,
+                    is_key: false,
+                },
+            ],
+        }
+    }
+
+    fn put_cstr(buf: &mut BytesMut, s: &str) {
+        buf.put_slice(s.as_bytes());
+        buf.put_u8(0);
+    }
+
+    fn put_text(buf: &mut BytesMut, s: &str) {
+        buf.put_u8(b't');
+        buf.put_i32(s.len() as i32);
+        buf.put_slice(s.as_bytes());
+    }
+
+    #[test]
+    fn test_parse_relation() {
+        let mut buf = BytesMut::new();
+        buf.put_u8(b'R');
+        buf.put_u32(16384);
+        put_cstr(&mut buf, "public");
+        put_cstr(&mut buf, "orders");
+        buf.put_u8(b'd');
+        buf.put_i16(2);
+        buf.put_u8(1);
+        put_cstr(&mut buf, "id");
+        buf.put_u32(INT4_OID);
+        buf.put_i32(-1);
+        buf.put_u8(0);
+        put_cstr(&mut buf, "price");
+        buf.put_u32(FLOAT8_OID);
+        buf.put_i32(-1);
+
+        let PgOutputMessage::Relation(relation) = PgOutputMessage::parse(buf.freeze()).unwrap()
+        else {
+            panic!("expected relation");
+        };
+
+        assert_eq!(relation.qualified_name(), "public.orders");
+        assert_eq!(relation.columns.len(), 2);
+        assert!(relation.columns[0].is_key);
+        assert!(!relation.columns[1].is_key);
+        assert_eq!(relation.columns[1].type_oid, FLOAT8_OID);
+    }
+
+    #[test]
+    fn test_parse_update() {
+        let mut buf = BytesMut::new();
+        buf.put_u8(b'U');
+        buf.put_u32(16384);
+        buf.put_u8(b'N');
+        buf.put_i16(4);
+        put_text(&mut buf, "5");
+        put_text(&mut buf, "10.5");
+        buf.put_u8(b'n');
+        buf.put_u8(b'u');
+
+        let message = PgOutputMessage::parse(buf.freeze()).unwrap();
+        assert_eq!(
+            message,
+            PgOutputMessage::Update {
+                relation: 16384,
+                old: None,
+                new: vec![
+                    TupleValue::Text("5".to_string()),
+                    TupleValue::Text("10.5".to_string()),
+                    TupleValue::Null,
+                    TupleValue::Unchanged,
+                ],
+            }
+        );
+
+        assert_eq!(
+            relation().to_debezium(&message),
+            vec![json!({
+                "before": null,
+                "after": {"id": 5, "price": 10.5, "created": null},
+                "op": "u"
+            })]
+        );
+    }
+
+    #[test]
+    fn test_truncated_message() {
+        let mut buf = BytesMut::new();
+        buf.put_u8(b'I');
+        buf.put_u32(16384);
+        buf.put_u8(b'N');
+        buf.put_i16(1);
+        buf.put_u8(b't');
+        buf.put_i32(100);
+
+        assert!(PgOutputMessage::parse(buf.freeze()).is_err());
+    }
+
+    #[test]
+    fn test_key_change() {
+        let message = PgOutputMessage::Update {
+            relation: 16384,
+            old: Some(vec![
+                TupleValue::Text("1".to_string()),
+                TupleValue::Null,
+                TupleValue::Null,
+                TupleValue::Null,
+            ]),
+            new: vec![
+                TupleValue::Text("2".to_string()),
+                TupleValue::Text("1".to_string()),
+                TupleValue::Text("2024-03-01 10:30:00.5".to_string()),
+                TupleValue::Text("hello".to_string()),
+            ],
+        };
+
+        assert_eq!(
+            relation().to_debezium(&message),
+            vec![
+                json!({
+                    "before": {"id": 1, "price": null, "created": null, "note": null},
+                    "after": null,
+                    "op": "d"
+                }),
+                json!({
+                    "before": null,
+                    "after": {
+                        "id": 2,
+                        "price": 1.0,
+                        "created": "2024-03-01T10:30:00.500+00:00",
+                        "note": "hello"
+                    },
+                    "op": "c"
+                }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::SourceOperator;
+use arroyo_operator::SourceFinishType;
+use arroyo_rpc::formats::{BadData, Format, Framing};
+use arroyo_rpc::grpc::rpc::{StopMode, TableConfig};
+use arroyo_rpc::{ControlMessage, ControlResp};
+use arroyo_types::{ArrowMessage, SignalMessage, UserError, Watermark};
+use async_trait::async_trait;
+use bincode::{Decode, Encode};
+use tokio::select;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use super::client::{
+    format_lsn, from_postgres_micros, quote_identifier, quote_literal, ReplicationClient,
+    ReplicationMessage,
+};
+use super::pgoutput::{PgOutputMessage, Relation};
+use super::{PostgresCdcConfig, PostgresCdcTable};
+
+/// How often we send a status update to the server, which must happen more frequently than the
+/// server's `wal_sender_timeout` (60 seconds by default)
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Copy, Clone, Debug, Encode, Decode, PartialEq, PartialOrd)]
+pub struct PostgresCdcState {
+    /// the end LSN of the last transaction that was fully emitted
+    lsn: u64,
+}
+
+pub struct PostgresCdcSourceFunc {
+    pub config: PostgresCdcConfig,
+    pub table: PostgresCdcTable,
+    pub format: Format,
+    pub framing: Option<Framing>,
+    pub bad_data: Option<BadData>,
+}
+
+/// Tracks progress through the replication stream
+#[derive(Default)]
+struct LsnState {
+    /// the end LSN of the last transaction whose changes have been emitted
+    committed: u64,
+    /// the LSN that was stored in the previous checkpoint, which is the furthest point we can
+    /// safely confirm to the server; changes before it are discarded from the slot
+    confirmed: u64,
+    /// the LSN stored by the most recent checkpoint
+    checkpointed: u64,
+    /// the furthest point in the WAL that we've received
+    received: u64,
+}
+
+/// Changes from the transaction currently being received, which are buffered until its commit
+/// so that a checkpoint never contains part of a transaction
+#[derive(Default)]
+struct Transaction {
+    timestamp: Option<SystemTime>,
+    changes: Vec<Vec<u8>>,
+}
+
+#[async_trait]
+impl SourceOperator for PostgresCdcSourceFunc {
+    fn name(&self) -> String {
+        format!("postgres-cdc-{}", self.table.table)
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        arroyo_state::global_table_config("p", "postgres cdc source state")
+    }
+
+    async fn run(&mut self, ctx: &mut ArrowContext) -> SourceFinishType {
+        match self.run_int(ctx).await {
+            Ok(r) => r,
+            Err(e) => {
+                ctx.control_tx
+                    .send(ControlResp::Error {
+                        operator_id: ctx.task_info.operator_id.clone(),
+                        task_index: ctx.task_info.task_index,
+                        message: e.name.clone(),
+                        details: e.details.clone(),
+                    })
+                    .await
+                    .unwrap();
+
+                panic!("{}: {}", e.name, e.details);
+            }
+        }
+    }
+}
+
+impl PostgresCdcSourceFunc {
+    /// The schema-qualified name of the table, as reported in relation messages
+    fn qualified_table(&self) -> String {
+        if self.table.table.contains('.') {
+            self.table.table.clone()
+        } else {
+            format!("public.{}", self.table.table)
+        }
+    }
+
+    async fn setup(&self, client: &mut ReplicationClient) -> anyhow::Result<()> {
+        let publication = client
+            .simple_query(&format!(
+                "SELECT 1 FROM pg_publication WHERE pubname = {}",
+                quote_literal(&self.table.publication)
+            ))
+            .await?;
+
+        if publication.is_empty() {
+            let table = self
+                .qualified_table()
+                .split('.')
+                .map(quote_identifier)
+                .collect::<Vec<_>>()
+                .join(".");
+
+            info!(
+                "creating publication {} for table {}",
+                self.table.publication, table
+            );
+            client
+                .simple_query(&format!(
+                    "CREATE PUBLICATION {} FOR TABLE {}",
+                    quote_identifier(&self.table.publication),
+                    table
+                ))
+                .await?;
+        }
+
+        let slot = client
+            .simple_query(&format!(
+                "SELECT 1 FROM pg_replication_slots WHERE slot_name = {}",
+                quote_literal(&self.table.slot)
+            ))
+            .await?;
+
+        if slot.is_empty() {
+            info!("creating replication slot {}", self.table.slot);
+            client
+                .simple_query(&format!(
+                    "CREATE_REPLICATION_SLOT {} LOGICAL pgoutput NOEXPORT_SNAPSHOT",
+                    quote_identifier(&self.table.slot)
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn run_idle(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
+        // a replication slot can only be read by a single consumer, so only the first subtask
+        // reads changes
+        warn!(
+            "Postgres CDC source {}-{} has no work as only one subtask can read from a \
+            replication slot... setting idle",
+            ctx.task_info.operator_id, ctx.task_info.task_index
+        );
+        ctx.broadcast(ArrowMessage::Signal(SignalMessage::Watermark(
+            Watermark::Idle,
+        )))
+        .await;
+
+        loop {
+            match ctx.control_rx.recv().await {
+                Some(ControlMessage::Checkpoint(c)) => {
+                    if self.start_checkpoint(c, ctx).await {
+                        return Ok(SourceFinishType::Immediate);
+                    }
+                }
+                Some(ControlMessage::Stop { mode }) => {
+                    info!("Stopping postgres cdc source: {:?}", mode);
+                    return Ok(match mode {
+                        StopMode::Graceful => SourceFinishType::Graceful,
+                        StopMode::Immediate => SourceFinishType::Immediate,
+                    });
+                }
+                Some(ControlMessage::Commit { .. }) => {
+                    unreachable!("sources shouldn't receive commit messages");
+                }
+                Some(ControlMessage::LoadCompacted { compacted }) => {
+                    ctx.load_compacted(compacted).await;
+                }
+                Some(ControlMessage::NoOp) => {}
+                None => {
+                    return Ok(SourceFinishType::Immediate);
+                }
+            }
+        }
+    }
+
+    async fn run_int(&mut self, ctx: &mut ArrowContext) -> Result<SourceFinishType, UserError> {
+        if ctx.task_info.task_index != 0 {
+            return self.run_idle(ctx).await;
+        }
+
+        let restored = ctx
+            .table_manager
+            .get_global_keyed_state::<String, PostgresCdcState>("p")
+            .await
+            .map_err(|e| UserError::new("failed to load postgres cdc state", e.to_string()))?
+            .get(&self.table.slot)
+            .map(|s| s.lsn);
+
+        let mut lsn = LsnState::default();
+        if let Some(restored) = restored {
+            info!(
+                "restoring postgres cdc source from {}",
+                format_lsn(restored)
+            );
+            lsn.committed = restored;
+            lsn.confirmed = restored;
+            lsn.checkpointed = restored;
+        }
+
+        let mut client = ReplicationClient::connect(&self.config)
+            .await
+            .map_err(|e| UserError::new("Could not connect to Postgres", format!("{:?}", e)))?;
+
+        self.setup(&mut client).await.map_err(|e| {
+            UserError::new("Failed to set up Postgres replication", format!("{:?}", e))
+        })?;
+
+        client
+            .start_replication(&self.table.slot, &self.table.publication, lsn.committed)
+            .await
+            .map_err(|e| {
+                UserError::new("Failed to start Postgres replication", format!("{:?}", e))
+            })?;
+
+        info!(
+            "started replication from slot {} at {}",
+            self.table.slot,
+            format_lsn(lsn.committed)
+        );
+
+        ctx.initialize_deserializer(
+            self.format.clone(),
+            self.framing.clone(),
+            self.bad_data.clone(),
+        );
+
+        let table_name = self.qualified_table();
+        let mut relations: HashMap<u32, Relation> = HashMap::new();
+        let mut transaction = Transaction::default();
+
+        let mut flush_ticker = tokio::time::interval(Duration::from_millis(50));
+        flush_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut status_ticker = tokio::time::interval(STATUS_INTERVAL);
+        status_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            select! {
+                message = client.next_message() => {
+                    let message = message.map_err(|e| UserError::new("Error reading from Postgres", format!("{:?}", e)))?;
+                    match message {
+                        Some(ReplicationMessage::XLogData { wal_end, data, .. }) => {
+                            lsn.received = lsn.received.max(wal_end);
+
+                            let message = PgOutputMessage::parse(data)
+                                .map_err(|e| UserError::new("Invalid message from Postgres", format!("{:?}", e)))?;
+
+                            match &message {
+                                PgOutputMessage::Begin { commit_timestamp, .. } => {
+                                    transaction.timestamp = Some(from_postgres_micros(*commit_timestamp));
+                                    transaction.changes.clear();
+                                }
+                                PgOutputMessage::Commit { end_lsn, .. } => {
+                                    let timestamp = transaction.timestamp.take().unwrap_or_else(SystemTime::now);
+                                    let changes = std::mem::take(&mut transaction.changes);
+
+                                    // transactions at or before our restored position have already
+                                    // been emitted
+                                    if *end_lsn > lsn.committed {
+                                        for change in changes {
+                                            ctx.deserialize_slice(&change, timestamp, None).await?;
+                                        }
+                                        lsn.committed = *end_lsn;
+                                    }
+
+                                    if ctx.should_flush() {
+                                        ctx.flush_buffer().await?;
+                                    }
+                                }
+                                PgOutputMessage::Relation(relation) => {
+                                    relations.insert(relation.oid, relation.clone());
+                                }
+                                PgOutputMessage::Insert { relation, .. }
+                                | PgOutputMessage::Update { relation, .. }
+                                | PgOutputMessage::Delete { relation, .. } => {
+                                    let relation = relations.get(relation).ok_or_else(|| {
+                                        UserError::new("Invalid message from Postgres",
+                                            format!("received change for unknown relation {}", relation))
+                                    })?;
+
+                                    if relation.qualified_name() != table_name {
+                                        continue;
+                                    }
+
+                                    for change in relation.to_debezium(&message) {
+                                        transaction.changes.push(serde_json::to_vec(&change).unwrap());
+                                    }
+                                }
+                                PgOutputMessage::Truncate { relations: truncated } => {
+                                    if truncated.iter().filter_map(|r| relations.get(r))
+                                        .any(|r| r.qualified_name() == table_name) {
+                                        warn!("table {} was truncated; truncations are not propagated by the postgres cdc source", table_name);
+                                    }
+                                }
+                                PgOutputMessage::Other(_) => {}
+                            }
+                        }
+                        Some(ReplicationMessage::Keepalive { wal_end, reply_requested }) => {
+                            lsn.received = lsn.received.max(wal_end);
+                            if reply_requested {
+                                client.send_status_update(lsn.received, lsn.confirmed).await
+                                    .map_err(|e| UserError::new("Error writing to Postgres", format!("{:?}", e)))?;
+                            }
+                        }
+                        None => {
+                            info!("replication stream for slot {} ended", self.table.slot);
+                            return Ok(SourceFinishType::Final);
+                        }
+                    }
+                }
+                _ = flush_ticker.tick() => {
+                    if ctx.should_flush() {
+                        ctx.flush_buffer().await?;
+                    }
+                }
+                _ = status_ticker.tick() => {
+                    client.send_status_update(lsn.received, lsn.confirmed).await
+                        .map_err(|e| UserError::new("Error writing to Postgres", format!("{:?}", e)))?;
+                }
+                control_message = ctx.control_rx.recv() => {
+                    match control_message {
+                        Some(ControlMessage::Checkpoint(c)) => {
+                            debug!("starting checkpointing {}", ctx.task_info.task_index);
+                            let s = ctx.table_manager.get_global_keyed_state("p").await
+                                .map_err(|err| UserError::new("failed to get global key value", err.to_string()))?;
+                            s.insert(self.table.slot.clone(), PostgresCdcState {
+                                lsn: lsn.committed,
+                            }).await;
+
+                            // sources aren't notified when a checkpoint completes, so we only
+                            // release WAL up to the previous checkpoint, which must have completed
+                            // for this one to have started
+                            lsn.confirmed = lsn.checkpointed;
+                            lsn.checkpointed = lsn.committed;
+
+                            if let Err(e) = client.send_status_update(lsn.received, lsn.confirmed).await {
+                                warn!("Failed to send status update to Postgres: {:?}", e);
+                            }
+
+                            if self.start_checkpoint(c, ctx).await {
+                                return Ok(SourceFinishType::Immediate);
+                            }
+                        }
+                        Some(ControlMessage::Stop { mode }) => {
+                            info!("Stopping postgres cdc source: {:?}", mode);
+
+                            match mode {
+                                StopMode::Graceful => {
+                                    return Ok(SourceFinishType::Graceful);
+                                }
+                                StopMode::Immediate => {
+                                    return Ok(SourceFinishType::Immediate);
+                                }
+                            }
+                        }
+                        Some(ControlMessage::Commit { .. }) => {
+                            unreachable!("sources shouldn't receive commit messages");
+                        }
+                        Some(ControlMessage::LoadCompacted { compacted }) => {
+                            ctx.load_compacted(compacted).await;
+                        }
+                        Some(ControlMessage::NoOp) => {}
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use arrow::array::{AsArray, RecordBatch};
+use arrow::datatypes::{DataType, Field, Fields, Int32Type, Schema, TimeUnit};
+use arroyo_operator::context::{batch_bounded, ArrowContext, BatchReceiver};
+use arroyo_operator::operator::SourceOperator;
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::formats::{Format, JsonFormat};
+use arroyo_rpc::grpc::rpc::StopMode;
+use arroyo_rpc::var_str::VarStr;
+use arroyo_rpc::ControlMessage;
+use arroyo_types::ArrowMessage;
+use rand::random;
+use tokio::sync::mpsc::channel;
+use tokio_postgres::NoTls;
+
+use super::source::PostgresCdcSourceFunc;
+use super::{PostgresCdcConfig, PostgresCdcTable};
+
+// these tests expect a Postgres server on localhost with wal_level = logical, as set up in CI
+const CONNECTION: &str = "host=localhost port=5432 user=arroyo password=arroyo dbname=arroyo";
+
+async fn connect() -> tokio_postgres::Client {
+    let (client, connection) = tokio_postgres::connect(CONNECTION, NoTls)
+        .await
+        .expect("failed to connect to Postgres");
+    tokio::spawn(connection);
+    client
+}
+
+fn row_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("id", DataType::Int32, true),
+        Field::new("name", DataType::Utf8, true),
+    ])
+}
+
+/// Collects `(op, id, name)` for each change, taking the values from `after` or, for deletes,
+/// from `before`
+async fn next_changes(
+    data_recv: &mut BatchReceiver,
+    count: usize,
+) -> Vec<(String, i32, Option<String>)> {
+    let mut changes = vec![];
+    while changes.len() < count {
+        let message = tokio::time::timeout(Duration::from_secs(30), data_recv.recv())
+            .await
+            .expect("timed out waiting for changes")
+            .expect("source stopped");
+
+        let ArrowMessage::Data(batch) = message else {
+            continue;
+        };
+
+        changes.extend(batch_changes(&batch));
+    }
+    changes
+}
+
+fn batch_changes(batch: &RecordBatch) -> Vec<(String, i32, Option<String>)> {
+    let ops = batch.column_by_name("op").unwrap().as_string::<i32>();
+    let before = batch.column_by_name("before").unwrap().as_struct();
+    let after = batch.column_by_name("after").unwrap().as_struct();
+
+    (0..batch.num_rows())
+        .map(|i| {
+            let op = ops.value(i).to_string();
+            let row = if op == "d" { before } else { after };
+            let id = row
+                .column_by_name("id")
+                .unwrap()
+                .as_primitive::<Int32Type>();
+            let name = row.column_by_name("name").unwrap().as_string::<i32>();
+            (
+                op,
+                id.value(i),
+                name.is_valid(i).then(|| name.value(i).to_string()),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_postgres_cdc_source() {
+    let client = connect().await;
+
+    let id = random::<u32>();
+    let table = format!("cdc_test_{}", id);
+    let slot = format!("cdc_test_slot_{}", id);
+    let publication = format!("cdc_test_pub_{}", id);
+
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {} (id INT PRIMARY KEY, name TEXT)",
+            table
+        ))
+        .await
+        .unwrap();
+
+    let mut source = PostgresCdcSourceFunc {
+        config: PostgresCdcConfig {
+            host: "localhost".to_string(),
+            port: Some(5432),
+            database: "arroyo".to_string(),
+            username: VarStr::new("arroyo".to_string()),
+            password: Some(VarStr::new("arroyo".to_string())),
+        },
+        table: PostgresCdcTable {
+            table: table.clone(),
+            slot: slot.clone(),
+            publication: publication.clone(),
+        },
+        format: Format::Json(JsonFormat {
+            debezium: true,
+            ..Default::default()
+        }),
+        framing: None,
+        bad_data: None,
+    };
+
+    let (control_tx, control_rx) = channel(128);
+    let (command_tx, _command_rx) = channel(128);
+    let (data_tx, mut data_recv) = batch_bounded(128);
+
+    let mut ctx = ArrowContext::new(
+        arroyo_types::get_test_task_info(),
+        None,
+        control_rx,
+        command_tx,
+        1,
+        vec![],
+        Some(ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(vec![
+                Field::new(
+                    "_timestamp",
+                    DataType::Timestamp(TimeUnit::Nanosecond, None),
+                    false,
+                ),
+                Field::new("before", DataType::Struct(row_fields()), true),
+                Field::new("after", DataType::Struct(row_fields()), true),
+                Field::new("op", DataType::Utf8, false),
+            ])),
+            0,
+        )),
+        None,
+        vec![vec![data_tx]],
+        vec![],
+        source.tables(),
+    )
+    .await;
+
+    let task = tokio::spawn(async move {
+        source.on_start(&mut ctx).await;
+        source.run(&mut ctx).await;
+    });
+
+    // changes are only captured once the source has created the slot and started streaming it
+    let start = std::time::Instant::now();
+    loop {
+        let active = client
+            .query(
+                "SELECT 1 FROM pg_replication_slots WHERE slot_name = $1 AND active",
+                &[&slot],
+            )
+            .await
+            .unwrap();
+        if !active.is_empty() {
+            break;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(30),
+            "timed out waiting for the replication slot"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    client
+        .batch_execute(&format!(
+            "INSERT INTO {table} VALUES (1, 'a'), (2, 'b');
+             UPDATE {table} SET name = 'c' WHERE id = 1;
+             DELETE FROM {table} WHERE id = 2;"
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        next_changes(&mut data_recv, 4).await,
+        vec![
+            ("c".to_string(), 1, Some("a".to_string())),
+            ("c".to_string(), 2, Some("b".to_string())),
+            ("u".to_string(), 1, Some("c".to_string())),
+            // with the default replica identity, deletes only carry the primary key
+            ("d".to_string(), 2, None),
+        ]
+    );
+
+    control_tx
+        .send(ControlMessage::Stop {
+            mode: StopMode::Immediate,
+        })
+        .await
+        .unwrap();
+    task.await.unwrap();
+
+    // the slot stays active until the server notices that the replication connection closed
+    for _ in 0..50 {
+        if client
+            .execute("SELECT pg_drop_replication_slot($1)", &[&slot])
+            .await
+            .is_ok()
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    client
+        .batch_execute(&format!(
+            "DROP PUBLICATION {}; DROP TABLE {};",
+            publication, table
+        ))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,198 @@
+use crate::redis::operator::sink::GeneralConnection;
+use crate::redis::RedisClient;
+use arrow::array::{ArrayRef, AsArray, RecordBatch};
+use arrow::datatypes::DataType;
+use arroyo_formats::de::{ArrowDeserializer, FieldValueType};
+use arroyo_operator::connector::LookupConnector;
+use arroyo_rpc::formats::BadData;
+use arroyo_rpc::LOOKUP_KEY_INDEX_FIELD;
+use arroyo_types::SourceError;
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// Looks up rows stored as String values in Redis, at the key given by the prefix followed by the
+/// value of the table's `metadata('key')` column
+pub struct RedisLookup {
+    client: RedisClient,
+    connection: Option<GeneralConnection>,
+    key_prefix: String,
+    key_column: String,
+    index_column: String,
+    deserializer: ArrowDeserializer,
+}
+
+#[async_trait]
+impl LookupConnector for RedisLookup {
+    fn name(&self) -> String {
+        "RedisLookup".to_string()
+    }
+
+    async fn lookup(&mut self, keys: &[ArrayRef]) -> Option<Result<RecordBatch, SourceError>> {
+        let [keys] = keys else {
+            panic!("redis lookups must have exactly one key");
+        };
+
+        if *keys.data_type() != DataType::Utf8 {
+            panic!("redis lookup keys must be TEXT, not {}", keys.data_type());
+        }
+        let keys = keys.as_string::<i32>();
+
+        if self.connection.is_none() {
+            match self.client.get_connection().await {
+                Ok(connection) => self.connection = Some(connection),
+                Err(e) => {
+                    return Some(Err(SourceError::other(
+                        "failed to connect to Redis",
+                        e.to_string(),
+                    )))
+                }
+            }
+        }
+        let connection = self.connection.as_mut().unwrap();
+
+        let redis_keys: Vec<String> = keys
+            .iter()
+            .map(|k| format!("{}{}", self.key_prefix, k.unwrap_or_default()))
+            .collect();
+
+        let values: Vec<Option<Vec<u8>>> = match connection.mget(&redis_keys).await {
+            Ok(values) => values,
+            Err(e) => {
+                return Some(Err(SourceError::other(
+                    "failed to read from Redis",
+                    e.to_string(),
+                )))
+            }
+        };
+
+        let now = SystemTime::now();
+        for (i, value) in values.into_iter().enumerate() {
+            let Some(value) = value else {
+                continue;
+            };
+
+            let additional_fields = HashMap::from([
+                (&self.index_column, FieldValueType::Int64(i as i64)),
+                (
+                    &self.key_column,
+                    FieldValueType::OptionalString(Some(keys.value(i))),
+                ),
+            ]);
+
+            let errors = self
+                .deserializer
+                .deserialize_slice(&mut [], &value, now, Some(additional_fields))
+                .await;
+
+            // with other bad_data settings, invalid values are dropped and treated as missing
+            if matches!(self.deserializer.bad_data(), BadData::Fail { .. }) {
+                if let Some(error) = errors.into_iter().next() {
+                    return Some(Err(error));
+                }
+            }
+        }
+
+        self.deserializer.flush_buffer()
+    }
+}
+
+impl RedisLookup {
+    pub(crate) fn new(
+        client: RedisClient,
+        key_prefix: String,
+        key_column: String,
+        deserializer: ArrowDeserializer,
+    ) -> Self {
+        Self {
+            client,
+            connection: None,
+            key_prefix,
+            key_column,
+            index_column: LOOKUP_KEY_INDEX_FIELD.to_string(),
+            deserializer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RedisLookup;
+    use crate::redis::{Address, RedisClient, RedisConfig, RedisConfigConnection};
+    use arrow::array::{Array, ArrayRef, AsArray, StringArray};
+    use arrow::datatypes::{DataType, Field, Int64Type, Schema, TimeUnit};
+    use arroyo_formats::de::ArrowDeserializer;
+    use arroyo_operator::connector::LookupConnector;
+    use arroyo_rpc::df::ArroyoSchema;
+    use arroyo_rpc::formats::{BadData, Format, JsonFormat};
+    use arroyo_rpc::{LOOKUP_KEY_INDEX_FIELD, TIMESTAMP_FIELD};
+    use redis::AsyncCommands;
+    use std::sync::Arc;
+
+    // requires a redis-server running on localhost:6379
+    #[tokio::test]
+    async fn test_lookup() {
+        let config = RedisConfig {
+            connection: RedisConfigConnection::Address(Address(
+                "redis://localhost:6379".to_string(),
+            )),
+            username: None,
+            password: None,
+        };
+
+        let client = RedisClient::new(&config).unwrap();
+        let mut connection = client.get_connection().await.unwrap();
+        let _: () = connection
+            .set("test_lookup:a", r#"{"name": "alice"}"#)
+            .await
+            .unwrap();
+        let _: () = connection
+            .set("test_lookup:c", r#"{"name": "carol"}"#)
+            .await
+            .unwrap();
+        let _: () = connection.del("test_lookup:b").await.unwrap();
+
+        let schema = ArroyoSchema::from_fields(vec![
+            Field::new("id", DataType::Utf8, true),
+            Field::new("name", DataType::Utf8, true),
+            Field::new(LOOKUP_KEY_INDEX_FIELD, DataType::Int64, true),
+            Field::new(
+                TIMESTAMP_FIELD,
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]);
+
+        let mut lookup = RedisLookup::new(
+            RedisClient::new(&config).unwrap(),
+            "test_lookup:".to_string(),
+            "id".to_string(),
+            ArrowDeserializer::new(
+                Format::Json(JsonFormat::default()),
+                schema,
+                None,
+                BadData::Fail {},
+            ),
+        );
+
+        let keys: ArrayRef = Arc::new(StringArray::from(vec!["a", "b", "c"]));
+        let batch = lookup.lookup(&[keys]).await.unwrap().unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        let ids = batch.column_by_name("id").unwrap().as_string::<i32>();
+        let names = batch.column_by_name("name").unwrap().as_string::<i32>();
+        let indices = batch
+            .column_by_name(LOOKUP_KEY_INDEX_FIELD)
+            .unwrap()
+            .as_primitive::<Int64Type>();
+
+        assert_eq!(ids.value(0), "a");
+        assert_eq!(names.value(0), "alice");
+        assert_eq!(indices.value(0), 0);
+        assert_eq!(ids.value(1), "c");
+        assert_eq!(names.value(1), "carol");
+        assert_eq!(indices.value(1), 2);
+        assert!(!indices.is_null(1));
+    }
+}
@@ -1,16 +1,21 @@
+mod lookup;
 mod operator;
 
 use anyhow::{anyhow, bail};
 use arrow::datatypes::DataType;
+use arroyo_formats::de::ArrowDeserializer;
 use arroyo_formats::ser::ArrowSerializer;
-use arroyo_operator::connector::{Connection, Connector};
+use arroyo_operator::connector::{Connection, Connector, LookupConnector, MetadataDef};
 use arroyo_operator::operator::OperatorNode;
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::formats::{Format, JsonFormat};
 use arroyo_rpc::var_str::VarStr;
 use redis::aio::ConnectionManager;
 use redis::cluster::ClusterClient;
 use redis::{Client, ConnectionInfo, IntoConnectionInfo};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::oneshot::Receiver;
 use typify::import_types;
 
@@ -20,7 +25,9 @@ use arroyo_rpc::api_types::connections::{
 };
 use arroyo_rpc::OperatorConfig;
 
+use crate::redis::lookup::RedisLookup;
 use crate::redis::operator::sink::{GeneralConnection, RedisSinkFunc};
+use crate::redis::operator::source::RedisSourceFunc;
 use crate::{pull_opt, pull_option_to_u64};
 
 pub struct RedisConnector {}
@@ -84,6 +91,21 @@ impl RedisClient {
     }
 }
 
+/// Returns a client for a single node, which is used for pub/sub; Redis Cluster forwards published
+/// messages to every node, so subscribing through any one of them receives all messages
+fn node_client(config: &RedisConfig) -> anyhow::Result<Client> {
+    let address = match &config.connection {
+        RedisConfigConnection::Address(address) => &address.0,
+        RedisConfigConnection::Addresses(addresses) => addresses
+            .first()
+            .map(|a| &a.0)
+            .ok_or_else(|| anyhow!("no Redis Cluster addresses configured"))?,
+    };
+
+    Client::open(from_address(config, address)?)
+        .map_err(|e| anyhow!("Failed to construct Redis client for {}: {:?}", address, e))
+}
+
 fn from_address(config: &RedisConfig, address: &str) -> anyhow::Result<ConnectionInfo> {
     let mut info: ConnectionInfo = address
         .to_string()
@@ -163,9 +185,10 @@ impl Connector for RedisConnector {
             id: "redis".to_string(),
             name: "Redis".to_string(),
             icon: ICON.to_string(),
-            description: "Write results to Redis".to_string(),
+            description: "Read from and write results to Redis, or look up rows from it in joins"
+                .to_string(),
             enabled: true,
-            source: false,
+            source: true,
             sink: true,
             testing: false,
             hidden: false,
@@ -175,8 +198,25 @@ impl Connector for RedisConnector {
         }
     }
 
-    fn table_type(&self, _: Self::ProfileT, _: Self::TableT) -> ConnectionType {
-        ConnectionType::Source
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        &[
+            MetadataDef {
+                name: "key",
+                data_type: DataType::Utf8,
+            },
+            MetadataDef {
+                name: "id",
+                data_type: DataType::Utf8,
+            },
+        ]
+    }
+
+    fn table_type(&self, _: Self::ProfileT, table: Self::TableT) -> ConnectionType {
+        match table.connector_type {
+            TableType::Source(_) => ConnectionType::Source,
+            TableType::Target(_) => ConnectionType::Sink,
+            TableType::Lookup(_) => ConnectionType::Lookup,
+        }
     }
 
     fn get_schema(
@@ -228,7 +268,7 @@ impl Connector for RedisConnector {
         options: &mut HashMap<String, String>,
         s: Option<&ConnectionSchema>,
         profile: Option<&ConnectionProfile>,
-        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+        metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
         let connection_config = match profile {
             Some(connection_profile) => {
@@ -290,7 +330,86 @@ impl Connector for RedisConnector {
             Ok(column)
         }
 
+        fn validate_numeric_column(
+            schema: &ConnectionSchema,
+            column: String,
+            sql: &str,
+            types: &[PrimitiveType],
+            allow_null: bool,
+        ) -> anyhow::Result<String> {
+            if !schema.fields.iter().any(|f| {
+                f.field_name == column
+                    && matches!(&f.field_type.r#type, FieldType::Primitive(t) if types.contains(t))
+                    && (allow_null || !f.nullable)
+            }) {
+                bail!(
+                    "invalid value '{}' for {}, must be the name of a{} {} column on the table",
+                    column,
+                    sql,
+                    if allow_null { "n" } else { " non-nullable" },
+                    if types.contains(&PrimitiveType::F64) {
+                        "numeric"
+                    } else {
+                        "integer"
+                    }
+                );
+            };
+
+            Ok(column)
+        }
+
+        const INTEGER_TYPES: &[PrimitiveType] = &[
+            PrimitiveType::Int32,
+            PrimitiveType::Int64,
+            PrimitiveType::UInt32,
+            PrimitiveType::UInt64,
+        ];
+
+        const NUMERIC_TYPES: &[PrimitiveType] = &[
+            PrimitiveType::Int32,
+            PrimitiveType::Int64,
+            PrimitiveType::UInt32,
+            PrimitiveType::UInt64,
+            PrimitiveType::F32,
+            PrimitiveType::F64,
+        ];
+
         let sink = match typ.as_str() {
+            "source" => TableType::Source(
+                match (
+                    options.remove("source.streams"),
+                    options.remove("source.channels"),
+                ) {
+                    (Some(streams), None) => SourceOptions::StreamSource {
+                        streams: streams.split(',').map(|s| s.trim().to_string()).collect(),
+                        group: pull_opt("source.group", options)?,
+                        start_from: match options.remove("source.start_from").as_deref() {
+                            Some("latest") | None => Some(StartFrom::Latest),
+                            Some("earliest") => Some(StartFrom::Earliest),
+                            Some(s) => {
+                                bail!("'{}' is not a valid value for source.start_from; must be one of 'latest' or 'earliest'", s);
+                            }
+                        },
+                        value_field: options.remove("source.value_field"),
+                    },
+                    (None, Some(channels)) => SourceOptions::PubSubSource {
+                        channels: channels.split(',').map(|s| s.trim().to_string()).collect(),
+                        pattern: match options.remove("source.pattern").as_deref() {
+                            Some("true") => Some(true),
+                            Some("false") | None => Some(false),
+                            Some(s) => {
+                                bail!("'{}' is not a valid value for source.pattern; must be one of 'true' or 'false'", s);
+                            }
+                        },
+                    },
+                    (Some(_), Some(_)) => {
+                        bail!("only one of `source.streams` or `source.channels` may be set");
+                    }
+                    (None, None) => {
+                        bail!("one of `source.streams` or `source.channels` must be set for redis sources");
+                    }
+                },
+            ),
             "sink" => TableType::Target(match pull_opt("target", options)?.as_str() {
                 "string" => Target::StringTable {
                     key_prefix: pull_opt("target.key_prefix", options)?,
@@ -302,6 +421,18 @@ impl Connector for RedisConnector {
                         .map(|t| t.try_into())
                         .transpose()
                         .map_err(|_| anyhow!("target.ttl_secs must be greater than 0"))?,
+                    ttl_column: options
+                        .remove("target.ttl_column")
+                        .map(|name| {
+                            validate_numeric_column(
+                                schema,
+                                name,
+                                "target.ttl_column",
+                                INTEGER_TYPES,
+                                true,
+                            )
+                        })
+                        .transpose()?,
                 },
                 "list" => Target::ListTable {
                     list_prefix: pull_opt("target.key_prefix", options)?,
@@ -333,15 +464,85 @@ impl Connector for RedisConnector {
                         .transpose()?,
                     hash_key_prefix: pull_opt("target.key_prefix", options)?,
                 },
+                "sorted_set" => Target::SortedSetTable {
+                    sorted_set_key_prefix: pull_opt("target.key_prefix", options)?,
+                    sorted_set_key_column: options
+                        .remove("target.key_column")
+                        .map(|name| validate_column(schema, name, "target.key_column"))
+                        .transpose()?,
+                    score_column: validate_numeric_column(
+                        schema,
+                        pull_opt("target.score_column", options)?,
+                        "target.score_column",
+                        NUMERIC_TYPES,
+                        false,
+                    )?,
+                    member_column: options
+                        .remove("target.member_column")
+                        .map(|name| validate_column(schema, name, "target.member_column"))
+                        .transpose()?,
+                },
+                "stream" => Target::StreamTable {
+                    stream_key_prefix: pull_opt("target.key_prefix", options)?,
+                    stream_key_column: options
+                        .remove("target.key_column")
+                        .map(|name| validate_column(schema, name, "target.key_column"))
+                        .transpose()?,
+                    stream_max_length: pull_option_to_u64("target.max_length", options)?
+                        .map(|t| t.try_into())
+                        .transpose()
+                        .map_err(|_| anyhow!("target.max_length must be greater than 0"))?,
+                },
                 s => {
                     bail!("'{}' is not a valid redis target", s);
                 }
             }),
+            "lookup" => {
+                let key_columns: Vec<_> = metadata_fields
+                    .iter()
+                    .flatten()
+                    .filter(|(_, (key, _))| key == "key")
+                    .map(|(column, _)| column)
+                    .collect();
+
+                let [key_column] = key_columns.as_slice() else {
+                    bail!("redis lookup tables must have exactly one column defined as `GENERATED ALWAYS AS (metadata('key')) STORED`, which rows are looked up by");
+                };
+
+                if schema.primary_keys.len() != 1 || !schema.primary_keys.contains(*key_column) {
+                    bail!(
+                        "the PRIMARY KEY of a redis lookup table must be its key column '{}'",
+                        key_column
+                    );
+                }
+
+                TableType::Lookup(LookupOptions {
+                    key_prefix: options.remove("lookup.key_prefix"),
+                })
+            }
             s => {
-                bail!("'{}' is not a valid type; must be `sink`", s);
+                bail!(
+                    "'{}' is not a valid type; must be `source`, `sink` or `lookup`",
+                    s
+                );
             }
         };
 
+        if matches!(sink, TableType::Target(_))
+            && metadata_fields.as_ref().is_some_and(|f| !f.is_empty())
+        {
+            bail!("metadata fields are only supported for redis source and lookup tables");
+        }
+
+        if !matches!(sink, TableType::Source(_))
+            && metadata_fields
+                .iter()
+                .flatten()
+                .any(|(_, (key, _))| key == "id")
+        {
+            bail!("metadata('id') is only supported for redis source tables");
+        }
+
         self.from_config(
             None,
             name,
@@ -350,7 +551,7 @@ impl Connector for RedisConnector {
                 connector_type: sink,
             },
             s,
-            None,
+            metadata_fields,
         )
     }
 
@@ -361,7 +562,7 @@ impl Connector for RedisConnector {
         config: Self::ProfileT,
         table: Self::TableT,
         schema: Option<&ConnectionSchema>,
-        _metadata_fields: Option<HashMap<String, (String, DataType)>>,
+        metadata_fields: Option<HashMap<String, (String, DataType)>>,
     ) -> anyhow::Result<Connection> {
         let schema = schema
             .map(|s| s.to_owned())
@@ -373,8 +574,47 @@ impl Connector for RedisConnector {
             .map(|t| t.to_owned())
             .ok_or_else(|| anyhow!("'format' must be set for Redis connection"))?;
 
+        let (connection_type, description) = match &table.connector_type {
+            TableType::Source(source) => {
+                match source {
+                    SourceOptions::StreamSource {
+                        streams,
+                        value_field,
+                        ..
+                    } => {
+                        if streams.is_empty() || streams.iter().any(|s| s.is_empty()) {
+                            bail!("redis stream sources must have at least one stream");
+                        }
+                        if value_field.is_none() && !matches!(format, Format::Json(_)) {
+                            bail!("redis stream sources without a value field read each entry as a JSON object, so must use the json format");
+                        }
+                    }
+                    SourceOptions::PubSubSource { channels, .. } => {
+                        if channels.is_empty() || channels.iter().any(|s| s.is_empty()) {
+                            bail!("redis pub/sub sources must have at least one channel");
+                        }
+                    }
+                }
+                (ConnectionType::Source, "RedisSource")
+            }
+            TableType::Target(_) => (ConnectionType::Sink, "RedisSink"),
+            TableType::Lookup(_) => {
+                if !matches!(format, Format::Json(_)) {
+                    bail!("redis lookup tables only support the json format");
+                }
+                (ConnectionType::Lookup, "RedisLookup")
+            }
+        };
+
         let _ = RedisClient::new(&config)?;
 
+        let metadata_fields = metadata_fields.map(|fields| {
+            fields
+                .into_iter()
+                .map(|(k, (v, _))| (k, v))
+                .collect::<HashMap<String, String>>()
+        });
+
         let config = OperatorConfig {
             connection: serde_json::to_value(config).unwrap(),
             table: serde_json::to_value(table).unwrap(),
@@ -382,17 +622,17 @@ impl Connector for RedisConnector {
             format: Some(format),
             bad_data: schema.bad_data.clone(),
             framing: schema.framing.clone(),
-            additional_fields: None,
+            additional_fields: metadata_fields,
         };
 
         Ok(Connection {
             id,
             connector: self.name(),
             name: name.to_string(),
-            connection_type: ConnectionType::Sink,
+            connection_type,
             schema,
             config: serde_json::to_string(&config).unwrap(),
-            description: "RedisSink".to_string(),
+            description: description.to_string(),
         })
     }
 
@@ -402,15 +642,57 @@ impl Connector for RedisConnector {
         table: Self::TableT,
         config: OperatorConfig,
     ) -> anyhow::Result<OperatorNode> {
+        match &table.connector_type {
+            TableType::Lookup(_) => {
+                bail!("redis lookup tables can only be used on the right side of a join");
+            }
+            TableType::Source(options) => {
+                let client = match options {
+                    SourceOptions::StreamSource { .. } => RedisClient::new(&profile)?,
+                    SourceOptions::PubSubSource { .. } => {
+                        RedisClient::Standard(node_client(&profile)?)
+                    }
+                };
+
+                return Ok(OperatorNode::from_source(Box::new(RedisSourceFunc {
+                    client,
+                    options: options.clone(),
+                    format: config.format.expect("redis table must have a format"),
+                    framing: config.framing,
+                    bad_data: config.bad_data,
+                    metadata_fields: config.additional_fields,
+                })));
+            }
+            TableType::Target(_) => {}
+        }
+
         let client = RedisClient::new(&profile)?;
 
         let (tx, cmd_rx) = tokio::sync::mpsc::channel(128);
         let (cmd_tx, rx) = tokio::sync::mpsc::channel(128);
 
+        let format = config.format.expect("redis table must have a format");
+        let updating = format.is_updating();
+
+        // string, hash and sorted set targets mirror updating tables by writing the rows
+        // themselves and removing retracted ones, rather than writing changelog messages
+        let format = match (&table.connector_type, format) {
+            (
+                TableType::Target(
+                    Target::StringTable { .. }
+                    | Target::HashTable { .. }
+                    | Target::SortedSetTable { .. },
+                ),
+                Format::Json(json),
+            ) if updating => Format::Json(JsonFormat {
+                debezium: false,
+                ..json
+            }),
+            (_, format) => format,
+        };
+
         Ok(OperatorNode::from_operator(Box::new(RedisSinkFunc {
-            serializer: ArrowSerializer::new(
-                config.format.expect("redis table must have a format"),
-            ),
+            serializer: ArrowSerializer::with_framing(format, config.framing),
             table,
             client,
             cmd_q: Some((cmd_tx, cmd_rx)),
@@ -418,6 +700,43 @@ impl Connector for RedisConnector {
             rx,
             key_index: None,
             hash_index: None,
+            score_index: None,
+            member_index: None,
+            ttl_index: None,
+            updating,
+            changelog: None,
         })))
     }
+
+    fn make_lookup(
+        &self,
+        profile: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+        schema: Arc<ArroyoSchema>,
+    ) -> anyhow::Result<Box<dyn LookupConnector>> {
+        let TableType::Lookup(LookupOptions { key_prefix }) = table.connector_type else {
+            bail!("redis table is not a lookup table");
+        };
+
+        let key_column = config
+            .additional_fields
+            .iter()
+            .flatten()
+            .find(|(_, key)| *key == "key")
+            .map(|(column, _)| column.clone())
+            .ok_or_else(|| anyhow!("redis lookup table has no metadata('key') column"))?;
+
+        Ok(Box::new(RedisLookup::new(
+            RedisClient::new(&profile)?,
+            key_prefix.unwrap_or_default(),
+            key_column,
+            ArrowDeserializer::new(
+                config.format.expect("redis table must have a format"),
+                schema.as_ref().clone(),
+                config.framing,
+                config.bad_data.unwrap_or_default(),
+            ),
+        )))
+    }
 }
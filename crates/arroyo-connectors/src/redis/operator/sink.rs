@@ -1,18 +1,22 @@
 use crate::redis::{ListOperation, RedisClient, RedisTable, TableType, Target};
-use arrow::array::{AsArray, RecordBatch};
+use crate::struct_rows;
+use arrow::array::{Array, AsArray, Float64Array, Int64Array, RecordBatch};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type, Int64Type, Schema};
 use arroyo_formats::ser::ArrowSerializer;
 use arroyo_operator::context::{ArrowContext, ErrorReporter};
 use arroyo_operator::operator::ArrowOperator;
-use arroyo_types::CheckpointBarrier;
+use arroyo_types::{CheckpointBarrier, UserError};
 use async_trait::async_trait;
 use redis::aio::{ConnectionLike, ConnectionManager};
 use redis::cluster_async::ClusterConnection;
+use redis::streams::StreamMaxlen;
 use redis::{Cmd, Pipeline, RedisFuture};
 use std::collections::HashSet;
 use std::time::{Duration, Instant};
 use tokio::select;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tracing::info;
+use tracing::{info, warn};
 
 const FLUSH_TIMEOUT: Duration = Duration::from_millis(100);
 const FLUSH_BYTES: usize = 10 * 1024 * 1024;
@@ -28,6 +32,30 @@ pub struct RedisSinkFunc {
 
     pub key_index: Option<usize>,
     pub hash_index: Option<usize>,
+    pub score_index: Option<usize>,
+    pub member_index: Option<usize>,
+    pub ttl_index: Option<usize>,
+
+    /// Whether the input is an updating table, which string, hash and sorted set targets mirror
+    /// by removing retracted values
+    pub updating: bool,
+    pub changelog: Option<ChangelogColumns>,
+}
+
+/// The indices of the debezium-style `before`, `after` and `op` columns of an updating input
+#[derive(Copy, Clone, Debug)]
+pub struct ChangelogColumns {
+    before: usize,
+    after: usize,
+    op: usize,
+}
+
+/// The rows of a batch (or the before or after rows of an updating batch), along with the
+/// numeric columns that commands are built from, cast to the types Redis expects
+struct Rows<'a> {
+    batch: &'a RecordBatch,
+    scores: Option<Float64Array>,
+    ttls: Option<Int64Array>,
 }
 
 impl RedisSinkFunc {
@@ -40,6 +68,219 @@ impl RedisSinkFunc {
 
         key
     }
+
+    fn rows<'a>(&self, batch: &'a RecordBatch) -> Result<Rows<'a>, UserError> {
+        let cast_column = |index: usize, data_type: &DataType, column: &str| {
+            cast(batch.column(index), data_type).map_err(|e| {
+                UserError::new(
+                    "invalid column for Redis sink",
+                    format!("could not cast the {column} column to {data_type}: {e}"),
+                )
+            })
+        };
+
+        Ok(Rows {
+            batch,
+            scores: self
+                .score_index
+                .map(|i| cast_column(i, &DataType::Float64, "score"))
+                .transpose()?
+                .map(|scores| scores.as_primitive::<Float64Type>().clone()),
+            ttls: self
+                .ttl_index
+                .map(|i| cast_column(i, &DataType::Int64, "ttl"))
+                .transpose()?
+                .map(|ttls| ttls.as_primitive::<Int64Type>().clone()),
+        })
+    }
+
+    fn hash_field(&self, rows: &Rows, idx: usize) -> String {
+        rows.batch
+            .column(self.hash_index.expect("no hash index"))
+            .as_string::<i32>()
+            .value(idx)
+            .to_string()
+    }
+
+    /// The sorted set member for a row, if it comes from a column rather than being the whole row
+    fn member(&self, rows: &Rows, idx: usize) -> Option<Vec<u8>> {
+        self.member_index.map(|i| {
+            rows.batch
+                .column(i)
+                .as_string::<i32>()
+                .value(idx)
+                .as_bytes()
+                .to_vec()
+        })
+    }
+
+    /// The command that writes a row, whose serialized form is `value`
+    fn write_cmd(&self, rows: &Rows, idx: usize, value: Vec<u8>) -> RedisCmd {
+        let TableType::Target(target) = &self.table.connector_type else {
+            unreachable!("redis sinks can only be created for sink tables");
+        };
+
+        match target {
+            Target::StringTable { key_prefix, .. } => RedisCmd::Data {
+                key: self.make_key(key_prefix, rows.batch, idx),
+                value,
+                // rows with a null or non-positive TTL fall back to the table's TTL
+                ttl: rows
+                    .ttls
+                    .as_ref()
+                    .filter(|ttls| ttls.is_valid(idx) && ttls.value(idx) > 0)
+                    .map(|ttls| ttls.value(idx) as u64),
+            },
+            Target::ListTable { list_prefix, .. } => RedisCmd::Data {
+                key: self.make_key(list_prefix, rows.batch, idx),
+                value,
+                ttl: None,
+            },
+            Target::HashTable {
+                hash_key_prefix, ..
+            } => RedisCmd::HData {
+                key: self.make_key(hash_key_prefix, rows.batch, idx),
+                field: self.hash_field(rows, idx),
+                value,
+            },
+            Target::SortedSetTable {
+                sorted_set_key_prefix,
+                ..
+            } => RedisCmd::ZData {
+                key: self.make_key(sorted_set_key_prefix, rows.batch, idx),
+                member: self.member(rows, idx).unwrap_or(value),
+                score: rows.scores.as_ref().expect("no score column").value(idx),
+            },
+            Target::StreamTable {
+                stream_key_prefix, ..
+            } => RedisCmd::Data {
+                key: self.make_key(stream_key_prefix, rows.batch, idx),
+                value,
+                ttl: None,
+            },
+        }
+    }
+
+    /// The command that removes a retracted row; `old_value` is the serialized row, which is only
+    /// needed for sorted sets whose members are whole rows
+    fn retract_cmd(&self, rows: &Rows, idx: usize, old_value: Option<Vec<u8>>) -> RedisCmd {
+        let TableType::Target(target) = &self.table.connector_type else {
+            unreachable!("redis sinks can only be created for sink tables");
+        };
+
+        match target {
+            Target::StringTable { key_prefix, .. } => RedisCmd::Del {
+                key: self.make_key(key_prefix, rows.batch, idx),
+            },
+            Target::HashTable {
+                hash_key_prefix, ..
+            } => RedisCmd::HDel {
+                key: self.make_key(hash_key_prefix, rows.batch, idx),
+                field: self.hash_field(rows, idx),
+            },
+            Target::SortedSetTable {
+                sorted_set_key_prefix,
+                ..
+            } => RedisCmd::ZRem {
+                key: self.make_key(sorted_set_key_prefix, rows.batch, idx),
+                member: self
+                    .member(rows, idx)
+                    .or(old_value)
+                    .expect("no serialized row to retract"),
+            },
+            Target::ListTable { .. } | Target::StreamTable { .. } => {
+                unreachable!("list and stream targets append changes rather than retracting them")
+            }
+        }
+    }
+
+    async fn send(&self, cmd: RedisCmd) {
+        self.tx.send(cmd).await.expect("Redis writer panicked");
+    }
+
+    /// How the writer turns the data commands for this sink's target into Redis commands
+    fn behavior(&self) -> RedisBehavior {
+        match self.table.connector_type {
+            TableType::Target(Target::StringTable { ttl_secs, .. }) => RedisBehavior::Set {
+                ttl: ttl_secs.map(|t| t.get() as usize),
+            },
+            TableType::Target(Target::ListTable {
+                max_length,
+                operation,
+                ..
+            }) => {
+                let max = max_length.map(|x| x.get() as usize);
+                match operation {
+                    ListOperation::Append => RedisBehavior::Push { append: true, max },
+                    ListOperation::Prepend => RedisBehavior::Push { append: false, max },
+                }
+            }
+            TableType::Target(Target::HashTable { .. }) => RedisBehavior::Hash,
+            TableType::Target(Target::SortedSetTable { .. }) => RedisBehavior::SortedSet,
+            TableType::Target(Target::StreamTable {
+                stream_max_length, ..
+            }) => RedisBehavior::Stream {
+                max: stream_max_length.map(|x| x.get() as usize),
+            },
+            TableType::Source(_) | TableType::Lookup(_) => {
+                unreachable!("redis sinks can only be created for sink tables")
+            }
+        }
+    }
+
+    /// Sends the commands for a batch to the writer
+    async fn write_batch(&mut self, batch: &RecordBatch) -> Result<(), UserError> {
+        let Some(changelog) = self.changelog else {
+            let rows = self.rows(batch)?;
+            for (i, value) in self.serializer.serialize(batch).enumerate() {
+                self.send(self.write_cmd(&rows, i, value)).await;
+            }
+            return Ok(());
+        };
+
+        let before = struct_rows(batch.column(changelog.before).as_struct());
+        let after = struct_rows(batch.column(changelog.after).as_struct());
+        let ops = batch.column(changelog.op).as_string::<i32>();
+
+        let old_rows = self.rows(&before)?;
+        let new_rows = self.rows(&after)?;
+
+        // retracting a sorted set member that is the whole row requires the old serialized row
+        let needs_old_values = self.member_index.is_none()
+            && matches!(
+                self.table.connector_type,
+                TableType::Target(Target::SortedSetTable { .. })
+            );
+        let mut old_values = needs_old_values.then(|| self.serializer.serialize(&before));
+
+        let new_values = self.serializer.serialize(&after);
+
+        // retractions are issued in order with writes, so that a key that is updated more than
+        // once in a batch ends up with its final value
+        for (i, new_value) in new_values.enumerate() {
+            let old_value = old_values.as_mut().and_then(|values| values.next());
+
+            let (retract, write) = match ops.value(i) {
+                "c" | "r" => (false, true),
+                "u" => (true, true),
+                "d" => (true, false),
+                op => {
+                    warn!("unknown debezium op '{}' in redis sink; ignoring", op);
+                    continue;
+                }
+            };
+
+            if retract {
+                self.send(self.retract_cmd(&old_rows, i, old_value)).await;
+            }
+
+            if write {
+                self.send(self.write_cmd(&new_rows, i, new_value)).await;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -47,12 +288,16 @@ enum RedisBehavior {
     Set { ttl: Option<usize> },
     Push { append: bool, max: Option<usize> },
     Hash,
+    SortedSet,
+    Stream { max: Option<usize> },
 }
 
 pub enum RedisCmd {
     Data {
         key: String,
         value: Vec<u8>,
+        /// for string targets, overrides the table's TTL
+        ttl: Option<u64>,
     },
 
     HData {
@@ -61,6 +306,26 @@ pub enum RedisCmd {
         value: Vec<u8>,
     },
 
+    ZData {
+        key: String,
+        member: Vec<u8>,
+        score: f64,
+    },
+
+    Del {
+        key: String,
+    },
+
+    HDel {
+        key: String,
+        field: String,
+    },
+
+    ZRem {
+        key: String,
+        member: Vec<u8>,
+    },
+
     Flush(u32),
 }
 
@@ -97,14 +362,127 @@ impl ConnectionLike for GeneralConnection {
     }
 }
 
+/// The pipeline of commands that the writer sends to Redis on its next flush
+struct CommandPipeline {
+    behavior: RedisBehavior,
+    pipeline: Pipeline,
+    size_estimate: usize,
+    /// the lists that have been pushed to since the last flush, which need to be trimmed
+    max_push_keys: HashSet<String>,
+}
+
+impl CommandPipeline {
+    fn new(behavior: RedisBehavior) -> Self {
+        Self {
+            behavior,
+            pipeline: redis::pipe(),
+            size_estimate: 0,
+            max_push_keys: HashSet::new(),
+        }
+    }
+
+    fn push(&mut self, cmd: RedisCmd) {
+        match cmd {
+            RedisCmd::Data {
+                key,
+                value,
+                ttl: row_ttl,
+            } => {
+                self.size_estimate += key.len() + value.len();
+
+                match self.behavior {
+                    RedisBehavior::Set { ttl } => {
+                        // TODO: resolve duplicates before sending
+                        if let Some(ttl) = row_ttl.or(ttl.map(|t| t as u64)) {
+                            self.pipeline.set_ex(key, value, ttl);
+                        } else {
+                            self.pipeline.set(key, value);
+                        }
+                    }
+                    RedisBehavior::Push { append, max } => {
+                        if max.is_some() && !self.max_push_keys.contains(&key) {
+                            self.max_push_keys.insert(key.clone());
+                        }
+
+                        if append {
+                            self.pipeline.rpush(key, value);
+                        } else {
+                            self.pipeline.lpush(key, value);
+                        }
+                    }
+                    RedisBehavior::Stream { max } => {
+                        let items = [("value", value)];
+                        if let Some(max) = max {
+                            self.pipeline
+                                .xadd_maxlen(key, StreamMaxlen::Equals(max), "*", &items);
+                        } else {
+                            self.pipeline.xadd(key, "*", &items);
+                        }
+                    }
+                    RedisBehavior::Hash | RedisBehavior::SortedSet => {
+                        unreachable!();
+                    }
+                }
+            }
+            RedisCmd::HData { key, field, value } => {
+                self.size_estimate += key.len() + field.len() + value.len();
+
+                self.pipeline.hset(key, field, value);
+            }
+            RedisCmd::ZData { key, member, score } => {
+                self.size_estimate += key.len() + member.len() + 8;
+
+                self.pipeline.zadd(key, member, score);
+            }
+            RedisCmd::Del { key } => {
+                self.size_estimate += key.len();
+
+                self.pipeline.del(key);
+            }
+            RedisCmd::HDel { key, field } => {
+                self.size_estimate += key.len() + field.len();
+
+                self.pipeline.hdel(key, field);
+            }
+            RedisCmd::ZRem { key, member } => {
+                self.size_estimate += key.len() + member.len();
+
+                self.pipeline.zrem(key, member);
+            }
+            RedisCmd::Flush(_) => {
+                unreachable!("flushes are handled by the writer");
+            }
+        }
+    }
+
+    /// Trims the lists that have been pushed to down to their maximum length
+    fn trim_lists(&mut self) {
+        if let RedisBehavior::Push {
+            max: Some(max),
+            append,
+        } = self.behavior
+        {
+            for k in self.max_push_keys.drain() {
+                if append {
+                    self.pipeline.ltrim(k, -(max as isize), -1);
+                } else {
+                    self.pipeline.ltrim(k, 0, max as isize - 1);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.pipeline.clear();
+        self.size_estimate = 0;
+    }
+}
+
 struct RedisWriter {
     rx: Receiver<RedisCmd>,
     tx: Sender<u32>,
-    max_push_keys: HashSet<String>,
-    behavior: RedisBehavior,
     connection: GeneralConnection,
-    pipeline: Pipeline,
-    size_estimate: usize,
+    commands: CommandPipeline,
     last_flushed: Instant,
     error_reporter: ErrorReporter,
 }
@@ -115,7 +493,7 @@ impl RedisWriter {
             loop {
                 let flush_duration = FLUSH_TIMEOUT.checked_sub(self.last_flushed.elapsed());
 
-                if self.size_estimate > FLUSH_BYTES || flush_duration.is_none() {
+                if self.commands.size_estimate > FLUSH_BYTES || flush_duration.is_none() {
                     self.flush().await;
                     continue;
                 }
@@ -129,39 +507,6 @@ impl RedisWriter {
                                 info!("closing Redis writer");
                                 return;
                             }
-                            Some(RedisCmd::Data { key, value }) => {
-                                self.size_estimate += key.len() + value.len();
-
-                                match self.behavior {
-                                    RedisBehavior::Set {ttl } => {
-                                        // TODO: resolve duplicates before sending
-                                        if let Some(ttl) = ttl {
-                                            self.pipeline.set_ex(key, value, ttl as u64);
-                                        } else {
-                                            self.pipeline.set(key, value);
-                                        }
-                                    }
-                                    RedisBehavior::Push { append, max } => {
-                                        if max.is_some() && !self.max_push_keys.contains(&key) {
-                                            self.max_push_keys.insert(key.clone());
-                                        }
-
-                                        if append {
-                                            self.pipeline.rpush(key, value);
-                                        } else {
-                                            self.pipeline.lpush(key, value);
-                                        }
-                                    }
-                                    RedisBehavior::Hash => {
-                                        unreachable!();
-                                    }
-                                }
-                            }
-                            Some(RedisCmd::HData { key, field, value }) => {
-                                self.size_estimate += key.len() + field.len() + value.len();
-
-                                self.pipeline.hset(key, field, value);
-                            }
                             Some(RedisCmd::Flush(i)) => {
                                 self.flush().await;
                                 if self.tx.send(i).await.is_err() {
@@ -169,6 +514,9 @@ impl RedisWriter {
                                     return;
                                 }
                             }
+                            Some(cmd) => {
+                                self.commands.push(cmd);
+                            }
                         }
                     }
                     _ = flush_timeout => {
@@ -182,25 +530,17 @@ impl RedisWriter {
     async fn flush(&mut self) {
         let mut attempts = 0;
 
-        if let RedisBehavior::Push {
-            max: Some(max),
-            append,
-        } = self.behavior
-        {
-            for k in self.max_push_keys.drain() {
-                if append {
-                    self.pipeline.ltrim(k, -(max as isize), -1);
-                } else {
-                    self.pipeline.ltrim(k, 0, max as isize - 1);
-                }
-            }
-        }
+        self.commands.trim_lists();
 
         while attempts < 20 {
-            match self.pipeline.query_async::<()>(&mut self.connection).await {
+            match self
+                .commands
+                .pipeline
+                .query_async::<()>(&mut self.connection)
+                .await
+            {
                 Ok(_) => {
-                    self.pipeline.clear();
-                    self.size_estimate = 0;
+                    self.commands.clear();
                     self.last_flushed = Instant::now();
                     return;
                 }
@@ -229,6 +569,52 @@ impl ArrowOperator for RedisSinkFunc {
     }
 
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let in_schema = ctx
+            .in_schemas
+            .first()
+            .expect("no in-schema for redis sink!")
+            .schema
+            .clone();
+
+        // string, hash and sorted set targets mirror updating inputs, so their columns are read
+        // from the before and after rows; list and stream targets append the changes themselves
+        let mirrors_updates = matches!(
+            self.table.connector_type,
+            TableType::Target(
+                Target::StringTable { .. }
+                    | Target::HashTable { .. }
+                    | Target::SortedSetTable { .. }
+            )
+        );
+
+        let schema = if self.updating && mirrors_updates {
+            let index = |name: &str| {
+                in_schema.index_of(name).unwrap_or_else(|_| {
+                    panic!("updating input for redis sink has no '{name}' column")
+                })
+            };
+
+            let changelog = ChangelogColumns {
+                before: index("before"),
+                after: index("after"),
+                op: index("op"),
+            };
+            self.changelog = Some(changelog);
+
+            let DataType::Struct(fields) = in_schema.field(changelog.after).data_type() else {
+                panic!("'after' column of updating input for redis sink is not a struct");
+            };
+            Schema::new(fields.clone())
+        } else {
+            in_schema.as_ref().clone()
+        };
+
+        let index_of = |column: &String| {
+            schema.index_of(column).unwrap_or_else(|_| {
+                panic!("column ({column}) does not exist in input schema for redis sink")
+            })
+        };
+
         match &self.table.connector_type {
             TableType::Target(Target::ListTable {
                 list_key_column: Some(key),
@@ -241,31 +627,45 @@ impl ArrowOperator for RedisSinkFunc {
             | TableType::Target(Target::HashTable {
                 hash_key_column: Some(key),
                 ..
+            })
+            | TableType::Target(Target::SortedSetTable {
+                sorted_set_key_column: Some(key),
+                ..
+            })
+            | TableType::Target(Target::StreamTable {
+                stream_key_column: Some(key),
+                ..
             }) => {
-                self.key_index = Some(
-                    ctx.in_schemas
-                        .first()
-                        .expect("no in-schema for redis sink!")
-                        .schema
-                        .index_of(key)
-                        .unwrap_or_else(|_| {
-                            panic!(
-                                "key column ({key}) does not exist in input schema for redis sink"
-                            )
-                        }),
-                );
+                self.key_index = Some(schema.index_of(key).unwrap_or_else(|_| {
+                    panic!("key column ({key}) does not exist in input schema for redis sink")
+                }));
             }
             _ => {}
         }
 
-        if let TableType::Target(Target::HashTable {
-            hash_field_column, ..
-        }) = &self.table.connector_type
-        {
-            self.hash_index = Some(ctx.in_schemas.first().expect("no in-schema for redis sink!")
-                .schema
-                .index_of(hash_field_column)
-                .unwrap_or_else(|_| panic!("hash field column ({hash_field_column}) does not exist in input schema for redis sink")));
+        match &self.table.connector_type {
+            TableType::Target(Target::HashTable {
+                hash_field_column, ..
+            }) => {
+                self.hash_index = Some(schema
+                    .index_of(hash_field_column)
+                    .unwrap_or_else(|_| panic!("hash field column ({hash_field_column}) does not exist in input schema for redis sink")));
+            }
+            TableType::Target(Target::SortedSetTable {
+                score_column,
+                member_column,
+                ..
+            }) => {
+                self.score_index = Some(index_of(score_column));
+                self.member_index = member_column.as_ref().map(index_of);
+            }
+            TableType::Target(Target::StringTable {
+                ttl_column: Some(ttl_column),
+                ..
+            }) => {
+                self.ttl_index = Some(index_of(ttl_column));
+            }
+            _ => {}
         }
 
         let mut attempts = 0;
@@ -278,33 +678,7 @@ impl ArrowOperator for RedisSinkFunc {
                         error_reporter: ctx.error_reporter.clone(),
                         tx,
                         rx,
-                        pipeline: redis::pipe(),
-                        size_estimate: 0,
-                        last_flushed: Instant::now(),
-                        max_push_keys: HashSet::new(),
-                        behavior: match self.table.connector_type {
-                            TableType::Target(Target::StringTable { ttl_secs, .. }) => {
-                                RedisBehavior::Set {
-                                    ttl: ttl_secs.map(|t| t.get() as usize),
-                                }
-                            }
-                            TableType::Target(Target::ListTable {
-                                max_length,
-                                operation,
-                                ..
-                            }) => {
-                                let max = max_length.map(|x| x.get() as usize);
-                                match operation {
-                                    ListOperation::Append => {
-                                        RedisBehavior::Push { append: true, max }
-                                    }
-                                    ListOperation::Prepend => {
-                                        RedisBehavior::Push { append: false, max }
-                                    }
-                                }
-                            }
-                            TableType::Target(Target::HashTable { .. }) => RedisBehavior::Hash,
-                        },
+                        commands: CommandPipeline::new(self.behavior()),
                     }
                     .start();
                     return;
@@ -321,42 +695,9 @@ impl ArrowOperator for RedisSinkFunc {
         panic!("Failed to establish connection to redis after 20 retries");
     }
 
-    async fn process_batch(&mut self, batch: RecordBatch, _: &mut ArrowContext) {
-        for (i, value) in self.serializer.serialize(&batch).enumerate() {
-            match &self.table.connector_type {
-                TableType::Target(target) => match &target {
-                    Target::StringTable { key_prefix, .. } => {
-                        let key = self.make_key(key_prefix, &batch, i);
-                        self.tx
-                            .send(RedisCmd::Data { key, value })
-                            .await
-                            .expect("Redis writer panicked");
-                    }
-                    Target::ListTable { list_prefix, .. } => {
-                        let key = self.make_key(list_prefix, &batch, i);
-
-                        self.tx
-                            .send(RedisCmd::Data { key, value })
-                            .await
-                            .expect("Redis writer panicked");
-                    }
-                    Target::HashTable {
-                        hash_key_prefix, ..
-                    } => {
-                        let key = self.make_key(hash_key_prefix, &batch, i);
-                        let field = batch
-                            .column(self.hash_index.expect("no hash index"))
-                            .as_string::<i32>()
-                            .value(i)
-                            .to_string();
-
-                        self.tx
-                            .send(RedisCmd::HData { key, field, value })
-                            .await
-                            .expect("Redis writer panicked");
-                    }
-                },
-            };
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        if let Err(e) = self.write_batch(&batch).await {
+            ctx.report_user_error(e).await;
         }
     }
 
@@ -383,3 +724,347 @@ impl ArrowOperator for RedisSinkFunc {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis::RedisConfig;
+    use arrow::array::{
+        ArrayRef, BinaryArray, Int32Array, StringArray, StructArray, TimestampNanosecondArray,
+    };
+    use arrow::buffer::NullBuffer;
+    use arrow::datatypes::{Field, Fields, TimeUnit};
+    use arroyo_rpc::formats::{Format, JsonFormat};
+    use arroyo_rpc::TIMESTAMP_FIELD;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn sink(target: Value) -> RedisSinkFunc {
+        let table: RedisTable =
+            serde_json::from_value(json!({ "connectorType": { "target": target } })).unwrap();
+        let config: RedisConfig = serde_json::from_value(
+            json!({ "connection": { "address": "redis://localhost:6379" } }),
+        )
+        .unwrap();
+
+        let (tx, cmd_rx) = tokio::sync::mpsc::channel(128);
+        let (cmd_tx, rx) = tokio::sync::mpsc::channel(128);
+
+        RedisSinkFunc {
+            serializer: ArrowSerializer::new(Format::Json(JsonFormat::default())),
+            table,
+            client: RedisClient::new(&config).unwrap(),
+            cmd_q: Some((cmd_tx, cmd_rx)),
+            tx,
+            rx,
+            key_index: None,
+            hash_index: None,
+            score_index: None,
+            member_index: None,
+            ttl_index: None,
+            updating: false,
+            changelog: None,
+        }
+    }
+
+    /// Makes the sink read its input as a changelog of `before`, `after` and `op` columns
+    fn updating(mut sink: RedisSinkFunc) -> RedisSinkFunc {
+        sink.updating = true;
+        sink.changelog = Some(ChangelogColumns {
+            before: 0,
+            after: 1,
+            op: 2,
+        });
+        sink
+    }
+
+    fn changelog(
+        fields: Fields,
+        before: Vec<ArrayRef>,
+        after: Vec<ArrayRef>,
+        ops: Vec<&str>,
+    ) -> RecordBatch {
+        let struct_type = DataType::Struct(fields.clone());
+        let nulls = |columns: &[ArrayRef]| {
+            NullBuffer::from(
+                (0..ops.len())
+                    .map(|i| columns.iter().any(|c| c.is_valid(i)))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let before_nulls = nulls(&before);
+        let after_nulls = nulls(&after);
+
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("before", struct_type.clone(), true),
+                Field::new("after", struct_type, true),
+                Field::new("op", DataType::Utf8, false),
+            ])),
+            vec![
+                Arc::new(StructArray::new(fields.clone(), before, Some(before_nulls))),
+                Arc::new(StructArray::new(fields, after, Some(after_nulls))),
+                Arc::new(StringArray::from(ops)),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn strings(values: Vec<Option<&str>>) -> ArrayRef {
+        Arc::new(StringArray::from(values))
+    }
+
+    fn floats(values: Vec<Option<f64>>) -> ArrayRef {
+        Arc::new(Float64Array::from(values))
+    }
+
+    /// Writes the batch with the sink and returns the Redis commands that would be sent, with
+    /// JSON arguments normalized so that they can be compared with `json!` values
+    async fn commands(sink: &mut RedisSinkFunc, batch: RecordBatch) -> Vec<Vec<String>> {
+        sink.write_batch(&batch).await.unwrap();
+
+        let (_, mut rx) = sink.cmd_q.take().unwrap();
+        let mut commands = CommandPipeline::new(sink.behavior());
+        while let Ok(cmd) = rx.try_recv() {
+            commands.push(cmd);
+        }
+        commands.trim_lists();
+
+        let packed = String::from_utf8(commands.pipeline.get_packed_pipeline()).unwrap();
+        let mut lines = packed.split("\r\n");
+        let mut parsed = vec![];
+        while let Some(header) = lines.next().filter(|line| !line.is_empty()) {
+            let args: usize = header.strip_prefix('*').unwrap().parse().unwrap();
+            parsed.push(
+                (0..args)
+                    .map(|_| {
+                        // skip the length of the argument
+                        lines.next();
+                        let arg = lines.next().unwrap();
+                        match serde_json::from_str::<Value>(arg) {
+                            Ok(value) if value.is_object() => value.to_string(),
+                            _ => arg.to_string(),
+                        }
+                    })
+                    .collect(),
+            );
+        }
+        parsed
+    }
+
+    fn cmd(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_string_target_uses_row_ttls_and_deletes_retractions() {
+        let mut sink = updating(sink(json!({
+            "keyPrefix": "user:",
+            "keyColumn": "id",
+            "ttlSecs": 60,
+            "ttlColumn": "ttl",
+        })));
+        sink.key_index = Some(0);
+        sink.ttl_index = Some(1);
+
+        let fields = Fields::from(vec![
+            Field::new("id", DataType::Utf8, true),
+            Field::new("ttl", DataType::Int32, true),
+        ]);
+        let batch = changelog(
+            fields,
+            vec![
+                strings(vec![None, Some("a"), Some("b")]),
+                Arc::new(Int32Array::from(vec![None, Some(30), Some(5)])),
+            ],
+            vec![
+                strings(vec![Some("a"), Some("a"), None]),
+                Arc::new(Int32Array::from(vec![Some(30), None, None])),
+            ],
+            vec!["c", "u", "d"],
+        );
+
+        let row_a = json!({"id": "a", "ttl": 30}).to_string();
+        let updated_a = json!({"id": "a", "ttl": null}).to_string();
+        assert_eq!(
+            commands(&mut sink, batch).await,
+            vec![
+                // the row's TTL overrides the table's
+                cmd(&["SETEX", "user:a", "30", &row_a]),
+                cmd(&["DEL", "user:a"]),
+                // and rows without one use the table's
+                cmd(&["SETEX", "user:a", "60", &updated_a]),
+                cmd(&["DEL", "user:b"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sorted_set_target_adds_and_removes_members() {
+        let mut sink = updating(sink(json!({
+            "sortedSetKeyPrefix": "leaderboard:",
+            "sortedSetKeyColumn": "game",
+            "scoreColumn": "score",
+            "memberColumn": "player",
+        })));
+        sink.key_index = Some(0);
+        sink.member_index = Some(1);
+        sink.score_index = Some(2);
+
+        let fields = Fields::from(vec![
+            Field::new("game", DataType::Utf8, true),
+            Field::new("player", DataType::Utf8, true),
+            Field::new("score", DataType::Float64, true),
+        ]);
+        let batch = changelog(
+            fields,
+            vec![
+                strings(vec![None, Some("g"), Some("g")]),
+                strings(vec![None, Some("a"), Some("b")]),
+                floats(vec![None, Some(2.5), Some(1.5)]),
+            ],
+            vec![
+                strings(vec![Some("g"), Some("g"), None]),
+                strings(vec![Some("a"), Some("a"), None]),
+                floats(vec![Some(2.5), Some(7.5), None]),
+            ],
+            vec!["c", "u", "d"],
+        );
+
+        assert_eq!(
+            commands(&mut sink, batch).await,
+            vec![
+                cmd(&["ZADD", "leaderboard:g", "2.5", "a"]),
+                cmd(&["ZREM", "leaderboard:g", "a"]),
+                cmd(&["ZADD", "leaderboard:g", "7.5", "a"]),
+                cmd(&["ZREM", "leaderboard:g", "b"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sorted_set_target_removes_whole_row_members() {
+        let mut sink = updating(sink(json!({
+            "sortedSetKeyPrefix": "scores",
+            "scoreColumn": "score",
+        })));
+        sink.score_index = Some(1);
+
+        let fields = Fields::from(vec![
+            Field::new("player", DataType::Utf8, true),
+            Field::new("score", DataType::Float64, true),
+        ]);
+        let batch = changelog(
+            fields,
+            vec![strings(vec![Some("a")]), floats(vec![Some(2.5)])],
+            vec![strings(vec![Some("a")]), floats(vec![Some(7.5)])],
+            vec!["u"],
+        );
+
+        let old_row = json!({"player": "a", "score": 2.5}).to_string();
+        let new_row = json!({"player": "a", "score": 7.5}).to_string();
+        assert_eq!(
+            commands(&mut sink, batch).await,
+            vec![
+                cmd(&["ZREM", "scores", &old_row]),
+                cmd(&["ZADD", "scores", "7.5", &new_row]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hash_target_sets_and_deletes_fields() {
+        let mut sink = updating(sink(json!({
+            "hashKeyPrefix": "users",
+            "hashFieldColumn": "id",
+        })));
+        sink.hash_index = Some(0);
+
+        let fields = Fields::from(vec![
+            Field::new("id", DataType::Utf8, true),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+        let batch = changelog(
+            fields,
+            vec![
+                strings(vec![None, Some("b")]),
+                strings(vec![None, Some("y")]),
+            ],
+            vec![
+                strings(vec![Some("a"), None]),
+                strings(vec![Some("x"), None]),
+            ],
+            vec!["c", "d"],
+        );
+
+        let row_a = json!({"id": "a", "name": "x"}).to_string();
+        assert_eq!(
+            commands(&mut sink, batch).await,
+            vec![
+                cmd(&["HSET", "users", "a", &row_a]),
+                cmd(&["HDEL", "users", "b"]),
+            ]
+        );
+    }
+
+    fn append_batch(column: Field, values: ArrayRef) -> RecordBatch {
+        let len = values.len();
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                column,
+                Field::new(
+                    TIMESTAMP_FIELD,
+                    DataType::Timestamp(TimeUnit::Nanosecond, None),
+                    false,
+                ),
+            ])),
+            vec![
+                values,
+                Arc::new(TimestampNanosecondArray::from(vec![0; len])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_target_caps_length() {
+        let mut sink = sink(json!({
+            "streamKeyPrefix": "events",
+            "streamMaxLength": 100,
+        }));
+
+        let batch = append_batch(
+            Field::new("value", DataType::Utf8, true),
+            strings(vec![Some("x")]),
+        );
+
+        let row = json!({"value": "x"}).to_string();
+        assert_eq!(
+            commands(&mut sink, batch).await,
+            vec![cmd(&[
+                "XADD", "events", "MAXLEN", "=", "100", "*", "value", &row
+            ])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_score_column_that_cannot_be_cast_is_an_error() {
+        let mut sink = sink(json!({
+            "sortedSetKeyPrefix": "scores",
+            "scoreColumn": "score",
+        }));
+        sink.score_index = Some(0);
+
+        let batch = append_batch(
+            Field::new("score", DataType::Binary, true),
+            Arc::new(BinaryArray::from(vec![b"x".as_slice()])),
+        );
+
+        let err = sink.write_batch(&batch).await.unwrap_err();
+        assert!(
+            err.details.contains("could not cast the score column"),
+            "{}",
+            err.details
+        );
+    }
+}
@@ -0,0 +1,730 @@
+use crate::redis::operator::sink::GeneralConnection;
+use crate::redis::{RedisClient, SourceOptions, StartFrom};
+use arroyo_formats::de::FieldValueType;
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::SourceOperator;
+use arroyo_operator::SourceFinishType;
+use arroyo_rpc::formats::{BadData, Format, Framing};
+use arroyo_rpc::grpc::rpc::{StopMode, TableConfig};
+use arroyo_rpc::{ControlMessage, ControlResp};
+use arroyo_types::{ArrowMessage, SignalMessage, UserError, Watermark};
+use async_trait::async_trait;
+use bincode::{Decode, Encode};
+use futures::StreamExt;
+use redis::streams::{StreamId, StreamInfoGroupsReply, StreamReadOptions, StreamReadReply};
+use redis::{AsyncCommands, RedisResult};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime};
+use tokio::select;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+/// The maximum number of entries read from a stream by each XREADGROUP
+const READ_COUNT: usize = 1000;
+/// How long the server holds a read open waiting for new entries once our streams have been
+/// drained, which bounds how long control messages wait while the source is idle
+const BLOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub struct RedisSourceFunc {
+    /// For pub/sub sources this is always a standard client, as published messages are forwarded
+    /// to every node of a cluster
+    pub client: RedisClient,
+    pub options: SourceOptions,
+    pub format: Format,
+    pub framing: Option<Framing>,
+    pub bad_data: Option<BadData>,
+    pub metadata_fields: Option<HashMap<String, String>>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, PartialEq)]
+pub struct RedisStreamState {
+    stream: String,
+    /// The ID of the last entry read from the stream
+    last_id: String,
+}
+
+#[async_trait]
+impl SourceOperator for RedisSourceFunc {
+    fn name(&self) -> String {
+        match &self.options {
+            SourceOptions::StreamSource { streams, .. } => {
+                format!("redis-streams-{}", streams.join(","))
+            }
+            SourceOptions::PubSubSource { channels, .. } => {
+                format!("redis-pubsub-{}", channels.join(","))
+            }
+        }
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        arroyo_state::global_table_config("r", "redis stream ids")
+    }
+
+    async fn run(&mut self, ctx: &mut ArrowContext) -> SourceFinishType {
+        ctx.initialize_deserializer(
+            self.format.clone(),
+            self.framing.clone(),
+            self.bad_data.clone(),
+        );
+
+        let result = match self.options.clone() {
+            SourceOptions::StreamSource {
+                streams,
+                group,
+                start_from,
+                value_field,
+            } => {
+                self.run_streams(
+                    ctx,
+                    streams,
+                    group,
+                    start_from.unwrap_or(StartFrom::Latest),
+                    value_field,
+                )
+                .await
+            }
+            SourceOptions::PubSubSource { channels, pattern } => {
+                self.run_pubsub(ctx, channels, pattern.unwrap_or(false))
+                    .await
+            }
+        };
+
+        match result {
+            Ok(r) => r,
+            Err(e) => {
+                ctx.control_tx
+                    .send(ControlResp::Error {
+                        operator_id: ctx.task_info.operator_id.clone(),
+                        task_index: ctx.task_info.task_index,
+                        message: e.name.clone(),
+                        details: e.details.clone(),
+                    })
+                    .await
+                    .unwrap();
+
+                panic!("{}: {}", e.name, e.details);
+            }
+        }
+    }
+}
+
+fn redis_error(message: &str, e: impl ToString) -> UserError {
+    UserError::new(format!("RedisSourceError: {}", message), e.to_string())
+}
+
+/// Streams are divided between subtasks by hash, so that each is read by a single consumer
+fn owns_stream(stream: &str, ctx: &ArrowContext) -> bool {
+    let mut hasher = DefaultHasher::new();
+    stream.hash(&mut hasher);
+    hasher.finish() as usize % ctx.task_info.parallelism == ctx.task_info.task_index
+}
+
+/// Stream IDs have the form `<millisecond timestamp>-<sequence number>`; we use the timestamp as
+/// the event time of the entry
+fn entry_timestamp(id: &str) -> SystemTime {
+    id.split_once('-')
+        .and_then(|(ms, _)| ms.parse().ok())
+        .map(|ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms))
+        .unwrap_or_else(SystemTime::now)
+}
+
+/// Returns the payload of a stream entry, which is either the value of `value_field` or, if that
+/// isn't set, all of the fields of the entry encoded as a JSON object
+fn entry_payload(entry: &StreamId, value_field: Option<&str>) -> Result<Vec<u8>, String> {
+    match value_field {
+        Some(field) => {
+            let value = entry
+                .map
+                .get(field)
+                .ok_or_else(|| format!("entry {} has no field '{}'", entry.id, field))?;
+            redis::from_redis_value(value).map_err(|e| {
+                format!(
+                    "invalid value for field '{}' in entry {}: {}",
+                    field, entry.id, e
+                )
+            })
+        }
+        None => {
+            let fields = entry
+                .map
+                .iter()
+                .map(|(k, v)| {
+                    Ok((
+                        k.clone(),
+                        serde_json::Value::String(redis::from_redis_value::<String>(v)?),
+                    ))
+                })
+                .collect::<RedisResult<serde_json::Map<_, _>>>()
+                .map_err(|e| format!("invalid value in entry {}: {}", entry.id, e))?;
+
+            Ok(serde_json::Value::Object(fields).to_string().into_bytes())
+        }
+    }
+}
+
+impl RedisSourceFunc {
+    fn connector_metadata<'a>(
+        &'a self,
+        key: &'a str,
+        id: Option<&'a str>,
+    ) -> Option<HashMap<&'a String, FieldValueType<'a>>> {
+        self.metadata_fields.as_ref().map(|fields| {
+            fields
+                .iter()
+                .filter_map(|(k, v)| {
+                    let value = match v.as_str() {
+                        "key" => FieldValueType::OptionalString(Some(key)),
+                        "id" => FieldValueType::OptionalString(id),
+                        _ => return None,
+                    };
+                    Some((k, value))
+                })
+                .collect()
+        })
+    }
+
+    /// Handles a control message, returning how the source should finish if it should stop
+    async fn handle_control(
+        &mut self,
+        control_message: Option<ControlMessage>,
+        ctx: &mut ArrowContext,
+    ) -> Option<SourceFinishType> {
+        match control_message {
+            Some(ControlMessage::Checkpoint(c)) => {
+                debug!("starting checkpointing {}", ctx.task_info.task_index);
+                if self.start_checkpoint(c, ctx).await {
+                    return Some(SourceFinishType::Immediate);
+                }
+            }
+            Some(ControlMessage::Stop { mode }) => {
+                info!("Stopping Redis source: {:?}", mode);
+
+                match mode {
+                    StopMode::Graceful => {
+                        return Some(SourceFinishType::Graceful);
+                    }
+                    StopMode::Immediate => {
+                        return Some(SourceFinishType::Immediate);
+                    }
+                }
+            }
+            Some(ControlMessage::Commit { .. }) => {
+                unreachable!("sources shouldn't receive commit messages");
+            }
+            Some(ControlMessage::LoadCompacted { compacted }) => {
+                ctx.load_compacted(compacted).await;
+            }
+            Some(ControlMessage::NoOp) | None => {}
+        }
+        None
+    }
+
+    /// Creates the consumer group on each of our streams if it doesn't exist, and positions it
+    /// after the last entry we read (if we have restored state), returning the last entry ID for
+    /// each stream
+    async fn init_streams(
+        &self,
+        connection: &mut GeneralConnection,
+        ctx: &mut ArrowContext,
+        streams: &[String],
+        group: &str,
+        start_from: StartFrom,
+    ) -> Result<HashMap<String, String>, UserError> {
+        let state: HashMap<String, String> = ctx
+            .table_manager
+            .get_global_keyed_state::<String, RedisStreamState>("r")
+            .await
+            .map_err(|e| redis_error("failed to get global key value", e))?
+            .get_all()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.last_id.clone()))
+            .collect();
+
+        let start_id = match start_from {
+            StartFrom::Latest => "$",
+            StartFrom::Earliest => "0",
+        };
+
+        let mut last_ids = HashMap::new();
+        for stream in streams.iter().filter(|s| owns_stream(s, ctx)) {
+            let created: RedisResult<()> = connection
+                .xgroup_create_mkstream(stream, group, start_id)
+                .await;
+
+            if let Err(e) = created {
+                if e.code() != Some("BUSYGROUP") {
+                    return Err(redis_error(
+                        &format!("failed to create consumer group for stream '{}'", stream),
+                        e,
+                    ));
+                }
+            }
+
+            let last_id = match state.get(stream) {
+                Some(last_id) => {
+                    connection
+                        .xgroup_setid::<_, _, _, ()>(stream, group, last_id)
+                        .await
+                        .map_err(|e| {
+                            redis_error(
+                                &format!("failed to restore position in stream '{}'", stream),
+                                e,
+                            )
+                        })?;
+                    last_id.clone()
+                }
+                None => {
+                    // start from wherever the group currently is, so that entries the group
+                    // delivers after this point are replayed if we restore before checkpointing
+                    let info: StreamInfoGroupsReply = connection
+                        .xinfo_groups(stream)
+                        .await
+                        .map_err(|e| redis_error("failed to fetch consumer group info", e))?;
+
+                    info.groups
+                        .into_iter()
+                        .find(|g| g.name == group)
+                        .map(|g| g.last_delivered_id)
+                        .ok_or_else(|| {
+                            redis_error(
+                                "failed to fetch consumer group info",
+                                format!("group '{}' not found on stream '{}'", group, stream),
+                            )
+                        })?
+                }
+            };
+
+            info!(
+                "Reading Redis stream '{}' with group '{}' after {}",
+                stream, group, last_id
+            );
+            last_ids.insert(stream.clone(), last_id);
+        }
+
+        Ok(last_ids)
+    }
+
+    /// Reads from streams with XREADGROUP. Entries are read with NOACK, as our position in each
+    /// stream is tracked in state and restored to the group with XGROUP SETID. Once a read finds
+    /// no new entries, the following reads BLOCK until entries arrive or [`BLOCK_TIMEOUT`] passes.
+    async fn run_streams(
+        &mut self,
+        ctx: &mut ArrowContext,
+        streams: Vec<String>,
+        group: String,
+        start_from: StartFrom,
+        value_field: Option<String>,
+    ) -> Result<SourceFinishType, UserError> {
+        let mut connection = self
+            .client
+            .get_connection()
+            .await
+            .map_err(|e| redis_error("failed to connect to Redis", e))?;
+
+        let mut last_ids = self
+            .init_streams(&mut connection, ctx, &streams, &group, start_from)
+            .await?;
+
+        if last_ids.is_empty() {
+            warn!(
+                "Redis source {}-{} has no streams to read from... setting idle",
+                ctx.task_info.operator_id, ctx.task_info.task_index
+            );
+            ctx.broadcast(ArrowMessage::Signal(SignalMessage::Watermark(
+                Watermark::Idle,
+            )))
+            .await;
+
+            loop {
+                let control_message = ctx.control_rx.recv().await;
+                if let Some(finish) = self.handle_control(control_message, ctx).await {
+                    return Ok(finish);
+                }
+            }
+        }
+
+        let consumer = format!(
+            "arroyo-{}-{}-{}",
+            ctx.task_info.job_id, ctx.task_info.operator_id, ctx.task_info.task_index
+        );
+        let read_options = || {
+            StreamReadOptions::default()
+                .group(&group, &consumer)
+                .count(READ_COUNT)
+                .noack()
+        };
+
+        // with a standard client all of our streams are read at once, but in a cluster they may
+        // live on different nodes, so they're read one at a time and share the block timeout
+        let our_streams: Vec<String> = last_ids.keys().cloned().collect();
+        let reads: Vec<Vec<String>> = if matches!(self.client, RedisClient::Clustered(_)) {
+            our_streams.iter().map(|s| vec![s.clone()]).collect()
+        } else {
+            vec![our_streams]
+        };
+        let block_ms = (BLOCK_TIMEOUT.as_millis() as usize / reads.len()).max(1);
+
+        let mut idle = false;
+        loop {
+            let mut read = 0;
+
+            for streams in &reads {
+                let options = if idle {
+                    read_options().block(block_ms)
+                } else {
+                    read_options()
+                };
+                let ids = vec![">"; streams.len()];
+
+                // a blocking read that times out returns nil
+                let reply: Option<StreamReadReply> = connection
+                    .xread_options(streams, &ids, &options)
+                    .await
+                    .map_err(|e| {
+                        redis_error(
+                            &format!("failed to read from streams '{}'", streams.join(", ")),
+                            e,
+                        )
+                    })?;
+
+                for stream in reply.unwrap_or_default().keys {
+                    for entry in stream.ids {
+                        read += 1;
+
+                        match entry_payload(&entry, value_field.as_deref()) {
+                            Ok(payload) => {
+                                let connector_metadata =
+                                    self.connector_metadata(&stream.key, Some(&entry.id));
+                                ctx.deserialize_slice(
+                                    &payload,
+                                    entry_timestamp(&entry.id),
+                                    connector_metadata,
+                                )
+                                .await?;
+                            }
+                            Err(e) => {
+                                ctx.report_error("Invalid Redis stream entry", e).await;
+                            }
+                        }
+
+                        last_ids.insert(stream.key.clone(), entry.id);
+                    }
+                }
+            }
+            idle = read == 0;
+
+            if ctx.should_flush() {
+                ctx.flush_buffer().await?;
+            }
+
+            let control_message = match ctx.control_rx.try_recv() {
+                Ok(message) => Some(message),
+                Err(TryRecvError::Empty) => continue,
+                Err(TryRecvError::Disconnected) => None,
+            };
+
+            if let Some(ControlMessage::Checkpoint(_)) = &control_message {
+                let s = ctx
+                    .table_manager
+                    .get_global_keyed_state("r")
+                    .await
+                    .map_err(|e| redis_error("failed to get global key value", e))?;
+
+                for (stream, last_id) in &last_ids {
+                    s.insert(
+                        stream.clone(),
+                        RedisStreamState {
+                            stream: stream.clone(),
+                            last_id: last_id.clone(),
+                        },
+                    )
+                    .await;
+                }
+            }
+
+            if let Some(finish) = self.handle_control(control_message, ctx).await {
+                return Ok(finish);
+            }
+        }
+    }
+
+    /// Subscribes to channels with SUBSCRIBE or PSUBSCRIBE. Pub/sub messages are only delivered to
+    /// connected subscribers, so there is nothing to checkpoint, and only the first subtask
+    /// subscribes to avoid reading every message more than once
+    async fn run_pubsub(
+        &mut self,
+        ctx: &mut ArrowContext,
+        channels: Vec<String>,
+        pattern: bool,
+    ) -> Result<SourceFinishType, UserError> {
+        if ctx.task_info.task_index > 0 {
+            warn!(
+                "Redis pub/sub source {}-{} can only be executed on a single worker... setting idle",
+                ctx.task_info.operator_id, ctx.task_info.task_index
+            );
+            ctx.broadcast(ArrowMessage::Signal(SignalMessage::Watermark(
+                Watermark::Idle,
+            )))
+            .await;
+
+            loop {
+                let control_message = ctx.control_rx.recv().await;
+                if let Some(finish) = self.handle_control(control_message, ctx).await {
+                    return Ok(finish);
+                }
+            }
+        }
+
+        let RedisClient::Standard(client) = &self.client else {
+            unreachable!("redis pub/sub sources must use a standard client");
+        };
+
+        let mut pubsub = client
+            .get_async_pubsub()
+            .await
+            .map_err(|e| redis_error("failed to connect to Redis", e))?;
+
+        for channel in &channels {
+            let subscribed = if pattern {
+                pubsub.psubscribe(channel).await
+            } else {
+                pubsub.subscribe(channel).await
+            };
+            subscribed
+                .map_err(|e| redis_error(&format!("failed to subscribe to '{}'", channel), e))?;
+        }
+
+        let mut messages = Box::pin(pubsub.into_on_message());
+        let mut flush_ticker = tokio::time::interval(Duration::from_millis(50));
+        flush_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            select! {
+                message = messages.next() => {
+                    let Some(message) = message else {
+                        return Err(redis_error("subscription ended", "the Redis pub/sub connection was closed"));
+                    };
+
+                    let connector_metadata = self.connector_metadata(message.get_channel_name(), None);
+                    ctx.deserialize_slice(message.get_payload_bytes(), SystemTime::now(), connector_metadata).await?;
+                }
+                _ = flush_ticker.tick() => {
+                    if ctx.should_flush() {
+                        ctx.flush_buffer().await?;
+                    }
+                }
+                control_message = ctx.control_rx.recv() => {
+                    if let Some(finish) = self.handle_control(control_message, ctx).await {
+                        return Ok(finish);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis::{Address, RedisConfig, RedisConfigConnection};
+    use arrow::array::AsArray;
+    use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use arroyo_operator::context::{batch_bounded, BatchReceiver};
+    use arroyo_rpc::df::ArroyoSchema;
+    use arroyo_rpc::formats::JsonFormat;
+    use arroyo_rpc::TIMESTAMP_FIELD;
+    use rand::random;
+    use std::sync::Arc;
+    use tokio::sync::mpsc::{channel, Sender};
+
+    fn client() -> RedisClient {
+        RedisClient::new(&RedisConfig {
+            connection: RedisConfigConnection::Address(Address(
+                "redis://localhost:6379".to_string(),
+            )),
+            username: None,
+            password: None,
+        })
+        .unwrap()
+    }
+
+    /// Runs a source for `options` that reads JSON objects with a `user` field, returning the
+    /// channels to control it and receive its output
+    async fn run_source(options: SourceOptions) -> (Sender<ControlMessage>, BatchReceiver) {
+        let mut source = RedisSourceFunc {
+            client: client(),
+            options,
+            format: Format::Json(JsonFormat::default()),
+            framing: None,
+            bad_data: None,
+            metadata_fields: None,
+        };
+
+        let (control_tx, control_rx) = channel(128);
+        let (command_tx, _command_rx) = channel(128);
+        let (data_tx, data_recv) = batch_bounded(128);
+
+        let mut task_info = arroyo_types::get_test_task_info();
+        task_info.job_id = format!("redis-job-{}", random::<u64>());
+
+        let mut ctx = ArrowContext::new(
+            task_info,
+            None,
+            control_rx,
+            command_tx,
+            1,
+            vec![],
+            Some(ArroyoSchema::from_fields(vec![
+                Field::new("user", DataType::Utf8, true),
+                Field::new(
+                    TIMESTAMP_FIELD,
+                    DataType::Timestamp(TimeUnit::Nanosecond, None),
+                    false,
+                ),
+            ])),
+            None,
+            vec![vec![data_tx]],
+            vec![],
+            source.tables(),
+        )
+        .await;
+
+        tokio::spawn(async move {
+            source.run(&mut ctx).await;
+        });
+
+        (control_tx, data_recv)
+    }
+
+    async fn next_users(data_recv: &mut BatchReceiver, count: usize) -> Vec<String> {
+        let mut users = vec![];
+        while users.len() < count {
+            let message = tokio::time::timeout(Duration::from_secs(10), data_recv.recv())
+                .await
+                .expect("timed out waiting for records")
+                .expect("source should still be running");
+
+            if let ArrowMessage::Data(batch) = message {
+                let column = batch.column_by_name("user").unwrap().as_string::<i32>();
+                users.extend(column.iter().map(|v| v.unwrap().to_string()));
+            }
+        }
+        users
+    }
+
+    async fn stop(control_tx: &Sender<ControlMessage>) {
+        control_tx
+            .send(ControlMessage::Stop {
+                mode: StopMode::Immediate,
+            })
+            .await
+            .unwrap();
+    }
+
+    // requires a redis-server running on localhost:6379
+    #[tokio::test]
+    async fn test_stream_source() {
+        let stream = format!("test_stream_source:{}", random::<u64>());
+        let mut connection = client().get_connection().await.unwrap();
+        for user in ["alice", "bob"] {
+            let _: String = connection
+                .xadd(&stream, "*", &[("user", user)])
+                .await
+                .unwrap();
+        }
+
+        let (control_tx, mut data_recv) = run_source(SourceOptions::StreamSource {
+            streams: vec![stream.clone()],
+            group: "arroyo".to_string(),
+            start_from: Some(StartFrom::Earliest),
+            value_field: None,
+        })
+        .await;
+
+        assert_eq!(next_users(&mut data_recv, 2).await, vec!["alice", "bob"]);
+
+        // give the source time to start blocking for new entries
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let _: String = connection
+            .xadd(&stream, "*", &[("user", "carol")])
+            .await
+            .unwrap();
+        assert_eq!(next_users(&mut data_recv, 1).await, vec!["carol"]);
+
+        stop(&control_tx).await;
+        let _: () = connection.del(&stream).await.unwrap();
+    }
+
+    // requires a redis-server running on localhost:6379
+    #[tokio::test]
+    async fn test_pubsub_source() {
+        let channel = format!("test_pubsub_source:{}", random::<u64>());
+        let (control_tx, mut data_recv) = run_source(SourceOptions::PubSubSource {
+            channels: vec![channel.clone()],
+            pattern: None,
+        })
+        .await;
+
+        // messages are only delivered once the source has subscribed
+        let mut connection = client().get_connection().await.unwrap();
+        let payload = serde_json::json!({"user": "alice"}).to_string();
+        let start = std::time::Instant::now();
+        while connection
+            .publish::<_, _, i64>(&channel, &payload)
+            .await
+            .unwrap()
+            == 0
+        {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "timed out waiting for the source to subscribe"
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        assert_eq!(next_users(&mut data_recv, 1).await, vec!["alice"]);
+
+        stop(&control_tx).await;
+    }
+
+    #[test]
+    fn test_entry_timestamp() {
+        assert_eq!(
+            entry_timestamp("1700000000123-4"),
+            SystemTime::UNIX_EPOCH + Duration::from_millis(1700000000123)
+        );
+    }
+
+    #[test]
+    fn test_entry_payload() {
+        let entry = StreamId {
+            id: "1-0".to_string(),
+            map: HashMap::from([
+                (
+                    "user".to_string(),
+                    redis::Value::BulkString(b"alice".to_vec()),
+                ),
+                (
+                    "value".to_string(),
+                    redis::Value::BulkString(b"{\"a\": 1}".to_vec()),
+                ),
+            ]),
+        };
+
+        assert_eq!(
+            entry_payload(&entry, Some("value")).unwrap(),
+            b"{\"a\": 1}".to_vec()
+        );
+        assert!(entry_payload(&entry, Some("missing")).is_err());
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&entry_payload(&entry, None).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"user": "alice", "value": "{\"a\": 1}"})
+        );
+    }
+}
@@ -174,10 +174,11 @@ impl Connector for SingleFileConnector {
             TableType::Sink => Ok(OperatorNode::from_operator(Box::new(SingleFileSink {
                 output_path: table.path,
                 file: None,
-                serializer: ArrowSerializer::new(
+                serializer: ArrowSerializer::with_framing(
                     config
                         .format
                         .expect("Format must be set for Single File Sink"),
+                    config.framing,
                 ),
             }))),
         }
@@ -33,10 +33,11 @@ impl ArrowOperator for SingleFileSink {
 
     async fn process_batch(&mut self, batch: RecordBatch, _ctx: &mut ArrowContext) {
         let values = self.serializer.serialize(&batch);
+        let separator = self.serializer.record_separator();
         let file = self.file.as_mut().unwrap();
         for value in values {
             file.write_all(&value).await.unwrap();
-            file.write_all(b"\n").await.unwrap();
+            file.write_all(separator).await.unwrap();
         }
     }
 
@@ -53,6 +54,8 @@ impl ArrowOperator for SingleFileSink {
             .cloned()
             .unwrap_or_default();
         let file = if offset > 0 {
+            // the file was already started before restoring, so it has any header it needs
+            self.serializer.skip_header();
             let file = OpenOptions::new()
                 .append(true)
                 .open(&self.output_path)
@@ -4,7 +4,7 @@ use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::SourceOperator;
 use arroyo_operator::SourceFinishType;
 use arroyo_rpc::{
-    formats::{BadData, Format, Framing},
+    formats::{BadData, CsvFormat, Format, Framing},
     grpc::rpc::{StopMode, TableConfig},
     ControlMessage,
 };
@@ -15,6 +15,8 @@ use tokio::{
 };
 use tracing::info;
 
+use crate::without_csv_header;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SingleFileSourceFunc {
     pub input_file: String,
@@ -31,7 +33,7 @@ impl SingleFileSourceFunc {
             return SourceFinishType::Final;
         }
         ctx.initialize_deserializer(
-            self.format.clone(),
+            without_csv_header(&self.format),
             self.framing.clone(),
             self.bad_data.clone(),
         );
@@ -44,6 +46,7 @@ impl SingleFileSourceFunc {
         let file = File::open(&self.input_file).await.expect(&self.input_file);
         let mut lines = BufReader::new(file).lines();
 
+        let has_header = matches!(self.format, Format::Csv(CsvFormat { header: true, .. }));
         let mut i = 0;
 
         while let Some(s) = lines.next_line().await.unwrap() {
@@ -51,9 +54,11 @@ impl SingleFileSourceFunc {
                 i += 1;
                 continue;
             }
-            ctx.deserialize_slice(s.as_bytes(), SystemTime::now(), None)
-                .await
-                .unwrap();
+            if !(has_header && self.lines_read == 0) {
+                ctx.deserialize_slice(s.as_bytes(), SystemTime::now(), None)
+                    .await
+                    .unwrap();
+            }
             if ctx.should_flush() {
                 ctx.flush_buffer().await.unwrap();
             }
@@ -135,7 +135,7 @@ impl Connector for StdoutConnector {
             .unwrap_or_else(|| Format::Json(JsonFormat::default()));
         Ok(OperatorNode::from_operator(Box::new(StdoutSink {
             stdout: BufWriter::new(tokio::io::stdout()),
-            serializer: ArrowSerializer::new(format),
+            serializer: ArrowSerializer::with_framing(format, c.framing),
         })))
     }
 }
@@ -18,9 +18,10 @@ impl ArrowOperator for StdoutSink {
     }
 
     async fn process_batch(&mut self, batch: RecordBatch, _: &mut ArrowContext) {
+        let separator = self.serializer.record_separator();
         for value in self.serializer.serialize(&batch) {
             self.stdout.write_all(&value).await.unwrap();
-            self.stdout.write_u8(b'\n').await.unwrap();
+            self.stdout.write_all(separator).await.unwrap();
         }
         self.stdout.flush().await.unwrap();
     }
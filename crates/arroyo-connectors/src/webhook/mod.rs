@@ -226,10 +226,11 @@ impl Connector for WebhookConnector {
                     .transpose()?,
             )?,
             semaphore: Arc::new(Semaphore::new(MAX_INFLIGHT as usize)),
-            serializer: ArrowSerializer::new(
+            serializer: ArrowSerializer::with_framing(
                 config
                     .format
                     .expect("No format configured for webhook sink"),
+                config.framing,
             ),
             last_reported_error_at: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
         })))
@@ -16,11 +16,12 @@ const COLLECTION_TIME: Duration = Duration::from_secs(5 * 60);
 const NUM_BUCKETS: usize = (COLLECTION_TIME.as_secs() / COLLECTION_RATE.as_secs()) as usize;
 const EWMA_ALPHA: f64 = 0.1;
 
-pub const RATE_METRICS: [MetricName; 4] = [
+pub const RATE_METRICS: [MetricName; 5] = [
     MetricName::BytesRecv,
     MetricName::BytesSent,
     MetricName::MessagesRecv,
     MetricName::MessagesSent,
+    MetricName::RecordsDropped,
 ];
 
 pub fn get_metric_name(name: &str) -> Option<MetricName> {
@@ -5,7 +5,9 @@ use arrow_schema::DataType;
 use arroyo_rpc::api_types::pipelines::{PipelineEdge, PipelineGraph, PipelineNode};
 use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::grpc::api;
-use arroyo_rpc::grpc::api::{ArrowProgram, ArrowProgramConfig, ConnectorOp, EdgeType};
+use arroyo_rpc::grpc::api::{
+    ArrowProgram, ArrowProgramConfig, ConnectorOp, EdgeType, LookupJoinOperator,
+};
 use petgraph::dot::Dot;
 use petgraph::graph::DiGraph;
 use petgraph::prelude::EdgeRef;
@@ -29,7 +31,11 @@ pub enum OperatorName {
     AsyncUdf,
     Join,
     InstantJoin,
+    LookupJoin,
+    TemporalJoin,
     WindowFunction,
+    TopN,
+    Deduplicate,
     TumblingWindowAggregate,
     SlidingWindowAggregate,
     SessionWindowAggregate,
@@ -44,6 +50,9 @@ pub enum LogicalEdgeType {
     Shuffle,
     LeftJoin,
     RightJoin,
+    /// Carries records that a source failed to deserialize to the consumers of its
+    /// dead-letter table
+    DeadLetter,
 }
 
 impl Display for LogicalEdgeType {
@@ -53,6 +62,7 @@ impl Display for LogicalEdgeType {
             LogicalEdgeType::Shuffle => write!(f, "⤨"),
             LogicalEdgeType::LeftJoin => write!(f, "-[left]⤨"),
             LogicalEdgeType::RightJoin => write!(f, "-[right]⤨"),
+            LogicalEdgeType::DeadLetter => write!(f, "-[dlq]⤨"),
         }
     }
 }
@@ -65,6 +75,7 @@ impl From<arroyo_rpc::grpc::api::EdgeType> for LogicalEdgeType {
             EdgeType::Shuffle => LogicalEdgeType::Shuffle,
             EdgeType::LeftJoin => LogicalEdgeType::LeftJoin,
             EdgeType::RightJoin => LogicalEdgeType::RightJoin,
+            EdgeType::DeadLetter => LogicalEdgeType::DeadLetter,
         }
     }
 }
@@ -76,6 +87,7 @@ impl From<LogicalEdgeType> for api::EdgeType {
             LogicalEdgeType::Shuffle => EdgeType::Shuffle,
             LogicalEdgeType::LeftJoin => EdgeType::LeftJoin,
             LogicalEdgeType::RightJoin => EdgeType::RightJoin,
+            LogicalEdgeType::DeadLetter => EdgeType::DeadLetter,
         }
     }
 }
@@ -284,7 +296,19 @@ impl LogicalProgram {
                 | OperatorName::ArrowKey => continue,
                 OperatorName::Join => "join-with-expiration".to_string(),
                 OperatorName::InstantJoin => "windowed-join".to_string(),
+                OperatorName::TemporalJoin => "temporal-join".to_string(),
+                OperatorName::LookupJoin => {
+                    let Ok(config) = LookupJoinOperator::decode(&t.operator_config[..]) else {
+                        continue;
+                    };
+                    format!(
+                        "{}-lookup-join",
+                        config.connector.map(|c| c.connector).unwrap_or_default()
+                    )
+                }
                 OperatorName::WindowFunction => "sql-window-function".to_string(),
+                OperatorName::TopN => "sql-top-n".to_string(),
+                OperatorName::Deduplicate => "sql-deduplicate".to_string(),
                 OperatorName::TumblingWindowAggregate => {
                     "sql-tumbling-window-aggregate".to_string()
                 }
@@ -0,0 +1,145 @@
+use arrow_schema::{DataType, Fields, Schema, TimeUnit};
+use arroyo_rpc::formats::{CsvFormat, TimestampFormat};
+use arroyo_types::SourceError;
+use serde_json::{Map, Number, Value};
+
+/// Parses delimited text into JSON objects, using the column order of the schema, so that they
+/// can be decoded by the JSON decoder.
+pub(crate) struct CsvDecoder {
+    format: CsvFormat,
+    builder: ::csv::ReaderBuilder,
+    fields: Fields,
+}
+
+impl CsvDecoder {
+    pub fn new(format: CsvFormat, schema: &Schema) -> Self {
+        let mut builder = ::csv::ReaderBuilder::new();
+        builder
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(format.delimiter as u8)
+            .quote(format.quote as u8)
+            .escape(format.escape.map(|c| c as u8))
+            .double_quote(format.escape.is_none())
+            .buffer_capacity(1024);
+
+        Self {
+            format,
+            builder,
+            fields: schema.fields.clone(),
+        }
+    }
+
+    /// Decodes the records in a buffer, skipping its first line if the format has a header.
+    /// Returns the bytes of each record along with its JSON object, or the reason it's invalid.
+    pub fn decode<'a>(&self, buf: &'a [u8]) -> Vec<(&'a [u8], Result<Value, SourceError>)> {
+        let mut reader = self.builder.from_reader(buf);
+        let mut record = ::csv::StringRecord::new();
+        let mut records = vec![];
+        let mut skip_header = self.format.header;
+
+        loop {
+            let start = reader.position().byte() as usize;
+            let result = reader.read_record(&mut record);
+            let end = reader.position().byte() as usize;
+            let raw = trim_line_breaks(&buf[start..end]);
+
+            match result {
+                Ok(false) => break,
+                _ if std::mem::take(&mut skip_header) => {}
+                Ok(true) => records.push((raw, self.record_to_json(&record))),
+                Err(e) => records.push((
+                    raw,
+                    Err(SourceError::bad_data(format!("invalid CSV: {}", e))),
+                )),
+            }
+
+            if end == start {
+                break;
+            }
+        }
+
+        records
+    }
+
+    fn record_to_json(&self, record: &::csv::StringRecord) -> Result<Value, SourceError> {
+        if record.len() != self.fields.len() {
+            return Err(SourceError::bad_data(format!(
+                "expected {} columns in CSV record, but found {}",
+                self.fields.len(),
+                record.len()
+            )));
+        }
+
+        let mut obj = Map::new();
+        for (field, value) in self.fields.iter().zip(record.iter()) {
+            obj.insert(
+                field.name().clone(),
+                self.to_json(field.name(), field.data_type(), value)?,
+            );
+        }
+
+        Ok(Value::Object(obj))
+    }
+
+    fn to_json(&self, name: &str, data_type: &DataType, value: &str) -> Result<Value, SourceError> {
+        if value == self.format.null_value {
+            return Ok(Value::Null);
+        }
+
+        let invalid = || {
+            SourceError::bad_data(format!(
+                "invalid value '{}' for column '{}' of type {}",
+                value, name, data_type
+            ))
+        };
+
+        Ok(match data_type {
+            DataType::Boolean => match value.trim().to_ascii_lowercase().as_str() {
+                "true" | "t" | "1" => Value::Bool(true),
+                "false" | "f" | "0" => Value::Bool(false),
+                _ => return Err(invalid()),
+            },
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float16
+            | DataType::Float32
+            | DataType::Float64 => {
+                Value::Number(value.trim().parse::<Number>().map_err(|_| invalid())?)
+            }
+            DataType::Timestamp(unit, _)
+                if self.format.timestamp_format == TimestampFormat::UnixMillis =>
+            {
+                let millis: i64 = value.trim().parse().map_err(|_| invalid())?;
+                // the JSON decoder interprets numeric timestamps in the unit of the column
+                Value::Number(
+                    match unit {
+                        TimeUnit::Second => millis / 1_000,
+                        TimeUnit::Millisecond => millis,
+                        TimeUnit::Microsecond => millis * 1_000,
+                        TimeUnit::Nanosecond => millis * 1_000_000,
+                    }
+                    .into(),
+                )
+            }
+            _ => Value::String(value.to_string()),
+        })
+    }
+}
+
+/// Trims the line breaks around a record, including any blank lines that the reader skipped
+fn trim_line_breaks(mut raw: &[u8]) -> &[u8] {
+    while let [b'\r' | b'\n', rest @ ..] = raw {
+        raw = rest;
+    }
+    while let [rest @ .., b'\r' | b'\n'] = raw {
+        raw = rest;
+    }
+    raw
+}
@@ -0,0 +1,113 @@
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+    TimestampSecondType,
+};
+use arrow_array::{Array, RecordBatch};
+use arrow_schema::{DataType, Schema, TimeUnit};
+use arroyo_rpc::formats::{CsvFormat, TimestampFormat};
+
+fn writer(format: &CsvFormat) -> ::csv::Writer<Vec<u8>> {
+    ::csv::WriterBuilder::new()
+        .delimiter(format.delimiter as u8)
+        .quote(format.quote as u8)
+        .escape(format.escape.map(|c| c as u8).unwrap_or(b'\\'))
+        .double_quote(format.escape.is_none())
+        .terminator(::csv::Terminator::Any(b'\n'))
+        .from_writer(vec![])
+}
+
+/// Writes each record with the CSV writer, returning them as separate buffers without trailing
+/// newlines
+fn write_records<'a, R: IntoIterator<Item = &'a str>>(
+    format: &CsvFormat,
+    records: impl Iterator<Item = R>,
+) -> Vec<Vec<u8>> {
+    let mut writer = writer(format);
+    let mut ends = vec![];
+    for record in records {
+        writer
+            .write_record(record)
+            .expect("writing to a buffer cannot fail");
+        writer.flush().expect("writing to a buffer cannot fail");
+        ends.push(writer.get_ref().len());
+    }
+
+    let buf = writer
+        .into_inner()
+        .expect("writing to a buffer cannot fail");
+
+    let mut start = 0;
+    ends.into_iter()
+        .map(|end| {
+            let record = buf[start..end - 1].to_vec();
+            start = end;
+            record
+        })
+        .collect()
+}
+
+pub(crate) fn header(format: &CsvFormat, schema: &Schema) -> Vec<u8> {
+    write_records(
+        format,
+        std::iter::once(schema.fields.iter().map(|f| f.name().as_str())),
+    )
+    .pop()
+    .unwrap()
+}
+
+pub(crate) fn serialize(format: &CsvFormat, batch: &RecordBatch) -> Vec<Vec<u8>> {
+    let options = FormatOptions::default().with_null(&format.null_value);
+
+    let unix_millis = format.timestamp_format == TimestampFormat::UnixMillis;
+    let millis: Vec<_> = batch
+        .columns()
+        .iter()
+        .map(|c| match c.data_type() {
+            DataType::Timestamp(unit, _) if unix_millis => Some(timestamp_millis(unit, c.as_ref())),
+            _ => None,
+        })
+        .collect();
+
+    let formatters: Vec<_> = batch
+        .columns()
+        .iter()
+        .map(|c| ArrayFormatter::try_new(c.as_ref(), &options).expect("unsupported CSV type"))
+        .collect();
+
+    let rows: Vec<Vec<String>> = (0..batch.num_rows())
+        .map(|row| {
+            formatters
+                .iter()
+                .zip(&millis)
+                .map(|(formatter, millis)| match millis {
+                    Some(millis) if millis.is_valid(row) => millis.value(row).to_string(),
+                    _ => formatter.value(row).to_string(),
+                })
+                .collect()
+        })
+        .collect();
+
+    write_records(
+        format,
+        rows.iter().map(|row| row.iter().map(|v| v.as_str())),
+    )
+}
+
+fn timestamp_millis(unit: &TimeUnit, column: &dyn Array) -> arrow_array::Int64Array {
+    match unit {
+        TimeUnit::Second => column
+            .as_primitive::<TimestampSecondType>()
+            .unary(|v| v * 1_000),
+        TimeUnit::Millisecond => column
+            .as_primitive::<TimestampMillisecondType>()
+            .reinterpret_cast(),
+        TimeUnit::Microsecond => column
+            .as_primitive::<TimestampMicrosecondType>()
+            .unary(|v| v / 1_000),
+        TimeUnit::Nanosecond => column
+            .as_primitive::<TimestampNanosecondType>()
+            .unary(|v| v / 1_000_000),
+    }
+}
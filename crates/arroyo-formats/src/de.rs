@@ -1,16 +1,19 @@
 use crate::avro::de;
+use crate::csv::de::CsvDecoder;
+use crate::dead_letter::{metadata_to_json, DeadLetterBuffer};
 use crate::proto::schema::get_pool;
 use crate::{proto, should_flush};
 use arrow::array::{Int32Builder, Int64Builder};
 use arrow::compute::kernels;
 use arrow_array::builder::{
-    ArrayBuilder, GenericByteBuilder, StringBuilder, TimestampNanosecondBuilder,
+    ArrayBuilder, BinaryBuilder, GenericByteBuilder, StringBuilder, TimestampNanosecondBuilder,
 };
 use arrow_array::types::GenericBinaryType;
 use arrow_array::RecordBatch;
 use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::formats::{
-    AvroFormat, BadData, Format, Framing, FramingMethod, JsonFormat, ProtobufFormat,
+    AvroFormat, BadData, Endianness, Format, Framing, FramingMethod, JsonFormat, LengthPrefixWidth,
+    LengthPrefixedFraming, NewlineDelimitedFraming, ProtobufFormat,
 };
 use arroyo_rpc::schema_resolver::{FailingSchemaResolver, FixedSchemaResolver, SchemaResolver};
 use arroyo_types::{to_nanos, SourceError};
@@ -26,6 +29,9 @@ pub enum FieldValueType<'a> {
     Int64(i64),
     Int32(i32),
     String(&'a String),
+    OptionalString(Option<&'a str>),
+    Bytes(Option<&'a [u8]>),
+    Timestamp(Option<SystemTime>),
     // Extend with more types as needed
 }
 
@@ -35,6 +41,22 @@ pub struct FramingIterator<'a> {
     offset: usize,
 }
 
+/// The end of a length-prefixed message whose last frame is cut off, either in its prefix or
+/// because the prefix gives a length past the end of the message
+#[derive(Debug, PartialEq)]
+pub struct TruncatedFrame<'a> {
+    pub data: &'a [u8],
+}
+
+impl<'a> TruncatedFrame<'a> {
+    pub fn to_error(&self) -> SourceError {
+        SourceError::bad_data(format!(
+            "length-prefixed frame is truncated; the message ends {} bytes into it",
+            self.data.len()
+        ))
+    }
+}
+
 impl<'a> FramingIterator<'a> {
     pub fn new(framing: Option<Arc<Framing>>, buf: &'a [u8]) -> Self {
         Self {
@@ -46,7 +68,7 @@ impl<'a> FramingIterator<'a> {
 }
 
 impl<'a> Iterator for FramingIterator<'a> {
-    type Item = &'a [u8];
+    type Item = Result<&'a [u8], TruncatedFrame<'a>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.offset >= self.buf.len() {
@@ -68,14 +90,76 @@ impl<'a> Iterator for FramingIterator<'a> {
                         let length =
                             (end - prev).min(newline.max_line_length.unwrap_or(u64::MAX) as usize);
 
-                        Some(&self.buf[prev..(prev + length)])
+                        Some(Ok(&self.buf[prev..(prev + length)]))
+                    }
+                    FramingMethod::Delimiter(delimited) => {
+                        let end =
+                            memchr::memmem::find(&self.buf[self.offset..], &delimited.delimiter)
+                                .map(|i| self.offset + i)
+                                .unwrap_or(self.buf.len());
+
+                        let prev = self.offset;
+                        self.offset = end + delimited.delimiter.len();
+
+                        Some(Ok(&self.buf[prev..end]))
+                    }
+                    FramingMethod::LengthPrefixed(length_prefixed) => {
+                        let prev = self.offset;
+                        let frame = read_length_prefix(length_prefixed, &self.buf[prev..])
+                            .map(|(header_len, length)| {
+                                (prev + header_len, (prev + header_len).checked_add(length))
+                            })
+                            .filter(|(_, end)| end.is_some_and(|end| end <= self.buf.len()));
+
+                        let Some((start, Some(end))) = frame else {
+                            self.offset = self.buf.len();
+                            return Some(Err(TruncatedFrame {
+                                data: &self.buf[prev..],
+                            }));
+                        };
+
+                        self.offset = end;
+                        Some(Ok(&self.buf[start..end]))
                     }
                 }
             }
             None => {
                 self.offset = self.buf.len();
-                Some(self.buf)
+                Some(Ok(self.buf))
+            }
+        }
+    }
+}
+
+/// Reads a length prefix from the start of `buf`, returning the size of the prefix and the
+/// length it encodes, or None if the buffer ends before the prefix does
+fn read_length_prefix(framing: &LengthPrefixedFraming, buf: &[u8]) -> Option<(usize, usize)> {
+    match framing.width {
+        LengthPrefixWidth::U16 => {
+            let bytes: [u8; 2] = buf.get(..2)?.try_into().unwrap();
+            let length = match framing.endianness {
+                Endianness::Big => u16::from_be_bytes(bytes),
+                Endianness::Little => u16::from_le_bytes(bytes),
+            };
+            Some((2, length as usize))
+        }
+        LengthPrefixWidth::U32 => {
+            let bytes: [u8; 4] = buf.get(..4)?.try_into().unwrap();
+            let length = match framing.endianness {
+                Endianness::Big => u32::from_be_bytes(bytes),
+                Endianness::Little => u32::from_le_bytes(bytes),
+            };
+            Some((4, length as usize))
+        }
+        LengthPrefixWidth::Varint => {
+            let mut length = 0u64;
+            for (i, b) in buf.iter().take(10).enumerate() {
+                length |= ((b & 0x7f) as u64) << (7 * i);
+                if b & 0x80 == 0 {
+                    return Some((i + 1, length as usize));
+                }
             }
+            None
         }
     }
 }
@@ -86,12 +170,15 @@ pub struct ArrowDeserializer {
     schema: ArroyoSchema,
     bad_data: BadData,
     json_decoder: Option<(arrow::json::reader::Decoder, TimestampNanosecondBuilder)>,
+    csv_decoder: Option<CsvDecoder>,
     buffered_count: usize,
     buffered_since: Instant,
     schema_registry: Arc<Mutex<HashMap<u32, apache_avro::schema::Schema>>>,
     proto_pool: DescriptorPool,
     schema_resolver: Arc<dyn SchemaResolver + Sync>,
     additional_fields_builder: Option<HashMap<String, Box<dyn ArrayBuilder>>>,
+    dead_letters: Option<DeadLetterBuffer>,
+    invalid_rows: usize,
 }
 
 impl ArrowDeserializer {
@@ -132,10 +219,20 @@ impl ArrowDeserializer {
             DescriptorPool::global()
         };
 
+        let csv_decoder = if let Format::Csv(csv) = &format {
+            Some(CsvDecoder::new(
+                csv.clone(),
+                &schema.schema_without_timestamp(),
+            ))
+        } else {
+            None
+        };
+
         Self {
             json_decoder: matches!(
                 format,
                 Format::Json(..)
+                    | Format::Csv(..)
                     | Format::Avro(AvroFormat {
                         into_unstructured_json: false,
                         ..
@@ -153,16 +250,22 @@ impl ArrowDeserializer {
                     ))
                     .with_limit_to_batch_size(false)
                     .with_strict_mode(false)
-                    .with_allow_bad_data(matches!(bad_data, BadData::Drop { .. }))
+                    .with_allow_bad_data(matches!(
+                        bad_data,
+                        BadData::Drop { .. } | BadData::Dlq { .. }
+                    ))
                     .build_decoder()
                     .unwrap(),
                     TimestampNanosecondBuilder::new(),
                 )
             }),
+            csv_decoder,
             format: Arc::new(format),
             framing: framing.map(Arc::new),
             schema,
             schema_registry: Arc::new(Mutex::new(HashMap::new())),
+            dead_letters: matches!(bad_data, BadData::Dlq { .. }).then(DeadLetterBuffer::new),
+            invalid_rows: 0,
             bad_data,
             schema_resolver,
             proto_pool,
@@ -179,17 +282,199 @@ impl ArrowDeserializer {
         timestamp: SystemTime,
         additional_fields: Option<HashMap<&String, FieldValueType<'_>>>,
     ) -> Vec<SourceError> {
+        self.deserialize_slice_at(buffer, msg, timestamp, additional_fields, &[])
+            .await
+    }
+
+    /// Like [`Self::deserialize_slice`], for a message at the given position in the source (like
+    /// its topic, partition and offset). The position is recorded in the metadata of dead letters
+    /// so that bad messages can be found, whether or not the table declares columns for it.
+    pub async fn deserialize_slice_at(
+        &mut self,
+        buffer: &mut [Box<dyn ArrayBuilder>],
+        msg: &[u8],
+        timestamp: SystemTime,
+        additional_fields: Option<HashMap<&String, FieldValueType<'_>>>,
+        position: &[(&str, FieldValueType<'_>)],
+    ) -> Vec<SourceError> {
+        let dead_letter_metadata = self
+            .dead_letters
+            .is_some()
+            .then(|| metadata_to_json(position, additional_fields.as_ref()))
+            .flatten();
+        let dead_letter_metadata = dead_letter_metadata.as_deref();
+
         match &*self.format {
-            Format::Avro(_) => self.deserialize_slice_avro(buffer, msg, timestamp).await,
-            _ => FramingIterator::new(self.framing.clone(), msg)
-                .map(|t| self.deserialize_single(buffer, t, timestamp, additional_fields.clone()))
-                .filter_map(|t| t.err())
-                .collect(),
+            Format::Avro(_) => {
+                let errors = self.deserialize_slice_avro(buffer, msg, timestamp).await;
+                for e in &errors {
+                    self.dead_letter(msg, e, dead_letter_metadata, timestamp);
+                }
+                errors
+            }
+            Format::Csv(_) => {
+                self.deserialize_slice_csv(msg, timestamp, additional_fields, dead_letter_metadata)
+            }
+            _ => {
+                let mut errors = vec![];
+                for frame in FramingIterator::new(self.framing.clone(), msg) {
+                    let frame = match frame {
+                        Ok(frame) => frame,
+                        Err(truncated) => {
+                            let e = truncated.to_error();
+                            self.dead_letter(truncated.data, &e, dead_letter_metadata, timestamp);
+                            errors.push(e);
+                            continue;
+                        }
+                    };
+                    if let Err(e) = self.deserialize_single(
+                        buffer,
+                        frame,
+                        timestamp,
+                        additional_fields.clone(),
+                        dead_letter_metadata,
+                    ) {
+                        self.dead_letter(frame, &e, dead_letter_metadata, timestamp);
+                        errors.push(e);
+                    }
+                }
+                errors
+            }
+        }
+    }
+
+    fn deserialize_slice_csv(
+        &mut self,
+        msg: &[u8],
+        timestamp: SystemTime,
+        additional_fields: Option<HashMap<&String, FieldValueType<'_>>>,
+        dead_letter_metadata: Option<&str>,
+    ) -> Vec<SourceError> {
+        let mut errors = vec![];
+
+        // the CSV reader splits the buffer into records itself, which lets quoted values contain
+        // line breaks; other framing methods are applied first, ending each frame with a newline
+        let split_lines = matches!(
+            self.framing.as_deref().map(|f| &f.method),
+            None | Some(FramingMethod::Newline(NewlineDelimitedFraming {
+                max_line_length: None,
+            }))
+        );
+
+        let framed;
+        let buf = if split_lines {
+            msg
+        } else {
+            let mut frames = Vec::with_capacity(msg.len());
+            for frame in FramingIterator::new(self.framing.clone(), msg) {
+                match frame {
+                    Ok(frame) => {
+                        frames.extend_from_slice(frame);
+                        frames.push(b'\n');
+                    }
+                    Err(truncated) => {
+                        let e = truncated.to_error();
+                        self.dead_letter(truncated.data, &e, dead_letter_metadata, timestamp);
+                        errors.push(e);
+                    }
+                }
+            }
+            framed = frames;
+            &framed[..]
+        };
+
+        let records = self
+            .csv_decoder
+            .as_ref()
+            .expect("csv decoder not initialized")
+            .decode(buf);
+
+        for (raw, record) in records {
+            let result = record.and_then(|json| {
+                self.deserialize_csv_record(
+                    raw,
+                    json,
+                    timestamp,
+                    additional_fields.clone(),
+                    dead_letter_metadata,
+                )
+            });
+            if let Err(e) = result {
+                self.dead_letter(raw, &e, dead_letter_metadata, timestamp);
+                errors.push(e);
+            }
+        }
+
+        errors
+    }
+
+    fn deserialize_csv_record(
+        &mut self,
+        raw: &[u8],
+        json: Value,
+        timestamp: SystemTime,
+        additional_fields: Option<HashMap<&String, FieldValueType>>,
+        dead_letter_metadata: Option<&str>,
+    ) -> Result<(), SourceError> {
+        self.init_additional_fields_builder(additional_fields.as_ref());
+
+        let Some((decoder, timestamp_builder)) = &mut self.json_decoder else {
+            panic!("json decoder not initialized");
+        };
+
+        decoder
+            .decode(json.to_string().as_bytes())
+            .map_err(|e| SourceError::bad_data(format!("invalid CSV: {:?}", e)))?;
+        timestamp_builder.append_value(to_nanos(timestamp) as i64);
+        Self::pending_dead_letter(&mut self.dead_letters, raw, dead_letter_metadata);
+
+        add_additional_fields_using_builder(additional_fields, &mut self.additional_fields_builder);
+        self.buffered_count += 1;
+
+        Ok(())
+    }
+
+    fn dead_letter(
+        &mut self,
+        raw: &[u8],
+        error: &SourceError,
+        metadata: Option<&str>,
+        timestamp: SystemTime,
+    ) {
+        if let (Some(dead_letters), SourceError::BadData { details }) =
+            (&mut self.dead_letters, error)
+        {
+            dead_letters.push(Some(raw), details, metadata.map(str::to_string), timestamp);
+        }
+    }
+
+    /// Records a row that has been passed to the json decoder, so that it can be sent to the
+    /// dead-letter queue if the decoder finds it to be invalid when flushed
+    fn pending_dead_letter(
+        dead_letters: &mut Option<DeadLetterBuffer>,
+        raw: &[u8],
+        metadata: Option<&str>,
+    ) {
+        if let Some(dead_letters) = dead_letters {
+            dead_letters.push_pending(raw, metadata.map(str::to_string));
         }
     }
 
     pub fn should_flush(&self) -> bool {
-        should_flush(self.buffered_count, self.buffered_since)
+        let dead_letters = self.dead_letters.as_ref().map(|d| d.len()).unwrap_or(0);
+        should_flush(self.buffered_count + dead_letters, self.buffered_since)
+    }
+
+    /// Returns the records that have been sent to the dead-letter queue since the last call,
+    /// if the deserializer is configured with `bad_data = 'dlq'`
+    pub fn flush_dead_letters(&mut self) -> Option<RecordBatch> {
+        self.dead_letters.as_mut()?.flush()
+    }
+
+    /// Returns the number of rows that were found to be invalid when the buffer was flushed
+    /// (and were dropped or sent to the dead-letter queue) since the last call
+    pub fn take_invalid_rows(&mut self) -> usize {
+        std::mem::take(&mut self.invalid_rows)
     }
 
     pub fn flush_buffer(&mut self) -> Option<Result<RecordBatch, SourceError>> {
@@ -215,7 +500,7 @@ impl ArrowDeserializer {
                         RecordBatch::try_new(self.schema.schema.clone(), columns).unwrap()
                     }),
             ),
-            BadData::Drop { .. } => Some(
+            BadData::Drop { .. } | BadData::Dlq { .. } => Some(
                 decoder
                     .flush_with_bad_data()
                     .map_err(|e| {
@@ -227,8 +512,16 @@ impl ArrowDeserializer {
                     .transpose()?
                     .map(|(batch, mask, _)| {
                         let mut columns = batch.columns().to_vec();
-                        let timestamp =
-                            kernels::filter::filter(&timestamp.finish(), &mask).unwrap();
+                        let timestamp = timestamp.finish();
+                        self.invalid_rows += mask.false_count();
+                        if let Some(dead_letters) = &mut self.dead_letters {
+                            dead_letters.resolve_pending(
+                                &mask,
+                                &timestamp,
+                                "record does not match the table schema",
+                            );
+                        }
+                        let timestamp = kernels::filter::filter(&timestamp, &mask).unwrap();
 
                         columns.insert(self.schema.timestamp_index, Arc::new(timestamp));
                         flush_additional_fields_builders(
@@ -248,6 +541,7 @@ impl ArrowDeserializer {
         msg: &[u8],
         timestamp: SystemTime,
         additional_fields: Option<HashMap<&String, FieldValueType>>,
+        dead_letter_metadata: Option<&str>,
     ) -> Result<(), SourceError> {
         match &*self.format {
             Format::RawString(_)
@@ -278,34 +572,17 @@ impl ArrowDeserializer {
                     msg
                 };
 
+                self.init_additional_fields_builder(additional_fields.as_ref());
+
                 let Some((decoder, timestamp_builder)) = &mut self.json_decoder else {
                     panic!("json decoder not initialized");
                 };
 
-                if self.additional_fields_builder.is_none() {
-                    if let Some(fields) = additional_fields.as_ref() {
-                        let mut builders = HashMap::new();
-                        for (key, value) in fields.iter() {
-                            let builder: Box<dyn ArrayBuilder> = match value {
-                                FieldValueType::Int32(_) => Box::new(Int32Builder::new()),
-                                FieldValueType::Int64(_) => Box::new(Int64Builder::new()),
-                                FieldValueType::String(_) => Box::new(StringBuilder::new()),
-                            };
-                            builders.insert(key, builder);
-                        }
-                        self.additional_fields_builder = Some(
-                            builders
-                                .into_iter()
-                                .map(|(k, v)| ((*k).clone(), v))
-                                .collect(),
-                        );
-                    }
-                }
-
                 decoder
                     .decode(msg)
                     .map_err(|e| SourceError::bad_data(format!("invalid JSON: {:?}", e)))?;
                 timestamp_builder.append_value(to_nanos(timestamp) as i64);
+                Self::pending_dead_letter(&mut self.dead_letters, msg, dead_letter_metadata);
 
                 add_additional_fields_using_builder(
                     additional_fields,
@@ -327,6 +604,7 @@ impl ArrowDeserializer {
                         .decode(json.to_string().as_bytes())
                         .map_err(|e| SourceError::bad_data(format!("invalid JSON: {:?}", e)))?;
                     timestamp_builder.append_value(to_nanos(timestamp) as i64);
+                    Self::pending_dead_letter(&mut self.dead_letters, msg, dead_letter_metadata);
 
                     add_additional_fields_using_builder(
                         additional_fields,
@@ -337,12 +615,39 @@ impl ArrowDeserializer {
                 }
             }
             Format::Avro(_) => unreachable!("this should not be called for avro"),
+            Format::Csv(_) => unreachable!("this should not be called for csv"),
             Format::Parquet(_) => todo!("parquet is not supported as an input format"),
         }
 
         Ok(())
     }
 
+    fn init_additional_fields_builder(
+        &mut self,
+        additional_fields: Option<&HashMap<&String, FieldValueType<'_>>>,
+    ) {
+        if self.additional_fields_builder.is_some() {
+            return;
+        }
+
+        if let Some(fields) = additional_fields {
+            let mut builders = HashMap::new();
+            for (key, value) in fields.iter() {
+                let builder: Box<dyn ArrayBuilder> = match value {
+                    FieldValueType::Int32(_) => Box::new(Int32Builder::new()),
+                    FieldValueType::Int64(_) => Box::new(Int64Builder::new()),
+                    FieldValueType::String(_) | FieldValueType::OptionalString(_) => {
+                        Box::new(StringBuilder::new())
+                    }
+                    FieldValueType::Bytes(_) => Box::new(BinaryBuilder::new()),
+                    FieldValueType::Timestamp(_) => Box::new(TimestampNanosecondBuilder::new()),
+                };
+                builders.insert((*key).clone(), builder);
+            }
+            self.additional_fields_builder = Some(builders);
+        }
+    }
+
     fn decode_into_json(
         &mut self,
         builders: &mut [Box<dyn ArrayBuilder>],
@@ -413,6 +718,7 @@ impl ArrowDeserializer {
                         .map_err(|e| SourceError::bad_data(format!("invalid JSON: {:?}", e)))?;
                     self.buffered_count += 1;
                     timestamp_builder.append_value(to_nanos(timestamp) as i64);
+                    Self::pending_dead_letter(&mut self.dead_letters, msg, None);
                 }
 
                 Ok(())
@@ -474,28 +780,48 @@ pub(crate) fn add_additional_fields(
         .schema
         .column_with_name(key)
         .unwrap_or_else(|| panic!("no '{}' column for additional fields", key));
+    append_additional_field(builder[idx].as_mut(), value);
+}
+
+fn append_additional_field(builder: &mut dyn ArrayBuilder, value: &FieldValueType<'_>) {
+    let builder = builder.as_any_mut();
     match value {
         FieldValueType::Int32(i) => {
-            builder[idx]
-                .as_any_mut()
+            builder
                 .downcast_mut::<Int32Builder>()
                 .expect("additional field has incorrect type")
                 .append_value(*i);
         }
         FieldValueType::Int64(i) => {
-            builder[idx]
-                .as_any_mut()
+            builder
                 .downcast_mut::<Int64Builder>()
                 .expect("additional field has incorrect type")
                 .append_value(*i);
         }
         FieldValueType::String(s) => {
-            builder[idx]
-                .as_any_mut()
+            builder
                 .downcast_mut::<StringBuilder>()
                 .expect("additional field has incorrect type")
                 .append_value(s);
         }
+        FieldValueType::OptionalString(s) => {
+            builder
+                .downcast_mut::<StringBuilder>()
+                .expect("additional field has incorrect type")
+                .append_option(*s);
+        }
+        FieldValueType::Bytes(b) => {
+            builder
+                .downcast_mut::<BinaryBuilder>()
+                .expect("additional field has incorrect type")
+                .append_option(*b);
+        }
+        FieldValueType::Timestamp(t) => {
+            builder
+                .downcast_mut::<TimestampNanosecondBuilder>()
+                .expect("additional field has incorrect type")
+                .append_option(t.map(|t| to_nanos(t) as i64));
+        }
     }
 }
 
@@ -509,29 +835,7 @@ pub(crate) fn add_additional_fields_using_builder(
                 .as_mut()
                 .and_then(|b| b.get_mut(*k))
             {
-                match v {
-                    FieldValueType::Int32(i) => {
-                        builder
-                            .as_any_mut()
-                            .downcast_mut::<Int32Builder>()
-                            .expect("additional field has incorrect type")
-                            .append_value(*i);
-                    }
-                    FieldValueType::Int64(i) => {
-                        builder
-                            .as_any_mut()
-                            .downcast_mut::<Int64Builder>()
-                            .expect("additional field has incorrect type")
-                            .append_value(*i);
-                    }
-                    FieldValueType::String(s) => {
-                        builder
-                            .as_any_mut()
-                            .downcast_mut::<StringBuilder>()
-                            .expect("additional field has incorrect type")
-                            .append_value(s);
-                    }
-                }
+                append_additional_field(builder.as_mut(), v);
             }
         }
     }
@@ -564,16 +868,17 @@ pub(crate) fn flush_additional_fields_builders(
 
 #[cfg(test)]
 mod tests {
-    use crate::de::{ArrowDeserializer, FieldValueType, FramingIterator};
+    use crate::de::{ArrowDeserializer, FieldValueType, FramingIterator, TruncatedFrame};
     use arrow::datatypes::Int32Type;
     use arrow_array::builder::{make_builder, ArrayBuilder};
     use arrow_array::cast::AsArray;
     use arrow_array::types::{GenericBinaryType, Int64Type, TimestampNanosecondType};
-    use arrow_array::RecordBatch;
+    use arrow_array::{Array, RecordBatch};
     use arrow_schema::{Schema, TimeUnit};
     use arroyo_rpc::df::ArroyoSchema;
     use arroyo_rpc::formats::{
-        BadData, Format, Framing, FramingMethod, JsonFormat, NewlineDelimitedFraming,
+        BadData, CsvFormat, DelimitedFraming, Endianness, Format, Framing, FramingMethod,
+        JsonFormat, LengthPrefixWidth, LengthPrefixedFraming, NewlineDelimitedFraming,
         RawBytesFormat,
     };
     use arroyo_types::{to_nanos, SourceError};
@@ -590,7 +895,7 @@ mod tests {
         }));
 
         let result: Vec<_> = FramingIterator::new(framing.clone(), "one block".as_bytes())
-            .map(|t| String::from_utf8(t.to_vec()).unwrap())
+            .map(|t| String::from_utf8(t.unwrap().to_vec()).unwrap())
             .collect();
 
         assert_eq!(vec!["one block".to_string()], result);
@@ -599,7 +904,7 @@ mod tests {
             framing.clone(),
             "one block\ntwo block\nthree block".as_bytes(),
         )
-        .map(|t| String::from_utf8(t.to_vec()).unwrap())
+        .map(|t| String::from_utf8(t.unwrap().to_vec()).unwrap())
         .collect();
 
         assert_eq!(
@@ -615,7 +920,7 @@ mod tests {
             framing.clone(),
             "one block\ntwo block\nthree block\n".as_bytes(),
         )
-        .map(|t| String::from_utf8(t.to_vec()).unwrap())
+        .map(|t| String::from_utf8(t.unwrap().to_vec()).unwrap())
         .collect();
 
         assert_eq!(
@@ -638,7 +943,7 @@ mod tests {
 
         let result: Vec<_> =
             FramingIterator::new(framing, "one block\ntwo block\nwhole".as_bytes())
-                .map(|t| String::from_utf8(t.to_vec()).unwrap())
+                .map(|t| String::from_utf8(t.unwrap().to_vec()).unwrap())
                 .collect();
 
         assert_eq!(
@@ -651,6 +956,90 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_delimiter_framing() {
+        let framing = Some(Arc::new(Framing {
+            method: FramingMethod::Delimiter(DelimitedFraming {
+                delimiter: b"||".to_vec(),
+            }),
+        }));
+
+        let result: Vec<_> = FramingIterator::new(framing, b"one|block||two block||||three||")
+            .map(|t| String::from_utf8(t.unwrap().to_vec()).unwrap())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "one|block".to_string(),
+                "two block".to_string(),
+                "".to_string(),
+                "three".to_string(),
+            ],
+            result
+        );
+    }
+
+    #[test]
+    fn test_length_prefixed_framing() {
+        let framing = |width, endianness| {
+            Some(Arc::new(Framing {
+                method: FramingMethod::LengthPrefixed(LengthPrefixedFraming { width, endianness }),
+            }))
+        };
+
+        let result: Vec<_> = FramingIterator::new(
+            framing(LengthPrefixWidth::U32, Endianness::Big),
+            b"\x00\x00\x00\x03one\x00\x00\x00\x00\x00\x00\x00\x05three",
+        )
+        .map(Result::unwrap)
+        .collect();
+        assert_eq!(vec![&b"one"[..], b"", b"three"], result);
+
+        let result: Vec<_> = FramingIterator::new(
+            framing(LengthPrefixWidth::U16, Endianness::Little),
+            b"\x03\x00one\x03\x00two",
+        )
+        .map(Result::unwrap)
+        .collect();
+        assert_eq!(vec![&b"one"[..], b"two"], result);
+
+        let long = vec![b'x'; 300];
+        let mut buf = vec![0xac, 0x02];
+        buf.extend_from_slice(&long);
+        buf.extend_from_slice(b"\x03one");
+        let result: Vec<_> =
+            FramingIterator::new(framing(LengthPrefixWidth::Varint, Endianness::Big), &buf)
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(vec![&long[..], b"one"], result);
+
+        // truncated frames are returned as errors, with the rest of the message
+        let result: Vec<_> = FramingIterator::new(
+            framing(LengthPrefixWidth::U32, Endianness::Big),
+            b"\x00\x00\x00\x03one\x00\x00\x00\x09two",
+        )
+        .collect();
+        assert_eq!(
+            vec![
+                Ok(&b"one"[..]),
+                Err(TruncatedFrame {
+                    data: b"\x00\x00\x00\x09two"
+                })
+            ],
+            result
+        );
+
+        let result: Vec<_> = FramingIterator::new(
+            framing(LengthPrefixWidth::U32, Endianness::Big),
+            b"\x00\x00\x00\x03one\x00\x00",
+        )
+        .collect();
+        assert_eq!(
+            vec![Ok(&b"one"[..]), Err(TruncatedFrame { data: b"\x00\x00" })],
+            result
+        );
+    }
+
     fn setup_deserializer(bad_data: BadData) -> (Vec<Box<dyn ArrayBuilder>>, ArrowDeserializer) {
         let schema = Arc::new(Schema::new(vec![
             arrow_schema::Field::new("x", arrow_schema::DataType::Int64, true),
@@ -726,6 +1115,125 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_bad_data_dlq() {
+        let (mut arrays, mut deserializer) = setup_deserializer(BadData::Dlq {});
+
+        let now = SystemTime::now();
+
+        for msg in [
+            json!({ "x": 5 }),
+            json!({ "x": "hello" }),
+            json!({ "x": 7 }),
+        ] {
+            assert_eq!(
+                deserializer
+                    .deserialize_slice(&mut arrays[..], msg.to_string().as_bytes(), now, None)
+                    .await,
+                vec![]
+            );
+        }
+
+        assert!(deserializer.flush_dead_letters().is_none());
+
+        let batch = deserializer.flush_buffer().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(deserializer.take_invalid_rows(), 1);
+        assert_eq!(deserializer.take_invalid_rows(), 0);
+
+        let dead_letters = deserializer.flush_dead_letters().unwrap();
+        assert_eq!(dead_letters.num_rows(), 1);
+        assert_eq!(
+            dead_letters.column(0).as_binary::<i32>().value(0),
+            json!({ "x": "hello" }).to_string().as_bytes()
+        );
+        assert_eq!(
+            dead_letters.column(1).as_string::<i32>().value(0),
+            "record does not match the table schema"
+        );
+        assert!(dead_letters.column(2).is_null(0));
+        assert_eq!(
+            dead_letters
+                .column(3)
+                .as_primitive::<TimestampNanosecondType>()
+                .value(0),
+            to_nanos(now) as i64
+        );
+
+        assert!(deserializer.flush_dead_letters().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_include_position() {
+        let (mut arrays, mut deserializer) = setup_deserializer(BadData::Dlq {});
+
+        let topic = "orders".to_string();
+        let position = [
+            ("topic", FieldValueType::String(&topic)),
+            ("partition", FieldValueType::Int32(3)),
+            ("offset", FieldValueType::Int64(42)),
+        ];
+
+        for msg in [json!({ "x": 5 }), json!({ "x": "hello" })] {
+            deserializer
+                .deserialize_slice_at(
+                    &mut arrays[..],
+                    msg.to_string().as_bytes(),
+                    SystemTime::now(),
+                    None,
+                    &position,
+                )
+                .await;
+        }
+        deserializer.flush_buffer().unwrap().unwrap();
+
+        let dead_letters = deserializer.flush_dead_letters().unwrap();
+        assert_eq!(dead_letters.num_rows(), 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(
+                dead_letters.column(2).as_string::<i32>().value(0)
+            )
+            .unwrap(),
+            json!({ "topic": "orders", "partition": 3, "offset": 42 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_truncated_frames_are_bad_data() {
+        let (mut arrays, mut deserializer) = setup_deserializer(BadData::Dlq {});
+        deserializer.framing = Some(Arc::new(Framing {
+            method: FramingMethod::LengthPrefixed(LengthPrefixedFraming {
+                width: LengthPrefixWidth::U16,
+                endianness: Endianness::Big,
+            }),
+        }));
+
+        let errors = deserializer
+            .deserialize_slice(
+                &mut arrays[..],
+                b"\x00\x07{\"x\":5}\x00\x07{\"x\"",
+                SystemTime::now(),
+                None,
+            )
+            .await;
+        assert_eq!(
+            errors,
+            vec![SourceError::bad_data(
+                "length-prefixed frame is truncated; the message ends 6 bytes into it"
+            )]
+        );
+
+        let batch = deserializer.flush_buffer().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 1);
+
+        let dead_letters = deserializer.flush_dead_letters().unwrap();
+        assert_eq!(dead_letters.num_rows(), 1);
+        assert_eq!(
+            dead_letters.column(0).as_binary::<i32>().value(0),
+            b"\x00\x07{\"x\""
+        );
+    }
+
     #[tokio::test]
     async fn test_bad_data_fail() {
         let (mut arrays, mut deserializer) = setup_deserializer(BadData::Fail {});
@@ -808,6 +1316,115 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_csv() {
+        let schema = Arc::new(Schema::new(vec![
+            arrow_schema::Field::new("id", arrow_schema::DataType::Int64, true),
+            arrow_schema::Field::new("name", arrow_schema::DataType::Utf8, true),
+            arrow_schema::Field::new("active", arrow_schema::DataType::Boolean, true),
+            arrow_schema::Field::new(
+                "_timestamp",
+                arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]));
+
+        let mut arrays: Vec<_> = schema
+            .fields
+            .iter()
+            .map(|f| make_builder(f.data_type(), 16))
+            .collect();
+
+        let mut deserializer = ArrowDeserializer::new(
+            Format::Csv(CsvFormat {
+                header: true,
+                ..Default::default()
+            }),
+            ArroyoSchema::from_schema_unkeyed(schema).unwrap(),
+            Some(Framing {
+                method: FramingMethod::Newline(NewlineDelimitedFraming {
+                    max_line_length: None,
+                }),
+            }),
+            BadData::Fail {},
+        );
+
+        let errors = deserializer
+            .deserialize_slice(
+                &mut arrays[..],
+                b"id,name,active\n1,\"hello, \"\"world\"\"\",true\n2,,false\n3,missing",
+                SystemTime::now(),
+                None,
+            )
+            .await;
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].details().contains("expected 3 columns"));
+
+        let batch = deserializer.flush_buffer().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.columns()[0].as_primitive::<Int64Type>().value(1), 2);
+        assert_eq!(
+            batch.columns()[1].as_string::<i32>().value(0),
+            "hello, \"world\""
+        );
+        assert!(batch.columns()[1].is_null(1));
+        assert!(batch.columns()[2].as_boolean().value(0));
+        assert!(!batch.columns()[2].as_boolean().value(1));
+    }
+
+    #[tokio::test]
+    async fn test_csv_header_is_first_line_of_buffer() {
+        let schema = Arc::new(Schema::new(vec![
+            arrow_schema::Field::new("name", arrow_schema::DataType::Utf8, true),
+            arrow_schema::Field::new("city", arrow_schema::DataType::Utf8, true),
+            arrow_schema::Field::new(
+                "_timestamp",
+                arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]));
+
+        let mut arrays: Vec<_> = schema
+            .fields
+            .iter()
+            .map(|f| make_builder(f.data_type(), 16))
+            .collect();
+
+        let mut deserializer = ArrowDeserializer::new(
+            Format::Csv(CsvFormat {
+                header: true,
+                ..Default::default()
+            }),
+            ArroyoSchema::from_schema_unkeyed(schema).unwrap(),
+            None,
+            BadData::Fail {},
+        );
+
+        // a row that matches the column names is only skipped at the start of a buffer, and
+        // quoted values may span lines
+        for msg in [
+            &b"name,city\r\nname,city\r\n\"two\nlines\",x\r\n"[..],
+            &b"name,city\nc,y"[..],
+        ] {
+            let errors = deserializer
+                .deserialize_slice(&mut arrays[..], msg, SystemTime::now(), None)
+                .await;
+            assert!(errors.is_empty(), "{:?}", errors);
+        }
+
+        let batch = deserializer.flush_buffer().unwrap().unwrap();
+        let values = |i: usize| {
+            batch.columns()[i]
+                .as_string::<i32>()
+                .iter()
+                .map(|v| v.unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(0), vec!["name", "two\nlines", "c"]);
+        assert_eq!(values(1), vec!["city", "x", "y"]);
+    }
+
     #[tokio::test]
     async fn test_additional_fields_deserialisation() {
         let schema = Arc::new(Schema::new(vec![
@@ -873,4 +1490,82 @@ mod tests {
             to_nanos(time) as i64
         );
     }
+
+    #[tokio::test]
+    async fn test_optional_additional_fields_deserialisation() {
+        let timestamp_type = arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None);
+        let schema = Arc::new(Schema::new(vec![
+            arrow_schema::Field::new("x", arrow_schema::DataType::Int64, true),
+            arrow_schema::Field::new("key", arrow_schema::DataType::Binary, true),
+            arrow_schema::Field::new("headers", arrow_schema::DataType::Utf8, true),
+            arrow_schema::Field::new("ts", timestamp_type.clone(), true),
+            arrow_schema::Field::new("_timestamp", timestamp_type, false),
+        ]));
+
+        let mut arrays: Vec<_> = schema
+            .fields
+            .iter()
+            .map(|f| make_builder(f.data_type(), 16))
+            .collect();
+
+        let arroyo_schema = ArroyoSchema::from_schema_unkeyed(schema.clone()).unwrap();
+
+        let mut deserializer = ArrowDeserializer::new(
+            Format::Json(JsonFormat {
+                confluent_schema_registry: false,
+                schema_id: None,
+                include_schema: false,
+                debezium: false,
+                unstructured: false,
+                timestamp_format: Default::default(),
+            }),
+            arroyo_schema,
+            None,
+            BadData::Fail {},
+        );
+
+        let time = SystemTime::now();
+        let key = "key".to_string();
+        let headers = "headers".to_string();
+        let ts = "ts".to_string();
+
+        for (i, (k, h, t)) in [
+            (Some(&b"k1"[..]), Some(r#"{"a":"b"}"#), Some(time)),
+            (None, None, None),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let additional_fields = std::collections::HashMap::from([
+                (&key, FieldValueType::Bytes(k)),
+                (&headers, FieldValueType::OptionalString(h)),
+                (&ts, FieldValueType::Timestamp(t)),
+            ]);
+
+            let result = deserializer
+                .deserialize_slice(
+                    &mut arrays,
+                    json!({ "x": i }).to_string().as_bytes(),
+                    time,
+                    Some(additional_fields),
+                )
+                .await;
+            assert!(result.is_empty());
+        }
+
+        let batch = deserializer.flush_buffer().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+
+        let keys = batch.columns()[1].as_bytes::<GenericBinaryType<i32>>();
+        assert_eq!(keys.value(0), b"k1");
+        assert!(keys.is_null(1));
+
+        let headers = batch.columns()[2].as_string::<i32>();
+        assert_eq!(headers.value(0), r#"{"a":"b"}"#);
+        assert!(headers.is_null(1));
+
+        let timestamps = batch.columns()[3].as_primitive::<TimestampNanosecondType>();
+        assert_eq!(timestamps.value(0), to_nanos(time) as i64);
+        assert!(timestamps.is_null(1));
+    }
 }
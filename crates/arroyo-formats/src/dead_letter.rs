@@ -0,0 +1,134 @@
+use arrow_array::builder::{BinaryBuilder, StringBuilder, TimestampNanosecondBuilder};
+use arrow_array::{Array, BooleanArray, RecordBatch, TimestampNanosecondArray};
+use arroyo_rpc::dead_letter_schema;
+use arroyo_types::to_nanos;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::de::FieldValueType;
+
+/// Collects records that failed to deserialize for a source configured with
+/// `bad_data = 'dlq'`, producing batches with the schema from
+/// [`arroyo_rpc::dead_letter_schema`].
+///
+/// Most formats are decoded through the arrow-json decoder, which only reports which rows
+/// did not match the schema when it is flushed. To be able to send the raw bytes of those rows
+/// to the dead-letter queue, the raw bytes and metadata of every buffered row are held in
+/// `pending` until the next flush.
+#[derive(Default)]
+pub struct DeadLetterBuffer {
+    raw: BinaryBuilder,
+    error: StringBuilder,
+    metadata: StringBuilder,
+    timestamp: TimestampNanosecondBuilder,
+    pending: Vec<(Vec<u8>, Option<String>)>,
+}
+
+impl DeadLetterBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.error.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(
+        &mut self,
+        raw: Option<&[u8]>,
+        error: &str,
+        metadata: Option<String>,
+        timestamp: SystemTime,
+    ) {
+        self.raw.append_option(raw);
+        self.error.append_value(error);
+        self.metadata.append_option(metadata);
+        self.timestamp.append_value(to_nanos(timestamp) as i64);
+    }
+
+    /// Records a row that has been passed to the json decoder, in case it turns out to be
+    /// invalid when the decoder is flushed
+    pub fn push_pending(&mut self, raw: &[u8], metadata: Option<String>) {
+        self.pending.push((raw.to_vec(), metadata));
+    }
+
+    /// Moves the pending rows for which `valid` is false into the buffer. If the number of
+    /// pending rows doesn't line up with the mask (which can happen if a single message
+    /// produced several rows) the raw bytes and metadata are left null.
+    pub fn resolve_pending(
+        &mut self,
+        valid: &BooleanArray,
+        timestamps: &TimestampNanosecondArray,
+        error: &str,
+    ) {
+        let pending = std::mem::take(&mut self.pending);
+        let aligned = pending.len() == valid.len();
+
+        for (i, (is_valid, timestamp)) in valid.iter().zip(timestamps.iter()).enumerate() {
+            if is_valid.unwrap_or(false) {
+                continue;
+            }
+
+            let (raw, metadata) = if aligned {
+                let (raw, metadata) = &pending[i];
+                (Some(raw.as_slice()), metadata.clone())
+            } else {
+                (None, None)
+            };
+
+            self.raw.append_option(raw);
+            self.error.append_value(error);
+            self.metadata.append_option(metadata);
+            self.timestamp.append_option(timestamp);
+        }
+    }
+
+    pub fn flush(&mut self) -> Option<RecordBatch> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let schema = dead_letter_schema();
+        let mut columns: Vec<Arc<dyn Array>> = vec![
+            Arc::new(self.raw.finish()),
+            Arc::new(self.error.finish()),
+            Arc::new(self.metadata.finish()),
+        ];
+        columns.insert(schema.timestamp_index, Arc::new(self.timestamp.finish()));
+
+        Some(RecordBatch::try_new(schema.schema.clone(), columns).unwrap())
+    }
+}
+
+/// Encodes the position of a message in its source (like its topic, partition and offset) and the
+/// additional fields declared for it as a JSON object, or returns None if there are neither
+pub fn metadata_to_json(
+    position: &[(&str, FieldValueType<'_>)],
+    fields: Option<&HashMap<&String, FieldValueType<'_>>>,
+) -> Option<String> {
+    let map: serde_json::Map<String, Value> = fields
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| (k.as_str(), v))
+        .chain(position.iter().map(|(k, v)| (*k, v)))
+        .map(|(k, v)| {
+            let v = match v {
+                FieldValueType::Int64(i) => Value::from(*i),
+                FieldValueType::Int32(i) => Value::from(*i),
+                FieldValueType::String(s) => Value::from(s.as_str()),
+                FieldValueType::OptionalString(s) => Value::from(*s),
+                FieldValueType::Bytes(b) => Value::from(b.map(String::from_utf8_lossy)),
+                FieldValueType::Timestamp(t) => Value::from(t.map(|t| to_nanos(t) as i64)),
+            };
+            (k.to_string(), v)
+        })
+        .collect();
+
+    (!map.is_empty()).then(|| Value::Object(map).to_string())
+}
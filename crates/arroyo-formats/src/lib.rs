@@ -3,6 +3,8 @@ use serde_json::{json, Value};
 use std::time::Instant;
 
 pub mod avro;
+pub mod csv;
+pub mod dead_letter;
 pub mod json;
 
 pub mod de;
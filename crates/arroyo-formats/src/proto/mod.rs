@@ -1,4 +1,5 @@
 pub mod de;
 pub mod schema;
+pub mod ser;
 #[cfg(test)]
 mod test;
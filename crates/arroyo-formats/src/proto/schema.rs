@@ -104,6 +104,50 @@ async fn write_files(base_path: &Path, files: &HashMap<String, String>) -> anyho
     Ok(())
 }
 
+/// Compiles a .proto file on the local filesystem into an encoded `FileDescriptorSet`. Imports
+/// are resolved relative to the directory containing the file.
+pub fn proto_file_to_descriptor(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| anyhow!("could not read proto file '{}': {}", path.display(), e))?;
+
+    let include_dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("invalid proto file path '{}'", path.display()))?;
+
+    let dir = temp_dir().join(Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir)?;
+    let output_file = dir.join("schema.bin");
+
+    let output = std::process::Command::new(prost_build::protoc_from_env())
+        .arg(format!("--descriptor_set_out={}", output_file.display()))
+        .arg("--include_imports")
+        .arg("-I")
+        .arg(include_dir)
+        .arg(&path)
+        .output()
+        .map_err(|e| anyhow!("unable to compile protobuf; is protoc installed? {e}"));
+
+    let bin = output.and_then(|output| {
+        if !output.status.success() {
+            bail!(
+                "failed to compile proto: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        std::fs::read(&output_file).context("failed to read protoc output")
+    });
+
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        warn!(
+            "Could not clean up temp directory '{:?}' from protobuf compilation: {}",
+            dir, e
+        );
+    }
+
+    bin
+}
+
 #[allow(async_fn_in_trait)]
 pub trait ProtoSchemaResolver {
     async fn resolve(&self, path: &str) -> anyhow::Result<Option<String>>;
@@ -0,0 +1,384 @@
+use anyhow::{anyhow, bail};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, TimestampMicrosecondType,
+    TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, ArrayRef, RecordBatch, StructArray};
+use arrow_schema::{DataType, TimeUnit};
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use integer_encoding::VarInt;
+use prost::Message;
+use prost_reflect::{DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor, Value};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+/// Encodes each row of the batch as a protobuf message of the given type. Columns are matched
+/// to message fields by name; null values are left unset.
+pub(crate) fn serialize_proto(
+    descriptor: &MessageDescriptor,
+    batch: &RecordBatch,
+) -> anyhow::Result<Vec<Vec<u8>>> {
+    let array = StructArray::from(batch.clone());
+
+    (0..array.len())
+        .map(|row| Ok(struct_to_message(descriptor, &array, row)?.encode_to_vec()))
+        .collect()
+}
+
+// see: https://docs.confluent.io/platform/current/schema-registry/fundamentals/serdes-develop/index.html#wire-format
+pub(crate) fn confluent_header(descriptor: &MessageDescriptor, schema_id: u32) -> Vec<u8> {
+    let mut buf = vec![0];
+    buf.extend(schema_id.to_be_bytes());
+
+    let indexes = message_indexes(descriptor);
+    if indexes == [0] {
+        // the common case of the first message in the file is encoded as a single 0
+        buf.push(0);
+    } else {
+        buf.extend((indexes.len() as i32).encode_var_vec());
+        for index in indexes {
+            buf.extend((index as i32).encode_var_vec());
+        }
+    }
+
+    buf
+}
+
+/// The path to the message within its file, as the index of each message in its parent
+fn message_indexes(descriptor: &MessageDescriptor) -> Vec<usize> {
+    let mut indexes = vec![];
+    let mut current = descriptor.clone();
+
+    loop {
+        match current.parent_message() {
+            Some(parent) => {
+                indexes.push(
+                    parent
+                        .child_messages()
+                        .position(|m| m == current)
+                        .expect("message not found in parent"),
+                );
+                current = parent;
+            }
+            None => {
+                indexes.push(
+                    current
+                        .parent_file()
+                        .messages()
+                        .position(|m| m == current)
+                        .expect("message not found in file"),
+                );
+                break;
+            }
+        }
+    }
+
+    indexes.reverse();
+    indexes
+}
+
+fn struct_to_message(
+    descriptor: &MessageDescriptor,
+    array: &StructArray,
+    row: usize,
+) -> anyhow::Result<DynamicMessage> {
+    let mut message = DynamicMessage::new(descriptor.clone());
+
+    for (column, field) in array.columns().iter().zip(array.fields()) {
+        let proto_field = descriptor.get_field_by_name(field.name()).ok_or_else(|| {
+            anyhow!(
+                "field '{}' does not exist in protobuf message '{}'",
+                field.name(),
+                descriptor.full_name()
+            )
+        })?;
+
+        if column.is_null(row) {
+            continue;
+        }
+
+        message.set_field(&proto_field, field_value(&proto_field, column, row)?);
+    }
+
+    Ok(message)
+}
+
+fn field_value(field: &FieldDescriptor, column: &ArrayRef, row: usize) -> anyhow::Result<Value> {
+    if field.is_map() {
+        // maps are represented as JSON in arrow, see `protobuf_to_arrow`
+        let json: JsonValue = serde_json::from_str(string_value(field, column, row)?)
+            .map_err(|e| anyhow!("invalid JSON for map field '{}': {}", field.name(), e))?;
+        return json_to_value(field, &json);
+    }
+
+    if field.is_list() {
+        let list = column
+            .as_list_opt::<i32>()
+            .ok_or_else(|| type_error(field, column))?
+            .value(row);
+
+        return Ok(Value::List(
+            (0..list.len())
+                .filter(|i| !list.is_null(*i))
+                .map(|i| scalar_value(field, &list, i))
+                .collect::<anyhow::Result<_>>()?,
+        ));
+    }
+
+    scalar_value(field, column, row)
+}
+
+fn scalar_value(field: &FieldDescriptor, column: &ArrayRef, row: usize) -> anyhow::Result<Value> {
+    Ok(match field.kind() {
+        Kind::Bool => Value::Bool(
+            column
+                .as_boolean_opt()
+                .ok_or_else(|| type_error(field, column))?
+                .value(row),
+        ),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            Value::I32(integer_value(field, column, row)?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            Value::I64(integer_value(field, column, row)?)
+        }
+        Kind::Uint32 | Kind::Fixed32 => Value::U32(integer_value(field, column, row)?),
+        Kind::Uint64 | Kind::Fixed64 => Value::U64(integer_value(field, column, row)?),
+        Kind::Float => Value::F32(float_value(field, column, row)? as f32),
+        Kind::Double => Value::F64(float_value(field, column, row)?),
+        Kind::String => Value::String(string_value(field, column, row)?.to_string()),
+        Kind::Bytes => Value::Bytes(match column.data_type() {
+            DataType::Binary => column.as_binary::<i32>().value(row).to_vec().into(),
+            DataType::LargeBinary => column.as_binary::<i64>().value(row).to_vec().into(),
+            // bytes are deserialized as base64-encoded strings, so we expect the same here
+            _ => BASE64_STANDARD
+                .decode(string_value(field, column, row)?)
+                .map_err(|e| anyhow!("invalid base64 for bytes field '{}': {}", field.name(), e))?
+                .into(),
+        }),
+        Kind::Enum(enum_descriptor) => match column.data_type() {
+            DataType::Utf8 | DataType::LargeUtf8 => {
+                let name = string_value(field, column, row)?;
+                Value::EnumNumber(
+                    enum_descriptor
+                        .get_value_by_name(name)
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "'{}' is not a valid value for enum '{}'",
+                                name,
+                                enum_descriptor.full_name()
+                            )
+                        })?
+                        .number(),
+                )
+            }
+            _ => Value::EnumNumber(integer_value(field, column, row)?),
+        },
+        Kind::Message(message) => match column.data_type() {
+            DataType::Struct(_) => {
+                Value::Message(struct_to_message(&message, column.as_struct(), row)?)
+            }
+            DataType::Timestamp(unit, _) if message.full_name() == "google.protobuf.Timestamp" => {
+                let nanos = timestamp_nanos(unit, column, row);
+                let mut timestamp = DynamicMessage::new(message);
+                timestamp.set_field_by_name("seconds", Value::I64(nanos.div_euclid(1_000_000_000)));
+                timestamp
+                    .set_field_by_name("nanos", Value::I32(nanos.rem_euclid(1_000_000_000) as i32));
+                Value::Message(timestamp)
+            }
+            _ => return Err(type_error(field, column)),
+        },
+    })
+}
+
+fn integer_value<T: TryFrom<i128>>(
+    field: &FieldDescriptor,
+    column: &ArrayRef,
+    row: usize,
+) -> anyhow::Result<T> {
+    let value = match column.data_type() {
+        DataType::Int8 => column.as_primitive::<Int8Type>().value(row) as i128,
+        DataType::Int16 => column.as_primitive::<Int16Type>().value(row) as i128,
+        DataType::Int32 => column.as_primitive::<Int32Type>().value(row) as i128,
+        DataType::Int64 => column.as_primitive::<Int64Type>().value(row) as i128,
+        DataType::UInt8 => column.as_primitive::<UInt8Type>().value(row) as i128,
+        DataType::UInt16 => column.as_primitive::<UInt16Type>().value(row) as i128,
+        DataType::UInt32 => column.as_primitive::<UInt32Type>().value(row) as i128,
+        DataType::UInt64 => column.as_primitive::<UInt64Type>().value(row) as i128,
+        _ => return Err(type_error(field, column)),
+    };
+
+    T::try_from(value).map_err(|_| {
+        anyhow!(
+            "value {} is out of range for protobuf field '{}'",
+            value,
+            field.name()
+        )
+    })
+}
+
+fn float_value(field: &FieldDescriptor, column: &ArrayRef, row: usize) -> anyhow::Result<f64> {
+    Ok(match column.data_type() {
+        DataType::Float32 => column.as_primitive::<Float32Type>().value(row) as f64,
+        DataType::Float64 => column.as_primitive::<Float64Type>().value(row),
+        _ => integer_value::<i64>(field, column, row)? as f64,
+    })
+}
+
+fn string_value<'a>(
+    field: &FieldDescriptor,
+    column: &'a ArrayRef,
+    row: usize,
+) -> anyhow::Result<&'a str> {
+    match column.data_type() {
+        DataType::Utf8 => Ok(column.as_string::<i32>().value(row)),
+        DataType::LargeUtf8 => Ok(column.as_string::<i64>().value(row)),
+        _ => Err(type_error(field, column)),
+    }
+}
+
+fn timestamp_nanos(unit: &TimeUnit, column: &ArrayRef, row: usize) -> i64 {
+    match unit {
+        TimeUnit::Second => column.as_primitive::<TimestampSecondType>().value(row) * 1_000_000_000,
+        TimeUnit::Millisecond => {
+            column.as_primitive::<TimestampMillisecondType>().value(row) * 1_000_000
+        }
+        TimeUnit::Microsecond => {
+            column.as_primitive::<TimestampMicrosecondType>().value(row) * 1_000
+        }
+        TimeUnit::Nanosecond => column.as_primitive::<TimestampNanosecondType>().value(row),
+    }
+}
+
+fn type_error(field: &FieldDescriptor, column: &ArrayRef) -> anyhow::Error {
+    anyhow!(
+        "cannot write column of type {} to protobuf field '{}' of type {:?}",
+        column.data_type(),
+        field.full_name(),
+        field.kind()
+    )
+}
+
+fn json_to_value(field: &FieldDescriptor, json: &JsonValue) -> anyhow::Result<Value> {
+    if field.is_map() {
+        let (JsonValue::Object(obj), Kind::Message(entry)) = (json, field.kind()) else {
+            bail!("expected a JSON object for map field '{}'", field.name());
+        };
+
+        let key_field = entry.map_entry_key_field();
+        let value_field = entry.map_entry_value_field();
+
+        let mut map = HashMap::new();
+        for (k, v) in obj {
+            map.insert(map_key(&key_field, k)?, json_to_value(&value_field, v)?);
+        }
+
+        return Ok(Value::Map(map));
+    }
+
+    if field.is_list() {
+        let JsonValue::Array(values) = json else {
+            bail!(
+                "expected a JSON array for repeated field '{}'",
+                field.name()
+            );
+        };
+
+        return Ok(Value::List(
+            values
+                .iter()
+                .filter(|v| !v.is_null())
+                .map(|v| json_scalar_to_value(field, v))
+                .collect::<anyhow::Result<_>>()?,
+        ));
+    }
+
+    json_scalar_to_value(field, json)
+}
+
+fn json_scalar_to_value(field: &FieldDescriptor, json: &JsonValue) -> anyhow::Result<Value> {
+    let invalid = || {
+        anyhow!(
+            "invalid value {} for protobuf field '{}'",
+            json,
+            field.full_name()
+        )
+    };
+
+    let int = || {
+        json.as_i64()
+            .map(i128::from)
+            .or_else(|| json.as_u64().map(i128::from))
+            .ok_or_else(invalid)
+    };
+
+    Ok(match (field.kind(), json) {
+        (Kind::Bool, JsonValue::Bool(b)) => Value::Bool(*b),
+        (Kind::Int32 | Kind::Sint32 | Kind::Sfixed32, _) => {
+            Value::I32(int()?.try_into().map_err(|_| invalid())?)
+        }
+        (Kind::Int64 | Kind::Sint64 | Kind::Sfixed64, _) => {
+            Value::I64(int()?.try_into().map_err(|_| invalid())?)
+        }
+        (Kind::Uint32 | Kind::Fixed32, _) => Value::U32(int()?.try_into().map_err(|_| invalid())?),
+        (Kind::Uint64 | Kind::Fixed64, _) => Value::U64(int()?.try_into().map_err(|_| invalid())?),
+        (Kind::Float, JsonValue::Number(n)) => Value::F32(n.as_f64().ok_or_else(invalid)? as f32),
+        (Kind::Double, JsonValue::Number(n)) => Value::F64(n.as_f64().ok_or_else(invalid)?),
+        (Kind::String, JsonValue::String(s)) => Value::String(s.clone()),
+        (Kind::Bytes, JsonValue::String(s)) => {
+            Value::Bytes(BASE64_STANDARD.decode(s).map_err(|_| invalid())?.into())
+        }
+        (Kind::Enum(e), JsonValue::String(s)) => {
+            Value::EnumNumber(e.get_value_by_name(s).ok_or_else(invalid)?.number())
+        }
+        (Kind::Enum(_), JsonValue::Number(_)) => {
+            Value::EnumNumber(int()?.try_into().map_err(|_| invalid())?)
+        }
+        (Kind::Message(descriptor), JsonValue::Object(obj)) => {
+            let mut message = DynamicMessage::new(descriptor.clone());
+            for (k, v) in obj {
+                if v.is_null() {
+                    continue;
+                }
+
+                let f = descriptor.get_field_by_name(k).ok_or_else(|| {
+                    anyhow!(
+                        "field '{}' does not exist in protobuf message '{}'",
+                        k,
+                        descriptor.full_name()
+                    )
+                })?;
+                message.set_field(&f, json_to_value(&f, v)?);
+            }
+            Value::Message(message)
+        }
+        _ => return Err(invalid()),
+    })
+}
+
+fn map_key(field: &FieldDescriptor, key: &str) -> anyhow::Result<MapKey> {
+    let invalid = || {
+        anyhow!(
+            "invalid map key '{}' for field '{}'",
+            key,
+            field.full_name()
+        )
+    };
+
+    Ok(match field.kind() {
+        Kind::Bool => MapKey::Bool(key.parse().map_err(|_| invalid())?),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            MapKey::I32(key.parse().map_err(|_| invalid())?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            MapKey::I64(key.parse().map_err(|_| invalid())?)
+        }
+        Kind::Uint32 | Kind::Fixed32 => MapKey::U32(key.parse().map_err(|_| invalid())?),
+        Kind::Uint64 | Kind::Fixed64 => MapKey::U64(key.parse().map_err(|_| invalid())?),
+        Kind::String => MapKey::String(key.to_string()),
+        _ => return Err(invalid()),
+    })
+}
@@ -1,11 +1,16 @@
+use crate::proto::de::deserialize_proto;
 use crate::proto::schema::{
-    protobuf_to_arrow, schema_file_to_descriptor, schema_file_to_descriptor_with_resolver,
-    ProtoSchemaResolver,
+    get_pool, protobuf_to_arrow, schema_file_to_descriptor,
+    schema_file_to_descriptor_with_resolver, ProtoSchemaResolver,
 };
+use crate::ser::ArrowSerializer;
 use arrow_schema::{DataType, Field, Schema};
+use arroyo_rpc::formats::{Format, ProtobufFormat};
 use arroyo_types::ArroyoExtensionType;
 use prost_reflect::DescriptorPool;
+use serde_json::json;
 use std::collections::HashMap;
+use std::io::Cursor;
 use std::sync::Arc;
 
 #[tokio::test]
@@ -207,3 +212,181 @@ async fn test_imports() {
     .await
     .unwrap();
 }
+
+async fn proto_format(
+    proto: &str,
+    message_name: &str,
+    confluent_schema_registry: bool,
+) -> ProtobufFormat {
+    let compiled_schema = schema_file_to_descriptor(proto, &HashMap::default())
+        .await
+        .unwrap();
+
+    ProtobufFormat {
+        into_unstructured_json: false,
+        message_name: Some(message_name.to_string()),
+        compiled_schema: Some(compiled_schema),
+        confluent_schema_registry,
+        schema_id: confluent_schema_registry.then_some(7),
+    }
+}
+
+/// Reads the JSON record into arrow using the schema derived from the proto, serializes it as
+/// protobuf, and deserializes it back into JSON
+fn round_trip(format: ProtobufFormat, record: serde_json::Value) -> (Vec<u8>, serde_json::Value) {
+    let mut pool = get_pool(format.compiled_schema.as_ref().unwrap()).unwrap();
+    let descriptor = pool
+        .get_message_by_name(format.message_name.as_ref().unwrap())
+        .unwrap();
+    let schema = Arc::new(protobuf_to_arrow(&descriptor).unwrap());
+
+    let batch = arrow_json::ReaderBuilder::new(schema)
+        .build(Cursor::new(record.to_string()))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+
+    let mut serializer = ArrowSerializer::new(Format::Protobuf(format.clone()));
+    let mut iter = serializer.serialize(&batch);
+    let bytes = iter.next().unwrap();
+    assert_eq!(iter.next(), None);
+
+    let json = deserialize_proto(&mut pool, &format, &bytes).unwrap();
+    (bytes, json)
+}
+
+#[tokio::test]
+async fn test_serialize_basic_types() {
+    let format = proto_format(
+        include_str!("protos/basic_types.proto"),
+        "TestBasicTypes",
+        false,
+    )
+    .await;
+
+    let record = json!({
+        "bool_field": true,
+        "int32_field": -5,
+        "int64_field": 1234567890123_i64,
+        "uint32_field": 42,
+        "uint64_field": 18446744073709551615_u64,
+        "float_field": 1.5,
+        "double_field": -2.25,
+    });
+
+    assert_eq!(round_trip(format, record.clone()).1, record);
+}
+
+#[tokio::test]
+async fn test_serialize_string_and_bytes() {
+    let format = proto_format(
+        include_str!("protos/string_and_bytes.proto"),
+        "TestStringAndBytes",
+        false,
+    )
+    .await;
+
+    let record = json!({
+        "string_field": "hello world",
+        "bytes_field": "AAECAwQ=",
+    });
+
+    assert_eq!(round_trip(format, record.clone()).1, record);
+}
+
+#[tokio::test]
+async fn test_serialize_nested_and_repeated() {
+    let format = proto_format(
+        include_str!("protos/nested_message.proto"),
+        "TestNestedMessage",
+        false,
+    )
+    .await;
+
+    let record = json!({
+        "nested_field": {"inner_field": 3},
+        "double_nested_field": {"inner_nested": {"inner_field": 4}},
+    });
+
+    assert_eq!(round_trip(format, record.clone()).1, record);
+
+    let format = proto_format(
+        include_str!("protos/repeated_fields.proto"),
+        "TestRepeatedFields",
+        false,
+    )
+    .await;
+
+    let record = json!({
+        "repeated_int": [1, 2, 3],
+        "repeated_string": ["a", "b"],
+    });
+
+    assert_eq!(round_trip(format, record.clone()).1, record);
+}
+
+#[tokio::test]
+async fn test_serialize_enums_and_maps() {
+    let format = proto_format(
+        include_str!("protos/enum_fields.proto"),
+        "TestEnumFields",
+        false,
+    )
+    .await;
+
+    let record = json!({"enum_field": "VALUE2"});
+    assert_eq!(round_trip(format, record.clone()).1, record);
+
+    let format = proto_format(
+        include_str!("protos/map_fields.proto"),
+        "TestMapFields",
+        false,
+    )
+    .await;
+
+    // maps are represented as JSON strings in arrow
+    let record = json!({
+        "int_to_string_map": r#"{"1": "one", "2": "two"}"#,
+        "string_to_message_map": r#"{"a": {"inner_field": 5}}"#,
+    });
+
+    assert_eq!(
+        round_trip(format, record).1,
+        json!({
+            "int_to_string_map": {"1": "one", "2": "two"},
+            "string_to_message_map": {"a": {"inner_field": 5}},
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_serialize_confluent() {
+    let format = proto_format(
+        include_str!("protos/basic_types.proto"),
+        "TestBasicTypes",
+        true,
+    )
+    .await;
+
+    let record = json!({"int32_field": 10});
+    let (bytes, json) = round_trip(format, record.clone());
+
+    // magic byte, schema id, and a single 0 for the first message in the file
+    assert_eq!(&bytes[..6], &[0, 0, 0, 0, 7, 0]);
+    assert_eq!(json, record);
+
+    let format = proto_format(
+        include_str!("protos/nested_message.proto"),
+        "TestNestedMessage.DoubleNestedMessage",
+        true,
+    )
+    .await;
+
+    let record = json!({"inner_nested": {"inner_field": 1}});
+    let (bytes, json) = round_trip(format, record.clone());
+
+    // message indexes [0, 1], encoded as zigzag varints with a length prefix
+    assert_eq!(&bytes[..8], &[0, 0, 0, 0, 7, 4, 0, 2]);
+    assert_eq!(json, record);
+}
@@ -1,34 +1,91 @@
 use crate::avro::schema;
-use crate::{avro, json};
+use crate::proto::schema::get_pool;
+use crate::{avro, csv, json, proto};
 use arrow_array::cast::AsArray;
 use arrow_array::types::GenericBinaryType;
 use arrow_array::RecordBatch;
 use arrow_json::writer::record_batch_to_vec;
 use arrow_schema::{DataType, Field};
 use arroyo_rpc::formats::{
-    AvroFormat, Format, JsonFormat, RawBytesFormat, RawStringFormat, TimestampFormat,
+    AvroFormat, CsvFormat, Endianness, Format, Framing, FramingMethod, JsonFormat,
+    LengthPrefixWidth, LengthPrefixedFraming, ProtobufFormat, RawBytesFormat, RawStringFormat,
+    TimestampFormat,
 };
 use arroyo_rpc::TIMESTAMP_FIELD;
+use prost_reflect::MessageDescriptor;
 use serde_json::Value;
 use std::sync::Arc;
 
 pub struct ArrowSerializer {
     kafka_schema: Option<Value>,
     avro_schema: Option<Arc<apache_avro::schema::Schema>>,
+    proto_descriptor: Option<MessageDescriptor>,
+    header_pending: bool,
     format: Format,
+    framing: Option<Arc<Framing>>,
     projection: Vec<usize>,
 }
 
 impl ArrowSerializer {
     pub fn new(format: Format) -> Self {
+        Self::with_framing(format, None)
+    }
+
+    /// Creates a serializer that frames each record, so that it can be read back by a source
+    /// with the same framing. Newline framing leaves records unchanged, as sinks that write
+    /// multiple records to a stream already separate them by newlines.
+    pub fn with_framing(format: Format, framing: Option<Framing>) -> Self {
+        let proto_descriptor =
+            match &format {
+                Format::Protobuf(ProtobufFormat {
+                    compiled_schema,
+                    message_name,
+                    ..
+                }) => {
+                    let pool = get_pool(
+                        compiled_schema
+                            .as_ref()
+                            .expect("must have compiled schema for protobuf format"),
+                    )
+                    .expect("unable to handle protobuf schema");
+
+                    let message_name = message_name
+                        .as_ref()
+                        .expect("must have message name for protobuf format");
+
+                    Some(pool.get_message_by_name(message_name).unwrap_or_else(|| {
+                        panic!("no message '{}' in protobuf schema", message_name)
+                    }))
+                }
+                _ => None,
+            };
+
         Self {
             kafka_schema: None,
             avro_schema: None,
+            proto_descriptor,
+            header_pending: matches!(format, Format::Csv(CsvFormat { header: true, .. })),
             format,
+            framing: framing.map(Arc::new),
             projection: vec![],
         }
     }
 
+    /// The bytes that separate serialized records when they're written one after another: a
+    /// newline, unless the framing already delimits them
+    pub fn record_separator(&self) -> &'static [u8] {
+        match self.framing.as_ref().map(|framing| &framing.method) {
+            Some(FramingMethod::Delimiter(_) | FramingMethod::LengthPrefixed(_)) => b"",
+            _ => b"\n",
+        }
+    }
+
+    /// Suppresses the header row for formats that write one, for example when appending to a
+    /// file that already has a header
+    pub fn skip_header(&mut self) {
+        self.header_pending = false;
+    }
+
     fn projection(schema: &arrow_schema::Schema) -> Vec<usize> {
         schema
             .fields
@@ -76,15 +133,30 @@ impl ArrowSerializer {
             .project(&self.projection)
             .expect("batch has wrong number of columns");
 
-        match &self.format {
+        let records = match &self.format {
             Format::Json(json) => self.serialize_json(json, &batch),
             Format::Avro(avro) => self.serialize_avro(avro, &batch),
             Format::Parquet(_) => todo!("parquet"),
             Format::RawString(RawStringFormat {}) => self.serialize_raw_string(&batch),
             Format::RawBytes(RawBytesFormat {}) => self.serialize_raw_bytes(&batch),
-            Format::Protobuf(_) => {
-                todo!("protobuf serializer!")
+            Format::Protobuf(proto) => self.serialize_proto(proto, &batch),
+            Format::Csv(format) => {
+                let header = std::mem::take(&mut self.header_pending)
+                    .then(|| csv::ser::header(format, &batch.schema()));
+
+                Box::new(
+                    header
+                        .into_iter()
+                        .chain(csv::ser::serialize(format, &batch)),
+                )
             }
+        };
+
+        match self.framing.clone() {
+            Some(framing) if !matches!(framing.method, FramingMethod::Newline(_)) => {
+                Box::new(records.map(move |record| frame(&framing.method, record)))
+            }
+            _ => records,
         }
     }
 
@@ -189,6 +261,40 @@ impl ArrowSerializer {
         Box::new(values.into_iter())
     }
 
+    fn serialize_proto(
+        &self,
+        format: &ProtobufFormat,
+        batch: &RecordBatch,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
+        let descriptor = self
+            .proto_descriptor
+            .as_ref()
+            .expect("must have protobuf descriptor for protobuf format");
+
+        let header = format.confluent_schema_registry.then(|| {
+            proto::ser::confluent_header(
+                descriptor,
+                format
+                    .schema_id
+                    .expect("must have schema id for confluent schema registry"),
+            )
+        });
+
+        let messages = proto::ser::serialize_proto(descriptor, batch)
+            .unwrap_or_else(|e| panic!("protobuf serialization failed: {:?}", e));
+
+        Box::new(messages.into_iter().map(move |message| {
+            if let Some(header) = &header {
+                let mut buf = Vec::with_capacity(header.len() + message.len());
+                buf.extend(header);
+                buf.extend(message);
+                buf
+            } else {
+                message
+            }
+        }))
+    }
+
     fn serialize_avro(
         &self,
         format: &AvroFormat,
@@ -236,12 +342,73 @@ impl ArrowSerializer {
     }
 }
 
+fn frame(method: &FramingMethod, mut record: Vec<u8>) -> Vec<u8> {
+    match method {
+        FramingMethod::Newline(_) => record,
+        FramingMethod::Delimiter(delimited) => {
+            record.extend_from_slice(&delimited.delimiter);
+            record
+        }
+        FramingMethod::LengthPrefixed(length_prefixed) => {
+            let mut framed = length_prefix(length_prefixed, record.len());
+            framed.extend_from_slice(&record);
+            framed
+        }
+    }
+}
+
+fn length_prefix(framing: &LengthPrefixedFraming, len: usize) -> Vec<u8> {
+    match framing.width {
+        LengthPrefixWidth::U16 => {
+            let len = u16::try_from(len).unwrap_or_else(|_| {
+                panic!(
+                    "record of {} bytes is too large for a u16 length prefix",
+                    len
+                )
+            });
+            match framing.endianness {
+                Endianness::Big => len.to_be_bytes().to_vec(),
+                Endianness::Little => len.to_le_bytes().to_vec(),
+            }
+        }
+        LengthPrefixWidth::U32 => {
+            let len = u32::try_from(len).unwrap_or_else(|_| {
+                panic!(
+                    "record of {} bytes is too large for a u32 length prefix",
+                    len
+                )
+            });
+            match framing.endianness {
+                Endianness::Big => len.to_be_bytes().to_vec(),
+                Endianness::Little => len.to_le_bytes().to_vec(),
+            }
+        }
+        LengthPrefixWidth::Varint => {
+            let mut len = len as u64;
+            let mut buf = vec![];
+            loop {
+                let b = (len & 0x7f) as u8;
+                len >>= 7;
+                if len == 0 {
+                    buf.push(b);
+                    return buf;
+                }
+                buf.push(b | 0x80);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::de::FramingIterator;
     use crate::ser::ArrowSerializer;
     use arrow_array::builder::TimestampNanosecondBuilder;
     use arrow_schema::{Schema, TimeUnit};
-    use arroyo_rpc::formats::{Format, RawBytesFormat, RawStringFormat, TimestampFormat};
+    use arroyo_rpc::formats::{
+        CsvFormat, DelimitedFraming, Endianness, Format, Framing, FramingMethod, LengthPrefixWidth,
+        LengthPrefixedFraming, RawBytesFormat, RawStringFormat, TimestampFormat,
+    };
     use arroyo_types::to_nanos;
     use std::sync::Arc;
     use std::time::{Duration, SystemTime};
@@ -426,4 +593,115 @@ mod tests {
         assert_eq!(iter.next().unwrap(), br#"{"value":null}"#);
         assert_eq!(iter.next().unwrap(), br#"{"value":1712274910045}"#);
     }
+
+    #[test]
+    fn test_csv() {
+        let mut serializer = ArrowSerializer::new(Format::Csv(CsvFormat {
+            header: true,
+            null_value: "NULL".to_string(),
+            timestamp_format: TimestampFormat::UnixMillis,
+            ..Default::default()
+        }));
+
+        let schema = Arc::new(Schema::new(vec![
+            arrow_schema::Field::new("value", arrow_schema::DataType::Utf8, true),
+            arrow_schema::Field::new("number", arrow_schema::DataType::Int32, false),
+            arrow_schema::Field::new(
+                "time",
+                arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+            arrow_schema::Field::new(
+                "_timestamp",
+                arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]));
+
+        let batch = arrow_array::RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(arrow_array::StringArray::from(vec![
+                    Some("a"),
+                    Some("b,\"c\""),
+                    None,
+                ])),
+                Arc::new(arrow_array::Int32Array::from(vec![1, 2, 3])),
+                Arc::new(arrow_array::TimestampNanosecondArray::from(vec![
+                    1612274910045331968,
+                    1612274910046000000,
+                    1612274910047000000,
+                ])),
+                Arc::new(arrow_array::TimestampNanosecondArray::from(vec![0, 0, 0])),
+            ],
+        )
+        .unwrap();
+
+        let mut iter = serializer.serialize(&batch);
+        assert_eq!(iter.next().unwrap(), b"value,number,time");
+        assert_eq!(iter.next().unwrap(), b"a,1,1612274910045");
+        assert_eq!(iter.next().unwrap(), b"\"b,\"\"c\"\"\",2,1612274910046");
+        assert_eq!(iter.next().unwrap(), b"NULL,3,1612274910047");
+        assert_eq!(iter.next(), None);
+
+        // the header is only written once per serializer
+        let mut iter = serializer.serialize(&batch);
+        assert_eq!(iter.next().unwrap(), b"a,1,1612274910045");
+    }
+
+    #[test]
+    fn test_framing_round_trip() {
+        let schema = Arc::new(Schema::new(vec![
+            arrow_schema::Field::new("value", arrow_schema::DataType::Utf8, false),
+            arrow_schema::Field::new(
+                "_timestamp",
+                arrow_schema::DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]));
+
+        let data = vec!["a".to_string(), "".to_string(), "x".repeat(200)];
+        let batch = arrow_array::RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(arrow_array::StringArray::from(data.clone())),
+                Arc::new(arrow_array::TimestampNanosecondArray::from(vec![0, 0, 0])),
+            ],
+        )
+        .unwrap();
+
+        let methods = [
+            FramingMethod::Delimiter(DelimitedFraming {
+                delimiter: vec![0x1e],
+            }),
+            FramingMethod::LengthPrefixed(LengthPrefixedFraming {
+                width: LengthPrefixWidth::U16,
+                endianness: Endianness::Little,
+            }),
+            FramingMethod::LengthPrefixed(LengthPrefixedFraming {
+                width: LengthPrefixWidth::U32,
+                endianness: Endianness::Big,
+            }),
+            FramingMethod::LengthPrefixed(LengthPrefixedFraming {
+                width: LengthPrefixWidth::Varint,
+                endianness: Endianness::Big,
+            }),
+        ];
+
+        for method in methods {
+            let framing = Framing { method };
+            let mut serializer = ArrowSerializer::with_framing(
+                Format::RawString(RawStringFormat {}),
+                Some(framing.clone()),
+            );
+
+            let buf: Vec<u8> = serializer.serialize(&batch).flatten().collect();
+
+            let result: Vec<_> = FramingIterator::new(Some(Arc::new(framing)), &buf)
+                .map(|t| String::from_utf8(t.unwrap().to_vec()).unwrap())
+                .collect();
+
+            assert_eq!(data, result);
+        }
+    }
 }
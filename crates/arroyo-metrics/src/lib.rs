@@ -3,7 +3,7 @@ use std::sync::{Arc, OnceLock, RwLock};
 
 use arroyo_types::{
     TaskInfo, BATCHES_RECV, BATCHES_SENT, BYTES_RECV, BYTES_SENT, DESERIALIZATION_ERRORS,
-    MESSAGES_RECV, MESSAGES_SENT,
+    MESSAGES_RECV, MESSAGES_SENT, RECORDS_DROPPED,
 };
 use lazy_static::lazy_static;
 use prometheus::{
@@ -85,6 +85,12 @@ lazy_static! {
         &TASK_METRIC_LABELS
     )
     .unwrap();
+    pub static ref RECORDS_DROPPED_COUNTER: IntCounterVec = register_int_counter_vec!(
+        RECORDS_DROPPED,
+        "Count of records dropped because they could not be deserialized",
+        &TASK_METRIC_LABELS
+    )
+    .unwrap();
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
@@ -96,10 +102,11 @@ pub enum TaskCounters {
     BytesReceived,
     BytesSent,
     DeserializationErrors,
+    RecordsDropped,
 }
 
 impl TaskCounters {
-    pub fn variants() -> [TaskCounters; 7] {
+    pub fn variants() -> [TaskCounters; 8] {
         use TaskCounters::*;
 
         [
@@ -110,6 +117,7 @@ impl TaskCounters {
             BytesReceived,
             BytesSent,
             DeserializationErrors,
+            RecordsDropped,
         ]
     }
 }
@@ -125,6 +133,7 @@ impl TaskCounters {
             TaskCounters::BytesReceived => &BYTES_RECEIVED_COUNTER,
             TaskCounters::BytesSent => &BYTES_SENT_COUNTER,
             TaskCounters::DeserializationErrors => &DESERIALIZATION_ERRORS_COUNTER,
+            TaskCounters::RecordsDropped => &RECORDS_DROPPED_COUNTER,
         }
     }
 
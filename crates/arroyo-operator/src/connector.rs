@@ -1,14 +1,19 @@
 use crate::operator::OperatorNode;
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
+use arrow::array::{ArrayRef, RecordBatch};
 use arrow::datatypes::DataType;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, TestSourceMessage,
 };
+use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::OperatorConfig;
+use arroyo_types::SourceError;
+use async_trait::async_trait;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
 use serde_json::value::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::oneshot;
 
@@ -23,6 +28,26 @@ pub struct Connection {
     pub description: String,
 }
 
+/// A metadata field that a connector can provide, which can be read into a column with
+/// `GENERATED ALWAYS AS (metadata('name')) STORED`
+#[derive(Debug, Clone)]
+pub struct MetadataDef {
+    pub name: &'static str,
+    pub data_type: DataType,
+}
+
+/// A connector that can fetch the rows for a set of keys from an external system, which is used
+/// to implement lookup joins
+#[async_trait]
+pub trait LookupConnector: Send {
+    fn name(&self) -> String;
+
+    /// Looks up the rows for the given keys. The returned batch has the schema the connector was
+    /// created with, and its [`arroyo_rpc::LOOKUP_KEY_INDEX_FIELD`] column contains, for each row,
+    /// the index of the key it was found for; keys that don't exist have no rows.
+    async fn lookup(&mut self, keys: &[ArrayRef]) -> Option<Result<RecordBatch, SourceError>>;
+}
+
 #[allow(clippy::wrong_self_convention)]
 pub trait Connector: Send {
     type ProfileT: DeserializeOwned + Serialize;
@@ -45,6 +70,11 @@ pub trait Connector: Send {
 
     fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector;
 
+    /// The metadata fields supported by this connector's source
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        &[]
+    }
+
     fn table_type(&self, config: Self::ProfileT, table: Self::TableT) -> ConnectionType;
 
     #[allow(unused)]
@@ -110,6 +140,22 @@ pub trait Connector: Send {
         table: Self::TableT,
         config: OperatorConfig,
     ) -> anyhow::Result<OperatorNode>;
+
+    /// Constructs a connector that can be used to look up rows for a lookup join; `schema` is
+    /// the schema of the rows it should return
+    #[allow(unused)]
+    fn make_lookup(
+        &self,
+        profile: Self::ProfileT,
+        table: Self::TableT,
+        config: OperatorConfig,
+        schema: Arc<ArroyoSchema>,
+    ) -> anyhow::Result<Box<dyn LookupConnector>> {
+        bail!(
+            "the {} connector does not support lookup tables",
+            self.name()
+        )
+    }
 }
 #[allow(clippy::type_complexity)]
 #[allow(clippy::wrong_self_convention)]
@@ -118,6 +164,8 @@ pub trait ErasedConnector: Send {
 
     fn metadata(&self) -> arroyo_rpc::api_types::connections::Connector;
 
+    fn metadata_defs(&self) -> &'static [MetadataDef];
+
     fn validate_config(&self, s: &serde_json::Value) -> Result<(), serde_json::Error>;
 
     fn validate_table(&self, s: &serde_json::Value) -> Result<(), serde_json::Error>;
@@ -179,6 +227,12 @@ pub trait ErasedConnector: Send {
     ) -> anyhow::Result<Connection>;
 
     fn make_operator(&self, config: OperatorConfig) -> anyhow::Result<OperatorNode>;
+
+    fn make_lookup(
+        &self,
+        config: OperatorConfig,
+        schema: Arc<ArroyoSchema>,
+    ) -> anyhow::Result<Box<dyn LookupConnector>>;
 }
 
 impl<C: Connector> ErasedConnector for C {
@@ -190,6 +244,10 @@ impl<C: Connector> ErasedConnector for C {
         self.metadata()
     }
 
+    fn metadata_defs(&self) -> &'static [MetadataDef] {
+        self.metadata_defs()
+    }
+
     fn config_description(&self, s: &serde_json::Value) -> Result<String, serde_json::Error> {
         Ok(self.config_description(self.parse_config(s)?))
     }
@@ -300,4 +358,19 @@ impl<C: Connector> ErasedConnector for C {
             config,
         )
     }
+    fn make_lookup(
+        &self,
+        config: OperatorConfig,
+        schema: Arc<ArroyoSchema>,
+    ) -> anyhow::Result<Box<dyn LookupConnector>> {
+        self.make_lookup(
+            self.parse_config(&config.connection).map_err(|e| {
+                anyhow!("invalid profile config for lookup {}: {:?}", self.name(), e)
+            })?,
+            self.parse_table(&config.table)
+                .map_err(|e| anyhow!("invalid table config for lookup {}: {:?}", self.name(), e))?,
+            config,
+            schema,
+        )
+    }
 }
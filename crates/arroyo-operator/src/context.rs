@@ -284,6 +284,7 @@ pub struct ArrowCollector {
     out_schema: Option<ArroyoSchema>,
     projection: Option<Vec<usize>>,
     out_qs: Vec<Vec<BatchSender>>,
+    dead_letter_qs: Vec<Vec<BatchSender>>,
     tx_queue_rem_gauges: QueueGauges,
     tx_queue_size_gauges: QueueGauges,
     tx_queue_bytes_gauges: QueueGauges,
@@ -402,8 +403,27 @@ impl ArrowCollector {
         }
     }
 
+    /// Sends records that failed to deserialize to the consumers of the source's dead-letter
+    /// table; if there are none, the records are counted as dropped
+    pub async fn collect_dead_letters(&mut self, record: RecordBatch) {
+        if self.dead_letter_qs.is_empty() {
+            TaskCounters::RecordsDropped
+                .for_task(&self.task_info, |c| c.inc_by(record.num_rows() as u64));
+            return;
+        }
+
+        for out_q in &self.dead_letter_qs {
+            for (partition, batch) in repartition(&record, &None, out_q.len()) {
+                out_q[partition]
+                    .send(ArrowMessage::Data(batch))
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+
     pub async fn broadcast(&mut self, message: ArrowMessage) {
-        for out_node in &self.out_qs {
+        for out_node in self.out_qs.iter().chain(&self.dead_letter_qs) {
             for q in out_node {
                 q.send(message.clone()).await.unwrap_or_else(|e| {
                     panic!(
@@ -428,6 +448,7 @@ impl ArrowContext {
         out_schema: Option<ArroyoSchema>,
         projection: Option<Vec<usize>>,
         out_qs: Vec<Vec<BatchSender>>,
+        dead_letter_qs: Vec<Vec<BatchSender>>,
         tables: HashMap<String, TableConfig>,
     ) -> Self {
         let (watermark, metadata) = if let Some(metadata) = restore_from {
@@ -506,6 +527,7 @@ impl ArrowContext {
             collector: ArrowCollector {
                 task_info: task_info.clone(),
                 out_qs,
+                dead_letter_qs,
                 tx_queue_rem_gauges,
                 tx_queue_size_gauges,
                 tx_queue_bytes_gauges,
@@ -559,6 +581,22 @@ impl ArrowContext {
             }
         }
 
+        if let Some(deserializer) = self.deserializer.as_mut() {
+            let invalid_rows = deserializer.take_invalid_rows() as u64;
+            if invalid_rows > 0 {
+                TaskCounters::DeserializationErrors
+                    .for_task(&self.task_info, |c| c.inc_by(invalid_rows));
+                if matches!(deserializer.bad_data(), BadData::Drop {}) {
+                    TaskCounters::RecordsDropped
+                        .for_task(&self.task_info, |c| c.inc_by(invalid_rows));
+                }
+            }
+
+            if let Some(dead_letters) = deserializer.flush_dead_letters() {
+                self.collector.collect_dead_letters(dead_letters).await;
+            }
+        }
+
         if let Some(error) = self.buffered_error.take() {
             return Err(error);
         }
@@ -671,6 +709,19 @@ impl ArrowContext {
         msg: &[u8],
         time: SystemTime,
         additional_fields: Option<HashMap<&String, FieldValueType<'_>>>,
+    ) -> Result<(), UserError> {
+        self.deserialize_slice_at(msg, time, additional_fields, &[])
+            .await
+    }
+
+    /// Deserializes a message read from the given position in the source (like its topic,
+    /// partition and offset), which is included in the metadata of dead letters
+    pub async fn deserialize_slice_at(
+        &mut self,
+        msg: &[u8],
+        time: SystemTime,
+        additional_fields: Option<HashMap<&String, FieldValueType<'_>>>,
+        position: &[(&str, FieldValueType<'_>)],
     ) -> Result<(), UserError> {
         let deserializer = self
             .deserializer
@@ -685,11 +736,12 @@ impl ArrowContext {
         }
 
         let errors = deserializer
-            .deserialize_slice(
+            .deserialize_slice_at(
                 &mut self.buffer.as_mut().expect("no out schema").buffer,
                 msg,
                 time,
                 additional_fields,
+                position,
             )
             .await;
         self.collect_source_errors(errors).await?;
@@ -698,7 +750,8 @@ impl ArrowContext {
     }
 
     /// Handling errors and rate limiting error reporting.
-    /// Considers the `bad_data` option to determine whether to drop or fail on bad data.
+    /// Considers the `bad_data` option to determine whether to drop, fail, or send bad data to the
+    /// dead-letter queue.
     async fn collect_source_errors(&mut self, errors: Vec<SourceError>) -> Result<(), UserError> {
         let bad_data = self
             .deserializer
@@ -723,7 +776,17 @@ impl ArrowContext {
                                     .unwrap();
                             })
                             .await;
-                        TaskCounters::DeserializationErrors.for_task(&self.task_info, |c| c.inc())
+                        TaskCounters::DeserializationErrors.for_task(&self.task_info, |c| c.inc());
+                        TaskCounters::RecordsDropped.for_task(&self.task_info, |c| c.inc());
+                    }
+                    BadData::Dlq {} => {
+                        // the deserializer has already added the record to its dead-letter buffer
+                        self.error_rate_limiter
+                            .rate_limit(|| async {
+                                warn!("Sending invalid data to dead-letter queue: {}", details);
+                            })
+                            .await;
+                        TaskCounters::DeserializationErrors.for_task(&self.task_info, |c| c.inc());
                     }
                     BadData::Fail {} => {
                         return Err(UserError::new("Deserialization error", details));
@@ -844,6 +907,7 @@ mod tests {
             out_schema: Some(ArroyoSchema::new_keyed(schema, 1, vec![0])),
             projection: None,
             out_qs,
+            dead_letter_qs: vec![],
             tx_queue_rem_gauges,
             tx_queue_size_gauges,
             tx_queue_bytes_gauges,
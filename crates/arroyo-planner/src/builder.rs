@@ -213,6 +213,7 @@ pub(crate) enum NamedNode {
     Source(TableReference),
     Watermark(TableReference),
     RemoteTable(TableReference),
+    DeadLetter(TableReference),
 }
 
 struct ArroyoExtensionPlanner {}
@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+use std::{fmt::Formatter, sync::Arc};
+
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::{
+    dead_letter_schema,
+    df::{ArroyoSchema, ArroyoSchemaRef},
+    grpc::api::ValuePlanOperator,
+};
+use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+use datafusion::common::{internal_err, plan_err, DFSchema, DFSchemaRef, Result, TableReference};
+use datafusion::logical_expr::{Expr, Extension, LogicalPlan, UserDefinedLogicalNodeCore};
+use datafusion_proto::{physical_plan::AsExecutionPlan, protobuf::PhysicalPlanNode};
+use prost::Message;
+
+use crate::{
+    builder::{NamedNode, Planner},
+    physical::ArroyoPhysicalExtensionCodec,
+};
+
+use super::{
+    table_source::{TableSourceExtension, TABLE_SOURCE_NAME},
+    ArroyoExtension, NodeWithIncomingEdges,
+};
+
+pub(crate) const DEAD_LETTER_NAME: &str = "DeadLetterExtension";
+const DEAD_LETTER_INPUT_NAME: &str = "DeadLetterInputExtension";
+
+/// Reads the dead-letter queue of a source. Its input is the source itself, which it is
+/// connected to by a dead-letter edge; `projection` is evaluated over the dead-letter records
+/// to produce the columns of the dead-letter table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DeadLetterExtension {
+    pub(crate) name: TableReference,
+    pub(crate) source: LogicalPlan,
+    pub(crate) projection: LogicalPlan,
+    pub(crate) schema: DFSchemaRef,
+}
+
+impl DeadLetterExtension {
+    /// `source` is the source's TableSourceExtension, and `projection` computes the columns of
+    /// the dead-letter table from the plan returned by [`Self::input`]
+    pub(crate) fn new(name: TableReference, source: LogicalPlan, projection: LogicalPlan) -> Self {
+        let schema = projection.schema().clone();
+        Self {
+            name,
+            source,
+            projection,
+            schema,
+        }
+    }
+
+    /// A placeholder for the records sent by the source over the dead-letter edge, which
+    /// projections for the dead-letter table can be built on
+    pub(crate) fn input(name: &TableReference) -> Result<LogicalPlan> {
+        let schema =
+            DFSchema::try_from_qualified_schema(name.clone(), &dead_letter_schema().schema)?;
+        Ok(LogicalPlan::Extension(Extension {
+            node: Arc::new(DeadLetterInputExtension {
+                schema: Arc::new(schema),
+            }),
+        }))
+    }
+}
+
+impl ArroyoExtension for DeadLetterExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        Some(NamedNode::DeadLetter(self.name.clone()))
+    }
+
+    fn plan_node(
+        &self,
+        planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            return plan_err!("DeadLetterExtension should have exactly one input");
+        }
+
+        let physical_plan = planner.sync_plan(&self.projection)?;
+        let physical_plan_node = PhysicalPlanNode::try_from_physical_plan(
+            physical_plan,
+            &ArroyoPhysicalExtensionCodec::default(),
+        )?;
+        let config = ValuePlanOperator {
+            name: format!("dead_letter({})", self.name),
+            physical_plan: physical_plan_node.encode_to_vec(),
+        };
+        let node = LogicalNode {
+            operator_id: format!("dead_letter_{}", index),
+            description: self.name.to_string(),
+            operator_name: OperatorName::ArrowValue,
+            parallelism: 1,
+            operator_config: config.encode_to_vec(),
+        };
+
+        // the edge carries the dead-letter records rather than the source's output
+        let edge = LogicalEdge::project_all(
+            LogicalEdgeType::DeadLetter,
+            dead_letter_schema().as_ref().clone(),
+        );
+
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_keys(Arc::new(self.schema.as_ref().into()), vec![]).unwrap()
+    }
+}
+
+impl UserDefinedLogicalNodeCore for DeadLetterExtension {
+    fn name(&self) -> &str {
+        DEAD_LETTER_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.source]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "DeadLetterExtension({}): {}", self.name, self.schema)
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        if inputs.len() != 1 {
+            return internal_err!("input size inconsistent");
+        }
+
+        Ok(Self {
+            name: self.name.clone(),
+            source: inputs[0].clone(),
+            projection: self.projection.clone(),
+            schema: self.schema.clone(),
+        })
+    }
+}
+
+/// Finds the sources that the plans only read through their dead-letter tables, returning their
+/// names and TableSourceExtensions
+pub(crate) fn sources_only_read_for_dead_letters(
+    plans: &[LogicalPlan],
+) -> Result<Vec<(TableReference, LogicalPlan)>> {
+    let mut dead_letter_sources: Vec<(TableReference, LogicalPlan)> = vec![];
+    let mut read = HashSet::new();
+
+    for plan in plans {
+        plan.apply(|plan| {
+            let LogicalPlan::Extension(Extension { node }) = plan else {
+                return Ok(TreeNodeRecursion::Continue);
+            };
+
+            if node.name() == DEAD_LETTER_NAME {
+                let dead_letter = node.as_any().downcast_ref::<DeadLetterExtension>().unwrap();
+                if let LogicalPlan::Extension(Extension { node }) = &dead_letter.source {
+                    if let Some(source) = node.as_any().downcast_ref::<TableSourceExtension>() {
+                        if !dead_letter_sources.iter().any(|(n, _)| *n == source.name) {
+                            dead_letter_sources
+                                .push((source.name.clone(), dead_letter.source.clone()));
+                        }
+                    }
+                }
+                // the source beneath a dead-letter table isn't read for its output
+                return Ok(TreeNodeRecursion::Jump);
+            }
+
+            if node.name() == TABLE_SOURCE_NAME {
+                let source = node
+                    .as_any()
+                    .downcast_ref::<TableSourceExtension>()
+                    .unwrap();
+                read.insert(source.name.clone());
+            }
+
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+    }
+
+    Ok(dead_letter_sources
+        .into_iter()
+        .filter(|(name, _)| !read.contains(name))
+        .collect())
+}
+
+/// Stands in for the dead-letter records within the plan of a [`DeadLetterExtension`]; like
+/// other inputs, it's planned as a memory exec that is fed by the operator at runtime
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DeadLetterInputExtension {
+    schema: DFSchemaRef,
+}
+
+impl UserDefinedLogicalNodeCore for DeadLetterInputExtension {
+    fn name(&self) -> &str {
+        DEAD_LETTER_INPUT_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "DeadLetterInputExtension: {}", self.schema)
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, _inputs: Vec<LogicalPlan>) -> Result<Self> {
+        Ok(self.clone())
+    }
+}
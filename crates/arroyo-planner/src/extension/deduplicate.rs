@@ -0,0 +1,144 @@
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::DeduplicateOperator;
+use datafusion::common::{internal_err, plan_err, DFSchemaRef, Result};
+use datafusion::logical_expr::{Expr, LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
+use crate::{fields_with_qualifiers, schema_from_df_fields_with_metadata, DFField};
+
+pub(crate) const DEDUPLICATE_EXTENSION_NAME: &str = "DeduplicateExtension";
+
+/// Deduplicates an unwindowed input by key, as for `ROW_NUMBER() OVER (PARTITION BY ..
+/// ORDER BY _timestamp) = 1`. Unlike a top-N, the output is append-only: either the first row
+/// for each key is emitted, or (when ordered by descending time) the last row for each key is
+/// emitted once no rows have arrived for it within the ttl.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DeduplicateExtension {
+    pub(crate) input: LogicalPlan,
+    /// the field holding the row number, which is always 1
+    pub(crate) row_number: DFField,
+    pub(crate) keep_last: bool,
+    /// how long a key is remembered after its row
+    pub(crate) ttl: Duration,
+    pub(crate) schema: DFSchemaRef,
+}
+
+impl DeduplicateExtension {
+    pub fn new(
+        input: LogicalPlan,
+        row_number: DFField,
+        keep_last: bool,
+        ttl: Duration,
+    ) -> Result<Self> {
+        let mut fields = fields_with_qualifiers(input.schema());
+        fields.push(row_number.clone());
+
+        let schema = Arc::new(schema_from_df_fields_with_metadata(
+            &fields,
+            input.schema().metadata().clone(),
+        )?);
+
+        Ok(Self {
+            input,
+            row_number,
+            keep_last,
+            ttl,
+            schema,
+        })
+    }
+}
+
+impl UserDefinedLogicalNodeCore for DeduplicateExtension {
+    fn name(&self) -> &str {
+        DEDUPLICATE_EXTENSION_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "DeduplicateExtension({}): {}",
+            if self.keep_last { "last" } else { "first" },
+            self.schema
+        )
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        if inputs.len() != 1 {
+            return internal_err!("input size inconsistent");
+        }
+
+        Self::new(
+            inputs[0].clone(),
+            self.row_number.clone(),
+            self.keep_last,
+            self.ttl,
+        )
+    }
+}
+
+impl ArroyoExtension for DeduplicateExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        _planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            return plan_err!("DeduplicateExtension requires exactly one input");
+        }
+        let input_schema = input_schemas[0].clone();
+
+        let config = DeduplicateOperator {
+            name: format!("deduplicate_{}", index),
+            input_schema: Some(input_schema.as_ref().clone().into()),
+            output_schema: Some(self.output_schema().into()),
+            keep_last: self.keep_last,
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+
+        let node = LogicalNode {
+            operator_id: format!("deduplicate_{}", index),
+            description: format!(
+                "Deduplicate<{}>",
+                if self.keep_last { "last" } else { "first" }
+            ),
+            operator_name: OperatorName::Deduplicate,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Shuffle, (*input_schema).clone());
+
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(Arc::new(self.schema.as_ref().into())).unwrap()
+    }
+}
@@ -3,8 +3,9 @@ use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
 use crate::physical::ArroyoPhysicalExtensionCodec;
 use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
 use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api;
 use arroyo_rpc::grpc::api::JoinOperator;
-use datafusion::common::{plan_err, DFSchemaRef, Result};
+use datafusion::common::{plan_err, DFSchemaRef, JoinType, Result};
 use datafusion::logical_expr::expr::Expr;
 use datafusion::logical_expr::{LogicalPlan, UserDefinedLogicalNodeCore};
 use datafusion_proto::generated::datafusion::PhysicalPlanNode;
@@ -15,11 +16,101 @@ use std::time::Duration;
 
 pub(crate) const JOIN_NODE_NAME: &str = "JoinNode";
 
+/// An interval join, whose condition bounds the right side's event time relative to the left's,
+/// allowing state to be expired by watermark
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct JoinInterval {
+    pub(crate) lower_micros: i64,
+    pub(crate) upper_micros: i64,
+    pub(crate) join_type: JoinType,
+    /// for outer joins, the join producing the null-padded rows for rows on the left (or
+    /// right) that never matched; the main join only produces the matched rows
+    pub(crate) left_unmatched: Option<LogicalPlan>,
+    pub(crate) right_unmatched: Option<LogicalPlan>,
+}
+
+/// An outer join of unwindowed streams. Rows on an outer side are emitted null-padded as soon as
+/// they arrive, and retracted once they're matched, so the output is updating.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct UpdatingOuterJoin {
+    pub(crate) join_type: JoinType,
+    /// the joins producing the null-padded rows for rows on the left (or right) without a match;
+    /// the main join only produces the matched rows
+    pub(crate) left_unmatched: Option<LogicalPlan>,
+    pub(crate) right_unmatched: Option<LogicalPlan>,
+    /// the schema of the join's output, including the updating metadata
+    pub(crate) schema: DFSchemaRef,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct JoinExtension {
     pub(crate) rewritten_join: LogicalPlan,
     pub(crate) is_instant: bool,
     pub(crate) ttl: Option<Duration>,
+    pub(crate) interval: Option<JoinInterval>,
+    pub(crate) updating_outer: Option<UpdatingOuterJoin>,
+}
+
+impl JoinExtension {
+    fn encode_plan(planner: &Planner, plan: &LogicalPlan) -> Result<Vec<u8>> {
+        let physical_plan_node = PhysicalPlanNode::try_from_physical_plan(
+            planner.sync_plan(plan)?,
+            &ArroyoPhysicalExtensionCodec::default(),
+        )?;
+        Ok(physical_plan_node.encode_to_vec())
+    }
+
+    fn join_type(join_type: JoinType) -> Result<api::JoinType> {
+        Ok(match join_type {
+            JoinType::Inner => api::JoinType::Inner,
+            JoinType::Left => api::JoinType::Left,
+            JoinType::Right => api::JoinType::Right,
+            JoinType::Full => api::JoinType::Full,
+            join_type => return plan_err!("unsupported join type {} for join", join_type),
+        })
+    }
+
+    fn interval_config(&self, planner: &Planner) -> Result<Option<api::JoinInterval>> {
+        let Some(interval) = &self.interval else {
+            return Ok(None);
+        };
+
+        Ok(Some(api::JoinInterval {
+            lower_micros: interval.lower_micros,
+            upper_micros: interval.upper_micros,
+            join_type: Self::join_type(interval.join_type)?.into(),
+            left_unmatched_plan: interval
+                .left_unmatched
+                .as_ref()
+                .map(|plan| Self::encode_plan(planner, plan))
+                .transpose()?,
+            right_unmatched_plan: interval
+                .right_unmatched
+                .as_ref()
+                .map(|plan| Self::encode_plan(planner, plan))
+                .transpose()?,
+        }))
+    }
+
+    fn updating_outer_config(&self, planner: &Planner) -> Result<Option<api::UpdatingOuterJoin>> {
+        let Some(outer) = &self.updating_outer else {
+            return Ok(None);
+        };
+
+        Ok(Some(api::UpdatingOuterJoin {
+            join_type: Self::join_type(outer.join_type)?.into(),
+            left_unmatched_plan: outer
+                .left_unmatched
+                .as_ref()
+                .map(|plan| Self::encode_plan(planner, plan))
+                .transpose()?,
+            right_unmatched_plan: outer
+                .right_unmatched
+                .as_ref()
+                .map(|plan| Self::encode_plan(planner, plan))
+                .transpose()?,
+        }))
+    }
 }
 
 impl ArroyoExtension for JoinExtension {
@@ -39,12 +130,6 @@ impl ArroyoExtension for JoinExtension {
         let left_schema = input_schemas[0].clone();
         let right_schema = input_schemas[1].clone();
 
-        let join_plan = planner.sync_plan(&self.rewritten_join)?;
-        let physical_plan_node = PhysicalPlanNode::try_from_physical_plan(
-            join_plan.clone(),
-            &ArroyoPhysicalExtensionCodec::default(),
-        )?;
-
         let operator_name = if self.is_instant {
             OperatorName::InstantJoin
         } else {
@@ -56,8 +141,10 @@ impl ArroyoExtension for JoinExtension {
             left_schema: Some(left_schema.as_ref().clone().into()),
             right_schema: Some(right_schema.as_ref().clone().into()),
             output_schema: Some(self.output_schema().into()),
-            join_plan: physical_plan_node.encode_to_vec(),
+            join_plan: Self::encode_plan(planner, &self.rewritten_join)?,
             ttl_micros: self.ttl.map(|t| t.as_micros() as u64),
+            interval: self.interval_config(planner)?,
+            updating_outer: self.updating_outer_config(planner)?,
         };
 
         let logical_node = LogicalNode {
@@ -93,7 +180,10 @@ impl UserDefinedLogicalNodeCore for JoinExtension {
     }
 
     fn schema(&self) -> &DFSchemaRef {
-        self.rewritten_join.schema()
+        match &self.updating_outer {
+            Some(outer) => &outer.schema,
+            None => self.rewritten_join.schema(),
+        }
     }
 
     fn expressions(&self) -> Vec<Expr> {
@@ -109,6 +199,8 @@ impl UserDefinedLogicalNodeCore for JoinExtension {
             rewritten_join: inputs[0].clone(),
             is_instant: self.is_instant,
             ttl: self.ttl,
+            interval: self.interval.clone(),
+            updating_outer: self.updating_outer.clone(),
         })
     }
 }
@@ -0,0 +1,287 @@
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+use arrow_schema::{DataType, Field, Schema};
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::{self, LookupJoinCondition, LookupJoinOperator};
+use arroyo_rpc::{LOOKUP_KEY_INDEX_FIELD, TIMESTAMP_FIELD};
+use datafusion::common::{
+    internal_err, plan_err, Column, DFSchemaRef, JoinType, Result, TableReference,
+};
+use datafusion::logical_expr::{Expr, LogicalPlan, UserDefinedLogicalNodeCore};
+use datafusion_proto::physical_plan::to_proto::serialize_physical_expr;
+use datafusion_proto::physical_plan::DefaultPhysicalExtensionCodec;
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
+use crate::schemas::add_timestamp_field;
+use crate::tables::{ConnectorTable, FieldSpec};
+use crate::{fields_with_qualifiers, schema_from_df_fields, DFField};
+
+pub(crate) const LOOKUP_SOURCE_NAME: &str = "LookupSource";
+pub(crate) const LOOKUP_JOIN_NAME: &str = "LookupJoin";
+
+/// A scan of a lookup table. This can't be planned on its own; instead it's absorbed into a
+/// [`LookupJoinExtension`] when it appears on the right side of a join.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LookupSource {
+    pub(crate) table: ConnectorTable,
+    pub(crate) schema: DFSchemaRef,
+}
+
+impl LookupSource {
+    pub fn new(name: TableReference, table: ConnectorTable) -> Result<Self> {
+        let fields: Vec<DFField> = table
+            .fields
+            .iter()
+            .map(|field| match field {
+                FieldSpec::StructField(field) => {
+                    Ok((Some(name.clone()), Arc::new(field.clone())).into())
+                }
+                FieldSpec::VirtualField { .. } => {
+                    plan_err!("virtual fields are not supported in lookup tables")
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        let schema = add_timestamp_field(Arc::new(schema_from_df_fields(&fields)?), Some(name))?;
+
+        Ok(Self { table, schema })
+    }
+}
+
+impl UserDefinedLogicalNodeCore for LookupSource {
+    fn name(&self) -> &str {
+        LOOKUP_SOURCE_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "LookupSource: {}", self.schema)
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        if !inputs.is_empty() {
+            return internal_err!("LookupSource cannot have inputs");
+        }
+
+        Ok(self.clone())
+    }
+}
+
+impl ArroyoExtension for LookupSource {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        _planner: &Planner,
+        _index: usize,
+        _input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        plan_err!(
+            "lookup table '{}' can only be used on the right side of a JOIN",
+            self.table.name
+        )
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(Arc::new(self.schema.as_ref().into())).unwrap()
+    }
+}
+
+/// Joins each incoming row with the rows of a lookup table that match its key, by querying the
+/// table's connector
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LookupJoinExtension {
+    pub(crate) input: LogicalPlan,
+    pub(crate) schema: DFSchemaRef,
+    pub(crate) table: ConnectorTable,
+    /// the columns of the lookup table (as seen by the query) that are included in the output
+    pub(crate) lookup_fields: Vec<DFField>,
+    pub(crate) on: Vec<(Expr, Column)>,
+    pub(crate) join_type: JoinType,
+}
+
+impl LookupJoinExtension {
+    pub fn new(
+        input: LogicalPlan,
+        table: ConnectorTable,
+        lookup_fields: Vec<DFField>,
+        on: Vec<(Expr, Column)>,
+        join_type: JoinType,
+    ) -> Result<Self> {
+        let mut input_fields = fields_with_qualifiers(input.schema());
+        let Some(timestamp_index) = input_fields
+            .iter()
+            .position(|f| f.name() == TIMESTAMP_FIELD)
+        else {
+            return internal_err!("input to a lookup join must have a timestamp field");
+        };
+        let timestamp = input_fields.remove(timestamp_index);
+
+        let output_fields: Vec<_> = input_fields
+            .into_iter()
+            .chain(
+                lookup_fields
+                    .iter()
+                    .map(|f| f.clone().with_nullable(join_type == JoinType::Left)),
+            )
+            .chain(std::iter::once(timestamp))
+            .collect();
+
+        Ok(Self {
+            schema: Arc::new(schema_from_df_fields(&output_fields)?),
+            input,
+            table,
+            lookup_fields,
+            on,
+            join_type,
+        })
+    }
+
+    /// The schema of the rows returned by the connector: the physical fields of the table, plus
+    /// the index of the key each row was found for
+    fn connector_schema(&self) -> ArroyoSchema {
+        let mut fields: Vec<_> = self
+            .table
+            .fields
+            .iter()
+            .map(|f| f.field().clone().with_nullable(true))
+            .collect();
+        fields.push(Field::new(LOOKUP_KEY_INDEX_FIELD, DataType::Int64, true));
+        fields.push(Field::new(
+            TIMESTAMP_FIELD,
+            DataType::Timestamp(arrow_schema::TimeUnit::Nanosecond, None),
+            false,
+        ));
+
+        ArroyoSchema::from_schema_unkeyed(Arc::new(Schema::new(fields))).unwrap()
+    }
+}
+
+impl UserDefinedLogicalNodeCore for LookupJoinExtension {
+    fn name(&self) -> &str {
+        LOOKUP_JOIN_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        self.on.iter().map(|(l, _)| l.clone()).collect()
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "LookupJoinExtension<{}>: {}",
+            self.table.name, self.schema
+        )
+    }
+
+    fn with_exprs_and_inputs(&self, exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        if inputs.len() != 1 {
+            return internal_err!("input size inconsistent");
+        }
+        if exprs.len() != self.on.len() {
+            return internal_err!("expression size inconsistent");
+        }
+
+        Self::new(
+            inputs[0].clone(),
+            self.table.clone(),
+            self.lookup_fields.clone(),
+            exprs
+                .into_iter()
+                .zip(self.on.iter().map(|(_, right)| right.clone()))
+                .collect(),
+            self.join_type,
+        )
+    }
+}
+
+impl ArroyoExtension for LookupJoinExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            return plan_err!("lookup join should have exactly one input");
+        }
+
+        let key_exprs = self
+            .on
+            .iter()
+            .map(|(left, right)| {
+                let p = planner.create_physical_expr(left, self.input.schema())?;
+                Ok(LookupJoinCondition {
+                    left_expr: serialize_physical_expr(p, &DefaultPhysicalExtensionCodec {})?
+                        .encode_to_vec(),
+                    right_key: right.name.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let join_type = match self.join_type {
+            JoinType::Inner => api::JoinType::Inner,
+            JoinType::Left => api::JoinType::Left,
+            t => return plan_err!("unsupported join type for lookup join: {}", t),
+        };
+
+        let config = LookupJoinOperator {
+            input_schema: Some(input_schemas[0].as_ref().clone().into()),
+            lookup_schema: Some(self.connector_schema().into()),
+            connector: Some(self.table.connector_op()),
+            key_exprs,
+            join_type: join_type as i32,
+            ttl_micros: self.table.lookup_cache_ttl.map(|t| t.as_micros() as u64),
+            max_capacity: self.table.lookup_cache_max_entries,
+        };
+
+        let node = LogicalNode {
+            operator_id: format!("lookup_join_{}", index),
+            description: format!("lookup_join<{}>", self.table.name),
+            operator_name: OperatorName::LookupJoin,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+
+        let incoming_edge =
+            LogicalEdge::project_all(LogicalEdgeType::Forward, input_schemas[0].as_ref().clone());
+
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![incoming_edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(Arc::new(self.schema.as_ref().into())).unwrap()
+    }
+}
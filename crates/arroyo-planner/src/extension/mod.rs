@@ -24,7 +24,12 @@ use crate::schemas::{add_timestamp_field, has_timestamp_field};
 use crate::{fields_with_qualifiers, schema_from_df_fields, DFField, ASYNC_RESULT_FIELD};
 use join::JoinExtension;
 
+use self::dead_letter::DeadLetterExtension;
 use self::debezium::{DebeziumUnrollingExtension, ToDebeziumExtension};
+use self::deduplicate::DeduplicateExtension;
+use self::lookup::{LookupJoinExtension, LookupSource};
+use self::temporal_join::TemporalJoinExtension;
+use self::top_n::TopNExtension;
 use self::updating_aggregate::UpdatingAggregateExtension;
 use self::{
     aggregate::AggregateExtension, key_calculation::KeyCalculationExtension,
@@ -33,12 +38,17 @@ use self::{
 };
 
 pub(crate) mod aggregate;
+pub(crate) mod dead_letter;
 pub(crate) mod debezium;
+pub(crate) mod deduplicate;
 pub(crate) mod join;
 pub(crate) mod key_calculation;
+pub(crate) mod lookup;
 pub(crate) mod remote_table;
 pub(crate) mod sink;
 pub(crate) mod table_source;
+pub(crate) mod temporal_join;
+pub(crate) mod top_n;
 pub(crate) mod updating_aggregate;
 pub(crate) mod watermark_node;
 pub(crate) mod window_fn;
@@ -87,6 +97,12 @@ impl<'a> TryFrom<&'a dyn UserDefinedLogicalNode> for &'a dyn ArroyoExtension {
             .or_else(|_| try_from_t::<ToDebeziumExtension>(node))
             .or_else(|_| try_from_t::<DebeziumUnrollingExtension>(node))
             .or_else(|_| try_from_t::<UpdatingAggregateExtension>(node))
+            .or_else(|_| try_from_t::<DeadLetterExtension>(node))
+            .or_else(|_| try_from_t::<LookupSource>(node))
+            .or_else(|_| try_from_t::<LookupJoinExtension>(node))
+            .or_else(|_| try_from_t::<TemporalJoinExtension>(node))
+            .or_else(|_| try_from_t::<TopNExtension>(node))
+            .or_else(|_| try_from_t::<DeduplicateExtension>(node))
             .map_err(|_| DataFusionError::Plan(format!("unexpected node: {}", node.name())))
     }
 }
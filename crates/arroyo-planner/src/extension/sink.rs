@@ -59,6 +59,10 @@ impl SinkExtension {
                 }
             }
             Table::MemoryTable { .. } => return plan_err!("memory tables not supported"),
+            Table::DeadLetterTable { .. } => {
+                return plan_err!("can't insert into a dead-letter table")
+            }
+            Table::LookupTable(_) => return plan_err!("can't insert into a lookup table"),
             Table::TableFromQuery { .. } => {}
             Table::PreviewSink { .. } => {
                 if input_is_updating {
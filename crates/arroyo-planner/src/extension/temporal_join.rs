@@ -0,0 +1,160 @@
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::{self, TemporalJoinOperator};
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD};
+use datafusion::common::{internal_err, plan_err, DFSchemaRef, JoinType, Result};
+use datafusion::logical_expr::{Expr, LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
+use crate::{fields_with_qualifiers, schema_from_df_fields};
+
+pub(crate) const TEMPORAL_JOIN_NAME: &str = "TemporalJoin";
+
+/// Joins each row on the left with the version of the row with the same key on the right that
+/// was valid as of the left row's event time. The left side is append-only, while the right side
+/// may be updating; the output has the columns of both sides (less the right's timestamp and
+/// updating metadata) and the left side's timestamp.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct TemporalJoinExtension {
+    pub(crate) left: LogicalPlan,
+    pub(crate) right: LogicalPlan,
+    pub(crate) schema: DFSchemaRef,
+    pub(crate) join_type: JoinType,
+    /// how long a version is kept once it's no longer updated
+    pub(crate) ttl: Duration,
+}
+
+impl TemporalJoinExtension {
+    pub fn new(
+        left: LogicalPlan,
+        right: LogicalPlan,
+        join_type: JoinType,
+        ttl: Duration,
+    ) -> Result<Self> {
+        let mut left_fields = fields_with_qualifiers(left.schema());
+        let Some(timestamp_index) = left_fields.iter().position(|f| f.name() == TIMESTAMP_FIELD)
+        else {
+            return internal_err!("left side of a temporal join must have a timestamp field");
+        };
+        let timestamp = left_fields.remove(timestamp_index);
+
+        let output_fields: Vec<_> = left_fields
+            .into_iter()
+            .chain(
+                fields_with_qualifiers(right.schema())
+                    .into_iter()
+                    .filter(|f| f.name() != TIMESTAMP_FIELD && f.name() != UPDATING_META_FIELD)
+                    .map(|f| {
+                        let nullable = f.is_nullable() || join_type == JoinType::Left;
+                        f.with_nullable(nullable)
+                    }),
+            )
+            .chain(std::iter::once(timestamp))
+            .collect();
+
+        Ok(Self {
+            schema: Arc::new(schema_from_df_fields(&output_fields)?),
+            left,
+            right,
+            join_type,
+            ttl,
+        })
+    }
+}
+
+impl UserDefinedLogicalNodeCore for TemporalJoinExtension {
+    fn name(&self) -> &str {
+        TEMPORAL_JOIN_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.left, &self.right]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "TemporalJoinExtension: {}", self.schema)
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        if inputs.len() != 2 {
+            return internal_err!("input size inconsistent");
+        }
+
+        Self::new(
+            inputs[0].clone(),
+            inputs[1].clone(),
+            self.join_type,
+            self.ttl,
+        )
+    }
+}
+
+impl ArroyoExtension for TemporalJoinExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        _planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 2 {
+            return plan_err!("temporal join should have exactly two inputs");
+        }
+        let left_schema = input_schemas[0].clone();
+        let right_schema = input_schemas[1].clone();
+
+        let join_type = match self.join_type {
+            JoinType::Inner => api::JoinType::Inner,
+            JoinType::Left => api::JoinType::Left,
+            t => return plan_err!("unsupported join type for temporal join: {}", t),
+        };
+
+        let config = TemporalJoinOperator {
+            name: format!("temporal_join_{}", index),
+            left_schema: Some(left_schema.as_ref().clone().into()),
+            right_schema: Some(right_schema.as_ref().clone().into()),
+            output_schema: Some(self.output_schema().into()),
+            join_type: join_type as i32,
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+
+        let node = LogicalNode {
+            operator_id: format!("temporal_join_{}", index),
+            description: "temporal_join".to_string(),
+            operator_name: OperatorName::TemporalJoin,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+
+        let left_edge =
+            LogicalEdge::project_all(LogicalEdgeType::LeftJoin, left_schema.as_ref().clone());
+        let right_edge =
+            LogicalEdge::project_all(LogicalEdgeType::RightJoin, right_schema.as_ref().clone());
+
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![left_edge, right_edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(Arc::new(self.schema.as_ref().into())).unwrap()
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::api::{TopNOperator, TopNOrdering};
+use arroyo_rpc::{updating_meta_field, TIMESTAMP_FIELD};
+use datafusion::common::{internal_err, plan_err, DFSchemaRef, Result};
+use datafusion::logical_expr::{expr, Expr, LogicalPlan, UserDefinedLogicalNodeCore};
+use datafusion_proto::physical_plan::to_proto::serialize_physical_expr;
+use datafusion_proto::physical_plan::DefaultPhysicalExtensionCodec;
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+use crate::extension::{ArroyoExtension, NodeWithIncomingEdges};
+use crate::{fields_with_qualifiers, schema_from_df_fields_with_metadata, DFField};
+
+pub(crate) const TOP_N_EXTENSION_NAME: &str = "TopNExtension";
+
+/// Computes the first rows of each key of an unwindowed input by an ordering, as for
+/// `ROW_NUMBER() OVER (PARTITION BY .. ORDER BY ..)` filtered to the first N row numbers. As new
+/// rows displace others the row numbers change, so the output is updating.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct TopNExtension {
+    pub(crate) input: LogicalPlan,
+    /// the field holding each row's row number
+    pub(crate) row_number: DFField,
+    pub(crate) order_by: Vec<Expr>,
+    /// how many rows are kept for each key, which is set from a filter on the row number
+    pub(crate) limit: Option<usize>,
+    pub(crate) ttl: Duration,
+    pub(crate) schema: DFSchemaRef,
+}
+
+impl TopNExtension {
+    pub fn new(
+        input: LogicalPlan,
+        row_number: DFField,
+        order_by: Vec<Expr>,
+        limit: Option<usize>,
+        ttl: Duration,
+    ) -> Result<Self> {
+        let timestamp_qualifier = input
+            .schema()
+            .qualified_field_with_unqualified_name(TIMESTAMP_FIELD)?
+            .0
+            .cloned();
+
+        let mut fields = fields_with_qualifiers(input.schema());
+        fields.push(row_number.clone());
+        fields.push((timestamp_qualifier, updating_meta_field()).into());
+
+        let schema = Arc::new(schema_from_df_fields_with_metadata(
+            &fields,
+            input.schema().metadata().clone(),
+        )?);
+
+        Ok(Self {
+            input,
+            row_number,
+            order_by,
+            limit,
+            ttl,
+            schema,
+        })
+    }
+
+    pub fn with_limit(&self, limit: usize) -> Self {
+        Self {
+            limit: Some(self.limit.map_or(limit, |l| l.min(limit))),
+            ..self.clone()
+        }
+    }
+}
+
+impl UserDefinedLogicalNodeCore for TopNExtension {
+    fn name(&self) -> &str {
+        TOP_N_EXTENSION_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "TopNExtension({:?}): {}", self.limit, self.schema)
+    }
+
+    fn with_exprs_and_inputs(&self, _exprs: Vec<Expr>, inputs: Vec<LogicalPlan>) -> Result<Self> {
+        if inputs.len() != 1 {
+            return internal_err!("input size inconsistent");
+        }
+
+        Self::new(
+            inputs[0].clone(),
+            self.row_number.clone(),
+            self.order_by.clone(),
+            self.limit,
+            self.ttl,
+        )
+    }
+}
+
+impl ArroyoExtension for TopNExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        planner: &Planner,
+        index: usize,
+        input_schemas: Vec<ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            return plan_err!("TopNExtension requires exactly one input");
+        }
+        let input_schema = input_schemas[0].clone();
+
+        let Some(limit) = self.limit else {
+            return plan_err!(
+                "Window functions require already windowed input, unless they're a ROW_NUMBER() \
+                that's filtered to the first rows of each partition (e.g., WHERE row_num <= 10)"
+            );
+        };
+
+        let order_by = self
+            .order_by
+            .iter()
+            .map(|e| {
+                let Expr::Sort(expr::Sort {
+                    expr,
+                    asc,
+                    nulls_first,
+                }) = e
+                else {
+                    return plan_err!("expected sort expression, found {}", e);
+                };
+
+                let p = planner.create_physical_expr(expr, self.input.schema())?;
+                Ok(TopNOrdering {
+                    expr: serialize_physical_expr(p, &DefaultPhysicalExtensionCodec {})?
+                        .encode_to_vec(),
+                    descending: !asc,
+                    nulls_first: *nulls_first,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let config = TopNOperator {
+            name: format!("top_n_{}", index),
+            input_schema: Some(input_schema.as_ref().clone().into()),
+            output_schema: Some(self.output_schema().into()),
+            order_by,
+            limit: limit as u64,
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+
+        let node = LogicalNode {
+            operator_id: format!("top_n_{}", index),
+            description: format!("TopN<{}>", limit),
+            operator_name: OperatorName::TopN,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Shuffle, (*input_schema).clone());
+
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(Arc::new(self.schema.as_ref().into())).unwrap()
+    }
+}
@@ -30,8 +30,10 @@ use datafusion::physical_plan::functions::make_scalar_function;
 
 use datafusion::prelude::{create_udf, SessionConfig};
 
-use datafusion::sql::sqlparser::dialect::PostgreSqlDialect;
-use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::sqlparser::dialect::{Dialect, PostgreSqlDialect};
+use datafusion::sql::sqlparser::keywords::{Keyword, RESERVED_FOR_TABLE_ALIAS};
+use datafusion::sql::sqlparser::parser::{Parser, ParserError};
+use datafusion::sql::sqlparser::tokenizer::{Token, TokenWithLocation, Tokenizer};
 use datafusion::sql::{planner::ContextProvider, sqlparser, TableReference};
 
 use datafusion::logical_expr::expr::ScalarFunction;
@@ -44,13 +46,14 @@ use datafusion::logical_expr::{AggregateUDF, TableSource};
 use logical::LogicalBatchInput;
 
 use schemas::window_arrow_struct;
-use tables::{Insert, Table};
+use tables::{ConnectorTable, Insert, Table};
 
 use crate::builder::PlanToGraphVisitor;
+use crate::extension::dead_letter::sources_only_read_for_dead_letters;
 use crate::extension::sink::SinkExtension;
 use crate::plan::ArroyoRewriter;
 use arroyo_datastream::logical::{DylibUdfConfig, ProgramConfig, PythonUdfConfig};
-use arroyo_rpc::api_types::connections::ConnectionProfile;
+use arroyo_rpc::api_types::connections::{ConnectionProfile, ConnectionType};
 use datafusion::common::DataFusionError;
 use std::collections::HashSet;
 use std::fmt::Debug;
@@ -74,7 +77,7 @@ use datafusion::logical_expr;
 use datafusion::logical_expr::expr_rewriter::FunctionRewrite;
 use datafusion::logical_expr::planner::ExprPlanner;
 use datafusion::optimizer::Analyzer;
-use datafusion::sql::sqlparser::ast::{OneOrManyWithParens, Statement};
+use datafusion::sql::sqlparser::ast::{Ident, OneOrManyWithParens, Statement};
 use std::time::{Duration, SystemTime};
 use std::{collections::HashMap, sync::Arc};
 use syn::Item;
@@ -93,12 +96,19 @@ pub struct CompiledSql {
 #[derive(Clone)]
 pub struct PlanningOptions {
     ttl: Duration,
+    /// how long deduplication remembers keys, if it's been set separately from the ttl
+    deduplicate_ttl: Option<Duration>,
+    /// tables queried with `FOR SYSTEM_TIME AS OF`, by the name the query refers to them by,
+    /// along with the column whose time they're queried as of
+    versioned_tables: HashMap<String, Column>,
 }
 
 impl Default for PlanningOptions {
     fn default() -> Self {
         Self {
             ttl: Duration::from_secs(24 * 60 * 60),
+            deduplicate_ttl: None,
+            versioned_tables: HashMap::new(),
         }
     }
 }
@@ -218,19 +228,52 @@ impl ArroyoSchemaProvider {
     }
 
     pub fn add_connector_table(&mut self, connection: Connection) {
-        self.tables.insert(
-            UniCase::new(connection.name.clone()),
-            Table::ConnectorTable(connection.into()),
-        );
+        let table: ConnectorTable = connection.into();
+        if let Err(e) = self.insert_table(match table.connection_type {
+            ConnectionType::Lookup => Table::LookupTable(table),
+            _ => Table::ConnectorTable(table),
+        }) {
+            warn!("skipping connection table: {}", e);
+        }
     }
 
     pub fn add_connection_profile(&mut self, profile: ConnectionProfile) {
         self.profiles.insert(profile.name.clone(), profile);
     }
 
-    fn insert_table(&mut self, table: Table) {
+    /// Adds a table, along with its dead-letter table if it has one. Fails if the dead-letter
+    /// table's generated name would shadow another table, or the other way around.
+    fn insert_table(&mut self, table: Table) -> Result<()> {
+        if let Some(Table::DeadLetterTable { source, .. }) = self.get_table(table.name()) {
+            return plan_err!(
+                "table '{}' has the same name as the dead-letter table of '{}'; rename one of them",
+                table.name(),
+                source
+            );
+        }
+
+        if let Some(dead_letter_table) = table.dead_letter_table() {
+            let replacing_own = matches!(
+                self.get_table(dead_letter_table.name()),
+                Some(Table::DeadLetterTable { source, .. }) if source.eq_ignore_ascii_case(table.name())
+            );
+            if self.get_table(dead_letter_table.name()).is_some() && !replacing_own {
+                return plan_err!(
+                    "the dead-letter table of '{}' would be named '{}', but a table with that name \
+                    already exists; rename one of them",
+                    table.name(),
+                    dead_letter_table.name()
+                );
+            }
+
+            self.tables.insert(
+                UniCase::new(dead_letter_table.name().to_string()),
+                dead_letter_table,
+            );
+        }
         self.tables
             .insert(UniCase::new(table.name().to_string()), table);
+        Ok(())
     }
 
     pub fn get_table(&self, table_name: impl Into<String>) -> Option<&Table> {
@@ -614,41 +657,139 @@ fn try_handle_set_variable(
             return plan_err!("invalid syntax for `SET` call");
         };
 
-        if opt.to_string() != "updating_ttl" {
+        let opt = opt.to_string();
+        if opt != "updating_ttl" && opt != "deduplicate_ttl" {
             return plan_err!(
-                "invalid option '{}'; supported options are 'updating_ttl'",
+                "invalid option '{}'; supported options are 'updating_ttl' and 'deduplicate_ttl'",
                 opt
             );
         }
 
         if value.len() != 1 {
-            return plan_err!("invalid `SET updating_ttl` call; expected exactly one expression");
+            return plan_err!(
+                "invalid `SET {}` call; expected exactly one expression",
+                opt
+            );
         }
 
         let sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(s)) =
             value.first().unwrap()
         else {
             return plan_err!(
-                "invalid `SET updating_ttl`; expected a singly-quoted string argument"
+                "invalid `SET {}`; expected a singly-quoted string argument",
+                opt
             );
         };
 
         let interval = parse_interval_day_time(s).map_err(|_| {
             DataFusionError::Plan(format!(
-                "could not parse '{}' as an interval in `SET updating_ttl` statement",
-                s
+                "could not parse '{}' as an interval in `SET {}` statement",
+                s, opt
             ))
         })?;
 
-        schema_provider.planning_options.ttl =
-            Duration::from_secs(interval.days as u64 * 24 * 60 * 60)
-                + Duration::from_millis(interval.milliseconds as u64);
+        let ttl = Duration::from_secs(interval.days as u64 * 24 * 60 * 60)
+            + Duration::from_millis(interval.milliseconds as u64);
+        if opt == "updating_ttl" {
+            schema_provider.planning_options.ttl = ttl;
+        } else {
+            schema_provider.planning_options.deduplicate_ttl = Some(ttl);
+        }
         return Ok(true);
     }
 
     Ok(false)
 }
 
+/// Parses the statements of a query, handling `FOR SYSTEM_TIME AS OF` clauses on tables (which
+/// the Postgres dialect doesn't support) by removing them before parsing and recording the time
+/// column each table is versioned by, so that its join can be planned as a temporal join
+fn parse_statements(
+    dialect: &dyn Dialect,
+    query: &str,
+    schema_provider: &mut ArroyoSchemaProvider,
+) -> Result<Vec<Statement>> {
+    let tokens = Tokenizer::new(dialect, query)
+        .tokenize_with_location()
+        .map_err(ParserError::from)?;
+
+    let is_whitespace = |token: &TokenWithLocation| matches!(token.token, Token::Whitespace(_));
+    let is_keyword = |token: &TokenWithLocation, keyword: Keyword| matches!(&token.token, Token::Word(w) if w.keyword == keyword);
+    let normalize = |ident: &Ident| match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    };
+
+    let mut output: Vec<TokenWithLocation> = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let clause: Vec<_> = tokens[i..]
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| !is_whitespace(t))
+            .take(4)
+            .collect();
+
+        let is_clause = clause.len() == 4
+            && clause
+                .iter()
+                .zip([Keyword::FOR, Keyword::SYSTEM_TIME, Keyword::AS, Keyword::OF])
+                .all(|((_, t), keyword)| is_keyword(t, keyword));
+
+        if !is_clause {
+            output.push(tokens[i].clone());
+            i += 1;
+            continue;
+        }
+
+        // the clause applies to the table named immediately before it
+        let Some(Token::Word(table)) = output
+            .iter()
+            .rev()
+            .find(|t| !is_whitespace(t))
+            .map(|t| &t.token)
+        else {
+            return plan_err!("FOR SYSTEM_TIME AS OF must follow a table name");
+        };
+        let table = Ident {
+            value: table.value.clone(),
+            quote_style: table.quote_style,
+        };
+
+        let start = i + clause[3].0 + 1;
+        let mut parser = Parser::new(dialect).with_tokens_with_locations(tokens[start..].to_vec());
+        let time = match parser.parse_expr()? {
+            sqlparser::ast::Expr::Identifier(ident) => Column::new_unqualified(normalize(&ident)),
+            sqlparser::ast::Expr::CompoundIdentifier(idents) if idents.len() == 2 => Column::new(
+                Some(TableReference::bare(normalize(&idents[0]))),
+                normalize(&idents[1]),
+            ),
+            expr => {
+                return plan_err!(
+                    "FOR SYSTEM_TIME AS OF must refer to the time column of the other side of the join, not '{}'",
+                    expr
+                )
+            }
+        };
+        let end = start + parser.index();
+
+        let name = parser
+            .parse_optional_table_alias(RESERVED_FOR_TABLE_ALIAS)?
+            .map(|alias| alias.name)
+            .unwrap_or(table);
+
+        schema_provider
+            .planning_options
+            .versioned_tables
+            .insert(normalize(&name), time);
+        i = end;
+    }
+
+    Ok(Parser::new(dialect)
+        .with_tokens_with_locations(output)
+        .parse_statements()?)
+}
+
 pub async fn parse_and_get_arrow_program(
     query: String,
     mut schema_provider: ArroyoSchemaProvider,
@@ -669,7 +810,7 @@ pub async fn parse_and_get_arrow_program(
         .with_physical_optimizer_rules(vec![]);
 
     let mut inserts = vec![];
-    for statement in Parser::parse_sql(&dialect, &query)? {
+    for statement in parse_statements(&dialect, &query, &mut schema_provider)? {
         if try_handle_set_variable(&statement, &mut schema_provider)? {
             continue;
         }
@@ -677,7 +818,7 @@ pub async fn parse_and_get_arrow_program(
         if let Some(table) =
             Table::try_from_statement(&statement, &schema_provider, &session_state)?
         {
-            schema_provider.insert_table(table);
+            schema_provider.insert_table(table)?;
         } else {
             inserts.push(Insert::try_from_statement(
                 &statement,
@@ -749,6 +890,12 @@ pub async fn parse_and_get_arrow_program(
                     Table::PreviewSink { .. } => {
                         plan_err!("queries shouldn't be able insert into preview sink.")
                     }
+                    Table::DeadLetterTable { .. } => {
+                        plan_err!("can't insert into a dead-letter table")
+                    }
+                    Table::LookupTable(_) => {
+                        plan_err!("can't insert into a lookup table")
+                    }
                 }
             }
             None => SinkExtension::new(
@@ -764,6 +911,20 @@ pub async fn parse_and_get_arrow_program(
             node: Arc::new(sink?),
         }));
     }
+    // sources take their output schema from their regular consumers, so a source that's only
+    // read through its dead-letter table has its output discarded by a blackhole sink
+    for (name, source) in sources_only_read_for_dead_letters(&extensions)? {
+        let name = format!("{}_output", name.table());
+        extensions.push(LogicalPlan::Extension(Extension {
+            node: Arc::new(SinkExtension::new(
+                TableReference::bare(name.clone()),
+                Table::ConnectorTable(ConnectorTable::blackhole_sink(&name)?),
+                source.schema().clone(),
+                Arc::new(source),
+            )?),
+        }));
+    }
+
     let mut plan_to_graph_visitor = PlanToGraphVisitor::new(&schema_provider, &session_state);
     for extension in extensions {
         plan_to_graph_visitor.add_plan(extension)?;
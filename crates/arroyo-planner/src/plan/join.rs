@@ -1,18 +1,25 @@
-use crate::extension::join::JoinExtension;
-use crate::extension::key_calculation::KeyCalculationExtension;
-use crate::plan::WindowDetectingVisitor;
-use crate::{fields_with_qualifiers, schema_from_df_fields_with_metadata, ArroyoSchemaProvider};
+use crate::extension::join::{JoinExtension, JoinInterval, UpdatingOuterJoin};
+use crate::extension::lookup::{LookupJoinExtension, LookupSource, LOOKUP_SOURCE_NAME};
+use crate::extension::remote_table::RemoteTableExtension;
+use crate::extension::temporal_join::TemporalJoinExtension;
+use crate::extension::ArroyoExtension;
+use crate::plan::{create_key_plan, WindowDetectingVisitor};
+use crate::{
+    fields_with_qualifiers, get_duration, schema_from_df_fields_with_metadata, ArroyoSchemaProvider,
+};
 use arroyo_datastream::WindowType;
-use arroyo_rpc::UPDATING_META_FIELD;
-use datafusion::common::tree_node::{Transformed, TreeNodeRewriter};
+use arroyo_rpc::{updating_meta_field, TIMESTAMP_FIELD, UPDATING_META_FIELD};
+use datafusion::common::tree_node::{Transformed, TreeNode, TreeNodeRewriter};
 use datafusion::common::{
     not_impl_err, plan_err, Column, DataFusionError, JoinConstraint, JoinType, Result, ScalarValue,
     TableReference,
 };
 use datafusion::logical_expr;
-use datafusion::logical_expr::expr::Alias;
+use datafusion::logical_expr::expr::{Alias, Between};
+use datafusion::logical_expr::utils::{conjunction, split_conjunction};
 use datafusion::logical_expr::{
-    build_join_schema, BinaryExpr, Case, Expr, Extension, Join, LogicalPlan, Projection,
+    build_join_schema, BinaryExpr, Case, Expr, Extension, Filter, Join, LogicalPlan, Operator,
+    Projection,
 };
 use datafusion::prelude::coalesce;
 use std::sync::Arc;
@@ -26,15 +33,7 @@ impl<'a> JoinRewriter<'a> {
         let left_window = WindowDetectingVisitor::get_window(&join.left)?;
         let right_window = WindowDetectingVisitor::get_window(&join.right)?;
         match (left_window, right_window) {
-            (None, None) => {
-                if join.join_type == JoinType::Inner {
-                    Ok(false)
-                } else {
-                    Err(DataFusionError::NotImplemented(
-                        "can't handle non-inner joins without windows".into(),
-                    ))
-                }
-            }
+            (None, None) => Ok(false),
             (None, Some(_)) => Err(DataFusionError::NotImplemented(
                 "can't handle mixed windowing between left (non-windowed) and right (windowed)."
                     .into(),
@@ -61,6 +60,490 @@ impl<'a> JoinRewriter<'a> {
         }
     }
 
+    fn contains_lookup_source(plan: &LogicalPlan) -> bool {
+        match plan {
+            LogicalPlan::Extension(Extension { node }) => node.name() == LOOKUP_SOURCE_NAME,
+            _ => plan.inputs().into_iter().any(Self::contains_lookup_source),
+        }
+    }
+
+    /// Finds the lookup source on the right side of a lookup join, along with the qualifier
+    /// the query uses to refer to it and any filters applied to it. Only filters, column-only
+    /// projections and aliases are allowed between the join and the lookup table.
+    fn find_lookup_source(
+        plan: &LogicalPlan,
+    ) -> Result<(&LookupSource, Option<TableReference>, Vec<Expr>)> {
+        match plan {
+            LogicalPlan::Extension(Extension { node }) if node.name() == LOOKUP_SOURCE_NAME => {
+                let source = node.as_any().downcast_ref::<LookupSource>().unwrap();
+                Ok((source, None, vec![]))
+            }
+            LogicalPlan::SubqueryAlias(alias) => {
+                let (source, qualifier, filters) = Self::find_lookup_source(&alias.input)?;
+                Ok((
+                    source,
+                    qualifier.or_else(|| Some(alias.alias.clone())),
+                    filters,
+                ))
+            }
+            LogicalPlan::Filter(filter) => {
+                let (source, qualifier, mut filters) = Self::find_lookup_source(&filter.input)?;
+                filters.push(filter.predicate.clone());
+                Ok((source, qualifier, filters))
+            }
+            LogicalPlan::Projection(projection)
+                if projection
+                    .expr
+                    .iter()
+                    .all(|e| matches!(e, Expr::Column(_))) =>
+            {
+                Self::find_lookup_source(&projection.input)
+            }
+            _ => plan_err!(
+                "the right side of a lookup join may only filter and select columns from the lookup table"
+            ),
+        }
+    }
+
+    /// If the right side of this join is a lookup table, plans it as a lookup join, which
+    /// queries the table's connector for each incoming row rather than reading the table as
+    /// a stream
+    fn maybe_plan_lookup_join(&self, join: &Join) -> Result<Option<LogicalPlan>> {
+        if Self::contains_lookup_source(&join.left) {
+            return plan_err!("lookup tables can only be used on the right side of a JOIN");
+        }
+
+        if !Self::contains_lookup_source(&join.right) {
+            return Ok(None);
+        }
+
+        if !matches!(join.join_type, JoinType::Inner | JoinType::Left) {
+            return plan_err!(
+                "lookup joins must be INNER or LEFT joins, not {}",
+                join.join_type
+            );
+        }
+
+        if join.filter.is_some() {
+            return plan_err!(
+                "lookup joins only support equality conditions on the lookup table's primary key"
+            );
+        }
+
+        if join.on.is_empty() {
+            return plan_err!(
+                "lookup joins must include an equijoin condition on the lookup table's primary key"
+            );
+        }
+
+        if join
+            .left
+            .schema()
+            .has_column_with_unqualified_name(UPDATING_META_FIELD)
+        {
+            return plan_err!("can't handle updating left side of lookup join");
+        }
+
+        let (source, qualifier, filters) = Self::find_lookup_source(&join.right)?;
+        let table = &source.table;
+
+        let primary_keys: Vec<_> = table.primary_keys.iter().collect();
+        let on = join
+            .on
+            .iter()
+            .map(|(left, right)| {
+                let Expr::Column(right) = right else {
+                    return plan_err!(
+                        "the right side of a lookup join condition must be a column of the lookup table, not {}",
+                        right
+                    );
+                };
+                if !primary_keys.contains(&&right.name) {
+                    return plan_err!(
+                        "lookup joins must be on the lookup table's primary key, but '{}' is not part of the primary key of '{}'",
+                        right.name,
+                        table.name
+                    );
+                }
+                Ok((left.clone(), right.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if on.len() != primary_keys.len() {
+            return plan_err!(
+                "lookup joins must specify every column of the primary key of '{}'",
+                table.name
+            );
+        }
+
+        if !filters.is_empty() && join.join_type == JoinType::Left {
+            return plan_err!("filters on the lookup table are not supported in LEFT lookup joins; apply them after the join");
+        }
+
+        let lookup_fields: Vec<_> = fields_with_qualifiers(join.right.schema())
+            .into_iter()
+            .filter(|f| f.name() != TIMESTAMP_FIELD)
+            .collect();
+
+        let mut input = join.left.clone();
+        let needs_remote = match input.as_ref() {
+            LogicalPlan::Extension(Extension { node }) => {
+                let extension: &dyn ArroyoExtension = node.try_into()?;
+                extension.transparent()
+            }
+            _ => true,
+        };
+        if needs_remote {
+            input = Arc::new(LogicalPlan::Extension(Extension {
+                node: Arc::new(RemoteTableExtension {
+                    input: input.as_ref().clone(),
+                    name: TableReference::bare("lookup join input"),
+                    schema: input.schema().clone(),
+                    materialize: false,
+                }),
+            }));
+        }
+
+        let mut plan = LogicalPlan::Extension(Extension {
+            node: Arc::new(LookupJoinExtension::new(
+                input.as_ref().clone(),
+                table.clone(),
+                lookup_fields,
+                on,
+                join.join_type,
+            )?),
+        });
+
+        // filters on the lookup table are applied to the joined rows
+        if let Some(predicate) = conjunction(filters) {
+            let predicate = match qualifier {
+                Some(qualifier) => {
+                    predicate
+                        .transform_up(|e| {
+                            Ok(match e {
+                                Expr::Column(c) => Transformed::yes(Expr::Column(Column {
+                                    relation: Some(qualifier.clone()),
+                                    name: c.name,
+                                })),
+                                e => Transformed::no(e),
+                            })
+                        })?
+                        .data
+                }
+                None => predicate,
+            };
+            plan = LogicalPlan::Filter(Filter::try_new(predicate, Arc::new(plan))?);
+        }
+
+        Ok(Some(plan))
+    }
+
+    /// Finds the name of the column that the plan's `_timestamp` is derived from (i.e., the
+    /// event time field of its source), if it's still available in the plan's output
+    fn event_time_field(plan: &LogicalPlan) -> Option<String> {
+        match plan {
+            LogicalPlan::Projection(projection) => {
+                let passes_through = |name: &str| {
+                    projection
+                        .expr
+                        .iter()
+                        .any(|e| matches!(e, Expr::Column(c) if c.name == name))
+                };
+
+                let name = projection.expr.iter().find_map(|e| match e {
+                    Expr::Alias(Alias { expr, name, .. }) if name == TIMESTAMP_FIELD => {
+                        match expr.as_ref() {
+                            Expr::Column(c) => Some(c.name.clone()),
+                            _ => None,
+                        }
+                    }
+                    Expr::Column(c) if c.name == TIMESTAMP_FIELD => {
+                        Self::event_time_field(&projection.input)
+                    }
+                    _ => None,
+                })?;
+
+                passes_through(&name).then_some(name)
+            }
+            LogicalPlan::Filter(filter) => Self::event_time_field(&filter.input),
+            LogicalPlan::SubqueryAlias(alias) => Self::event_time_field(&alias.input),
+            LogicalPlan::Extension(Extension { node }) if node.inputs().len() == 1 => {
+                let input = node.inputs()[0];
+                let same_fields = node.schema().fields().iter().map(|f| f.name()).eq(input
+                    .schema()
+                    .fields()
+                    .iter()
+                    .map(|f| f.name()));
+
+                if same_fields {
+                    Self::event_time_field(input)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses an expression of the form `time_column [(+|-) INTERVAL]` referring to one side of
+    /// the join, returning whether it's on the left side and its offset in microseconds
+    fn time_term(
+        expr: &Expr,
+        join: &Join,
+        event_time_fields: &(Option<String>, Option<String>),
+    ) -> Option<(bool, i64)> {
+        let (column, offset) = match expr {
+            Expr::Column(c) => (c, 0),
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                let Expr::Column(c) = left.as_ref() else {
+                    return None;
+                };
+                let offset = get_duration(right).ok()?.as_micros() as i64;
+                match op {
+                    Operator::Plus => (c, offset),
+                    Operator::Minus => (c, -offset),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        let is_time_column = |field: &Option<String>| {
+            column.name == TIMESTAMP_FIELD || field.as_ref() == Some(&column.name)
+        };
+
+        if join.left.schema().has_column(column) && is_time_column(&event_time_fields.0) {
+            Some((true, offset))
+        } else if join.right.schema().has_column(column) && is_time_column(&event_time_fields.1) {
+            Some((false, offset))
+        } else {
+            None
+        }
+    }
+
+    /// Determines whether this is an interval join, whose condition bounds the right side's
+    /// event time relative to the left's (for example,
+    /// `b.ts BETWEEN a.ts - INTERVAL '5' MINUTE AND a.ts + INTERVAL '10' MINUTE`). If so,
+    /// returns the bounds (in microseconds) on the right timestamp minus the left timestamp.
+    fn interval_bounds(join: &Join) -> Result<Option<(i64, i64)>> {
+        let Some(filter) = join.filter.as_ref() else {
+            return Ok(None);
+        };
+        let event_time_fields = (
+            Self::event_time_field(&join.left),
+            Self::event_time_field(&join.right),
+        );
+
+        let mut comparisons = vec![];
+        for expr in split_conjunction(filter) {
+            match expr {
+                Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                    comparisons.push((left.as_ref(), *op, right.as_ref()));
+                }
+                Expr::Between(Between {
+                    expr,
+                    negated: false,
+                    low,
+                    high,
+                }) => {
+                    comparisons.push((expr.as_ref(), Operator::GtEq, low.as_ref()));
+                    comparisons.push((expr.as_ref(), Operator::LtEq, high.as_ref()));
+                }
+                _ => {}
+            }
+        }
+
+        let mut lower: Option<i64> = None;
+        let mut upper: Option<i64> = None;
+        for (left, op, right) in comparisons {
+            if !matches!(
+                op,
+                Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq
+            ) {
+                continue;
+            }
+
+            let (Some((left_is_left, left_offset)), Some((right_is_left, right_offset))) = (
+                Self::time_term(left, join, &event_time_fields),
+                Self::time_term(right, join, &event_time_fields),
+            ) else {
+                continue;
+            };
+
+            // normalize to a bound on (right time - left time)
+            let (op, bound) = match (left_is_left, right_is_left) {
+                (false, true) => (op, right_offset - left_offset),
+                (true, false) => (op.swap().unwrap(), left_offset - right_offset),
+                _ => continue,
+            };
+
+            match op {
+                Operator::Gt | Operator::GtEq => {
+                    lower = Some(lower.map_or(bound, |l| l.max(bound)));
+                }
+                _ => {
+                    upper = Some(upper.map_or(bound, |u| u.min(bound)));
+                }
+            }
+        }
+
+        match lower.zip(upper) {
+            Some((lower, upper)) if lower > upper => plan_err!(
+                "interval join's lower bound is greater than its upper bound, so no rows can match \
+                (the right side's event time must be at least {}µs and at most {}µs after the left's)",
+                lower,
+                upper
+            ),
+            bounds => Ok(bounds),
+        }
+    }
+
+    /// For outer joins, filters the join output to the rows that were matched on the
+    /// left and/or right sides
+    fn filter_matched(
+        rewritten_join: LogicalPlan,
+        left_matched: bool,
+        right_matched: bool,
+    ) -> Result<LogicalPlan> {
+        let timestamps: Vec<_> = fields_with_qualifiers(rewritten_join.schema())
+            .into_iter()
+            .filter(|f| f.name() == TIMESTAMP_FIELD)
+            .map(|f| Expr::Column(f.qualified_column()))
+            .collect();
+
+        let [left, right] = &timestamps[..] else {
+            return not_impl_err!("join must have two timestamp fields");
+        };
+
+        let matched = |expr: &Expr, matched: bool| {
+            if matched {
+                expr.clone().is_not_null()
+            } else {
+                expr.clone().is_null()
+            }
+        };
+
+        Ok(LogicalPlan::Filter(Filter::try_new(
+            matched(left, left_matched).and(matched(right, right_matched)),
+            Arc::new(rewritten_join),
+        )?))
+    }
+
+    /// Splits an outer join into the join producing only matched rows and the joins producing the
+    /// null-padded rows for unmatched rows on the left and right sides, for those that are outer
+    fn outer_join_plans(
+        &mut self,
+        rewritten_join: LogicalPlan,
+        join_type: JoinType,
+    ) -> Result<(LogicalPlan, Option<LogicalPlan>, Option<LogicalPlan>)> {
+        let main = Self::filter_matched(rewritten_join.clone(), true, true)?;
+        let left_unmatched = matches!(join_type, JoinType::Left | JoinType::Full)
+            .then(|| Self::filter_matched(rewritten_join.clone(), true, false))
+            .transpose()?
+            .map(|plan| self.post_join_timestamp_projection(plan))
+            .transpose()?;
+        let right_unmatched = matches!(join_type, JoinType::Right | JoinType::Full)
+            .then(|| Self::filter_matched(rewritten_join, false, true))
+            .transpose()?
+            .map(|plan| self.post_join_timestamp_projection(plan))
+            .transpose()?;
+
+        Ok((
+            self.post_join_timestamp_projection(main)?,
+            left_unmatched,
+            right_unmatched,
+        ))
+    }
+
+    /// The name the query refers to the relation on one side of a join by, which is the
+    /// qualifier of its timestamp
+    fn relation_name(plan: &LogicalPlan) -> Option<String> {
+        plan.schema()
+            .qualified_field_with_unqualified_name(TIMESTAMP_FIELD)
+            .ok()
+            .and_then(|(qualifier, _)| qualifier)
+            .map(|qualifier| qualifier.table().to_string())
+    }
+
+    /// If the right side of this join was queried `FOR SYSTEM_TIME AS OF` the left side's event
+    /// time, plans it as a temporal join, which joins each row on the left against the version of
+    /// the right side that was valid at that row's event time
+    fn maybe_plan_temporal_join(&self, join: &Join) -> Result<Option<LogicalPlan>> {
+        let versioned_tables = &self.schema_provider.planning_options.versioned_tables;
+
+        if Self::relation_name(&join.left).is_some_and(|name| versioned_tables.contains_key(&name))
+        {
+            return plan_err!(
+                "tables queried FOR SYSTEM_TIME AS OF must be on the right side of a JOIN"
+            );
+        }
+
+        let Some(time) =
+            Self::relation_name(&join.right).and_then(|name| versioned_tables.get(&name))
+        else {
+            return Ok(None);
+        };
+
+        let is_time_column = time.name == TIMESTAMP_FIELD
+            || Self::event_time_field(&join.left).as_ref() == Some(&time.name);
+        if !join.left.schema().has_column(time) || !is_time_column {
+            return plan_err!(
+                "FOR SYSTEM_TIME AS OF must refer to the event time of the left side of the join, not '{}'",
+                time
+            );
+        }
+
+        if !matches!(join.join_type, JoinType::Inner | JoinType::Left) {
+            return plan_err!(
+                "temporal joins must be INNER or LEFT joins, not {}",
+                join.join_type
+            );
+        }
+
+        if join.on.is_empty() {
+            return plan_err!(
+                "temporal joins must include an equijoin condition on the versioned table's key"
+            );
+        }
+
+        if join.filter.is_some() {
+            return plan_err!("temporal joins only support equality conditions; apply other conditions after the join");
+        }
+
+        if join.join_constraint != JoinConstraint::On || join.null_equals_null {
+            return not_impl_err!("can't handle join constraint other than ON");
+        }
+
+        if join
+            .left
+            .schema()
+            .has_column_with_unqualified_name(UPDATING_META_FIELD)
+        {
+            return plan_err!("can't handle updating left side of temporal join");
+        }
+
+        if WindowDetectingVisitor::get_window(&join.left)?.is_some()
+            || WindowDetectingVisitor::get_window(&join.right)?.is_some()
+        {
+            return not_impl_err!("can't handle windowed inputs to temporal joins");
+        }
+
+        let (left_expressions, right_expressions): (Vec<_>, Vec<_>) =
+            join.on.iter().cloned().unzip();
+
+        let left = create_key_plan(join.left.clone(), left_expressions, "left")?;
+        let right = create_key_plan(join.right.clone(), right_expressions, "right")?;
+
+        Ok(Some(LogicalPlan::Extension(Extension {
+            node: Arc::new(TemporalJoinExtension::new(
+                left,
+                right,
+                join.join_type,
+                self.schema_provider.planning_options.ttl,
+            )?),
+        })))
+    }
+
     fn check_updating(left: &LogicalPlan, right: &LogicalPlan) -> Result<()> {
         if left
             .schema()
@@ -77,42 +560,6 @@ impl<'a> JoinRewriter<'a> {
         Ok(())
     }
 
-    fn create_join_key_plan(
-        &self,
-        input: Arc<LogicalPlan>,
-        join_expressions: Vec<Expr>,
-        name: &'static str,
-    ) -> Result<LogicalPlan> {
-        let key_count = join_expressions.len();
-
-        let join_expressions: Vec<_> = join_expressions
-            .into_iter()
-            .enumerate()
-            .map(|(index, expr)| {
-                expr.alias_qualified(
-                    Some(TableReference::bare("_arroyo")),
-                    format!("_key_{}", index),
-                )
-            })
-            .chain(
-                fields_with_qualifiers(input.schema())
-                    .iter()
-                    .map(|field| Expr::Column(field.qualified_column())),
-            )
-            .collect();
-
-        // Calculate initial projection with default names
-        let projection = Projection::try_new(join_expressions, input)?;
-        let key_calculation_extension = KeyCalculationExtension::new_named_and_trimmed(
-            LogicalPlan::Projection(projection),
-            (0..key_count).collect(),
-            name.to_string(),
-        );
-        Ok(LogicalPlan::Extension(Extension {
-            node: Arc::new(key_calculation_extension),
-        }))
-    }
-
     fn post_join_timestamp_projection(&mut self, input: LogicalPlan) -> Result<LogicalPlan> {
         let schema = input.schema().clone();
         let mut schema_with_timestamp = fields_with_qualifiers(&schema);
@@ -196,7 +643,18 @@ impl<'a> TreeNodeRewriter for JoinRewriter<'a> {
         let LogicalPlan::Join(join) = node else {
             return Ok(Transformed::no(node));
         };
+
+        if let Some(plan) = self.maybe_plan_lookup_join(&join)? {
+            return Ok(Transformed::yes(plan));
+        }
+
+        if let Some(plan) = self.maybe_plan_temporal_join(&join)? {
+            return Ok(Transformed::yes(plan));
+        }
+
+        let interval = Self::interval_bounds(&join)?;
         let is_instant = Self::check_join_windowing(&join)?;
+        let interval = interval.filter(|_| !is_instant);
 
         let Join {
             left,
@@ -220,8 +678,8 @@ impl<'a> TreeNodeRewriter for JoinRewriter<'a> {
         let (left_expressions, right_expressions): (Vec<_>, Vec<_>) =
             on.clone().into_iter().unzip();
 
-        let left_input = self.create_join_key_plan(left, left_expressions, "left")?;
-        let right_input = self.create_join_key_plan(right, right_expressions, "right")?;
+        let left_input = create_key_plan(left, left_expressions, "left")?;
+        let right_input = create_key_plan(right, right_expressions, "right")?;
         let rewritten_join = LogicalPlan::Join(Join {
             schema: Arc::new(build_join_schema(
                 left_input.schema(),
@@ -237,13 +695,76 @@ impl<'a> TreeNodeRewriter for JoinRewriter<'a> {
             filter,
         });
 
-        let final_logical_plan = self.post_join_timestamp_projection(rewritten_join)?;
+        let (final_logical_plan, interval, updating_outer) = match interval {
+            Some((lower_micros, upper_micros)) if join_type != JoinType::Inner => {
+                // rows that are still unmatched once their interval has passed are emitted by
+                // the unmatched plans
+                let (main, left_unmatched, right_unmatched) =
+                    self.outer_join_plans(rewritten_join, join_type)?;
+
+                (
+                    main,
+                    Some(JoinInterval {
+                        lower_micros,
+                        upper_micros,
+                        join_type,
+                        left_unmatched,
+                        right_unmatched,
+                    }),
+                    None,
+                )
+            }
+            None if !is_instant && join_type != JoinType::Inner => {
+                // without an interval, unmatched rows are emitted immediately and retracted once
+                // they're matched, which makes the output updating
+                let (main, left_unmatched, right_unmatched) =
+                    self.outer_join_plans(rewritten_join, join_type)?;
+
+                let mut fields = fields_with_qualifiers(main.schema());
+                let timestamp_qualifier = main
+                    .schema()
+                    .qualified_field_with_unqualified_name(TIMESTAMP_FIELD)?
+                    .0
+                    .cloned();
+                fields.push((timestamp_qualifier, updating_meta_field()).into());
+                let schema = Arc::new(schema_from_df_fields_with_metadata(
+                    &fields,
+                    main.schema().metadata().clone(),
+                )?);
+
+                (
+                    main,
+                    None,
+                    Some(UpdatingOuterJoin {
+                        join_type,
+                        left_unmatched,
+                        right_unmatched,
+                        schema,
+                    }),
+                )
+            }
+            interval => (
+                self.post_join_timestamp_projection(rewritten_join)?,
+                interval.map(|(lower_micros, upper_micros)| JoinInterval {
+                    lower_micros,
+                    upper_micros,
+                    join_type,
+                    left_unmatched: None,
+                    right_unmatched: None,
+                }),
+                None,
+            ),
+        };
 
         let join_extension = JoinExtension {
             rewritten_join: final_logical_plan,
             is_instant,
-            // only non-instant (updating) joins have a TTL
-            ttl: (!is_instant).then_some(self.schema_provider.planning_options.ttl),
+            // only non-instant (updating) joins have a TTL; interval joins instead expire their
+            // state by watermark
+            ttl: (!is_instant && interval.is_none())
+                .then_some(self.schema_provider.planning_options.ttl),
+            interval,
+            updating_outer,
         };
 
         Ok(Transformed::yes(LogicalPlan::Extension(Extension {
@@ -10,9 +10,10 @@ use std::{collections::HashSet, sync::Arc};
 
 use aggregate::AggregateRewriter;
 use datafusion::logical_expr::{
-    expr::Alias, Aggregate, Expr, Extension, Filter, LogicalPlan, SubqueryAlias,
+    expr::Alias, Aggregate, Expr, Extension, Filter, LogicalPlan, Projection, SubqueryAlias,
 };
 use join::JoinRewriter;
+use top_n::TopNRewriter;
 
 use self::window_fn::WindowFunctionRewriter;
 use crate::rewriters::TimeWindowNullCheckRemover;
@@ -20,6 +21,7 @@ use crate::{
     extension::{
         aggregate::{AggregateExtension, AGGREGATE_EXTENSION_NAME},
         join::JOIN_NODE_NAME,
+        key_calculation::KeyCalculationExtension,
     },
     fields_with_qualifiers, find_window,
     rewriters::SourceRewriter,
@@ -34,6 +36,7 @@ use crate::{
 
 mod aggregate;
 mod join;
+mod top_n;
 mod window_fn;
 
 #[derive(Debug, Default)]
@@ -61,6 +64,43 @@ fn extract_column(expr: &Expr) -> Option<&Column> {
     }
 }
 
+/// Computes the key expressions as `_arroyo._key_N` columns ahead of the input's columns, so that
+/// the output can be shuffled by them
+fn create_key_plan(
+    input: Arc<LogicalPlan>,
+    key_expressions: Vec<Expr>,
+    name: &'static str,
+) -> Result<LogicalPlan> {
+    let key_count = key_expressions.len();
+
+    let key_expressions: Vec<_> = key_expressions
+        .into_iter()
+        .enumerate()
+        .map(|(index, expr)| {
+            expr.alias_qualified(
+                Some(TableReference::bare("_arroyo")),
+                format!("_key_{}", index),
+            )
+        })
+        .chain(
+            fields_with_qualifiers(input.schema())
+                .iter()
+                .map(|field| Expr::Column(field.qualified_column())),
+        )
+        .collect();
+
+    // Calculate initial projection with default names
+    let projection = Projection::try_new(key_expressions, input)?;
+    let key_calculation_extension = KeyCalculationExtension::new_named_and_trimmed(
+        LogicalPlan::Projection(projection),
+        (0..key_count).collect(),
+        name.to_string(),
+    );
+    Ok(LogicalPlan::Extension(Extension {
+        node: Arc::new(key_calculation_extension),
+    }))
+}
+
 impl TreeNodeVisitor<'_> for WindowDetectingVisitor {
     type Node = LogicalPlan;
 
@@ -331,14 +371,29 @@ impl<'a> TreeNodeRewriter for ArroyoRewriter<'a> {
                     .predicate
                     .clone()
                     .rewrite(&mut TimeWindowNullCheckRemover {})?;
-                return Ok(if expr.transformed {
+                let node = if expr.transformed {
                     Transformed::yes(LogicalPlan::Filter(Filter::try_new(expr.data, f.input)?))
                 } else {
                     Transformed::no(LogicalPlan::Filter(f))
+                };
+
+                // a filter on the row number of a top-N sets how many rows it keeps
+                return node.transform_data(|node| {
+                    TopNRewriter {
+                        schema_provider: self.schema_provider,
+                    }
+                    .f_up(node)
                 });
             }
             LogicalPlan::Window(_) => {
-                return WindowFunctionRewriter {}.f_up(node);
+                let node = TopNRewriter {
+                    schema_provider: self.schema_provider,
+                }
+                .f_up(node)?;
+                if node.transformed {
+                    return Ok(node);
+                }
+                return WindowFunctionRewriter {}.f_up(node.data);
             }
             LogicalPlan::Sort(_) => {
                 return plan_err!("ORDER BY is not currently supported ({})", node.display());
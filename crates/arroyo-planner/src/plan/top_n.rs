@@ -0,0 +1,328 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use arroyo_rpc::{TIMESTAMP_FIELD, UPDATING_META_FIELD};
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::tree_node::{Transformed, TreeNodeRewriter};
+use datafusion::common::{plan_err, Column, Result, ScalarValue};
+use datafusion::logical_expr::expr::{Cast, Sort, WindowFunction};
+use datafusion::logical_expr::utils::split_conjunction;
+use datafusion::logical_expr::{
+    BinaryExpr, BuiltInWindowFunction, Expr, Extension, Filter, LogicalPlan, Operator, Projection,
+    SubqueryAlias, UserDefinedLogicalNode, Window, WindowFunctionDefinition,
+};
+
+use crate::extension::deduplicate::DeduplicateExtension;
+use crate::extension::top_n::{TopNExtension, TOP_N_EXTENSION_NAME};
+use crate::plan::{create_key_plan, extract_column, WindowDetectingVisitor};
+use crate::{fields_with_qualifiers, ArroyoSchemaProvider};
+
+/// Plans `ROW_NUMBER()` over unwindowed input that's filtered to the first rows of each partition,
+/// like
+///
+/// ```sql
+/// SELECT * FROM (
+///     SELECT *, ROW_NUMBER() OVER (PARTITION BY k ORDER BY v DESC) as rn FROM t
+/// ) WHERE rn <= 10
+/// ```
+///
+/// as a [`TopNExtension`]. The window is planned first, without a limit, which is then set when
+/// the filter on the row number above it is planned. Keeping only the first row of each partition
+/// by `_timestamp` is planned as a [`DeduplicateExtension`] instead. This is the only way to ask
+/// for deduplication: a dedicated `DEDUPLICATE` hint isn't supported, as the SQL parser has no
+/// syntax for query hints.
+pub(crate) struct TopNRewriter<'a> {
+    pub schema_provider: &'a ArroyoSchemaProvider,
+}
+
+impl<'a> TopNRewriter<'a> {
+    fn plan_row_number(&self, window: &Window) -> Result<Option<LogicalPlan>> {
+        if WindowDetectingVisitor::get_window(&window.input)?.is_some() {
+            return Ok(None);
+        }
+
+        let [window_expr] = window.window_expr.as_slice() else {
+            return Ok(None);
+        };
+
+        let Expr::WindowFunction(WindowFunction {
+            fun,
+            partition_by,
+            order_by,
+            ..
+        }) = window_expr.clone().unalias()
+        else {
+            return Ok(None);
+        };
+
+        if !matches!(
+            fun,
+            WindowFunctionDefinition::BuiltInWindowFunction(BuiltInWindowFunction::RowNumber)
+        ) {
+            return Ok(None);
+        }
+
+        if window
+            .input
+            .schema()
+            .has_column_with_unqualified_name(UPDATING_META_FIELD)
+        {
+            return plan_err!("can't compute ROW_NUMBER() over an updating input");
+        }
+
+        if order_by.is_empty() {
+            return plan_err!("ROW_NUMBER() over unwindowed input requires an ORDER BY");
+        }
+
+        // ordering by the field the event time was computed from is ordering by event time
+        let timestamp = Expr::Column(Column::from(
+            window
+                .input
+                .schema()
+                .qualified_field_with_unqualified_name(TIMESTAMP_FIELD)?,
+        ));
+        let order_by = order_by
+            .into_iter()
+            .map(|e| match e {
+                Expr::Sort(Sort {
+                    expr,
+                    asc,
+                    nulls_first,
+                }) => {
+                    let expr = match *expr {
+                        Expr::Column(c) if is_event_time(&window.input, &c) => timestamp.clone(),
+                        expr => expr,
+                    };
+                    Expr::Sort(Sort::new(Box::new(expr), asc, nulls_first))
+                }
+                e => e,
+            })
+            .collect();
+
+        let Some(row_number) = fields_with_qualifiers(&window.schema).pop() else {
+            return plan_err!("window has no output fields");
+        };
+
+        let input = if partition_by.is_empty() {
+            window.input.as_ref().clone()
+        } else {
+            create_key_plan(window.input.clone(), partition_by, "top_n")?
+        };
+
+        let top_n = TopNExtension::new(
+            input,
+            row_number,
+            order_by,
+            None,
+            self.schema_provider.planning_options.ttl,
+        )?;
+
+        Ok(Some(LogicalPlan::Extension(Extension {
+            node: Arc::new(top_n),
+        })))
+    }
+
+    fn plan_filter(&self, filter: &Filter) -> Result<Option<LogicalPlan>> {
+        for conjunct in split_conjunction(&filter.predicate) {
+            let Some((column, limit)) = row_number_bound(conjunct) else {
+                continue;
+            };
+
+            let options = &self.schema_provider.planning_options;
+            let deduplicate_ttl = options.deduplicate_ttl.unwrap_or(options.ttl);
+            if let Some(input) = set_limit(&filter.input, column, limit, deduplicate_ttl)? {
+                return Ok(Some(LogicalPlan::Filter(Filter::try_new(
+                    filter.predicate.clone(),
+                    Arc::new(input),
+                )?)));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'a> TreeNodeRewriter for TopNRewriter<'a> {
+    type Node = LogicalPlan;
+
+    fn f_up(&mut self, node: Self::Node) -> Result<Transformed<Self::Node>> {
+        let planned = match &node {
+            LogicalPlan::Window(window) => self.plan_row_number(window)?,
+            LogicalPlan::Filter(filter) => self.plan_filter(filter)?,
+            _ => None,
+        };
+
+        Ok(match planned {
+            Some(plan) => Transformed::yes(plan),
+            None => Transformed::no(node),
+        })
+    }
+}
+
+fn row_number_column(expr: &Expr) -> Option<&Column> {
+    match expr {
+        Expr::Cast(Cast { expr, .. }) => row_number_column(expr),
+        e => extract_column(e),
+    }
+}
+
+/// If the predicate limits a column to its first values, as in `rn <= 10`, `rn < 11` or `rn = 1`,
+/// returns the column and the number of values it's limited to
+fn row_number_bound(expr: &Expr) -> Option<(&Column, usize)> {
+    let Expr::BinaryExpr(BinaryExpr { left, op, right }) = expr else {
+        return None;
+    };
+
+    let (column, op, value) = match (left.as_ref(), right.as_ref()) {
+        (e, Expr::Literal(value)) => (row_number_column(e)?, *op, value),
+        (Expr::Literal(value), e) => (row_number_column(e)?, op.swap()?, value),
+        _ => return None,
+    };
+
+    let ScalarValue::Int64(Some(value)) = value.cast_to(&DataType::Int64).ok()? else {
+        return None;
+    };
+
+    let limit = match op {
+        Operator::LtEq => value,
+        Operator::Lt => value - 1,
+        Operator::Eq if value == 1 => 1,
+        _ => return None,
+    };
+
+    (limit > 0).then_some((column, limit as usize))
+}
+
+/// Whether the column is the event time of the plan's rows, either `_timestamp` itself or the field
+/// it was computed from
+fn is_event_time(plan: &LogicalPlan, column: &Column) -> bool {
+    if column.name == TIMESTAMP_FIELD {
+        return true;
+    }
+
+    match plan {
+        LogicalPlan::Projection(projection) => {
+            let Ok(index) = projection.schema.index_of_column(column) else {
+                return false;
+            };
+            let expr = projection.expr[index].clone().unalias();
+
+            if projection
+                .schema
+                .fields()
+                .iter()
+                .position(|f| f.name() == TIMESTAMP_FIELD)
+                .is_some_and(|i| projection.expr[i].clone().unalias() == expr)
+            {
+                return true;
+            }
+
+            match expr {
+                Expr::Column(column) => is_event_time(&projection.input, &column),
+                _ => false,
+            }
+        }
+        LogicalPlan::SubqueryAlias(alias) => {
+            let Ok(index) = alias.schema.index_of_column(column) else {
+                return false;
+            };
+            let column = Column::from(alias.input.schema().qualified_field(index));
+            is_event_time(&alias.input, &column)
+        }
+        LogicalPlan::Filter(filter) => is_event_time(&filter.input, column),
+        _ => false,
+    }
+}
+
+/// The first row of each key by event time is a deduplication, which keeps the first row for each
+/// key or, if the order is descending, the last
+fn keeps_last(top_n: &TopNExtension) -> Option<bool> {
+    if top_n.limit != Some(1) {
+        return None;
+    }
+
+    let [Expr::Sort(Sort { expr, asc, .. })] = top_n.order_by.as_slice() else {
+        return None;
+    };
+    let Expr::Column(column) = expr.as_ref() else {
+        return None;
+    };
+
+    (column.name == TIMESTAMP_FIELD).then_some(!asc)
+}
+
+/// Sets the limit of the top-N whose row number is `column`, rebuilding the plan down to it. If
+/// that makes it a deduplication, it remembers keys for `deduplicate_ttl`.
+fn set_limit(
+    plan: &LogicalPlan,
+    column: &Column,
+    limit: usize,
+    deduplicate_ttl: Duration,
+) -> Result<Option<LogicalPlan>> {
+    match plan {
+        LogicalPlan::Extension(Extension { node }) if node.name() == TOP_N_EXTENSION_NAME => {
+            let top_n = node.as_any().downcast_ref::<TopNExtension>().unwrap();
+            let row_number_index = top_n.schema.fields().len() - 2;
+            if top_n.schema.index_of_column(column).ok() != Some(row_number_index) {
+                return Ok(None);
+            }
+
+            let top_n = top_n.with_limit(limit);
+            let node: Arc<dyn UserDefinedLogicalNode> = match keeps_last(&top_n) {
+                Some(keep_last) => Arc::new(DeduplicateExtension::new(
+                    top_n.input,
+                    top_n.row_number,
+                    keep_last,
+                    deduplicate_ttl,
+                )?),
+                None => Arc::new(top_n),
+            };
+
+            Ok(Some(LogicalPlan::Extension(Extension { node })))
+        }
+        LogicalPlan::Projection(projection) => {
+            let Ok(index) = projection.schema.index_of_column(column) else {
+                return Ok(None);
+            };
+            let Some(column) = extract_column(&projection.expr[index]) else {
+                return Ok(None);
+            };
+            let Some(input) = set_limit(&projection.input, column, limit, deduplicate_ttl)? else {
+                return Ok(None);
+            };
+
+            Ok(Some(LogicalPlan::Projection(
+                Projection::try_new_with_schema(
+                    projection.expr.clone(),
+                    Arc::new(input),
+                    projection.schema.clone(),
+                )?,
+            )))
+        }
+        LogicalPlan::SubqueryAlias(alias) => {
+            let Ok(index) = alias.schema.index_of_column(column) else {
+                return Ok(None);
+            };
+            let column = Column::from(alias.input.schema().qualified_field(index));
+            let Some(input) = set_limit(&alias.input, &column, limit, deduplicate_ttl)? else {
+                return Ok(None);
+            };
+
+            Ok(Some(LogicalPlan::SubqueryAlias(SubqueryAlias::try_new(
+                Arc::new(input),
+                alias.alias.clone(),
+            )?)))
+        }
+        LogicalPlan::Filter(filter) => {
+            let Some(input) = set_limit(&filter.input, column, limit, deduplicate_ttl)? else {
+                return Ok(None);
+            };
+
+            Ok(Some(LogicalPlan::Filter(Filter::try_new(
+                filter.predicate.clone(),
+                Arc::new(input),
+            )?)))
+        }
+        _ => Ok(None),
+    }
+}
@@ -1,9 +1,12 @@
+use crate::extension::dead_letter::DeadLetterExtension;
 use crate::extension::debezium::DebeziumUnrollingExtension;
+use crate::extension::lookup::{LookupJoinExtension, LookupSource, LOOKUP_JOIN_NAME};
 use crate::extension::remote_table::RemoteTableExtension;
 use crate::extension::sink::SinkExtension;
 use crate::extension::table_source::TableSourceExtension;
 use crate::extension::watermark_node::WatermarkNode;
 use crate::schemas::add_timestamp_field;
+use crate::tables::dead_letter_table_fields;
 use crate::tables::ConnectorTable;
 use crate::tables::FieldSpec;
 use crate::tables::Table;
@@ -61,20 +64,24 @@ impl<'a> SourceRewriter<'a> {
                 .ok_or_else(|| {
                     DataFusionError::Plan(format!("Watermark field {} not found", watermark_field))
                 })?,
-            None => Expr::BinaryExpr(BinaryExpr {
-                left: Box::new(Expr::Column(Column {
-                    relation: None,
-                    name: "_timestamp".to_string(),
-                })),
-                op: logical_expr::Operator::Minus,
-                right: Box::new(Expr::Literal(ScalarValue::DurationNanosecond(Some(
-                    Duration::from_secs(1).as_nanos() as i64,
-                )))),
-            }),
+            None => Self::default_watermark_expression(),
         };
         Ok(expr)
     }
 
+    fn default_watermark_expression() -> Expr {
+        Expr::BinaryExpr(BinaryExpr {
+            left: Box::new(Expr::Column(Column {
+                relation: None,
+                name: "_timestamp".to_string(),
+            })),
+            op: logical_expr::Operator::Minus,
+            right: Box::new(Expr::Literal(ScalarValue::DurationNanosecond(Some(
+                Duration::from_secs(1).as_nanos() as i64,
+            )))),
+        })
+    }
+
     fn projection_expressions(
         table: &ConnectorTable,
         qualifier: &TableReference,
@@ -205,6 +212,71 @@ impl<'a> SourceRewriter<'a> {
         })))
     }
 
+    fn mutate_dead_letter_table(
+        &self,
+        table_scan: &TableScan,
+        source_name: &str,
+    ) -> DFResult<Transformed<LogicalPlan>> {
+        let Some(Table::ConnectorTable(source)) = self.schema_provider.get_table(source_name)
+        else {
+            return plan_err!("source table {} not found", source_name);
+        };
+
+        let qualifier = table_scan.table_name.clone();
+        let input = DeadLetterExtension::input(&qualifier)?;
+
+        let mut expressions: Vec<_> = dead_letter_table_fields()
+            .iter()
+            .map(|f| {
+                if f.name() == "connector" {
+                    Expr::Literal(ScalarValue::Utf8(Some(source.connector.clone())))
+                        .alias_qualified(Some(qualifier.clone()), "connector")
+                } else {
+                    Expr::Column(Column::new(Some(qualifier.clone()), f.name()))
+                }
+            })
+            .collect();
+
+        if let Some(projection) = &table_scan.projection {
+            expressions = projection.iter().map(|i| expressions[*i].clone()).collect();
+        }
+        expressions.push(Expr::Column(Column::new(
+            Some(qualifier.clone()),
+            TIMESTAMP_FIELD,
+        )));
+
+        let projection =
+            LogicalPlan::Projection(Projection::try_new(expressions, Arc::new(input))?);
+
+        let source_extension = LogicalPlan::Extension(Extension {
+            node: Arc::new(TableSourceExtension::new(
+                TableReference::bare(source.name.clone()),
+                source.clone(),
+            )),
+        });
+
+        let dead_letter = LogicalPlan::Extension(Extension {
+            node: Arc::new(DeadLetterExtension::new(
+                qualifier.clone(),
+                source_extension,
+                projection,
+            )),
+        });
+
+        let watermark_node =
+            WatermarkNode::new(dead_letter, qualifier, Self::default_watermark_expression())
+                .map_err(|err| {
+                    DataFusionError::Internal(format!(
+                        "failed to create watermark expression: {}",
+                        err
+                    ))
+                })?;
+
+        Ok(Transformed::yes(LogicalPlan::Extension(Extension {
+            node: Arc::new(watermark_node),
+        })))
+    }
+
     fn mutate_table_from_query(
         &self,
         table_scan: &TableScan,
@@ -245,6 +317,39 @@ impl<'a> SourceRewriter<'a> {
         )?);
         Ok(Transformed::yes(projection))
     }
+
+    fn mutate_lookup_table(
+        &self,
+        table_scan: &TableScan,
+        table: &ConnectorTable,
+    ) -> DFResult<Transformed<LogicalPlan>> {
+        let qualifier = table_scan.table_name.clone();
+        let source = LogicalPlan::Extension(Extension {
+            node: Arc::new(LookupSource::new(qualifier.clone(), table.clone())?),
+        });
+
+        let Some(projection) = &table_scan.projection else {
+            return Ok(Transformed::yes(source));
+        };
+
+        let expressions = projection
+            .iter()
+            .map(|i| {
+                Expr::Column(Column::new(
+                    Some(qualifier.clone()),
+                    table.fields[*i].field().name(),
+                ))
+            })
+            .chain(std::iter::once(Expr::Column(Column::new(
+                Some(qualifier.clone()),
+                TIMESTAMP_FIELD,
+            ))))
+            .collect();
+
+        Ok(Transformed::yes(LogicalPlan::Projection(
+            Projection::try_new(expressions, Arc::new(source))?,
+        )))
+    }
 }
 
 impl<'a> TreeNodeRewriter for SourceRewriter<'a> {
@@ -289,6 +394,10 @@ impl<'a> TreeNodeRewriter for SourceRewriter<'a> {
             Table::PreviewSink { .. } => Err(DataFusionError::Plan(
                 "can't select from a preview sink".to_string(),
             )),
+            Table::DeadLetterTable { source, .. } => {
+                self.mutate_dead_letter_table(&table_scan, source)
+            }
+            Table::LookupTable(table) => self.mutate_lookup_table(&table_scan, table),
         }
     }
 }
@@ -576,11 +685,16 @@ impl<'a> SourceMetadataVisitor<'a> {
                 let SinkExtension { name, .. } = node.as_any().downcast_ref::<SinkExtension>()?;
                 name.to_string()
             }
+            LOOKUP_JOIN_NAME => {
+                let LookupJoinExtension { table, .. } =
+                    node.as_any().downcast_ref::<LookupJoinExtension>()?;
+                table.name.clone()
+            }
             _ => return None,
         };
         let table = self.schema_provider.get_table(&table_name)?;
         match table {
-            Table::ConnectorTable(table) => table.id,
+            Table::ConnectorTable(table) | Table::LookupTable(table) => table.id,
             _ => None,
         }
     }
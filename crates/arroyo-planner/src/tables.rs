@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::{collections::HashMap, time::Duration};
@@ -14,12 +15,14 @@ use crate::{
 };
 use crate::{rewrite_plan, DEFAULT_IDLE_TIME};
 use arroyo_datastream::default_sink;
+use arroyo_formats::proto::schema::{get_pool, proto_file_to_descriptor, protobuf_to_arrow};
 use arroyo_operator::connector::Connection;
 use arroyo_rpc::api_types::connections::{
     ConnectionProfile, ConnectionSchema, ConnectionType, SourceField,
 };
-use arroyo_rpc::formats::{BadData, Format, Framing, JsonFormat};
+use arroyo_rpc::formats::{BadData, Format, Framing, JsonFormat, ProtobufFormat};
 use arroyo_rpc::grpc::api::ConnectorOp;
+use arroyo_rpc::{dead_letter_fields, DEAD_LETTER_TABLE_SUFFIX};
 use arroyo_types::ArroyoExtensionType;
 use datafusion::common::{config::ConfigOptions, DFSchema, Result};
 use datafusion::common::{plan_err, Column, DataFusionError};
@@ -72,12 +75,17 @@ pub struct ConnectorTable {
     pub config: String,
     pub description: String,
     pub format: Option<Format>,
+    pub bad_data: Option<BadData>,
     pub event_time_field: Option<String>,
     pub watermark_field: Option<String>,
     pub idle_time: Option<Duration>,
     pub primary_keys: Arc<Vec<String>>,
 
     pub inferred_fields: Option<Vec<DFField>>,
+
+    // for lookup tables
+    pub lookup_cache_max_entries: Option<u64>,
+    pub lookup_cache_ttl: Option<Duration>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -207,16 +215,95 @@ impl From<Connection> for ConnectorTable {
             config: value.config,
             description: value.description,
             format: value.schema.format.clone(),
+            bad_data: value.schema.bad_data.clone(),
             event_time_field: None,
             watermark_field: None,
             idle_time: DEFAULT_IDLE_TIME,
-            primary_keys: Arc::new(vec![]),
+            // lookup tables are queried by their primary key
+            primary_keys: Arc::new(if value.connection_type == ConnectionType::Lookup {
+                value.schema.primary_keys.iter().cloned().collect()
+            } else {
+                vec![]
+            }),
             inferred_fields: None,
+            lookup_cache_max_entries: None,
+            lookup_cache_ttl: None,
         }
     }
 }
 
 impl ConnectorTable {
+    /// Resolves the schema for a protobuf table at planning time, so that the compiled
+    /// descriptor can be shipped with the pipeline. If no fields were declared, they are
+    /// derived from the protobuf message.
+    fn compile_protobuf_schema(
+        format: &mut ProtobufFormat,
+        fields: &mut Vec<FieldSpec>,
+        options: &mut HashMap<String, String>,
+    ) -> Result<()> {
+        if let Some(path) = options.remove("protobuf.schema_file") {
+            if format.compiled_schema.is_some() {
+                return plan_err!(
+                    "only one of 'protobuf.schema_file' and 'protobuf.descriptor' may be set"
+                );
+            }
+
+            format.compiled_schema =
+                Some(proto_file_to_descriptor(Path::new(&path)).map_err(|e| {
+                    DataFusionError::Plan(format!("failed to compile protobuf schema: {}", e))
+                })?);
+        }
+
+        let Some(encoded) = &format.compiled_schema else {
+            return plan_err!(
+                "protobuf format requires a schema; set either 'protobuf.schema_file' or 'protobuf.descriptor'"
+            );
+        };
+
+        let pool = get_pool(encoded)
+            .map_err(|e| DataFusionError::Plan(format!("invalid protobuf descriptor: {}", e)))?;
+
+        let message_name = format.message_name.as_deref().unwrap_or_default();
+        let descriptor = pool.get_message_by_name(message_name).ok_or_else(|| {
+            DataFusionError::Plan(format!(
+                "message '{}' not found in protobuf schema; messages are {}",
+                message_name,
+                pool.all_messages()
+                    .map(|m| m.full_name().to_string())
+                    .filter(|m| !m.starts_with("google.protobuf."))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
+
+        if fields.is_empty() && !format.into_unstructured_json {
+            let schema = protobuf_to_arrow(&descriptor).map_err(|e| {
+                DataFusionError::Plan(format!("failed to convert protobuf schema: {}", e))
+            })?;
+
+            *fields = schema
+                .fields
+                .iter()
+                .map(|f| FieldSpec::StructField((**f).clone()))
+                .collect();
+        }
+
+        Ok(())
+    }
+
+    /// A sink that discards its input, for sources whose output isn't otherwise consumed
+    pub(crate) fn blackhole_sink(name: &str) -> Result<Self> {
+        Self::from_options(
+            name,
+            "blackhole",
+            vec![],
+            vec![],
+            &mut HashMap::new(),
+            None,
+            None,
+        )
+    }
+
     fn from_options(
         name: &str,
         connector: &str,
@@ -248,9 +335,13 @@ impl ConnectorTable {
         let connector = connector_for_type(connector)
             .ok_or_else(|| DataFusionError::Plan(format!("Unknown connector '{}'", connector)))?;
 
-        let format = Format::from_opts(options)
+        let mut format = Format::from_opts(options)
             .map_err(|e| DataFusionError::Plan(format!("invalid format: '{e}'")))?;
 
+        if let Some(Format::Protobuf(proto)) = &mut format {
+            Self::compile_protobuf_schema(proto, &mut fields, options)?;
+        }
+
         let framing = Framing::from_opts(options)
             .map_err(|e| DataFusionError::Plan(format!("invalid framing: '{e}'")))?;
 
@@ -296,9 +387,42 @@ impl ConnectorTable {
             schema_fields,
             None,
             Some(fields.is_empty()),
+            primary_keys.iter().cloned().collect(),
         )
         .map_err(|e| DataFusionError::Plan(format!("could not create connection schema: {}", e)))?;
 
+        if let Some(metadata_columns) = &connector_metadata_columns {
+            let defs = connector.metadata_defs();
+            for (column, (key, data_type)) in metadata_columns {
+                let Some(def) = defs.iter().find(|def| def.name == key) else {
+                    return plan_err!(
+                        "unknown metadata field '{}' for column '{}'; the {} connector supports {}",
+                        key,
+                        column,
+                        connector.name(),
+                        if defs.is_empty() {
+                            "no metadata fields".to_string()
+                        } else {
+                            defs.iter()
+                                .map(|def| format!("'{}'", def.name))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        }
+                    );
+                };
+
+                if def.data_type != *data_type {
+                    return plan_err!(
+                        "metadata field '{}' for column '{}' has type {}, but the column is declared as {}",
+                        key,
+                        column,
+                        def.data_type,
+                        data_type
+                    );
+                }
+            }
+        }
+
         let connection = connector
             .from_options(
                 name,
@@ -326,6 +450,32 @@ impl ConnectorTable {
             .filter(|t| *t <= 0)
             .map(|t| Duration::from_micros(t as u64));
 
+        table.lookup_cache_max_entries = options
+            .remove("lookup.cache.max_entries")
+            .map(|t| match u64::from_str(&t) {
+                // a cache that can't hold any entries would never be hit
+                Ok(entries) if entries > 0 => Ok(entries),
+                _ => plan_err!("lookup.cache.max_entries must be set to a positive number"),
+            })
+            .transpose()?;
+
+        table.lookup_cache_ttl = options
+            .remove("lookup.cache.ttl_secs")
+            .map(|t| u64::from_str(&t))
+            .transpose()
+            .map_err(|_| {
+                DataFusionError::Plan(
+                    "lookup.cache.ttl_secs must be set to a positive number".to_string(),
+                )
+            })?
+            .map(Duration::from_secs);
+
+        if (table.lookup_cache_max_entries.is_some() || table.lookup_cache_ttl.is_some())
+            && table.connection_type != ConnectionType::Lookup
+        {
+            return plan_err!("lookup.cache options can only be set on lookup tables");
+        }
+
         if !options.is_empty() {
             let keys: Vec<String> = options.keys().map(|s| format!("'{}'", s)).collect();
             return plan_err!(
@@ -341,6 +491,23 @@ impl ConnectorTable {
             return plan_err!("Debezium source must have at least one PRIMARY KEY field");
         }
 
+        if table.connection_type == ConnectionType::Lookup {
+            if primary_keys.is_empty() {
+                return plan_err!(
+                    "lookup tables must have a PRIMARY KEY, which rows are looked up by"
+                );
+            }
+
+            // rows are only ever found by their keys, so they can't be null
+            for field in &mut table.fields {
+                if let FieldSpec::StructField(f) = field {
+                    if primary_keys.contains(f.name()) {
+                        *f = f.clone().with_nullable(false);
+                    }
+                }
+            }
+        }
+
         table.primary_keys = Arc::new(primary_keys);
 
         Ok(table)
@@ -407,7 +574,7 @@ impl ConnectorTable {
         )
     }
 
-    fn connector_op(&self) -> ConnectorOp {
+    pub(crate) fn connector_op(&self) -> ConnectorOp {
         ConnectorOp {
             connector: self.connector.clone(),
             config: self.config.clone(),
@@ -429,6 +596,9 @@ impl ConnectorTable {
             ConnectionType::Sink => {
                 return plan_err!("cannot read from sink");
             }
+            ConnectionType::Lookup => {
+                return plan_err!("lookup tables can only be used on the right side of a join");
+            }
         };
 
         if self.is_updating() && self.has_virtual_fields() {
@@ -486,6 +656,15 @@ pub enum Table {
     PreviewSink {
         logical_plan: LogicalPlan,
     },
+    /// The records that failed to deserialize in a source table configured with
+    /// `bad_data = 'dlq'`
+    DeadLetterTable {
+        name: String,
+        source: String,
+    },
+    /// A table whose rows are queried by key from an external system as they're needed, which
+    /// can only appear on the right side of a join
+    LookupTable(ConnectorTable),
 }
 
 fn value_to_inner_string(value: &Value) -> Result<String> {
@@ -707,18 +886,23 @@ impl Table {
                         ),
                         None => None,
                     };
-                    Ok(Some(Table::ConnectorTable(
-                        ConnectorTable::from_options(
-                            &name,
-                            connector,
-                            fields,
-                            primary_keys,
-                            &mut with_map,
-                            connection_profile,
-                            connector_metadata_columns,
-                        )
-                        .map_err(|e| e.context(format!("Failed to create table {}", name)))?,
-                    )))
+                    let table = ConnectorTable::from_options(
+                        &name,
+                        connector,
+                        fields,
+                        primary_keys,
+                        &mut with_map,
+                        connection_profile,
+                        connector_metadata_columns,
+                    )
+                    .map_err(|e| e.context(format!("Failed to create table {}", name)))?;
+
+                    Ok(Some(match table.connection_type {
+                        ConnectionType::Lookup => Table::LookupTable(table),
+                        ConnectionType::Source | ConnectionType::Sink => {
+                            Table::ConnectorTable(table)
+                        }
+                    }))
                 }
             }
         } else {
@@ -753,11 +937,27 @@ impl Table {
         }
     }
 
+    /// If this is a source that sends bad data to a dead-letter queue, returns the table
+    /// that can be used to read from it
+    pub fn dead_letter_table(&self) -> Option<Table> {
+        let Table::ConnectorTable(table) = self else {
+            return None;
+        };
+
+        (table.connection_type == ConnectionType::Source
+            && matches!(table.bad_data, Some(BadData::Dlq {})))
+        .then(|| Table::DeadLetterTable {
+            name: format!("{}{}", table.name, DEAD_LETTER_TABLE_SUFFIX),
+            source: table.name.clone(),
+        })
+    }
+
     pub fn name(&self) -> &str {
         match self {
             Table::MemoryTable { name, .. } | Table::TableFromQuery { name, .. } => name.as_str(),
-            Table::ConnectorTable(c) => c.name.as_str(),
+            Table::ConnectorTable(c) | Table::LookupTable(c) => c.name.as_str(),
             Table::PreviewSink { .. } => "preview",
+            Table::DeadLetterTable { name, .. } => name.as_str(),
         }
     }
 
@@ -794,6 +994,11 @@ impl Table {
                 fields,
                 inferred_fields,
                 ..
+            })
+            | Table::LookupTable(ConnectorTable {
+                fields,
+                inferred_fields,
+                ..
             }) => inferred_fields
                 .as_ref()
                 .map(|fs| fs.iter().map(|f| f.field().clone()).collect())
@@ -809,6 +1014,7 @@ impl Table {
             Table::PreviewSink { logical_plan } => {
                 logical_plan.schema().fields().iter().cloned().collect()
             }
+            Table::DeadLetterTable { .. } => dead_letter_table_fields(),
         }
     }
 
@@ -818,10 +1024,20 @@ impl Table {
             Table::MemoryTable { .. } => plan_err!("can't write to a memory table"),
             Table::TableFromQuery { .. } => todo!(),
             Table::PreviewSink { logical_plan: _ } => Ok(default_sink()),
+            Table::DeadLetterTable { .. } => plan_err!("can't write to a dead-letter table"),
+            Table::LookupTable(_) => plan_err!("can't write to a lookup table"),
         }
     }
 }
 
+/// The fields of a dead-letter table: those produced by the source (see
+/// [`arroyo_rpc::dead_letter_fields`]), plus the name of the source's connector
+pub(crate) fn dead_letter_table_fields() -> Vec<FieldRef> {
+    let mut fields: Vec<_> = dead_letter_fields().iter().cloned().collect();
+    fields.insert(2, Arc::new(Field::new("connector", DataType::Utf8, false)));
+    fields
+}
+
 #[derive(Debug)]
 pub enum Insert {
     InsertQuery {
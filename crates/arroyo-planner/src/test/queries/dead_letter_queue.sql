@@ -0,0 +1,26 @@
+create table orders (
+    id BIGINT,
+    amount DOUBLE,
+    offset BIGINT GENERATED ALWAYS AS (metadata('offset_id')) STORED,
+    partition INT GENERATED ALWAYS AS (metadata('partition')) STORED
+) with (
+    connector = 'kafka',
+    topic = 'orders',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    bad_data = 'dlq',
+    type = 'source'
+);
+
+create table orders_errors with (
+    connector = 'kafka',
+    topic = 'orders_errors',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'sink'
+);
+
+INSERT INTO orders_errors
+SELECT raw, error, connector, metadata FROM orders_dlq;
+
+SELECT id, amount FROM orders WHERE amount > 100;
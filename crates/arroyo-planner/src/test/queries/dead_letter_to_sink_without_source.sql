@@ -0,0 +1,22 @@
+create table orders (
+    id BIGINT,
+    amount DOUBLE
+) with (
+    connector = 'kafka',
+    topic = 'orders',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    bad_data = 'dlq',
+    type = 'source'
+);
+
+create table errors with (
+    connector = 'kafka',
+    topic = 'order_errors',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'sink'
+);
+
+INSERT INTO errors
+SELECT error, metadata FROM orders_dlq;
@@ -0,0 +1,13 @@
+create table orders (
+    id BIGINT,
+    amount DOUBLE
+) with (
+    connector = 'kafka',
+    topic = 'orders',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    bad_data = 'dlq',
+    type = 'source'
+);
+
+SELECT error FROM orders_dlq;
@@ -0,0 +1,32 @@
+CREATE TABLE events (
+  timestamp TIMESTAMP,
+  event_id TEXT,
+  user_id TEXT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/events.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE unique_events (
+  timestamp TIMESTAMP,
+  event_id TEXT,
+  user_id TEXT
+) WITH (
+  connector = 'kafka',
+  bootstrap_servers = 'localhost:9092',
+  type = 'sink',
+  topic = 'unique_events',
+  format = 'json'
+);
+
+SET deduplicate_ttl = '1 hour';
+
+INSERT INTO unique_events
+SELECT timestamp, event_id, user_id
+FROM (
+  SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY timestamp) as rn
+  FROM events
+) WHERE rn = 1;
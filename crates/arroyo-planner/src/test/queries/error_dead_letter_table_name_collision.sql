@@ -0,0 +1,24 @@
+--fail=the dead-letter table of 'orders' would be named 'orders_dlq', but a table with that name already exists
+create table orders_dlq (
+    id BIGINT
+) with (
+    connector = 'kafka',
+    topic = 'orders_dlq',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'source'
+);
+
+create table orders (
+    id BIGINT,
+    amount DOUBLE
+) with (
+    connector = 'kafka',
+    topic = 'orders',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    bad_data = 'dlq',
+    type = 'source'
+);
+
+SELECT id FROM orders;
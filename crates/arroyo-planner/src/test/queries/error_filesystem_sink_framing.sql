@@ -0,0 +1,18 @@
+--fail=FileSystem sinks only support newline framing
+CREATE TABLE impulse WITH (
+    connector = 'impulse',
+    event_rate = '10'
+);
+
+CREATE TABLE output (
+    counter BIGINT UNSIGNED
+) WITH (
+    connector = 'filesystem',
+    type = 'sink',
+    path = '/tmp/arroyo/output',
+    format = 'json',
+    framing = 'length_prefixed'
+);
+
+INSERT INTO output
+SELECT counter FROM impulse;
@@ -0,0 +1,29 @@
+--fail=interval join's lower bound is greater than its upper bound
+CREATE TABLE orders (
+  timestamp TIMESTAMP,
+  order_id BIGINT,
+  customer_id BIGINT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/orders.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE shipments (
+  timestamp TIMESTAMP,
+  order_id BIGINT,
+  carrier TEXT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/shipments.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+SELECT o.order_id, o.customer_id, s.carrier
+FROM orders o
+LEFT JOIN shipments s ON o.order_id = s.order_id
+  AND s.timestamp BETWEEN o.timestamp + INTERVAL '10' MINUTE AND o.timestamp + INTERVAL '5' MINUTE;
@@ -0,0 +1,26 @@
+--fail=lookup joins must be on the lookup table's primary key
+create table events (
+    event_id TEXT,
+    customer_name TEXT
+) with (
+    connector = 'kafka',
+    topic = 'events',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'source'
+);
+
+create table customers (
+    id TEXT GENERATED ALWAYS AS (metadata('key')) STORED PRIMARY KEY,
+    name TEXT
+) with (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    format = 'json',
+    type = 'lookup'
+);
+
+SELECT c.id, e.event_id
+FROM events e
+JOIN customers c
+ON e.customer_name = c.name;
@@ -0,0 +1,26 @@
+--fail=lookup tables can only be used on the right side of a JOIN
+create table events (
+    event_id TEXT,
+    customer_id TEXT
+) with (
+    connector = 'kafka',
+    topic = 'events',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'source'
+);
+
+create table customers (
+    id TEXT GENERATED ALWAYS AS (metadata('key')) STORED PRIMARY KEY,
+    name TEXT
+) with (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    format = 'json',
+    type = 'lookup'
+);
+
+SELECT c.name, e.event_id
+FROM customers c
+JOIN events e
+ON c.id = e.customer_id;
@@ -0,0 +1,12 @@
+--fail=message 'Missing' not found in protobuf schema
+CREATE TABLE events WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'events',
+    format = 'protobuf',
+    'protobuf.schema_file' = '../arroyo-formats/src/proto/test/protos/basic_types.proto',
+    'protobuf.message_name' = 'Missing'
+);
+
+SELECT * FROM events;
@@ -0,0 +1,5 @@
+--fail=ROW_NUMBER() over unwindowed input requires an ORDER BY
+SELECT * FROM (
+  SELECT *, ROW_NUMBER() OVER (PARTITION BY bid.auction) as rn
+  FROM nexmark WHERE bid IS NOT NULL
+) WHERE rn <= 10
@@ -0,0 +1,13 @@
+--fail=unknown metadata field 'offset' for column 'offset'
+create table users (
+    id TEXT,
+    offset BIGINT GENERATED ALWAYS AS (metadata('offset')) STORED
+) with (
+    connector = 'kafka',
+    topic = 'order_topic',
+    format='json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type='source'
+);
+
+SELECT * FROM users;
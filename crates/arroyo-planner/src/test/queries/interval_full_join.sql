@@ -0,0 +1,29 @@
+CREATE TABLE orders (
+  timestamp TIMESTAMP,
+  order_id BIGINT,
+  customer_id BIGINT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/orders.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE shipments (
+  timestamp TIMESTAMP,
+  order_id BIGINT,
+  carrier TEXT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/shipments.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+SELECT o.order_id, s.order_id, s.carrier
+FROM orders o
+FULL OUTER JOIN shipments s ON o.order_id = s.order_id
+  AND o.timestamp >= s.timestamp - INTERVAL '1' HOUR
+  AND s.timestamp > o.timestamp;
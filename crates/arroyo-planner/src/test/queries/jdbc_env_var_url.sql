@@ -0,0 +1,26 @@
+CREATE TABLE events (
+    user_id TEXT,
+    points BIGINT
+) WITH (
+    connector = 'kafka',
+    topic = 'events',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'source'
+);
+
+CREATE TABLE scores (
+    user_id TEXT PRIMARY KEY,
+    score BIGINT
+) WITH (
+    connector = 'jdbc',
+    url = '{{ JDBC_SINK_URL_UNSET_IN_PLANNER }}',
+    table = 'public.scores',
+    format = 'debezium_json',
+    type = 'sink'
+);
+
+INSERT INTO scores
+SELECT user_id, sum(points) as score
+FROM events
+GROUP BY user_id;
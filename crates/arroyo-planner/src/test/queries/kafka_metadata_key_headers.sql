@@ -0,0 +1,18 @@
+create table users (
+    id TEXT,
+    name TEXT,
+    msg_key BYTEA GENERATED ALWAYS AS (metadata('key')) STORED,
+    msg_key_text TEXT GENERATED ALWAYS AS (metadata('key_text')) STORED,
+    headers JSON GENERATED ALWAYS AS (metadata('headers')) STORED,
+    ts TIMESTAMP GENERATED ALWAYS AS (metadata('timestamp')) STORED
+) with (
+    connector = 'kafka',
+    topic = 'order_topic',
+    format='json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type='source'
+);
+
+SELECT msg_key_text, count(*) FROM users
+WHERE extract_json_string(headers, '$.region') = 'eu'
+GROUP BY msg_key_text, tumble(interval '1 minute');
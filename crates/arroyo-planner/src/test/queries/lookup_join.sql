@@ -0,0 +1,30 @@
+create table events (
+    event_id TEXT,
+    customer_id TEXT,
+    amount BIGINT
+) with (
+    connector = 'kafka',
+    topic = 'events',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'source'
+);
+
+create table customers (
+    id TEXT GENERATED ALWAYS AS (metadata('key')) STORED PRIMARY KEY,
+    name TEXT,
+    plan TEXT
+) with (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    format = 'json',
+    type = 'lookup',
+    'lookup.key_prefix' = 'customer:',
+    'lookup.cache.max_entries' = '10000',
+    'lookup.cache.ttl_secs' = '60'
+);
+
+SELECT e.event_id, e.amount, c.name
+FROM events e
+LEFT JOIN customers c
+ON concat('c-', e.customer_id) = c.id;
@@ -0,0 +1,28 @@
+create table events (
+    event_id TEXT,
+    customer_id TEXT,
+    amount BIGINT
+) with (
+    connector = 'kafka',
+    topic = 'events',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'source'
+);
+
+create table customers (
+    id TEXT GENERATED ALWAYS AS (metadata('key')) STORED PRIMARY KEY,
+    name TEXT,
+    plan TEXT
+) with (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    format = 'json',
+    type = 'lookup'
+);
+
+SELECT e.event_id, count(*)
+FROM events e
+JOIN (SELECT id, name FROM customers WHERE plan = 'enterprise') c
+ON e.customer_id = c.id
+GROUP BY e.event_id, tumble(interval '1 minute');
@@ -0,0 +1,15 @@
+create table events (
+    value TEXT,
+    topic TEXT GENERATED ALWAYS AS (metadata('topic')) STORED,
+    properties JSON GENERATED ALWAYS AS (metadata('user_properties')) STORED
+) with (
+    connector = 'mqtt',
+    url = 'tcp://localhost:1883',
+    topic = 'events/#',
+    format = 'json',
+    type = 'source'
+);
+
+SELECT topic, count(*) FROM events
+WHERE extract_json_string(properties, '$.region') = 'eu'
+GROUP BY topic, tumble(interval '1 minute');
@@ -0,0 +1,12 @@
+CREATE TABLE events WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'source',
+    topic = 'events',
+    format = 'protobuf',
+    'protobuf.schema_file' = '../arroyo-formats/src/proto/test/protos/basic_types.proto',
+    'protobuf.message_name' = 'TestBasicTypes'
+);
+
+SELECT int32_field, sum(double_field) FROM events
+GROUP BY int32_field, tumble(interval '10 seconds');
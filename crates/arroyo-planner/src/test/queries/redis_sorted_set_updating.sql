@@ -0,0 +1,30 @@
+create table events (
+    user_id TEXT,
+    points BIGINT
+) with (
+    connector = 'kafka',
+    topic = 'events',
+    format = 'json',
+    bootstrap_servers = '0.0.0.0:9092',
+    type = 'source'
+);
+
+create table leaderboard (
+    user_id TEXT NOT NULL,
+    score BIGINT NOT NULL
+) with (
+    connector = 'redis',
+    address = 'redis://localhost:6379',
+    format = 'debezium_json',
+    type = 'sink',
+    target = 'sorted_set',
+    'target.key_prefix' = 'leaderboard',
+    'target.score_column' = 'score',
+    'target.member_column' = 'user_id'
+);
+
+INSERT INTO leaderboard
+SELECT user_id, sum(points) as score
+FROM events
+WHERE user_id IS NOT NULL
+GROUP BY user_id;
@@ -0,0 +1,28 @@
+CREATE TABLE orders (
+  timestamp TIMESTAMP,
+  order_id BIGINT,
+  currency TEXT,
+  amount DOUBLE
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/orders.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE currency_rates (
+  currency TEXT PRIMARY KEY,
+  rate DOUBLE
+) WITH (
+  connector = 'kafka',
+  bootstrap_servers = 'localhost:9092',
+  type = 'source',
+  topic = 'currency_rates',
+  format = 'debezium_json'
+);
+
+SELECT o.order_id, o.amount * r.rate AS converted
+FROM orders o
+LEFT JOIN currency_rates FOR SYSTEM_TIME AS OF o.timestamp AS r
+  ON o.currency = r.currency;
@@ -0,0 +1,32 @@
+CREATE TABLE scores (
+  timestamp TIMESTAMP,
+  player TEXT,
+  game TEXT,
+  score BIGINT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/scores.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE leaderboard (
+  game TEXT,
+  player TEXT,
+  score BIGINT,
+  position BIGINT
+) WITH (
+  connector = 'kafka',
+  bootstrap_servers = 'localhost:9092',
+  type = 'sink',
+  topic = 'leaderboard',
+  format = 'debezium_json'
+);
+
+INSERT INTO leaderboard
+SELECT game, player, score, CAST(rn AS BIGINT)
+FROM (
+  SELECT *, ROW_NUMBER() OVER (PARTITION BY game ORDER BY score DESC) as rn
+  FROM scores
+) WHERE rn <= 3;
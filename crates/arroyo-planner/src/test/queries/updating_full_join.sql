@@ -0,0 +1,40 @@
+CREATE TABLE orders (
+  timestamp TIMESTAMP,
+  order_id BIGINT,
+  customer_id BIGINT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/orders.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE shipments (
+  timestamp TIMESTAMP,
+  order_id BIGINT,
+  carrier TEXT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/shipments.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE order_status (
+  order_id BIGINT,
+  customer_id BIGINT,
+  carrier TEXT
+) WITH (
+  connector = 'kafka',
+  bootstrap_servers = 'localhost:9092',
+  type = 'sink',
+  topic = 'order_status',
+  format = 'debezium_json'
+);
+
+INSERT INTO order_status
+SELECT o.order_id, o.customer_id, s.carrier
+FROM orders o
+FULL OUTER JOIN shipments s ON o.order_id = s.order_id;
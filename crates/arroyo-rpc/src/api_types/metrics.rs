@@ -15,6 +15,7 @@ pub enum MetricName {
     Backpressure,
     TxQueueSize,
     TxQueueRem,
+    RecordsDropped,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
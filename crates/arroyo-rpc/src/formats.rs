@@ -1,3 +1,5 @@
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
@@ -244,11 +246,147 @@ pub struct ProtobufFormat {
 
     #[serde(default)]
     pub confluent_schema_registry: bool,
+
+    #[serde(default)]
+    #[schema(read_only)]
+    pub schema_id: Option<u32>,
 }
 
 impl ProtobufFormat {
-    pub fn from_opts(_opts: &mut HashMap<String, String>) -> Result<Self, String> {
-        Err("Protobuf is not yet supported in CREATE TABLE statements".to_string())
+    /// Parses the protobuf options from a CREATE TABLE statement. A schema may be provided inline
+    /// as a base64-encoded `FileDescriptorSet` via `protobuf.descriptor`; schemas provided as
+    /// `.proto` files (`protobuf.schema_file`) must be compiled by the caller, as that requires
+    /// protoc.
+    pub fn from_opts(opts: &mut HashMap<String, String>) -> Result<Self, String> {
+        let message_name = opts
+            .remove("protobuf.message_name")
+            .filter(|m| !m.is_empty())
+            .ok_or_else(|| "protobuf.message_name must be set for protobuf format".to_string())?;
+
+        let compiled_schema = opts
+            .remove("protobuf.descriptor")
+            .map(|d| BASE64_STANDARD.decode(d.trim()))
+            .transpose()
+            .map_err(|e| format!("invalid protobuf.descriptor; must be base64-encoded: {}", e))?;
+
+        Ok(Self {
+            into_unstructured_json: opts
+                .remove("protobuf.into_unstructured_json")
+                .filter(|t| t == "true")
+                .is_some(),
+            message_name: Some(message_name),
+            compiled_schema,
+            confluent_schema_registry: opts
+                .remove("protobuf.confluent_schema_registry")
+                .filter(|t| t == "true")
+                .is_some(),
+            schema_id: None,
+        })
+    }
+}
+
+fn default_csv_delimiter() -> char {
+    ','
+}
+
+fn default_csv_quote() -> char {
+    '"'
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvFormat {
+    #[serde(default = "default_csv_delimiter")]
+    pub delimiter: char,
+
+    #[serde(default = "default_csv_quote")]
+    pub quote: char,
+
+    /// The character used to escape quotes within quoted fields; if unset, quotes are escaped
+    /// by doubling them
+    #[serde(default)]
+    pub escape: Option<char>,
+
+    /// Whether the data has a header row with the column names. Sources skip the first line of
+    /// each file or message, and sinks write one at the start of each file.
+    #[serde(default)]
+    pub header: bool,
+
+    /// The string used to represent null values
+    #[serde(default)]
+    pub null_value: String,
+
+    #[serde(default)]
+    pub timestamp_format: TimestampFormat,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            delimiter: default_csv_delimiter(),
+            quote: default_csv_quote(),
+            escape: None,
+            header: false,
+            null_value: String::new(),
+            timestamp_format: TimestampFormat::default(),
+        }
+    }
+}
+
+impl CsvFormat {
+    fn from_opts(tsv: bool, opts: &mut HashMap<String, String>) -> Result<Self, String> {
+        fn single_char(
+            opts: &mut HashMap<String, String>,
+            key: &str,
+        ) -> Result<Option<char>, String> {
+            opts.remove(key)
+                .map(|v| {
+                    let v = match v.as_str() {
+                        "\\t" => "\t",
+                        v => v,
+                    };
+                    let mut chars = v.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if c.is_ascii() => Ok(c),
+                        _ => Err(format!("{} must be a single ASCII character", key)),
+                    }
+                })
+                .transpose()
+        }
+
+        let delimiter = single_char(opts, "csv.delimiter")?.unwrap_or(if tsv { '\t' } else { ',' });
+        let quote = single_char(opts, "csv.quote")?.unwrap_or_else(default_csv_quote);
+        let escape = single_char(opts, "csv.escape")?;
+
+        if delimiter == quote {
+            return Err("csv.delimiter and csv.quote must be different characters".to_string());
+        }
+
+        let header = opts
+            .remove("csv.header")
+            .map(|h| match h.as_str() {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err("csv.header must be 'true' or 'false'".to_string()),
+            })
+            .transpose()?
+            .unwrap_or(false);
+
+        let timestamp_format: TimestampFormat = opts
+            .remove("csv.timestamp_format")
+            .map(|t| t.as_str().try_into())
+            .transpose()
+            .map_err(|_| "csv.timestamp_format".to_string())?
+            .unwrap_or_default();
+
+        Ok(Self {
+            delimiter,
+            quote,
+            escape,
+            header,
+            null_value: opts.remove("csv.null").unwrap_or_default(),
+            timestamp_format,
+        })
     }
 }
 
@@ -261,6 +399,7 @@ pub enum Format {
     Parquet(ParquetFormat),
     RawString(RawStringFormat),
     RawBytes(RawBytesFormat),
+    Csv(CsvFormat),
 }
 
 impl Format {
@@ -277,6 +416,8 @@ impl Format {
             "raw_string" => Format::RawString(RawStringFormat {}),
             "raw_bytes" => Format::RawBytes(RawBytesFormat {}),
             "parquet" => Format::Parquet(ParquetFormat {}),
+            "csv" => Format::Csv(CsvFormat::from_opts(false, opts)?),
+            "tsv" => Format::Csv(CsvFormat::from_opts(true, opts)?),
             f => return Err(format!("Unknown format '{}'", f)),
         }))
     }
@@ -288,7 +429,8 @@ impl Format {
             | Format::Avro(_)
             | Format::Parquet(_)
             | Format::RawString(_)
-            | Format::Protobuf(_) => false,
+            | Format::Protobuf(_)
+            | Format::Csv(_) => false,
             Format::RawBytes(_) => false,
         }
     }
@@ -299,6 +441,8 @@ impl Format {
 pub enum BadData {
     Fail {},
     Drop {},
+    /// Send records that fail to deserialize to the source's dead-letter table
+    Dlq {},
 }
 
 impl Default for BadData {
@@ -316,6 +460,7 @@ impl BadData {
         let method = match method.as_str() {
             "drop" => BadData::Drop {},
             "fail" => BadData::Fail {},
+            "dlq" => BadData::Dlq {},
             f => return Err(format!("Unknown invalid data behavior '{}'", f)),
         };
 
@@ -337,6 +482,10 @@ impl Framing {
 
         let method = match method.as_str() {
             "newline" => FramingMethod::Newline(NewlineDelimitedFraming::from_opts(opts)?),
+            "length_prefixed" => {
+                FramingMethod::LengthPrefixed(LengthPrefixedFraming::from_opts(opts)?)
+            }
+            "delimiter" => FramingMethod::Delimiter(DelimitedFraming::from_opts(opts)?),
             f => return Err(format!("Unknown framing method '{}'", f)),
         };
 
@@ -365,8 +514,142 @@ impl NewlineDelimitedFraming {
     }
 }
 
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, ToSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum LengthPrefixWidth {
+    U16,
+    #[default]
+    U32,
+    Varint,
+}
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, ToSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+/// Each message is preceded by its length in bytes, encoded as a fixed-width integer or as an
+/// unsigned LEB128 varint (as used by protobuf's delimited encoding)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LengthPrefixedFraming {
+    #[serde(default)]
+    pub width: LengthPrefixWidth,
+    #[serde(default)]
+    pub endianness: Endianness,
+}
+
+impl LengthPrefixedFraming {
+    pub fn from_opts(opts: &mut HashMap<String, String>) -> Result<Self, String> {
+        let width = match opts.remove("framing.length_prefixed.width").as_deref() {
+            Some("u16") => LengthPrefixWidth::U16,
+            Some("u32") | None => LengthPrefixWidth::U32,
+            Some("varint") => LengthPrefixWidth::Varint,
+            Some(f) => {
+                return Err(format!(
+                    "invalid value '{}' for framing.length_prefixed.width; must be one of 'u16', 'u32', or 'varint'",
+                    f
+                ))
+            }
+        };
+
+        let endianness = match opts.remove("framing.length_prefixed.endianness").as_deref() {
+            Some(_) if width == LengthPrefixWidth::Varint => {
+                return Err(
+                    "framing.length_prefixed.endianness cannot be set for varint lengths"
+                        .to_string(),
+                )
+            }
+            Some("big") | None => Endianness::Big,
+            Some("little") => Endianness::Little,
+            Some(f) => {
+                return Err(format!(
+                    "invalid value '{}' for framing.length_prefixed.endianness; must be one of 'big' or 'little'",
+                    f
+                ))
+            }
+        };
+
+        Ok(LengthPrefixedFraming { width, endianness })
+    }
+}
+
+/// Messages are separated by an arbitrary, non-empty sequence of bytes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DelimitedFraming {
+    pub delimiter: Vec<u8>,
+}
+
+impl DelimitedFraming {
+    pub fn from_opts(opts: &mut HashMap<String, String>) -> Result<Self, String> {
+        let value = opts
+            .remove("framing.delimiter.value")
+            .ok_or("framing.delimiter.value is required for delimiter framing")?;
+
+        let delimiter = Self::unescape(&value)?;
+        if delimiter.is_empty() {
+            return Err("framing.delimiter.value must not be empty".to_string());
+        }
+
+        Ok(DelimitedFraming { delimiter })
+    }
+
+    /// Converts a delimiter string into bytes, supporting the escapes `\n`, `\r`, `\t`, `\0`,
+    /// `\\`, and `\xNN` for arbitrary bytes
+    fn unescape(value: &str) -> Result<Vec<u8>, String> {
+        let mut bytes = vec![];
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                continue;
+            }
+
+            match chars.next() {
+                Some('n') => bytes.push(b'\n'),
+                Some('r') => bytes.push(b'\r'),
+                Some('t') => bytes.push(b'\t'),
+                Some('0') => bytes.push(0),
+                Some('\\') => bytes.push(b'\\'),
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    let byte = u8::from_str_radix(&hex, 16)
+                        .ok()
+                        .filter(|_| hex.len() == 2)
+                        .ok_or_else(|| {
+                            format!("invalid escape '\\x{}' in framing.delimiter.value", hex)
+                        })?;
+                    bytes.push(byte);
+                }
+                Some(c) => {
+                    return Err(format!(
+                        "invalid escape '\\{}' in framing.delimiter.value",
+                        c
+                    ))
+                }
+                None => {
+                    return Err("framing.delimiter.value cannot end with '\\'".to_string());
+                }
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum FramingMethod {
     Newline(NewlineDelimitedFraming),
+    LengthPrefixed(LengthPrefixedFraming),
+    Delimiter(DelimitedFraming),
 }
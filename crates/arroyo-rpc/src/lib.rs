@@ -9,6 +9,7 @@ use std::sync::{Arc, OnceLock};
 use std::{fs, time::SystemTime};
 
 use crate::api_types::connections::PrimitiveType;
+use crate::df::{ArroyoSchema, ArroyoSchemaRef};
 use crate::formats::{BadData, Format, Framing};
 use crate::grpc::rpc::{LoadCompactedDataReq, SubtaskCheckpointMetadata};
 use anyhow::Result;
@@ -217,6 +218,10 @@ pub fn error_chain(e: anyhow::Error) -> String {
 
 pub const TIMESTAMP_FIELD: &str = "_timestamp";
 pub const UPDATING_META_FIELD: &str = "_updating_meta";
+pub const DEAD_LETTER_TABLE_SUFFIX: &str = "_dlq";
+/// Column added to the results of a lookup connector that holds, for each row, the index of the
+/// key it was looked up by
+pub const LOOKUP_KEY_INDEX_FIELD: &str = "__lookup_key_index";
 
 pub fn updating_meta_fields() -> Fields {
     static UPDATING_META_FIELDS: OnceLock<Fields> = OnceLock::new();
@@ -244,6 +249,40 @@ pub fn updating_meta_field() -> Arc<Field> {
         })
         .clone()
 }
+
+/// The fields of the records that sources with `bad_data = 'dlq'` emit for data that fails to
+/// deserialize: the raw bytes (if available), the error, and a JSON object containing the
+/// message's position in the source (like its topic, partition and offset) along with any
+/// metadata columns declared on the table
+pub fn dead_letter_fields() -> Fields {
+    static DEAD_LETTER_FIELDS: OnceLock<Fields> = OnceLock::new();
+
+    DEAD_LETTER_FIELDS
+        .get_or_init(|| {
+            Fields::from(vec![
+                Field::new("raw", DataType::Binary, true),
+                Field::new("error", DataType::Utf8, false),
+                Field::new("metadata", DataType::Utf8, true),
+            ])
+        })
+        .clone()
+}
+
+pub fn dead_letter_schema() -> ArroyoSchemaRef {
+    static DEAD_LETTER_SCHEMA: OnceLock<ArroyoSchemaRef> = OnceLock::new();
+
+    DEAD_LETTER_SCHEMA
+        .get_or_init(|| {
+            Arc::new(ArroyoSchema::from_fields(
+                dead_letter_fields()
+                    .iter()
+                    .map(|f| f.as_ref().clone())
+                    .collect(),
+            ))
+        })
+        .clone()
+}
+
 // need to handle the empty case as a row converter without sort fields emits empty Rows.
 #[derive(Debug)]
 pub enum Converter {
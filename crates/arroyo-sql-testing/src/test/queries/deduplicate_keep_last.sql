@@ -0,0 +1,28 @@
+CREATE TABLE impulse (
+  timestamp TIMESTAMP,
+  counter bigint unsigned not null,
+  subtask_index bigint unsigned not null
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/impulse.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE output (
+  remainder bigint,
+  counter bigint
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+INSERT INTO output
+SELECT counter % 5, counter
+FROM (
+  SELECT *, ROW_NUMBER() OVER (PARTITION BY counter % 5 ORDER BY timestamp DESC) as rn
+  FROM impulse
+) WHERE rn = 1;
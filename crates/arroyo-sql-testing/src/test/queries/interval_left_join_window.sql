@@ -0,0 +1,43 @@
+CREATE TABLE impulse (
+  timestamp TIMESTAMP,
+  counter bigint unsigned not null,
+  subtask_index bigint unsigned not null
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/impulse.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp',
+  watermark_field = 'timestamp'
+);
+
+CREATE VIEW impulse_third AS (
+  SELECT * FROM impulse
+  WHERE counter % 3 = 0
+);
+
+CREATE TABLE output (
+  window_end TIMESTAMP,
+  row_count bigint,
+  matched_count bigint
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+INSERT INTO output
+SELECT window.end, row_count, matched_count
+FROM (
+  SELECT TUMBLE(INTERVAL '2' SECOND) as window,
+    count(*) as row_count,
+    count(right_counter) as matched_count
+  FROM (
+    SELECT A.counter as left_counter, B.counter as right_counter
+    FROM impulse A
+    LEFT JOIN impulse_third B ON A.counter = B.counter
+      AND B.timestamp BETWEEN A.timestamp AND A.timestamp + INTERVAL '5' SECOND
+  )
+  GROUP BY 1
+);
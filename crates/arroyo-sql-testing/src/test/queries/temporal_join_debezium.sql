@@ -0,0 +1,40 @@
+-- the rates are timestamped when they're read, so orders far in the future join with the
+-- version of each rate that's current once the whole changelog has been read
+CREATE TABLE orders (
+  timestamp TIMESTAMP,
+  order_id BIGINT,
+  currency TEXT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/orders.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE currency_rates (
+  currency TEXT PRIMARY KEY,
+  rate DOUBLE
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/currency_rates.json',
+  format = 'debezium_json',
+  type = 'source'
+);
+
+CREATE TABLE output (
+  order_id BIGINT,
+  currency TEXT,
+  rate DOUBLE
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+INSERT INTO output
+SELECT o.order_id, o.currency, r.rate
+FROM orders o
+LEFT JOIN currency_rates FOR SYSTEM_TIME AS OF o.timestamp AS r
+  ON o.currency = r.currency;
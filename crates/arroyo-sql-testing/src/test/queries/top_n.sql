@@ -0,0 +1,30 @@
+CREATE TABLE impulse (
+  timestamp TIMESTAMP,
+  counter bigint unsigned not null,
+  subtask_index bigint unsigned not null
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/impulse.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE output (
+  parity bigint,
+  counter bigint,
+  position bigint
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'debezium_json',
+  type = 'sink'
+);
+
+INSERT INTO output
+SELECT parity, counter, CAST(rn AS BIGINT)
+FROM (
+  SELECT counter % 2 as parity, counter,
+    ROW_NUMBER() OVER (PARTITION BY counter % 2 ORDER BY counter % 10 DESC) as rn
+  FROM impulse
+) WHERE rn <= 2;
@@ -1,4 +1,4 @@
---fail=This feature is not implemented: can't handle non-inner joins without windows
+--fail=Error during planning: can't handle updating right side of join
 CREATE TABLE impulse (
       timestamp TIMESTAMP,
       counter bigint unsigned not null,
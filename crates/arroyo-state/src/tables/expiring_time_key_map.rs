@@ -6,14 +6,14 @@ use std::{
 };
 
 use anyhow::{anyhow, bail, Ok, Result};
-use arrow::compute::{concat_batches, filter_record_batch, kernels::aggregate, take};
+use arrow::compute::{concat_batches, filter_record_batch, kernels::aggregate, not, take};
 use arrow::row::OwnedRow;
 use arrow_array::{
     cast::AsArray,
     types::{TimestampNanosecondType, UInt64Type},
     BooleanArray, PrimitiveArray, RecordBatch, TimestampNanosecondArray, UInt64Array,
 };
-use arrow_ord::{partition::partition, sort::sort_to_indices};
+use arrow_ord::{cmp::gt_eq, partition::partition, sort::sort_to_indices};
 use arroyo_rpc::{
     df::server_for_hash_array,
     grpc::rpc::{
@@ -940,6 +940,19 @@ impl KeyTimeView {
     }
 
     pub async fn insert(&mut self, batch: RecordBatch) -> Result<Vec<OwnedRow>> {
+        Ok(self
+            .insert_partitioned(batch)
+            .await?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect())
+    }
+
+    /// Inserts the batch, returning each key in it along with its (unkeyed) rows
+    pub async fn insert_partitioned(
+        &mut self,
+        batch: RecordBatch,
+    ) -> Result<Vec<(OwnedRow, RecordBatch)>> {
         self.state_tx
             .send(StateMessage::TableData {
                 table: self.parent.table_name.to_string(),
@@ -949,7 +962,75 @@ impl KeyTimeView {
         Ok(self.insert_internal(batch)?)
     }
 
-    fn insert_internal(&mut self, batch: RecordBatch) -> Result<Vec<OwnedRow>> {
+    /// Returns all of the rows in the view, coalesced into one batch per key
+    pub fn all_batches(&mut self) -> Result<Vec<(Vec<u8>, RecordBatch)>> {
+        let keys: Vec<_> = self.keyed_data.keys().cloned().collect();
+        keys.into_iter()
+            .map(|key| {
+                let batch = self.get_batch(&key)?.expect("key was just listed").clone();
+                Ok((key, batch))
+            })
+            .collect()
+    }
+
+    /// Replaces the in-memory rows for the key, removing the key if the batch is empty. This
+    /// doesn't affect what's been written to state, which is only expired by retention.
+    pub fn replace_batch(&mut self, key: &[u8], batch: RecordBatch) {
+        if batch.num_rows() == 0 {
+            self.keyed_data.remove(key);
+        } else {
+            self.keyed_data
+                .insert(key.to_vec(), BatchData::SingleBatch(batch));
+        }
+    }
+
+    /// Removes the rows whose timestamps are before the watermark minus the table's retention,
+    /// so that memory is bounded by the retention rather than by when the data was checkpointed.
+    /// Returns the removed rows along with their keys.
+    pub fn expire(&mut self, watermark: Option<SystemTime>) -> Result<Vec<(Vec<u8>, RecordBatch)>> {
+        let Some(cutoff) =
+            watermark.and_then(|watermark| watermark.checked_sub(self.parent.retention))
+        else {
+            return Ok(vec![]);
+        };
+        let cutoff = TimestampNanosecondArray::new_scalar(
+            i64::try_from(to_nanos(cutoff)).unwrap_or(i64::MAX),
+        );
+        let timestamp_index = self.value_schema.timestamp_index;
+
+        let mut expired = vec![];
+        let mut emptied = vec![];
+        for (key, data) in self.keyed_data.iter_mut() {
+            let batches = match data {
+                BatchData::SingleBatch(batch) => std::slice::from_mut(batch),
+                BatchData::BatchVec(batches) => batches.as_mut_slice(),
+            };
+            let mut removed = vec![];
+            for batch in batches.iter_mut() {
+                let retained = gt_eq(batch.column(timestamp_index), &cutoff)?;
+                if retained.true_count() < batch.num_rows() {
+                    removed.push(filter_record_batch(batch, &not(&retained)?)?);
+                    *batch = filter_record_batch(batch, &retained)?;
+                }
+            }
+            if !removed.is_empty() {
+                expired.push((
+                    key.clone(),
+                    concat_batches(&self.value_schema.schema, removed.iter())?,
+                ));
+            }
+            if batches.iter().all(|batch| batch.num_rows() == 0) {
+                emptied.push(key.clone());
+            }
+        }
+
+        for key in emptied {
+            self.keyed_data.remove(&key);
+        }
+        Ok(expired)
+    }
+
+    fn insert_internal(&mut self, batch: RecordBatch) -> Result<Vec<(OwnedRow, RecordBatch)>> {
         let sorted_batch = self.schema.sort(batch, false)?;
         let value_batch = sorted_batch.project(&self.value_indices)?;
         let mut rows = vec![];
@@ -966,7 +1047,7 @@ impl KeyTimeView {
             };
             let key_row = self.key_converter.convert_columns(&key_columns)?;
             let contents = self.keyed_data.get_mut(key_row.as_ref());
-            rows.push(key_row.clone());
+            rows.push((key_row.clone(), value_batch.clone()));
             let batch = match contents {
                 Some(BatchData::BatchVec(vec)) => {
                     vec.push(value_batch);
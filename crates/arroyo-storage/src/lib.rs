@@ -496,6 +496,19 @@ impl StorageProvider {
         &self,
         include_subdirectories: bool,
     ) -> Result<impl Stream<Item = Result<Path, object_store::Error>> + '_, StorageError> {
+        Ok(self
+            .list_with_metadata(include_subdirectories)
+            .await?
+            .map(|meta| meta.map(|meta| meta.location)))
+    }
+
+    /// Like [`Self::list`], but returns the full metadata (including the last modified time)
+    /// for each object
+    pub async fn list_with_metadata(
+        &self,
+        include_subdirectories: bool,
+    ) -> Result<impl Stream<Item = Result<ObjectMeta, object_store::Error>> + '_, StorageError>
+    {
         let key_path: Option<Path> = self.config.key().map(|key| key.to_string().into());
         let key_part_count = key_path
             .as_ref()
@@ -508,12 +521,12 @@ impl StorageProvider {
                 let result = {
                     match meta {
                         Ok(metadata) => {
-                            let path = metadata.location;
-                            if !include_subdirectories && path.parts().count() != key_part_count + 1
+                            if !include_subdirectories
+                                && metadata.location.parts().count() != key_part_count + 1
                             {
                                 None
                             } else {
-                                Some(Ok(path))
+                                Some(Ok(metadata))
                             }
                         }
                         Err(err) => Some(Err(err)),
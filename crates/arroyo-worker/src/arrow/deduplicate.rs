@@ -0,0 +1,453 @@
+use anyhow::Result;
+use arrow::compute::{concat_batches, take_record_batch};
+use arrow_array::{RecordBatch, TimestampNanosecondArray, UInt32Array, UInt64Array};
+use arrow_schema::SchemaRef;
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, DisplayableOperator, OperatorConstructor, OperatorNode, Registry,
+};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::{api, rpc::TableConfig};
+use arroyo_rpc::Converter;
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{from_nanos, to_nanos, Watermark};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Deduplicates rows by key, producing an append-only output. When keeping the first row, a key's
+/// first row is emitted as soon as it arrives, and later rows for the key are dropped until the
+/// ttl has passed since it. When keeping the last row, the latest row for each key is held until
+/// no rows have arrived for the key within the ttl, and is then emitted with the event time of the
+/// watermark that released it, so that it isn't behind the watermark downstream. The ttl is
+/// therefore also how long keep-last rows are delayed.
+pub struct Deduplicate {
+    input_schema: ArroyoSchemaRef,
+    value_schema: ArroyoSchema,
+    output_schema: SchemaRef,
+    keep_last: bool,
+    ttl: Duration,
+    key_converter: Converter,
+    /// when keeping the last row, the keys that are held by the event time of their rows
+    expirations: BTreeMap<SystemTime, HashSet<Vec<u8>>>,
+}
+
+impl Deduplicate {
+    fn held_time(&self, batch: &RecordBatch) -> i64 {
+        self.value_schema.timestamp_column(batch).value(0)
+    }
+
+    fn with_row_number(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(UInt64Array::from_value(1, batch.num_rows())));
+        Ok(RecordBatch::try_new(self.output_schema.clone(), columns)?)
+    }
+
+    async fn process(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) -> Result<()> {
+        let table = ctx
+            .table_manager
+            .get_key_time_table("deduplicate", ctx.last_present_watermark())
+            .await
+            .expect("should have deduplicate table");
+
+        let key_indices = self.input_schema.key_indices.clone().unwrap_or_default();
+        let sorted = self.input_schema.sort(batch, false)?;
+        let unkeyed = self.input_schema.unkeyed_batch(&sorted)?;
+        let timestamps = self.input_schema.timestamp_column(&sorted).values();
+
+        let mut held = vec![];
+        let mut emitted = vec![];
+        for range in self.input_schema.partition(&sorted, false)? {
+            let key = self
+                .key_converter
+                .convert_columns(
+                    sorted
+                        .slice(range.start, 1)
+                        .project(&key_indices)?
+                        .columns(),
+                )?
+                .as_ref()
+                .to_vec();
+
+            // the earliest (or, keeping the last, the latest) of the key's rows in this batch
+            let candidates = timestamps[range.clone()].iter().enumerate();
+            let (offset, time) = if self.keep_last {
+                candidates.max_by_key(|(_, t)| **t)
+            } else {
+                candidates.min_by_key(|(_, t)| **t)
+            }
+            .expect("partitions are non-empty");
+            let (index, time) = (range.start + offset, *time);
+
+            let held_time = table.get_batch(&key)?.map(|b| self.held_time(b));
+            if self.keep_last {
+                if held_time.is_some_and(|t| t > time) {
+                    continue;
+                }
+                self.expirations
+                    .entry(from_nanos(time as u128))
+                    .or_default()
+                    .insert(key.clone());
+            } else {
+                if held_time.is_some() {
+                    continue;
+                }
+                emitted.push(index as u32);
+            }
+
+            table.replace_batch(&key, unkeyed.slice(index, 1));
+            held.push(index as u32);
+        }
+
+        let held = take_record_batch(&sorted, &UInt32Array::from(held))?;
+        if held.num_rows() > 0 {
+            table.write_batch_to_state(held).await?;
+        }
+
+        if !emitted.is_empty() {
+            let emitted = take_record_batch(&unkeyed, &UInt32Array::from(emitted))?;
+            ctx.collect(self.with_row_number(emitted)?).await;
+        }
+        Ok(())
+    }
+
+    /// Emits the last rows of the keys that haven't had rows within the ttl
+    async fn emit_last(&mut self, watermark: SystemTime, ctx: &mut ArrowContext) -> Result<()> {
+        let Some(cutoff) = watermark.checked_sub(self.ttl) else {
+            return Ok(());
+        };
+
+        let table = ctx
+            .table_manager
+            .get_key_time_table("deduplicate", Some(watermark))
+            .await
+            .expect("should have deduplicate table");
+
+        let remaining = self.expirations.split_off(&cutoff);
+        let expired = std::mem::replace(&mut self.expirations, remaining);
+
+        let mut rows = vec![];
+        for key in expired.into_values().flatten() {
+            let Some(batch) = table.get_batch(&key)? else {
+                continue;
+            };
+            // keys with later rows are still waiting, under the time of those rows
+            if from_nanos(self.held_time(batch) as u128) < cutoff {
+                rows.push(batch.clone());
+                table.replace_batch(
+                    &key,
+                    RecordBatch::new_empty(self.value_schema.schema.clone()),
+                );
+            }
+        }
+
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let batch = concat_batches(&self.value_schema.schema, rows.iter())?;
+        let mut columns = batch.columns().to_vec();
+        columns[self.value_schema.timestamp_index] = Arc::new(
+            TimestampNanosecondArray::from_value(to_nanos(watermark) as i64, batch.num_rows()),
+        );
+        let batch = RecordBatch::try_new(batch.schema(), columns)?;
+        ctx.collect(self.with_row_number(batch)?).await;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for Deduplicate {
+    fn name(&self) -> String {
+        "Deduplicate".to_string()
+    }
+
+    fn display(&self) -> DisplayableOperator {
+        DisplayableOperator {
+            name: Cow::Borrowed("Deduplicate"),
+            fields: vec![
+                ("keep_last", AsDisplayable::Debug(&self.keep_last)),
+                ("ttl", AsDisplayable::Debug(&self.ttl)),
+            ],
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let table = ctx
+            .table_manager
+            .get_key_time_table("deduplicate", ctx.last_present_watermark())
+            .await
+            .expect("should have deduplicate table");
+
+        // state may have more than one row for a key, of which the latest is the one that's held
+        for (key, batch) in table.all_batches().expect("should read deduplicate table") {
+            let timestamps = self.value_schema.timestamp_column(&batch).values();
+            let Some((index, time)) = timestamps.iter().enumerate().max_by_key(|(_, t)| **t) else {
+                continue;
+            };
+
+            if self.keep_last {
+                self.expirations
+                    .entry(from_nanos(*time as u128))
+                    .or_default()
+                    .insert(key.clone());
+            }
+            table.replace_batch(&key, batch.slice(index, 1));
+        }
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        self.process(batch, ctx)
+            .await
+            .expect("should process deduplicate batch");
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        let Some(last_watermark) = ctx.last_present_watermark() else {
+            return Some(watermark);
+        };
+
+        if self.keep_last {
+            self.emit_last(last_watermark, ctx)
+                .await
+                .expect("should emit last rows");
+        } else {
+            ctx.table_manager
+                .get_key_time_table("deduplicate", Some(last_watermark))
+                .await
+                .expect("should have deduplicate table")
+                .expire(Some(last_watermark))
+                .expect("should expire seen keys");
+        }
+
+        Some(watermark)
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "deduplicate".to_string(),
+            timestamp_table_config(
+                "deduplicate",
+                "deduplicated rows",
+                self.ttl,
+                false,
+                self.input_schema.as_ref().clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct DeduplicateConstructor;
+impl OperatorConstructor for DeduplicateConstructor {
+    type ConfigT = api::DeduplicateOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        _registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let mut input_schema: ArroyoSchema = config.input_schema.unwrap().try_into()?;
+        // without a PARTITION BY every row has the same (empty) key
+        if input_schema
+            .key_indices
+            .as_ref()
+            .is_some_and(|k| k.is_empty())
+        {
+            input_schema.key_indices = None;
+        }
+        let input_schema = Arc::new(input_schema);
+        let output_schema: ArroyoSchema = config.output_schema.unwrap().try_into()?;
+
+        Ok(OperatorNode::from_operator(Box::new(Deduplicate {
+            key_converter: input_schema.converter(false)?,
+            value_schema: input_schema.schema_without_keys()?,
+            input_schema,
+            output_schema: output_schema.schema,
+            keep_last: config.keep_last,
+            ttl: Duration::from_micros(config.ttl_micros),
+            expirations: BTreeMap::new(),
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::TimestampNanosecondType;
+    use arrow_array::{StringArray, TimestampNanosecondArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use arroyo_operator::context::{batch_bounded, BatchReceiver};
+    use arroyo_rpc::TIMESTAMP_FIELD;
+    use arroyo_types::{get_test_task_info, ArrowMessage};
+    use rand::random;
+    use tokio::sync::mpsc::channel;
+
+    fn input_schema() -> ArroyoSchema {
+        ArroyoSchema::new_keyed(
+            Arc::new(Schema::new(vec![
+                Field::new("_key_0", DataType::Utf8, false),
+                Field::new("value", DataType::Utf8, false),
+                Field::new(
+                    TIMESTAMP_FIELD,
+                    DataType::Timestamp(TimeUnit::Nanosecond, None),
+                    false,
+                ),
+            ])),
+            2,
+            vec![0],
+        )
+    }
+
+    /// (key, value, event time in seconds)
+    fn batch(rows: &[(&str, &str, u64)]) -> RecordBatch {
+        RecordBatch::try_new(
+            input_schema().schema,
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.1))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    rows.iter().map(|r| r.2 as i64 * 1_000_000_000),
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn deduplicate(keep_last: bool) -> (Deduplicate, ArrowContext, BatchReceiver) {
+        let input_schema = Arc::new(input_schema());
+        let value_schema = input_schema.schema_without_keys().unwrap();
+        let mut fields = value_schema.schema.fields().to_vec();
+        fields.push(Arc::new(Field::new("row_number", DataType::UInt64, false)));
+        let output_schema = Arc::new(Schema::new(fields));
+
+        let mut deduplicate = Deduplicate {
+            key_converter: input_schema.converter(false).unwrap(),
+            input_schema: input_schema.clone(),
+            value_schema: value_schema.clone(),
+            output_schema: output_schema.clone(),
+            keep_last,
+            ttl: Duration::from_secs(10),
+            expirations: BTreeMap::new(),
+        };
+
+        let (_, control_rx) = channel(128);
+        let (command_tx, _) = channel(128);
+        let (data_tx, data_rx) = batch_bounded(128);
+
+        let mut task_info = get_test_task_info();
+        task_info.job_id = format!("deduplicate-{}", random::<u64>());
+
+        let mut ctx = ArrowContext::new(
+            task_info,
+            None,
+            control_rx,
+            command_tx,
+            1,
+            vec![input_schema.as_ref().clone()],
+            Some(ArroyoSchema::new_unkeyed(
+                output_schema,
+                value_schema.timestamp_index,
+            )),
+            None,
+            vec![vec![data_tx]],
+            vec![],
+            deduplicate.tables(),
+        )
+        .await;
+
+        deduplicate.on_start(&mut ctx).await;
+        (deduplicate, ctx, data_rx)
+    }
+
+    /// The output rows as (value, event time in seconds)
+    async fn output(data_rx: &mut BatchReceiver) -> Vec<(String, u64)> {
+        let Ok(Some(ArrowMessage::Data(batch))) =
+            tokio::time::timeout(Duration::from_millis(100), data_rx.recv()).await
+        else {
+            return vec![];
+        };
+
+        let values = batch.column(0).as_string::<i32>();
+        let timestamps = batch.column(1).as_primitive::<TimestampNanosecondType>();
+        let mut rows: Vec<_> = (0..batch.num_rows())
+            .map(|i| {
+                (
+                    values.value(i).to_string(),
+                    timestamps.value(i) as u64 / 1_000_000_000,
+                )
+            })
+            .collect();
+        rows.sort();
+        rows
+    }
+
+    async fn advance_watermark(
+        deduplicate: &mut Deduplicate,
+        ctx: &mut ArrowContext,
+        seconds: u64,
+    ) {
+        let watermark = Watermark::EventTime(from_nanos(seconds as u128 * 1_000_000_000));
+        ctx.watermarks.set(0, watermark);
+        deduplicate.handle_watermark(watermark, ctx).await;
+    }
+
+    fn row(value: &str, time: u64) -> (String, u64) {
+        (value.to_string(), time)
+    }
+
+    #[tokio::test]
+    async fn test_keep_first_until_expired() {
+        let (mut deduplicate, mut ctx, mut data_rx) = deduplicate(false).await;
+
+        deduplicate
+            .process_batch(
+                batch(&[("a", "x", 1), ("a", "y", 2), ("b", "z", 3)]),
+                &mut ctx,
+            )
+            .await;
+        assert_eq!(output(&mut data_rx).await, vec![row("x", 1), row("z", 3)]);
+
+        deduplicate
+            .process_batch(batch(&[("a", "w", 4)]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await, vec![]);
+
+        // once the ttl has passed since a's first row, it's forgotten
+        advance_watermark(&mut deduplicate, &mut ctx, 12).await;
+        deduplicate
+            .process_batch(batch(&[("a", "v", 13)]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await, vec![row("v", 13)]);
+    }
+
+    #[tokio::test]
+    async fn test_keep_last_once_expired() {
+        let (mut deduplicate, mut ctx, mut data_rx) = deduplicate(true).await;
+
+        deduplicate
+            .process_batch(
+                batch(&[("a", "x", 1), ("a", "y", 2), ("b", "z", 3)]),
+                &mut ctx,
+            )
+            .await;
+        deduplicate
+            .process_batch(batch(&[("a", "w", 15)]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await, vec![]);
+
+        // b has had no rows within the ttl, while a has, so only b's last row is emitted, at the
+        // watermark so that it isn't late downstream
+        advance_watermark(&mut deduplicate, &mut ctx, 20).await;
+        assert_eq!(output(&mut data_rx).await, vec![row("z", 20)]);
+
+        advance_watermark(&mut deduplicate, &mut ctx, 30).await;
+        assert_eq!(output(&mut data_rx).await, vec![row("w", 30)]);
+    }
+}
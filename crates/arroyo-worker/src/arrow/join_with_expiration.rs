@@ -1,6 +1,10 @@
 use anyhow::Result;
-use arrow::compute::concat_batches;
-use arrow_array::RecordBatch;
+use arrow::compute::{concat_batches, filter_record_batch, not};
+use arrow::row::{Row, RowConverter, SortField};
+use arrow_array::cast::AsArray;
+use arrow_array::types::TimestampNanosecondType;
+use arrow_array::{ArrayRef, BooleanArray, FixedSizeBinaryArray, RecordBatch, StructArray};
+use arrow_schema::SchemaRef;
 use arroyo_df::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::{
@@ -9,22 +13,151 @@ use arroyo_operator::operator::{
 use arroyo_rpc::{
     df::ArroyoSchema,
     grpc::{api, rpc::TableConfig},
+    updating_meta_fields, UPDATING_META_FIELD,
 };
 use arroyo_state::timestamp_table_config;
+use arroyo_types::{to_nanos, Watermark};
 use datafusion::execution::context::SessionContext;
 use datafusion::execution::runtime_env::{RuntimeConfig, RuntimeEnv};
-use datafusion::physical_plan::ExecutionPlan;
+use datafusion::physical_plan::{ExecutionPlan, SendableRecordBatchStream};
 use datafusion_proto::{physical_plan::AsExecutionPlan, protobuf::PhysicalPlanNode};
 use futures::StreamExt;
+use md5::{Digest, Md5};
 use prost::Message;
 use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
 use std::{
     collections::HashMap,
     sync::{Arc, RwLock},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 use tracing::warn;
 
+/// The rows on one side of an outer interval join that may still be matched by the other side.
+/// Once a row's interval has passed, it's joined one last time against the other side's rows for
+/// its key, producing a null-padded row if there was never a match.
+struct UnmatchedRows {
+    /// how long after (or, if negative, before) a row's event time it may still be matched, in
+    /// nanos
+    offset: i128,
+    timestamp_index: usize,
+    /// batches of rows along with their key, by the earliest event time in the batch
+    pending: BTreeMap<i64, Vec<(Vec<u8>, RecordBatch)>>,
+    plan: Arc<dyn ExecutionPlan>,
+}
+
+impl UnmatchedRows {
+    fn timestamps(&self, batch: &RecordBatch) -> Vec<i64> {
+        batch
+            .column(self.timestamp_index)
+            .as_primitive::<TimestampNanosecondType>()
+            .values()
+            .to_vec()
+    }
+
+    fn insert(&mut self, key: Vec<u8>, batch: RecordBatch) {
+        let Some(min) = self.timestamps(&batch).into_iter().min() else {
+            return;
+        };
+        self.pending.entry(min).or_default().push((key, batch));
+    }
+
+    /// Removes and returns the rows whose interval is over as of the watermark
+    fn take_expired(&mut self, watermark: i128) -> Vec<(Vec<u8>, RecordBatch)> {
+        let mut expired = vec![];
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() as i128 + self.offset >= watermark {
+                break;
+            }
+
+            for (key, batch) in entry.remove() {
+                let is_expired: BooleanArray = self
+                    .timestamps(&batch)
+                    .into_iter()
+                    .map(|t| Some((t as i128 + self.offset) < watermark))
+                    .collect();
+
+                let remaining = filter_record_batch(&batch, &not(&is_expired).unwrap()).unwrap();
+                expired.push((
+                    key.clone(),
+                    filter_record_batch(&batch, &is_expired).unwrap(),
+                ));
+                self.insert(key, remaining);
+            }
+        }
+        expired
+    }
+}
+
+/// For outer joins of unwindowed streams, rows on an outer side are emitted null-padded as soon as
+/// they arrive if they don't match, and retracted once they're matched. When rows expire after the
+/// TTL, the rows they were joined into are retracted, so the output is the join of the rows within
+/// the TTL.
+struct UpdatingOuter {
+    left_unmatched: Option<Arc<dyn ExecutionPlan>>,
+    right_unmatched: Option<Arc<dyn ExecutionPlan>>,
+    /// converts output rows (less their updating metadata) to bytes, to compare and identify them
+    row_converter: RowConverter,
+    output_schema: SchemaRef,
+}
+
+impl UpdatingOuter {
+    /// The changes that turn the output `before` into `after`: retractions of the rows `after`
+    /// has fewer copies of, followed by the rows it has more copies of. Each row is identified by
+    /// a hash of its values and which copy it is, so that identical rows have their own ids and a
+    /// retraction has the same id as the row it retracts.
+    fn changes(&self, before: &[RecordBatch], after: &[RecordBatch]) -> Result<RecordBatch> {
+        let convert = |batches: &[RecordBatch]| {
+            batches
+                .iter()
+                .map(|batch| self.row_converter.convert_columns(batch.columns()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let (before, after) = (convert(before)?, convert(after)?);
+
+        let mut counts: BTreeMap<Row, (usize, usize)> = BTreeMap::new();
+        for row in before.iter().flat_map(|rows| rows.iter()) {
+            counts.entry(row).or_default().0 += 1;
+        }
+        for row in after.iter().flat_map(|rows| rows.iter()) {
+            counts.entry(row).or_default().1 += 1;
+        }
+
+        let mut retractions = vec![];
+        let mut insertions = vec![];
+        for (row, (before, after)) in counts {
+            retractions.extend((after..before).map(|copy| (row, copy)));
+            insertions.extend((before..after).map(|copy| (row, copy)));
+        }
+        let is_retract: BooleanArray = retractions
+            .iter()
+            .map(|_| Some(true))
+            .chain(insertions.iter().map(|_| Some(false)))
+            .collect();
+        let rows: Vec<_> = retractions.into_iter().chain(insertions).collect();
+
+        let ids = FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+            rows.iter().map(|(row, copy)| {
+                let mut hasher = Md5::new();
+                hasher.update(row.as_ref());
+                hasher.update(copy.to_le_bytes());
+                Some(hasher.finalize())
+            }),
+            16,
+        )?;
+
+        let mut columns = self
+            .row_converter
+            .convert_rows(rows.iter().map(|(row, _)| *row))?;
+        columns.push(Arc::new(StructArray::new(
+            updating_meta_fields(),
+            vec![Arc::new(is_retract) as ArrayRef, Arc::new(ids)],
+            None,
+        )));
+        Ok(RecordBatch::try_new(self.output_schema.clone(), columns)?)
+    }
+}
+
 pub struct JoinWithExpiration {
     left_expiration: Duration,
     right_expiration: Duration,
@@ -35,6 +168,16 @@ pub struct JoinWithExpiration {
     left_passer: Arc<RwLock<Option<RecordBatch>>>,
     right_passer: Arc<RwLock<Option<RecordBatch>>>,
     join_execution_plan: Arc<dyn ExecutionPlan>,
+    /// for interval joins, state is expired by watermark rather than by TTL
+    is_interval: bool,
+    left_unmatched: Option<UnmatchedRows>,
+    right_unmatched: Option<UnmatchedRows>,
+    /// how far the forwarded watermark is held back for an outer interval join. Null-padded rows
+    /// are emitted once the watermark passes the end of their interval, so they keep their event
+    /// time behind the watermark that triggered them; holding the watermark back by the longest
+    /// interval keeps them at or after the last watermark sent downstream.
+    watermark_delay: Duration,
+    updating_outer: Option<UpdatingOuter>,
 }
 
 impl JoinWithExpiration {
@@ -49,7 +192,7 @@ impl JoinWithExpiration {
             .await
             .expect("should have left table");
         let left_rows = left_table
-            .insert(record_batch.clone())
+            .insert_partitioned(record_batch.clone())
             .await
             .expect("should insert");
         let right_table = ctx
@@ -58,7 +201,7 @@ impl JoinWithExpiration {
             .await
             .expect("should have right table");
         let mut right_batches = vec![];
-        for row in left_rows {
+        for (row, _) in &left_rows {
             if let Some(batch) = right_table
                 .get_batch(row.as_ref())
                 .expect("shouldn't error getting batch")
@@ -68,11 +211,18 @@ impl JoinWithExpiration {
         }
         let right_batch = concat_batches(&self.right_schema.schema, right_batches.iter()).unwrap();
         self.compute_pair(
+            self.join_execution_plan.clone(),
             self.left_input_schema.unkeyed_batch(&record_batch)?,
             right_batch,
             ctx,
         )
         .await;
+
+        if let Some(unmatched) = &mut self.left_unmatched {
+            for (row, batch) in left_rows {
+                unmatched.insert(row.as_ref().to_vec(), batch);
+            }
+        }
         Ok(())
     }
 
@@ -87,7 +237,7 @@ impl JoinWithExpiration {
             .await
             .expect("should have right table");
         let right_rows = right_table
-            .insert(right_batch.clone())
+            .insert_partitioned(right_batch.clone())
             .await
             .expect("should insert");
         let left_table = ctx
@@ -96,7 +246,7 @@ impl JoinWithExpiration {
             .await
             .expect("should have left table");
         let mut left_batches = vec![];
-        for row in right_rows {
+        for (row, _) in &right_rows {
             if let Some(batch) = left_table
                 .get_batch(row.as_ref())
                 .expect("shouldn't error getting batch")
@@ -106,34 +256,282 @@ impl JoinWithExpiration {
         }
         let left_batch = concat_batches(&self.left_schema.schema, left_batches.iter()).unwrap();
         self.compute_pair(
+            self.join_execution_plan.clone(),
             left_batch,
             self.right_input_schema.unkeyed_batch(&right_batch)?,
             ctx,
         )
         .await;
+
+        if let Some(unmatched) = &mut self.right_unmatched {
+            for (row, batch) in right_rows {
+                unmatched.insert(row.as_ref().to_vec(), batch);
+            }
+        }
         Ok(())
     }
 
+    /// Processes a batch for an outer join of unwindowed streams. The output for the keys in the
+    /// batch is computed before and after its rows are added, and the difference is emitted, so
+    /// rows on an outer side are emitted null-padded until they're matched and then retracted.
+    ///
+    /// This relies on the output emitted so far being the join of the rows in state, which
+    /// `expire_updating` maintains as rows expire. Every stored row of the batch's keys is joined
+    /// twice per batch, so the work for each row grows with the number of rows its key has within
+    /// the TTL, and a key with n rows costs O(n²) overall; joins on keys with many rows per TTL
+    /// should use a shorter TTL or an interval join.
+    async fn process_updating(
+        &mut self,
+        side: &str,
+        batch: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) -> Result<()> {
+        let (other_side, schema, other_schema) = match side {
+            "left" => ("right", &self.left_schema, &self.right_schema),
+            _ => ("left", &self.right_schema, &self.left_schema),
+        };
+        let (schema, other_schema) = (schema.schema.clone(), other_schema.schema.clone());
+
+        let table = ctx
+            .table_manager
+            .get_key_time_table(side, ctx.last_present_watermark())
+            .await
+            .expect("should have table");
+        let new_rows = table.insert_partitioned(batch).await?;
+
+        // the rows for each key before and after this batch; new rows are appended to the end of
+        // a key's rows
+        let mut previous = vec![];
+        let mut current = vec![];
+        for (key, rows) in &new_rows {
+            let all = table
+                .get_batch(key.as_ref())?
+                .expect("key was just inserted");
+            previous.push(all.slice(0, all.num_rows() - rows.num_rows()));
+            current.push(all.clone());
+        }
+
+        let other_table = ctx
+            .table_manager
+            .get_key_time_table(other_side, ctx.last_present_watermark())
+            .await
+            .expect("should have table");
+        let mut other_batches = vec![];
+        for (key, _) in &new_rows {
+            if let Some(batch) = other_table.get_batch(key.as_ref())? {
+                other_batches.push(batch.clone());
+            }
+        }
+
+        let other = concat_batches(&other_schema, other_batches.iter())?;
+        let previous = concat_batches(&schema, previous.iter())?;
+        let current = concat_batches(&schema, current.iter())?;
+        let (before, after) = match side {
+            "left" => (
+                self.updating_output(previous, other.clone()).await?,
+                self.updating_output(current, other).await?,
+            ),
+            _ => (
+                self.updating_output(other.clone(), previous).await?,
+                self.updating_output(other, current).await?,
+            ),
+        };
+
+        let output = self
+            .updating_outer
+            .as_ref()
+            .expect("should be an updating outer join")
+            .changes(&before, &after)?;
+        if output.num_rows() > 0 {
+            ctx.collect(output).await;
+        }
+        Ok(())
+    }
+
+    /// Expires the rows of an updating outer join that are older than the TTL, emitting the
+    /// changes to the output of their keys: the rows they were joined into are retracted, and
+    /// rows on an outer side that are left without a match are emitted null-padded again. This
+    /// keeps the output emitted so far equal to the join of the rows still in state, which is
+    /// what the next batch for the key is compared against.
+    async fn expire_updating(&mut self, time: SystemTime, ctx: &mut ArrowContext) -> Result<()> {
+        // the rows removed from each side, by key
+        let mut expired: HashMap<Vec<u8>, [Vec<RecordBatch>; 2]> = HashMap::new();
+        for (i, side) in ["left", "right"].into_iter().enumerate() {
+            let removed = ctx
+                .table_manager
+                .get_key_time_table(side, Some(time))
+                .await
+                .expect("should have table")
+                .expire(Some(time))?;
+            for (key, rows) in removed {
+                expired.entry(key).or_default()[i].push(rows);
+            }
+        }
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        // the rows of the affected keys on each side, before and after they expired
+        let mut before: [Vec<RecordBatch>; 2] = Default::default();
+        let mut after: [Vec<RecordBatch>; 2] = Default::default();
+        for (i, side) in ["left", "right"].into_iter().enumerate() {
+            let table = ctx
+                .table_manager
+                .get_key_time_table(side, Some(time))
+                .await
+                .expect("should have table");
+            for (key, removed) in &expired {
+                if let Some(remaining) = table.get_batch(key)? {
+                    before[i].push(remaining.clone());
+                    after[i].push(remaining.clone());
+                }
+                before[i].extend(removed[i].iter().cloned());
+            }
+        }
+
+        let [left_before, right_before] = before;
+        let [left_after, right_after] = after;
+        let (left, right) = (&self.left_schema.schema, &self.right_schema.schema);
+        let before = self
+            .updating_output(
+                concat_batches(left, left_before.iter())?,
+                concat_batches(right, right_before.iter())?,
+            )
+            .await?;
+        let after = self
+            .updating_output(
+                concat_batches(left, left_after.iter())?,
+                concat_batches(right, right_after.iter())?,
+            )
+            .await?;
+
+        let output = self
+            .updating_outer
+            .as_ref()
+            .expect("should be an updating outer join")
+            .changes(&before, &after)?;
+        if output.num_rows() > 0 {
+            ctx.collect(output).await;
+        }
+        Ok(())
+    }
+
+    /// The output of an updating outer join for the rows: those that matched, along with the
+    /// unmatched rows of its outer sides, null-padded
+    async fn updating_output(
+        &self,
+        left: RecordBatch,
+        right: RecordBatch,
+    ) -> Result<Vec<RecordBatch>> {
+        let outer = self
+            .updating_outer
+            .as_ref()
+            .expect("should be an updating outer join");
+
+        let plans = [
+            Some(self.join_execution_plan.clone()),
+            outer.left_unmatched.clone(),
+            outer.right_unmatched.clone(),
+        ];
+        let mut output = vec![];
+        for plan in plans.into_iter().flatten() {
+            output.push(self.execute_pair(plan, left.clone(), right.clone()).await?);
+        }
+        Ok(output)
+    }
+
+    /// Finds the rows of `side` whose interval has passed, and joins them against the rows with
+    /// the same keys on the other side to emit the ones that never matched, null-padded
+    async fn emit_unmatched(&mut self, side: &str, watermark: i128, ctx: &mut ArrowContext) {
+        let (unmatched, other_side, schema) = match side {
+            "left" => (&mut self.left_unmatched, "right", &self.left_schema),
+            _ => (&mut self.right_unmatched, "left", &self.right_schema),
+        };
+        let Some(unmatched) = unmatched else {
+            return;
+        };
+
+        let expired = unmatched.take_expired(watermark);
+        if expired.is_empty() {
+            return;
+        }
+        let plan = unmatched.plan.clone();
+
+        let other_table = ctx
+            .table_manager
+            .get_key_time_table(other_side, ctx.last_present_watermark())
+            .await
+            .expect("should have table");
+
+        let mut keys = HashSet::new();
+        let mut other_batches = vec![];
+        for (key, _) in &expired {
+            if keys.insert(key) {
+                if let Some(batch) = other_table
+                    .get_batch(key)
+                    .expect("shouldn't error getting batch")
+                {
+                    other_batches.push(batch.clone());
+                }
+            }
+        }
+
+        let batch = concat_batches(&schema.schema, expired.iter().map(|(_, b)| b)).unwrap();
+        let (left, right) = if side == "left" {
+            let other = concat_batches(&self.right_schema.schema, other_batches.iter()).unwrap();
+            (batch, other)
+        } else {
+            let other = concat_batches(&self.left_schema.schema, other_batches.iter()).unwrap();
+            (other, batch)
+        };
+
+        self.compute_pair(plan, left, right, ctx).await;
+    }
+
     async fn compute_pair(
         &mut self,
+        plan: Arc<dyn ExecutionPlan>,
         left: RecordBatch,
         right: RecordBatch,
         ctx: &mut ArrowContext,
     ) {
-        {
-            self.right_passer.write().unwrap().replace(right);
-            self.left_passer.write().unwrap().replace(left);
-        }
-        self.join_execution_plan.reset().unwrap();
-        let mut records = self
-            .join_execution_plan
-            .execute(0, SessionContext::new().task_ctx())
-            .expect("successfully computed?");
+        let mut records = self.execute(plan, left, right);
         while let Some(batch) = records.next().await {
             let batch = batch.expect("should be able to compute batch");
             ctx.collect(batch).await;
         }
     }
+
+    /// Like `compute_pair`, but returns the joined rows rather than emitting them
+    async fn execute_pair(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        left: RecordBatch,
+        right: RecordBatch,
+    ) -> Result<RecordBatch> {
+        let schema = plan.schema();
+        let mut records = self.execute(plan, left, right);
+        let mut batches = vec![];
+        while let Some(batch) = records.next().await {
+            batches.push(batch?);
+        }
+        Ok(concat_batches(&schema, batches.iter())?)
+    }
+
+    fn execute(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        left: RecordBatch,
+        right: RecordBatch,
+    ) -> SendableRecordBatchStream {
+        {
+            self.right_passer.write().unwrap().replace(right);
+            self.left_passer.write().unwrap().replace(left);
+        }
+        plan.reset().unwrap();
+        plan.execute(0, SessionContext::new().task_ctx())
+            .expect("successfully computed?")
+    }
 }
 
 #[async_trait::async_trait]
@@ -143,22 +541,65 @@ impl ArrowOperator for JoinWithExpiration {
     }
 
     fn display(&self) -> DisplayableOperator {
+        let mut fields = vec![
+            (
+                "left_expiration",
+                AsDisplayable::Debug(&self.left_expiration),
+            ),
+            (
+                "right_expiration",
+                AsDisplayable::Debug(&self.right_expiration),
+            ),
+            (
+                "join_execution_plan",
+                self.join_execution_plan.as_ref().into(),
+            ),
+        ];
+        if let Some(unmatched) = &self.left_unmatched {
+            fields.push(("left_unmatched_plan", unmatched.plan.as_ref().into()));
+        }
+        if let Some(unmatched) = &self.right_unmatched {
+            fields.push(("right_unmatched_plan", unmatched.plan.as_ref().into()));
+        }
+        if let Some(outer) = &self.updating_outer {
+            if let Some(plan) = &outer.left_unmatched {
+                fields.push(("left_unmatched_plan", plan.as_ref().into()));
+            }
+            if let Some(plan) = &outer.right_unmatched {
+                fields.push(("right_unmatched_plan", plan.as_ref().into()));
+            }
+        }
+
         DisplayableOperator {
             name: Cow::Borrowed("JoinWithExpiration"),
-            fields: vec![
-                (
-                    "left_expiration",
-                    AsDisplayable::Debug(&self.left_expiration),
-                ),
-                (
-                    "right_expiration",
-                    AsDisplayable::Debug(&self.right_expiration),
-                ),
-                (
-                    "join_execution_plan",
-                    self.join_execution_plan.as_ref().into(),
-                ),
-            ],
+            fields,
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        // restore the rows that may still be emitted unmatched; those whose interval had passed
+        // as of the restored watermark were handled before the checkpoint
+        let watermark = ctx.last_present_watermark();
+        for (side, unmatched) in [
+            ("left", &mut self.left_unmatched),
+            ("right", &mut self.right_unmatched),
+        ] {
+            let Some(unmatched) = unmatched else {
+                continue;
+            };
+
+            let table = ctx
+                .table_manager
+                .get_key_time_table(side, watermark)
+                .await
+                .expect("should have table");
+            for (key, batch) in table.all_batches().expect("should read table") {
+                unmatched.insert(key, batch);
+            }
+
+            if let Some(watermark) = watermark {
+                unmatched.take_expired(to_nanos(watermark) as i128);
+            }
         }
     }
 
@@ -172,6 +613,18 @@ impl ArrowOperator for JoinWithExpiration {
         record_batch: RecordBatch,
         ctx: &mut ArrowContext,
     ) {
+        if self.updating_outer.is_some() {
+            let side = match index / (total_inputs / 2) {
+                0 => "left",
+                1 => "right",
+                _ => unreachable!(),
+            };
+            self.process_updating(side, record_batch, ctx)
+                .await
+                .expect("should process batch");
+            return;
+        }
+
         match index / (total_inputs / 2) {
             0 => self
                 .process_left(record_batch, ctx)
@@ -185,6 +638,44 @@ impl ArrowOperator for JoinWithExpiration {
         }
     }
 
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        let Watermark::EventTime(time) = watermark else {
+            return Some(watermark);
+        };
+
+        if self.is_interval {
+            let nanos = to_nanos(time) as i128;
+            self.emit_unmatched("left", nanos, ctx).await;
+            self.emit_unmatched("right", nanos, ctx).await;
+        }
+
+        // interval joins expire rows once their interval has passed, and updating outer joins
+        // once they're older than the TTL, which is the tables' retention
+        if self.updating_outer.is_some() {
+            self.expire_updating(time, ctx)
+                .await
+                .expect("should expire updating join");
+        } else if self.is_interval {
+            for side in ["left", "right"] {
+                ctx.table_manager
+                    .get_key_time_table(side, Some(time))
+                    .await
+                    .expect("should have table")
+                    .expire(Some(time))
+                    .expect("should expire table");
+            }
+        }
+
+        Some(Watermark::EventTime(
+            time.checked_sub(self.watermark_delay)
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        ))
+    }
+
     fn tables(&self) -> HashMap<String, TableConfig> {
         let mut tables = HashMap::new();
         tables.insert(
@@ -211,6 +702,10 @@ impl ArrowOperator for JoinWithExpiration {
     }
 }
 
+fn micros_to_duration(micros: i64) -> Duration {
+    Duration::from_micros(micros.max(0) as u64)
+}
+
 pub struct JoinWithExpirationConstructor;
 impl OperatorConstructor for JoinWithExpirationConstructor {
     type ConfigT = api::JoinOperator;
@@ -228,32 +723,122 @@ impl OperatorConstructor for JoinWithExpirationConstructor {
                 right: right_passer.clone(),
             },
         };
-        let join_physical_plan_node = PhysicalPlanNode::decode(&mut config.join_plan.as_slice())?;
-        let join_execution_plan = join_physical_plan_node.try_into_physical_plan(
-            registry.as_ref(),
-            &RuntimeEnv::new(RuntimeConfig::new())?,
-            &codec,
-        )?;
+        let decode_plan = |plan: &[u8]| -> anyhow::Result<Arc<dyn ExecutionPlan>> {
+            Ok(PhysicalPlanNode::decode(plan)?.try_into_physical_plan(
+                registry.as_ref(),
+                &RuntimeEnv::new(RuntimeConfig::new())?,
+                &codec,
+            )?)
+        };
+        let join_execution_plan = decode_plan(&config.join_plan)?;
 
         let left_input_schema: ArroyoSchema = config.left_schema.unwrap().try_into()?;
         let right_input_schema: ArroyoSchema = config.right_schema.unwrap().try_into()?;
         let left_schema = left_input_schema.schema_without_keys()?;
         let right_schema = right_input_schema.schema_without_keys()?;
 
-        let mut ttl = Duration::from_micros(
-            config
-                .ttl_micros
-                .expect("ttl must be set for non-instant join"),
-        );
+        let (left_expiration, right_expiration, left_unmatched, right_unmatched, watermark_delay) =
+            if let Some(interval) = &config.interval {
+                // a row on the left may match rows on the right up to `upper` after it, and a row
+                // on the right may match rows on the left up to `-lower` after it
+                let (lower, upper) = (interval.lower_micros, interval.upper_micros);
+                let join_type = api::JoinType::try_from(interval.join_type)?;
+                let left_outer = matches!(join_type, api::JoinType::Left | api::JoinType::Full);
+                let right_outer = matches!(join_type, api::JoinType::Right | api::JoinType::Full);
 
-        if ttl == Duration::ZERO {
-            warn!("TTL was not set for join with expiration");
-            ttl = Duration::from_secs(24 * 60 * 60);
-        }
+                // when checking whether a row on an outer side matched, its matches on the other
+                // side must still be in state, so they're kept for the full width of the interval
+                let width = upper.saturating_sub(lower);
+                let left_expiration = if right_outer { upper.max(width) } else { upper };
+                let right_expiration = if left_outer {
+                    (-lower).max(width)
+                } else {
+                    -lower
+                };
+
+                let unmatched = |plan: &Option<Vec<u8>>, offset: i64, schema: &ArroyoSchema| {
+                    plan.as_ref()
+                        .map(|plan| {
+                            anyhow::Ok(UnmatchedRows {
+                                offset: offset as i128 * 1000,
+                                timestamp_index: schema.timestamp_index,
+                                pending: BTreeMap::new(),
+                                plan: decode_plan(plan)?,
+                            })
+                        })
+                        .transpose()
+                };
+
+                let left_unmatched = unmatched(&interval.left_unmatched_plan, upper, &left_schema)?;
+                let right_unmatched =
+                    unmatched(&interval.right_unmatched_plan, -lower, &right_schema)?;
+                let watermark_delay = [(&left_unmatched, upper), (&right_unmatched, -lower)]
+                    .into_iter()
+                    .filter(|(unmatched, _)| unmatched.is_some())
+                    .map(|(_, offset)| offset)
+                    .max()
+                    .unwrap_or(0);
+
+                (
+                    micros_to_duration(left_expiration),
+                    micros_to_duration(right_expiration),
+                    left_unmatched,
+                    right_unmatched,
+                    micros_to_duration(watermark_delay),
+                )
+            } else {
+                let mut ttl = Duration::from_micros(
+                    config
+                        .ttl_micros
+                        .expect("ttl must be set for non-instant join"),
+                );
+
+                if ttl == Duration::ZERO {
+                    warn!("TTL was not set for join with expiration");
+                    ttl = Duration::from_secs(24 * 60 * 60);
+                }
+                (ttl, ttl, None, None, Duration::ZERO)
+            };
+
+        let updating_outer = config
+            .updating_outer
+            .as_ref()
+            .map(|outer| {
+                let output_schema: ArroyoSchema = config
+                    .output_schema
+                    .clone()
+                    .expect("join should have output schema")
+                    .try_into()?;
+                let row_converter = RowConverter::new(
+                    output_schema
+                        .schema
+                        .fields()
+                        .iter()
+                        .filter(|f| f.name() != UPDATING_META_FIELD)
+                        .map(|f| SortField::new(f.data_type().clone()))
+                        .collect(),
+                )?;
+
+                anyhow::Ok(UpdatingOuter {
+                    left_unmatched: outer
+                        .left_unmatched_plan
+                        .as_ref()
+                        .map(|plan| decode_plan(plan))
+                        .transpose()?,
+                    right_unmatched: outer
+                        .right_unmatched_plan
+                        .as_ref()
+                        .map(|plan| decode_plan(plan))
+                        .transpose()?,
+                    row_converter,
+                    output_schema: output_schema.schema,
+                })
+            })
+            .transpose()?;
 
         Ok(OperatorNode::from_operator(Box::new(JoinWithExpiration {
-            left_expiration: ttl,
-            right_expiration: ttl,
+            left_expiration,
+            right_expiration,
             left_input_schema,
             right_input_schema,
             left_schema,
@@ -261,6 +846,454 @@ impl OperatorConstructor for JoinWithExpirationConstructor {
             left_passer,
             right_passer,
             join_execution_plan,
+            is_interval: config.interval.is_some(),
+            left_unmatched,
+            right_unmatched,
+            watermark_delay,
+            updating_outer,
         })))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Array, Int64Array, StringArray, TimestampNanosecondArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use arroyo_operator::context::{batch_bounded, BatchReceiver};
+    use arroyo_rpc::grpc::api::{arroyo_exec_node, ArroyoExecNode, MemExecNode};
+    use arroyo_rpc::{updating_meta_field, TIMESTAMP_FIELD};
+    use arroyo_types::{from_nanos, get_test_task_info, ArrowMessage};
+    use datafusion::common::{JoinType, ScalarValue};
+    use datafusion::physical_expr::expressions::{Column, Literal};
+    use datafusion::physical_expr::PhysicalExpr;
+    use datafusion::physical_plan::joins::{HashJoinExec, PartitionMode};
+    use datafusion::physical_plan::projection::ProjectionExec;
+    use datafusion_proto::protobuf::physical_plan_node::PhysicalPlanType;
+    use datafusion_proto::protobuf::PhysicalExtensionNode;
+    use rand::random;
+    use tokio::sync::mpsc::channel;
+
+    fn input_schema() -> ArroyoSchema {
+        ArroyoSchema::new_keyed(
+            Arc::new(Schema::new(vec![
+                Field::new("_key_0", DataType::Utf8, false),
+                Field::new("value", DataType::Int64, false),
+                Field::new(
+                    TIMESTAMP_FIELD,
+                    DataType::Timestamp(TimeUnit::Nanosecond, None),
+                    false,
+                ),
+            ])),
+            2,
+            vec![0],
+        )
+    }
+
+    /// (key, value, event time in seconds)
+    fn batch(rows: &[(&str, i64, u64)]) -> RecordBatch {
+        RecordBatch::try_new(
+            input_schema().schema,
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.1))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    rows.iter().map(|r| r.2 as i64 * 1_000_000_000),
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// A plan that reads the rows passed in for one side of the join
+    fn read_side(
+        side: &str,
+        schema: &Schema,
+        codec: &ArroyoPhysicalExtensionCodec,
+    ) -> Arc<dyn ExecutionPlan> {
+        let node = ArroyoExecNode {
+            node: Some(arroyo_exec_node::Node::MemExec(MemExecNode {
+                table_name: side.to_string(),
+                schema: serde_json::to_string(schema).unwrap(),
+            })),
+        };
+        PhysicalPlanNode {
+            physical_plan_type: Some(PhysicalPlanType::Extension(PhysicalExtensionNode {
+                node: node.encode_to_vec(),
+                inputs: vec![],
+            })),
+        }
+        .try_into_physical_plan(
+            &Registry::default(),
+            &RuntimeEnv::new(RuntimeConfig::new()).unwrap(),
+            codec,
+        )
+        .unwrap()
+    }
+
+    /// A left interval join where right rows match left rows up to `upper` seconds after them.
+    /// No right rows are sent, so the join itself produces nothing (it reads the empty right
+    /// side) and every left row is eventually emitted unmatched (its plan reads the left side).
+    async fn left_interval_join(upper: u64) -> (JoinWithExpiration, ArrowContext, BatchReceiver) {
+        let input_schema = input_schema();
+        let schema = input_schema.schema_without_keys().unwrap();
+
+        let left_passer = Arc::new(RwLock::new(None));
+        let right_passer = Arc::new(RwLock::new(None));
+        let codec = ArroyoPhysicalExtensionCodec {
+            context: DecodingContext::LockedJoinPair {
+                left: left_passer.clone(),
+                right: right_passer.clone(),
+            },
+        };
+
+        let upper = Duration::from_secs(upper);
+        let mut join = JoinWithExpiration {
+            left_expiration: upper,
+            right_expiration: upper,
+            left_input_schema: input_schema.clone(),
+            right_input_schema: input_schema.clone(),
+            left_schema: schema.clone(),
+            right_schema: schema.clone(),
+            left_passer,
+            right_passer,
+            join_execution_plan: read_side("right", &schema.schema, &codec),
+            is_interval: true,
+            left_unmatched: Some(UnmatchedRows {
+                offset: upper.as_nanos() as i128,
+                timestamp_index: schema.timestamp_index,
+                pending: BTreeMap::new(),
+                plan: read_side("left", &schema.schema, &codec),
+            }),
+            right_unmatched: None,
+            watermark_delay: upper,
+            updating_outer: None,
+        };
+
+        let (_, control_rx) = channel(128);
+        let (command_tx, _) = channel(128);
+        let (data_tx, data_rx) = batch_bounded(128);
+
+        let mut task_info = get_test_task_info();
+        task_info.job_id = format!("interval-join-{}", random::<u64>());
+
+        let mut ctx = ArrowContext::new(
+            task_info,
+            None,
+            control_rx,
+            command_tx,
+            2,
+            vec![input_schema.clone(), input_schema.clone()],
+            Some(schema.clone()),
+            None,
+            vec![vec![data_tx]],
+            vec![],
+            join.tables(),
+        )
+        .await;
+
+        join.on_start(&mut ctx).await;
+        (join, ctx, data_rx)
+    }
+
+    /// The event times, in seconds, of the rows emitted so far
+    async fn output_times(data_rx: &mut BatchReceiver) -> Vec<u64> {
+        let mut times = vec![];
+        while let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_millis(100), data_rx.recv()).await
+        {
+            if let ArrowMessage::Data(batch) = message {
+                times.extend(
+                    batch
+                        .column(1)
+                        .as_primitive::<TimestampNanosecondType>()
+                        .values()
+                        .iter()
+                        .map(|t| *t as u64 / 1_000_000_000),
+                );
+            }
+        }
+        times.sort();
+        times
+    }
+
+    /// Advances both inputs to the watermark, returning the watermark forwarded downstream in
+    /// seconds
+    async fn advance_watermark(
+        join: &mut JoinWithExpiration,
+        ctx: &mut ArrowContext,
+        seconds: u64,
+    ) -> u64 {
+        let watermark = Watermark::EventTime(from_nanos(seconds as u128 * 1_000_000_000));
+        ctx.watermarks.set(0, watermark);
+        ctx.watermarks.set(1, watermark);
+        let Some(Watermark::EventTime(forwarded)) = join.handle_watermark(watermark, ctx).await
+        else {
+            panic!("expected an event time watermark");
+        };
+        (to_nanos(forwarded) / 1_000_000_000) as u64
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_rows_are_not_late_for_a_downstream_window() {
+        let (mut join, mut ctx, mut data_rx) = left_interval_join(5).await;
+
+        join.process_batch_index(
+            0,
+            2,
+            batch(&[("a", 1, 10), ("b", 2, 12), ("c", 3, 21)]),
+            &mut ctx,
+        )
+        .await;
+        assert_eq!(output_times(&mut data_rx).await, Vec::<u64>::new());
+
+        // a tumbling window after the join drops rows behind the last watermark it received, so
+        // every row emitted must be at or after the watermark forwarded before it
+        let mut forwarded = 0;
+        let steps: [(u64, Vec<u64>); 3] = [(14, vec![]), (18, vec![10, 12]), (30, vec![21])];
+        for (watermark, expected) in steps {
+            let next = advance_watermark(&mut join, &mut ctx, watermark).await;
+            let times = output_times(&mut data_rx).await;
+            assert_eq!(times, expected, "at watermark {}", watermark);
+            assert!(
+                times.iter().all(|t| *t >= forwarded),
+                "rows {:?} are behind the watermark {}",
+                times,
+                forwarded
+            );
+
+            // the watermark is held back by the interval
+            assert_eq!(next, watermark - 5);
+            forwarded = next;
+        }
+    }
+
+    fn updating_input_schema() -> ArroyoSchema {
+        ArroyoSchema::new_keyed(
+            Arc::new(Schema::new(vec![
+                Field::new("_key_0", DataType::Utf8, false),
+                Field::new("key", DataType::Utf8, false),
+                Field::new("value", DataType::Int64, false),
+                Field::new(
+                    TIMESTAMP_FIELD,
+                    DataType::Timestamp(TimeUnit::Nanosecond, None),
+                    false,
+                ),
+            ])),
+            3,
+            vec![0],
+        )
+    }
+
+    /// (key, value, event time in seconds)
+    fn updating_batch(rows: &[(&str, i64, u64)]) -> RecordBatch {
+        RecordBatch::try_new(
+            updating_input_schema().schema,
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.1))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    rows.iter().map(|r| r.2 as i64 * 1_000_000_000),
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// An updating left join on `key`, whose rows are kept for the ttl
+    async fn updating_left_join(ttl: u64) -> (JoinWithExpiration, ArrowContext, BatchReceiver) {
+        let input_schema = updating_input_schema();
+        let schema = input_schema.schema_without_keys().unwrap();
+
+        let left_passer = Arc::new(RwLock::new(None));
+        let right_passer = Arc::new(RwLock::new(None));
+        let codec = ArroyoPhysicalExtensionCodec {
+            context: DecodingContext::LockedJoinPair {
+                left: left_passer.clone(),
+                right: right_passer.clone(),
+            },
+        };
+
+        let hash_join = |join_type| -> Arc<dyn ExecutionPlan> {
+            let key = Arc::new(Column::new("key", 0)) as Arc<dyn PhysicalExpr>;
+            Arc::new(
+                HashJoinExec::try_new(
+                    read_side("left", &schema.schema, &codec),
+                    read_side("right", &schema.schema, &codec),
+                    vec![(key.clone(), key)],
+                    None,
+                    &join_type,
+                    None,
+                    PartitionMode::CollectLeft,
+                    false,
+                )
+                .unwrap(),
+            )
+        };
+
+        // the left rows without a match, padded with nulls for the right columns
+        let mut padded: Vec<(Arc<dyn PhysicalExpr>, String)> = vec![];
+        for (i, field) in schema.schema.fields().iter().enumerate() {
+            padded.push((Arc::new(Column::new(field.name(), i)), field.name().clone()));
+        }
+        for field in schema.schema.fields() {
+            padded.push((
+                Arc::new(Literal::new(
+                    ScalarValue::try_from(field.data_type()).unwrap(),
+                )),
+                format!("right_{}", field.name()),
+            ));
+        }
+        let left_unmatched =
+            Arc::new(ProjectionExec::try_new(padded, hash_join(JoinType::LeftAnti)).unwrap());
+
+        let mut fields = vec![];
+        for side in ["left", "right"] {
+            for field in schema.schema.fields() {
+                fields.push(Arc::new(
+                    field
+                        .as_ref()
+                        .clone()
+                        .with_name(format!("{}_{}", side, field.name()))
+                        .with_nullable(true),
+                ));
+            }
+        }
+        let row_converter = RowConverter::new(
+            fields
+                .iter()
+                .map(|f| SortField::new(f.data_type().clone()))
+                .collect(),
+        )
+        .unwrap();
+        fields.push(updating_meta_field());
+        let output_schema = Arc::new(Schema::new(fields));
+
+        let ttl = Duration::from_secs(ttl);
+        let mut join = JoinWithExpiration {
+            left_expiration: ttl,
+            right_expiration: ttl,
+            left_input_schema: input_schema.clone(),
+            right_input_schema: input_schema.clone(),
+            left_schema: schema.clone(),
+            right_schema: schema.clone(),
+            left_passer,
+            right_passer,
+            join_execution_plan: hash_join(JoinType::Inner),
+            is_interval: false,
+            left_unmatched: None,
+            right_unmatched: None,
+            watermark_delay: Duration::ZERO,
+            updating_outer: Some(UpdatingOuter {
+                left_unmatched: Some(left_unmatched),
+                right_unmatched: None,
+                row_converter,
+                output_schema: output_schema.clone(),
+            }),
+        };
+
+        let (_, control_rx) = channel(128);
+        let (command_tx, _) = channel(128);
+        let (data_tx, data_rx) = batch_bounded(128);
+
+        let mut task_info = get_test_task_info();
+        task_info.job_id = format!("updating-join-{}", random::<u64>());
+
+        let mut ctx = ArrowContext::new(
+            task_info,
+            None,
+            control_rx,
+            command_tx,
+            2,
+            vec![input_schema.clone(), input_schema.clone()],
+            Some(ArroyoSchema::new_unkeyed(
+                output_schema,
+                schema.timestamp_index,
+            )),
+            None,
+            vec![vec![data_tx]],
+            vec![],
+            join.tables(),
+        )
+        .await;
+
+        join.on_start(&mut ctx).await;
+        (join, ctx, data_rx)
+    }
+
+    /// Applies the changes emitted so far to the view of the join's output, by id, as
+    /// (left value, right value). Every retraction must be of a row in the view.
+    async fn apply_changes(
+        data_rx: &mut BatchReceiver,
+        view: &mut HashMap<Vec<u8>, (i64, Option<i64>)>,
+    ) {
+        while let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_millis(100), data_rx.recv()).await
+        {
+            let ArrowMessage::Data(batch) = message else {
+                continue;
+            };
+            let left = batch
+                .column(1)
+                .as_primitive::<arrow_array::types::Int64Type>();
+            let right = batch
+                .column(4)
+                .as_primitive::<arrow_array::types::Int64Type>();
+            let metadata = batch.column(6).as_struct();
+            let is_retract = metadata.column(0).as_boolean();
+            let ids = metadata.column(1).as_fixed_size_binary();
+
+            for i in 0..batch.num_rows() {
+                let row = (left.value(i), right.is_valid(i).then(|| right.value(i)));
+                let id = ids.value(i).to_vec();
+                if is_retract.value(i) {
+                    assert_eq!(
+                        view.remove(&id),
+                        Some(row),
+                        "retracted a row that isn't in the output"
+                    );
+                } else {
+                    assert_eq!(view.insert(id, row), None, "inserted a row twice");
+                }
+            }
+        }
+    }
+
+    fn sorted_rows(view: &HashMap<Vec<u8>, (i64, Option<i64>)>) -> Vec<(i64, Option<i64>)> {
+        let mut rows: Vec<_> = view.values().copied().collect();
+        rows.sort();
+        rows
+    }
+
+    #[tokio::test]
+    async fn test_updating_join_output_follows_expired_rows() {
+        let (mut join, mut ctx, mut data_rx) = updating_left_join(10).await;
+        let mut view = HashMap::new();
+
+        join.process_batch_index(0, 2, updating_batch(&[("k", 1, 5)]), &mut ctx)
+            .await;
+        apply_changes(&mut data_rx, &mut view).await;
+        assert_eq!(sorted_rows(&view), vec![(1, None)]);
+
+        join.process_batch_index(1, 2, updating_batch(&[("k", 2, 1)]), &mut ctx)
+            .await;
+        apply_changes(&mut data_rx, &mut view).await;
+        assert_eq!(sorted_rows(&view), vec![(1, Some(2))]);
+
+        // the right row expires before the left one, which is left unmatched again
+        advance_watermark(&mut join, &mut ctx, 12).await;
+        apply_changes(&mut data_rx, &mut view).await;
+        assert_eq!(sorted_rows(&view), vec![(1, None)]);
+
+        // so a new right row retracts the null-padded row that was actually emitted
+        join.process_batch_index(1, 2, updating_batch(&[("k", 3, 13)]), &mut ctx)
+            .await;
+        apply_changes(&mut data_rx, &mut view).await;
+        assert_eq!(sorted_rows(&view), vec![(1, Some(3))]);
+
+        advance_watermark(&mut join, &mut ctx, 30).await;
+        apply_changes(&mut data_rx, &mut view).await;
+        assert_eq!(sorted_rows(&view), vec![]);
+    }
+}
@@ -0,0 +1,722 @@
+use anyhow::anyhow;
+use arrow::compute::take;
+use arrow::row::{OwnedRow, RowConverter, SortField};
+use arrow_array::cast::AsArray;
+use arrow_array::types::Int64Type;
+use arrow_array::{new_null_array, Array, ArrayRef, RecordBatch, UInt32Array};
+use arroyo_connectors::connectors;
+use arroyo_operator::connector::LookupConnector;
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, DisplayableOperator, OperatorConstructor, OperatorNode, Registry,
+};
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_rpc::grpc::api;
+use arroyo_rpc::{retry, LOOKUP_KEY_INDEX_FIELD};
+use arroyo_types::SourceError;
+use async_trait::async_trait;
+use datafusion::physical_expr::PhysicalExpr;
+use datafusion_proto::physical_plan::from_proto::parse_physical_expr;
+use datafusion_proto::physical_plan::DefaultPhysicalExtensionCodec;
+use datafusion_proto::protobuf::PhysicalExprNode;
+use prost::Message;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How many times a failed lookup is retried before it's reported
+const LOOKUP_RETRIES: u32 = 3;
+
+/// An LRU cache of lookup results, where entries optionally expire after a TTL. `None` values
+/// record that a key was not found.
+struct LookupCache {
+    max_capacity: Option<usize>,
+    ttl: Option<Duration>,
+    tick: u64,
+    entries: HashMap<OwnedRow, (u64, Instant, Option<OwnedRow>)>,
+    by_tick: BTreeMap<u64, OwnedRow>,
+}
+
+impl LookupCache {
+    fn new(max_capacity: Option<usize>, ttl: Option<Duration>) -> Self {
+        Self {
+            max_capacity,
+            ttl,
+            tick: 0,
+            entries: HashMap::new(),
+            by_tick: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &OwnedRow) -> Option<Option<OwnedRow>> {
+        let (tick, inserted, value) = self.entries.get_mut(key)?;
+        if self.ttl.is_some_and(|ttl| inserted.elapsed() > ttl) {
+            self.by_tick.remove(&*tick);
+            self.entries.remove(key);
+            return None;
+        }
+
+        self.tick += 1;
+        let row = self.by_tick.remove(&*tick).unwrap();
+        self.by_tick.insert(self.tick, row);
+        *tick = self.tick;
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: OwnedRow, value: Option<OwnedRow>) {
+        self.tick += 1;
+        if let Some((old_tick, _, _)) = self
+            .entries
+            .insert(key.clone(), (self.tick, Instant::now(), value))
+        {
+            self.by_tick.remove(&old_tick);
+        }
+        self.by_tick.insert(self.tick, key);
+
+        while self
+            .max_capacity
+            .is_some_and(|capacity| self.entries.len() > capacity)
+        {
+            let (_, key) = self.by_tick.pop_first().unwrap();
+            self.entries.remove(&key);
+        }
+    }
+}
+
+pub struct LookupJoinOperator {
+    connector: Box<dyn LookupConnector>,
+    config: api::LookupJoinOperator,
+    registry: Arc<Registry>,
+    join_type: api::JoinType,
+    key_exprs: Vec<Arc<dyn PhysicalExpr>>,
+    key_row_converter: RowConverter,
+    result_row_converter: RowConverter,
+    null_result_row: Option<OwnedRow>,
+    cache: Option<LookupCache>,
+}
+
+pub struct LookupJoinConstructor;
+
+impl OperatorConstructor for LookupJoinConstructor {
+    type ConfigT = api::LookupJoinOperator;
+
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let op = config
+            .connector
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing connector for lookup join"))?;
+
+        let lookup_schema: ArroyoSchema = config
+            .lookup_schema
+            .clone()
+            .ok_or_else(|| anyhow!("missing lookup schema for lookup join"))?
+            .try_into()?;
+
+        let connector = connectors()
+            .get(op.connector.as_str())
+            .ok_or_else(|| anyhow!("no connector with name '{}'", op.connector))?
+            .make_lookup(serde_json::from_str(&op.config)?, Arc::new(lookup_schema))?;
+
+        let join_type = api::JoinType::try_from(config.join_type)
+            .map_err(|_| anyhow!("invalid join type {}", config.join_type))?;
+
+        let cache = (config.max_capacity.is_some() || config.ttl_micros.is_some()).then(|| {
+            LookupCache::new(
+                config.max_capacity.map(|c| c as usize),
+                config.ttl_micros.map(Duration::from_micros),
+            )
+        });
+
+        Ok(OperatorNode::from_operator(Box::new(LookupJoinOperator {
+            connector,
+            config,
+            registry,
+            join_type,
+            key_exprs: vec![],
+            key_row_converter: RowConverter::new(vec![]).unwrap(),
+            result_row_converter: RowConverter::new(vec![]).unwrap(),
+            null_result_row: None,
+            cache,
+        })))
+    }
+}
+
+impl LookupJoinOperator {
+    /// The range of the output columns that come from the lookup table; these sit between the
+    /// input columns (minus the timestamp) and the timestamp
+    fn result_columns(ctx: &ArrowContext) -> std::ops::Range<usize> {
+        let input_columns = ctx.in_schemas[0].schema.fields.len() - 1;
+        let output_columns = ctx.out_schema.as_ref().unwrap().schema.fields.len();
+        input_columns..(output_columns - 1)
+    }
+
+    /// Queries the connector for the given keys, returning the result row (if any) for each. Failed
+    /// lookups are retried, and if they keep failing the failure is reported and None is returned.
+    async fn fetch(
+        &mut self,
+        keys: &[ArrayRef],
+        ctx: &mut ArrowContext,
+    ) -> Option<Vec<Option<OwnedRow>>> {
+        let mut results = vec![None; keys.first().map(|k| k.len()).unwrap_or(0)];
+
+        let result = retry!(
+            match self.connector.lookup(keys).await {
+                None => Ok(None),
+                Some(Ok(batch)) => Ok(Some(batch)),
+                Some(Err(SourceError::BadData { details })) => {
+                    ctx.report_error("Failed to deserialize lookup result", details)
+                        .await;
+                    Ok(None)
+                }
+                Some(Err(SourceError::Other { name, details })) => {
+                    Err(format!("{}: {}", name, details))
+                }
+            },
+            LOOKUP_RETRIES,
+            Duration::from_millis(50),
+            Duration::from_secs(1),
+            |e| warn!(
+                "lookup from {} failed, retrying: {}",
+                self.connector.name(),
+                e
+            )
+        );
+
+        let batch = match result {
+            Ok(Some(batch)) => batch,
+            Ok(None) => return Some(results),
+            Err(details) => {
+                ctx.report_error(
+                    format!("Lookup from {} failed", self.connector.name()),
+                    details,
+                )
+                .await;
+                return None;
+            }
+        };
+
+        let out_schema = ctx.out_schema.as_ref().unwrap().schema.clone();
+        let columns: Vec<_> = Self::result_columns(ctx)
+            .map(|i| {
+                batch
+                    .column_by_name(out_schema.field(i).name())
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "lookup result is missing column {}",
+                            out_schema.field(i).name()
+                        )
+                    })
+                    .clone()
+            })
+            .collect();
+
+        let rows = self.result_row_converter.convert_columns(&columns).unwrap();
+
+        let indices = batch
+            .column_by_name(LOOKUP_KEY_INDEX_FIELD)
+            .expect("lookup result is missing the key index column")
+            .as_primitive::<Int64Type>();
+
+        for (index, row) in indices.iter().zip(rows.iter()) {
+            if let Some(result) = index.and_then(|i| results.get_mut(i as usize)) {
+                *result = Some(row.owned());
+            }
+        }
+
+        Some(results)
+    }
+}
+
+#[async_trait]
+impl ArrowOperator for LookupJoinOperator {
+    fn name(&self) -> String {
+        format!("LookupJoin<{}>", self.connector.name())
+    }
+
+    fn display(&self) -> DisplayableOperator {
+        DisplayableOperator {
+            name: Cow::Borrowed("LookupJoinOperator"),
+            fields: vec![
+                ("connector", self.connector.name().into()),
+                ("join_type", AsDisplayable::Debug(&self.join_type)),
+                (
+                    "key_exprs",
+                    self.key_exprs
+                        .iter()
+                        .map(|e| format!("{}", e))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                        .into(),
+                ),
+                ("ttl_micros", AsDisplayable::Debug(&self.config.ttl_micros)),
+                (
+                    "max_capacity",
+                    AsDisplayable::Debug(&self.config.max_capacity),
+                ),
+            ],
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let input_schema = ctx.in_schemas[0].schema.clone();
+
+        self.key_exprs = self
+            .config
+            .key_exprs
+            .iter()
+            .map(|condition| {
+                parse_physical_expr(
+                    &PhysicalExprNode::decode(&mut condition.left_expr.as_slice()).unwrap(),
+                    &*self.registry,
+                    &input_schema,
+                    &DefaultPhysicalExtensionCodec {},
+                )
+                .expect("invalid lookup join key expression")
+            })
+            .collect();
+
+        self.key_row_converter = RowConverter::new(
+            self.key_exprs
+                .iter()
+                .map(|e| SortField::new(e.data_type(&input_schema).unwrap()))
+                .collect(),
+        )
+        .unwrap();
+
+        let out_schema = ctx.out_schema.as_ref().unwrap().schema.clone();
+        let result_fields: Vec<_> = Self::result_columns(ctx)
+            .map(|i| out_schema.field(i).clone())
+            .collect();
+
+        self.result_row_converter = RowConverter::new(
+            result_fields
+                .iter()
+                .map(|f| SortField::new(f.data_type().clone()))
+                .collect(),
+        )
+        .unwrap();
+
+        // rows for keys that aren't found in a left join are filled in with nulls
+        let nulls: Vec<_> = result_fields
+            .iter()
+            .map(|f| new_null_array(f.data_type(), 1))
+            .collect();
+        self.null_result_row = Some(
+            self.result_row_converter
+                .convert_columns(&nulls)
+                .unwrap()
+                .row(0)
+                .owned(),
+        );
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        let num_rows = batch.num_rows();
+        let key_arrays: Vec<_> = self
+            .key_exprs
+            .iter()
+            .map(|expr| expr.evaluate(&batch).unwrap().into_array(num_rows).unwrap())
+            .collect();
+
+        let keys = self.key_row_converter.convert_columns(&key_arrays).unwrap();
+
+        // find the distinct keys that need to be fetched from the connector
+        let mut results: HashMap<OwnedRow, Option<OwnedRow>> = HashMap::new();
+        let mut to_fetch: Vec<OwnedRow> = vec![];
+        let mut to_fetch_indices: Vec<u32> = vec![];
+        for i in 0..num_rows {
+            if key_arrays.iter().any(|k| k.is_null(i)) {
+                continue;
+            }
+
+            let key = keys.row(i).owned();
+            if results.contains_key(&key) {
+                continue;
+            }
+
+            match self.cache.as_mut().and_then(|cache| cache.get(&key)) {
+                Some(result) => {
+                    results.insert(key, result);
+                }
+                None => {
+                    // filled in once the key has been fetched
+                    results.insert(key.clone(), None);
+                    to_fetch.push(key);
+                    to_fetch_indices.push(i as u32);
+                }
+            }
+        }
+
+        if !to_fetch.is_empty() {
+            let indices = UInt32Array::from(to_fetch_indices);
+            let fetch_keys: Vec<_> = key_arrays
+                .iter()
+                .map(|k| take(k, &indices, None).unwrap())
+                .collect();
+
+            // if the lookup failed, the keys are treated as not found for this batch but aren't
+            // cached, so they're looked up again for the next one
+            if let Some(fetched) = self.fetch(&fetch_keys, ctx).await {
+                for (key, result) in to_fetch.into_iter().zip(fetched) {
+                    if let Some(cache) = &mut self.cache {
+                        cache.insert(key.clone(), result.clone());
+                    }
+                    results.insert(key, result);
+                }
+            }
+        }
+
+        let null_row = self.null_result_row.as_ref().unwrap();
+        let mut kept = vec![];
+        let mut result_rows = vec![];
+        for i in 0..num_rows {
+            let result = results.get(&keys.row(i).owned()).cloned().flatten();
+            match (result, self.join_type) {
+                (Some(row), _) => {
+                    kept.push(i as u32);
+                    result_rows.push(row);
+                }
+                (None, api::JoinType::Left) => {
+                    kept.push(i as u32);
+                    result_rows.push(null_row.clone());
+                }
+                (None, _) => {}
+            }
+        }
+
+        if kept.is_empty() {
+            return;
+        }
+
+        let kept = UInt32Array::from(kept);
+        let timestamp_index = ctx.in_schemas[0].timestamp_index;
+        let mut columns: Vec<_> = batch
+            .columns()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != timestamp_index)
+            .map(|(_, c)| take(c, &kept, None).unwrap())
+            .collect();
+
+        columns.extend(
+            self.result_row_converter
+                .convert_rows(result_rows.iter().map(|r| r.row()))
+                .unwrap(),
+        );
+
+        columns.push(take(batch.column(timestamp_index), &kept, None).unwrap());
+
+        let out_schema = ctx.out_schema.as_ref().unwrap().schema.clone();
+        ctx.collect(RecordBatch::try_new(out_schema, columns).unwrap())
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Int64Array, StringArray, TimestampNanosecondArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use arroyo_operator::context::{batch_bounded, BatchReceiver};
+    use arroyo_rpc::grpc::api::LookupJoinCondition;
+    use arroyo_rpc::{ControlResp, TIMESTAMP_FIELD};
+    use arroyo_types::{get_test_task_info, ArrowMessage};
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion_proto::physical_plan::to_proto::serialize_physical_expr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    fn row(converter: &RowConverter, value: &str) -> OwnedRow {
+        converter
+            .convert_columns(&[Arc::new(StringArray::from_iter_values([value])) as ArrayRef])
+            .unwrap()
+            .row(0)
+            .owned()
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let converter = RowConverter::new(vec![SortField::new(DataType::Utf8)]).unwrap();
+        let mut cache = LookupCache::new(Some(2), None);
+
+        cache.insert(row(&converter, "a"), Some(row(&converter, "1")));
+        cache.insert(row(&converter, "b"), None);
+        assert_eq!(
+            cache.get(&row(&converter, "a")),
+            Some(Some(row(&converter, "1")))
+        );
+
+        // b is now the least recently used
+        cache.insert(row(&converter, "c"), Some(row(&converter, "3")));
+        assert_eq!(cache.get(&row(&converter, "b")), None);
+        assert!(cache.get(&row(&converter, "a")).is_some());
+        assert!(cache.get(&row(&converter, "c")).is_some());
+    }
+
+    #[test]
+    fn test_cache_records_missing_keys_and_expires_entries() {
+        let converter = RowConverter::new(vec![SortField::new(DataType::Utf8)]).unwrap();
+        let mut cache = LookupCache::new(None, Some(Duration::from_millis(20)));
+
+        cache.insert(row(&converter, "a"), None);
+        assert_eq!(cache.get(&row(&converter, "a")), Some(None));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(&row(&converter, "a")), None);
+        assert!(cache.entries.is_empty() && cache.by_tick.is_empty());
+    }
+
+    /// Looks up names by customer id, failing the first `failures` lookups
+    struct TestLookup {
+        names: HashMap<String, String>,
+        failures: usize,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LookupConnector for TestLookup {
+        fn name(&self) -> String {
+            "test".to_string()
+        }
+
+        async fn lookup(&mut self, keys: &[ArrayRef]) -> Option<Result<RecordBatch, SourceError>> {
+            if self.lookups.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Some(Err(SourceError::other("connection", "connection reset")));
+            }
+
+            let (indices, names): (Vec<_>, Vec<_>) = keys[0]
+                .as_string::<i32>()
+                .iter()
+                .enumerate()
+                .filter_map(|(i, key)| Some((i as i64, self.names.get(key?)?.clone())))
+                .unzip();
+
+            Some(Ok(RecordBatch::try_new(
+                Arc::new(Schema::new(vec![
+                    Field::new("name", DataType::Utf8, true),
+                    Field::new(LOOKUP_KEY_INDEX_FIELD, DataType::Int64, false),
+                ])),
+                vec![
+                    Arc::new(StringArray::from(names)),
+                    Arc::new(Int64Array::from(indices)),
+                ],
+            )
+            .unwrap()))
+        }
+    }
+
+    fn input_schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("event_id", DataType::Utf8, false),
+            Field::new("customer_id", DataType::Utf8, true),
+            Field::new(
+                TIMESTAMP_FIELD,
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+        ]))
+    }
+
+    /// (event id, customer id)
+    fn batch(rows: &[(&str, Option<&str>)]) -> RecordBatch {
+        RecordBatch::try_new(
+            input_schema(),
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(StringArray::from_iter(rows.iter().map(|r| r.1))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    (0..rows.len()).map(|i| i as i64),
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn lookup_join(
+        join_type: api::JoinType,
+        failures: usize,
+    ) -> (
+        LookupJoinOperator,
+        ArrowContext,
+        BatchReceiver,
+        Receiver<ControlResp>,
+        Arc<AtomicUsize>,
+    ) {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let connector = TestLookup {
+            names: HashMap::from([("c1".to_string(), "alice".to_string())]),
+            failures,
+            lookups: lookups.clone(),
+        };
+
+        let key_expr = serialize_physical_expr(
+            Arc::new(Column::new("customer_id", 1)),
+            &DefaultPhysicalExtensionCodec {},
+        )
+        .unwrap()
+        .encode_to_vec();
+
+        let mut operator = LookupJoinOperator {
+            connector: Box::new(connector),
+            config: api::LookupJoinOperator {
+                key_exprs: vec![LookupJoinCondition {
+                    left_expr: key_expr,
+                    right_key: "id".to_string(),
+                }],
+                join_type: join_type as i32,
+                max_capacity: Some(10),
+                ..Default::default()
+            },
+            registry: Arc::new(Registry::default()),
+            join_type,
+            key_exprs: vec![],
+            key_row_converter: RowConverter::new(vec![]).unwrap(),
+            result_row_converter: RowConverter::new(vec![]).unwrap(),
+            null_result_row: None,
+            cache: Some(LookupCache::new(Some(10), None)),
+        };
+
+        let (_, control_rx) = channel(128);
+        let (command_tx, command_rx) = channel(128);
+        let (data_tx, data_rx) = batch_bounded(128);
+
+        let mut ctx = ArrowContext::new(
+            get_test_task_info(),
+            None,
+            control_rx,
+            command_tx,
+            1,
+            vec![ArroyoSchema::new_unkeyed(input_schema(), 2)],
+            Some(ArroyoSchema::new_unkeyed(
+                Arc::new(Schema::new(vec![
+                    Field::new("event_id", DataType::Utf8, false),
+                    Field::new("customer_id", DataType::Utf8, true),
+                    Field::new("name", DataType::Utf8, true),
+                    Field::new(
+                        TIMESTAMP_FIELD,
+                        DataType::Timestamp(TimeUnit::Nanosecond, None),
+                        false,
+                    ),
+                ])),
+                3,
+            )),
+            None,
+            vec![vec![data_tx]],
+            vec![],
+            HashMap::new(),
+        )
+        .await;
+
+        operator.on_start(&mut ctx).await;
+        (operator, ctx, data_rx, command_rx, lookups)
+    }
+
+    /// The joined rows as (event id, name)
+    async fn output(data_rx: &mut BatchReceiver) -> Vec<(String, Option<String>)> {
+        let Ok(Some(ArrowMessage::Data(batch))) =
+            tokio::time::timeout(Duration::from_millis(100), data_rx.recv()).await
+        else {
+            return vec![];
+        };
+
+        let ids = batch.column(0).as_string::<i32>();
+        let names = batch.column(2).as_string::<i32>();
+        (0..batch.num_rows())
+            .map(|i| {
+                (
+                    ids.value(i).to_string(),
+                    names.is_valid(i).then(|| names.value(i).to_string()),
+                )
+            })
+            .collect()
+    }
+
+    fn joined(id: &str, name: Option<&str>) -> (String, Option<String>) {
+        (id.to_string(), name.map(|n| n.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_left_join_fills_in_missing_keys_and_caches_results() {
+        let (mut operator, mut ctx, mut data_rx, _, lookups) =
+            lookup_join(api::JoinType::Left, 0).await;
+
+        operator
+            .process_batch(
+                batch(&[
+                    ("e1", Some("c1")),
+                    ("e2", Some("c2")),
+                    ("e3", Some("c1")),
+                    ("e4", None),
+                ]),
+                &mut ctx,
+            )
+            .await;
+        assert_eq!(
+            output(&mut data_rx).await,
+            vec![
+                joined("e1", Some("alice")),
+                joined("e2", None),
+                joined("e3", Some("alice")),
+                joined("e4", None),
+            ]
+        );
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // both the found and the missing key are served from the cache
+        operator
+            .process_batch(batch(&[("e5", Some("c2")), ("e6", Some("c1"))]), &mut ctx)
+            .await;
+        assert_eq!(
+            output(&mut data_rx).await,
+            vec![joined("e5", None), joined("e6", Some("alice"))]
+        );
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_inner_join_retries_failed_lookups() {
+        let (mut operator, mut ctx, mut data_rx, _, lookups) =
+            lookup_join(api::JoinType::Inner, 2).await;
+
+        operator
+            .process_batch(batch(&[("e1", Some("c1")), ("e2", Some("c2"))]), &mut ctx)
+            .await;
+        assert_eq!(
+            output(&mut data_rx).await,
+            vec![joined("e1", Some("alice"))]
+        );
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_lookups_that_keep_failing_are_reported_and_not_cached() {
+        let (mut operator, mut ctx, mut data_rx, mut command_rx, lookups) =
+            lookup_join(api::JoinType::Left, LOOKUP_RETRIES as usize + 1).await;
+
+        operator
+            .process_batch(batch(&[("e1", Some("c1"))]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await, vec![joined("e1", None)]);
+        assert_eq!(lookups.load(Ordering::SeqCst), LOOKUP_RETRIES as usize + 1);
+
+        let Some(ControlResp::Error { message, .. }) = command_rx.recv().await else {
+            panic!("lookup failure should have been reported");
+        };
+        assert_eq!(message, "Lookup from test failed");
+
+        // the next batch looks the key up again, which now succeeds
+        operator
+            .process_batch(batch(&[("e2", Some("c1"))]), &mut ctx)
+            .await;
+        assert_eq!(
+            output(&mut data_rx).await,
+            vec![joined("e2", Some("alice"))]
+        );
+    }
+}
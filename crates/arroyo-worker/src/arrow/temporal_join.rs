@@ -0,0 +1,832 @@
+use anyhow::Result;
+use arrow::compute::{concat_batches, partition, sort_to_indices, take};
+use arrow_array::cast::AsArray;
+use arrow_array::{
+    new_null_array, Array, ArrayRef, RecordBatch, TimestampNanosecondArray, UInt32Array,
+};
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, DisplayableOperator, OperatorConstructor, OperatorNode, Registry,
+};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::{api, rpc::TableConfig};
+use arroyo_rpc::{Converter, UPDATING_META_FIELD};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{from_nanos, to_nanos, CheckpointBarrier, Watermark};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// The versions of the right side for a single key, ordered by event time (and then by arrival)
+struct Versions {
+    batch: RecordBatch,
+    order: Vec<usize>,
+    timestamps: Vec<i64>,
+    is_retract: Vec<bool>,
+}
+
+impl Versions {
+    fn new(batch: RecordBatch, schema: &ArroyoSchema, retract_index: Option<usize>) -> Self {
+        let timestamps = schema.timestamp_column(&batch).values().to_vec();
+        let is_retract = match retract_index {
+            Some(index) => {
+                let is_retract = batch
+                    .column(index)
+                    .as_struct()
+                    .column_by_name("is_retract")
+                    .expect("updating metadata should have is_retract")
+                    .as_boolean()
+                    .clone();
+                (0..batch.num_rows())
+                    .map(|i| is_retract.is_valid(i) && is_retract.value(i))
+                    .collect()
+            }
+            None => vec![false; batch.num_rows()],
+        };
+
+        // sort_by_key is stable, so versions with the same time stay in the order they arrived
+        let mut order: Vec<_> = (0..batch.num_rows()).collect();
+        order.sort_by_key(|i| timestamps[*i]);
+
+        Self {
+            batch,
+            order,
+            timestamps,
+            is_retract,
+        }
+    }
+
+    /// The number of versions (in time order) that are at or before `time`
+    fn count_at_or_before(&self, time: i64) -> usize {
+        self.order.partition_point(|i| self.timestamps[*i] <= time)
+    }
+
+    /// The row of the version that was valid at `time`, if there was one; a version is replaced
+    /// by the next one for its key, and a retraction means there was no valid version
+    fn valid_at(&self, time: i64) -> Option<u32> {
+        let count = self.count_at_or_before(time);
+        let index = *self.order.get(count.checked_sub(1)?)?;
+        (!self.is_retract[index]).then_some(index as u32)
+    }
+}
+
+pub struct TemporalJoin {
+    left_input_schema: ArroyoSchemaRef,
+    right_input_schema: ArroyoSchemaRef,
+    right_schema: ArroyoSchema,
+    output_schema: ArroyoSchemaRef,
+    is_left_join: bool,
+    ttl: Duration,
+    key_converter: Converter,
+    /// the columns of the unkeyed left and right rows that are included in the output
+    left_value_indices: Vec<usize>,
+    right_value_indices: Vec<usize>,
+    retract_index: Option<usize>,
+    /// rows on the left waiting for the watermark to pass their event time, by event time
+    pending: BTreeMap<SystemTime, Vec<RecordBatch>>,
+    /// keys with versions that may be superseded by later ones and could be compacted
+    uncompacted: HashSet<Vec<u8>>,
+}
+
+impl TemporalJoin {
+    /// Splits a batch into batches of rows with the same event time
+    fn split_by_time(&self, batch: &RecordBatch) -> Result<Vec<(SystemTime, RecordBatch)>> {
+        let timestamps = self.left_input_schema.timestamp_column(batch);
+        let indices = sort_to_indices(timestamps, None, None)?;
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|c| take(c, &indices, None))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sorted = RecordBatch::try_new(batch.schema(), columns)?;
+        let sorted_timestamps = self.left_input_schema.timestamp_column(&sorted);
+
+        Ok(partition(&[sorted
+            .column(self.left_input_schema.timestamp_index)
+            .clone()])?
+        .ranges()
+        .into_iter()
+        .map(|range| {
+            (
+                from_nanos(sorted_timestamps.value(range.start) as u128),
+                sorted.slice(range.start, range.end - range.start),
+            )
+        })
+        .collect())
+    }
+
+    async fn process_left(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) -> Result<()> {
+        let watermark = ctx.last_present_watermark();
+
+        // rows before the watermark are late, as the versions they'd need may have been compacted
+        let batch = self.left_input_schema.filter_by_time(batch, watermark)?;
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+
+        let max_timestamp = self
+            .left_input_schema
+            .timestamp_column(&batch)
+            .values()
+            .iter()
+            .max()
+            .copied()
+            .unwrap();
+        ctx.table_manager
+            .get_expiring_time_key_table("left", watermark)
+            .await
+            .expect("should have left table")
+            .insert(from_nanos(max_timestamp as u128), batch.clone());
+
+        for (time, batch) in self.split_by_time(&batch)? {
+            self.pending.entry(time).or_default().push(batch);
+        }
+        Ok(())
+    }
+
+    async fn process_right(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) -> Result<()> {
+        let keys = ctx
+            .table_manager
+            .get_key_time_table("right", ctx.last_present_watermark())
+            .await
+            .expect("should have right table")
+            .insert(batch)
+            .await?;
+
+        self.uncompacted
+            .extend(keys.into_iter().map(|key| key.as_ref().to_vec()));
+        Ok(())
+    }
+
+    /// Joins the left rows with the versions valid at their event times
+    async fn join(&mut self, left: RecordBatch, ctx: &mut ArrowContext) -> Result<()> {
+        let right_table = ctx
+            .table_manager
+            .get_key_time_table("right", ctx.last_present_watermark())
+            .await
+            .expect("should have right table");
+
+        let key_indices = self
+            .left_input_schema
+            .key_indices
+            .clone()
+            .unwrap_or_default();
+        let sorted = self.left_input_schema.sort(left, false)?;
+        let unkeyed = self.left_input_schema.unkeyed_batch(&sorted)?;
+        let timestamps = self.left_input_schema.timestamp_column(&sorted);
+
+        let mut output = vec![];
+        for range in self.left_input_schema.partition(&sorted, false)? {
+            let key = self.key_converter.convert_columns(
+                sorted
+                    .slice(range.start, 1)
+                    .project(&key_indices)?
+                    .columns(),
+            )?;
+
+            let versions = right_table
+                .get_batch(key.as_ref())?
+                .map(|batch| Versions::new(batch.clone(), &self.right_schema, self.retract_index));
+
+            let (left_indices, right_indices): (Vec<_>, Vec<_>) = range
+                .filter_map(|i| {
+                    let version = versions
+                        .as_ref()
+                        .and_then(|v| v.valid_at(timestamps.value(i)));
+                    (version.is_some() || self.is_left_join).then_some((i as u32, version))
+                })
+                .unzip();
+
+            if left_indices.is_empty() {
+                continue;
+            }
+
+            let left_indices = UInt32Array::from(left_indices);
+            let right_indices = UInt32Array::from(right_indices);
+
+            let mut columns = self
+                .left_value_indices
+                .iter()
+                .map(|i| take(unkeyed.column(*i), &left_indices, None))
+                .collect::<Result<Vec<ArrayRef>, _>>()?;
+
+            for i in &self.right_value_indices {
+                columns.push(match &versions {
+                    Some(versions) => take(versions.batch.column(*i), &right_indices, None)?,
+                    None => new_null_array(
+                        self.right_schema.schema.field(*i).data_type(),
+                        left_indices.len(),
+                    ),
+                });
+            }
+
+            columns.push(take(timestamps, &left_indices, None)?);
+            output.push(RecordBatch::try_new(
+                self.output_schema.schema.clone(),
+                columns,
+            )?);
+        }
+
+        if !output.is_empty() {
+            ctx.collect(concat_batches(&self.output_schema.schema, output.iter())?)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Removes the versions that can no longer be joined with: once the watermark has passed,
+    /// only the last version before it (and any after it) may be valid for rows still to come.
+    /// That version is kept however old it is, as it's valid until the key's next version.
+    async fn compact(&mut self, watermark: SystemTime, ctx: &mut ArrowContext) -> Result<()> {
+        let watermark = to_nanos(watermark) as i64;
+        let right_table = ctx
+            .table_manager
+            .get_key_time_table("right", ctx.last_present_watermark())
+            .await
+            .expect("should have right table");
+
+        let mut uncompacted = HashSet::new();
+        for key in self.uncompacted.drain() {
+            let Some(batch) = right_table.get_batch(&key)? else {
+                continue;
+            };
+            let versions = Versions::new(batch.clone(), &self.right_schema, self.retract_index);
+
+            let before = versions.count_at_or_before(watermark - 1);
+            let mut keep = &versions.order[before.saturating_sub(1)..];
+            if before > 0 && versions.is_retract[keep[0]] {
+                keep = &keep[1..];
+            }
+
+            if keep.len() < versions.order.len() {
+                let indices = UInt32Array::from_iter_values(keep.iter().map(|i| *i as u32));
+                let columns = versions
+                    .batch
+                    .columns()
+                    .iter()
+                    .map(|c| take(c, &indices, None))
+                    .collect::<Result<Vec<_>, _>>()?;
+                right_table.replace_batch(
+                    &key,
+                    RecordBatch::try_new(versions.batch.schema(), columns)?,
+                );
+            }
+
+            // versions at or after the watermark may still be superseded
+            if before < versions.order.len() {
+                uncompacted.insert(key);
+            }
+        }
+        self.uncompacted = uncompacted;
+        Ok(())
+    }
+
+    /// Writes the versions that are still valid at the watermark but are older than the right
+    /// table's retention back to state, so they aren't dropped when it's restored. They're
+    /// restamped to just before the watermark, which doesn't change what they join with as only
+    /// left rows at or after it are still to be joined.
+    async fn retain_valid_versions(&mut self, ctx: &mut ArrowContext) -> Result<()> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Ok(());
+        };
+        let Some(cutoff) = watermark.checked_sub(self.ttl) else {
+            return Ok(());
+        };
+        let (watermark, cutoff) = (to_nanos(watermark) as i64, to_nanos(cutoff) as i64);
+
+        let right_table = ctx
+            .table_manager
+            .get_key_time_table("right", ctx.last_present_watermark())
+            .await
+            .expect("should have right table");
+
+        let mut keys = vec![];
+        let mut rows = vec![];
+        for (key, batch) in right_table.all_batches()? {
+            let versions = Versions::new(batch, &self.right_schema, self.retract_index);
+            let Some(index) = versions.valid_at(watermark - 1) else {
+                continue;
+            };
+            if versions.timestamps[index as usize] >= cutoff {
+                continue;
+            }
+
+            let mut columns = versions.batch.columns().to_vec();
+            let mut timestamps = versions.timestamps.clone();
+            timestamps[index as usize] = watermark - 1;
+            let timestamp_index = self.right_schema.timestamp_index;
+            columns[timestamp_index] = Arc::new(
+                TimestampNanosecondArray::from(timestamps)
+                    .with_data_type(columns[timestamp_index].data_type().clone()),
+            );
+            let batch = RecordBatch::try_new(versions.batch.schema(), columns)?;
+
+            rows.push(batch.slice(index as usize, 1));
+            right_table.replace_batch(&key, batch);
+            keys.push(key);
+        }
+
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        // add the key columns back to the rows, in the positions they have in the input
+        let key_indices = self
+            .right_input_schema
+            .key_indices
+            .clone()
+            .unwrap_or_default();
+        let values = concat_batches(&self.right_schema.schema, rows.iter())?;
+        let mut key_columns = self
+            .key_converter
+            .convert_raw_rows(keys.iter().map(|k| k.as_slice()).collect())?
+            .into_iter();
+        let mut value_columns = values.columns().iter().cloned();
+        let columns = (0..self.right_input_schema.schema.fields().len())
+            .map(|i| {
+                if key_indices.contains(&i) {
+                    key_columns.next()
+                } else {
+                    value_columns.next()
+                }
+                .expect("should have a column for each field")
+            })
+            .collect();
+
+        right_table
+            .write_batch_to_state(RecordBatch::try_new(
+                self.right_input_schema.schema.clone(),
+                columns,
+            )?)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for TemporalJoin {
+    fn name(&self) -> String {
+        "TemporalJoin".to_string()
+    }
+
+    fn display(&self) -> DisplayableOperator {
+        DisplayableOperator {
+            name: Cow::Borrowed("TemporalJoin"),
+            fields: vec![
+                ("left_join", AsDisplayable::Debug(&self.is_left_join)),
+                ("ttl", AsDisplayable::Debug(&self.ttl)),
+            ],
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let watermark = ctx.last_present_watermark();
+
+        // left rows before the watermark were joined before the checkpoint
+        let left_batches: Vec<_> = ctx
+            .table_manager
+            .get_expiring_time_key_table("left", watermark)
+            .await
+            .expect("should have left table")
+            .all_batches_for_watermark(watermark)
+            .flat_map(|(_, batches)| batches.clone())
+            .collect();
+        for batch in left_batches {
+            let batch = self
+                .left_input_schema
+                .filter_by_time(batch, watermark)
+                .expect("should filter restored batch");
+            for (time, batch) in self.split_by_time(&batch).expect("should split batch") {
+                self.pending.entry(time).or_default().push(batch);
+            }
+        }
+
+        let right_keys = ctx
+            .table_manager
+            .get_key_time_table("right", watermark)
+            .await
+            .expect("should have right table")
+            .all_batches()
+            .expect("should read right table")
+            .into_iter()
+            .map(|(key, _)| key);
+        self.uncompacted.extend(right_keys);
+    }
+
+    async fn process_batch(&mut self, _record_batch: RecordBatch, _ctx: &mut ArrowContext) {
+        unreachable!();
+    }
+
+    async fn process_batch_index(
+        &mut self,
+        index: usize,
+        total_inputs: usize,
+        record_batch: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) {
+        match index / (total_inputs / 2) {
+            0 => self
+                .process_left(record_batch, ctx)
+                .await
+                .expect("should process left"),
+            1 => self
+                .process_right(record_batch, ctx)
+                .await
+                .expect("should process right"),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        int_watermark: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Some(int_watermark);
+        };
+
+        // every version up to the watermark has arrived, so the left rows before it can be joined
+        let remaining = self.pending.split_off(&watermark);
+        let ready = std::mem::replace(&mut self.pending, remaining);
+        let ready: Vec<_> = ready.into_values().flatten().collect();
+        if !ready.is_empty() {
+            let left = concat_batches(&self.left_input_schema.schema, ready.iter())
+                .expect("should concat left batches");
+            self.join(left, ctx).await.expect("should join left rows");
+        }
+
+        self.compact(watermark, ctx)
+            .await
+            .expect("should compact versions");
+
+        Some(int_watermark)
+    }
+
+    async fn handle_checkpoint(&mut self, _b: CheckpointBarrier, ctx: &mut ArrowContext) {
+        self.retain_valid_versions(ctx)
+            .await
+            .expect("should retain valid versions");
+
+        let watermark = ctx.last_present_watermark();
+        ctx.table_manager
+            .get_expiring_time_key_table("left", watermark)
+            .await
+            .expect("should have left table")
+            .flush(watermark)
+            .await
+            .expect("should flush");
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "left".to_string(),
+            timestamp_table_config(
+                "left",
+                "temporal join input",
+                Duration::ZERO,
+                false,
+                self.left_input_schema.as_ref().clone(),
+            ),
+        );
+        tables.insert(
+            "right".to_string(),
+            timestamp_table_config(
+                "right",
+                "temporal join versions",
+                self.ttl,
+                false,
+                self.right_input_schema.as_ref().clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct TemporalJoinConstructor;
+impl OperatorConstructor for TemporalJoinConstructor {
+    type ConfigT = api::TemporalJoinOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        _registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let left_input_schema: Arc<ArroyoSchema> =
+            Arc::new(config.left_schema.unwrap().try_into()?);
+        let right_input_schema: Arc<ArroyoSchema> =
+            Arc::new(config.right_schema.unwrap().try_into()?);
+        let output_schema: Arc<ArroyoSchema> = Arc::new(config.output_schema.unwrap().try_into()?);
+
+        let left_schema = left_input_schema.schema_without_keys()?;
+        let right_schema = right_input_schema.schema_without_keys()?;
+
+        let left_value_indices = (0..left_schema.schema.fields().len())
+            .filter(|i| *i != left_schema.timestamp_index)
+            .collect();
+        let retract_index = right_schema.schema.index_of(UPDATING_META_FIELD).ok();
+        let right_value_indices = (0..right_schema.schema.fields().len())
+            .filter(|i| *i != right_schema.timestamp_index && Some(*i) != retract_index)
+            .collect();
+
+        let join_type = api::JoinType::try_from(config.join_type)?;
+
+        Ok(OperatorNode::from_operator(Box::new(TemporalJoin {
+            key_converter: left_input_schema.converter(false)?,
+            left_input_schema,
+            right_input_schema,
+            right_schema,
+            output_schema,
+            is_left_join: join_type == api::JoinType::Left,
+            ttl: Duration::from_micros(config.ttl_micros),
+            left_value_indices,
+            right_value_indices,
+            retract_index,
+            pending: BTreeMap::new(),
+            uncompacted: HashSet::new(),
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrow::test_utils::checkpoint;
+    use arrow_array::types::Float64Type;
+    use arrow_array::{BooleanArray, Float64Array, StringArray, StructArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use arroyo_operator::context::{batch_bounded, BatchReceiver};
+    use arroyo_rpc::grpc::rpc::CheckpointMetadata;
+    use arroyo_rpc::{updating_meta_field, updating_meta_fields, ControlResp, TIMESTAMP_FIELD};
+    use arroyo_types::{get_test_task_info, ArrowMessage, TaskInfo};
+    use rand::random;
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    fn timestamp_field() -> Field {
+        Field::new(
+            TIMESTAMP_FIELD,
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        )
+    }
+
+    fn seconds(s: u64) -> TimestampNanosecondArray {
+        TimestampNanosecondArray::from_iter_values([s as i64 * 1_000_000_000])
+    }
+
+    /// An order with the currency it's in and its event time in seconds
+    fn order(id: &str, currency: &str, time: u64) -> RecordBatch {
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("_key_0", DataType::Utf8, false),
+                Field::new("order_id", DataType::Utf8, false),
+                timestamp_field(),
+            ])),
+            vec![
+                Arc::new(StringArray::from_iter_values([currency])),
+                Arc::new(StringArray::from_iter_values([id])),
+                Arc::new(seconds(time)),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// A version of a currency's rate at the time in seconds, or its retraction
+    fn rate(currency: &str, rate: f64, time: u64, is_retract: bool) -> RecordBatch {
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("_key_0", DataType::Utf8, false),
+                Field::new("rate", DataType::Float64, false),
+                timestamp_field(),
+                updating_meta_field().as_ref().clone(),
+            ])),
+            vec![
+                Arc::new(StringArray::from_iter_values([currency])),
+                Arc::new(Float64Array::from_iter_values([rate])),
+                Arc::new(seconds(time)),
+                Arc::new(StructArray::new(
+                    updating_meta_fields(),
+                    vec![
+                        Arc::new(BooleanArray::from(vec![is_retract])),
+                        new_null_array(&DataType::FixedSizeBinary(16), 1),
+                    ],
+                    None,
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn operator(is_left_join: bool, ttl: Duration) -> TemporalJoin {
+        let left_input_schema = Arc::new(ArroyoSchema::new_keyed(
+            order("", "", 0).schema(),
+            2,
+            vec![0],
+        ));
+        let right_input_schema = Arc::new(ArroyoSchema::new_keyed(
+            rate("", 0.0, 0, false).schema(),
+            2,
+            vec![0],
+        ));
+        let right_schema = right_input_schema.schema_without_keys().unwrap();
+        let output_schema = Arc::new(ArroyoSchema::new_unkeyed(
+            Arc::new(Schema::new(vec![
+                Field::new("order_id", DataType::Utf8, false),
+                Field::new("rate", DataType::Float64, true),
+                timestamp_field(),
+            ])),
+            2,
+        ));
+
+        TemporalJoin {
+            key_converter: left_input_schema.converter(false).unwrap(),
+            left_input_schema,
+            right_input_schema,
+            right_schema,
+            output_schema,
+            is_left_join,
+            ttl,
+            left_value_indices: vec![0],
+            right_value_indices: vec![0],
+            retract_index: Some(2),
+            pending: BTreeMap::new(),
+            uncompacted: HashSet::new(),
+        }
+    }
+
+    async fn context(
+        join: &mut TemporalJoin,
+        task_info: TaskInfo,
+        restore_from: Option<CheckpointMetadata>,
+    ) -> (ArrowContext, BatchReceiver, Receiver<ControlResp>) {
+        let (_, control_rx) = channel(128);
+        let (command_tx, command_rx) = channel(128);
+        let (data_tx, data_rx) = batch_bounded(128);
+
+        let mut ctx = ArrowContext::new(
+            task_info,
+            restore_from,
+            control_rx,
+            command_tx,
+            2,
+            vec![
+                join.left_input_schema.as_ref().clone(),
+                join.right_input_schema.as_ref().clone(),
+            ],
+            Some(join.output_schema.as_ref().clone()),
+            None,
+            vec![vec![data_tx]],
+            vec![],
+            join.tables(),
+        )
+        .await;
+
+        join.on_start(&mut ctx).await;
+        (ctx, data_rx, command_rx)
+    }
+
+    fn task_info() -> TaskInfo {
+        let mut task_info = get_test_task_info();
+        task_info.job_id = format!("temporal-join-{}", random::<u64>());
+        task_info
+    }
+
+    async fn advance(join: &mut TemporalJoin, ctx: &mut ArrowContext, time: u64) {
+        let watermark = Watermark::EventTime(from_nanos(time as u128 * 1_000_000_000));
+        ctx.watermarks.set(0, watermark);
+        ctx.watermarks.set(1, watermark);
+        join.handle_watermark(watermark, ctx).await;
+    }
+
+    /// The joined rows as (order id, rate)
+    async fn output(data_rx: &mut BatchReceiver) -> Vec<(String, Option<f64>)> {
+        let Ok(Some(ArrowMessage::Data(batch))) =
+            tokio::time::timeout(Duration::from_millis(100), data_rx.recv()).await
+        else {
+            return vec![];
+        };
+
+        let ids = batch.column(0).as_string::<i32>();
+        let rates = batch.column(1).as_primitive::<Float64Type>();
+        (0..batch.num_rows())
+            .map(|i| {
+                (
+                    ids.value(i).to_string(),
+                    rates.is_valid(i).then(|| rates.value(i)),
+                )
+            })
+            .collect()
+    }
+
+    fn joined(id: &str, rate: Option<f64>) -> (String, Option<f64>) {
+        (id.to_string(), rate)
+    }
+
+    async fn versions(join: &TemporalJoin, ctx: &mut ArrowContext, currency: &str) -> usize {
+        let key = join
+            .key_converter
+            .convert_columns(&[Arc::new(StringArray::from_iter_values([currency])) as ArrayRef])
+            .unwrap();
+        ctx.table_manager
+            .get_key_time_table("right", ctx.last_present_watermark())
+            .await
+            .unwrap()
+            .get_batch(key.as_ref())
+            .unwrap()
+            .map(|batch| batch.num_rows())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn test_update_at_the_same_time_replaces_the_version() {
+        let mut join = operator(true, Duration::from_secs(60));
+        let (mut ctx, mut data_rx, _) = context(&mut join, task_info(), None).await;
+
+        // an update arrives as a retraction followed by an insertion at the same time
+        for batch in [
+            rate("USD", 1.0, 1, false),
+            rate("USD", 1.0, 5, true),
+            rate("USD", 2.0, 5, false),
+            rate("USD", 2.0, 7, true),
+        ] {
+            join.process_batch_index(1, 2, batch, &mut ctx).await;
+        }
+
+        for (id, time) in [("a", 3), ("b", 5), ("c", 6), ("d", 8)] {
+            join.process_batch_index(0, 2, order(id, "USD", time), &mut ctx)
+                .await;
+        }
+        join.process_batch_index(0, 2, order("e", "EUR", 4), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await, vec![]);
+
+        advance(&mut join, &mut ctx, 10).await;
+        let mut rows = output(&mut data_rx).await;
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            rows,
+            vec![
+                joined("a", Some(1.0)),
+                joined("b", Some(2.0)),
+                joined("c", Some(2.0)),
+                joined("d", None),
+                joined("e", None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compaction_keeps_the_version_valid_at_the_watermark() {
+        let mut join = operator(false, Duration::from_secs(60));
+        let (mut ctx, mut data_rx, _) = context(&mut join, task_info(), None).await;
+
+        for batch in [
+            rate("USD", 1.0, 1, false),
+            rate("USD", 2.0, 3, false),
+            rate("USD", 3.0, 12, false),
+            rate("EUR", 1.0, 1, false),
+            rate("EUR", 1.0, 3, true),
+        ] {
+            join.process_batch_index(1, 2, batch, &mut ctx).await;
+        }
+
+        advance(&mut join, &mut ctx, 10).await;
+        assert_eq!(versions(&join, &mut ctx, "USD").await, 2);
+        assert_eq!(versions(&join, &mut ctx, "EUR").await, 0);
+
+        join.process_batch_index(0, 2, order("a", "USD", 11), &mut ctx)
+            .await;
+        join.process_batch_index(0, 2, order("b", "USD", 12), &mut ctx)
+            .await;
+        join.process_batch_index(0, 2, order("c", "EUR", 11), &mut ctx)
+            .await;
+
+        advance(&mut join, &mut ctx, 20).await;
+        let mut rows = output(&mut data_rx).await;
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(rows, vec![joined("a", Some(2.0)), joined("b", Some(3.0))]);
+        assert_eq!(versions(&join, &mut ctx, "USD").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_valid_versions_older_than_the_ttl_survive_restore() {
+        let task_info = task_info();
+        let mut join = operator(false, Duration::from_secs(10));
+        let (mut ctx, _data_rx, mut command_rx) = context(&mut join, task_info.clone(), None).await;
+
+        join.process_batch_index(1, 2, rate("USD", 1.0, 1, false), &mut ctx)
+            .await;
+        advance(&mut join, &mut ctx, 30).await;
+        let metadata = checkpoint(&mut join, &mut ctx, &mut command_rx, 1).await;
+
+        let mut join = operator(false, Duration::from_secs(10));
+        let (mut ctx, mut data_rx, _) = context(&mut join, task_info, Some(metadata)).await;
+        assert_eq!(versions(&join, &mut ctx, "USD").await, 1);
+
+        join.process_batch_index(0, 2, order("a", "USD", 31), &mut ctx)
+            .await;
+        advance(&mut join, &mut ctx, 40).await;
+        assert_eq!(output(&mut data_rx).await, vec![joined("a", Some(1.0))]);
+    }
+}
@@ -0,0 +1,67 @@
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::ArrowOperator;
+use arroyo_rpc::grpc::rpc::{CheckpointMetadata, TaskCheckpointCompletedReq};
+use arroyo_rpc::ControlResp;
+use arroyo_state::checkpoint_state::CheckpointState;
+use arroyo_state::{BackingStore, StateBackend};
+use arroyo_types::CheckpointBarrier;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::mpsc::Receiver;
+
+/// Checkpoints the operator at the epoch and writes the checkpoint metadata the way the controller
+/// does, returning the metadata that a new context for the same task can be restored from
+pub async fn checkpoint(
+    operator: &mut dyn ArrowOperator,
+    ctx: &mut ArrowContext,
+    command_rx: &mut Receiver<ControlResp>,
+    epoch: u32,
+) -> CheckpointMetadata {
+    let barrier = CheckpointBarrier {
+        epoch,
+        min_epoch: 1,
+        timestamp: SystemTime::now(),
+        then_stop: false,
+    };
+    operator.handle_checkpoint(barrier, ctx).await;
+    ctx.table_manager
+        .checkpoint(barrier, ctx.last_present_watermark())
+        .await;
+
+    let completed = loop {
+        if let ControlResp::CheckpointCompleted(completed) = command_rx
+            .recv()
+            .await
+            .expect("should receive checkpoint completion")
+        {
+            break completed;
+        }
+    };
+
+    let job_id = ctx.task_info.job_id.clone();
+    let mut state = CheckpointState::new(
+        Arc::new(job_id.clone()),
+        format!("checkpoint-{}", epoch),
+        epoch,
+        1,
+        HashMap::from([(completed.operator_id.clone(), 1)]),
+    );
+    state
+        .checkpoint_finished(TaskCheckpointCompletedReq {
+            worker_id: 0,
+            time: completed.subtask_metadata.finish_time,
+            job_id: job_id.clone(),
+            operator_id: completed.operator_id,
+            epoch,
+            metadata: Some(completed.subtask_metadata),
+            needs_commit: false,
+        })
+        .await
+        .expect("should finish checkpoint");
+    state.save_state().await.expect("should save checkpoint");
+
+    StateBackend::load_checkpoint_metadata(&job_id, epoch)
+        .await
+        .expect("should load checkpoint metadata")
+}
@@ -0,0 +1,733 @@
+use anyhow::Result;
+use arrow::compute::{concat_batches, lexsort_to_indices, take_record_batch, SortColumn};
+use arrow_array::{
+    ArrayRef, BooleanArray, FixedSizeBinaryArray, RecordBatch, StructArray,
+    TimestampNanosecondArray, UInt32Array, UInt64Array,
+};
+use arrow_schema::{SchemaRef, SortOptions};
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{
+    ArrowOperator, AsDisplayable, DisplayableOperator, OperatorConstructor, OperatorNode, Registry,
+};
+use arroyo_rpc::df::{ArroyoSchema, ArroyoSchemaRef};
+use arroyo_rpc::grpc::{api, rpc::TableConfig};
+use arroyo_rpc::{updating_meta_fields, Converter};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{from_nanos, to_nanos, CheckpointBarrier, Watermark};
+use datafusion::physical_expr::PhysicalExpr;
+use datafusion_proto::physical_plan::from_proto::parse_physical_expr;
+use datafusion_proto::physical_plan::DefaultPhysicalExtensionCodec;
+use datafusion_proto::protobuf::PhysicalExprNode;
+use md5::{Digest, Md5};
+use prost::Message;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Keeps the first `limit` rows of each key by an ordering, emitting the changes to the ranking
+/// as retractions and insertions. The rows at each rank share an id, so a change to the row at a
+/// rank is an update of that rank.
+///
+/// Like the state of an updating aggregate, a key's ranking is dropped once no rows have arrived
+/// for it within the ttl, without retracting it, so the last ranking emitted for the key stands.
+/// If rows for the key arrive again, it's ranked afresh and they update the ranks from the first.
+///
+/// Ranked rows are only written to state when they enter the top N, and state is retained for the
+/// ttl by event time, so at each checkpoint the ranked rows of live keys that are older than the
+/// ttl are restamped to just before the watermark and written again.
+pub struct TopN {
+    input_schema: ArroyoSchemaRef,
+    value_schema: ArroyoSchema,
+    output_schema: SchemaRef,
+    order_by: Vec<(Arc<dyn PhysicalExpr>, SortOptions)>,
+    limit: usize,
+    ttl: Duration,
+    key_converter: Converter,
+    /// the latest event time seen for each key, which is dropped once it's older than the ttl
+    last_seen: HashMap<Vec<u8>, SystemTime>,
+    expirations: BTreeMap<SystemTime, HashSet<Vec<u8>>>,
+}
+
+impl TopN {
+    /// The indices of the first `limit` rows of the batch, in rank order. Ties are broken by event
+    /// time and then by the order of the rows, so that rows already in the top N aren't displaced
+    /// by equal ones that come after them, including when the ranking is restored from state.
+    fn rank(&self, batch: &RecordBatch) -> Result<UInt32Array> {
+        let mut columns = self
+            .order_by
+            .iter()
+            .map(|(expr, options)| {
+                Ok(SortColumn {
+                    values: expr.evaluate(batch)?.into_array(batch.num_rows())?,
+                    options: Some(*options),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        columns.push(SortColumn {
+            values: batch.column(self.value_schema.timestamp_index).clone(),
+            options: None,
+        });
+        columns.push(SortColumn {
+            values: Arc::new(UInt32Array::from_iter_values(0..batch.num_rows() as u32)),
+            options: None,
+        });
+
+        Ok(lexsort_to_indices(&columns, Some(self.limit))?)
+    }
+
+    fn touch(&mut self, key: Vec<u8>, time: SystemTime) {
+        if self.last_seen.get(&key).is_some_and(|last| *last >= time) {
+            return;
+        }
+        self.expirations
+            .entry(time)
+            .or_default()
+            .insert(key.clone());
+        self.last_seen.insert(key, time);
+    }
+
+    fn max_time(&self, batch: &RecordBatch) -> Option<SystemTime> {
+        let timestamps = self.value_schema.timestamp_column(batch);
+        let max = timestamps.values().iter().max()?;
+        Some(from_nanos(*max as u128))
+    }
+
+    /// Builds output rows from the given (row, rank) pairs of the candidates
+    fn with_ranks(
+        &self,
+        key: &[u8],
+        candidates: &RecordBatch,
+        rows: &[(u32, usize)],
+        is_retract: bool,
+    ) -> Result<RecordBatch> {
+        let indices = UInt32Array::from_iter_values(rows.iter().map(|(i, _)| *i));
+        let mut columns = take_record_batch(candidates, &indices)?.columns().to_vec();
+        columns.push(Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|(_, rank)| *rank as u64 + 1),
+        )));
+
+        let ids = FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+            rows.iter().map(|(_, rank)| {
+                let mut hasher = Md5::new();
+                hasher.update(key);
+                hasher.update(rank.to_le_bytes());
+                Some(hasher.finalize())
+            }),
+            16,
+        )?;
+        columns.push(Arc::new(StructArray::new(
+            updating_meta_fields(),
+            vec![
+                Arc::new(BooleanArray::from(vec![is_retract; rows.len()])) as ArrayRef,
+                Arc::new(ids),
+            ],
+            None,
+        )));
+
+        Ok(RecordBatch::try_new(self.output_schema.clone(), columns)?)
+    }
+
+    /// The changes that turn the current ranking (the first `current` candidates) into the new
+    /// one, with the retractions first
+    fn changes(
+        &self,
+        key: &[u8],
+        current: usize,
+        candidates: &RecordBatch,
+        top: &UInt32Array,
+    ) -> Result<[RecordBatch; 2]> {
+        let mut retractions = vec![];
+        let mut insertions = vec![];
+        for rank in 0..current.max(top.len()) {
+            let new = top.values().get(rank).copied();
+            if rank < current {
+                if new == Some(rank as u32) {
+                    continue;
+                }
+                retractions.push((rank as u32, rank));
+            }
+            if let Some(new) = new {
+                insertions.push((new, rank));
+            }
+        }
+
+        Ok([
+            self.with_ranks(key, candidates, &retractions, true)?,
+            self.with_ranks(key, candidates, &insertions, false)?,
+        ])
+    }
+
+    async fn process(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) -> Result<()> {
+        let table = ctx
+            .table_manager
+            .get_key_time_table("top_n", ctx.last_present_watermark())
+            .await
+            .expect("should have top n table");
+
+        let key_indices = self.input_schema.key_indices.clone().unwrap_or_default();
+        let sorted = self.input_schema.sort(batch, false)?;
+        let unkeyed = self.input_schema.unkeyed_batch(&sorted)?;
+
+        let mut output = vec![];
+        let mut entering = vec![];
+        for range in self.input_schema.partition(&sorted, false)? {
+            let key = self
+                .key_converter
+                .convert_columns(
+                    sorted
+                        .slice(range.start, 1)
+                        .project(&key_indices)?
+                        .columns(),
+                )?
+                .as_ref()
+                .to_vec();
+
+            let rows = unkeyed.slice(range.start, range.len());
+            let current = match table.get_batch(&key)? {
+                Some(current) => current.clone(),
+                None => RecordBatch::new_empty(self.value_schema.schema.clone()),
+            };
+            let candidates = concat_batches(&self.value_schema.schema, [&current, &rows])?;
+            let top = self.rank(&candidates)?;
+
+            output.extend(self.changes(&key, current.num_rows(), &candidates, &top)?);
+
+            // only the rows that make it into the top N need to be kept in state
+            entering.push(take_record_batch(
+                &sorted,
+                &UInt32Array::from_iter_values(
+                    top.values()
+                        .iter()
+                        .filter(|i| **i as usize >= current.num_rows())
+                        .map(|i| (range.start + *i as usize - current.num_rows()) as u32),
+                ),
+            )?);
+
+            table.replace_batch(&key, take_record_batch(&candidates, &top)?);
+            if let Some(time) = self.max_time(&rows) {
+                self.touch(key, time);
+            }
+        }
+
+        let entering = concat_batches(&self.input_schema.schema, entering.iter())?;
+        if entering.num_rows() > 0 {
+            table.write_batch_to_state(entering).await?;
+        }
+
+        let output = concat_batches(&self.output_schema, output.iter())?;
+        if output.num_rows() > 0 {
+            ctx.collect(output).await;
+        }
+        Ok(())
+    }
+
+    /// Writes the ranked rows of keys that are still live but are older than the ttl back to
+    /// state, so the ranking isn't lost when it's restored. They're restamped to just before the
+    /// watermark, which keeps them ahead of tied rows still to come.
+    async fn retain_ranked_rows(&mut self, ctx: &mut ArrowContext) -> Result<()> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Ok(());
+        };
+        let Some(cutoff) = watermark.checked_sub(self.ttl) else {
+            return Ok(());
+        };
+        let (watermark_nanos, cutoff_nanos) = (to_nanos(watermark) as i64, to_nanos(cutoff) as i64);
+
+        let table = ctx
+            .table_manager
+            .get_key_time_table("top_n", ctx.last_present_watermark())
+            .await
+            .expect("should have top n table");
+
+        let mut keys = vec![];
+        let mut rows = vec![];
+        for (key, batch) in table.all_batches()? {
+            if !self.last_seen.get(&key).is_some_and(|last| *last >= cutoff) {
+                continue;
+            }
+            let timestamps = self.value_schema.timestamp_column(&batch);
+            let stale: Vec<_> = (0..batch.num_rows())
+                .filter(|i| timestamps.value(*i) < cutoff_nanos)
+                .map(|i| i as u32)
+                .collect();
+            if stale.is_empty() {
+                continue;
+            }
+
+            let mut restamped = timestamps.values().to_vec();
+            for i in &stale {
+                restamped[*i as usize] = watermark_nanos - 1;
+            }
+            let timestamp_index = self.value_schema.timestamp_index;
+            let mut columns = batch.columns().to_vec();
+            columns[timestamp_index] = Arc::new(
+                TimestampNanosecondArray::from(restamped)
+                    .with_data_type(columns[timestamp_index].data_type().clone()),
+            );
+            let batch = RecordBatch::try_new(batch.schema(), columns)?;
+
+            rows.push(take_record_batch(
+                &batch,
+                &UInt32Array::from_iter_values(stale.iter().copied()),
+            )?);
+            keys.extend(std::iter::repeat(key.clone()).take(stale.len()));
+            table.replace_batch(&key, batch);
+        }
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        // add the key columns back to the rows, in the positions they have in the input
+        let key_indices = self.input_schema.key_indices.clone().unwrap_or_default();
+        let values = concat_batches(&self.value_schema.schema, rows.iter())?;
+        let mut key_columns = self
+            .key_converter
+            .convert_raw_rows(keys.iter().map(|k| k.as_slice()).collect())?
+            .into_iter();
+        let mut value_columns = values.columns().iter().cloned();
+        let columns = (0..self.input_schema.schema.fields().len())
+            .map(|i| {
+                if key_indices.contains(&i) {
+                    key_columns.next()
+                } else {
+                    value_columns.next()
+                }
+                .expect("should have a column for each field")
+            })
+            .collect();
+
+        table
+            .write_batch_to_state(RecordBatch::try_new(
+                self.input_schema.schema.clone(),
+                columns,
+            )?)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for TopN {
+    fn name(&self) -> String {
+        "TopN".to_string()
+    }
+
+    fn display(&self) -> DisplayableOperator {
+        DisplayableOperator {
+            name: Cow::Borrowed("TopN"),
+            fields: vec![
+                ("limit", AsDisplayable::Debug(&self.limit)),
+                ("ttl", AsDisplayable::Debug(&self.ttl)),
+            ],
+        }
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let table = ctx
+            .table_manager
+            .get_key_time_table("top_n", ctx.last_present_watermark())
+            .await
+            .expect("should have top n table");
+
+        // state may have rows that were later displaced, so the ranking is recomputed
+        for (key, batch) in table.all_batches().expect("should read top n table") {
+            let top = self.rank(&batch).expect("should rank restored rows");
+            table.replace_batch(
+                &key,
+                take_record_batch(&batch, &top).expect("should take top rows"),
+            );
+            if let Some(time) = self.max_time(&batch) {
+                self.touch(key, time);
+            }
+        }
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        self.process(batch, ctx)
+            .await
+            .expect("should process top n batch");
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        let Some(cutoff) = ctx
+            .last_present_watermark()
+            .and_then(|watermark| watermark.checked_sub(self.ttl))
+        else {
+            return Some(watermark);
+        };
+
+        let table = ctx
+            .table_manager
+            .get_key_time_table("top_n", ctx.last_present_watermark())
+            .await
+            .expect("should have top n table");
+
+        // the rankings of keys without rows within the ttl are dropped, but not retracted
+        let remaining = self.expirations.split_off(&cutoff);
+        let expired = std::mem::replace(&mut self.expirations, remaining);
+        for key in expired.into_values().flatten() {
+            if self.last_seen.get(&key).is_some_and(|last| *last < cutoff) {
+                self.last_seen.remove(&key);
+                table.replace_batch(
+                    &key,
+                    RecordBatch::new_empty(self.value_schema.schema.clone()),
+                );
+            }
+        }
+
+        Some(watermark)
+    }
+
+    async fn handle_checkpoint(&mut self, _b: CheckpointBarrier, ctx: &mut ArrowContext) {
+        self.retain_ranked_rows(ctx)
+            .await
+            .expect("should retain ranked rows");
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "top_n".to_string(),
+            timestamp_table_config(
+                "top_n",
+                "top n rows",
+                self.ttl,
+                false,
+                self.input_schema.as_ref().clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct TopNConstructor;
+impl OperatorConstructor for TopNConstructor {
+    type ConfigT = api::TopNOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let mut input_schema: ArroyoSchema = config.input_schema.unwrap().try_into()?;
+        // without a PARTITION BY every row has the same (empty) key
+        if input_schema
+            .key_indices
+            .as_ref()
+            .is_some_and(|k| k.is_empty())
+        {
+            input_schema.key_indices = None;
+        }
+        let input_schema = Arc::new(input_schema);
+        let output_schema: ArroyoSchema = config.output_schema.unwrap().try_into()?;
+        let value_schema = input_schema.schema_without_keys()?;
+
+        let order_by = config
+            .order_by
+            .iter()
+            .map(|ordering| {
+                let expr = parse_physical_expr(
+                    &PhysicalExprNode::decode(&mut ordering.expr.as_slice())?,
+                    registry.as_ref(),
+                    &value_schema.schema,
+                    &DefaultPhysicalExtensionCodec {},
+                )?;
+                Ok((
+                    expr,
+                    SortOptions {
+                        descending: ordering.descending,
+                        nulls_first: ordering.nulls_first,
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(OperatorNode::from_operator(Box::new(TopN {
+            key_converter: input_schema.converter(false)?,
+            input_schema,
+            value_schema,
+            output_schema: output_schema.schema,
+            order_by,
+            limit: config.limit as usize,
+            ttl: Duration::from_micros(config.ttl_micros),
+            last_seen: HashMap::new(),
+            expirations: BTreeMap::new(),
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrow::test_utils::checkpoint;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int64Type, UInt64Type};
+    use arrow_array::{Int64Array, StringArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use arroyo_operator::context::{batch_bounded, BatchReceiver};
+    use arroyo_rpc::grpc::rpc::CheckpointMetadata;
+    use arroyo_rpc::{updating_meta_field, ControlResp, TIMESTAMP_FIELD};
+    use arroyo_types::{get_test_task_info, ArrowMessage, TaskInfo};
+    use datafusion::physical_expr::expressions::col;
+    use rand::random;
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    fn input_schema() -> ArroyoSchema {
+        ArroyoSchema::new_keyed(
+            Arc::new(Schema::new(vec![
+                Field::new("_key_0", DataType::Utf8, false),
+                Field::new("player", DataType::Utf8, false),
+                Field::new("score", DataType::Int64, false),
+                Field::new(
+                    TIMESTAMP_FIELD,
+                    DataType::Timestamp(TimeUnit::Nanosecond, None),
+                    false,
+                ),
+            ])),
+            3,
+            vec![0],
+        )
+    }
+
+    /// (game, player, score, event time in seconds)
+    fn batch(rows: &[(&str, &str, i64, u64)]) -> RecordBatch {
+        RecordBatch::try_new(
+            input_schema().schema,
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.1))),
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.2))),
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    rows.iter().map(|r| r.3 as i64 * 1_000_000_000),
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn operator(limit: usize, ttl: Duration) -> TopN {
+        let input_schema = Arc::new(input_schema());
+        let value_schema = input_schema.schema_without_keys().unwrap();
+        let mut fields = value_schema.schema.fields().to_vec();
+        fields.push(Arc::new(Field::new("row_number", DataType::UInt64, false)));
+        fields.push(updating_meta_field());
+
+        TopN {
+            key_converter: input_schema.converter(false).unwrap(),
+            order_by: vec![(
+                col("score", &value_schema.schema).unwrap(),
+                SortOptions {
+                    descending: true,
+                    nulls_first: false,
+                },
+            )],
+            input_schema,
+            value_schema,
+            output_schema: Arc::new(Schema::new(fields)),
+            limit,
+            ttl,
+            last_seen: HashMap::new(),
+            expirations: BTreeMap::new(),
+        }
+    }
+
+    async fn context(
+        top_n: &mut TopN,
+        task_info: TaskInfo,
+        restore_from: Option<CheckpointMetadata>,
+    ) -> (ArrowContext, BatchReceiver, Receiver<ControlResp>) {
+        let (_, control_rx) = channel(128);
+        let (command_tx, command_rx) = channel(128);
+        let (data_tx, data_rx) = batch_bounded(128);
+
+        let mut ctx = ArrowContext::new(
+            task_info,
+            restore_from,
+            control_rx,
+            command_tx,
+            1,
+            vec![top_n.input_schema.as_ref().clone()],
+            Some(ArroyoSchema::new_unkeyed(
+                top_n.output_schema.clone(),
+                top_n.value_schema.timestamp_index,
+            )),
+            None,
+            vec![vec![data_tx]],
+            vec![],
+            top_n.tables(),
+        )
+        .await;
+
+        top_n.on_start(&mut ctx).await;
+        (ctx, data_rx, command_rx)
+    }
+
+    fn task_info() -> TaskInfo {
+        let mut task_info = get_test_task_info();
+        task_info.job_id = format!("top-n-{}", random::<u64>());
+        task_info
+    }
+
+    async fn top_n(limit: usize, ttl: Duration) -> (TopN, ArrowContext, BatchReceiver) {
+        let mut top_n = operator(limit, ttl);
+        let (ctx, data_rx, _) = context(&mut top_n, task_info(), None).await;
+        (top_n, ctx, data_rx)
+    }
+
+    /// The output rows as (player, score, rank, is_retract), along with their ids
+    async fn output(data_rx: &mut BatchReceiver) -> (Vec<(String, i64, u64, bool)>, Vec<Vec<u8>>) {
+        let Ok(Some(ArrowMessage::Data(batch))) =
+            tokio::time::timeout(Duration::from_millis(100), data_rx.recv()).await
+        else {
+            return (vec![], vec![]);
+        };
+
+        let players = batch.column(0).as_string::<i32>();
+        let scores = batch.column(1).as_primitive::<Int64Type>();
+        let ranks = batch.column(3).as_primitive::<UInt64Type>();
+        let metadata = batch.column(4).as_struct();
+        let is_retract = metadata.column(0).as_boolean();
+        let ids = metadata.column(1).as_fixed_size_binary();
+
+        (
+            (0..batch.num_rows())
+                .map(|i| {
+                    (
+                        players.value(i).to_string(),
+                        scores.value(i),
+                        ranks.value(i),
+                        is_retract.value(i),
+                    )
+                })
+                .collect(),
+            (0..batch.num_rows())
+                .map(|i| ids.value(i).to_vec())
+                .collect(),
+        )
+    }
+
+    fn row(player: &str, score: i64, rank: u64, is_retract: bool) -> (String, i64, u64, bool) {
+        (player.to_string(), score, rank, is_retract)
+    }
+
+    #[tokio::test]
+    async fn test_rank_changes_update_ranks() {
+        let (mut top_n, mut ctx, mut data_rx) = top_n(2, Duration::from_secs(60)).await;
+
+        top_n
+            .process_batch(batch(&[("g", "a", 10, 1), ("g", "b", 20, 2)]), &mut ctx)
+            .await;
+        let (rows, first_ids) = output(&mut data_rx).await;
+        assert_eq!(rows, vec![row("b", 20, 1, false), row("a", 10, 2, false)]);
+
+        // c displaces a from second place, while b keeps first
+        top_n
+            .process_batch(batch(&[("g", "c", 15, 3)]), &mut ctx)
+            .await;
+        let (rows, ids) = output(&mut data_rx).await;
+        assert_eq!(rows, vec![row("a", 10, 2, true), row("c", 15, 2, false)]);
+        assert_eq!(ids, vec![first_ids[1].clone(), first_ids[1].clone()]);
+
+        // rows below the top N don't change the ranking
+        top_n
+            .process_batch(batch(&[("g", "d", 5, 4)]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await.0, vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_ties_are_won_by_earlier_rows() {
+        let (mut top_n, mut ctx, mut data_rx) = top_n(1, Duration::from_secs(60)).await;
+
+        top_n
+            .process_batch(batch(&[("g", "a", 10, 2)]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await.0, vec![row("a", 10, 1, false)]);
+
+        // an equal row that comes later doesn't displace a
+        top_n
+            .process_batch(batch(&[("g", "b", 10, 3)]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await.0, vec![]);
+
+        // but one with an earlier event time does
+        top_n
+            .process_batch(batch(&[("g", "c", 10, 1)]), &mut ctx)
+            .await;
+        assert_eq!(
+            output(&mut data_rx).await.0,
+            vec![row("a", 10, 1, true), row("c", 10, 1, false)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_rankings_are_dropped_without_retractions() {
+        let (mut top_n, mut ctx, mut data_rx) = top_n(1, Duration::from_secs(10)).await;
+
+        top_n
+            .process_batch(batch(&[("g", "a", 10, 1)]), &mut ctx)
+            .await;
+        let (rows, first_ids) = output(&mut data_rx).await;
+        assert_eq!(rows, vec![row("a", 10, 1, false)]);
+
+        let watermark = Watermark::EventTime(from_nanos(20_000_000_000));
+        ctx.watermarks.set(0, watermark);
+        top_n.handle_watermark(watermark, &mut ctx).await;
+        assert_eq!(output(&mut data_rx).await.0, vec![]);
+
+        // the key is ranked afresh, so a lower score takes first place as an update of it
+        top_n
+            .process_batch(batch(&[("g", "b", 5, 21)]), &mut ctx)
+            .await;
+        let (rows, ids) = output(&mut data_rx).await;
+        assert_eq!(rows, vec![row("b", 5, 1, false)]);
+        assert_eq!(ids, first_ids);
+    }
+
+    #[tokio::test]
+    async fn test_long_ranked_rows_survive_restore() {
+        let task_info = task_info();
+        let mut top_n = operator(1, Duration::from_secs(10));
+        let (mut ctx, mut data_rx, mut command_rx) =
+            context(&mut top_n, task_info.clone(), None).await;
+
+        top_n
+            .process_batch(batch(&[("g", "a", 10, 1)]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await.0, vec![row("a", 10, 1, false)]);
+
+        // lower scores keep the key live without entering the top N
+        for time in [5, 10, 15, 20] {
+            top_n
+                .process_batch(batch(&[("g", "b", 5, time)]), &mut ctx)
+                .await;
+            assert_eq!(output(&mut data_rx).await.0, vec![]);
+        }
+
+        // a is now older than the ttl, but still first
+        let watermark = Watermark::EventTime(from_nanos(25_000_000_000));
+        ctx.watermarks.set(0, watermark);
+        top_n.handle_watermark(watermark, &mut ctx).await;
+        let metadata = checkpoint(&mut top_n, &mut ctx, &mut command_rx, 1).await;
+
+        let mut top_n = operator(1, Duration::from_secs(10));
+        let (mut ctx, mut data_rx, _) = context(&mut top_n, task_info, Some(metadata)).await;
+
+        top_n
+            .process_batch(batch(&[("g", "c", 7, 26)]), &mut ctx)
+            .await;
+        assert_eq!(output(&mut data_rx).await.0, vec![]);
+
+        top_n
+            .process_batch(batch(&[("g", "d", 20, 27)]), &mut ctx)
+            .await;
+        assert_eq!(
+            output(&mut data_rx).await.0,
+            vec![row("a", 10, 1, true), row("d", 20, 1, false)]
+        );
+    }
+}
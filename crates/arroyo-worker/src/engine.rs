@@ -13,10 +13,14 @@ use futures::StreamExt;
 use tracing::{info, warn};
 
 use crate::arrow::async_udf::AsyncUdfConstructor;
+use crate::arrow::deduplicate::DeduplicateConstructor;
 use crate::arrow::instant_join::InstantJoinConstructor;
 use crate::arrow::join_with_expiration::JoinWithExpirationConstructor;
+use crate::arrow::lookup_join::LookupJoinConstructor;
 use crate::arrow::session_aggregating_window::SessionAggregatingWindowConstructor;
 use crate::arrow::sliding_aggregating_window::SlidingAggregatingWindowConstructor;
+use crate::arrow::temporal_join::TemporalJoinConstructor;
+use crate::arrow::top_n::TopNConstructor;
 use crate::arrow::tumbling_aggregating_window::TumblingAggregateWindowConstructor;
 use crate::arrow::updating_aggregator::UpdatingAggregatingConstructor;
 use crate::arrow::watermark_generator::WatermarkGeneratorConstructor;
@@ -222,13 +226,17 @@ impl Program {
                 .map(|edge| edge.weight().schema.clone())
                 .collect();
 
+            // dead-letter edges carry their own schema, so they don't determine the output
+            // schema of the node
             let out_schema = logical
                 .edges_directed(idx, Direction::Outgoing)
+                .filter(|edge| edge.weight().edge_type != LogicalEdgeType::DeadLetter)
                 .map(|edge| edge.weight().schema.clone())
                 .next();
 
             let projection = logical
                 .edges_directed(idx, Direction::Outgoing)
+                .filter(|edge| edge.weight().edge_type != LogicalEdgeType::DeadLetter)
                 .map(|edge| edge.weight().projection.clone())
                 .next()
                 .unwrap_or_default();
@@ -295,7 +303,8 @@ impl Program {
                 }
                 LogicalEdgeType::Shuffle
                 | LogicalEdgeType::LeftJoin
-                | LogicalEdgeType::RightJoin => {
+                | LogicalEdgeType::RightJoin
+                | LogicalEdgeType::DeadLetter => {
                     for f in &from_nodes {
                         for (idx, t) in to_nodes.iter().enumerate() {
                             let (tx, rx) = batch_bounded(queue_size);
@@ -675,6 +684,7 @@ impl Engine {
 
         let mut in_qs_map: BTreeMap<(LogicalEdgeType, usize), Vec<BatchReceiver>> = BTreeMap::new();
         let mut out_qs_map: BTreeMap<usize, BTreeMap<usize, BatchSender>> = BTreeMap::new();
+        let mut dead_letter_qs_map: BTreeMap<usize, BTreeMap<usize, BatchSender>> = BTreeMap::new();
         let task_info = {
             let mut graph = self.program.graph.write().unwrap();
             for edge in graph.edge_indices() {
@@ -699,7 +709,12 @@ impl Engine {
                 };
 
                 let tx = edge.weight().tx.as_ref().unwrap().clone();
-                out_qs_map
+                let qs_map = if edge.weight().edge == LogicalEdgeType::DeadLetter {
+                    &mut dead_letter_qs_map
+                } else {
+                    &mut out_qs_map
+                };
+                qs_map
                     .entry(edge.weight().out_logical_idx)
                     .or_default()
                     .insert(edge.weight().edge_idx, tx);
@@ -727,6 +742,10 @@ impl Engine {
                 .into_values()
                 .map(|v| v.into_values().collect())
                 .collect(),
+            dead_letter_qs_map
+                .into_values()
+                .map(|v| v.into_values().collect())
+                .collect(),
             tables,
         )
         .await;
@@ -768,7 +787,11 @@ pub fn construct_operator(
         OperatorName::ExpressionWatermark => Box::new(WatermarkGeneratorConstructor),
         OperatorName::Join => Box::new(JoinWithExpirationConstructor),
         OperatorName::InstantJoin => Box::new(InstantJoinConstructor),
+        OperatorName::LookupJoin => Box::new(LookupJoinConstructor),
+        OperatorName::TemporalJoin => Box::new(TemporalJoinConstructor),
         OperatorName::WindowFunction => Box::new(WindowFunctionConstructor),
+        OperatorName::TopN => Box::new(TopNConstructor),
+        OperatorName::Deduplicate => Box::new(DeduplicateConstructor),
         OperatorName::ConnectorSource | OperatorName::ConnectorSink => {
             let op: api::ConnectorOp = prost::Message::decode(&mut config.as_slice()).unwrap();
             return connectors()
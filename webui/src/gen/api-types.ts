@@ -143,6 +143,8 @@ export interface components {
       fail: Record<string, never>;
     }, {
       drop: Record<string, never>;
+    }, {
+      dlq: Record<string, never>;
     }]>;
     Checkpoint: {
       backend: string;
@@ -193,6 +195,7 @@ export interface components {
       format?: components["schemas"]["Format"] | null;
       framing?: components["schemas"]["Framing"] | null;
       inferred?: boolean | null;
+      primaryKeys?: (string)[];
       structName?: string | null;
     };
     ConnectionTable: {
@@ -220,7 +223,7 @@ export interface components {
       schema?: components["schemas"]["ConnectionSchema"] | null;
     };
     /** @enum {string} */
-    ConnectionType: "source" | "sink";
+    ConnectionType: "source" | "sink" | "lookup";
     Connector: {
       connectionConfig?: string | null;
       customSchemas: boolean;
@@ -238,6 +241,24 @@ export interface components {
     ConnectorCollection: {
       data: (components["schemas"]["Connector"])[];
     };
+    CsvFormat: {
+      delimiter?: string;
+      /** @description The character used to escape quotes within quoted fields; if unset, quotes are escaped
+       * by doubling them */
+      escape?: string | null;
+      /** @description Whether the data has a header row with the column names. Sources skip header rows, and
+       * sinks write one at the start of each file. */
+      header?: boolean;
+      /** @description The string used to represent null values */
+      nullValue?: string;
+      quote?: string;
+      timestampFormat?: components["schemas"]["TimestampFormat"];
+    };
+    DelimitedFraming: {
+      delimiter: (number)[];
+    };
+    /** @enum {string} */
+    Endianness: "big" | "little";
     ErrorResp: {
       error: string;
     };
@@ -260,13 +281,19 @@ export interface components {
       raw_string: components["schemas"]["RawStringFormat"];
     }, {
       raw_bytes: components["schemas"]["RawBytesFormat"];
+    }, {
+      csv: components["schemas"]["CsvFormat"];
     }]>;
     Framing: {
       method: components["schemas"]["FramingMethod"];
     };
-    FramingMethod: {
+    FramingMethod: OneOf<[{
       newline: components["schemas"]["NewlineDelimitedFraming"];
-    };
+    }, {
+      lengthPrefixed: components["schemas"]["LengthPrefixedFraming"];
+    }, {
+      delimiter: components["schemas"]["DelimitedFraming"];
+    }]>;
     GlobalUdf: {
       /** Format: int64 */
       createdAt: number;
@@ -328,6 +355,12 @@ export interface components {
       timestampFormat?: components["schemas"]["TimestampFormat"];
       unstructured?: boolean;
     };
+    /** @enum {string} */
+    LengthPrefixWidth: "u16" | "u32" | "varint";
+    LengthPrefixedFraming: {
+      endianness?: components["schemas"]["Endianness"];
+      width?: components["schemas"]["LengthPrefixWidth"];
+    };
     Metric: {
       /** Format: int64 */
       time: number;
@@ -339,7 +372,7 @@ export interface components {
       subtasks: (components["schemas"]["SubtaskMetrics"])[];
     };
     /** @enum {string} */
-    MetricName: "bytes_recv" | "bytes_sent" | "messages_recv" | "messages_sent" | "backpressure" | "tx_queue_size" | "tx_queue_rem";
+    MetricName: "bytes_recv" | "bytes_sent" | "messages_recv" | "messages_sent" | "backpressure" | "tx_queue_size" | "tx_queue_rem" | "records_dropped";
     NewlineDelimitedFraming: {
       /** Format: int64 */
       maxLineLength?: number | null;
@@ -445,6 +478,8 @@ export interface components {
       confluentSchemaRegistry?: boolean;
       intoUnstructuredJson?: boolean;
       messageName?: string | null;
+      /** Format: int32 */
+      schemaId?: number | null;
     };
     QueryValidationResult: {
       errors: (string)[];
@@ -398,6 +398,17 @@ export const DefineSchema = ({
         },
       },
     },
+    {
+      name: 'Length-prefixed',
+      value: {
+        method: {
+          lengthPrefixed: {
+            width: 'u32',
+            endianness: 'big',
+          },
+        },
+      },
+    },
   ];
 
   type BadDataOption = {
@@ -408,6 +419,7 @@ export const DefineSchema = ({
   const badDataOptions: BadDataOption[] = [
     { name: 'Fail', value: { fail: {} } },
     { name: 'Drop', value: { drop: {} } },
+    { name: 'Dead-letter queue', value: { dlq: {} } },
   ];
 
   const onFormatChange = (e: ChangeEvent<DataFormatOption>) => {
@@ -489,8 +501,9 @@ export const DefineSchema = ({
         </Select>
         <FormHelperText maxW={'lg'}>
           This option describes how the job should handle data that doesn't match the defined
-          schema. 'Fail' will cause the job to fail, while 'Drop' will cause the job to drop
-          (ignore) bad data.
+          schema. 'Fail' will cause the job to fail, 'Drop' will cause the job to drop (ignore) bad
+          data, and 'Dead-letter queue' will send it to a table named after the source with a
+          '_dlq' suffix, which can be written to any sink.
         </FormHelperText>
       </FormControl>
 